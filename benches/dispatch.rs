@@ -0,0 +1,72 @@
+//! Overhead of calling the functions of the fixture ui crate in
+//! `tests/fixture` through its hot library, next to calling them statically.
+//!
+//! Run with `cargo bench --bench dispatch`. The first run compiles iced for
+//! the fixture library and takes a few minutes.
+
+use std::path::{Path as FsPath, PathBuf};
+use std::process::Command;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use hot_ice::bench::{Dispatch, Path, StateBorrow};
+use hot_ice::macro_use::DynMessage;
+use hot_ice_fixture::{Message, State};
+
+/// Target directory of the fixture library, separate from the one the
+/// benchmarks are built in so the nested cargo never waits for its lock.
+fn target_dir() -> PathBuf {
+    FsPath::new(env!("CARGO_TARGET_TMPDIR")).join("bench-fixture")
+}
+
+/// Builds the fixture library, see `tests/reload.rs`.
+fn build() -> String {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args(["rustc", "--lib", "-p", "hot_ice_fixture", "--manifest-path"])
+        .arg(FsPath::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", target_dir())
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "failed to build the fixture library");
+
+    target_dir().join("debug").display().to_string()
+}
+
+fn dispatch(c: &mut Criterion) {
+    let app = hot_ice::application(State::new, State::update, State::view);
+    let mut dispatch = Dispatch::new(app, build());
+
+    let mut group = c.benchmark_group("update");
+    for path in Path::ALL {
+        group.bench_function(path.to_string(), |b| {
+            b.iter(|| dispatch.update(path, Message::Increment.into_hot_message()))
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("view");
+    for path in Path::ALL {
+        group.bench_function(path.to_string(), |b| b.iter(|| dispatch.view(path)));
+    }
+    group.finish();
+}
+
+fn boundary(c: &mut Criterion) {
+    let mut group = c.benchmark_group("message");
+    for path in Path::ALL {
+        group.bench_function(path.to_string(), |b| {
+            b.iter(|| hot_ice::bench::message(path, &Message::Increment))
+        });
+    }
+    group.finish();
+
+    let state = StateBorrow::new(vec![0_u64; 64]);
+    let mut group = c.benchmark_group("state_borrow");
+    for path in Path::ALL {
+        group.bench_function(path.to_string(), |b| b.iter(|| state.get(path).len()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dispatch, boundary);
+criterion_main!(benches);
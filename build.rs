@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Recorded in the build fingerprint the host compares against the
+    // cdylib's before calling into it, see `src/fingerprint.rs`.
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .filter(|version| !version.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=HOT_ICE_RUSTC_VERSION={version}");
+
+    // The versions of iced and tokio the lockfile of the workspace building
+    // this crate resolved, also part of the fingerprint.
+    let lockfile = find_lockfile();
+    if let Some(lockfile) = &lockfile {
+        println!("cargo:rerun-if-changed={}", lockfile.display());
+    }
+    let iced = locked_version(lockfile.as_deref(), "iced");
+    println!("cargo:rustc-env=HOT_ICE_ICED_VERSION={iced}");
+    // The host's tokio runtime is shared with the library, see
+    // `src/host_runtime.rs`.
+    if std::env::var_os("CARGO_FEATURE_TOKIO").is_some() {
+        let tokio = locked_version(lockfile.as_deref(), "tokio");
+        println!("cargo:rustc-env=HOT_ICE_TOKIO_VERSION={tokio}");
+    }
+
+    // Checked against the toolchain building the library with Cranelift, see
+    // `src/compiler.rs`.
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string());
+    println!("cargo:rustc-env=HOT_ICE_TARGET={target}");
+}
+
+/// The `Cargo.lock` of the workspace being built: the first one above the
+/// target directory, which is where `OUT_DIR` is, or else the one of this
+/// crate's own workspace.
+fn find_lockfile() -> Option<PathBuf> {
+    let out_dir = std::env::var_os("OUT_DIR").map(PathBuf::from);
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+    [out_dir, manifest_dir]
+        .into_iter()
+        .flatten()
+        .find_map(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join("Cargo.lock"))
+                .find(|lockfile| lockfile.is_file())
+        })
+}
+
+/// The versions of the package `name` in `lockfile`, joined by `,` if it was
+/// resolved to several. `unknown` without a lockfile.
+fn locked_version(lockfile: Option<&Path>, name: &str) -> String {
+    let content = lockfile
+        .and_then(|lockfile| std::fs::read_to_string(lockfile).ok())
+        .unwrap_or_default();
+    let name_line = format!("name = \"{name}\"");
+    let mut versions: Vec<_> = content
+        .split("[[package]]")
+        .filter(|package| package.lines().any(|line| line.trim() == name_line))
+        .filter_map(|package| {
+            package
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("version = \""))
+                .and_then(|version| version.strip_suffix('"'))
+        })
+        .collect();
+    versions.sort_unstable();
+    versions.dedup();
+    match versions.is_empty() {
+        true => "unknown".to_string(),
+        false => versions.join(","),
+    }
+}
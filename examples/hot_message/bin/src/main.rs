@@ -2,33 +2,7 @@ use tracing_error::ErrorLayer;
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 use ui::State;
 
-#[cfg(feature = "reload")]
-use hot_ice::application;
-#[cfg(not(feature = "reload"))]
-use iced::application;
-
-fn main() {
-    init_tracing();
-
-    #[cfg(feature = "reload")]
-    let reloader_settings = hot_ice::ReloaderSettings {
-        feature: Some("reload".to_string()),
-        ..Default::default()
-    };
-
-    let app = application(State::new, State::update, State::view)
-        .subscription(State::subscription)
-        .theme(State::theme)
-        .style(State::style)
-        .scale_factor(State::scale_factor)
-        .title(State::title);
-
-    #[cfg(not(feature = "reload"))]
-    app.run().unwrap();
-
-    #[cfg(feature = "reload")]
-    app.reloader_settings(reloader_settings).run().unwrap();
-}
+hot_ice::hot_main!(State, init = init_tracing);
 
 pub fn init_tracing() {
     let fmt_layer = fmt::layer().compact();
@@ -2,33 +2,25 @@ use tracing_error::ErrorLayer;
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 use ui::State;
 
-#[cfg(feature = "reload")]
-use hot_ice::application;
-#[cfg(not(feature = "reload"))]
-use iced::application;
-
 fn main() {
     init_tracing();
 
-    let app = application(State::new, State::update, State::view)
-        .subscription(State::subscription)
-        .theme(State::theme)
-        .style(State::style)
-        .scale_factor(State::scale_factor)
-        .title(State::title);
-
-    #[cfg(not(feature = "reload"))]
-    app.run().unwrap();
-
-    #[cfg(feature = "reload")]
-    {
-        let reloader_settings = hot_ice::ReloaderSettings {
+    hot_ice::maybe_hot_application!(
+        State::new,
+        State::update,
+        State::view,
+        reloader_settings = hot_ice::ReloaderSettings {
             compile_in_reloader: false,
             ..Default::default()
-        };
-
-        app.reloader_settings(reloader_settings).run().unwrap();
-    }
+        },
+    )
+    .subscription(State::subscription)
+    .theme(State::theme)
+    .style(State::style)
+    .scale_factor(State::scale_factor)
+    .title(State::title)
+    .run()
+    .unwrap();
 }
 
 pub fn init_tracing() {
@@ -6,6 +6,33 @@
 pub const SERIALIZE_STATE_FUNCTION_NAME: &str = "serialize_state_slkdfjaf3lasjfdsa";
 pub const DESERIALIZE_STATE_FUNCTION_NAME: &str = "deserialize_state_sldafjal3lkfjasldf";
 pub const FREE_SERIALIZED_DATA_FUNCTION_NAME: &str = "free_serialized_data_lsadkjfa3alfjda";
+pub const STATE_TYPE_HASH_FUNCTION_NAME: &str = "state_type_hash_lskdjfa3lkfjasdf";
+pub const ADOPT_STATE_FUNCTION_NAME: &str = "adopt_state_lskdjfa3lkfjasdf";
+pub const STATE_FIELDS_FUNCTION_NAME: &str = "state_fields_lskdjfa3lkfjasdf";
+pub const SERIALIZE_STATE_FIELDS_FUNCTION_NAME: &str = "serialize_state_fields_lskdjfa3lkfjasdf";
+pub const RESTORE_STATE_FIELDS_FUNCTION_NAME: &str = "restore_state_fields_lskdjfa3lkfjasdf";
+pub const DROP_STATE_FIELDS_FUNCTION_NAME: &str = "drop_state_fields_lskdjfa3lkfjasdf";
 pub const LOAD_FONT_FUNCTION_NAME: &str = "load_font_into_system_lskdjfa3lkfjasdf";
 pub const START_WORKER_FUNCTION_NAME: &str = "start_worker_lskdjfa3lkfjasdf";
 pub const STOP_WORKER_FUNCTION_NAME: &str = "stop_worker_lskdjfa3lkfjasdf";
+pub const EXECUTOR_TYPE_FUNCTION_NAME: &str = "executor_type_lskdjfa3lkfjasdf";
+pub const SET_INTERNER_FUNCTION_NAME: &str = "set_interner_lskdjfa3lkfjasdf";
+pub const SET_HOST_RESOURCES_FUNCTION_NAME: &str = "set_host_resources_lskdjfa3lkfjasdf";
+pub const SET_HOST_CONTEXT_FUNCTION_NAME: &str = "set_host_context_lskdjfa3lkfjasdf";
+pub const SET_HOST_ALLOCATOR_FUNCTION_NAME: &str = "set_host_allocator_lskdjfa3lkfjasdf";
+pub const FINGERPRINT_FUNCTION_NAME: &str = "hot_ice_fingerprint_lskdjfa3lkfjasdf";
+
+// Prefix of the per-function companion symbols emitted by `#[hot_fn]`. Each
+// companion returns the kind and signature hash of the hot function it
+// describes, e.g. `hot_export_lskdjfa3lkfjasdf_update` for `update`.
+pub const HOT_EXPORT_SYMBOL_PREFIX: &str = "hot_export_lskdjfa3lkfjasdf_";
+
+// Prefix of the marker symbols emitted by `#[hot_fn(not_hot)]` in place of the
+// function's own export, e.g. `hot_not_hot_lskdjfa3lkfjasdf_view` for `view`.
+// The host calls functions with a marker statically.
+pub const NOT_HOT_SYMBOL_PREFIX: &str = "hot_not_hot_lskdjfa3lkfjasdf_";
+
+// Prefix of the getters emitted by `#[hot_data]`, e.g.
+// `hot_data_lskdjfa3lkfjasdf_LEVELS` for `static LEVELS`. Each returns the
+// address and type hash of its item.
+pub const HOT_DATA_SYMBOL_PREFIX: &str = "hot_data_lskdjfa3lkfjasdf_";
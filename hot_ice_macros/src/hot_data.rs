@@ -0,0 +1,98 @@
+use hot_ice_common::HOT_DATA_SYMBOL_PREFIX;
+use quote::quote;
+use syn::{Ident, Token, parse_macro_input};
+
+#[derive(Default)]
+struct HotDataArgs {
+    feature: Option<String>,
+}
+
+impl syn::parse::Parse for HotDataArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut feature = None;
+
+        while !input.is_empty() {
+            let key: Ident = input.parse()?;
+
+            if key == "feature" {
+                input.parse::<Token![=]>()?;
+                let lit: syn::LitStr = input.parse()?;
+                feature = Some(lit.value());
+            } else {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    "unknown argument, `#[hot_data]` only takes `feature = \"...\"`",
+                ));
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(HotDataArgs { feature })
+    }
+}
+
+pub fn hot_data(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = if attr.is_empty() {
+        HotDataArgs::default()
+    } else {
+        parse_macro_input!(attr as HotDataArgs)
+    };
+
+    let item = parse_macro_input!(item as syn::Item);
+    // A const has no address of its own, the getter copies it into a
+    // static.
+    let (ident, ty, data) = match &item {
+        syn::Item::Static(item) if matches!(item.mutability, syn::StaticMutability::None) => {
+            let ident = &item.ident;
+            (ident, &item.ty, quote! { &#ident })
+        }
+        syn::Item::Const(item) => {
+            let (ident, ty) = (&item.ident, &item.ty);
+            let data = quote! {{
+                static DATA: #ty = #ident;
+                &DATA
+            }};
+            (ident, ty, data)
+        }
+        _ => {
+            return syn::Error::new_spanned(
+                &item,
+                "`#[hot_data]` exports a `static` or `const` item, a `static mut` \
+                 can't be read by the host",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let getter = Ident::new(
+        &format!("{HOT_DATA_SYMBOL_PREFIX}{ident}"),
+        proc_macro2::Span::call_site(),
+    );
+    let cfg = args.feature.map(|feature| {
+        let feature = syn::LitStr::new(&feature, proc_macro2::Span::call_site());
+        quote! { #[cfg(feature = #feature)] }
+    });
+
+    quote! {
+        #item
+
+        #cfg
+        #[doc(hidden)]
+        #[unsafe(no_mangle)]
+        pub fn #getter() -> hot_ice::macro_use::HotDataExport {
+            let data: &'static #ty = #data;
+            hot_ice::macro_use::HotDataExport {
+                data: data as *const #ty as *const (),
+                type_hash: <#ty as hot_ice::TypeHash>::TYPE_HASH,
+            }
+        }
+    }
+    .into()
+}
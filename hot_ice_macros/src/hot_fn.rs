@@ -1,9 +1,10 @@
-use hot_ice_common::LOAD_FONT_FUNCTION_NAME;
-use quote::quote;
+use hot_ice_common::{HOT_EXPORT_SYMBOL_PREFIX, LOAD_FONT_FUNCTION_NAME, NOT_HOT_SYMBOL_PREFIX};
+use quote::{quote, quote_spanned};
 use syn::{
     Ident, Token,
     parse::{Parse, ParseStream},
     parse_macro_input,
+    spanned::Spanned,
 };
 
 // Used to make sure the generated code does not conflict with user-defined functions
@@ -11,23 +12,108 @@ const INNER_FUNCTION_POSTFIX: &str = "sdlksldkdkslskfjei";
 
 struct MacroArgs {
     hot_state: bool,
+    not_hot: bool,
     feature: Option<String>,
+    strip: bool,
+    sub_state: Option<SubState>,
+    /// Executor exported for the worker by an `update`, the default one if
+    /// `None`.
+    executor: Option<syn::Type>,
+}
+
+/// Where `#[hot_fn(sub_state = Parent.field)]` finds the sub-state the
+/// function is a method of: the root state `parent` and the fields leading
+/// from it to the sub-state.
+struct SubState {
+    parent: syn::Path,
+    fields: Vec<syn::Member>,
+}
+
+impl SubState {
+    fn from_expr(expr: syn::Expr) -> syn::Result<Self> {
+        let mut fields = Vec::new();
+        let mut base = expr;
+        while let syn::Expr::Field(field) = base {
+            fields.push(field.member);
+            base = *field.base;
+        }
+        fields.reverse();
+
+        match base {
+            syn::Expr::Path(path) if path.qself.is_none() && !fields.is_empty() => Ok(SubState {
+                parent: path.path,
+                fields,
+            }),
+            base => Err(syn::Error::new_spanned(
+                base,
+                "expected the root state followed by the fields of the sub-state, \
+                 e.g. `sub_state = crate::State.counter`",
+            )),
+        }
+    }
+
+    /// Name of the exported function for `original_fn_name`, the fields
+    /// joined with it, e.g. `counter_view`.
+    fn export_ident(&self, original_fn_name: &syn::Ident) -> proc_macro2::Ident {
+        let mut name = String::new();
+        for field in &self.fields {
+            match field {
+                syn::Member::Named(ident) => name.push_str(&ident.to_string()),
+                syn::Member::Unnamed(index) => name.push_str(&index.index.to_string()),
+            }
+            name.push('_');
+        }
+        name.push_str(&original_fn_name.to_string());
+        proc_macro2::Ident::new(&name, original_fn_name.span())
+    }
+
+    /// Borrows the sub-state out of the `HotState` named `state`.
+    fn access(&self, mutable: bool) -> proc_macro2::TokenStream {
+        let parent = &self.parent;
+        let fields = &self.fields;
+        if mutable {
+            quote! { &mut state.ref_mut_state::<#parent>() #(.#fields)* }
+        } else {
+            quote! { &state.ref_state::<#parent>() #(.#fields)* }
+        }
+    }
 }
 
 impl Parse for MacroArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut hot_state = false;
+        let mut not_hot = false;
         let mut feature = None;
+        let mut strip = false;
+        let mut sub_state = None;
+        let mut executor = None;
 
         while !input.is_empty() {
             let key: Ident = input.parse()?;
 
             if key == "hot_state" {
                 hot_state = true;
+            } else if key == "not_hot" {
+                not_hot = true;
+            } else if key == "not" && input.peek(Token![-]) {
+                input.parse::<Token![-]>()?;
+                let hot: Ident = input.parse()?;
+                if hot != "hot" {
+                    return Err(syn::Error::new(hot.span(), "expected `not-hot`"));
+                }
+                not_hot = true;
+            } else if key == "strip" {
+                strip = true;
             } else if key == "feature" {
                 input.parse::<Token![=]>()?;
                 let lit: syn::LitStr = input.parse()?;
                 feature = Some(lit.value());
+            } else if key == "sub_state" {
+                input.parse::<Token![=]>()?;
+                sub_state = Some(SubState::from_expr(input.parse()?)?);
+            } else if key == "executor" {
+                input.parse::<Token![=]>()?;
+                executor = Some(input.parse()?);
             }
 
             if !input.is_empty() {
@@ -35,7 +121,21 @@ impl Parse for MacroArgs {
             }
         }
 
-        Ok(MacroArgs { hot_state, feature })
+        if strip && feature.is_none() {
+            return Err(input.error("`strip` needs a `feature` to strip the macro without"));
+        }
+        if sub_state.is_some() && not_hot {
+            return Err(input.error("`sub_state` cannot be combined with `not_hot`"));
+        }
+
+        Ok(MacroArgs {
+            hot_state,
+            not_hot,
+            feature,
+            strip,
+            sub_state,
+            executor,
+        })
     }
 }
 
@@ -44,6 +144,8 @@ enum FnType {
     Update,
     View,
     Subscription,
+    Setup,
+    OnEvent,
     Other,
 }
 
@@ -60,7 +162,11 @@ pub fn hot_fn(
     let args = if attr.is_empty() {
         MacroArgs {
             hot_state: false,
+            not_hot: false,
             feature: None,
+            strip: false,
+            sub_state: None,
+            executor: None,
         }
     } else {
         parse_macro_input!(attr_clone as MacroArgs)
@@ -68,15 +174,88 @@ pub fn hot_fn(
 
     let hot_state = args.hot_state;
 
+    // The original function is all that is left without the feature, it must
+    // not need hot_ice on its own.
+    if args.strip && takes_context(&input) {
+        let context = input.sig.inputs.last();
+        return syn::Error::new_spanned(
+            context,
+            "`strip` cannot remove a `&hot_ice::Context` argument, \
+             the function needs hot_ice without the feature",
+        )
+        .to_compile_error()
+        .into();
+    }
+
     let fn_type = detect_fn_type(&input);
 
-    let generated_code = match fn_type {
-        FnType::Boot => boot(hot_state, item),
-        FnType::Update => update(hot_state, item),
-        FnType::View => view(hot_state, item),
-        FnType::Subscription => subscription(hot_state, item),
-        FnType::Other => generate_simple_wrapper(hot_state, item),
+    // The future runs as a task, only `update` returns one to run it in.
+    if let Some(asyncness) = input.sig.asyncness {
+        if !matches!(fn_type, FnType::Update) {
+            return syn::Error::new_spanned(
+                asyncness,
+                "only `update` can be async, other functions return their value right away",
+            )
+            .to_compile_error()
+            .into();
+        }
+        if args.strip {
+            return syn::Error::new_spanned(
+                asyncness,
+                "`strip` cannot remove the blocking on an async `update`, \
+                 plain iced needs hot_ice to call it without the feature",
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
+    if let Some(executor) = &args.executor
+        && (!matches!(fn_type, FnType::Update) || args.sub_state.is_some())
+    {
+        return syn::Error::new_spanned(
+            executor,
+            "the executor is exported by `update` of the root state, \
+             name it there or invoke `hot_ice::export_executor!` instead",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // The other kinds are wrapped as methods, a function without `self` is
+    // none of them.
+    if matches!(fn_type, FnType::Other)
+        && !matches!(input.sig.inputs.first(), Some(syn::FnArg::Receiver(_)))
+    {
+        return syn::Error::new_spanned(
+            &input.sig,
+            "cannot tell which function of the application this is, name it \
+             `boot`, `update`, `view`, `subscription`, `theme`, `style`, \
+             `scale_factor`, `title` or `on_event`, or return the type iced expects of it",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let generated_code = match &args.sub_state {
+        Some(sub_state) => sub_state_fn(sub_state, fn_type, item),
+        None => match fn_type {
+            FnType::Boot => boot(hot_state, input.clone()).into(),
+            FnType::Update => update(hot_state, args.executor.as_ref(), item),
+            FnType::View => view(hot_state, item),
+            FnType::Subscription => subscription(hot_state, item),
+            FnType::Setup => setup(hot_state, item),
+            FnType::OnEvent => on_event(hot_state, item),
+            FnType::Other => generate_simple_wrapper(hot_state, item),
+        },
+    };
+
+    let generated_code = if args.not_hot {
+        pin_static(&input.sig.ident, generated_code)
+    } else {
+        generated_code
     };
+    let generated_code = hide_generated(&input.sig.ident, generated_code);
 
     // If a feature is specified, wrap the generated code with feature gates.
     // The generated code may contain multiple items (e.g. export_executor!(),
@@ -84,7 +263,11 @@ pub fn hot_fn(
     // so we must apply it to each item individually.
     if let Some(feature_name) = args.feature {
         let generated_tokens = proc_macro2::TokenStream::from(generated_code);
-        let original_tokens = proc_macro2::TokenStream::from(item_clone2);
+        let original_items = if input.sig.asyncness.is_some() && args.sub_state.is_none() {
+            blocking_update(&input)
+        } else {
+            vec![proc_macro2::TokenStream::from(item_clone2)]
+        };
 
         let feature_lit = syn::LitStr::new(&feature_name, proc_macro2::Span::call_site());
         let cfg_attr: syn::Attribute = syn::parse_quote!(#[cfg(feature = #feature_lit)]);
@@ -100,8 +283,10 @@ pub fn hot_fn(
         let wrapped = quote! {
             #( #gated_items )*
 
-            #[cfg(not(feature = #feature_lit))]
-            #original_tokens
+            #(
+                #[cfg(not(feature = #feature_lit))]
+                #original_items
+            )*
         };
 
         wrapped.into()
@@ -110,62 +295,308 @@ pub fn hot_fn(
     }
 }
 
+/// Keeps the generated items out of rustdoc and autocompletion. The user's
+/// doc comments move from the inner function to the wrapper, which takes the
+/// function's name, every other generated item is hidden.
+fn hide_generated(
+    original_fn_name: &syn::Ident,
+    generated_code: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let file: syn::File = syn::parse(generated_code).expect("generated code should be valid items");
+    let inner_fn_name = inner_fn_name(original_fn_name);
+    let mut items = file.items;
+
+    let mut docs = Vec::new();
+    for item in &mut items {
+        if let syn::Item::Fn(function) = item {
+            if function.sig.ident == inner_fn_name {
+                let (user_docs, attrs) = std::mem::take(&mut function.attrs)
+                    .into_iter()
+                    .partition(|attr| attr.path().is_ident("doc"));
+                docs = user_docs;
+                function.attrs = attrs;
+                function.attrs.push(syn::parse_quote!(#[doc(hidden)]));
+            }
+        }
+    }
+
+    for item in &mut items {
+        if let syn::Item::Fn(function) = item {
+            if function.sig.ident == inner_fn_name {
+                continue;
+            }
+            if function.sig.ident == *original_fn_name {
+                function.attrs.splice(0..0, docs.drain(..));
+            } else if !function
+                .attrs
+                .iter()
+                .any(|attr| quote!(#attr).to_string() == quote!(#[doc(hidden)]).to_string())
+            {
+                function.attrs.push(syn::parse_quote!(#[doc(hidden)]));
+            }
+            function
+                .attrs
+                .push(syn::parse_quote!(#[allow(non_snake_case, clippy::all)]));
+        }
+    }
+
+    quote!( #( #items )* ).into()
+}
+
+/// Keeps the wrapper named `original_fn_name` out of the library's exports
+/// for `#[hot_fn(not_hot)]`. Its `no_mangle` and export companion are
+/// replaced with a marker telling the host to call it statically, the other
+/// generated items are kept.
+fn pin_static(
+    original_fn_name: &syn::Ident,
+    generated_code: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let file: syn::File = syn::parse(generated_code).expect("generated code should be valid items");
+    let companion = format!("{HOT_EXPORT_SYMBOL_PREFIX}{original_fn_name}");
+
+    let items = file.items.into_iter().filter_map(|mut item| {
+        if let syn::Item::Fn(function) = &mut item {
+            if function.sig.ident == companion {
+                return None;
+            }
+            if function.sig.ident == *original_fn_name {
+                function.attrs.retain(|attr| {
+                    quote!(#attr).to_string() != quote!(#[unsafe(no_mangle)]).to_string()
+                });
+            }
+        }
+        Some(item)
+    });
+
+    let marker_ident = proc_macro2::Ident::new(
+        &format!("{NOT_HOT_SYMBOL_PREFIX}{original_fn_name}"),
+        proc_macro2::Span::call_site(),
+    );
+
+    quote! {
+        #( #items )*
+
+        #[doc(hidden)]
+        #[unsafe(no_mangle)]
+        pub fn #marker_ident() {}
+    }
+    .into()
+}
+
+/// Detects the kind of function from its return type and, for return types
+/// behind an alias such as `type UpdateResult = Task<Message>`, from the
+/// conventional names of the functions of an iced application.
 fn detect_fn_type(input: &syn::ItemFn) -> FnType {
     let return_type = &input.sig.output;
     let return_type_str = quote!(#return_type).to_string();
     let inputs = &input.sig.inputs;
+    let name = input.sig.ident.to_string();
+
+    // A trailing `&Context` is passed through to the wrapped function.
+    let args = inputs.len() - usize::from(takes_context(input));
 
-    // Boot: 0 args, returns tuple
-    if inputs.is_empty() {
+    // Boot: 0 args besides the `&Context`, returns tuple
+    if args == 0 {
         if let syn::ReturnType::Type(_, ty) = return_type {
             if let syn::Type::Tuple(_) = **ty {
                 return FnType::Boot;
             }
         }
+        if name == "boot" || name == "new" {
+            return FnType::Boot;
+        }
     }
 
-    if inputs.len() == 1 {
-        if return_type_str.contains("Element") {
+    if args == 1 {
+        if takes_mut_self(input) && (return_type_str.contains("Task") || name == "setup") {
+            return FnType::Setup;
+        }
+        // A view may return any widget converting into an `Element`.
+        if return_type_str.contains("Element") || (name == "view" && !takes_mut_self(input)) {
             return FnType::View;
         }
-        if return_type_str.contains("Subscription") {
+        if return_type_str.contains("Subscription") || name == "subscription" {
             return FnType::Subscription;
         }
     }
 
-    if inputs.len() == 2 {
-        if return_type_str.contains("Task") {
+    if args == 2 {
+        // A view of one of several windows takes its `window::Id`, unlike
+        // `title(&self, window::Id)` it returns an element.
+        if takes_window(input)
+            && !takes_mut_self(input)
+            && (return_type_str.contains("Element") || name == "view")
+        {
+            return FnType::View;
+        }
+        if return_type_str.contains("Task") || name == "update" {
             return FnType::Update;
         }
     }
 
+    if args == 3 && (name == "on_event" || takes_event(input)) {
+        return FnType::OnEvent;
+    }
+
     FnType::Other
 }
 
-fn boot(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let mut input = parse_macro_input!(item as syn::ItemFn);
+/// Whether `input` takes `&mut self`.
+fn takes_mut_self(input: &syn::ItemFn) -> bool {
+    matches!(
+        input.sig.inputs.first(),
+        Some(syn::FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_some()
+    )
+}
+
+/// Whether the second argument of `input`, after the receiver, is an `Event`.
+fn takes_event(input: &syn::ItemFn) -> bool {
+    matches!(
+        input.sig.inputs.iter().nth(1),
+        Some(syn::FnArg::Typed(pat_type))
+            if matches!(&*pat_type.ty, syn::Type::Path(path) if path.path.segments.last().is_some_and(|s| s.ident == "Event"))
+    )
+}
+
+/// The `window::Id` the second argument of `input`, after the receiver,
+/// has, if it is one.
+fn window_type(input: &syn::ItemFn) -> Option<&syn::Type> {
+    let Some(syn::FnArg::Typed(pat_type)) = input.sig.inputs.iter().nth(1) else {
+        return None;
+    };
+    let syn::Type::Path(path) = &*pat_type.ty else {
+        return None;
+    };
+    let mut segments = path.path.segments.iter().rev();
+    let is_window_id = segments.next().is_some_and(|s| s.ident == "Id")
+        && segments.next().is_none_or(|s| s.ident == "window");
+    is_window_id.then_some(&*pat_type.ty)
+}
+
+/// Whether the second argument of `input`, after the receiver, is a
+/// `window::Id`.
+fn takes_window(input: &syn::ItemFn) -> bool {
+    window_type(input).is_some()
+}
+
+/// The `window::Id` parameter of the exported wrapper of a view, the
+/// argument passed on to the wrapped function and the type included in the
+/// signature hash.
+fn window_tokens(
+    input: &syn::ItemFn,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+) {
+    match window_type(input) {
+        Some(ty) => (
+            quote! { , window: #ty },
+            quote! { , window },
+            quote! { #ty, },
+        ),
+        None => Default::default(),
+    }
+}
+
+/// Whether the last argument of `input` is a `&Context`.
+fn takes_context(input: &syn::ItemFn) -> bool {
+    let Some(syn::FnArg::Typed(pat_type)) = input.sig.inputs.last() else {
+        return false;
+    };
+    let syn::Type::Reference(reference) = &*pat_type.ty else {
+        return false;
+    };
+    matches!(
+        &*reference.elem,
+        syn::Type::Path(path) if path.path.segments.last().is_some_and(|s| s.ident == "Context")
+    )
+}
+
+/// The `&Context` parameter of the exported wrapper, the argument passed on
+/// to the wrapped function and the type included in the signature hash.
+fn context_tokens(
+    context: bool,
+) -> (
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+) {
+    if context {
+        (
+            quote! { , context: &hot_ice::Context },
+            quote! { , context },
+            quote! { &hot_ice::Context, },
+        )
+    } else {
+        Default::default()
+    }
+}
+
+fn boot(hot_state: bool, mut input: syn::ItemFn) -> proc_macro2::TokenStream {
     let FnInfo {
         original_fn_name,
         inner_fn_ident,
         vis,
+        output_span,
         ..
     } = extract_fn_info(&input);
 
     input.sig.ident = inner_fn_ident.clone();
 
+    // `boot` has no receiver, it may take the host's `&Context`.
+    let args: Vec<_> = input.sig.inputs.iter().cloned().collect();
+    let arg_names: Vec<_> = input
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::FnArg::Typed(syn::PatType { pat, .. }) => match &**pat {
+                syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.clone()),
+                _ => None,
+            },
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+
     // Extract the Message type from the return type
     let message_type = extract_message_type_from_return(&input.sig.output);
 
-    let expanded = if hot_state {
-        if let Some(msg_type) = message_type {
+    // A `boot` or `new` returning an alias of the tuple is turned into it.
+    let aliased = match &input.sig.output {
+        syn::ReturnType::Type(_, ty) => {
+            !matches!(&**ty, syn::Type::Tuple(_))
+                && !matches!(&**ty, syn::Type::Path(path) if path.path.is_ident("Self"))
+        }
+        syn::ReturnType::Default => false,
+    };
+    let boot_type = match &message_type {
+        Some(msg_type) => quote_spanned! {output_span=> (Self, iced::Task<#msg_type>) },
+        None => quote_spanned! {output_span=> (Self, iced::Task<_>) },
+    };
+    let boot_call = if aliased {
+        quote_spanned! {output_span=>
+            hot_ice::macro_use::IntoBoot::into_boot(Self::#inner_fn_ident(#(#arg_names),*))
+        }
+    } else {
+        quote_spanned! {output_span=> Self::#inner_fn_ident(#(#arg_names),*) }
+    };
+    let into_hot = into_hot_message(output_span);
+    let map_task = quote_spanned! {output_span=>
+        task.map(#into_hot)
+    };
+
+    if hot_state {
+        if message_type.is_some() || aliased {
             // We have a Task<Message> in the return type - call function directly
             quote! {
-                #vis fn #original_fn_name() -> (hot_ice::macro_use::HotState, iced::Task<hot_ice::macro_use::HotMessage>) {
-                    let (app, task): (Self, iced::Task<#msg_type>) = Self::#inner_fn_ident();
+                #vis fn #original_fn_name(#(#args),*) -> (hot_ice::macro_use::HotState, iced::Task<hot_ice::macro_use::HotMessage>) {
+                    let (app, task): #boot_type = #boot_call;
 
                     (
                         hot_ice::macro_use::HotState::new(app),
-                        task.map(hot_ice::macro_use::DynMessage::into_hot_message)
+                        #map_task
                     )
                 }
 
@@ -174,8 +605,8 @@ fn boot(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStre
         } else {
             // No Task in return type - create empty task
             quote! {
-                #vis fn #original_fn_name() -> (hot_ice::macro_use::HotState, iced::Task<hot_ice::macro_use::HotMessage>) {
-                    let app = Self::#inner_fn_ident();
+                #vis fn #original_fn_name(#(#args),*) -> (hot_ice::macro_use::HotState, iced::Task<hot_ice::macro_use::HotMessage>) {
+                    let app = #boot_call;
 
                     (
                         hot_ice::macro_use::HotState::new(app),
@@ -187,20 +618,20 @@ fn boot(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStre
             }
         }
     } else {
-        if let Some(msg_type) = message_type {
+        if message_type.is_some() || aliased {
             quote! {
-                #vis fn #original_fn_name() -> (Self, iced::Task<hot_ice::macro_use::HotMessage>) {
-                    let (app, task): (Self, iced::Task<#msg_type>) = Self::#inner_fn_ident();
+                #vis fn #original_fn_name(#(#args),*) -> (Self, iced::Task<hot_ice::macro_use::HotMessage>) {
+                    let (app, task): #boot_type = #boot_call;
 
-                    (app, task.map(hot_ice::macro_use::DynMessage::into_hot_message))
+                    (app, #map_task)
                 }
 
                 #input
             }
         } else {
             quote! {
-                #vis fn #original_fn_name() -> (Self, iced::Task<hot_ice::macro_use::HotMessage>) {
-                    let app = Self::#inner_fn_ident();
+                #vis fn #original_fn_name(#(#args),*) -> (Self, iced::Task<hot_ice::macro_use::HotMessage>) {
+                    let app = #boot_call;
 
                     (app, iced::Task::none())
                 }
@@ -208,9 +639,7 @@ fn boot(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStre
                 #input
             }
         }
-    };
-
-    proc_macro::TokenStream::from(expanded)
+    }
 }
 
 /// Extract the Message type from a return type like (Self, Task<Message>)
@@ -246,6 +675,66 @@ fn extract_task_inner_type(ty: &syn::Type) -> Option<syn::Type> {
     None
 }
 
+/// The `Element` a view returning `ty` is converted into, and whether it
+/// needs an `.into()` to get there.
+///
+/// Besides `Element<'a, Message, ...>` a view may return `impl Into<Element<...>>`
+/// or a widget such as `Column<'a, Message, ...>`, whose generics are taken to
+/// follow the lifetime, Message, Theme, Renderer order of `Element`.
+fn view_element_type(ty: &syn::Type) -> (syn::Type, bool) {
+    match ty {
+        syn::Type::ImplTrait(impl_trait) => {
+            let into_target = impl_trait.bounds.iter().find_map(|bound| {
+                let syn::TypeParamBound::Trait(bound) = bound else {
+                    return None;
+                };
+                let last_seg = bound.path.segments.last()?;
+                let syn::PathArguments::AngleBracketed(args) = &last_seg.arguments else {
+                    return None;
+                };
+                match args.args.first() {
+                    Some(syn::GenericArgument::Type(target)) if last_seg.ident == "Into" => {
+                        Some(target.clone())
+                    }
+                    _ => None,
+                }
+            });
+            match into_target {
+                Some(target) => (target, true),
+                None => (ty.clone(), false),
+            }
+        }
+        syn::Type::Path(type_path) => match type_path.path.segments.last() {
+            Some(last_seg) if last_seg.ident != "Element" && !is_element_alias(ty) => {
+                let arguments = last_seg.arguments.clone();
+                (syn::parse_quote!(hot_ice::iced::Element #arguments), true)
+            }
+            _ => (ty.clone(), false),
+        },
+        _ => (ty.clone(), false),
+    }
+}
+
+/// Whether `ty` names an `Element` through an alias such as
+/// `type AppElement<'a> = Element<'a, Message>`: unlike a widget, it takes no
+/// Message type.
+fn is_element_alias(ty: &syn::Type) -> bool {
+    let syn::Type::Path(type_path) = ty else {
+        return false;
+    };
+    let Some(last_seg) = type_path.path.segments.last() else {
+        return false;
+    };
+    let takes_types = match &last_seg.arguments {
+        syn::PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .any(|arg| matches!(arg, syn::GenericArgument::Type(_))),
+        _ => false,
+    };
+    last_seg.ident != "Element" && !takes_types
+}
+
 /// Transform Element<'a, Message, ...> return type to Element<'a, HotMessage, ...>
 /// This preserves the lifetime, Theme, and Renderer generics while only changing the Message type.
 fn transform_element_return_type(output: &syn::ReturnType) -> syn::ReturnType {
@@ -339,89 +828,235 @@ fn transform_element_type(ty: &syn::Type) -> syn::Type {
     }
 }
 
-fn update(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+fn update(
+    hot_state: bool,
+    executor: Option<&syn::Type>,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
     let mut input = parse_macro_input!(item as syn::ItemFn);
     let FnInfo {
         original_fn_name,
         inner_fn_ident,
         vis,
+        output_span,
         ..
     } = extract_fn_info(&input);
 
     input.sig.ident = inner_fn_ident.clone();
+    let (context_param, context_arg, context_type) = context_tokens(takes_context(&input));
+
+    let state_type = if hot_state {
+        quote! { &mut hot_ice::macro_use::HotState }
+    } else {
+        quote! { &mut Self }
+    };
+    let export = export_companion(
+        &original_fn_name,
+        "Update",
+        quote! {
+            (
+                #state_type,
+                hot_ice::macro_use::HotMessage,
+                #context_type
+                ::core::result::Result<iced::Task<hot_ice::macro_use::HotMessage>, hot_ice::macro_use::HotIceError>,
+            )
+        },
+    );
+
+    let message_conversion = receive_message(&input, output_span);
+    let into_hot = into_hot_message(output_span);
+    let export_executor = match executor {
+        Some(executor) => quote! { hot_ice::export_executor!(#executor); },
+        None => quote! { hot_ice::export_executor!(); },
+    };
+    let update_call = if hot_state {
+        let call = spawn_async(
+            &input,
+            quote! { Self::#inner_fn_ident(state.ref_mut_state(), message #context_arg) },
+        );
+        quote_spanned! {output_span=>
+            hot_ice::macro_use::time_update(|| #call).map(#into_hot)
+        }
+    } else {
+        let call = spawn_async(
+            &input,
+            quote! { self.#inner_fn_ident(message #context_arg) },
+        );
+        quote_spanned! {output_span=>
+            hot_ice::macro_use::time_update(|| #call).map(#into_hot)
+        }
+    };
 
     let expanded = if hot_state {
         quote! {
 
-            hot_ice::export_executor!();
+            #export_executor
+
+            hot_ice::export_interner!();
+
+            hot_ice::export_host_resources!();
+
+            hot_ice::export_host_context!();
+
+            hot_ice::export_fingerprint!();
 
             #[unsafe(no_mangle)]
             #vis fn #original_fn_name(
                 state: &mut hot_ice::macro_use::HotState,
-                message: hot_ice::macro_use::HotMessage,
+                message: hot_ice::macro_use::HotMessage
+                #context_param
             ) -> ::core::result::Result<iced::Task<hot_ice::macro_use::HotMessage>, hot_ice::macro_use::HotIceError> {
-                let message = message
-                    .into_message()
-                    .map_err(|m| hot_ice::macro_use::HotIceError::MessageDowncastError(::std::format!("{:?}", m)))?;
+                let ::core::option::Option::Some(message) = #message_conversion? else {
+                    return ::core::result::Result::Ok(iced::Task::none());
+                };
 
-                match hot_ice::macro_use::catch_panic(|| {
-                    Self::#inner_fn_ident(state.ref_mut_state(), message)
-                        .map(hot_ice::macro_use::DynMessage::into_hot_message)
-                }) {
+                match hot_ice::macro_use::catch_panic(|| #update_call) {
                     ::core::result::Result::Ok(task) => ::core::result::Result::Ok(task),
                     ::core::result::Result::Err(err_msg) => {
-                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg))
+                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg)))
                     }
                 }
             }
             #input
+
+            #export
         }
     } else {
         quote! {
 
-            hot_ice::export_executor!();
+            #export_executor
+
+            hot_ice::export_interner!();
+
+            hot_ice::export_host_resources!();
+
+            hot_ice::export_host_context!();
+
+            hot_ice::export_fingerprint!();
 
             #[unsafe(no_mangle)]
             #vis fn #original_fn_name(
                 &mut self,
-                message: hot_ice::macro_use::HotMessage,
+                message: hot_ice::macro_use::HotMessage
+                #context_param
             ) -> ::core::result::Result<iced::Task<hot_ice::macro_use::HotMessage>, hot_ice::macro_use::HotIceError> {
-                let message = message.into_message()
-                    .map_err(|message| hot_ice::macro_use::HotIceError::MessageDowncastError(::std::format!("{:?}", message)))?;
+                let ::core::option::Option::Some(message) = #message_conversion? else {
+                    return ::core::result::Result::Ok(iced::Task::none());
+                };
 
-                match hot_ice::macro_use::catch_panic(|| {
-                    self.#inner_fn_ident(message)
-                        .map(hot_ice::macro_use::DynMessage::into_hot_message)
-                }) {
+                match hot_ice::macro_use::catch_panic(|| #update_call) {
                     ::core::result::Result::Ok(task) => ::core::result::Result::Ok(task),
                     ::core::result::Result::Err(err_msg) => {
-                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg))
+                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg)))
                     }
                 }
             }
             #input
+
+            #export
         }
     };
 
     proc_macro::TokenStream::from(expanded)
 }
 
+/// Runs the future `call` returns as a task if the update `input` is async,
+/// see `hot_ice::macro_use::spawn_update`.
+fn spawn_async(input: &syn::ItemFn, call: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if input.sig.asyncness.is_some() {
+        quote! { hot_ice::macro_use::spawn_update(#call) }
+    } else {
+        call
+    }
+}
+
+/// The async update `input` as plain iced calls it without the feature: a
+/// function of its name blocking on the renamed async function.
+fn blocking_update(input: &syn::ItemFn) -> Vec<proc_macro2::TokenStream> {
+    let FnInfo {
+        inner_fn_ident,
+        arg_names,
+        ..
+    } = extract_fn_info(input);
+
+    let mut inner = input.clone();
+    inner.sig.ident = inner_fn_ident.clone();
+    inner.attrs.retain(|attr| !attr.path().is_ident("doc"));
+    inner.attrs.push(syn::parse_quote!(#[doc(hidden)]));
+
+    let call = match input.sig.inputs.first() {
+        Some(syn::FnArg::Receiver(_)) => quote! { self.#inner_fn_ident(#(#arg_names),*) },
+        _ => quote! { Self::#inner_fn_ident(#(#arg_names),*) },
+    };
+    let mut outer = input.clone();
+    outer.sig.asyncness = None;
+    outer.block = syn::parse_quote!({ hot_ice::macro_use::block_on_update(#call) });
+
+    vec![quote!(#outer), quote!(#inner)]
+}
+
+/// Converts the `HotMessage` named `message` to the type of the message
+/// argument of the update `input`.
+fn receive_message(
+    input: &syn::ItemFn,
+    output_span: proc_macro2::Span,
+) -> proc_macro2::TokenStream {
+    let message_span =
+        input
+            .sig
+            .inputs
+            .iter()
+            .nth(1)
+            .map_or(output_span, |message| match message {
+                syn::FnArg::Typed(message) => message.ty.span(),
+                syn::FnArg::Receiver(receiver) => receiver.span(),
+            });
+    let message_type = match input.sig.inputs.iter().nth(1) {
+        Some(syn::FnArg::Typed(message)) => {
+            let ty = &message.ty;
+            quote!(#ty)
+        }
+        _ => quote!(_),
+    };
+    quote_spanned! {message_span=>
+        {
+            #[allow(unused_imports)]
+            use hot_ice::macro_use::{ViaDowncast as _, ViaTransfer as _};
+            (&hot_ice::macro_use::Bridge::<#message_type>::default()).receive(message)
+        }
+    }
+}
+
 fn view(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut input = parse_macro_input!(item as syn::ItemFn);
     let FnInfo {
         original_fn_name,
         inner_fn_ident,
         vis,
+        output_span,
         ..
     } = extract_fn_info(&input);
 
     input.sig.ident = inner_fn_ident.clone();
+    let (window_param, window_arg, window_type) = window_tokens(&input);
+    let (context_param, context_arg, context_type) = context_tokens(takes_context(&input));
 
-    let transformed = transform_element_return_type(&input.sig.output);
-    let inner_return_type = match transformed {
-        syn::ReturnType::Default => quote! { () },
-        syn::ReturnType::Type(_, ty) => quote! { #ty },
+    let (inner_return_type, into_element) = hot_element(&input, output_span);
+    let into_hot = into_hot_message(output_span);
+    let map_element = quote_spanned! {output_span=>
+        element.map(#into_hot)
+    };
+
+    let state_type = if hot_state {
+        quote! { &hot_ice::macro_use::HotState }
+    } else {
+        quote! { &Self }
     };
+    let export = export_companion(
+        &original_fn_name,
+        "View",
+        quote! { (#state_type, #window_type #context_type hot_ice::macro_use::HotResult<#inner_return_type>) },
+    );
 
     let load_font_ident =
         proc_macro2::Ident::new(LOAD_FONT_FUNCTION_NAME, proc_macro2::Span::call_site());
@@ -448,14 +1083,15 @@ fn view(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStre
     let expanded = if hot_state {
         quote! {
             #[unsafe(no_mangle)]
-            #vis fn #original_fn_name(state: &hot_ice::macro_use::HotState) -> hot_ice::macro_use::HotResult<#inner_return_type> {
+            #vis fn #original_fn_name(state: &hot_ice::macro_use::HotState #window_param #context_param) -> hot_ice::macro_use::HotResult<#inner_return_type> {
                 hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| {
-                    Self::#inner_fn_ident(state.ref_state())
-                        .map(hot_ice::macro_use::DynMessage::into_hot_message)
+                    let element = Self::#inner_fn_ident(state.ref_state() #window_arg #context_arg);
+                    #into_element
+                    #map_element
                 }) {
                     ::core::result::Result::Ok(element) => ::core::result::Result::Ok(element),
                     ::core::result::Result::Err(err_msg) => {
-                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg))
+                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg)))
                     }
                 })
             }
@@ -463,18 +1099,21 @@ fn view(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStre
             #input
 
             #load_font_fn
+
+            #export
         }
     } else {
         quote! {
             #[unsafe(no_mangle)]
-            #vis fn #original_fn_name(&self) -> hot_ice::macro_use::HotResult<#inner_return_type> {
+            #vis fn #original_fn_name(&self #window_param #context_param) -> hot_ice::macro_use::HotResult<#inner_return_type> {
                 hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| {
-                    self.#inner_fn_ident()
-                        .map(hot_ice::macro_use::DynMessage::into_hot_message)
+                    let element = Self::#inner_fn_ident(self #window_arg #context_arg);
+                    #into_element
+                    #map_element
                 }) {
                     ::core::result::Result::Ok(element) => ::core::result::Result::Ok(element),
                     ::core::result::Result::Err(err_msg) => {
-                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg))
+                        ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg)))
                     }
                 })
             }
@@ -482,62 +1121,463 @@ fn view(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStre
             #input
 
             #load_font_fn
+
+            #export
         }
     };
 
     proc_macro::TokenStream::from(expanded)
 }
 
+/// The `Element` of `HotMessage`s the view `input` is turned into, and the
+/// statement converting its return value named `element` into the `Element`.
+///
+/// Views returning a widget or `impl Into<Element>` are converted into the
+/// `Element` first, so the wrapper can map its messages.
+fn hot_element(
+    input: &syn::ItemFn,
+    output_span: proc_macro2::Span,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let (element_type, into_element) = match &input.sig.output {
+        syn::ReturnType::Default => (syn::parse_quote!(()), false),
+        syn::ReturnType::Type(_, ty) => view_element_type(ty),
+    };
+    let inner_return_type = if is_element_alias(&element_type) {
+        quote! { <#element_type as hot_ice::macro_use::HotElement>::Hot }
+    } else {
+        match transform_element_return_type(&syn::parse_quote!(-> #element_type)) {
+            syn::ReturnType::Default => quote! { () },
+            syn::ReturnType::Type(_, ty) => quote! { #ty },
+        }
+    };
+    let into_element = if into_element {
+        quote_spanned! {output_span=>
+            let element: #element_type = ::core::convert::Into::into(element);
+        }
+    } else {
+        quote! {}
+    };
+    (inner_return_type, into_element)
+}
+
 fn subscription(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut input = parse_macro_input!(item as syn::ItemFn);
     let FnInfo {
         original_fn_name,
         inner_fn_ident,
         vis,
+        output_span,
         ..
     } = extract_fn_info(&input);
 
     input.sig.ident = inner_fn_ident.clone();
 
+    let state_type = if hot_state {
+        quote! { &hot_ice::macro_use::HotState }
+    } else {
+        quote! { &Self }
+    };
+    let export = export_companion(
+        &original_fn_name,
+        "Subscription",
+        quote! {
+            (
+                #state_type,
+                hot_ice::macro_use::HotResult<iced::Subscription<hot_ice::macro_use::HotMessage>>,
+            )
+        },
+    );
+
+    let into_hot = into_hot_message(output_span);
+    let call = if hot_state {
+        quote_spanned! {output_span=>
+            Self::#inner_fn_ident(state.ref_state())
+                .map(#into_hot)
+        }
+    } else {
+        quote_spanned! {output_span=>
+            self.#inner_fn_ident().map(#into_hot)
+        }
+    };
+
     let expanded = if hot_state {
         quote! {
             #[unsafe(no_mangle)]
             #vis fn #original_fn_name(state: &hot_ice::macro_use::HotState) -> hot_ice::macro_use::HotResult<iced::Subscription<hot_ice::macro_use::HotMessage>> {
-                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| {
-                    Self::#inner_fn_ident(state.ref_state())
-                        .map(hot_ice::macro_use::DynMessage::into_hot_message)
-                }) {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
                     Ok(subscription) => Ok(subscription),
-                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg)),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
                 })
             }
             #input
+
+            #export
         }
     } else {
         quote! {
             #[unsafe(no_mangle)]
             #vis fn #original_fn_name(&self) -> hot_ice::macro_use::HotResult<iced::Subscription<hot_ice::macro_use::HotMessage>> {
-                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| {
-                    self.#inner_fn_ident()
-                        .map(hot_ice::macro_use::DynMessage::into_hot_message)
-                }) {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
                     Ok(subscription) => Ok(subscription),
-                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg)),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
+                })
+            }
+            #input
+
+            #export
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+fn setup(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input = parse_macro_input!(item as syn::ItemFn);
+    let FnInfo {
+        original_fn_name,
+        inner_fn_ident,
+        vis,
+        output_span,
+        ..
+    } = extract_fn_info(&input);
+
+    input.sig.ident = inner_fn_ident.clone();
+
+    let state_type = if hot_state {
+        quote! { &mut hot_ice::macro_use::HotState }
+    } else {
+        quote! { &mut Self }
+    };
+    let export = export_companion(
+        &original_fn_name,
+        "Setup",
+        quote! {
+            (
+                #state_type,
+                hot_ice::macro_use::HotResult<iced::Task<hot_ice::macro_use::HotMessage>>,
+            )
+        },
+    );
+
+    let into_hot = into_hot_message(output_span);
+    let call = if hot_state {
+        quote_spanned! {output_span=>
+            Self::#inner_fn_ident(state.ref_mut_state())
+                .map(#into_hot)
+        }
+    } else {
+        quote_spanned! {output_span=>
+            self.#inner_fn_ident().map(#into_hot)
+        }
+    };
+
+    let expanded = if hot_state {
+        quote! {
+            #[unsafe(no_mangle)]
+            #vis fn #original_fn_name(state: &mut hot_ice::macro_use::HotState) -> hot_ice::macro_use::HotResult<iced::Task<hot_ice::macro_use::HotMessage>> {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
+                    Ok(task) => Ok(task),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
+                })
+            }
+            #input
+
+            #export
+        }
+    } else {
+        quote! {
+            #[unsafe(no_mangle)]
+            #vis fn #original_fn_name(&mut self) -> hot_ice::macro_use::HotResult<iced::Task<hot_ice::macro_use::HotMessage>> {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
+                    Ok(task) => Ok(task),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
                 })
             }
             #input
+
+            #export
         }
     };
 
     proc_macro::TokenStream::from(expanded)
 }
 
+fn on_event(hot_state: bool, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input = parse_macro_input!(item as syn::ItemFn);
+    if takes_context(&input) {
+        let context = input.sig.inputs.last();
+        return syn::Error::new_spanned(
+            context,
+            "`on_event` is called by the host with the event and window only, \
+             it cannot take a `&hot_ice::Context`",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let FnInfo {
+        original_fn_name,
+        inner_fn_ident,
+        vis,
+        output_span,
+        args_no_receiver,
+        arg_names,
+        ..
+    } = extract_fn_info(&input);
+
+    input.sig.ident = inner_fn_ident.clone();
+
+    let state_type = if hot_state {
+        quote! { &hot_ice::macro_use::HotState }
+    } else {
+        quote! { &Self }
+    };
+    let arg_types = args_no_receiver.iter().filter_map(|arg| match arg {
+        syn::FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+        syn::FnArg::Receiver(_) => None,
+    });
+    let export = export_companion(
+        &original_fn_name,
+        "OnEvent",
+        quote! {
+            (
+                #state_type,
+                #(#arg_types,)*
+                hot_ice::macro_use::HotResult<::core::option::Option<hot_ice::macro_use::HotMessage>>,
+            )
+        },
+    );
+
+    let into_hot = into_hot_message(output_span);
+    let call = if hot_state {
+        quote_spanned! {output_span=>
+            Self::#inner_fn_ident(state.ref_state(), #(#arg_names),*)
+                .map(#into_hot)
+        }
+    } else {
+        quote_spanned! {output_span=>
+            self.#inner_fn_ident(#(#arg_names),*).map(#into_hot)
+        }
+    };
+
+    let expanded = if hot_state {
+        quote! {
+            #[unsafe(no_mangle)]
+            #vis fn #original_fn_name(state: &hot_ice::macro_use::HotState, #(#args_no_receiver),*) -> hot_ice::macro_use::HotResult<::core::option::Option<hot_ice::macro_use::HotMessage>> {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
+                    Ok(message) => Ok(message),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
+                })
+            }
+            #input
+
+            #export
+        }
+    } else {
+        let original_inputs = &input.sig.inputs;
+        quote! {
+            #[unsafe(no_mangle)]
+            #vis fn #original_fn_name(#original_inputs) -> hot_ice::macro_use::HotResult<::core::option::Option<hot_ice::macro_use::HotMessage>> {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
+                    Ok(message) => Ok(message),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
+                })
+            }
+            #input
+
+            #export
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// Generates the export of a method of a sub-state for
+/// `#[hot_fn(sub_state = Parent.field)]`. The method itself is kept as it is,
+/// so the parent's functions keep calling it. Next to it a function named
+/// after the fields and the method, e.g. `counter_view`, takes the parent's
+/// `HotState` like a `#[hot_fn(hot_state)]` function of the root state,
+/// borrows the sub-state out of it and calls the method. It deals in the
+/// messages of the sub-state.
+fn sub_state_fn(
+    sub_state: &SubState,
+    fn_type: FnType,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as syn::ItemFn);
+    let FnInfo {
+        original_fn_name,
+        vis,
+        output_span,
+        return_type,
+        args_no_receiver,
+        arg_names,
+        ..
+    } = extract_fn_info(&input);
+    let export_ident = sub_state.export_ident(&original_fn_name);
+    let into_hot = into_hot_message(output_span);
+
+    let (context_param, context_arg, context_type) = context_tokens(takes_context(&input));
+    let shared = sub_state.access(false);
+
+    let expanded = match fn_type {
+        FnType::Update => {
+            let exclusive = sub_state.access(true);
+            let message_conversion = receive_message(&input, output_span);
+            let call = spawn_async(
+                &input,
+                quote! { Self::#original_fn_name(#exclusive, message #context_arg) },
+            );
+            let export = export_companion(
+                &export_ident,
+                "Update",
+                quote! {
+                    (
+                        &mut hot_ice::macro_use::HotState,
+                        hot_ice::macro_use::HotMessage,
+                        #context_type
+                        ::core::result::Result<iced::Task<hot_ice::macro_use::HotMessage>, hot_ice::macro_use::HotIceError>,
+                    )
+                },
+            );
+            quote! {
+                #[unsafe(no_mangle)]
+                #vis fn #export_ident(
+                    state: &mut hot_ice::macro_use::HotState,
+                    message: hot_ice::macro_use::HotMessage
+                    #context_param
+                ) -> ::core::result::Result<iced::Task<hot_ice::macro_use::HotMessage>, hot_ice::macro_use::HotIceError> {
+                    let ::core::option::Option::Some(message) = #message_conversion? else {
+                        return ::core::result::Result::Ok(iced::Task::none());
+                    };
+
+                    match hot_ice::macro_use::catch_panic(|| {
+                        hot_ice::macro_use::time_update(|| #call).map(#into_hot)
+                    }) {
+                        ::core::result::Result::Ok(task) => ::core::result::Result::Ok(task),
+                        ::core::result::Result::Err(err_msg) => {
+                            ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg)))
+                        }
+                    }
+                }
+
+                #input
+
+                #export
+            }
+        }
+        FnType::View => {
+            let (inner_return_type, into_element) = hot_element(&input, output_span);
+            let (window_param, window_arg, window_type) = window_tokens(&input);
+            let export = export_companion(
+                &export_ident,
+                "View",
+                quote! { (&hot_ice::macro_use::HotState, #window_type #context_type hot_ice::macro_use::HotResult<#inner_return_type>) },
+            );
+            quote! {
+                #[unsafe(no_mangle)]
+                #vis fn #export_ident(state: &hot_ice::macro_use::HotState #window_param #context_param) -> hot_ice::macro_use::HotResult<#inner_return_type> {
+                    hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| {
+                        let element = Self::#original_fn_name(#shared #window_arg #context_arg);
+                        #into_element
+                        element.map(#into_hot)
+                    }) {
+                        ::core::result::Result::Ok(element) => ::core::result::Result::Ok(element),
+                        ::core::result::Result::Err(err_msg) => {
+                            ::core::result::Result::Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg)))
+                        }
+                    })
+                }
+
+                #input
+
+                #export
+            }
+        }
+        FnType::Subscription => {
+            let export = export_companion(
+                &export_ident,
+                "Subscription",
+                quote! {
+                    (
+                        &hot_ice::macro_use::HotState,
+                        hot_ice::macro_use::HotResult<iced::Subscription<hot_ice::macro_use::HotMessage>>,
+                    )
+                },
+            );
+            quote! {
+                #[unsafe(no_mangle)]
+                #vis fn #export_ident(state: &hot_ice::macro_use::HotState) -> hot_ice::macro_use::HotResult<iced::Subscription<hot_ice::macro_use::HotMessage>> {
+                    hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| Self::#original_fn_name(#shared).map(#into_hot)) {
+                        Ok(subscription) => Ok(subscription),
+                        Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
+                    })
+                }
+
+                #input
+
+                #export
+            }
+        }
+        FnType::Other => {
+            let arg_types = args_no_receiver.iter().filter_map(|arg| match arg {
+                syn::FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+                syn::FnArg::Receiver(_) => None,
+            });
+            let export = export_companion(
+                &export_ident,
+                detect_other_kind(&input),
+                quote! { (&hot_ice::macro_use::HotState, #(#arg_types,)* hot_ice::macro_use::HotResult<#return_type>) },
+            );
+            quote! {
+                #[unsafe(no_mangle)]
+                #vis fn #export_ident(state: &hot_ice::macro_use::HotState, #(#args_no_receiver),*) -> hot_ice::macro_use::HotResult<#return_type> {
+                    hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| Self::#original_fn_name(#shared, #(#arg_names),*)) {
+                        Ok(result) => Ok(result),
+                        Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
+                    })
+                }
+
+                #input
+
+                #export
+            }
+        }
+        FnType::Boot | FnType::Setup | FnType::OnEvent => {
+            return syn::Error::new_spanned(
+                &input.sig,
+                "only `update`, `view`, `subscription`, `theme`, `style`, \
+                 `scale_factor` and `title` can be hot functions of a sub-state, \
+                 the host calls the others on the root state",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// The closure turning a message of the user's type into a `HotMessage`,
+/// which picks the bridge of `src/transfer.rs` for the type.
+fn into_hot_message(span: proc_macro2::Span) -> proc_macro2::TokenStream {
+    quote_spanned! {span=>
+        |message| {
+            #[allow(unused_imports)]
+            use hot_ice::macro_use::{ViaDowncast as _, ViaTransfer as _};
+            (&hot_ice::macro_use::Bridge::of(&message)).send(message)
+        }
+    }
+}
+
 /// Helper struct containing parsed function info for the simple panic-catching functions.
 /// All fields are owned to avoid borrow conflicts when mutating the input function.
 struct FnInfo {
     original_fn_name: syn::Ident,
     inner_fn_ident: proc_macro2::Ident,
     vis: syn::Visibility,
+    /// Span of the user's return type, given to the code converting what the
+    /// function returns so type errors point at it.
+    output_span: proc_macro2::Span,
     return_type: proc_macro2::TokenStream,
     args_no_receiver: Vec<syn::FnArg>,
     arg_names: Vec<syn::Ident>,
@@ -547,10 +1587,14 @@ struct FnInfo {
 /// Clones all necessary data to avoid borrow conflicts.
 fn extract_fn_info(input: &syn::ItemFn) -> FnInfo {
     let original_fn_name = input.sig.ident.clone();
-    let inner_fn_name = format!("{}_inner_{}", &input.sig.ident, INNER_FUNCTION_POSTFIX);
-    let inner_fn_ident = proc_macro2::Ident::new(&inner_fn_name, proc_macro2::Span::call_site());
+    let inner_fn_name = inner_fn_name(&input.sig.ident);
+    let inner_fn_ident = proc_macro2::Ident::new(&inner_fn_name, input.sig.ident.span());
 
     let vis = input.vis.clone();
+    let output_span = match &input.sig.output {
+        syn::ReturnType::Default => input.sig.ident.span(),
+        syn::ReturnType::Type(_, ty) => ty.span(),
+    };
     let return_type = match &input.sig.output {
         syn::ReturnType::Default => quote! { () },
         syn::ReturnType::Type(_, ty) => quote! { #ty },
@@ -571,12 +1615,18 @@ fn extract_fn_info(input: &syn::ItemFn) -> FnInfo {
         original_fn_name,
         inner_fn_ident,
         vis,
+        output_span,
         return_type,
         args_no_receiver,
         arg_names,
     }
 }
 
+/// Name the user's function is renamed to, the wrapper takes its name.
+fn inner_fn_name(original_fn_name: &syn::Ident) -> String {
+    format!("{original_fn_name}_inner_{INNER_FUNCTION_POSTFIX}")
+}
+
 /// Generates a simple panic-catching wrapper function that returns HotResult<T>.
 /// Used by theme, style, scale_factor, and title.
 fn generate_simple_wrapper(
@@ -588,6 +1638,7 @@ fn generate_simple_wrapper(
         original_fn_name,
         inner_fn_ident,
         vis,
+        output_span,
         return_type,
         args_no_receiver,
         arg_names,
@@ -595,30 +1646,201 @@ fn generate_simple_wrapper(
 
     input.sig.ident = inner_fn_ident.clone();
 
+    let state_type = if hot_state {
+        quote! { &hot_ice::macro_use::HotState }
+    } else {
+        quote! { &Self }
+    };
+    let arg_types = args_no_receiver.iter().filter_map(|arg| match arg {
+        syn::FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+        syn::FnArg::Receiver(_) => None,
+    });
+    let export = export_companion(
+        &original_fn_name,
+        detect_other_kind(&input),
+        quote! { (#state_type, #(#arg_types,)* hot_ice::macro_use::HotResult<#return_type>) },
+    );
+    let call = if hot_state {
+        quote_spanned! {output_span=> Self::#inner_fn_ident(state.ref_state(), #(#arg_names),*) }
+    } else {
+        quote_spanned! {output_span=> self.#inner_fn_ident(#(#arg_names),*) }
+    };
+
     let expanded = if hot_state {
         quote! {
             #[unsafe(no_mangle)]
             #vis fn #original_fn_name(state: &hot_ice::macro_use::HotState, #(#args_no_receiver),*) -> hot_ice::macro_use::HotResult<#return_type> {
-                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| Self::#inner_fn_ident(state.ref_state(), #(#arg_names),*)) {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
                     Ok(result) => Ok(result),
-                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg)),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
                 })
             }
             #input
+
+            #export
         }
     } else {
         let original_inputs = &input.sig.inputs;
         quote! {
             #[unsafe(no_mangle)]
             #vis fn #original_fn_name(#original_inputs) -> hot_ice::macro_use::HotResult<#return_type> {
-                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| self.#inner_fn_ident(#(#arg_names),*)) {
+                hot_ice::macro_use::HotResult(match hot_ice::macro_use::catch_panic(|| #call) {
                     Ok(result) => Ok(result),
-                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(err_msg)),
+                    Err(err_msg) => Err(hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err_msg))),
                 })
             }
             #input
+
+            #export
         }
     };
 
     proc_macro::TokenStream::from(expanded)
 }
+
+/// Picks the [`HotFunction`] kind reported by the export companion of a
+/// function handled by [`generate_simple_wrapper`]. The function name is
+/// checked first, then the return type. Functions of no kind the builder
+/// knows are called as a `HotExtension`.
+fn detect_other_kind(input: &syn::ItemFn) -> &'static str {
+    match input.sig.ident.to_string().as_str() {
+        "theme" => return "Theme",
+        "style" => return "Style",
+        "scale_factor" => return "ScaleFactor",
+        "title" => return "Title",
+        _ => {}
+    }
+
+    let return_type = &input.sig.output;
+    let return_type_str = quote!(#return_type).to_string();
+
+    if return_type_str.contains("Style") {
+        "Style"
+    } else if return_type_str.contains("Theme") {
+        "Theme"
+    } else if return_type_str.contains("String") || returns_str(return_type) {
+        "Title"
+    } else if return_type_str.contains("f32") || return_type_str.contains("f64") {
+        "ScaleFactor"
+    } else {
+        "Extension"
+    }
+}
+
+/// Whether `output` is a `&str`, e.g. the `&'static str` of a constant title.
+fn returns_str(output: &syn::ReturnType) -> bool {
+    let syn::ReturnType::Type(_, ty) = output else {
+        return false;
+    };
+    matches!(
+        &**ty,
+        syn::Type::Reference(reference)
+            if matches!(&*reference.elem, syn::Type::Path(path) if path.path.is_ident("str"))
+    )
+}
+
+/// Generates the companion symbol that reports the kind and signature hash of
+/// a hot function to the host's export registry.
+///
+/// `signature` is the tuple of argument types followed by the return type of
+/// the exported wrapper, matching what the host hashes for the same function.
+fn export_companion(
+    original_fn_name: &syn::Ident,
+    kind: &str,
+    signature: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let companion_ident = proc_macro2::Ident::new(
+        &format!("{HOT_EXPORT_SYMBOL_PREFIX}{original_fn_name}"),
+        proc_macro2::Span::call_site(),
+    );
+    let kind = match kind {
+        // Interned in the host, the name has to outlive the library.
+        "Extension" => {
+            let name = original_fn_name.to_string();
+            quote! { Extension(hot_ice::macro_use::intern(#name)) }
+        }
+        kind => {
+            let kind = proc_macro2::Ident::new(kind, proc_macro2::Span::call_site());
+            quote! { #kind }
+        }
+    };
+    let signature = erase_lifetimes(signature);
+
+    quote! {
+        #[doc(hidden)]
+        #[unsafe(no_mangle)]
+        pub fn #companion_ident() -> hot_ice::macro_use::HotExport {
+            hot_ice::macro_use::HotExport {
+                kind: hot_ice::macro_use::HotFunction::#kind,
+                type_hash: hot_ice::macro_use::type_hash::<#signature>(),
+            }
+        }
+    }
+}
+
+/// Replaces every named lifetime with `'_` so a type taken from the user's
+/// signature can be named inside a function that does not declare them.
+fn erase_lifetimes(tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    use proc_macro2::{Group, Spacing, TokenTree};
+
+    let mut after_apostrophe = false;
+    tokens
+        .into_iter()
+        .map(|token| {
+            let token = match token {
+                TokenTree::Ident(ident) if after_apostrophe => {
+                    TokenTree::Ident(proc_macro2::Ident::new("_", ident.span()))
+                }
+                TokenTree::Group(group) => {
+                    let mut erased = Group::new(group.delimiter(), erase_lifetimes(group.stream()));
+                    erased.set_span(group.span());
+                    TokenTree::Group(erased)
+                }
+                token => token,
+            };
+            after_apostrophe = matches!(
+                &token,
+                TokenTree::Punct(punct) if punct.as_char() == '\'' && punct.spacing() == Spacing::Joint
+            );
+            token
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boot_taking_the_context_is_detected() {
+        let input: syn::ItemFn = syn::parse_quote! {
+            pub fn boot(context: &hot_ice::Context) -> Self {
+                Self::default()
+            }
+        };
+
+        assert!(matches!(detect_fn_type(&input), FnType::Boot));
+    }
+
+    #[test]
+    fn boot_forwards_the_context() {
+        let input: syn::ItemFn = syn::parse_quote! {
+            pub fn boot(context: &hot_ice::Context) -> Self {
+                Self::default()
+            }
+        };
+        let inner = proc_macro2::Ident::new(
+            &inner_fn_name(&input.sig.ident),
+            proc_macro2::Span::call_site(),
+        );
+
+        let expanded = boot(false, input).to_string();
+
+        let wrapper = quote! {
+            pub fn boot(context: &hot_ice::Context) -> (Self, iced::Task<hot_ice::macro_use::HotMessage>)
+        };
+        assert!(expanded.contains(&wrapper.to_string()), "{expanded}");
+        let call = quote! { let app = Self::#inner(context); };
+        assert!(expanded.contains(&call.to_string()), "{expanded}");
+    }
+}
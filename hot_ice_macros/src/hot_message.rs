@@ -0,0 +1,221 @@
+use quote::{format_ident, quote};
+use syn::parse_macro_input;
+
+use crate::hot_state::fnv1a;
+
+pub fn derive_hot_message(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(item as syn::DeriveInput);
+    match expand(&ast) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+struct Variant<'a> {
+    variant: &'a syn::Variant,
+    skip: bool,
+}
+
+fn expand(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let syn::Data::Enum(data) = &ast.data else {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "`HotMessage` can only be derived for a message enum",
+        ));
+    };
+    if !ast.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &ast.generics,
+            "`HotMessage` cannot be derived for a generic message type",
+        ));
+    }
+
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| {
+            Ok(Variant {
+                variant,
+                skip: skip_attr(&variant.attrs)?,
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let transferred: Vec<_> = variants.iter().filter(|v| !v.skip).collect();
+
+    let name = &ast.ident;
+    let name_str = name.to_string();
+    // Changes with every edit of the enum, messages of another definition
+    // are transferred through serde.
+    let type_hash = fnv1a(quote!(#ast).to_string().as_bytes());
+
+    // The enums serde derives for, without the skipped variants. The one
+    // serialized borrows the fields of the message.
+    let borrowed_variants = transferred.iter().map(|Variant { variant, .. }| {
+        let ident = &variant.ident;
+        match &variant.fields {
+            syn::Fields::Named(fields) => {
+                let fields = fields.named.iter().map(|field| {
+                    let (ident, ty) = (&field.ident, &field.ty);
+                    quote!(#ident: &'hot_ice #ty)
+                });
+                quote!(#ident { #( #fields ),* })
+            }
+            syn::Fields::Unnamed(fields) => {
+                let types = fields.unnamed.iter().map(|field| &field.ty);
+                quote!(#ident( #( &'hot_ice #types ),* ))
+            }
+            syn::Fields::Unit => quote!(#ident),
+        }
+    });
+    let owned_variants = transferred.iter().map(|Variant { variant, .. }| {
+        let ident = &variant.ident;
+        match &variant.fields {
+            syn::Fields::Named(fields) => {
+                let fields = fields.named.iter().map(|field| {
+                    let (ident, ty) = (&field.ident, &field.ty);
+                    quote!(#ident: #ty)
+                });
+                quote!(#ident { #( #fields ),* })
+            }
+            syn::Fields::Unnamed(fields) => {
+                let types = fields.unnamed.iter().map(|field| &field.ty);
+                quote!(#ident( #( #types ),* ))
+            }
+            syn::Fields::Unit => quote!(#ident),
+        }
+    });
+
+    let to_borrowed = variants.iter().map(|Variant { variant, skip }| {
+        let ident = &variant.ident;
+        let (pattern, bindings) = bind_fields(variant);
+        if *skip {
+            let variant_str = ident.to_string();
+            quote! {
+                #name::#ident #pattern => {
+                    return ::core::result::Result::Err(
+                        <__S::Error as hot_ice::macro_use::serde::ser::Error>::custom(
+                            hot_ice::NotTransferable { variant: #variant_str },
+                        ),
+                    );
+                }
+            }
+        } else {
+            quote!(#name::#ident #pattern => __Borrowed::#ident #bindings,)
+        }
+    });
+    let from_owned = transferred.iter().map(|Variant { variant, .. }| {
+        let ident = &variant.ident;
+        let (pattern, bindings) = bind_fields(variant);
+        quote!(__Owned::#ident #pattern => #name::#ident #bindings,)
+    });
+    let not_transferable = variants
+        .iter()
+        .filter(|v| v.skip)
+        .map(|Variant { variant, .. }| {
+            let ident = &variant.ident;
+            let variant_str = ident.to_string();
+            let pattern = match &variant.fields {
+                syn::Fields::Named(_) => quote!({ .. }),
+                syn::Fields::Unnamed(_) => quote!((..)),
+                syn::Fields::Unit => quote!(),
+            };
+            quote! {
+                #name::#ident #pattern => ::core::result::Result::Err(
+                    hot_ice::NotTransferable { variant: #variant_str },
+                ),
+            }
+        });
+
+    Ok(quote! {
+        const _: () = {
+            #[derive(hot_ice::macro_use::serde_derive::Serialize)]
+            #[serde(crate = "hot_ice::macro_use::serde", rename = #name_str)]
+            #[allow(dead_code)]
+            enum __Borrowed<'hot_ice> {
+                #( #borrowed_variants, )*
+                #[serde(skip)]
+                __Lifetime(::core::marker::PhantomData<&'hot_ice ()>),
+            }
+
+            #[derive(hot_ice::macro_use::serde_derive::Deserialize)]
+            #[serde(crate = "hot_ice::macro_use::serde", rename = #name_str)]
+            #[allow(dead_code)]
+            enum __Owned {
+                #( #owned_variants, )*
+            }
+
+            impl hot_ice::macro_use::serde::Serialize for #name {
+                fn serialize<__S>(&self, serializer: __S) -> ::core::result::Result<__S::Ok, __S::Error>
+                where
+                    __S: hot_ice::macro_use::serde::Serializer,
+                {
+                    let borrowed = match self {
+                        #( #to_borrowed )*
+                    };
+                    hot_ice::macro_use::serde::Serialize::serialize(&borrowed, serializer)
+                }
+            }
+
+            impl<'de> hot_ice::macro_use::serde::Deserialize<'de> for #name {
+                fn deserialize<__D>(deserializer: __D) -> ::core::result::Result<Self, __D::Error>
+                where
+                    __D: hot_ice::macro_use::serde::Deserializer<'de>,
+                {
+                    let owned: __Owned = hot_ice::macro_use::serde::Deserialize::deserialize(deserializer)?;
+                    ::core::result::Result::Ok(match owned {
+                        #( #from_owned )*
+                    })
+                }
+            }
+
+            impl hot_ice::TransferMessage for #name {
+                const TYPE_HASH: ::core::primitive::u64 = #type_hash;
+
+                #[allow(unreachable_patterns)]
+                fn transferable(&self) -> ::core::result::Result<(), hot_ice::NotTransferable> {
+                    match self {
+                        #( #not_transferable )*
+                        _ => ::core::result::Result::Ok(()),
+                    }
+                }
+            }
+        };
+    })
+}
+
+/// Whether the variant is marked `#[hot_message(skip)]`.
+fn skip_attr(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let mut skip = false;
+    for attr in attrs {
+        if !attr.path().is_ident("hot_message") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip`"))
+            }
+        })?;
+    }
+    Ok(skip)
+}
+
+/// The pattern binding the fields of `variant`, and the same bindings to
+/// construct another variant of the same shape from them.
+fn bind_fields(variant: &syn::Variant) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    match &variant.fields {
+        syn::Fields::Named(fields) => {
+            let idents: Vec<_> = fields.named.iter().map(|field| &field.ident).collect();
+            (quote!({ #( #idents ),* }), quote!({ #( #idents ),* }))
+        }
+        syn::Fields::Unnamed(fields) => {
+            let idents: Vec<_> = (0..fields.unnamed.len())
+                .map(|i| format_ident!("field_{i}"))
+                .collect();
+            (quote!(( #( #idents ),* )), quote!(( #( #idents ),* )))
+        }
+        syn::Fields::Unit => (quote!(), quote!()),
+    }
+}
@@ -1,17 +1,25 @@
 use hot_ice_common::{
-    DESERIALIZE_STATE_FUNCTION_NAME, FREE_SERIALIZED_DATA_FUNCTION_NAME,
-    SERIALIZE_STATE_FUNCTION_NAME,
+    ADOPT_STATE_FUNCTION_NAME, DESERIALIZE_STATE_FUNCTION_NAME, DROP_STATE_FIELDS_FUNCTION_NAME,
+    FREE_SERIALIZED_DATA_FUNCTION_NAME, RESTORE_STATE_FIELDS_FUNCTION_NAME, SERIALIZE_STATE_FIELDS_FUNCTION_NAME,
+    SERIALIZE_STATE_FUNCTION_NAME, STATE_FIELDS_FUNCTION_NAME, STATE_TYPE_HASH_FUNCTION_NAME,
 };
-use quote::quote;
+use quote::{quote, quote_spanned};
 use syn::{Ident, Token, parse_macro_input};
 
+#[derive(Default)]
 struct HotStateArgs {
     feature: Option<String>,
+    /// Method called on the state after it was deserialized.
+    rebuild: Option<Ident>,
+    /// Emit the struct without any trace of the macro when the feature is off.
+    strip: bool,
 }
 
 impl syn::parse::Parse for HotStateArgs {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let mut feature = None;
+        let mut rebuild = None;
+        let mut strip = false;
 
         while !input.is_empty() {
             let key: Ident = input.parse()?;
@@ -20,6 +28,16 @@ impl syn::parse::Parse for HotStateArgs {
                 input.parse::<Token![=]>()?;
                 let lit: syn::LitStr = input.parse()?;
                 feature = Some(lit.value());
+            } else if key == "rebuild" {
+                rebuild = if input.peek(Token![=]) {
+                    input.parse::<Token![=]>()?;
+                    let lit: syn::LitStr = input.parse()?;
+                    Some(lit.parse()?)
+                } else {
+                    Some(key)
+                };
+            } else if key == "strip" {
+                strip = true;
             }
 
             if !input.is_empty() {
@@ -27,7 +45,15 @@ impl syn::parse::Parse for HotStateArgs {
             }
         }
 
-        Ok(HotStateArgs { feature })
+        if strip && feature.is_none() {
+            return Err(input.error("`strip` needs a `feature` to strip the macro without"));
+        }
+
+        Ok(HotStateArgs {
+            feature,
+            rebuild,
+            strip,
+        })
     }
 }
 
@@ -35,15 +61,34 @@ pub fn hot_state(
     attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let item_clone = item.clone();
-
     let args = if attr.is_empty() {
-        HotStateArgs { feature: None }
+        HotStateArgs::default()
     } else {
         parse_macro_input!(attr as HotStateArgs)
     };
 
-    let generated_code = generate_hot_state(item);
+    let mut ast = parse_macro_input!(item as syn::DeriveInput);
+    // The state is reset to its `Default`, which an enum can name with
+    // `#[hot_state(default)]` on a variant, with or without the feature.
+    let default_impl = match default_variant(&mut ast) {
+        Ok(default_impl) => default_impl,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    // Without `strip` this is applied without the feature as well, the
+    // struct still derives serde. With it the attributes are removed, so the
+    // struct needs neither serde nor hot_ice.
+    let mut stripped = ast.clone();
+    if let Err(err) = skip_fields(&mut ast) {
+        return err.to_compile_error().into();
+    }
+    let item_clone = if args.strip {
+        strip_fields(&mut stripped);
+        quote!(#stripped)
+    } else {
+        quote!(#ast)
+    };
+
+    let generated_code = generate_hot_state(ast, args.rebuild.as_ref());
 
     // If a feature is specified, wrap the generated code with feature gates.
     // The generated code contains multiple items (struct + impl block), so we
@@ -67,20 +112,135 @@ pub fn hot_state(
 
             #[cfg(not(feature = #feature_lit))]
             #original_tokens
+
+            #default_impl
         };
 
         wrapped.into()
     } else {
-        generated_code
+        let generated_tokens = proc_macro2::TokenStream::from(generated_code);
+        quote!(#generated_tokens #default_impl).into()
     }
 }
 
-fn generate_hot_state(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let mut ast = parse_macro_input!(item as syn::DeriveInput);
+/// The fields of the struct, or of every variant of the enum.
+fn fields_mut(data: &mut syn::Data) -> Box<dyn Iterator<Item = &mut syn::Field> + '_> {
+    match data {
+        syn::Data::Struct(data) => Box::new(data.fields.iter_mut()),
+        syn::Data::Enum(data) => Box::new(
+            data.variants
+                .iter_mut()
+                .flat_map(|variant| variant.fields.iter_mut()),
+        ),
+        syn::Data::Union(_) => Box::new(std::iter::empty()),
+    }
+}
+
+/// Removes the `#[hot_state(default)]` attribute of an enum's variant and
+/// implements `Default` with that variant, its fields set to their defaults.
+fn default_variant(ast: &mut syn::DeriveInput) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let syn::Data::Enum(data) = &mut ast.data else {
+        return Ok(None);
+    };
+
+    let mut default = None;
+    for variant in data.variants.iter_mut() {
+        let mut marked = false;
+        let mut result = Ok(());
+        variant.attrs.retain(|attr| {
+            if !attr.path().is_ident("hot_state") {
+                return true;
+            }
+            result = result.clone().and(attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `default`"))
+                }
+            }));
+            marked = true;
+            false
+        });
+        result?;
+
+        if marked {
+            if default.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "only one variant can be the `#[hot_state(default)]`",
+                ));
+            }
+            default = Some(variant.clone());
+        }
+    }
+
+    let Some(variant) = default else {
+        return Ok(None);
+    };
+    let variant_ident = &variant.ident;
+    let value = match &variant.fields {
+        syn::Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(Self::#variant_ident { #( #names: ::core::default::Default::default() ),* })
+        }
+        syn::Fields::Unnamed(fields) => {
+            let defaults = fields
+                .unnamed
+                .iter()
+                .map(|_| quote!(::core::default::Default::default()));
+            quote!(Self::#variant_ident( #( #defaults ),* ))
+        }
+        syn::Fields::Unit => quote!(Self::#variant_ident),
+    };
+
+    let name = &ast.ident;
+    let (impl_generics, type_generics, where_clause) = ast.generics.split_for_impl();
+    Ok(Some(quote! {
+        impl #impl_generics ::core::default::Default for #name #type_generics #where_clause {
+            fn default() -> Self {
+                #value
+            }
+        }
+    }))
+}
+
+/// Replaces the `#[hot_state(skip)]` attributes of the fields with
+/// `#[serde(skip)]`, the fields are reset to their `Default` on reload.
+fn skip_fields(ast: &mut syn::DeriveInput) -> syn::Result<()> {
+    for field in fields_mut(&mut ast.data) {
+        for attr in field.attrs.iter_mut() {
+            if !attr.path().is_ident("hot_state") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip`"))
+                }
+            })?;
+            *attr = syn::parse_quote!(#[serde(skip)]);
+        }
+    }
+    Ok(())
+}
 
+/// Removes the `#[hot_state(skip)]` attributes of the fields.
+fn strip_fields(ast: &mut syn::DeriveInput) {
+    for field in fields_mut(&mut ast.data) {
+        field
+            .attrs
+            .retain(|attr| !attr.path().is_ident("hot_state"));
+    }
+}
+
+fn generate_hot_state(
+    mut ast: syn::DeriveInput,
+    rebuild: Option<&Ident>,
+) -> proc_macro::TokenStream {
     let mut has_struct_default = false;
     for attr in &ast.attrs {
-        if attr.path().is_ident("hot_ice::serde") {
+        if attr.path().is_ident("serde") || attr.path().is_ident("hot_ice::serde") {
             if quote::ToTokens::to_token_stream(&attr.meta)
                 .to_string()
                 .contains("default")
@@ -91,7 +251,19 @@ fn generate_hot_state(item: proc_macro::TokenStream) -> proc_macro::TokenStream
         }
     }
 
-    if !has_struct_default {
+    // serde only takes a container `default` on structs, the fields of an
+    // enum's variants are defaulted one by one instead.
+    if let syn::Data::Enum(data) = &mut ast.data {
+        for variant in data.variants.iter_mut() {
+            if let syn::Fields::Named(fields) = &mut variant.fields {
+                for field in fields.named.iter_mut() {
+                    if !field.attrs.iter().any(|attr| attr.path().is_ident("serde")) {
+                        field.attrs.push(syn::parse_quote!(#[serde(default)]));
+                    }
+                }
+            }
+        }
+    } else if !has_struct_default {
         let default_attr: syn::Attribute = syn::parse_quote!(#[serde(default)]);
         ast.attrs.push(default_attr);
     }
@@ -109,19 +281,50 @@ fn generate_hot_state(item: proc_macro::TokenStream) -> proc_macro::TokenStream
         FREE_SERIALIZED_DATA_FUNCTION_NAME,
         proc_macro2::Span::call_site(),
     );
+    let state_type_hash_ident = proc_macro2::Ident::new(
+        STATE_TYPE_HASH_FUNCTION_NAME,
+        proc_macro2::Span::call_site(),
+    );
+    let adopt_state_ident =
+        proc_macro2::Ident::new(ADOPT_STATE_FUNCTION_NAME, proc_macro2::Span::call_site());
+    // Skipped fields hold their default after a failed deserialization too.
+    // A panic is reported like one of a hot function instead of unwinding
+    // into the host, an earlier error of `result` is kept.
+    let rebuild = rebuild.map(|rebuild| {
+        quote_spanned! {rebuild.span()=>
+            let result = result.and(
+                hot_ice::macro_use::catch_panic(|| Self::#rebuild(state.ref_mut_state::<Self>()))
+                    .map_err(|err| hot_ice::macro_use::HotIceError::FunctionPanicked(::std::sync::Arc::from(err))),
+            );
+        }
+    });
+    // The bounds the state has to meet are checked by these calls, their
+    // errors point at the name of the type.
+    let span = struct_name.span();
+    let serialize = quote_spanned! {span=> state.serialize_state::<Self>() };
+    let deserialize = quote_spanned! {span=> state.deserialize_state::<Self>(data) };
+    // `0` unless the state derives `TypeHash`, it is never handed over then.
+    let type_hash = quote_spanned! {span=> {
+        use hot_ice::macro_use::{Unverified as _, ViaTypeHash as _};
+        (&hot_ice::macro_use::Verified::<Self>::default()).handoff_hash()
+    }};
+    let adopt = quote_spanned! {span=> state.adopt_state::<Self>() };
+    let field_transfer = generate_field_transfer(&ast, rebuild.as_ref());
 
     quote!(
         #ast
 
         impl #struct_name {
 
+            #[doc(hidden)]
+            #[allow(non_snake_case, clippy::all)]
             #[unsafe(no_mangle)]
             pub fn #serialize_state_ident(
                 state: &hot_ice::macro_use::HotState,
                 out_ptr: *mut *mut ::core::primitive::u8,
                 out_len: *mut ::core::primitive::usize,
             ) -> ::core::result::Result<(), hot_ice::macro_use::HotIceError> {
-                let data = state.serialize_state::<Self>()?;
+                let data = #serialize?;
 
                 let len = data.len();
                 let mut boxed_slice = data.into_boxed_slice();
@@ -136,17 +339,28 @@ fn generate_hot_state(item: proc_macro::TokenStream) -> proc_macro::TokenStream
                 ::core::result::Result::Ok(())
             }
 
+            #[doc(hidden)]
+            #[allow(non_snake_case, clippy::all)]
             #[unsafe(no_mangle)]
             pub fn #deserialize_state_ident(
                 state: &mut hot_ice::macro_use::HotState,
                 data_ptr: *const ::core::primitive::u8,
                 data_len: ::core::primitive::usize,
             ) -> ::core::result::Result<(), hot_ice::macro_use::HotIceError> {
-                let data = unsafe { ::core::slice::from_raw_parts(data_ptr, data_len) };
-                state.deserialize_state::<Self>(data)
+                // Nothing was serialized if the old library failed to.
+                let data: &[::core::primitive::u8] = if data_ptr.is_null() {
+                    &[]
+                } else {
+                    unsafe { ::core::slice::from_raw_parts(data_ptr, data_len) }
+                };
+                let result = #deserialize;
+                #rebuild
+                result
             }
 
             /// Free memory allocated by serialize_state
+            #[doc(hidden)]
+            #[allow(non_snake_case, clippy::all)]
             #[unsafe(no_mangle)]
             pub fn #free_serialized_data_ident(ptr: *mut ::core::primitive::u8, len: ::core::primitive::usize) {
                 if !ptr.is_null() && len > 0 {
@@ -156,7 +370,249 @@ fn generate_hot_state(item: proc_macro::TokenStream) -> proc_macro::TokenStream
                     }
                 }
             }
+
+            /// Identifies the state type, see `HotState::adopt_state`
+            #[doc(hidden)]
+            #[allow(non_snake_case, clippy::all)]
+            #[unsafe(no_mangle)]
+            pub fn #state_type_hash_ident() -> ::core::primitive::u64 {
+                #type_hash
+            }
+
+            /// Takes over the state of the previous library without serializing it
+            #[doc(hidden)]
+            #[allow(non_snake_case, clippy::all)]
+            #[unsafe(no_mangle)]
+            pub fn #adopt_state_ident(
+                state: &mut hot_ice::macro_use::HotState,
+            ) -> ::core::result::Result<(), hot_ice::macro_use::HotIceError> {
+                #adopt;
+                let result = ::core::result::Result::Ok(());
+                #rebuild
+                result
+            }
+
+            #field_transfer
         }
     )
     .into()
 }
+
+/// Exports the fields of a struct with named fields, so the reloader can move
+/// the fields whose type did not change to the next library and only
+/// serialize the others. Nothing for enums and other structs, their state is
+/// always transferred as a whole.
+fn generate_field_transfer(
+    ast: &syn::DeriveInput,
+    rebuild: Option<&proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(fields),
+        ..
+    }) = &ast.data
+    else {
+        return quote!();
+    };
+    if fields.named.is_empty() || !ast.generics.params.is_empty() {
+        return quote!();
+    }
+
+    let state_fields_ident =
+        proc_macro2::Ident::new(STATE_FIELDS_FUNCTION_NAME, proc_macro2::Span::call_site());
+    let serialize_fields_ident = proc_macro2::Ident::new(
+        SERIALIZE_STATE_FIELDS_FUNCTION_NAME,
+        proc_macro2::Span::call_site(),
+    );
+    let restore_fields_ident = proc_macro2::Ident::new(
+        RESTORE_STATE_FIELDS_FUNCTION_NAME,
+        proc_macro2::Span::call_site(),
+    );
+    let drop_fields_ident = proc_macro2::Ident::new(
+        DROP_STATE_FIELDS_FUNCTION_NAME,
+        proc_macro2::Span::call_site(),
+    );
+
+    let mut entries = Vec::new();
+    let mut serialize = Vec::new();
+    let mut restore = Vec::new();
+    let mut drop = Vec::new();
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        let name = ident.to_string();
+        let name_hash = fnv1a(name.as_bytes());
+        // `0` unless the field's type derives `TypeHash`, such a field is
+        // never moved.
+        let type_hash = quote! {{
+            use hot_ice::macro_use::{Unverified as _, ViaTypeHash as _};
+            (&hot_ice::macro_use::Verified::<#ty>::default()).handoff_hash()
+        }};
+        // Skipped fields need not implement serde, they are moved or reset.
+        let skipped = field.attrs.iter().any(|attr| {
+            attr.path().is_ident("serde")
+                && quote::ToTokens::to_token_stream(&attr.meta)
+                    .to_string()
+                    .contains("skip")
+        });
+
+        entries.push(quote! {
+            hot_ice::macro_use::StateField {
+                name_hash: #name_hash,
+                type_hash: #type_hash,
+                offset: ::core::mem::offset_of!(Self, #ident),
+            }
+        });
+        let moved = quote! {
+            ::core::option::Option::Some(field)
+                if field.type_hash != 0 && field.type_hash == #type_hash =>
+            {
+                new.#ident =
+                    unsafe { ::core::ptr::read(old.add(field.offset).cast::<#ty>()) };
+            }
+        };
+        if skipped {
+            restore.push(quote! {
+                match hot_ice::macro_use::find_field(previous, #name_hash) {
+                    #moved
+                    _ => {}
+                }
+            });
+        } else {
+            serialize.push(quote! {
+                if names.contains(&#name_hash) {
+                    fields.insert(#name, &state.#ident)?;
+                }
+            });
+            restore.push(quote! {
+                match hot_ice::macro_use::find_field(previous, #name_hash) {
+                    #moved
+                    _ => {
+                        if let ::core::option::Option::Some(value) = fields.take(#name) {
+                            new.#ident = value;
+                        }
+                    }
+                }
+            });
+        }
+        drop.push(quote! {
+            if !moved.contains(&#name_hash) {
+                unsafe {
+                    ::core::ptr::drop_in_place(::core::ptr::addr_of_mut!((*previous).#ident));
+                }
+            }
+        });
+    }
+
+    quote! {
+        /// Lists the fields of the state, see `StateField`
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn #state_fields_ident(
+            out_len: *mut ::core::primitive::usize,
+        ) -> *const hot_ice::macro_use::StateField {
+            static FIELDS: ::std::sync::OnceLock<
+                ::std::vec::Vec<hot_ice::macro_use::StateField>,
+            > = ::std::sync::OnceLock::new();
+            let fields = FIELDS.get_or_init(|| ::std::vec![#( #entries ),*]);
+            unsafe {
+                *out_len = fields.len();
+            }
+            fields.as_ptr()
+        }
+
+        /// Serializes the fields named by `names`, the ones whose type changed
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn #serialize_fields_ident(
+            state: &hot_ice::macro_use::HotState,
+            names_ptr: *const ::core::primitive::u64,
+            names_len: ::core::primitive::usize,
+            out_ptr: *mut *mut ::core::primitive::u8,
+            out_len: *mut ::core::primitive::usize,
+        ) -> ::core::result::Result<(), hot_ice::macro_use::HotIceError> {
+            let names = unsafe { ::core::slice::from_raw_parts(names_ptr, names_len) };
+            let state = state.ref_state::<Self>();
+            let mut fields = hot_ice::macro_use::FieldMap::default();
+            #( #serialize )*
+            let data = fields.encode()?;
+
+            let len = data.len();
+            let mut boxed_slice = data.into_boxed_slice();
+            let ptr = boxed_slice.as_mut_ptr();
+            ::core::mem::forget(boxed_slice);
+
+            unsafe {
+                *out_ptr = ptr;
+                *out_len = len;
+            }
+
+            ::core::result::Result::Ok(())
+        }
+
+        /// Builds the state from the fields of the previous library's state,
+        /// the previous state is handed back through `previous_out`
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn #restore_fields_ident(
+            state: &mut hot_ice::macro_use::HotState,
+            previous_ptr: *const hot_ice::macro_use::StateField,
+            previous_len: ::core::primitive::usize,
+            data_ptr: *const ::core::primitive::u8,
+            data_len: ::core::primitive::usize,
+            previous_out: *mut *mut ::core::primitive::u8,
+        ) -> ::core::result::Result<(), hot_ice::macro_use::HotIceError> {
+            let previous = unsafe { ::core::slice::from_raw_parts(previous_ptr, previous_len) };
+            let data: &[::core::primitive::u8] = if data_ptr.is_null() {
+                &[]
+            } else {
+                unsafe { ::core::slice::from_raw_parts(data_ptr, data_len) }
+            };
+            let (old, result) = state.restore_fields::<Self>(data, |new, old, fields| {
+                let _ = &fields;
+                #( #restore )*
+            });
+            unsafe {
+                *previous_out = old;
+            }
+            #rebuild
+            result
+        }
+
+        /// Drops the fields of this library's state the next library did not
+        /// move out, named by `moved`, and frees the state
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn #drop_fields_ident(
+            previous: *mut ::core::primitive::u8,
+            moved_ptr: *const ::core::primitive::u64,
+            moved_len: ::core::primitive::usize,
+        ) {
+            let moved = unsafe { ::core::slice::from_raw_parts(moved_ptr, moved_len) };
+            let previous = previous.cast::<Self>();
+            // A panicking `Drop` leaks the rest instead of unwinding into
+            // the host.
+            let _ = hot_ice::macro_use::catch_panic(|| {
+                #( #drop )*
+                if ::core::mem::size_of::<Self>() != 0 {
+                    unsafe {
+                        ::std::alloc::dealloc(
+                            previous.cast(),
+                            ::std::alloc::Layout::new::<Self>(),
+                        );
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// 64-bit FNV-1a hash of `bytes`, stable across compilations.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
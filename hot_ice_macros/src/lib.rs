@@ -2,9 +2,12 @@
 //!
 //! Procedural macros for enabling hot reloading in Iced applications.
 //!
-//! This crate provides two main macros:
+//! This crate provides five main macros:
 //! - [`hot_fn`] - Transforms functions for hot reloading
 //! - [`hot_state`] - Enables state serialization for persistence across reloads
+//! - [`HotMessage`] - Lets messages cross edits of their type
+//! - [`hot_data`] - Exports a data table the binary reads from the library
+//! - [`TypeHash`] - Hashes the full definition of a type
 //!
 //! ## Quick Start
 //!
@@ -59,8 +62,11 @@
 //! }
 //! ```
 
+mod hot_data;
 mod hot_fn;
+mod hot_message;
 mod hot_state;
+mod type_hash;
 
 /// Marks a struct for hot state serialization and persistence.
 ///
@@ -150,6 +156,53 @@ mod hot_state;
 /// Removed fields are silently ignored during deserialization, so you can
 /// safely remove fields without breaking existing sessions.
 ///
+/// # Fields That Cannot Be Serialized
+///
+/// Fields marked `#[hot_state(skip)]` are not serialized and hold their
+/// `Default` after a reload. Pass `rebuild` (or `rebuild = "method"`) to
+/// call `fn rebuild(&mut self)` once the state was deserialized, e.g. to
+/// reopen a connection:
+///
+/// ```rust,ignore
+/// #[hot_ice::hot_state(rebuild)]
+/// #[derive(Default, Serialize, Deserialize)]
+/// pub struct State {
+///     address: String,
+///     #[hot_state(skip)]
+///     socket: Option<TcpStream>,
+/// }
+///
+/// impl State {
+///     fn rebuild(&mut self) {
+///         self.socket = TcpStream::connect(&self.address).ok();
+///     }
+/// }
+/// ```
+///
+/// `rebuild` also runs if the saved state could not be deserialized and the
+/// state was reset to its default, and after the state was handed over
+/// without serializing it. A panic in it is reported like one in a hot
+/// function.
+///
+/// # Enums
+///
+/// The state can be an enum. serde takes `#[serde(default)]` on structs only,
+/// the named fields of the variants get it instead, and `#[hot_state(skip)]`
+/// works on the fields of variants as well. Mark the variant the state is
+/// reset to with `#[hot_state(default)]`, the macro implements `Default` with
+/// it and its fields set to their defaults:
+///
+/// ```rust,ignore
+/// #[hot_ice::hot_state]
+/// #[derive(Serialize, Deserialize)]
+/// pub enum State {
+///     #[hot_state(default)]
+///     Loading,
+///     Ready(App),
+///     Errored(String),
+/// }
+/// ```
+///
 /// # Conditional Compilation
 ///
 /// Use `feature = "..."` to conditionally enable hot state based on a feature flag:
@@ -164,6 +217,19 @@ mod hot_state;
 ///
 /// When the feature is not enabled, the struct is emitted unchanged
 /// (without serialization derives or FFI functions).
+///
+/// Add `strip` to also remove the `#[hot_state(skip)]` field attributes
+/// instead of turning them into `#[serde(skip)]`, so the struct builds
+/// without serde and hot_ice when the feature is off:
+///
+/// ```rust,ignore
+/// #[hot_ice_macros::hot_state(feature = "reload", strip)]
+/// #[cfg_attr(feature = "reload", derive(serde::Serialize, serde::Deserialize))]
+/// pub struct State {
+///     #[hot_state(skip)]
+///     socket: Option<TcpStream>,
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn hot_state(
     attr: proc_macro::TokenStream,
@@ -175,20 +241,44 @@ pub fn hot_state(
 /// Transforms a function for hot reloading support.
 ///
 /// This macro wraps your function to enable dynamic loading and hot reloading.
-/// The function type is automatically detected based on its signature.
+/// The function type is automatically detected based on its signature. A
+/// return type behind an alias, like `type UpdateResult = Task<Message>`, is
+/// recognized by the function's name instead: `boot` or `new`, `update`,
+/// `view`, `subscription`, `setup`, `theme`, `style`, `scale_factor`,
+/// `title` and `on_event`.
 ///
 /// # Supported Functions
 ///
 /// | Function | Signature | Description |
 /// |----------|-----------|-------------|
 /// | `boot` | `() -> (State, Task<Message>)` | Application initialization |
-/// | `update` | `(&mut self, Message) -> Task<Message>` | Message handling |
-/// | `view` | `(&self) -> Element<Message>` | UI rendering |
+/// | `update` | `(&mut self, Message) -> Task<Message>`, also `async` | Message handling |
+/// | `view` | `(&self) -> Element<Message>` or `(&self, window::Id) -> Element<Message>` | UI rendering |
 /// | `subscription` | `(&self) -> Subscription<Message>` | Event subscriptions |
 /// | `theme` | `(&self) -> Option<Theme>` | Theme selection |
 /// | `style` | `(&self, &Theme) -> theme::Style` | Window styling |
-/// | `scale_factor` | `(&self) -> f32` | Display scaling |
-/// | `title` | `(&self) -> String` | Window title |
+/// | `scale_factor` | `(&self) -> f32` or `(&self) -> f64` | Display scaling |
+/// | `title` | `(&self) -> String`, `(&self, window::Id) -> String` or `(&self) -> &'static str` | Window title |
+/// | `setup` | `(&mut self) -> Task<Message>` | Rerun after every reload |
+/// | `on_event` | `(&self, Event, window::Id) -> Option<Message>` | Uncaptured runtime events |
+/// | any other | `(&self, ...) -> T` | Called through a `hot_ice::HotExtension` |
+///
+/// Like in plain iced, `view` may also return a widget such as
+/// `Column<'_, Message>` or `impl Into<Element<'_, Message>>`, the generated
+/// wrapper converts it into the `Element`.
+///
+/// `update` and `view` may take a `&hot_ice::Context` as their last argument
+/// to reach services of the host, e.g.
+/// `(&mut self, Message, &hot_ice::Context) -> Task<Message>`.
+///
+/// # Exports of `update`
+///
+/// Next to a hot `update`, the macro also exports the symbols the host uses
+/// to hand its services to the library, once per cdylib:
+/// `hot_ice::export_executor!`, `hot_ice::export_interner!`,
+/// `hot_ice::export_host_resources!`, `hot_ice::export_host_context!` and
+/// `hot_ice::export_fingerprint!`. A cdylib without a hot `update` invokes
+/// the ones it needs itself.
 ///
 /// # Arguments
 ///
@@ -219,7 +309,8 @@ pub fn hot_state(
 ///
 /// ## `not-hot` / `not_hot`
 ///
-/// Disables hot reloading for `update` and `subscription` functions:
+/// Disables hot reloading for one function of any kind, so it stays pinned
+/// to the version compiled into the binary while the others are reloaded:
 ///
 /// ```rust,ignore
 /// #[hot_ice::hot_fn(not_hot)]
@@ -229,7 +320,66 @@ pub fn hot_state(
 /// ```
 ///
 /// The function still gets the wrapper transformation but without
-/// `#[unsafe(no_mangle)]`, so it won't be exported for dynamic loading.
+/// `#[unsafe(no_mangle)]` and its export companion, so it won't be exported
+/// for dynamic loading. A marker symbol tells the host to call it statically
+/// and report it as static instead of missing.
+///
+/// ## `executor = Type`
+///
+/// On `update`, exports `Type` as the executor the library's worker threads
+/// run in, instead of the default one. It has to be the executor given to
+/// the builder with `.executor::<Type>()`, the host refuses a library
+/// exporting another one and names the fix:
+///
+/// ```rust,ignore
+/// #[hot_ice::hot_fn(executor = MyExecutor)]
+/// pub fn update(&mut self, message: Message) -> Task<Message> {
+///     // Your code here
+/// }
+/// ```
+///
+/// ## `async fn update`
+///
+/// `update` may be async. The wrapper polls the future once inside the
+/// executor of the library's worker threads, one that has to wait becomes a
+/// task on them with `hot_ice::macro_use::spawn_update`:
+///
+/// ```rust,ignore
+/// #[hot_ice::hot_fn]
+/// pub async fn update(&mut self, message: Message) -> Task<Message> {
+///     self.cache.refresh().await;
+///     Task::none()
+/// }
+/// ```
+///
+/// The future borrows the state mutably until it finishes, so the update is
+/// not reentrant: the reloader buffers the program's messages, draws nothing
+/// of the program and holds reloads back in the meantime. Slow work goes into
+/// the returned task. Without the feature plain iced blocks on the async
+/// function, `strip` is rejected.
+///
+/// ## `sub_state = Parent.field`
+///
+/// For methods of a sub-state held in a field of the `#[hot_state]` root
+/// state, e.g. the `counter::State` in `State::counter`. The method is kept
+/// as it is for the parent to call, next to it the macro exports a function
+/// named after the fields and the method that takes the parent's `HotState`,
+/// which can be handed to the builder like a `#[hot_fn(hot_state)]` function
+/// of the root state:
+///
+/// ```rust,ignore
+/// impl counter::State {
+///     // Exported as `counter::State::counter_view`
+///     #[hot_ice::hot_fn(sub_state = crate::State.counter)]
+///     pub fn view(&self) -> Element<'_, counter::Message> {
+///         // Your code here
+///     }
+/// }
+/// ```
+///
+/// The export produces and takes the messages of the sub-state. `update`,
+/// `view`, `subscription`, `theme`, `style`, `scale_factor` and `title` can
+/// be methods of a sub-state.
 ///
 /// ## `feature = "..."`
 ///
@@ -251,6 +401,23 @@ pub fn hot_state(
 /// // Original function unchanged
 /// ```
 ///
+/// ## `strip`
+///
+/// With `feature`, makes sure the function builds without hot_ice when the
+/// feature is off, so `hot_ice` can be an optional dependency enabled by the
+/// feature. Use the attribute from `hot_ice_macros` then, `hot_ice` is not
+/// there to resolve it:
+///
+/// ```rust,ignore
+/// #[hot_ice_macros::hot_fn(feature = "reload", strip)]
+/// pub fn view(&self) -> Element<'_, Message> {
+///     // Plain iced without the "reload" feature
+/// }
+/// ```
+///
+/// Functions taking a `&hot_ice::Context` are rejected, they need hot_ice
+/// without the feature as well.
+///
 /// ## Combining Arguments
 ///
 /// Arguments can be combined:
@@ -357,3 +524,90 @@ pub fn hot_fn(
 ) -> proc_macro::TokenStream {
     crate::hot_fn::hot_fn(attr, item)
 }
+
+/// Exports a `static` or `const` item, so the binary reads the item of the
+/// loaded library with `hot_ice::hot_data`.
+///
+/// Meant for tables that are tweaked more often than the code using them,
+/// e.g. the definitions of the levels of a game. The item keeps its type,
+/// code of the ui crate uses it as before. The type implements
+/// `hot_ice::TypeHash`, the binary only reads an item whose type has the
+/// same definition as its own.
+///
+/// ```rust,ignore
+/// #[derive(hot_ice::TypeHash)]
+/// pub struct LevelDef { /* ... */ }
+///
+/// #[hot_ice::hot_data(feature = "reload")]
+/// pub static LEVELS: &[LevelDef] = &[LevelDef::new("meadow", 3), LevelDef::new("cave", 5)];
+///
+/// // In the binary
+/// let levels = hot_ice::hot_data::<&[LevelDef]>("LEVELS");
+/// ```
+///
+/// With `feature = "..."` the getter is only exported with the feature.
+#[proc_macro_attribute]
+pub fn hot_data(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    crate::hot_data::hot_data(attr, item)
+}
+
+/// Lets the messages of an enum cross an edit of the enum.
+///
+/// A task or subscription started before a reload can deliver its message to
+/// the `update` of the next library. If the message enum was edited in
+/// between, the message is serialized by the library that produced it and
+/// deserialized into the edited enum, instead of being read with the wrong
+/// layout. The derive implements `serde::Serialize`, `serde::Deserialize`
+/// and `hot_ice::TransferMessage` for the enum, which must be `Clone` and
+/// `Debug` as every message.
+///
+/// Variants whose payload cannot be serialized are marked
+/// `#[hot_message(skip)]`. Their messages fail to serialize with
+/// `hot_ice::NotTransferable`, and are dropped with a warning when they
+/// arrive after an edit.
+///
+/// ```rust,ignore
+/// #[derive(Debug, Clone, hot_ice::HotMessage)]
+/// pub enum Message {
+///     Increment,
+///     Rename { name: String },
+///     #[hot_message(skip)]
+///     Loaded(Arc<image::Handle>),
+/// }
+/// ```
+#[proc_macro_derive(HotMessage, attributes(hot_message))]
+pub fn hot_message(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    crate::hot_message::derive_hot_message(item)
+}
+
+/// Implements `hot_ice::TypeHash`, a hash of the full definition of a type.
+///
+/// The hash combines the tokens of the definition, the size and alignment of
+/// the type and the `TypeHash` of every field, so it changes with every edit
+/// of a type nested in it as well. Every field's type has to implement
+/// `TypeHash`: derive it for the nested types of the crate, it is implemented
+/// for the common types of `std`.
+///
+/// Derived on a `#[hot_state]` struct it lets the state be handed to the next
+/// library without serializing it, see `ReloaderSettings::state_handoff`.
+///
+/// ```rust,ignore
+/// #[hot_ice::hot_state]
+/// #[derive(Debug, Clone, hot_ice::TypeHash)]
+/// pub struct State {
+///     document: Document,
+///     zoom: f32,
+/// }
+///
+/// #[derive(Debug, Clone, Default, Serialize, Deserialize, hot_ice::TypeHash)]
+/// pub struct Document {
+///     lines: Vec<String>,
+/// }
+/// ```
+#[proc_macro_derive(TypeHash)]
+pub fn type_hash(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    crate::type_hash::derive_type_hash(item)
+}
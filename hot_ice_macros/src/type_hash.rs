@@ -0,0 +1,56 @@
+use quote::quote;
+use syn::parse_macro_input;
+
+use crate::hot_state::fnv1a;
+
+pub fn derive_type_hash(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(item as syn::DeriveInput);
+    match expand(&ast) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let types: Vec<&syn::Type> = match &ast.data {
+        syn::Data::Struct(data) => data.fields.iter().map(|field| &field.ty).collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter()
+            .flat_map(|variant| variant.fields.iter().map(|field| &field.ty))
+            .collect(),
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "`TypeHash` cannot be derived for a union, which field is set is not known",
+            ));
+        }
+    };
+
+    // Changes with every edit of the definition, the fields' hashes with
+    // every edit of the types nested in it.
+    let definition_hash = fnv1a(quote!(#ast).to_string().as_bytes());
+
+    let name = &ast.ident;
+    let mut generics = ast.generics.clone();
+    let where_clause = generics.make_where_clause();
+    for ty in &types {
+        where_clause
+            .predicates
+            .push(syn::parse_quote!(#ty: hot_ice::TypeHash));
+    }
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        unsafe impl #impl_generics hot_ice::TypeHash for #name #type_generics #where_clause {
+            const TYPE_HASH: ::core::primitive::u64 = hot_ice::macro_use::combine_hashes(&[
+                #definition_hash,
+                ::core::mem::size_of::<Self>() as ::core::primitive::u64,
+                ::core::mem::align_of::<Self>() as ::core::primitive::u64,
+                #( <#types as hot_ice::TypeHash>::TYPE_HASH, )*
+            ]);
+            const BORROWS_LIBRARY: ::core::primitive::bool =
+                false #( || <#types as hot_ice::TypeHash>::BORROWS_LIBRARY )*;
+        }
+    })
+}
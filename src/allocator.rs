@@ -0,0 +1,251 @@
+//! Routing of cdylib heap allocations to the host binary's allocator.
+//!
+//! Heap data created in the cdylib regularly ends up owned by the host (task
+//! outputs, elements, strings) and is freed there, possibly after the library
+//! was unloaded. That is only sound if both sides use the same allocator.
+//! [`use_host_allocator!`] installs a `#[global_allocator]` in the cdylib that
+//! forwards every call to the allocator of the host, which hands over its
+//! [`AllocatorVTable`] as soon as the library is opened, before it calls
+//! any of its functions.
+//!
+//! The dynamic loader runs the library's constructors before that, and blocks
+//! they allocate must never reach the host's `dealloc`. They are carved out of
+//! a static arena of the [`HostAllocator`] instead: whether a block belongs to
+//! the host is decided by its address alone, so no block changes owner when
+//! the vtable is installed.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::UnsafeCell;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// Size of the arena serving the allocations made before the host's vtable
+/// is installed.
+const BOOTSTRAP_ARENA_BYTES: usize = 64 * 1024;
+
+/// Allocation functions of the host binary, handed to the cdylib over FFI.
+pub struct AllocatorVTable {
+    pub alloc: unsafe fn(Layout) -> *mut u8,
+    pub alloc_zeroed: unsafe fn(Layout) -> *mut u8,
+    pub dealloc: unsafe fn(*mut u8, Layout),
+    pub realloc: unsafe fn(*mut u8, Layout, usize) -> *mut u8,
+}
+
+static HOST_VTABLE: AllocatorVTable = AllocatorVTable {
+    alloc: std::alloc::alloc,
+    alloc_zeroed: std::alloc::alloc_zeroed,
+    dealloc: std::alloc::dealloc,
+    realloc: std::alloc::realloc,
+};
+
+/// Returns the vtable of this binary's global allocator.
+pub(crate) fn host_vtable() -> &'static AllocatorVTable {
+    &HOST_VTABLE
+}
+
+/// Global allocator of the cdylib that forwards to the host's allocator.
+///
+/// The host installs its vtable right after opening the library, before
+/// calling anything in it. Only code run by the dynamic loader itself, like
+/// the constructors of C dependencies, comes earlier; its allocations are
+/// served from a static arena of 64 KiB that is never handed to the host and
+/// never reused. Allocating more than that before the vtable is installed
+/// fails.
+pub struct HostAllocator {
+    vtable: AtomicPtr<AllocatorVTable>,
+    arena: UnsafeCell<[u8; BOOTSTRAP_ARENA_BYTES]>,
+    arena_used: AtomicUsize,
+}
+
+// Safety: the arena is only written through the disjoint blocks handed out by
+// `alloc_in_arena`, which reserves them atomically.
+unsafe impl Sync for HostAllocator {}
+
+impl HostAllocator {
+    pub const fn new() -> Self {
+        Self {
+            vtable: AtomicPtr::new(ptr::null_mut()),
+            arena: UnsafeCell::new([0; BOOTSTRAP_ARENA_BYTES]),
+            arena_used: AtomicUsize::new(0),
+        }
+    }
+
+    /// Installs the host's vtable. Only the first call has an effect.
+    pub fn set(&self, vtable: &'static AllocatorVTable) {
+        let _ = self.vtable.compare_exchange(
+            ptr::null_mut(),
+            ptr::from_ref(vtable).cast_mut(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+    }
+
+    fn vtable(&self) -> Option<&'static AllocatorVTable> {
+        // Safety: the pointer is either null or was created from a
+        // `&'static AllocatorVTable` in `set`.
+        unsafe { self.vtable.load(Ordering::Acquire).as_ref() }
+    }
+
+    /// Reserves a block of the arena, null once it is exhausted. The arena is
+    /// zeroed static memory and no block is handed out twice, so the blocks
+    /// are zeroed too.
+    fn alloc_in_arena(&self, layout: Layout) -> *mut u8 {
+        let base = self.arena.get().cast::<u8>();
+        let mut used = self.arena_used.load(Ordering::Relaxed);
+        loop {
+            let start = used + base.wrapping_add(used).align_offset(layout.align());
+            let Some(end) = start
+                .checked_add(layout.size())
+                .filter(|end| *end <= BOOTSTRAP_ARENA_BYTES)
+            else {
+                return ptr::null_mut();
+            };
+            match self.arena_used.compare_exchange_weak(
+                used,
+                end,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return base.wrapping_add(start),
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    fn in_arena(&self, ptr: *mut u8) -> bool {
+        let start = self.arena.get() as usize;
+        (start..start + BOOTSTRAP_ARENA_BYTES).contains(&(ptr as usize))
+    }
+}
+
+impl Default for HostAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl GlobalAlloc for HostAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.vtable() {
+            Some(vtable) => unsafe { (vtable.alloc)(layout) },
+            None => self.alloc_in_arena(layout),
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        match self.vtable() {
+            Some(vtable) => unsafe { (vtable.alloc_zeroed)(layout) },
+            None => self.alloc_in_arena(layout),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Blocks of the arena are not reused. Every other block was allocated
+        // by the host, after its vtable was installed.
+        if let Some(vtable) = self.vtable()
+            && !self.in_arena(ptr)
+        {
+            unsafe { (vtable.dealloc)(ptr, layout) }
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if let Some(vtable) = self.vtable()
+            && !self.in_arena(ptr)
+        {
+            return unsafe { (vtable.realloc)(ptr, layout, new_size) };
+        }
+
+        // Moves the block out of the arena, or within it before the vtable
+        // is installed.
+        let new_layout = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
+        let new_ptr = unsafe { self.alloc(new_layout) };
+        if !new_ptr.is_null() {
+            unsafe { ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size)) };
+        }
+        new_ptr
+    }
+}
+
+/// Makes the cdylib allocate through the host binary's global allocator.
+///
+/// Invoke once at the root of the cdylib crate. Required when the host
+/// installs its own `#[global_allocator]` (e.g. mimalloc), recommended
+/// whenever heap data created in the library outlives a reload.
+///
+/// ```rust,ignore
+/// #[cfg(feature = "reload")]
+/// hot_ice::use_host_allocator!();
+/// ```
+#[macro_export]
+macro_rules! use_host_allocator {
+    () => {
+        #[global_allocator]
+        static HOT_ICE_HOST_ALLOCATOR: $crate::macro_use::HostAllocator =
+            $crate::macro_use::HostAllocator::new();
+
+        /// Receives the host's allocator vtable, see `hot_ice::use_host_allocator!`.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn set_host_allocator_lskdjfa3lkfjasdf(
+            vtable: &'static $crate::macro_use::AllocatorVTable,
+        ) {
+            HOT_ICE_HOST_ALLOCATOR.set(vtable);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwards_to_installed_vtable() {
+        static ALLOCATOR: HostAllocator = HostAllocator::new();
+        let layout = Layout::from_size_align(64, 16).unwrap();
+
+        unsafe {
+            let early = ALLOCATOR.alloc_zeroed(layout);
+            assert!(ALLOCATOR.in_arena(early));
+            assert_eq!(early as usize % 16, 0);
+            early.write_bytes(7, 64);
+
+            ALLOCATOR.set(host_vtable());
+            let zeroed = ALLOCATOR.alloc_zeroed(layout);
+            assert!(!ALLOCATOR.in_arena(zeroed));
+            assert!(
+                std::slice::from_raw_parts(zeroed, 64)
+                    .iter()
+                    .all(|b| *b == 0)
+            );
+            let grown = ALLOCATOR.realloc(zeroed, layout, 128);
+            assert!(!grown.is_null());
+            ALLOCATOR.dealloc(grown, Layout::from_size_align(128, 16).unwrap());
+
+            // A block from before the install is copied out of the arena and
+            // never handed to the host's `dealloc`.
+            let moved = ALLOCATOR.realloc(early, layout, 128);
+            assert!(!ALLOCATOR.in_arena(moved));
+            assert!(
+                std::slice::from_raw_parts(moved, 64)
+                    .iter()
+                    .all(|b| *b == 7)
+            );
+            ALLOCATOR.dealloc(early, layout);
+            ALLOCATOR.dealloc(moved, Layout::from_size_align(128, 16).unwrap());
+        }
+    }
+
+    #[test]
+    fn arena_is_bounded() {
+        static ALLOCATOR: HostAllocator = HostAllocator::new();
+        let layout = Layout::from_size_align(BOOTSTRAP_ARENA_BYTES / 2, 8).unwrap();
+
+        unsafe {
+            assert!(!ALLOCATOR.alloc(layout).is_null());
+            assert!(!ALLOCATOR.alloc(layout).is_null());
+            assert!(ALLOCATOR.alloc(layout).is_null());
+        }
+    }
+}
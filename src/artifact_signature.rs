@@ -0,0 +1,219 @@
+//! Signed libraries, for transports that receive them from another machine.
+//!
+//! A library swapped in by the reloader is native code running in the
+//! program, so a transport receiving builds over the network lets anyone who
+//! can reach it run code on the device. The machine building the library
+//! signs it with an [`ArtifactSigner`], which writes the ed25519 signature
+//! next to it as `<lib>.<ext>.sig`, and the transport sends both. On the
+//! device the transport is wrapped in [`Signed`], and a library is only
+//! loaded if its signature is valid for one of the trusted keys. The reloader
+//! checks the copy it is about to load, right before loading it, on startup,
+//! on every reload and when an older generation is activated again:
+//!
+//! ```rust,ignore
+//! const DEV_MACHINE: [u8; 32] = *include_bytes!("../dev_machine.pub");
+//!
+//! ReloaderSettings {
+//!     transport: Some(Arc::new(Signed::new(receiver, [DEV_MACHINE])?)),
+//!     ..Default::default()
+//! }
+//! ```
+//!
+//! The signature only covers the library, the channel is not encrypted.
+//! There is no optional TLS feature: hot_ice ships no network transport of
+//! its own, the bytes travel through the [`ReloadTransport`] of the program,
+//! so a transport that has to hide the library on the wire brings its own
+//! TLS.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+use crate::lib_reloader::lib_prefix_and_ext;
+use crate::transport::{ReloadSignal, ReloadTransport};
+
+/// The file holding the signature of `library`, `<lib>.<ext>.sig`.
+pub fn signature_path(library: &Path) -> PathBuf {
+    let (_, ext) = lib_prefix_and_ext();
+    library.with_extension(format!("{ext}.sig"))
+}
+
+/// Signs libraries on the machine building them.
+pub struct ArtifactSigner {
+    key: SigningKey,
+}
+
+impl ArtifactSigner {
+    /// A signer with the 32 bytes of an ed25519 secret key.
+    pub fn from_bytes(secret_key: &[u8; 32]) -> Self {
+        Self {
+            key: SigningKey::from_bytes(secret_key),
+        }
+    }
+
+    /// A signer with the secret key stored in the 32 byte file at `path`.
+    pub fn from_key_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let secret_key = bytes.as_slice().try_into().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("an ed25519 secret key has 32 bytes, not {}", bytes.len()),
+            )
+        })?;
+        Ok(Self::from_bytes(&secret_key))
+    }
+
+    /// The public key the device has to trust, see [`Signed::new`].
+    pub fn public_key(&self) -> [u8; 32] {
+        self.key.verifying_key().to_bytes()
+    }
+
+    /// Signs the library at `library` and writes the signature next to it.
+    /// Returns the path of the signature.
+    pub fn sign(&self, library: &Path) -> io::Result<PathBuf> {
+        let signature = self.key.sign(&fs::read(library)?);
+        let path = signature_path(library);
+        fs::write(&path, signature.to_bytes())?;
+        Ok(path)
+    }
+}
+
+impl fmt::Debug for ArtifactSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArtifactSigner")
+            .field("public_key", &self.key.verifying_key())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Why a library was refused.
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    #[error("{0} is not an ed25519 public key")]
+    InvalidKey(String),
+    #[error("The library or its signature could not be read: {0}")]
+    Unreadable(#[from] io::Error),
+    #[error("The signature file is malformed")]
+    Malformed,
+    #[error("The library is not signed by a trusted key")]
+    Untrusted,
+}
+
+/// Checks the signatures of libraries on the device.
+#[derive(Debug, Clone)]
+pub struct ArtifactVerifier {
+    trusted: Vec<VerifyingKey>,
+}
+
+impl ArtifactVerifier {
+    /// A verifier accepting the signatures of the public keys `trusted`.
+    pub fn new(trusted: impl IntoIterator<Item = [u8; 32]>) -> Result<Self, SignatureError> {
+        let trusted = trusted
+            .into_iter()
+            .map(|key| {
+                VerifyingKey::from_bytes(&key).map_err(|_| SignatureError::InvalidKey(hex(&key)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { trusted })
+    }
+
+    /// Checks that the signature next to `library` was made by a trusted key
+    /// for its current content.
+    pub fn verify(&self, library: &Path) -> Result<(), SignatureError> {
+        let content = fs::read(library)?;
+        let signature = fs::read(signature_path(library))?;
+        self.verify_bytes(&content, &signature)
+    }
+
+    /// Checks that `signature` was made by a trusted key for `library`, the
+    /// content of a library file.
+    pub fn verify_bytes(&self, library: &[u8], signature: &[u8]) -> Result<(), SignatureError> {
+        let signature = Signature::from_slice(signature).map_err(|_| SignatureError::Malformed)?;
+
+        self.trusted
+            .iter()
+            .any(|key| key.verify_strict(library, &signature).is_ok())
+            .then_some(())
+            .ok_or(SignatureError::Untrusted)
+    }
+}
+
+/// Wraps a transport so that only libraries signed by a trusted key are
+/// loaded. An unsigned or tampered library is logged and the loaded one stays
+/// in place.
+#[derive(Debug)]
+pub struct Signed<T> {
+    transport: T,
+    verifier: Arc<ArtifactVerifier>,
+}
+
+impl<T: ReloadTransport> Signed<T> {
+    /// Accepts the libraries `transport` announces if they are signed by one
+    /// of the public keys `trusted`.
+    pub fn new(
+        transport: T,
+        trusted: impl IntoIterator<Item = [u8; 32]>,
+    ) -> Result<Self, SignatureError> {
+        Ok(Self {
+            transport,
+            verifier: Arc::new(ArtifactVerifier::new(trusted)?),
+        })
+    }
+}
+
+impl<T: ReloadTransport> ReloadTransport for Signed<T> {
+    fn start(&self, library: &Path, signal: ReloadSignal) {
+        self.transport.start(library, signal);
+    }
+
+    fn verifier(&self) -> Option<Arc<ArtifactVerifier>> {
+        Some(Arc::clone(&self.verifier))
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib_reloader::fake_artifact;
+
+    #[test]
+    fn only_libraries_signed_by_trusted_keys_pass() {
+        let library = std::env::temp_dir()
+            .join(format!("hot_ice_signed_{}", std::process::id()))
+            .with_extension(lib_prefix_and_ext().1);
+        fs::write(&library, fake_artifact(b"build 1")).unwrap();
+
+        let signer = ArtifactSigner::from_bytes(&[7; 32]);
+        let verifier = ArtifactVerifier::new([signer.public_key()]).unwrap();
+        assert!(matches!(
+            verifier.verify(&library),
+            Err(SignatureError::Unreadable(_))
+        ));
+
+        let signature = signer.sign(&library).unwrap();
+        assert!(verifier.verify(&library).is_ok());
+
+        let stranger = ArtifactVerifier::new([ArtifactSigner::from_bytes(&[8; 32]).public_key()]);
+        assert!(matches!(
+            stranger.unwrap().verify(&library),
+            Err(SignatureError::Untrusted)
+        ));
+
+        fs::write(&library, fake_artifact(b"build 2")).unwrap();
+        assert!(matches!(
+            verifier.verify(&library),
+            Err(SignatureError::Untrusted)
+        ));
+
+        let _ = fs::remove_file(&library);
+        let _ = fs::remove_file(&signature);
+    }
+}
@@ -0,0 +1,195 @@
+//! Yardsticks for the overhead of the hot dispatch path.
+//!
+//! Every helper runs one call either through the library or the way a
+//! static program would, chosen with [`Path`], so the two can be measured
+//! side by side. They fit the closures of `criterion`'s `Bencher::iter`:
+//!
+//! ```rust,ignore
+//! let app = hot_ice::application(State::new, State::update, State::view);
+//! let mut dispatch = Dispatch::new(app, "target/bench/debug");
+//!
+//! let mut group = c.benchmark_group("update");
+//! for path in Path::ALL {
+//!     group.bench_function(path.to_string(), |b| {
+//!         b.iter(|| dispatch.update(path, Message::Increment.into_hot_message()))
+//!     });
+//! }
+//! ```
+//!
+//! `benches/dispatch.rs` measures the library of `tests/fixture` with them.
+
+use std::fmt;
+use std::hint::black_box;
+
+use iced_core::window;
+
+use crate::hot_application::HotIce;
+use crate::hot_program::HotProgram;
+use crate::hot_state::{DynState, HotState};
+use crate::message::{DynMessage, HotMessage, MessageSource};
+use crate::reloader::{FunctionState, Reloader};
+
+/// Which way a call is dispatched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Path {
+    /// Through the loaded library, the way messages of the hot view are.
+    Hot,
+    /// To the function linked into the binary.
+    Static,
+}
+
+impl Path {
+    pub const ALL: [Path; 2] = [Path::Hot, Path::Static];
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Path::Hot => write!(f, "hot"),
+            Path::Static => write!(f, "static"),
+        }
+    }
+}
+
+/// Calls `update` and `view` of an application, with its library loaded but
+/// without the reloader's own handling of messages around them.
+pub struct Dispatch<P: HotProgram + 'static> {
+    reloader: Reloader<P>,
+    program: P,
+    window: window::Id,
+}
+
+impl<P> Dispatch<P>
+where
+    P: HotProgram + 'static,
+    P::Message: Clone,
+{
+    /// Boots `app` and loads its library from `lib_dir`, which has to be
+    /// built already. Panics if it can't be loaded, the hot path would be
+    /// measured on the static functions otherwise.
+    pub fn new(app: HotIce<P>, lib_dir: impl Into<String>) -> Self {
+        let (program, mut settings, lib_name) = app.into_parts();
+        settings.lib_dir = lib_dir.into();
+        settings.compile_in_reloader = false;
+
+        let (reloader, _listen_for_lib_changes) =
+            Reloader::new(&program, &settings, lib_name, Vec::new());
+        assert!(
+            reloader.is_ready(),
+            "The library of {lib_name} in {} could not be loaded",
+            settings.lib_dir
+        );
+
+        Self {
+            reloader,
+            program,
+            window: window::Id::unique(),
+        }
+    }
+
+    /// Passes `message` to `update`. The returned task is dropped.
+    ///
+    /// Panics if the hot path fell back to the static function.
+    pub fn update(&mut self, path: Path, message: P::Message) {
+        let (state, library) = self.reloader.state_and_library();
+        let message = match path {
+            Path::Hot => MessageSource::Dynamic(message),
+            Path::Static => MessageSource::Static(message),
+        };
+        let (task, fn_state) = self
+            .program
+            .update(state, message, library)
+            .unwrap_or_else(|err| panic!("update(): {err}"));
+        check_path(path, &fn_state, "update");
+        drop(black_box(task));
+    }
+
+    /// Builds the view of the main window. The element is dropped.
+    pub fn view(&self, path: Path) {
+        let library = match path {
+            Path::Hot => self.reloader.library(),
+            Path::Static => None,
+        };
+        let (element, fn_state) = self
+            .program
+            .view(self.reloader.state(), self.window, library)
+            .unwrap_or_else(|err| panic!("view(): {err}"));
+        check_path(path, &fn_state, "view");
+        drop(black_box(element));
+    }
+}
+
+fn check_path(path: Path, fn_state: &FunctionState, function: &str) {
+    if path == Path::Hot {
+        assert!(
+            matches!(fn_state, FunctionState::Hot),
+            "{function} was not called through the library: {fn_state:?}"
+        );
+    }
+}
+
+/// Moves `message` the way it crosses the hot boundary: into a
+/// [`HotMessage`] and out again by its `TypeId`.
+pub fn message<M: DynMessage + Clone>(path: Path, message: &M) -> M {
+    let message = black_box(message.clone());
+    match path {
+        Path::Hot => black_box(HotMessage::from_message(message))
+            .into_message()
+            .unwrap_or_else(|message| panic!("{message:?} was taken out as another type")),
+        Path::Static => message,
+    }
+}
+
+/// A state as the functions of a `#[hot_state]` program borrow it, through
+/// the type-erased [`HotState`], next to a plain copy.
+pub struct StateBorrow<T> {
+    hot: HotState,
+    plain: T,
+}
+
+impl<T: DynState + Clone> StateBorrow<T> {
+    pub fn new(state: T) -> Self {
+        Self {
+            hot: HotState::new(state.clone()),
+            plain: state,
+        }
+    }
+
+    pub fn get(&self, path: Path) -> &T {
+        match path {
+            Path::Hot => black_box(&self.hot).ref_state::<T>(),
+            Path::Static => black_box(&self.plain),
+        }
+    }
+
+    pub fn get_mut(&mut self, path: Path) -> &mut T {
+        match path {
+            Path::Hot => black_box(&mut self.hot).ref_mut_state::<T>(),
+            Path::Static => black_box(&mut self.plain),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+    struct State {
+        values: Vec<u32>,
+    }
+
+    #[test]
+    fn both_paths_see_the_same_values() {
+        let sent = [1_u64; 8];
+        for path in Path::ALL {
+            assert_eq!(message(path, &sent), sent);
+        }
+
+        let mut state = StateBorrow::new(State { values: vec![1, 2] });
+        for path in Path::ALL {
+            state.get_mut(path).values.push(3);
+            assert_eq!(state.get(path).values, [1, 2, 3]);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Blocking work of hot code, run on threads of the host.
+//!
+//! A thread spawned by the library to crunch numbers keeps running code of
+//! the library after a reload, and nothing joins it before the library is
+//! unloaded. [`Context::spawn_blocking`] hands the work to a pool of threads
+//! owned by the host instead. Each job is tagged with the library generation
+//! it was spawned by: jobs of an earlier generation that have not started
+//! yet are dropped when the next library is loaded, and a retired library is
+//! only unloaded once the jobs that were spawned while it ran have finished.
+
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use iced_core::time::Instant;
+use iced_futures::MaybeSend;
+use iced_runtime::Task;
+
+use crate::context::Context;
+use crate::log_target::RELOAD;
+use crate::metrics;
+use crate::panic_hook::catch_panic;
+
+/// Work of the library, boxed by its copy of `hot_ice`. Dropping it without
+/// running it cancels the task waiting for its output.
+pub(crate) type Job = Box<dyn FnOnce() + Send>;
+
+impl Context {
+    /// Runs `f` on a thread of the host and produces its output. The task
+    /// produces nothing if `f` panics or the next library generation was
+    /// loaded before `f` started.
+    ///
+    /// ```rust,ignore
+    /// context.spawn_blocking(move || render_thumbnails(&paths)).map(Message::Rendered)
+    /// ```
+    pub fn spawn_blocking<T>(&self, f: impl FnOnce() -> T + Send + 'static) -> Task<T>
+    where
+        T: MaybeSend + 'static,
+    {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        self.spawn_job(Box::new(move || match catch_panic(f) {
+            Ok(output) => {
+                let _ = sender.send(output);
+            }
+            Err(panic) => Context::current().log(
+                log::Level::Error,
+                &format!("Blocking job panicked: {panic}"),
+            ),
+        }));
+        Task::future(async { receiver.await.ok() }).and_then(Task::done)
+    }
+}
+
+/// Runs `f` on a thread of the host, see [`Context::spawn_blocking`].
+pub fn spawn_blocking<T>(f: impl FnOnce() -> T + Send + 'static) -> Task<T>
+where
+    T: MaybeSend + 'static,
+{
+    Context::current().spawn_blocking(f)
+}
+
+// ---------------------------------------------------------------------------
+// Host side, called through the context's table
+// ---------------------------------------------------------------------------
+
+struct Queued {
+    generation: u64,
+    sequence: u64,
+    job: Job,
+}
+
+#[derive(Default)]
+struct Pool {
+    queued: VecDeque<Queued>,
+    /// Sequence numbers of the jobs running now.
+    running: Vec<u64>,
+    next_sequence: u64,
+    threads: usize,
+    idle: usize,
+}
+
+static POOL: Mutex<Option<Pool>> = Mutex::new(None);
+
+/// Signals a queued job to the threads, and a finished job to
+/// [`wait_for_jobs`].
+static CHANGED: Condvar = Condvar::new();
+
+pub(crate) fn spawn(job: Job) {
+    let Ok(mut pool) = POOL.lock() else {
+        return;
+    };
+    let pool = pool.get_or_insert_with(Pool::default);
+
+    let sequence = pool.next_sequence;
+    pool.next_sequence += 1;
+    pool.queued.push_back(Queued {
+        generation: metrics::reloads(),
+        sequence,
+        job,
+    });
+
+    let max_threads = thread::available_parallelism().map_or(4, usize::from);
+    if pool.queued.len() > pool.idle && pool.threads < max_threads {
+        let spawned = thread::Builder::new()
+            .name("hot-ice-blocking".into())
+            .spawn(run_jobs);
+        match spawned {
+            Ok(_) => pool.threads += 1,
+            Err(err) => log::error!(target: RELOAD, "Failed to spawn a blocking thread: {err}"),
+        }
+    }
+    CHANGED.notify_all();
+}
+
+fn run_jobs() {
+    let Ok(mut guard) = POOL.lock() else {
+        return;
+    };
+    loop {
+        let Some(pool) = guard.as_mut() else {
+            return;
+        };
+        let Some(queued) = pool.queued.pop_front() else {
+            pool.idle += 1;
+            guard = match CHANGED.wait(guard) {
+                Ok(guard) => guard,
+                Err(_) => return,
+            };
+            if let Some(pool) = guard.as_mut() {
+                pool.idle -= 1;
+            }
+            continue;
+        };
+
+        pool.running.push(queued.sequence);
+        drop(guard);
+        // The library catches panics of its jobs, this keeps the thread
+        // alive should one escape anyway.
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(queued.job));
+
+        guard = match POOL.lock() {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+        if let Some(pool) = guard.as_mut() {
+            pool.running.retain(|sequence| *sequence != queued.sequence);
+        }
+        CHANGED.notify_all();
+    }
+}
+
+/// Drops the jobs of generations before `generation` that have not started,
+/// called once a library was loaded, while the previous one still is.
+pub(crate) fn cancel_older(generation: u64) {
+    let cancelled: VecDeque<Queued> = {
+        let Ok(mut pool) = POOL.lock() else {
+            return;
+        };
+        let Some(pool) = pool.as_mut() else {
+            return;
+        };
+        let (cancelled, kept) = pool
+            .queued
+            .drain(..)
+            .partition(|queued| queued.generation < generation);
+        pool.queued = kept;
+        cancelled
+    };
+    if !cancelled.is_empty() {
+        log::info!(
+            target: RELOAD,
+            "Cancelled {} blocking job(s) of an earlier library",
+            cancelled.len()
+        );
+    }
+}
+
+/// Sequence number the next job spawned will get.
+pub(crate) fn next_sequence() -> u64 {
+    POOL.lock()
+        .ok()
+        .and_then(|pool| pool.as_ref().map(|pool| pool.next_sequence))
+        .unwrap_or(0)
+}
+
+/// Waits until no job spawned before `sequence` is queued or running,
+/// returns false if that takes longer than `timeout`.
+pub(crate) fn wait_for_jobs(sequence: u64, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let Ok(mut guard) = POOL.lock() else {
+        return false;
+    };
+    loop {
+        let pending = guard.as_ref().is_some_and(|pool| {
+            pool.running.iter().any(|running| *running < sequence)
+                || pool.queued.iter().any(|queued| queued.sequence < sequence)
+        });
+        if !pending {
+            return true;
+        }
+        let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+            return false;
+        };
+        guard = match CHANGED.wait_timeout(guard, remaining) {
+            Ok((guard, _)) => guard,
+            Err(_) => return false,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on_stream;
+    use iced_runtime::{Action, task};
+
+    #[test]
+    fn runs_jobs_on_host_threads_and_cancels_older_ones() {
+        let task = Context::host().spawn_blocking(|| thread::current().name().map(str::to_string));
+        let mut actions = block_on_stream(task::into_stream(task).unwrap());
+        assert!(matches!(
+            actions.next(),
+            Some(Action::Output(Some(name))) if name == "hot-ice-blocking"
+        ));
+
+        // Keep every thread busy so the next job stays queued.
+        let (release, released) = std::sync::mpsc::channel::<()>();
+        let released = std::sync::Arc::new(Mutex::new(released));
+        let busy = thread::available_parallelism().map_or(4, usize::from);
+        for _ in 0..busy {
+            let released = std::sync::Arc::clone(&released);
+            spawn(Box::new(move || {
+                let _ = released.lock().unwrap().recv();
+            }));
+        }
+        let queued = Context::host().spawn_blocking(|| 1);
+        let sequence = next_sequence();
+
+        cancel_older(metrics::reloads() + 1);
+        let mut actions = block_on_stream(task::into_stream(queued).unwrap());
+        assert!(actions.next().is_none());
+
+        drop(release);
+        assert!(wait_for_jobs(sequence, Duration::from_secs(5)));
+    }
+}
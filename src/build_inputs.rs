@@ -0,0 +1,108 @@
+//! Files the library is built from outside of the watched directory.
+//!
+//! `cargo watch` only watches the directory of the ui crate, but a build may
+//! read more: shaders embedded with `include_str!` from a directory shared by
+//! several crates, or whatever a build script lists with
+//! `cargo:rerun-if-changed`. Cargo writes every input of a build into the
+//! dep-info file next to the library, the ones inside the workspace are
+//! watched as well, together with `ReloaderSettings::extra_watch_paths`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::log_target::RELOAD;
+
+/// The inputs of the last build of `library` that changes should rebuild it
+/// from, but that are not inside `watch_dir`. Only files of the workspaces
+/// in `roots` count, sources of registry crates and build output never
+/// change between builds.
+pub(crate) fn outside_watch_dir(
+    library: &Path,
+    watch_dir: &Path,
+    roots: &[&Path],
+    target_dir: &Path,
+) -> Vec<PathBuf> {
+    let dep_info = library.with_extension("d");
+    let Ok(content) = fs::read_to_string(&dep_info) else {
+        log::debug!(target: RELOAD, "No dep-info at {}", dep_info.display());
+        return Vec::new();
+    };
+
+    let mut inputs: Vec<PathBuf> = parse_dep_info(&content)
+        .into_iter()
+        .filter(|input| {
+            !input.starts_with(watch_dir)
+                && !input.starts_with(target_dir)
+                && roots.iter().any(|root| input.starts_with(root))
+        })
+        .collect();
+    inputs.sort();
+    inputs.dedup();
+    inputs
+}
+
+/// The dependencies of the first rule of a Makefile style dep-info file.
+/// Spaces in paths are escaped with a backslash.
+fn parse_dep_info(content: &str) -> Vec<PathBuf> {
+    let Some(rule) = content.lines().find(|line| !line.trim().is_empty()) else {
+        return Vec::new();
+    };
+    // Windows paths contain colons too, but never one followed by a space.
+    let Some((_, dependencies)) = rule.split_once(": ") else {
+        return Vec::new();
+    };
+
+    let mut inputs = Vec::new();
+    let mut current = String::new();
+    let mut chars = dependencies.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => {
+                current.push(' ');
+                chars.next();
+            }
+            ' ' => {
+                if !current.is_empty() {
+                    inputs.push(PathBuf::from(std::mem::take(&mut current)));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        inputs.push(PathBuf::from(current));
+    }
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_outside_the_crate_are_found() {
+        let dir = std::env::temp_dir().join(format!("hot_ice_inputs_{}", std::process::id()));
+        let library = dir.join("target/hot/libui.so");
+        fs::create_dir_all(library.parent().unwrap()).unwrap();
+        let ws = dir.display();
+        fs::write(
+            library.with_extension("d"),
+            format!(
+                "{ws}/target/hot/libui.so: {ws}/ui/src/lib.rs {ws}/shaders/blur\\ pass.wgsl \
+                 {ws}/target/hot/build/ui-1/out/gen.rs /cargo/registry/iced/src/lib.rs \
+                 {ws}/shaders/blur\\ pass.wgsl\n\n{ws}/ui/src/lib.rs:\n"
+            ),
+        )
+        .unwrap();
+
+        let inputs = outside_watch_dir(
+            &library,
+            &dir.join("ui"),
+            &[dir.as_path()],
+            &dir.join("target"),
+        );
+        assert_eq!(inputs, vec![dir.join("shaders/blur pass.wgsl")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
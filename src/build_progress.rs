@@ -0,0 +1,166 @@
+//! Progress of the library build, shown in the window title and, through
+//! `src/taskbar.rs`, on the taskbar button on Windows and the dock tile on
+//! macOS, see [`ReloaderSettings::taskbar_progress`](crate::ReloaderSettings).
+//!
+//! Cargo prints no total while its output is piped, so the number of crates
+//! the previous build compiled stands in for it.
+
+/// A line of cargo's output that tells how far the build is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildEvent {
+    /// `cargo watch` started a build.
+    Started,
+    /// A crate started compiling.
+    Unit,
+    Finished {
+        success: bool,
+    },
+}
+
+impl BuildEvent {
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_start();
+        if line.starts_with("[Running ") {
+            Some(Self::Started)
+        } else if line.starts_with("Compiling ") {
+            Some(Self::Unit)
+        } else if line.starts_with("Finished ") {
+            Some(Self::Finished { success: true })
+        } else if line.starts_with("error: could not compile") {
+            Some(Self::Finished { success: false })
+        } else {
+            None
+        }
+    }
+}
+
+/// What the taskbar button or dock tile shows of the build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Indicator {
+    /// No build is running and the last one did not fail.
+    Idle,
+    /// Percent of the build done, `None` without a previous build to compare
+    /// with.
+    Building(Option<u8>),
+    /// The last build failed, shown until the next one starts.
+    Failed,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BuildProgress {
+    building: bool,
+    units: usize,
+    /// Crates compiled by the previous build.
+    expected: Option<usize>,
+    failed: bool,
+}
+
+impl BuildProgress {
+    /// Records `event`, returns whether it finished the build.
+    pub(crate) fn record(&mut self, event: BuildEvent) -> bool {
+        match event {
+            BuildEvent::Started => {
+                self.building = true;
+                self.failed = false;
+                self.units = 0;
+                false
+            }
+            BuildEvent::Unit => {
+                // The first crate of a build `cargo watch` did not announce.
+                self.building = true;
+                self.failed = false;
+                self.units += 1;
+                false
+            }
+            BuildEvent::Finished { success } => {
+                if success && self.units > 0 {
+                    self.expected = Some(self.units);
+                }
+                let finished = self.building;
+                self.building = false;
+                self.failed = finished && !success;
+                self.units = 0;
+                finished
+            }
+        }
+    }
+
+    /// The prefix of the window title while a build runs, e.g.
+    /// `[Building 40%]`, or `[Building 3]` without a previous build to
+    /// compare with.
+    pub(crate) fn title_prefix(&self) -> Option<String> {
+        if !self.building {
+            return None;
+        }
+        Some(match self.percent() {
+            Some(percent) => format!("[Building {percent}%]"),
+            None => format!("[Building {}]", self.units),
+        })
+    }
+
+    pub(crate) fn indicator(&self) -> Indicator {
+        if self.building {
+            Indicator::Building(self.percent())
+        } else if self.failed {
+            Indicator::Failed
+        } else {
+            Indicator::Idle
+        }
+    }
+
+    fn percent(&self) -> Option<u8> {
+        // A build may compile more crates than the previous one.
+        self.expected
+            .map(|expected| (self.units * 100 / expected).min(99) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_progress_from_the_previous_build() {
+        fn feed(progress: &mut BuildProgress, line: &str) -> Option<bool> {
+            BuildEvent::parse(line).map(|event| progress.record(event))
+        }
+        let mut progress = BuildProgress::default();
+
+        assert_eq!(
+            feed(&mut progress, "[Running 'cargo build -p ui']"),
+            Some(false)
+        );
+        assert_eq!(
+            feed(&mut progress, "   Compiling serde v1.0.0"),
+            Some(false)
+        );
+        assert_eq!(
+            feed(&mut progress, "   Compiling ui v0.1.0 (/ws/ui)"),
+            Some(false)
+        );
+        assert_eq!(feed(&mut progress, "warning: unused import"), None);
+        assert_eq!(progress.title_prefix().as_deref(), Some("[Building 2]"));
+        assert_eq!(progress.indicator(), Indicator::Building(None));
+        assert_eq!(
+            feed(&mut progress, "    Finished `hot` profile in 3.1s"),
+            Some(true)
+        );
+        assert_eq!(progress.title_prefix(), None);
+        assert_eq!(progress.indicator(), Indicator::Idle);
+
+        feed(&mut progress, "[Running 'cargo build -p ui']");
+        feed(&mut progress, "   Compiling ui v0.1.0 (/ws/ui)");
+        assert_eq!(progress.title_prefix().as_deref(), Some("[Building 50%]"));
+        assert_eq!(progress.indicator(), Indicator::Building(Some(50)));
+        assert_eq!(
+            feed(&mut progress, "error: could not compile `ui`"),
+            Some(true)
+        );
+        assert_eq!(progress.indicator(), Indicator::Failed);
+
+        // A failed build does not change the estimate.
+        feed(&mut progress, "   Compiling ui v0.1.0 (/ws/ui)");
+        assert_eq!(progress.title_prefix().as_deref(), Some("[Building 50%]"));
+        assert_eq!(progress.indicator(), Indicator::Building(Some(50)));
+    }
+}
@@ -5,6 +5,8 @@ use std::io::ErrorKind;
 use std::path::Path;
 use std::process::{Command, Stdio};
 
+use crate::log_target::RELOAD;
+
 static CODESIGN_BIN: &str = "codesign";
 
 pub(crate) struct CodeSigner {
@@ -19,8 +21,9 @@ impl CodeSigner {
             .spawn(), Err(err) if err.kind() == ErrorKind::NotFound);
 
         if !found {
-            eprintln!(
-                "[hot-lib-reloader] The MacOS `{CODESIGN_BIN}` executable cannot be found. See https://github.com/rksm/hot-lib-reloader-rs/issues/15 for more information for why this is needed. To install the XCode command line tools use brew or see https://mac.install.guide/commandlinetools/ for more options"
+            log::warn!(
+                target: RELOAD,
+                "The MacOS `{CODESIGN_BIN}` executable cannot be found. See https://github.com/rksm/hot-lib-reloader-rs/issues/15 for more information for why this is needed. To install the XCode command line tools use brew or see https://mac.install.guide/commandlinetools/ for more options"
             );
         }
 
@@ -29,7 +32,7 @@ impl CodeSigner {
 
     pub(crate) fn codesign(&self, f: impl AsRef<Path>) {
         if !self.found {
-            log::debug!("skipping codesigning");
+            log::debug!(target: RELOAD, "skipping codesigning");
             return;
         }
 
@@ -44,14 +47,14 @@ impl CodeSigner {
             .and_then(|proc| proc.wait_with_output());
         match result {
             Ok(result) => {
-                log::debug!("codesigning success");
+                log::debug!(target: RELOAD, "codesigning success");
                 let stdout = String::from_utf8_lossy(&result.stdout);
                 let stderr = String::from_utf8_lossy(&result.stderr);
-                log::debug!("[codesign stdout] {}", stdout);
-                log::debug!("[codesign stderr] {}", stderr);
+                log::debug!(target: RELOAD, "[codesign stdout] {}", stdout);
+                log::debug!(target: RELOAD, "[codesign stderr] {}", stderr);
             }
             Err(err) => {
-                eprintln!("[hot-lib-reloader] codesigning of {f} failed: {err}");
+                log::error!(target: RELOAD, "codesigning of {f} failed: {err}");
             }
         }
     }
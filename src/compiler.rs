@@ -0,0 +1,442 @@
+//! The build step of the reloader.
+//!
+//! With `compile_in_reloader` the reloader builds the library once at start,
+//! then rebuilds it with `cargo watch` whenever the sources change. What runs
+//! is up to the [`Compiler`] of the settings: [`Cargo`] runs `cargo rustc`
+//! with the hot profile, [`Cranelift`] the same with the Cranelift codegen
+//! backend, and [`External`] builds nothing, for libraries built by another
+//! tool. Implement the trait to try other backends for the dev loop.
+
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use crate::log_target::RELOAD;
+
+/// Cargo profile the library is built with. It inherits from `dev` and is
+/// tuned for short rebuilds by [`hot_profile_env`], what a `[profile.hot]` in
+/// the workspace manifest sets takes precedence.
+pub(crate) const HOT_PROFILE: &str = "hot";
+
+/// What to build, handed to the [`Compiler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CompileRequest {
+    /// Name of the library crate.
+    pub library: String,
+    /// Root of the cargo workspace, the working directory of the build.
+    pub workspace_root: PathBuf,
+    /// `CARGO_TARGET_DIR` of the build.
+    pub target_dir: String,
+    /// Feature enabling the hot functions, see `ReloaderSettings::feature`.
+    pub feature: Option<String>,
+    /// Link with `mold` or `lld`, see `ReloaderSettings::fast_linker`.
+    pub fast_linker: bool,
+    /// Extra `rustc` arguments, e.g. linking the prelinked dependencies.
+    pub rustc_args: Vec<String>,
+}
+
+/// How a build ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileResult {
+    /// The library was written to the library directory.
+    Built,
+    /// The build ran and failed, it is retried after a few seconds.
+    Failed(String),
+    /// The build could not be started, the reloader shows the error.
+    NotRun(String),
+}
+
+/// Builds the library, set with `ReloaderSettings::compiler`.
+pub trait Compiler: Debug + Send + Sync + 'static {
+    /// The command building the library for `request`. `cargo watch` reruns
+    /// it whenever the sources change. `None` if the library is built outside
+    /// the reloader.
+    fn command(&self, request: &CompileRequest) -> Option<Command>;
+
+    /// Builds the library once, on a thread of its own. `progress` gets every
+    /// line of output, shown in the reloader's overlay. Runs
+    /// [`command`](Self::command) by default.
+    fn compile(&self, request: &CompileRequest, progress: &mut dyn FnMut(String)) -> CompileResult {
+        match self.command(request) {
+            Some(command) => run(command, progress),
+            None => CompileResult::Built,
+        }
+    }
+}
+
+/// `cargo rustc` with the hot profile, the compiler used unless another is
+/// set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cargo;
+
+impl Compiler for Cargo {
+    fn command(&self, request: &CompileRequest) -> Option<Command> {
+        Some(cargo_command(request, &[]))
+    }
+}
+
+/// `cargo rustc` with the hot profile and the Cranelift codegen backend,
+/// which generates unoptimized code faster than LLVM.
+///
+/// Cranelift needs a nightly toolchain with the `rustc-codegen-cranelift`
+/// component. The host has to be built by the same toolchain, e.g. pinned in
+/// `rust-toolchain.toml`, or the reloader refuses the library. Every build
+/// checks this first with [`Cranelift::supported`] and is not run otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Cranelift;
+
+impl Cranelift {
+    /// Checks that the toolchain of `workspace_root` can build a library for
+    /// this host with Cranelift: it is a nightly, has the codegen backend
+    /// installed and is the toolchain the host was built with. The error says
+    /// what is missing.
+    pub fn supported(workspace_root: &Path) -> Result<(), String> {
+        let output = Command::new("rustc")
+            .current_dir(workspace_root)
+            .arg("-vV")
+            .output()
+            .map_err(|err| format!("failed to run rustc: {err}"))?;
+        check_toolchain(&String::from_utf8_lossy(&output.stdout))?;
+
+        // Without rustup the component can't be listed, the build reports it.
+        let Ok(components) = Command::new("rustup")
+            .current_dir(workspace_root)
+            .args(["component", "list", "--installed"])
+            .output()
+        else {
+            return Ok(());
+        };
+        let installed = String::from_utf8_lossy(&components.stdout)
+            .lines()
+            .any(|component| component.starts_with("rustc-codegen-cranelift"));
+        if components.status.success() && !installed {
+            return Err(
+                "the rustc-codegen-cranelift component is not installed, add it with \
+                 `rustup component add rustc-codegen-cranelift-preview`"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// [`Cranelift`] if the toolchain of the current directory supports it,
+    /// [`Cargo`] otherwise. For settings shared by a team where not everyone
+    /// has a nightly installed.
+    pub fn or_cargo() -> Arc<dyn Compiler> {
+        match Self::supported(Path::new(".")) {
+            Ok(()) => {
+                log::info!(target: RELOAD, "Building the hot library with Cranelift");
+                Arc::new(Cranelift)
+            }
+            Err(reason) => {
+                log::info!(target: RELOAD, "Not building with Cranelift, {reason}");
+                Arc::new(Cargo)
+            }
+        }
+    }
+}
+
+impl Compiler for Cranelift {
+    fn command(&self, request: &CompileRequest) -> Option<Command> {
+        let mut command = cargo_command(request, &["-Zcodegen-backend"]);
+        command.env("CARGO_PROFILE_HOT_CODEGEN_BACKEND", "cranelift");
+        Some(command)
+    }
+
+    fn compile(&self, request: &CompileRequest, progress: &mut dyn FnMut(String)) -> CompileResult {
+        if let Err(reason) = Self::supported(&request.workspace_root) {
+            return CompileResult::NotRun(format!("Cannot build with Cranelift: {reason}"));
+        }
+        match self.command(request) {
+            Some(command) => run(command, progress),
+            None => CompileResult::Built,
+        }
+    }
+}
+
+/// Checks the output of `rustc -vV` against the toolchain the host was built
+/// with.
+fn check_toolchain(verbose_version: &str) -> Result<(), String> {
+    let version = verbose_version.lines().next().unwrap_or_default().trim();
+    let host = verbose_version
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .unwrap_or_default()
+        .trim();
+
+    if !version.contains("-nightly") {
+        return Err(format!(
+            "Cranelift needs a nightly toolchain, found {version:?}"
+        ));
+    }
+    if version != env!("HOT_ICE_RUSTC_VERSION") {
+        return Err(format!(
+            "the library would be built by {version:?}, the host by {:?}",
+            env!("HOT_ICE_RUSTC_VERSION")
+        ));
+    }
+    if host != env!("HOT_ICE_TARGET") {
+        return Err(format!(
+            "the library would be built for {host}, the host is built for {}",
+            env!("HOT_ICE_TARGET")
+        ));
+    }
+    Ok(())
+}
+
+/// Builds nothing, the library is written to the library directory by
+/// another tool. The reloader loads it whenever it changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct External;
+
+impl Compiler for External {
+    fn command(&self, _request: &CompileRequest) -> Option<Command> {
+        None
+    }
+}
+
+fn cargo_command(request: &CompileRequest, cargo_flags: &[&str]) -> Command {
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(&request.workspace_root)
+        .args(cargo_flags)
+        .args(build_args(
+            &request.library,
+            request.feature.as_deref(),
+            &request.rustc_args,
+        ))
+        .envs(hot_profile_env(
+            &request.workspace_root,
+            request.fast_linker,
+        ))
+        .env("CARGO_TARGET_DIR", &request.target_dir);
+    command
+}
+
+/// Runs `command`, handing the lines it writes to stderr to `progress`.
+fn run(mut command: Command, progress: &mut dyn FnMut(String)) -> CompileResult {
+    log::info!(
+        target: RELOAD,
+        "Working directory for build command: {}",
+        command
+            .get_current_dir()
+            .map_or_else(|| ".".into(), |dir| dir.display().to_string())
+    );
+
+    let mut child = match command.stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(err) => return CompileResult::NotRun(err.to_string()),
+    };
+
+    if let Some(stderr) = child.stderr.take() {
+        for line in BufReader::new(stderr).lines() {
+            match line {
+                Ok(line) => progress(line),
+                Err(err) => {
+                    log::error!(target: RELOAD, "Failed to read line from stderr: {}", err);
+                }
+            }
+        }
+    }
+
+    match child.wait() {
+        Ok(status) if status.success() => CompileResult::Built,
+        Ok(status) => CompileResult::Failed(status.to_string()),
+        Err(err) => CompileResult::NotRun(format!("Failed to wait for child process: {err}")),
+    }
+}
+
+/// `command` as a line for a shell, with its environment unless
+/// `with_env` is false.
+pub(crate) fn shell_line(command: &Command, with_env: bool) -> String {
+    let env = command
+        .get_envs()
+        .filter(|_| with_env)
+        .filter_map(|(key, value)| Some((key, value?)))
+        .map(|(key, value)| format!("{}={:?} ", key.to_string_lossy(), value));
+    let program = std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(OsStr::to_string_lossy)
+        .collect::<Vec<_>>()
+        .join(" ");
+    env.collect::<String>() + &program
+}
+
+fn build_args<'a>(
+    library_name: &'a str,
+    feature: Option<&'a str>,
+    rustc_args: &'a [String],
+) -> Vec<&'a str> {
+    let mut args = vec![
+        "rustc",
+        "--package",
+        library_name,
+        "--lib",
+        "--crate-type",
+        "cdylib",
+        "--profile",
+        HOT_PROFILE,
+    ];
+    if let Some(feature) = feature {
+        args.push("--features");
+        args.push(feature);
+    }
+    if !rustc_args.is_empty() {
+        args.push("--");
+        args.extend(rustc_args.iter().map(String::as_str));
+    }
+    args
+}
+
+/// Environment defining the [`HOT_PROFILE`]: no optimizations, line tables
+/// only, incremental, many codegen units, no LTO and unwinding panics, which
+/// the host catches. Settings of a `[profile.hot]` in the manifest of
+/// `workspace_root`, and `CARGO_PROFILE_HOT_*` variables already set, are
+/// left alone, cargo would let the environment override them. With
+/// `fast_linker` the library is also linked with `mold` or `lld` when
+/// installed.
+fn hot_profile_env(workspace_root: &Path, fast_linker: bool) -> Vec<(&'static str, String)> {
+    let manifest = std::fs::read_to_string(workspace_root.join("Cargo.toml")).unwrap_or_default();
+    let configured = profile_keys(&manifest, HOT_PROFILE);
+
+    let mut env: Vec<_> = [
+        ("inherits", "CARGO_PROFILE_HOT_INHERITS", "dev"),
+        ("opt-level", "CARGO_PROFILE_HOT_OPT_LEVEL", "0"),
+        ("debug", "CARGO_PROFILE_HOT_DEBUG", "line-tables-only"),
+        ("incremental", "CARGO_PROFILE_HOT_INCREMENTAL", "true"),
+        ("codegen-units", "CARGO_PROFILE_HOT_CODEGEN_UNITS", "256"),
+        ("lto", "CARGO_PROFILE_HOT_LTO", "false"),
+        ("panic", "CARGO_PROFILE_HOT_PANIC", "unwind"),
+    ]
+    .into_iter()
+    .filter(|(key, var, _)| {
+        !configured.iter().any(|configured| configured == *key) && std::env::var_os(var).is_none()
+    })
+    .map(|(_, var, value)| (var, value.to_string()))
+    .collect();
+
+    if let Some(linker) = fast_linker.then(detect_fast_linker).flatten() {
+        let mut rustflags = std::env::var("RUSTFLAGS").unwrap_or_default();
+        if !rustflags.is_empty() {
+            rustflags.push(' ');
+        }
+        rustflags.push_str(&format!("-C link-arg=-fuse-ld={linker}"));
+        env.push(("RUSTFLAGS", rustflags));
+    }
+    env
+}
+
+/// The keys set in the `[profile.<profile>]` table of `manifest`.
+fn profile_keys(manifest: &str, profile: &str) -> Vec<String> {
+    let header = format!("[profile.{profile}]");
+    manifest
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != header)
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, _)| key.trim().trim_matches('"').to_string())
+        .filter(|key| !key.is_empty() && !key.starts_with('#'))
+        .collect()
+}
+
+/// Returns the fastest linker found in `PATH`, only supported on Linux.
+fn detect_fast_linker() -> Option<&'static str> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let path = std::env::var_os("PATH")?;
+    let found = |binary: &str| std::env::split_paths(&path).any(|dir| dir.join(binary).is_file());
+    let linker = [("mold", "mold"), ("ld.lld", "lld")]
+        .into_iter()
+        .find(|(binary, _)| found(binary))
+        .map(|(_, linker)| linker);
+
+    match linker {
+        Some(linker) => log::info!(target: RELOAD, "Linking the hot library with {linker}"),
+        None => log::warn!(
+            target: RELOAD,
+            "fast_linker is set, but neither mold nor lld was found"
+        ),
+    }
+    linker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> CompileRequest {
+        CompileRequest {
+            library: "ui".to_string(),
+            workspace_root: PathBuf::from("/app"),
+            target_dir: "target/reload".to_string(),
+            feature: Some("reload".to_string()),
+            fast_linker: false,
+            rustc_args: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn compilers_build_the_hot_profile() {
+        let cargo = shell_line(&Cargo.command(&request()).unwrap(), false);
+        assert_eq!(
+            cargo,
+            "cargo rustc --package ui --lib --crate-type cdylib --profile hot --features reload"
+        );
+
+        let cranelift = Cranelift.command(&request()).unwrap();
+        assert!(shell_line(&cranelift, false).starts_with("cargo -Zcodegen-backend rustc "));
+        assert!(shell_line(&cranelift, true).contains("CARGO_PROFILE_HOT_CODEGEN_BACKEND="));
+
+        assert!(External.command(&request()).is_none());
+        assert_eq!(
+            External.compile(&request(), &mut |_| {}),
+            CompileResult::Built
+        );
+    }
+
+    #[test]
+    fn manifest_settings_of_the_hot_profile_are_kept() {
+        let manifest = "[profile.dev]\nopt-level = 1\n\n[profile.hot]\ninherits = \"dev\"\n\
+                        opt-level = 1 # faster\n\n[profile.hot.package.\"*\"]\nopt-level = 3\n";
+        assert_eq!(profile_keys(manifest, "hot"), ["inherits", "opt-level"]);
+        assert!(profile_keys("[workspace]\n", "hot").is_empty());
+
+        let dir = std::env::temp_dir().join(format!("hot_ice_profile_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+        let env = hot_profile_env(&dir, false);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(
+            !env.iter()
+                .any(|(var, _)| *var == "CARGO_PROFILE_HOT_OPT_LEVEL")
+        );
+        assert!(env.iter().any(|(var, _)| *var == "CARGO_PROFILE_HOT_LTO"));
+    }
+
+    #[test]
+    fn cranelift_needs_the_host_toolchain() {
+        let host = format!(
+            "{}\nbinary: rustc\nhost: {}\n",
+            env!("HOT_ICE_RUSTC_VERSION"),
+            env!("HOT_ICE_TARGET")
+        );
+        assert_eq!(
+            check_toolchain(&host).is_ok(),
+            env!("HOT_ICE_RUSTC_VERSION").contains("-nightly")
+        );
+
+        let stable = "rustc 1.90.0 (1159e78c4 2025-09-14)\nhost: x86_64-unknown-linux-gnu\n";
+        assert!(check_toolchain(stable).unwrap_err().contains("nightly"));
+
+        let other = "rustc 1.0.0-nightly (0000000 2015-01-01)\nhost: x86_64-unknown-linux-gnu\n";
+        assert!(check_toolchain(other).unwrap_err().contains("the host by"));
+    }
+}
@@ -0,0 +1,432 @@
+//! A hot part of an otherwise static application.
+//!
+//! [`component`] loads the `view` and `update` of a single piece of state from
+//! its own cdylib and reloads them whenever the library is rebuilt, without
+//! the rest of the application going through [`application`](crate::application).
+//! The host calls the component from its own `view`, `update` and
+//! `subscription`, the component's messages travel through it wrapped in a
+//! [`ComponentMessage`].
+//!
+//! The library's functions are plain `#[hot_fn]` methods on the component's
+//! state, named `update` and `view`:
+//!
+//! ```rust,ignore
+//! // panel_lib/src/lib.rs
+//! impl PanelState {
+//!     #[hot_fn]
+//!     pub fn update(&mut self, message: PanelMessage) -> Task<PanelMessage> { .. }
+//!
+//!     #[hot_fn]
+//!     pub fn view(&self) -> Element<'_, PanelMessage> { .. }
+//! }
+//! ```
+//!
+//! Unlike the hot application the component does not build the library, run
+//! a worker, transfer state or keep fonts in sync. Libraries replaced by a
+//! rebuild stay loaded until the component is dropped, widget states and
+//! tasks may still point into them.
+
+use std::any::TypeId;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use hot_ice_common::{SET_HOST_CONTEXT_FUNCTION_NAME, SET_INTERNER_FUNCTION_NAME};
+use iced::{Element, Subscription, Task};
+use iced_futures::BoxStream;
+use iced_futures::futures::stream;
+use iced_futures::subscription::{self, EventStream, Hasher, Recipe};
+
+use crate::context::Context;
+use crate::error::{HotIceError, HotResult};
+use crate::fingerprint;
+use crate::lib_reloader::{LibReloader, RetiredLibrary, UpdateResult};
+use crate::log_target::{DISPATCH, RELOAD};
+use crate::message::{DynMessage, HotMessage};
+use crate::registry;
+use crate::reloader::{HotFunction, ReloaderSettings, build_command, create_missing_lib_dir};
+use crate::transport::ReloadEvent;
+
+const UPDATE: &str = "update";
+const VIEW: &str = "view";
+
+/// Creates a component whose `view` and `update` are loaded from the cdylib
+/// `lib_name`.
+///
+/// ```rust,ignore
+/// struct App {
+///     panel: HotComponent<PanelState, PanelMessage>,
+///     panel_state: PanelState,
+/// }
+///
+/// fn update(app: &mut App, message: Message) -> Task<Message> {
+///     match message {
+///         Message::Panel(message) => app
+///             .panel
+///             .update(&mut app.panel_state, message)
+///             .map(Message::Panel),
+///     }
+/// }
+///
+/// fn view(app: &App) -> Element<'_, Message> {
+///     app.panel.view(&app.panel_state).map(Message::Panel)
+/// }
+///
+/// fn subscription(app: &App) -> Subscription<Message> {
+///     app.panel.subscription().map(Message::Panel)
+/// }
+///
+/// let panel = hot_ice::component::<PanelState, PanelMessage>("panel_lib");
+/// ```
+pub fn component<State, Message>(lib_name: &'static str) -> HotComponent<State, Message> {
+    HotComponent {
+        lib_name,
+        reloader_settings: ReloaderSettings::default(),
+        library: Arc::new(OnceLock::new()),
+        _types: PhantomData,
+    }
+}
+
+/// The `view` and `update` of `State`, loaded from a cdylib, see
+/// [`component`].
+pub struct HotComponent<State, Message> {
+    lib_name: &'static str,
+    reloader_settings: ReloaderSettings,
+    library: Arc<OnceLock<Result<Mutex<Library>, String>>>,
+    _types: PhantomData<fn(&State) -> Message>,
+}
+
+struct Library {
+    reloader: LibReloader,
+    /// Kept until the component is dropped, the application may still hold
+    /// widget states and tasks created by them.
+    retired: Vec<RetiredLibrary>,
+    /// Why the loaded library is not called into.
+    refused: Option<String>,
+}
+
+impl<State, Message> HotComponent<State, Message>
+where
+    State: 'static,
+    Message: DynMessage + Clone,
+{
+    /// Sets where the library is found and how it is watched. Only
+    /// `lib_dir`, `target_dir`, `file_watch_debounce`, `transport` and
+    /// `artifact_cleanup` apply to a component.
+    ///
+    /// Default: [`ReloaderSettings::default`]
+    pub fn reloader_settings(self, reloader_settings: ReloaderSettings) -> Self {
+        Self {
+            reloader_settings,
+            library: Arc::new(OnceLock::new()),
+            ..self
+        }
+    }
+
+    /// Calls the library's `update`. Messages are dropped while the library
+    /// is not loaded.
+    pub fn update(
+        &self,
+        state: &mut State,
+        message: ComponentMessage<Message>,
+    ) -> Task<ComponentMessage<Message>> {
+        let Inner::Message(message) = message.0 else {
+            // Reloads the library, the host redraws after every update.
+            let _ = self.with_library(|_| Ok(()));
+            return Task::none();
+        };
+
+        let result = self.with_library(|lib| {
+            let update = unsafe {
+                symbol::<fn(&mut State, HotMessage) -> Result<Task<HotMessage>, HotIceError>>(
+                    lib,
+                    UPDATE,
+                    HotFunction::Update,
+                    registry::type_hash::<(
+                        &mut State,
+                        HotMessage,
+                        Result<Task<HotMessage>, HotIceError>,
+                    )>(),
+                )?
+            };
+            update(state, message).map_err(|err| err.to_string())
+        });
+
+        match result {
+            Ok(task) => task.map(ComponentMessage::hot),
+            Err(err) => {
+                log::error!(target: DISPATCH, "{}: {}", self.lib_name, err);
+                Task::none()
+            }
+        }
+    }
+
+    /// Calls the library's `view`, or shows why it can't be called.
+    pub fn view<'a>(&self, state: &'a State) -> Element<'a, ComponentMessage<Message>> {
+        let result = self.with_library(|lib| {
+            let view = unsafe {
+                symbol::<fn(&'a State) -> HotResult<Element<'a, HotMessage>>>(
+                    lib,
+                    VIEW,
+                    HotFunction::View,
+                    registry::type_hash::<(&State, HotResult<Element<'_, HotMessage>>)>(),
+                )?
+            };
+            view(state).0.map_err(|err| err.to_string())
+        });
+
+        match result {
+            Ok(element) => element.map(ComponentMessage::hot),
+            Err(err) => iced_widget::text(format!("{}: {}", self.lib_name, err)).into(),
+        }
+    }
+
+    /// Produces a message whenever the library was rebuilt, so the host
+    /// updates and redraws the component.
+    pub fn subscription(&self) -> Subscription<ComponentMessage<Message>> {
+        let _ = self.with_library(|_| Ok(()));
+        if self.watching().is_none() {
+            return Subscription::none();
+        }
+        subscription::from_recipe(Rebuilds {
+            lib_name: self.lib_name,
+            library: self.library.clone(),
+            _message: PhantomData::<fn() -> ComponentMessage<Message>>,
+        })
+    }
+
+    /// The watched library, once it could be watched.
+    fn watching(&self) -> Option<&Mutex<Library>> {
+        self.library.get()?.as_ref().ok()
+    }
+
+    /// Runs `call` with the loaded library, reloading it first if it was
+    /// rebuilt.
+    fn with_library<T>(
+        &self,
+        call: impl FnOnce(&LibReloader) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let library = self.library.get_or_init(|| {
+            create_missing_lib_dir(&self.reloader_settings.lib_dir);
+            let reloader = LibReloader::new(
+                &self.reloader_settings.lib_dir,
+                self.lib_name,
+                &*self.reloader_settings.transport(),
+                None,
+                self.reloader_settings.artifact_cleanup.clone(),
+                0,
+            )
+            .map_err(|err| format!("Cannot watch {}: {}", self.reloader_settings.lib_dir, err))?;
+            let refused = reloader
+                .loaded_lib_file()
+                .and_then(|_| prepare(&reloader).err());
+            Ok(Mutex::new(Library {
+                reloader,
+                retired: Vec::new(),
+                refused,
+            }))
+        });
+        let library = library.as_ref().map_err(Clone::clone)?;
+        let Ok(mut library) = library.lock() else {
+            return Err("The library is poisoned".to_string());
+        };
+
+        match library.reloader.update() {
+            Ok(UpdateResult::Unchanged) => {}
+            Ok(UpdateResult::Reloaded { retired }) => {
+                log::info!(target: RELOAD, "Reloaded component {}", self.lib_name);
+                library.retired.extend(retired);
+                library.refused = prepare(&library.reloader).err();
+            }
+            Err(err) => {
+                log::error!(target: RELOAD, "Failed to reload {}: {}", self.lib_name, err);
+            }
+        }
+
+        if library.reloader.loaded_lib_file().is_none() {
+            return Err(
+                match build_command(&self.reloader_settings, self.lib_name) {
+                    Some(command) => format!("Not built yet, build it with: {command}"),
+                    None => "Not built yet".to_string(),
+                },
+            );
+        }
+        if let Some(refused) = &library.refused {
+            return Err(refused.clone());
+        }
+        call(&library.reloader)
+    }
+}
+
+/// Checks a freshly loaded library and hands it the host's services.
+fn prepare(lib: &LibReloader) -> Result<(), String> {
+    fingerprint::check_library(lib)
+        .map_err(|mismatch| format!("Refusing to call into incompatible library: {mismatch}"))?;
+    fingerprint::check_panic_strategy(lib);
+
+    if let Ok(set_interner) =
+        unsafe { lib.get_symbol::<fn(fn(&str) -> &'static str)>(SET_INTERNER_FUNCTION_NAME) }
+    {
+        set_interner(crate::intern::intern);
+    }
+    if let Ok(set_host_context) =
+        unsafe { lib.get_symbol::<fn(&'static Context)>(SET_HOST_CONTEXT_FUNCTION_NAME) }
+    {
+        set_host_context(Context::host());
+    }
+    Ok(())
+}
+
+/// Looks up the hot function `name` after checking its companion symbol
+/// reports the expected kind and signature.
+///
+/// # Safety
+///
+/// `type_hash` must be the hash of the signature of `F`.
+unsafe fn symbol<F: Copy>(
+    lib: &LibReloader,
+    name: &'static str,
+    kind: HotFunction,
+    type_hash: u64,
+) -> Result<F, String> {
+    match registry::exported(lib, name) {
+        Some(export) if export.kind == kind && export.type_hash == type_hash => {}
+        Some(_) => {
+            return Err(format!(
+                "`{name}` in the library does not match the component's state and message"
+            ));
+        }
+        None => return Err(format!("The library exports no `#[hot_fn] {name}`")),
+    }
+
+    unsafe { lib.get_symbol::<F>(name) }
+        .map_err(|_| HotIceError::FunctionNotFound(name).to_string())
+}
+
+/// A message of a [`HotComponent`].
+///
+/// Messages of the component's library and the ones the host creates with
+/// [`From`] are passed on to its `update`.
+pub struct ComponentMessage<Message>(Inner, PhantomData<fn() -> Message>);
+
+enum Inner {
+    Message(HotMessage),
+    Rebuilt,
+}
+
+impl<Message: DynMessage + Clone> ComponentMessage<Message> {
+    fn hot(message: HotMessage) -> Self {
+        Self(Inner::Message(message), PhantomData)
+    }
+
+    /// Takes the component's message out, to react to it in the host.
+    pub fn into_message(self) -> Result<Message, Self> {
+        match self.0 {
+            Inner::Message(message) => message
+                .into_message()
+                .map_err(|message| Self(Inner::Message(message), PhantomData)),
+            Inner::Rebuilt => Err(self),
+        }
+    }
+}
+
+impl<Message: DynMessage + Clone> From<Message> for ComponentMessage<Message> {
+    fn from(message: Message) -> Self {
+        Self::hot(HotMessage::from_message(message))
+    }
+}
+
+impl<Message> Clone for ComponentMessage<Message> {
+    fn clone(&self) -> Self {
+        let inner = match &self.0 {
+            Inner::Message(message) => Inner::Message(message.clone()),
+            Inner::Rebuilt => Inner::Rebuilt,
+        };
+        Self(inner, PhantomData)
+    }
+}
+
+impl<Message> fmt::Debug for ComponentMessage<Message> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Inner::Message(message) => message.fmt(f),
+            Inner::Rebuilt => f.write_str("Rebuilt"),
+        }
+    }
+}
+
+struct Rebuilds<Message> {
+    lib_name: &'static str,
+    library: Arc<OnceLock<Result<Mutex<Library>, String>>>,
+    _message: PhantomData<fn() -> Message>,
+}
+
+impl<Message: DynMessage + Clone> Recipe for Rebuilds<ComponentMessage<Message>> {
+    type Output = ComponentMessage<Message>;
+
+    fn hash(&self, state: &mut Hasher) {
+        TypeId::of::<Self>().hash(state);
+        self.lib_name.hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: EventStream) -> BoxStream<Self::Output> {
+        let changes = self
+            .library
+            .get()
+            .and_then(|library| library.as_ref().ok())
+            .and_then(|library| library.lock().ok())
+            .map(|mut library| library.reloader.subscribe_to_file_changes());
+        let Some(changes) = changes else {
+            return iced_futures::boxed_stream(stream::empty());
+        };
+
+        iced_futures::boxed_stream(stream::unfold(changes, |changes| async move {
+            match changes.recv().await.ok()? {
+                ReloadEvent::Changed => {
+                    Some((ComponentMessage(Inner::Rebuilt, PhantomData), changes))
+                }
+                // Logged by the transport, the component keeps its library.
+                ReloadEvent::WatcherDied(_) => None,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum PanelMessage {
+        Toggle,
+    }
+
+    #[test]
+    fn shows_why_an_unbuilt_library_is_not_called() {
+        let lib_dir =
+            std::env::temp_dir().join(format!("hot_ice_component_{}", std::process::id()));
+        let panel =
+            component::<bool, PanelMessage>("no_such_panel").reloader_settings(ReloaderSettings {
+                lib_dir: lib_dir.display().to_string(),
+                ..ReloaderSettings::default()
+            });
+
+        let mut state = false;
+        let task = panel.update(&mut state, PanelMessage::Toggle.into());
+        assert_eq!(task.units(), 0);
+        assert!(!state);
+        assert!(
+            panel
+                .with_library(|_| Ok(()))
+                .unwrap_err()
+                .starts_with("Not built yet")
+        );
+
+        let message = ComponentMessage::from(PanelMessage::Toggle);
+        assert_eq!(format!("{:?}", message.clone()), "HotMessage(Toggle)");
+        assert_eq!(message.into_message().ok(), Some(PanelMessage::Toggle));
+
+        let _ = std::fs::remove_dir_all(lib_dir);
+    }
+}
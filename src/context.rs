@@ -0,0 +1,265 @@
+//! Host services passed to hot functions.
+//!
+//! `update` and `view` can take a `&Context` as their last argument, `boot`
+//! as its only one. The context is a pointer to a struct in the host binary,
+//! its methods call through a `#[repr(C)]` table of host functions, so they
+//! reach the host's interner, resources, assets and logger from any library
+//! generation.
+//!
+//! The host also hands its context to every library through the symbol
+//! exported by [`export_host_context!`], so code without a `&Context`
+//! argument reaches it with [`Context::current`].
+
+use std::fmt::{self, Debug};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::task::Waker;
+use std::time::Duration;
+
+use crate::blocking::{self, Job};
+use crate::during_reload;
+use crate::host_resource::{self, HostOwned};
+use crate::hot_assets::{self, Shader, TextBundle};
+use crate::hot_state;
+use crate::hot_tokens::TokenFile;
+use crate::lent_state;
+use crate::reload_deferral;
+use crate::stream_handle::{self, StreamHandle};
+use crate::task::{self, Request};
+use crate::type_hash::TypeHash;
+use crate::{intern, metrics};
+
+/// Access to services of the host binary from hot code.
+///
+/// Add it as the last argument of `update` or `view`:
+///
+/// ```rust,ignore
+/// #[hot_ice::hot_fn]
+/// pub fn update(&mut self, message: Message, context: &hot_ice::Context) -> Task<Message> {
+///     let pool = context.resource::<Pool>();
+///     context.log(log::Level::Info, &format!("{message:?}"));
+///     Task::none()
+/// }
+/// ```
+#[repr(C)]
+pub struct Context {
+    vtable: &'static ContextVTable,
+}
+
+/// Functions of the host, only ever appended to.
+#[repr(C)]
+struct ContextVTable {
+    intern: fn(&str) -> &'static str,
+    resource: fn(u64) -> Option<*const ()>,
+    generation: fn() -> u64,
+    log: fn(log::Level, &str),
+    text_bundle: fn(&Path) -> &'static TextBundle,
+    token_file: fn(&Path) -> &'static TokenFile,
+    register_stream: fn() -> u64,
+    poll_stream_aborted: fn(u64, &Waker) -> bool,
+    abort_stream: fn(u64),
+    release_stream: fn(u64),
+    spawn_blocking: fn(Job),
+    record_update_body: fn(Duration),
+    poll_reloading: fn(&Waker) -> bool,
+    request: fn(Request),
+    shader: fn(&Path) -> &'static Shader,
+    defer_reload: fn(bool),
+    checked_state: fn() -> bool,
+    lend_state: fn(bool),
+}
+
+static HOST_VTABLE: ContextVTable = ContextVTable {
+    intern: intern::intern,
+    resource: host_resource::lookup,
+    generation: metrics::reloads,
+    log: host_log,
+    text_bundle: hot_assets::load::<TextBundle>,
+    token_file: hot_assets::load::<TokenFile>,
+    register_stream: stream_handle::register,
+    poll_stream_aborted: stream_handle::poll_aborted,
+    abort_stream: stream_handle::abort,
+    release_stream: stream_handle::release,
+    spawn_blocking: blocking::spawn,
+    record_update_body: metrics::record_update_body,
+    poll_reloading: during_reload::poll_reloading,
+    request: task::send,
+    shader: hot_assets::load::<Shader>,
+    defer_reload: reload_deferral::hold,
+    checked_state: hot_state::checked_state,
+    lend_state: lent_state::lend,
+};
+
+static HOST_CONTEXT: Context = Context {
+    vtable: &HOST_VTABLE,
+};
+
+static CURRENT: OnceLock<&'static Context> = OnceLock::new();
+
+impl Context {
+    /// The context of this binary, handed to hot functions by the host.
+    pub(crate) fn host() -> &'static Context {
+        &HOST_CONTEXT
+    }
+
+    /// The host's context, for code that has no `&Context` argument. In a
+    /// loaded library this is the context handed over by the host.
+    pub fn current() -> &'static Context {
+        CURRENT.get().copied().unwrap_or(&HOST_CONTEXT)
+    }
+
+    /// Interns `value` in the host, see [`intern`](crate::intern). The string
+    /// is never freed.
+    pub fn intern(&self, value: &str) -> &'static str {
+        (self.vtable.intern)(value)
+    }
+
+    /// The resource of type `T` registered with `HotIce::host_resource`.
+    pub fn resource<T: TypeHash + Send + Sync>(&self) -> Option<HostOwned<T>> {
+        HostOwned::lookup_in(self.vtable.resource)
+    }
+
+    /// Number of hot libraries loaded so far, 0 while no library is loaded.
+    pub fn generation(&self) -> u64 {
+        (self.vtable.generation)()
+    }
+
+    /// Logs `message` with the host's logger, the library has no logger of
+    /// its own.
+    pub fn log(&self, level: log::Level, message: &str) {
+        (self.vtable.log)(level, message)
+    }
+
+    /// The bundle loaded from `path` by the host, see
+    /// [`hot_assets::text_bundle`].
+    pub fn text_bundle(&self, path: impl AsRef<Path>) -> &'static TextBundle {
+        (self.vtable.text_bundle)(path.as_ref())
+    }
+
+    /// The shader loaded from `path` by the host, see
+    /// [`hot_shader`](crate::hot_shader).
+    pub fn shader(&self, path: impl AsRef<Path>) -> &'static Shader {
+        (self.vtable.shader)(path.as_ref())
+    }
+
+    /// The token file loaded from `path` by the host, see
+    /// [`hot_tokens!`](crate::hot_tokens!).
+    pub fn token_file(&self, path: impl AsRef<Path>) -> &'static TokenFile {
+        (self.vtable.token_file)(path.as_ref())
+    }
+
+    /// Aborts the stream of `handle`, see [`StreamHandle::abort`].
+    pub fn abort_stream(&self, handle: StreamHandle) {
+        (self.vtable.abort_stream)(handle.id())
+    }
+
+    pub(crate) fn register_stream(&self) -> StreamHandle {
+        StreamHandle::from_id((self.vtable.register_stream)())
+    }
+
+    pub(crate) fn poll_stream_aborted(&self, handle: StreamHandle, waker: &Waker) -> bool {
+        (self.vtable.poll_stream_aborted)(handle.id(), waker)
+    }
+
+    pub(crate) fn release_stream(&self, handle: StreamHandle) {
+        (self.vtable.release_stream)(handle.id())
+    }
+
+    pub(crate) fn spawn_job(&self, job: Job) {
+        (self.vtable.spawn_blocking)(job)
+    }
+
+    pub(crate) fn record_update_body(&self, elapsed: Duration) {
+        (self.vtable.record_update_body)(elapsed)
+    }
+
+    pub(crate) fn poll_reloading(&self, waker: &Waker) -> bool {
+        (self.vtable.poll_reloading)(waker)
+    }
+
+    pub(crate) fn request(&self, request: Request) {
+        (self.vtable.request)(request)
+    }
+
+    pub(crate) fn defer_reload(&self, held: bool) {
+        (self.vtable.defer_reload)(held)
+    }
+
+    pub(crate) fn checked_state(&self) -> bool {
+        (self.vtable.checked_state)()
+    }
+
+    pub(crate) fn lend_state(&self, lent: bool) {
+        (self.vtable.lend_state)(lent)
+    }
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("generation", &self.generation())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Number of hot libraries loaded so far, the generation of the library
+/// running now. It changes with every reload, so it resets widgets keyed by
+/// it, busts caches and tells log lines of different generations apart:
+///
+/// ```rust,ignore
+/// let key = format!("chart-{}", hot_ice::generation());
+/// ```
+///
+/// Same as `Context::current().generation()`.
+pub fn generation() -> u64 {
+    Context::current().generation()
+}
+
+fn host_log(level: log::Level, message: &str) {
+    log::log!(target: "hot_ice::context", level, "{message}");
+}
+
+/// Called by the symbol generated with [`export_host_context!`]. Only the
+/// first call has an effect, each library load starts with fresh statics.
+#[doc(hidden)]
+pub fn set_host_context(context: &'static Context) {
+    let _ = CURRENT.set(context);
+}
+
+/// Exports the FFI function the host uses to hand its [`Context`] to the
+/// cdylib, one of the [exports of `update`](crate::hot_fn#exports-of-update).
+#[macro_export]
+macro_rules! export_host_context {
+    () => {
+        /// Receives the host's context, see `hot_ice::Context::current`.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn set_host_context_lskdjfa3lkfjasdf(context: &'static $crate::Context) {
+            $crate::macro_use::set_host_context(context);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(u32);
+
+    unsafe impl TypeHash for Counter {
+        const TYPE_HASH: u64 = 3;
+    }
+
+    #[test]
+    fn calls_into_the_host() {
+        let context = Context::host();
+        assert!(std::ptr::eq(context.intern("ctx"), intern::intern("ctx")));
+
+        assert!(context.resource::<Counter>().is_none());
+        host_resource::register(Counter(3));
+        assert_eq!(context.resource::<Counter>().unwrap().0, 3);
+
+        assert_eq!(generation(), metrics::reloads());
+    }
+}
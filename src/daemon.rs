@@ -0,0 +1,360 @@
+//! Several hot programs on one event loop.
+//!
+//! A tool with a main window and an inspector usually keeps each in its own
+//! crate. [`daemon`] runs such programs side by side: every program gets a
+//! window, a [`Reloader`] with its own library, worker and overlay, and the
+//! supervisor routes messages, windows and subscriptions to the program they
+//! belong to.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+
+use iced_core::{Element, Settings, theme, window};
+use iced_futures::Subscription;
+use iced_winit::{
+    Error,
+    program::Program,
+    runtime::{Task, window as runtime_window},
+};
+
+use crate::log_target::DISPATCH;
+use crate::{
+    executor,
+    external::map_action,
+    hot_application::{Configured, HotIce, OnExit},
+    hot_program::HotProgram,
+    reloader::{self, Reload, Reloader},
+    winit,
+};
+
+/// Creates a daemon running `program` in the first window.
+///
+/// Add more programs with [`HotDaemon::program`], each opens its own window.
+/// The daemon exits once every window is closed.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// hot_ice::daemon(hot_ice::application(Main::boot, Main::update, Main::view))
+///     .program(
+///         hot_ice::application(Inspector::boot, Inspector::update, Inspector::view)
+///             .window_size((400, 800)),
+///     )
+///     .run()
+///     .unwrap();
+/// ```
+pub fn daemon<P>(program: HotIce<P>) -> HotDaemon<P>
+where
+    P: HotProgram + 'static,
+    P::Message: Clone,
+{
+    HotDaemon {
+        programs: Vec::new(),
+        on_exit: Vec::new(),
+        first: PhantomData,
+    }
+    .program(program)
+}
+
+/// Hot programs sharing one event loop, see [`daemon`].
+///
+/// The first program decides the theme, renderer and executor of the
+/// daemon, and its `style` applies to every window.
+pub struct HotDaemon<P>
+where
+    P: HotProgram,
+{
+    programs: Vec<Box<dyn ErasedProgram<P::Theme, P::Renderer>>>,
+    on_exit: Vec<OnExit>,
+    first: PhantomData<fn() -> P>,
+}
+
+impl<P> HotDaemon<P>
+where
+    P: HotProgram + 'static,
+    P::Message: Clone,
+{
+    /// Adds a program in a window of its own.
+    ///
+    /// Every program needs its own message type, it is what actions of its
+    /// worker and external handle are routed by.
+    pub fn program<Q>(mut self, program: HotIce<Q>) -> Self
+    where
+        Q: HotProgram<Theme = P::Theme, Renderer = P::Renderer> + 'static,
+        Q::Message: Clone,
+    {
+        let index = self.programs.len();
+        executor::set_action_route::<reloader::Message<Configured<Q>>>(move |action| {
+            if let Some(proxy) = executor::get_global_proxy::<DaemonMessage>() {
+                proxy.send_action(map_action(action, |message| {
+                    DaemonMessage::new(index, message)
+                }));
+            }
+        });
+
+        let (mut program, on_exit) = program.into_reload();
+        program.disable_window_persistence();
+        self.programs.push(Box::new(program));
+        self.on_exit.extend(on_exit);
+        self
+    }
+
+    /// Runs every program until all windows are closed, then calls the
+    /// `on_exit` callbacks of the programs in the order they were added.
+    pub fn run(self) -> Result<(), Error> {
+        let supervisor = Supervisor::<P> {
+            programs: self.programs,
+            first: PhantomData,
+        };
+
+        let result = winit::run(supervisor);
+
+        for on_exit in self.on_exit {
+            on_exit();
+        }
+        result
+    }
+}
+
+/// A message of one of the programs.
+pub(crate) struct DaemonMessage {
+    program: usize,
+    message: Box<dyn Any + Send>,
+}
+
+impl DaemonMessage {
+    fn new<M: Send + 'static>(program: usize, message: M) -> Self {
+        Self {
+            program,
+            message: Box::new(message),
+        }
+    }
+}
+
+impl Debug for DaemonMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DaemonMessage")
+            .field("program", &self.program)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The reloaders of the programs and the window of each.
+pub(crate) struct Daemon {
+    reloaders: Vec<Box<dyn Any>>,
+    /// Windows opened for the programs. The first program's window, and any
+    /// window a program opens itself, belongs to the first program.
+    windows: HashMap<window::Id, usize>,
+}
+
+impl Daemon {
+    fn program_of(&self, window: window::Id) -> usize {
+        self.windows.get(&window).copied().unwrap_or(0)
+    }
+}
+
+/// A [`Reload`] program with its types erased behind the daemon's message.
+trait ErasedProgram<Theme, Renderer> {
+    fn settings(&self) -> Settings;
+    fn window(&self) -> window::Settings;
+    fn boot(&self, index: usize) -> (Box<dyn Any>, Task<DaemonMessage>);
+    fn update(
+        &self,
+        index: usize,
+        state: &mut dyn Any,
+        message: Box<dyn Any + Send>,
+    ) -> Task<DaemonMessage>;
+    fn view<'a>(
+        &self,
+        index: usize,
+        state: &'a dyn Any,
+        window: window::Id,
+    ) -> Element<'a, DaemonMessage, Theme, Renderer>;
+    fn title(&self, state: &dyn Any, window: window::Id) -> String;
+    fn subscription(&self, index: usize, state: &dyn Any) -> Subscription<DaemonMessage>;
+    fn theme(&self, state: &dyn Any, window: window::Id) -> Option<Theme>;
+    fn style(&self, state: &dyn Any, theme: &Theme) -> theme::Style;
+    fn scale_factor(&self, state: &dyn Any, window: window::Id) -> f32;
+}
+
+impl<P> ErasedProgram<P::Theme, P::Renderer> for Reload<P>
+where
+    P: HotProgram + 'static,
+    P::Message: Clone,
+{
+    fn settings(&self) -> Settings {
+        Program::settings(self)
+    }
+
+    fn window(&self) -> window::Settings {
+        Program::window(self).unwrap_or_default()
+    }
+
+    fn boot(&self, index: usize) -> (Box<dyn Any>, Task<DaemonMessage>) {
+        let (reloader, task) = Program::boot(self);
+        let task = task.map(move |message| DaemonMessage::new(index, message));
+        (Box::new(reloader), task)
+    }
+
+    fn update(
+        &self,
+        index: usize,
+        state: &mut dyn Any,
+        message: Box<dyn Any + Send>,
+    ) -> Task<DaemonMessage> {
+        let Ok(message) = message.downcast::<reloader::Message<P>>() else {
+            log::error!(target: DISPATCH, "Dropped a message of another program in {}", P::name());
+            return Task::none();
+        };
+        Program::update(self, reloader_mut(state), *message)
+            .map(move |message| DaemonMessage::new(index, message))
+    }
+
+    fn view<'a>(
+        &self,
+        index: usize,
+        state: &'a dyn Any,
+        window: window::Id,
+    ) -> Element<'a, DaemonMessage, P::Theme, P::Renderer> {
+        Program::view(self, reloader_ref(state), window)
+            .map(move |message| DaemonMessage::new(index, message))
+    }
+
+    fn title(&self, state: &dyn Any, window: window::Id) -> String {
+        Program::title(self, reloader_ref::<P>(state), window)
+    }
+
+    fn subscription(&self, index: usize, state: &dyn Any) -> Subscription<DaemonMessage> {
+        Program::subscription(self, reloader_ref(state))
+            .with(index)
+            .map(|(index, message)| DaemonMessage::new(index, message))
+    }
+
+    fn theme(&self, state: &dyn Any, window: window::Id) -> Option<P::Theme> {
+        Program::theme(self, reloader_ref(state), window)
+    }
+
+    fn style(&self, state: &dyn Any, theme: &P::Theme) -> theme::Style {
+        Program::style(self, reloader_ref(state), theme)
+    }
+
+    fn scale_factor(&self, state: &dyn Any, window: window::Id) -> f32 {
+        Program::scale_factor(self, reloader_ref::<P>(state), window)
+    }
+}
+
+fn reloader_ref<P: HotProgram + 'static>(state: &dyn Any) -> &Reloader<P> {
+    state
+        .downcast_ref()
+        .expect("every program is booted with its own reloader")
+}
+
+fn reloader_mut<P: HotProgram + 'static>(state: &mut dyn Any) -> &mut Reloader<P> {
+    state
+        .downcast_mut()
+        .expect("every program is booted with its own reloader")
+}
+
+/// The [`Program`] run by a [`HotDaemon`].
+struct Supervisor<P: HotProgram> {
+    programs: Vec<Box<dyn ErasedProgram<P::Theme, P::Renderer>>>,
+    first: PhantomData<fn() -> P>,
+}
+
+impl<P> Program for Supervisor<P>
+where
+    P: HotProgram + 'static,
+{
+    type State = Daemon;
+    type Message = DaemonMessage;
+    type Theme = P::Theme;
+    type Renderer = P::Renderer;
+    type Executor = P::Executor;
+
+    fn name() -> &'static str {
+        P::name()
+    }
+
+    fn settings(&self) -> Settings {
+        let mut programs = self.programs.iter().map(|program| program.settings());
+        let mut settings = programs.next().unwrap_or_default();
+        for other in programs {
+            settings.fonts.extend(other.fonts);
+        }
+        settings
+    }
+
+    fn window(&self) -> Option<window::Settings> {
+        self.programs.first().map(|program| program.window())
+    }
+
+    fn boot(&self) -> (Self::State, Task<Self::Message>) {
+        let mut daemon = Daemon {
+            reloaders: Vec::with_capacity(self.programs.len()),
+            windows: HashMap::new(),
+        };
+        let mut tasks = Vec::with_capacity(self.programs.len() * 2);
+
+        for (index, program) in self.programs.iter().enumerate() {
+            let (reloader, task) = program.boot(index);
+            daemon.reloaders.push(reloader);
+            tasks.push(task);
+
+            if index > 0 {
+                let (id, open) = runtime_window::open(program.window());
+                daemon.windows.insert(id, index);
+                tasks.push(open.discard());
+            }
+        }
+
+        (daemon, Task::batch(tasks))
+    }
+
+    fn update(&self, state: &mut Self::State, message: Self::Message) -> Task<Self::Message> {
+        let DaemonMessage { program, message } = message;
+        match (self.programs.get(program), state.reloaders.get_mut(program)) {
+            (Some(erased), Some(reloader)) => erased.update(program, reloader.as_mut(), message),
+            _ => Task::none(),
+        }
+    }
+
+    fn view<'a>(
+        &self,
+        state: &'a Self::State,
+        window: window::Id,
+    ) -> Element<'a, Self::Message, Self::Theme, Self::Renderer> {
+        let index = state.program_of(window);
+        self.programs[index].view(index, state.reloaders[index].as_ref(), window)
+    }
+
+    fn title(&self, state: &Self::State, window: window::Id) -> String {
+        let index = state.program_of(window);
+        self.programs[index].title(state.reloaders[index].as_ref(), window)
+    }
+
+    fn subscription(&self, state: &Self::State) -> Subscription<Self::Message> {
+        Subscription::batch(
+            self.programs
+                .iter()
+                .zip(&state.reloaders)
+                .enumerate()
+                .map(|(index, (program, reloader))| program.subscription(index, reloader.as_ref())),
+        )
+    }
+
+    fn theme(&self, state: &Self::State, window: window::Id) -> Option<Self::Theme> {
+        let index = state.program_of(window);
+        self.programs[index].theme(state.reloaders[index].as_ref(), window)
+    }
+
+    fn style(&self, state: &Self::State, theme: &Self::Theme) -> theme::Style {
+        self.programs[0].style(state.reloaders[0].as_ref(), theme)
+    }
+
+    fn scale_factor(&self, state: &Self::State, window: window::Id) -> f32 {
+        let index = state.program_of(window);
+        self.programs[index].scale_factor(state.reloaders[index].as_ref(), window)
+    }
+}
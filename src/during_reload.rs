@@ -0,0 +1,211 @@
+//! Messages of high-frequency subscriptions while a reload is running.
+//!
+//! Subscriptions keep running on the worker during a reload, and the reloader
+//! buffers every message they produce until the new library is loaded. A
+//! 60Hz tick fills the buffer with hundreds of stale messages that are all
+//! replayed at once. A subscription wrapped with [`during_reload`] thins out
+//! its own messages while the host is reloading, as [`DuringReload`] says.
+//! The host keeps the reloading flag, the library reaches it through its
+//! [`Context`].
+
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+use futures::stream::{self, Fuse, Stream, StreamExt};
+use futures::{FutureExt, ready};
+use iced_futures::subscription::{self, EventStream, Hasher, Recipe};
+use iced_futures::{BoxStream, MaybeSend, Subscription};
+
+use crate::context::Context;
+
+/// What a subscription of [`during_reload`] does with its messages while a
+/// reload is running.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DuringReload {
+    /// Every message is buffered and replayed after the reload.
+    #[default]
+    Buffer,
+    /// Only the latest message is kept, it is delivered once the reload
+    /// finished.
+    Latest,
+    /// At most one message per `Duration` is buffered, the latest of the ones
+    /// in between is delivered once the reload finished.
+    AtMostEvery(Duration),
+}
+
+/// Wraps the recipes of `subscription` so they thin out their messages during
+/// a reload, as `policy` says. The policy is hashed with each recipe, so the
+/// subscription keeps running across reloads as long as its key and the
+/// policy stay the same.
+///
+/// ```rust,ignore
+/// fn subscription(&self) -> Subscription<Message> {
+///     hot_ice::during_reload(
+///         DuringReload::Latest,
+///         iced::time::every(Duration::from_millis(16)).map(Message::Tick),
+///     )
+/// }
+/// ```
+pub fn during_reload<T>(policy: DuringReload, subscription: Subscription<T>) -> Subscription<T>
+where
+    T: MaybeSend + 'static,
+{
+    if policy == DuringReload::Buffer {
+        return subscription;
+    }
+    Subscription::batch(
+        subscription::into_recipes(subscription)
+            .into_iter()
+            .map(|inner| subscription::from_recipe(Thinned { inner, policy })),
+    )
+}
+
+struct Thinned<T> {
+    inner: Box<dyn Recipe<Output = T>>,
+    policy: DuringReload,
+}
+
+impl<T: MaybeSend + 'static> Recipe for Thinned<T> {
+    type Output = T;
+
+    fn hash(&self, state: &mut Hasher) {
+        self.inner.hash(state);
+        self.policy.hash(state);
+    }
+
+    fn stream(self: Box<Self>, input: EventStream) -> BoxStream<T> {
+        iced_futures::boxed_stream(thinned(self.inner.stream(input), self.policy))
+    }
+}
+
+fn thinned<T, S>(inner: S, policy: DuringReload) -> impl Stream<Item = T>
+where
+    T: MaybeSend + 'static,
+    S: Stream<Item = T> + MaybeSend + Unpin + 'static,
+{
+    let mut inner: Fuse<S> = inner.fuse();
+    let mut held: Option<T> = None;
+    // Running while the next message may not be buffered yet.
+    let mut interval: Option<Pin<Box<futures_timer::Delay>>> = None;
+
+    stream::poll_fn(move |cx| {
+        if !Context::current().poll_reloading(cx.waker()) {
+            interval = None;
+            if let Some(message) = held.take() {
+                return Poll::Ready(Some(message));
+            }
+            return inner.poll_next_unpin(cx);
+        }
+
+        let period = match policy {
+            DuringReload::AtMostEvery(period) => Some(period),
+            _ => None,
+        };
+        loop {
+            match inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(message)) => {
+                    if let Some(period) = period
+                        && interval.is_none()
+                    {
+                        interval = Some(Box::pin(futures_timer::Delay::new(period)));
+                        return Poll::Ready(Some(message));
+                    }
+                    held = Some(message);
+                }
+                Poll::Ready(None) => return Poll::Ready(held.take()),
+                Poll::Pending => break,
+            }
+        }
+
+        let Some(period) = period else {
+            return Poll::Pending;
+        };
+        if let Some(delay) = &mut interval {
+            ready!(delay.poll_unpin(cx));
+            interval = None;
+        }
+        match held.take() {
+            Some(message) => {
+                interval = Some(Box::pin(futures_timer::Delay::new(period)));
+                Poll::Ready(Some(message))
+            }
+            None => Poll::Pending,
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Host side, called through the context's table
+// ---------------------------------------------------------------------------
+
+struct Reload {
+    running: bool,
+    /// Streams holding a message until the reload finished.
+    waiting: Vec<Waker>,
+}
+
+static RELOAD: Mutex<Reload> = Mutex::new(Reload {
+    running: false,
+    waiting: Vec::new(),
+});
+
+/// Called by the reloader when a reload starts and once it finished.
+pub(crate) fn set_reloading(running: bool) {
+    let waiting = {
+        let Ok(mut reload) = RELOAD.lock() else {
+            return;
+        };
+        reload.running = running;
+        if running {
+            return;
+        }
+        std::mem::take(&mut reload.waiting)
+    };
+    waiting.into_iter().for_each(Waker::wake);
+}
+
+/// Whether a reload is running, `waker` is woken once it finished.
+pub(crate) fn poll_reloading(waker: &Waker) -> bool {
+    let Ok(mut reload) = RELOAD.lock() else {
+        return false;
+    };
+    if reload.running && !reload.waiting.iter().any(|w| w.will_wake(waker)) {
+        reload.waiting.push(waker.clone());
+    }
+    reload.running
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+
+    #[test]
+    fn keeps_the_latest_message_during_a_reload() {
+        let (sender, receiver) = mpsc::unbounded();
+        let mut latest = Box::pin(thinned(receiver, DuringReload::Latest));
+        let mut every = Box::pin(thinned(
+            stream::iter([1, 2, 3]),
+            DuringReload::AtMostEvery(Duration::from_secs(60)),
+        ));
+
+        set_reloading(true);
+        for tick in 0..100 {
+            sender.unbounded_send(tick).unwrap();
+        }
+        assert!(latest.next().now_or_never().is_none());
+        // The first message is buffered, the stream ended before the next.
+        assert_eq!(block_on(every.next()), Some(1));
+        assert_eq!(block_on(every.next()), Some(3));
+
+        set_reloading(false);
+        assert_eq!(block_on(latest.next()), Some(99));
+        sender.unbounded_send(100).unwrap();
+        assert_eq!(block_on(latest.next()), Some(100));
+    }
+}
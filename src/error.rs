@@ -7,20 +7,42 @@ pub enum HotReloaderError {
     LibraryLoadError(#[from] libloading::Error),
     #[error("The hot reloadable library has not been loaded. Has it not been built yet?")]
     LibraryNotLoaded,
+    #[error("The library does not export {0}")]
+    SymbolNotFound(String),
+    #[error("The library {0:?} is not completely written: {1}")]
+    IncompleteArtifact(std::path::PathBuf, String),
+    #[cfg(feature = "signed-artifacts")]
+    #[error("Refusing to load {0:?}: {1}")]
+    UntrustedArtifact(
+        std::path::PathBuf,
+        crate::artifact_signature::SignatureError,
+    ),
 }
 
+/// Why a hot function could not be called or did not return, see
+/// [`ffi`](crate::ffi).
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum HotIceError {
+    /// No library is loaded.
     #[error("Could not find function library")]
     LibraryNotFound,
+    /// The library does not export the function of this name.
     #[error("Could not load function: {0}")]
     FunctionNotFound(&'static str),
+    /// The function panicked, with the panic's message and location.
     #[error("Hot function call panicked: {0}")]
-    FunctionPanicked(&'static str),
+    FunctionPanicked(std::sync::Arc<str>),
     #[error("Unable to acquire lock on reloader")]
     LockAcquisitionError,
+    /// A message was not of the type the function takes.
     #[error("Failed to downcast Message: {0}")]
     MessageDowncastError(String),
+    /// A message of an earlier definition of its type could not be
+    /// deserialized into the current one.
+    #[error("Failed to transfer Message to its edited type: {0}")]
+    MessageTransferError(String),
+    /// The state is not of the type the function takes.
     #[error("State type mismatch")]
     StateTypeMismatch,
     #[error("Failed to serialize state: {0}")]
@@ -29,8 +51,21 @@ pub enum HotIceError {
     FailedToDeserializeState(String),
     #[error("Failed to acquire lock on state")]
     StateLockAcquisitionError,
+    /// A serialized state failed its checks, the state is reset.
+    #[error("Corrupt state buffer: {0}")]
+    CorruptStateBuffer(String),
     #[error("Serialization not supported")]
     SerializationNotSupported,
+    #[error("The event loop is not running")]
+    EventLoopNotRunning,
+}
+
+/// A message of a variant marked `#[hot_message(skip)]`, which cannot be
+/// serialized to cross an edit of its type.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Message variant `{variant}` is not transferable")]
+pub struct NotTransferable {
+    pub variant: &'static str,
 }
 
 impl<T> From<std::sync::PoisonError<T>> for HotIceError {
@@ -39,10 +74,25 @@ impl<T> From<std::sync::PoisonError<T>> for HotIceError {
     }
 }
 
+/// What a function exported to the host returns, the result of
+/// [`ffi::guarded`](crate::ffi::guarded). A struct of its own so the host
+/// tells it apart from a plain return value of the same type.
 pub struct HotResult<T>(pub Result<T, HotIceError>);
 
+impl<T> HotResult<T> {
+    pub fn into_inner(self) -> Result<T, HotIceError> {
+        self.0
+    }
+}
+
 impl<T> From<Result<T, HotIceError>> for HotResult<T> {
     fn from(result: Result<T, HotIceError>) -> Self {
         HotResult(result)
     }
 }
+
+impl<T> From<HotResult<T>> for Result<T, HotIceError> {
+    fn from(result: HotResult<T>) -> Self {
+        result.0
+    }
+}
@@ -0,0 +1,244 @@
+//! The errors of hot functions shown by the reloader's overlay.
+//!
+//! Each error is a toast of its own, so an error that replaces another one
+//! of the same function no longer hides it. At most [`MAX_TOASTS`] are shown
+//! at once, each of them dismissed by hand or after a timeout. Every error
+//! is also kept in a history of the last [`MAX_HISTORY`] errors, which
+//! outlives reloads so errors of earlier saves can still be read.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use iced_core::{Animation, animation::Easing, time::Instant};
+use iced_winit::runtime::task;
+
+use crate::reloader::HotFunction;
+
+/// Toasts shown at once, older ones are dismissed for new ones.
+pub(crate) const MAX_TOASTS: usize = 5;
+
+/// Errors kept in the history.
+pub(crate) const MAX_HISTORY: usize = 100;
+
+/// An error on screen.
+pub(crate) struct ErrorToast {
+    pub(crate) id: u64,
+    pub(crate) function: HotFunction,
+    pub(crate) message: String,
+    /// Time since the reloader started when the error occurred.
+    pub(crate) at: Duration,
+    /// Auto-dismiss timer, running while the toast is shown collapsed.
+    pub(crate) handle: Option<task::Handle>,
+    pub(crate) sensor_key: u16,
+    pub(crate) expanded: bool,
+    pub(crate) animation: Animation<bool>,
+    pub(crate) dismissing: bool,
+}
+
+impl ErrorToast {
+    /// Fades the toast out, it is removed once the animation has finished.
+    pub(crate) fn dismiss(&mut self, now: Instant) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+        if !self.dismissing {
+            self.dismissing = true;
+            self.animation.go_mut(false, now);
+        }
+    }
+}
+
+/// An error of the history.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ErrorRecord {
+    pub(crate) function: HotFunction,
+    pub(crate) message: String,
+    /// Libraries loaded before the error occurred.
+    pub(crate) generation: u64,
+    /// Time since the reloader started when the error occurred.
+    pub(crate) at: Duration,
+}
+
+pub(crate) struct ErrorToasts {
+    /// Oldest first.
+    toasts: Vec<ErrorToast>,
+    /// Oldest first.
+    history: VecDeque<ErrorRecord>,
+    next_id: u64,
+    started: Instant,
+}
+
+impl ErrorToasts {
+    pub(crate) fn new() -> Self {
+        Self {
+            toasts: Vec::new(),
+            history: VecDeque::new(),
+            next_id: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Shows `message` as the error of `function`, unless its latest toast
+    /// shows it already. Returns whether the error is new.
+    pub(crate) fn report(
+        &mut self,
+        function: HotFunction,
+        message: &str,
+        generation: u64,
+        now: Instant,
+    ) -> bool {
+        let latest = self
+            .toasts
+            .iter_mut()
+            .rev()
+            .find(|toast| toast.function == function);
+        if let Some(toast) = latest.filter(|toast| toast.message == message) {
+            if toast.dismissing {
+                // Dismissed while the function still fails, show it again.
+                if let Some(handle) = toast.handle.take() {
+                    handle.abort();
+                }
+                toast.sensor_key = toast.sensor_key.wrapping_add(1);
+                toast.expanded = false;
+                toast.animation = Animation::new(false).quick().easing(Easing::EaseOut);
+                toast.animation.go_mut(true, now);
+                toast.dismissing = false;
+            }
+            return false;
+        }
+
+        let at = now.saturating_duration_since(self.started);
+        if self.history.len() == MAX_HISTORY {
+            let _ = self.history.pop_front();
+        }
+        self.history.push_back(ErrorRecord {
+            function,
+            message: message.to_string(),
+            generation,
+            at,
+        });
+
+        let mut animation = Animation::new(false).quick().easing(Easing::EaseOut);
+        animation.go_mut(true, now);
+        self.toasts.push(ErrorToast {
+            id: self.next_id,
+            function,
+            message: message.to_string(),
+            at,
+            handle: None,
+            sensor_key: 0,
+            expanded: false,
+            animation,
+            dismissing: false,
+        });
+        self.next_id += 1;
+
+        let shown = self.toasts.iter().filter(|toast| !toast.dismissing).count();
+        for toast in self
+            .toasts
+            .iter_mut()
+            .filter(|toast| !toast.dismissing)
+            .take(shown.saturating_sub(MAX_TOASTS))
+        {
+            toast.dismiss(now);
+        }
+
+        true
+    }
+
+    pub(crate) fn get_mut(&mut self, id: u64) -> Option<&mut ErrorToast> {
+        self.toasts.iter_mut().find(|toast| toast.id == id)
+    }
+
+    /// The toasts, oldest first.
+    pub(crate) fn toasts(&self) -> &[ErrorToast] {
+        &self.toasts
+    }
+
+    pub(crate) fn is_animating(&self, now: Instant) -> bool {
+        self.toasts
+            .iter()
+            .any(|toast| toast.animation.is_animating(now))
+    }
+
+    /// Removes the toasts that have faded out.
+    pub(crate) fn remove_dismissed(&mut self, now: Instant) {
+        self.toasts.retain_mut(|toast| {
+            let faded = toast.dismissing && !toast.animation.is_animating(now);
+            if faded && let Some(handle) = toast.handle.take() {
+                handle.abort();
+            }
+            !faded
+        });
+    }
+
+    /// Removes every toast, the history is kept.
+    pub(crate) fn clear_toasts(&mut self) {
+        for toast in self.toasts.drain(..) {
+            if let Some(handle) = toast.handle {
+                handle.abort();
+            }
+        }
+    }
+
+    /// The history, oldest first.
+    pub(crate) fn history(&self) -> &VecDeque<ErrorRecord> {
+        &self.history
+    }
+
+    pub(crate) fn clear_history(&mut self) {
+        self.history.clear();
+    }
+}
+
+/// Formats the time since the reloader started as `mm:ss`.
+pub(crate) fn format_time(at: Duration) -> String {
+    let seconds = at.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_stack_up_to_the_limit() {
+        let mut toasts = ErrorToasts::new();
+        let now = Instant::now();
+
+        assert!(toasts.report(HotFunction::Update, "first", 1, now));
+        assert!(!toasts.report(HotFunction::Update, "first", 1, now));
+        assert!(toasts.report(HotFunction::Update, "second", 2, now));
+        assert_eq!(toasts.toasts().len(), 2);
+
+        for i in 0..MAX_TOASTS {
+            toasts.report(HotFunction::View, &i.to_string(), 2, now);
+        }
+        let shown: Vec<_> = toasts
+            .toasts()
+            .iter()
+            .filter(|toast| !toast.dismissing)
+            .map(|toast| toast.message.as_str())
+            .collect();
+        assert_eq!(shown, ["0", "1", "2", "3", "4"]);
+
+        toasts.clear_toasts();
+        assert!(toasts.toasts().is_empty());
+        assert_eq!(toasts.history().len(), 2 + MAX_TOASTS);
+        assert_eq!(toasts.history()[1].message, "second");
+        assert_eq!(toasts.history()[1].generation, 2);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut toasts = ErrorToasts::new();
+        let now = Instant::now();
+        for i in 0..MAX_HISTORY + 1 {
+            toasts.report(HotFunction::Update, &i.to_string(), 0, now);
+        }
+        assert_eq!(toasts.history().len(), MAX_HISTORY);
+        assert_eq!(toasts.history()[0].message, "1");
+
+        assert_eq!(format_time(Duration::from_secs(754)), "12:34");
+    }
+}
@@ -23,27 +23,40 @@
 //!
 //! # How it works
 //!
-//! 1. On library load, the cdylib creates an executor and spawns a worker thread
-//! 2. The worker thread enters the executor's TLS context via `Executor::enter()`
-//! 3. The cdylib runs the type-erased polling loop (`erased_worker_loop`)
-//! 4. The main binary sends type-erased streams to the worker via a channel
+//! 1. On library load, the cdylib creates an executor and spawns the worker
+//!    threads, `ReloaderSettings::worker_threads` of them
+//! 2. Each worker thread enters the executor's TLS context via `Executor::enter()`
+//! 3. The cdylib runs a type-erased polling loop (`erased_worker_loop`) per thread
+//! 4. The main binary sends type-erased streams to the workers via a channel
+//!    per thread, round-robin
 //! 5. The worker polls streams; each action item is forwarded via a callback
-//!    that reconstructs `Action<M>` and calls `Proxy::send_action()`
+//!    that reconstructs `Action<M>` and hands it to a bounded `ActionQueue`,
+//!    which calls `Proxy::send_action()` as the event loop keeps up
 //! 6. Panics in user async code are caught by `catch_unwind` inside the cdylib
 //! 7. On library unload, a shutdown command stops the worker and the thread is joined
 
-use std::any::Any;
-use std::sync::{Arc, OnceLock};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::pin::{Pin, pin};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{self, Poll, Waker};
 
+use iced_runtime::Task;
+
+use crate::host_runtime::RuntimeHandleFfi;
+use crate::lent_state::LentState;
 use crate::lib_reloader::LibReloader;
+use crate::log_target::WORKER;
 use crate::winit::Proxy;
+use crate::worker_queue::{ActionQueue, WorkerQueue};
 
 /// Wrapper around `*mut ()` that implements `Send + Sync`.
 ///
 /// Used to pass raw callback pointers into async contexts.
 /// Safety: the pointed-to data must actually be safe to access from the
-/// worker thread (which it is — `CallbackContext<M>` is only accessed
-/// sequentially from the single worker thread).
+/// worker threads (which it is — `CallbackContext<M>` only holds a
+/// thread-safe `ActionQueue<M>`).
 #[derive(Clone, Copy)]
 struct SendPtr(*mut ());
 unsafe impl Send for SendPtr {}
@@ -70,21 +83,81 @@ pub fn get_global_proxy<M: Send + 'static>() -> Option<Proxy<M>> {
     GLOBAL_PROXY.get()?.downcast_ref::<Proxy<M>>().cloned()
 }
 
+/// Routes of programs whose message is not the one of the event loop, set
+/// by `HotDaemon` for each of its programs. Keyed by message type.
+static ACTION_ROUTES: Mutex<Option<HashMap<TypeId, Box<dyn Any + Send>>>> = Mutex::new(None);
+
+/// Sends the actions of one program to the event loop.
+///
+/// A single program owns the loop and sends through the global [`Proxy`].
+/// The programs of a daemon share it, their actions are wrapped into the
+/// daemon's message on the way.
+pub struct ActionSender<M> {
+    send: Arc<dyn Fn(Action<M>) + Send + Sync>,
+}
+
+impl<M: Send + 'static> ActionSender<M> {
+    pub(crate) fn new(send: impl Fn(Action<M>) + Send + Sync + 'static) -> Self {
+        Self {
+            send: Arc::new(send),
+        }
+    }
+
+    /// Sends an action to the event loop, bypassing backpressure like
+    /// [`Proxy::send_action`].
+    pub fn send_action(&self, action: Action<M>) {
+        (self.send)(action)
+    }
+}
+
+impl<M> Clone for ActionSender<M> {
+    fn clone(&self) -> Self {
+        Self {
+            send: Arc::clone(&self.send),
+        }
+    }
+}
+
+/// The sender of the program with message `M`, `None` until the event loop
+/// has started.
+pub fn get_action_sender<M: Send + 'static>() -> Option<ActionSender<M>> {
+    if let Some(proxy) = get_global_proxy::<M>() {
+        return Some(ActionSender::new(move |action| proxy.send_action(action)));
+    }
+
+    let routes = ACTION_ROUTES.lock().ok()?;
+    let route = routes.as_ref()?.get(&TypeId::of::<M>())?;
+    route.downcast_ref::<ActionSender<M>>().cloned()
+}
+
+/// Routes the actions of the program with message `M` through `send`.
+pub(crate) fn set_action_route<M: Send + 'static>(
+    send: impl Fn(Action<M>) + Send + Sync + 'static,
+) {
+    let Ok(mut routes) = ACTION_ROUTES.lock() else {
+        return;
+    };
+    let sender = ActionSender::new(send);
+    routes
+        .get_or_insert_with(HashMap::new)
+        .insert(TypeId::of::<M>(), Box::new(sender));
+}
+
 // ---------------------------------------------------------------------------
 // FFI function pointer types
 // ---------------------------------------------------------------------------
 
 pub mod ffi {
-    /// Starts a worker thread inside the cdylib.
+    /// Starts the worker threads inside the cdylib.
     ///
-    /// `ctx` is a `*mut ErasedWorkerContext` created by the main binary.
-    /// The cdylib takes ownership, spawns a thread with executor TLS,
-    /// and runs the polling loop.
+    /// `ctx` is a `*mut ErasedWorkerPool` created by the main binary.
+    /// The cdylib takes ownership, spawns a thread with executor TLS per
+    /// worker context, and runs the polling loop on each.
     ///
     /// Returns an opaque handle for `stop_worker`, or null on failure.
     pub type StartWorkerFn = unsafe fn(ctx: *mut ()) -> *mut ();
 
-    /// Stops the worker thread and joins it.
+    /// Stops the worker threads and joins them.
     pub type StopWorkerFn = unsafe fn(handle: *mut ());
 }
 
@@ -107,6 +180,10 @@ pub type ActionCallbackFn = unsafe fn(ctx: *mut (), action_ptr: *mut ());
 /// The main binary receives the panic message as a UTF-8 byte slice.
 pub type PanicCallbackFn = unsafe fn(ctx: *mut (), msg_ptr: *const u8, msg_len: usize);
 
+/// Called by each worker thread of the cdylib when it starts, with the id
+/// the operating system knows it by, 0 where it is not known.
+pub type ThreadStartedFn = unsafe fn(ctx: *mut (), thread_id: u64);
+
 /// Non-generic command sent over the channel from main binary to worker.
 pub enum ErasedWorkerCommand {
     /// Poll this type-erased stream to completion.
@@ -138,12 +215,24 @@ pub struct ErasedWorkerContext {
     pub callback_ctx: *mut (),
     pub action_callback: ActionCallbackFn,
     pub panic_callback: PanicCallbackFn,
+    pub thread_started: ThreadStartedFn,
 }
 
 // Safety: command_rx is Send, callback_ctx points to a Send type
 // (CallbackContext<M>), function pointers are inherently thread-safe.
 unsafe impl Send for ErasedWorkerContext {}
 
+/// The contexts of all worker threads of a library, one per thread.
+///
+/// The cdylib's `start_worker` function receives this as `*mut ()` and
+/// spawns a thread for each context, all entering the same executor.
+pub struct ErasedWorkerPool {
+    pub workers: Vec<ErasedWorkerContext>,
+    /// The host's tokio runtime, entered by the worker threads with the
+    /// `tokio` feature.
+    pub runtime: RuntimeHandleFfi,
+}
+
 // ---------------------------------------------------------------------------
 // Erased polling loop — compiled into the cdylib via hot_ice dependency
 // ---------------------------------------------------------------------------
@@ -161,8 +250,11 @@ pub fn erased_worker_loop(ctx: ErasedWorkerContext) {
         callback_ctx,
         action_callback,
         panic_callback,
+        thread_started,
     } = ctx;
 
+    unsafe { thread_started(callback_ctx, crate::stuck_worker::current_thread_id()) };
+
     futures::executor::block_on(erased_worker_loop_async(
         command_rx,
         callback_ctx,
@@ -200,7 +292,7 @@ async fn erased_worker_loop_async(
                     cmd = command_rx.select_next_some() => {
                         match cmd {
                             ErasedWorkerCommand::RunStream(stream) => {
-                                log::debug!("[worker] Received RunStream command");
+                                log::debug!(target: WORKER, "Received RunStream command");
                                 let cb_ctx = ctx;
                                 let action_cb = action_callback;
                                 let panic_cb = panic_callback;
@@ -216,7 +308,7 @@ async fn erased_worker_loop_async(
                                 ));
                             }
                             ErasedWorkerCommand::RunRecipeFactory { factory, cancel } => {
-                                log::debug!("[worker] Received RunRecipeFactory command");
+                                log::debug!(target: WORKER, "Received RunRecipeFactory command");
                                 let cb_ctx = ctx;
                                 let action_cb = action_callback;
                                 let panic_cb = panic_callback;
@@ -274,7 +366,7 @@ async fn erased_worker_loop_async(
             // machinery itself panics. Log the error before forgetting
             // the payload to avoid silently swallowing panics.
             let msg = extract_panic_message(&panic);
-            log::error!("[worker] outer loop caught panic: {}", msg);
+            log::error!(target: WORKER, "outer loop caught panic: {}", msg);
             // Forget the payload to avoid cross-cdylib drop issues.
             std::mem::forget(panic);
         }
@@ -305,22 +397,24 @@ async fn erased_drain_stream(
     action_callback: ActionCallbackFn,
 ) {
     use futures::StreamExt;
-    log::trace!("[worker] erased_drain_stream: starting to poll stream");
+    log::trace!(target: WORKER, "erased_drain_stream: starting to poll stream");
     futures::pin_mut!(stream);
     let mut count = 0u64;
     while let Some(action_ptr) = stream.next().await {
         count += 1;
-        log::trace!("[worker] erased_drain_stream: got item #{}", count);
+        log::trace!(target: WORKER, "erased_drain_stream: got item #{}", count);
         unsafe {
             action_callback(callback_ctx.0, action_ptr);
         }
         log::trace!(
-            "[worker] erased_drain_stream: action_callback returned for item #{}",
+            target: WORKER,
+            "erased_drain_stream: action_callback returned for item #{}",
             count
         );
     }
     log::trace!(
-        "[worker] erased_drain_stream: stream ended after {} items",
+        target: WORKER,
+        "erased_drain_stream: stream ended after {} items",
         count
     );
 }
@@ -342,7 +436,7 @@ async fn erased_drain_stream_cancelable(
     loop {
         futures::select! {
             _ = cancel => {
-                log::debug!("[worker] erased_drain_stream_cancelable: cancelled");
+                log::debug!(target: WORKER, "erased_drain_stream_cancelable: cancelled");
                 break;
             }
             item = stream.select_next_some() => {
@@ -362,12 +456,13 @@ async fn erased_drain_active(
     use futures::stream::StreamExt;
 
     if active.len() <= 1 {
-        log::info!("hot-ice drain: no active streams, exiting immediately");
+        log::info!(target: WORKER, "no active streams, exiting immediately");
         return;
     }
 
     log::info!(
-        "hot-ice drain: waiting for {} active stream(s) to complete (timeout: {:?})",
+        target: WORKER,
+        "waiting for {} active stream(s) to complete (timeout: {:?})",
         active.len() - 1,
         timeout,
     );
@@ -379,13 +474,14 @@ async fn erased_drain_active(
         futures::select! {
             _ = active.select_next_some() => {
                 if active.len() <= 1 {
-                    log::info!("hot-ice drain: all streams completed");
+                    log::info!(target: WORKER, "all streams completed");
                     return;
                 }
             }
             _ = deadline => {
                 log::warn!(
-                    "hot-ice drain: timeout after {:?}, dropping {} remaining stream(s)",
+                    target: WORKER,
+                    "timeout after {:?}, dropping {} remaining stream(s)",
                     timeout,
                     active.len() - 1,
                 );
@@ -414,10 +510,13 @@ fn extract_panic_message(err: &Box<dyn Any + Send>) -> &str {
 /// Holds the generic state that callbacks need.
 /// Allocated on the main binary's heap, passed to the cdylib as `*mut ()`.
 struct CallbackContext<M: Send + 'static> {
-    proxy: Proxy<M>,
+    queue: Arc<ActionQueue<M>>,
+    /// Operating system ids of the worker threads, see [`ThreadStartedFn`].
+    threads: Mutex<Vec<u64>>,
 }
 
-/// Reconstructs `Action<M>` from the opaque pointer and sends it via proxy.
+/// Reconstructs `Action<M>` from the opaque pointer and queues it for the
+/// event loop.
 ///
 /// # Safety
 ///
@@ -426,9 +525,21 @@ struct CallbackContext<M: Send + 'static> {
 unsafe fn action_callback_impl<M: Send + 'static>(ctx: *mut (), action_ptr: *mut ()) {
     let cb_ctx = unsafe { &*(ctx as *const CallbackContext<M>) };
     let action = unsafe { *Box::from_raw(action_ptr as *mut Action<M>) };
-    log::trace!("[worker] action_callback_impl: delivering action via proxy");
-    cb_ctx.proxy.send_action(action);
-    log::trace!("[worker] action_callback_impl: proxy.send_action returned");
+    log::trace!(target: WORKER, "action_callback_impl: queueing action");
+    cb_ctx.queue.push(action);
+    log::trace!(target: WORKER, "action_callback_impl: action queued");
+}
+
+/// Records the id of a worker thread that started.
+///
+/// # Safety
+///
+/// `ctx` must point to a valid `CallbackContext<M>`.
+unsafe fn thread_started_impl<M: Send + 'static>(ctx: *mut (), thread_id: u64) {
+    let cb_ctx = unsafe { &*(ctx as *const CallbackContext<M>) };
+    if let Ok(mut threads) = cb_ctx.threads.lock() {
+        threads.push(thread_id);
+    }
 }
 
 /// Receives a panic message from the cdylib and logs it.
@@ -448,21 +559,26 @@ unsafe fn panic_callback_impl<M: Send + 'static>(
         let bytes = unsafe { std::slice::from_raw_parts(msg_ptr, msg_len) };
         std::str::from_utf8(bytes).unwrap_or("invalid utf-8 in panic message")
     };
-    log::error!("hot-ice worker: stream panicked: {}", msg);
+    log::error!(target: WORKER, "stream panicked: {}", msg);
 }
 
 // ---------------------------------------------------------------------------
 // CdylibWorker — main binary's handle to the worker thread
 // ---------------------------------------------------------------------------
 
-/// Handle to a running worker thread inside a cdylib.
+/// Handle to the running worker threads inside a cdylib.
 ///
-/// Owns the command channel sender and the FFI handle for stopping.
-/// When dropped or shut down, the worker thread is joined.
+/// Owns the command channel senders and the FFI handle for stopping.
+/// When dropped or shut down, the worker threads are joined.
 pub struct CdylibWorker<M: Send + 'static> {
-    /// Send erased commands to the worker thread.
-    command_tx: fmpsc::UnboundedSender<ErasedWorkerCommand>,
-    /// FFI function to stop the worker (joins thread).
+    /// Send erased commands to the worker threads, one channel per thread.
+    command_tx: Vec<fmpsc::UnboundedSender<ErasedWorkerCommand>>,
+    /// The thread the next stream is sent to.
+    next_thread: AtomicUsize,
+    /// Delivers the actions of the worker's streams, also referenced by the
+    /// callback context.
+    queue: Arc<ActionQueue<M>>,
+    /// FFI function to stop the worker (joins the threads).
     stop_fn: ffi::StopWorkerFn,
     /// Opaque handle returned by `start_worker`, passed to `stop_worker`.
     worker_handle: *mut (),
@@ -483,36 +599,55 @@ impl<M: Send + 'static> CdylibWorker<M> {
     ///
     /// Loads the `start_worker` and `stop_worker` FFI symbols, creates the
     /// communication channels with type-erased protocol, and calls into
-    /// the cdylib to spawn the worker thread.
+    /// the cdylib to spawn `threads` worker threads. The actions of their
+    /// streams reach `sender` through a queue limited by `queue`.
     ///
     /// # Safety
     ///
     /// The library must export `start_worker_*` and `stop_worker_*` symbols
     /// (generated by `export_executor!`).
-    pub unsafe fn start(lib_reloader: &LibReloader, proxy: Proxy<M>) -> Result<Self, String> {
+    pub unsafe fn start(
+        lib_reloader: &LibReloader,
+        sender: ActionSender<M>,
+        queue: &WorkerQueue,
+        threads: usize,
+    ) -> Result<Self, String> {
         let start_fn: ffi::StartWorkerFn = unsafe {
-            *lib_reloader
-                .get_symbol(hot_ice_common::START_WORKER_FUNCTION_NAME.as_bytes())
+            lib_reloader
+                .get_symbol(hot_ice_common::START_WORKER_FUNCTION_NAME)
                 .map_err(|e| format!("Failed to get start_worker: {}", e))?
         };
         let stop_fn: ffi::StopWorkerFn = unsafe {
-            *lib_reloader
-                .get_symbol(hot_ice_common::STOP_WORKER_FUNCTION_NAME.as_bytes())
+            lib_reloader
+                .get_symbol(hot_ice_common::STOP_WORKER_FUNCTION_NAME)
                 .map_err(|e| format!("Failed to get stop_worker: {}", e))?
         };
 
-        let (command_tx, command_rx) = fmpsc::unbounded();
-
         // Allocate callback context on the heap
-        let cb_ctx = Box::new(CallbackContext { proxy });
+        let queue = ActionQueue::new(sender, queue);
+        let cb_ctx = Box::new(CallbackContext {
+            queue: Arc::clone(&queue),
+            threads: Mutex::new(Vec::new()),
+        });
         let callback_ctx_ptr = Box::into_raw(cb_ctx) as *mut ();
 
-        // Create the erased worker context
-        let ctx = Box::new(ErasedWorkerContext {
-            command_rx,
-            callback_ctx: callback_ctx_ptr,
-            action_callback: action_callback_impl::<M>,
-            panic_callback: panic_callback_impl::<M>,
+        // Create an erased worker context per thread
+        let (command_tx, workers) = (0..threads.max(1))
+            .map(|_| {
+                let (command_tx, command_rx) = fmpsc::unbounded();
+                let ctx = ErasedWorkerContext {
+                    command_rx,
+                    callback_ctx: callback_ctx_ptr,
+                    action_callback: action_callback_impl::<M>,
+                    panic_callback: panic_callback_impl::<M>,
+                    thread_started: thread_started_impl::<M>,
+                };
+                (command_tx, ctx)
+            })
+            .unzip();
+        let ctx = Box::new(ErasedWorkerPool {
+            workers,
+            runtime: RuntimeHandleFfi::host(),
         });
         let ctx_ptr = Box::into_raw(ctx) as *mut ();
 
@@ -521,7 +656,7 @@ impl<M: Send + 'static> CdylibWorker<M> {
         if worker_handle.is_null() {
             // Reclaim to avoid leaks
             unsafe {
-                let _ = Box::from_raw(ctx_ptr as *mut ErasedWorkerContext);
+                let _ = Box::from_raw(ctx_ptr as *mut ErasedWorkerPool);
                 let _ = Box::from_raw(callback_ctx_ptr as *mut CallbackContext<M>);
             }
             return Err("start_worker returned null".into());
@@ -529,6 +664,8 @@ impl<M: Send + 'static> CdylibWorker<M> {
 
         Ok(Self {
             command_tx,
+            next_thread: AtomicUsize::new(0),
+            queue,
             stop_fn,
             worker_handle,
             callback_ctx_ptr,
@@ -549,10 +686,14 @@ impl<M: Send + 'static> CdylibWorker<M> {
         cancel: oneshot::Receiver<()>,
     ) {
         if let Err(e) = self
-            .command_tx
+            .next_command_tx()
             .unbounded_send(ErasedWorkerCommand::RunRecipeFactory { factory, cancel })
         {
-            log::warn!("[worker] failed to send RunRecipeFactory: receiver dropped ({})", e);
+            log::warn!(
+                target: WORKER,
+                "failed to send RunRecipeFactory: receiver dropped ({})",
+                e
+            );
         }
     }
 
@@ -566,19 +707,26 @@ impl<M: Send + 'static> CdylibWorker<M> {
         let erased: ErasedStream =
             Box::pin(stream.map(|action| Box::into_raw(Box::new(action)) as *mut ()));
         let _ = self
-            .command_tx
+            .next_command_tx()
             .unbounded_send(ErasedWorkerCommand::RunStream(erased));
     }
 
-    /// Shuts down the worker thread.
+    /// The channel of the thread whose turn it is to take a stream.
+    fn next_command_tx(&self) -> &fmpsc::UnboundedSender<ErasedWorkerCommand> {
+        let next = self.next_thread.fetch_add(1, Ordering::Relaxed);
+        &self.command_tx[next % self.command_tx.len()]
+    }
+
+    /// Shuts down the worker threads.
     ///
     /// Sends a shutdown command, then calls the cdylib's `stop_worker` FFI
-    /// function which joins the thread. Finally frees the callback context.
+    /// function which joins the threads. Finally frees the callback context.
     pub fn shutdown(&mut self) {
-        let _ = self
-            .command_tx
-            .unbounded_send(ErasedWorkerCommand::Shutdown);
-        self.command_tx.close_channel();
+        for command_tx in &self.command_tx {
+            let _ = command_tx.unbounded_send(ErasedWorkerCommand::Shutdown);
+            command_tx.close_channel();
+        }
+        self.queue.close();
 
         if !self.worker_handle.is_null() {
             unsafe {
@@ -602,12 +750,14 @@ impl<M: Send + 'static> CdylibWorker<M> {
     /// Sends a `Drain` command with the given timeout, closes the channel,
     /// and returns a handle. The caller should call `DrainHandle::join()` on
     /// a background thread — it blocks until the worker exits (drain
-    /// completes or times out).
+    /// completes or times out). The queue no longer blocks the worker, the
+    /// event loop may be the one joining it.
     pub fn begin_drain(mut self, timeout: std::time::Duration) -> DrainHandle<M> {
-        let _ = self
-            .command_tx
-            .unbounded_send(ErasedWorkerCommand::Drain { timeout });
-        self.command_tx.close_channel();
+        for command_tx in &self.command_tx {
+            let _ = command_tx.unbounded_send(ErasedWorkerCommand::Drain { timeout });
+            command_tx.close_channel();
+        }
+        self.queue.close();
 
         let handle = DrainHandle {
             stop_fn: self.stop_fn,
@@ -619,6 +769,8 @@ impl<M: Send + 'static> CdylibWorker<M> {
         // Prevent Drop from calling shutdown
         self.worker_handle = std::ptr::null_mut();
         self.callback_ctx_ptr = std::ptr::null_mut();
+        // The callback context keeps the queue alive until the worker exited.
+        drop(unsafe { std::ptr::read(&self.queue) });
         std::mem::forget(self);
 
         handle
@@ -633,6 +785,54 @@ impl<M: Send + 'static> Drop for CdylibWorker<M> {
     }
 }
 
+/// Checks that the cdylib's worker runs in the executor `E` of the
+/// application builder. A library exporting no executor passes, it starts no
+/// worker.
+pub(crate) fn check_executor<E: iced_futures::Executor>(lib: &LibReloader) -> Result<(), String> {
+    let Ok(executor_type) = (unsafe {
+        lib.get_symbol::<fn() -> &'static str>(hot_ice_common::EXECUTOR_TYPE_FUNCTION_NAME)
+    }) else {
+        return Ok(());
+    };
+
+    match executor_mismatch(std::any::type_name::<E>(), executor_type()) {
+        Some(mismatch) => Err(mismatch),
+        None => Ok(()),
+    }
+}
+
+/// Tells how to fix a library whose worker runs in `library` while the
+/// builder runs `host`, `None` if they agree.
+fn executor_mismatch(host: &str, library: &str) -> Option<String> {
+    // With the `tokio` feature the default export enters the host's runtime
+    // instead of building the default executor.
+    let default_host = std::any::type_name::<iced_futures::backend::default::Executor>();
+    let default_library = std::any::type_name::<DefaultExecutor>();
+    if host == library || (host == default_host && library == default_library) {
+        return None;
+    }
+
+    Some(if library == default_library {
+        format!(
+            "the application builder runs the executor `{host}`, but the library exports \
+             the default one: write `#[hot_fn(executor = {host})]` on `update`, or invoke \
+             `hot_ice::export_executor!({host})` if `update` is not a hot function"
+        )
+    } else if host == default_host {
+        format!(
+            "the library exports the executor `{library}`, but the application builder \
+             runs the default one: call `.executor::<{library}>()` on the builder, or \
+             remove `executor = ..` from `#[hot_fn]` on `update`"
+        )
+    } else {
+        format!(
+            "the application builder runs the executor `{host}`, but the library exports \
+             `{library}`: name `{host}` in `#[hot_fn(executor = ..)]` on `update`, or in \
+             `hot_ice::export_executor!(..)`"
+        )
+    })
+}
+
 // ---------------------------------------------------------------------------
 // DrainHandle — background cleanup handle for a draining worker
 // ---------------------------------------------------------------------------
@@ -656,12 +856,12 @@ impl<M: Send + 'static> DrainHandle<M> {
     /// Blocks until the worker thread exits (drain completes or times out).
     pub fn join(mut self) {
         if !self.worker_handle.is_null() {
-            log::info!("hot-ice drain: joining old worker thread");
+            log::info!(target: WORKER, "joining old worker thread");
             unsafe {
                 (self.stop_fn)(self.worker_handle);
             }
             self.worker_handle = std::ptr::null_mut();
-            log::info!("hot-ice drain: old worker thread joined");
+            log::info!(target: WORKER, "old worker thread joined");
         }
         if !self.callback_ctx_ptr.is_null() {
             unsafe {
@@ -670,12 +870,51 @@ impl<M: Send + 'static> DrainHandle<M> {
             self.callback_ctx_ptr = std::ptr::null_mut();
         }
     }
+
+    /// Joins the worker on a supervisor thread, waiting at most `timeout`
+    /// for it. A worker whose stream blocks without reaching an await point
+    /// never sees the drain, it is left to the supervisor and reported as a
+    /// [`StuckWorker`]. Its library must not be unloaded then.
+    pub fn join_within(self, timeout: std::time::Duration) -> Result<(), StuckWorker> {
+        let threads = self.thread_ids();
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let supervisor = std::thread::Builder::new()
+            .name("hot-ice-worker-supervisor".into())
+            .spawn(move || {
+                self.join();
+                let _ = done_tx.send(());
+            });
+        if let Err(err) = supervisor {
+            log::warn!(target: WORKER, "Cannot supervise the worker, not waiting for it: {err}");
+            return Ok(());
+        }
+
+        match done_rx.recv_timeout(timeout) {
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                Err(StuckWorker::inspect(&threads, timeout))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The ids of the worker threads that started.
+    fn thread_ids(&self) -> Vec<u64> {
+        if self.callback_ctx_ptr.is_null() {
+            return Vec::new();
+        }
+        let cb_ctx = unsafe { &*(self.callback_ctx_ptr as *const CallbackContext<M>) };
+        cb_ctx
+            .threads
+            .lock()
+            .map(|threads| threads.clone())
+            .unwrap_or_default()
+    }
 }
 
 impl<M: Send + 'static> Drop for DrainHandle<M> {
     fn drop(&mut self) {
         if !self.worker_handle.is_null() {
-            log::warn!("hot-ice drain: DrainHandle dropped without join(), joining now");
+            log::warn!(target: WORKER, "DrainHandle dropped without join(), joining now");
             unsafe {
                 (self.stop_fn)(self.worker_handle);
             }
@@ -694,72 +933,258 @@ impl<M: Send + 'static> Drop for DrainHandle<M> {
 // Cdylib-side: export_executor! macro
 // ============================================================================
 
+#[cfg(feature = "tokio")]
+pub use crate::host_runtime::HostRuntime;
+
+pub use crate::stuck_worker::StuckWorker;
+
+/// The executor `export_executor!()` runs the worker threads in: the host's
+/// tokio runtime with the `tokio` feature, iced's default executor otherwise.
+#[cfg(feature = "tokio")]
+pub type DefaultExecutor = HostRuntime;
+
+/// The executor `export_executor!()` runs the worker threads in: the host's
+/// tokio runtime with the `tokio` feature, iced's default executor otherwise.
+#[cfg(not(feature = "tokio"))]
+pub type DefaultExecutor = iced_futures::backend::default::Executor;
+
+#[doc(hidden)]
+pub use crate::host_runtime::install as install_host_runtime;
+
+/// Enters the executor of this library's worker threads, set by
+/// `export_executor!` once it created it.
+type EnterExecutor = Box<dyn Fn(&mut dyn FnMut()) + Send + Sync>;
+
+static UPDATE_EXECUTOR: OnceLock<EnterExecutor> = OnceLock::new();
+
+/// Lets [`spawn_update`] run futures in `executor`.
+#[doc(hidden)]
+pub fn set_update_executor<E>(executor: Arc<E>)
+where
+    E: iced_futures::Executor + Send + Sync + 'static,
+{
+    let _ = UPDATE_EXECUTOR.set(Box::new(move |f: &mut dyn FnMut()| executor.enter(f)));
+}
+
+/// Entered by [`spawn_update`] while no worker threads run, e.g. without
+/// the `worker` feature or in a plain iced build.
+static FALLBACK_EXECUTOR: OnceLock<Option<EnterExecutor>> = OnceLock::new();
+
+fn fallback_executor() -> Option<&'static EnterExecutor> {
+    FALLBACK_EXECUTOR
+        .get_or_init(|| {
+            use iced_futures::Executor;
+
+            match DefaultExecutor::new() {
+                Ok(executor) => {
+                    let executor = Arc::new(executor);
+                    let enter: EnterExecutor =
+                        Box::new(move |f: &mut dyn FnMut()| executor.enter(f));
+                    Some(enter)
+                }
+                Err(err) => {
+                    log::error!(target: WORKER, "Cannot create an executor for async update: {}", err);
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
+/// Runs the future of an `async fn update` inside the executor the
+/// library's worker threads run in, so that the runtime's timers, IO and
+/// `spawn` are available to it. Before the worker threads started, or
+/// without them, the future runs inside a [`DefaultExecutor`] of its own.
+///
+/// The future is polled once right away, an update that finishes without
+/// waiting returns its task like a plain `update`. Otherwise the future
+/// becomes a task of its own, run on the worker like the tasks `update`
+/// returns, and the task it finishes with runs after it. Until then it
+/// keeps borrowing the state, and the reloader calls none of the program's
+/// functions, buffers its messages and holds reloads back.
+pub fn spawn_update<'a, T>(future: impl Future<Output = Task<T>> + Send + 'a) -> Task<T>
+where
+    T: Send + 'static,
+{
+    let mut future: Pin<Box<dyn Future<Output = Task<T>> + Send + 'a>> = Box::pin(future);
+    let mut first_poll = Poll::Pending;
+    enter_update_executor(&mut || {
+        if first_poll.is_pending() {
+            first_poll = future
+                .as_mut()
+                .poll(&mut task::Context::from_waker(Waker::noop()));
+        }
+    });
+    if let Poll::Ready(task) = first_poll {
+        return task;
+    }
+
+    // SAFETY: the future borrows the state and the arguments of `update`.
+    // While the `LentState` next to it is alive the reloader neither touches
+    // nor drops the state and keeps the library loaded, it is dropped right
+    // after the future.
+    let future: Pin<Box<dyn Future<Output = Task<T>> + Send + 'static>> =
+        unsafe { std::mem::transmute(future) };
+    Task::future(LentUpdate {
+        future,
+        _lent: LentState::new(),
+    })
+    .then(|task| task)
+}
+
+/// The future of an async update running on the worker, see
+/// [`spawn_update`]. Fields drop in order, the state is returned after the
+/// future let go of it.
+struct LentUpdate<T> {
+    future: Pin<Box<dyn Future<Output = Task<T>> + Send>>,
+    _lent: LentState,
+}
+
+impl<T> Future for LentUpdate<T> {
+    type Output = Task<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Task<T>> {
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Runs the future of an `async fn update` to completion on the calling
+/// thread, inside the executor [`spawn_update`] uses. Only plain iced calls
+/// it, a build without the feature has no reloader to lend the state to, so
+/// the event loop waits until the update is done.
+pub fn block_on_update<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut output = None;
+    enter_update_executor(&mut || {
+        if output.is_none() {
+            output = Some(futures::executor::block_on(future.as_mut()));
+        }
+    });
+    match output {
+        Some(output) => output,
+        // The executor did not call back, the future runs without it.
+        None => futures::executor::block_on(future),
+    }
+}
+
+/// Calls `f` inside the executor of the library's worker threads, or the
+/// fallback one if they did not start.
+fn enter_update_executor(f: &mut dyn FnMut()) {
+    match UPDATE_EXECUTOR.get().or_else(fallback_executor) {
+        Some(enter) => enter(f),
+        None => f(),
+    }
+}
+
 /// Generates the `#[unsafe(no_mangle)]` FFI functions that the main binary
 /// loads from the cdylib to start and stop a worker thread with the correct
 /// executor TLS context.
 ///
-/// The default invocation uses `iced_futures::backend::default::Executor`,
-/// which matches whatever the user has enabled via cargo features
-/// (tokio, smol, thread-pool, etc.).
+/// The default invocation uses [`DefaultExecutor`]. With the `tokio` feature
+/// that is the host's tokio runtime, otherwise
+/// `iced_futures::backend::default::Executor`, which matches whatever the user
+/// has enabled via cargo features (smol, thread-pool, etc.).
 ///
 /// # Custom executor
 ///
 /// If the user calls `.executor::<MyExecutor>()` on the application builder,
-/// they must also invoke this macro with the same type in their cdylib crate:
+/// the cdylib must export the same type. `#[hot_fn]` on `update` exports the
+/// default executor, name the custom one there instead:
+///
+/// ```rust,ignore
+/// #[hot_ice::hot_fn(executor = MyExecutor)]
+/// pub fn update(&mut self, message: Message) -> Task<Message> { .. }
+/// ```
+///
+/// A library without a hot `update` invokes this macro itself:
 ///
 /// ```rust,ignore
 /// hot_ice::export_executor!(MyExecutor);
 /// ```
+///
+/// The host refuses to call into a library exporting another executor than
+/// the builder's, see [`check_executor`].
 #[macro_export]
 macro_rules! export_executor {
     () => {
-        $crate::export_executor!($crate::macro_use::iced_futures::backend::default::Executor);
+        $crate::export_executor!($crate::executor::DefaultExecutor);
     };
     ($executor_ty:ty) => {
-        /// Starts a worker thread inside this cdylib.
+        /// Starts the worker threads inside this cdylib.
         ///
-        /// Receives an `ErasedWorkerContext` as `*mut ()`, creates an executor,
-        /// spawns a thread that enters the executor's TLS context, and runs
-        /// the type-erased polling loop.
+        /// Receives an `ErasedWorkerPool` as `*mut ()`, creates an executor,
+        /// spawns a thread per worker context that enters the executor's TLS
+        /// context, and runs the type-erased polling loop on each.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
         #[unsafe(no_mangle)]
         pub unsafe fn start_worker_lskdjfa3lkfjasdf(ctx_ptr: *mut ()) -> *mut () {
+            // The pool stays owned by the host until the executor is running.
+            let pool_ref = unsafe { &*(ctx_ptr as *const $crate::executor::ErasedWorkerPool) };
+            if let Err(err) = $crate::executor::install_host_runtime(pool_ref) {
+                $crate::macro_use::log::error!(target: "hot_ice::worker", "Cannot use the host's runtime: {}", err);
+                return ::std::ptr::null_mut();
+            }
+
             let executor = match <$executor_ty as $crate::macro_use::iced_futures::Executor>::new()
             {
                 Ok(e) => e,
                 Err(err) => {
-                    ::std::eprintln!("hot_ice: failed to create executor in cdylib: {}", err);
+                    $crate::macro_use::log::error!(
+                        target: "hot_ice::worker",
+                        "Failed to create executor in cdylib: {}",
+                        err
+                    );
                     return ::std::ptr::null_mut();
                 }
             };
 
             let executor = ::std::sync::Arc::new(executor);
-            let exec_for_thread = executor.clone();
+            $crate::executor::set_update_executor(::std::sync::Arc::clone(&executor));
 
-            // Reconstruct the Box<ErasedWorkerContext> from the raw pointer.
-            // ErasedWorkerContext is Send (unsafe impl), so the Box can
+            // Reconstruct the Box<ErasedWorkerPool> from the raw pointer.
+            // ErasedWorkerContext is Send (unsafe impl), so each context can
             // cross the thread::spawn boundary without a wrapper.
-            let ctx_box: ::std::boxed::Box<$crate::executor::ErasedWorkerContext> = unsafe {
-                ::std::boxed::Box::from_raw(ctx_ptr as *mut $crate::executor::ErasedWorkerContext)
+            let pool: ::std::boxed::Box<$crate::executor::ErasedWorkerPool> = unsafe {
+                ::std::boxed::Box::from_raw(ctx_ptr as *mut $crate::executor::ErasedWorkerPool)
             };
 
-            let join_handle = ::std::thread::Builder::new()
-                .name("hot-ice-worker".into())
-                .spawn(move || {
-                    let worker_ctx = *ctx_box;
-                    <$executor_ty as $crate::macro_use::iced_futures::Executor>::enter(
-                        &exec_for_thread,
-                        move || {
-                            $crate::executor::erased_worker_loop(worker_ctx);
-                        },
-                    );
+            let join_handles: ::std::vec::Vec<::std::thread::JoinHandle<()>> = pool
+                .workers
+                .into_iter()
+                .enumerate()
+                .map(|(index, worker_ctx)| {
+                    let exec_for_thread = executor.clone();
+                    ::std::thread::Builder::new()
+                        .name(::std::format!("hot-ice-worker-{index}"))
+                        .spawn(move || {
+                            <$executor_ty as $crate::macro_use::iced_futures::Executor>::enter(
+                                &exec_for_thread,
+                                move || {
+                                    $crate::executor::erased_worker_loop(worker_ctx);
+                                },
+                            );
+                        })
+                        .expect("hot_ice: failed to spawn cdylib worker thread")
                 })
-                .expect("hot_ice: failed to spawn cdylib worker thread");
+                .collect();
 
-            let handle = ::std::boxed::Box::new((join_handle, executor));
+            let handle = ::std::boxed::Box::new((join_handles, executor));
             ::std::boxed::Box::into_raw(handle) as *mut ()
         }
 
-        /// Stops the worker thread by joining it.
+        /// Names the executor the worker threads run in, the host checks it
+        /// against the executor of the application builder.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn executor_type_lskdjfa3lkfjasdf() -> &'static str {
+            ::core::any::type_name::<$executor_ty>()
+        }
+
+        /// Stops the worker threads by joining them.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
         #[unsafe(no_mangle)]
         pub unsafe fn stop_worker_lskdjfa3lkfjasdf(handle: *mut ()) {
             if handle.is_null() {
@@ -769,14 +1194,16 @@ macro_rules! export_executor {
                 ::std::boxed::Box::from_raw(
                     handle
                         as *mut (
-                            ::std::thread::JoinHandle<()>,
+                            ::std::vec::Vec<::std::thread::JoinHandle<()>>,
                             ::std::sync::Arc<$executor_ty>,
                         ),
                 )
             };
-            let (join_handle, _executor) = *handle;
-            if let Err(err) = join_handle.join() {
-                ::std::eprintln!("hot_ice: worker thread panicked: {:?}", err);
+            let (join_handles, _executor) = *handle;
+            for join_handle in join_handles {
+                if let Err(err) = join_handle.join() {
+                    $crate::macro_use::log::error!(target: "hot_ice::worker", "Worker thread panicked: {:?}", err);
+                }
             }
         }
     };
@@ -786,6 +1213,56 @@ macro_rules! export_executor {
 mod tests {
     use super::*;
 
+    #[test]
+    fn async_updates_run_to_completion() {
+        let mut count = 0;
+        let doubled = block_on_update(async {
+            count += 1;
+            futures::future::ready(count * 2).await
+        });
+        assert_eq!((count, doubled), (1, 2));
+    }
+
+    #[test]
+    fn pending_updates_lend_the_state_until_they_finish() {
+        use futures::executor::block_on_stream;
+        use iced_runtime::task::into_stream;
+
+        use crate::lent_state;
+
+        let outputs = |task: Task<u32>| -> Vec<u32> {
+            block_on_stream(into_stream(task).unwrap())
+                .filter_map(|action| match action {
+                    Action::Output(output) => Some(output),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let ready = spawn_update(async { Task::done(1) });
+        assert!(!lent_state::is_lent());
+        assert_eq!(outputs(ready), [1]);
+
+        let mut count = 0;
+        let (tx, rx) = oneshot::channel();
+        let pending = spawn_update(async {
+            count += rx.await.unwrap();
+            Task::done(count * 2)
+        });
+        assert!(lent_state::is_lent());
+        tx.send(2).unwrap();
+        assert_eq!(outputs(pending), [4]);
+        assert!(!lent_state::is_lent());
+        assert_eq!(count, 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_update_runs_inside_a_runtime_without_workers() {
+        let in_runtime = block_on_update(async { tokio::runtime::Handle::try_current().is_ok() });
+        assert!(in_runtime);
+    }
+
     #[test]
     fn extract_panic_message_from_string() {
         let payload: Box<dyn Any + Send> = Box::new(String::from("string panic"));
@@ -803,4 +1280,64 @@ mod tests {
         let payload: Box<dyn Any + Send> = Box::new(42i32);
         assert_eq!(extract_panic_message(&payload), "unknown panic");
     }
+
+    #[test]
+    fn actions_follow_the_route_of_their_message() {
+        struct Routed(u32);
+
+        assert!(get_action_sender::<Routed>().is_none());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        set_action_route::<Routed>(move |action| {
+            if let Action::Output(Routed(value)) = action {
+                let _ = tx.send(value);
+            }
+        });
+        get_action_sender::<Routed>()
+            .unwrap()
+            .send_action(Action::Output(Routed(7)));
+        assert_eq!(rx.try_recv(), Ok(7));
+    }
+
+    #[test]
+    fn executor_mismatch_names_the_macro_to_fix() {
+        struct MyExecutor;
+
+        let default_host = std::any::type_name::<iced_futures::backend::default::Executor>();
+        let default_library = std::any::type_name::<DefaultExecutor>();
+        let custom = std::any::type_name::<MyExecutor>();
+
+        assert_eq!(executor_mismatch(default_host, default_library), None);
+        assert_eq!(executor_mismatch(custom, custom), None);
+        assert!(
+            executor_mismatch(custom, default_library)
+                .unwrap()
+                .contains(&format!("#[hot_fn(executor = {custom})]"))
+        );
+        assert!(
+            executor_mismatch(default_host, custom)
+                .unwrap()
+                .contains(&format!(".executor::<{custom}>()"))
+        );
+    }
+
+    #[test]
+    fn a_worker_that_does_not_stop_is_left_to_the_supervisor() {
+        unsafe fn blocked_stop(_handle: *mut ()) {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+        }
+
+        let drain = DrainHandle::<()> {
+            stop_fn: blocked_stop,
+            worker_handle: std::ptr::dangling_mut(),
+            callback_ctx_ptr: std::ptr::null_mut(),
+            _marker: std::marker::PhantomData,
+        };
+        let started = std::time::Instant::now();
+        let stuck = drain
+            .join_within(std::time::Duration::from_millis(20))
+            .unwrap_err();
+        assert!(started.elapsed() < std::time::Duration::from_millis(400));
+        assert!(stuck.to_string().contains("did not stop within 20ms"));
+    }
 }
@@ -0,0 +1,97 @@
+//! Sending messages into the application from outside of iced.
+//!
+//! [`ExternalHandle`] wraps the event loop proxy that the runtime stores
+//! globally on startup, so code running on another runtime or thread can
+//! feed messages to `update` without access to the internal [`Proxy`].
+//!
+//! [`Proxy`]: crate::winit::Proxy
+
+use std::fmt;
+
+use iced_runtime::Action;
+
+use crate::error::HotIceError;
+use crate::executor::get_action_sender;
+use crate::hot_program::HotProgram;
+use crate::message::MessageSource;
+use crate::reloader::Message;
+
+/// A cloneable handle for sending messages to a running application from
+/// outside of iced, e.g. from another async runtime.
+///
+/// Obtained from [`HotIce::external_handle`](crate::hot_application::HotIce::external_handle).
+/// Messages are delivered to `update` like the output of a task. They
+/// bypass the backpressure of the task queue, so avoid flooding the
+/// event loop.
+pub struct ExternalHandle<M> {
+    send: fn(Action<M>) -> Result<(), HotIceError>,
+}
+
+impl<M> ExternalHandle<M> {
+    pub(crate) fn new<P>() -> Self
+    where
+        P: HotProgram<Message = M> + 'static,
+    {
+        Self {
+            send: send_action::<P>,
+        }
+    }
+
+    /// Sends a message to the application.
+    ///
+    /// Fails with [`HotIceError::EventLoopNotRunning`] if the application
+    /// has not been started yet.
+    pub fn send(&self, message: M) -> Result<(), HotIceError> {
+        (self.send)(Action::Output(message))
+    }
+
+    /// Sends an [`Action`] to the runtime, e.g. a window or clipboard
+    /// action.
+    ///
+    /// Fails with [`HotIceError::EventLoopNotRunning`] if the application
+    /// has not been started yet.
+    pub fn send_action(&self, action: Action<M>) -> Result<(), HotIceError> {
+        (self.send)(action)
+    }
+}
+
+impl<M> Clone for ExternalHandle<M> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M> Copy for ExternalHandle<M> {}
+
+impl<M> fmt::Debug for ExternalHandle<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalHandle").finish_non_exhaustive()
+    }
+}
+
+fn send_action<P>(action: Action<P::Message>) -> Result<(), HotIceError>
+where
+    P: HotProgram + 'static,
+{
+    let sender = get_action_sender::<Message<P>>().ok_or(HotIceError::EventLoopNotRunning)?;
+    sender.send_action(map_action(action, |message| {
+        Message::AppMessage(MessageSource::Static(message))
+    }));
+    Ok(())
+}
+
+/// Maps the output of `action` with `f`, every other action is passed
+/// through unchanged.
+pub(crate) fn map_action<A, B>(action: Action<A>, f: impl FnOnce(A) -> B) -> Action<B> {
+    match action {
+        Action::Output(message) => Action::Output(f(message)),
+        Action::LoadFont { bytes, channel } => Action::LoadFont { bytes, channel },
+        Action::Widget(operation) => Action::Widget(operation),
+        Action::Clipboard(action) => Action::Clipboard(action),
+        Action::Window(action) => Action::Window(action),
+        Action::System(action) => Action::System(action),
+        Action::Image(action) => Action::Image(action),
+        Action::Reload => Action::Reload,
+        Action::Exit => Action::Exit,
+    }
+}
@@ -0,0 +1,46 @@
+//! Building blocks of the functions `#[hot_fn]` exports, for writing
+//! exported functions by hand.
+//!
+//! A function the host calls in the library must not unwind into the host
+//! and reports its failures as a [`HotIceError`]. The wrappers generated by
+//! the macros run the user's function in [`guarded`] and return its
+//! [`HotResult`], a hand-written export does the same:
+//!
+//! ```rust,ignore
+//! #[unsafe(no_mangle)]
+//! pub fn chart_view(state: &Chart) -> HotResult<Element<'_, Message>> {
+//!     hot_ice::ffi::guarded(|| state.view().into())
+//! }
+//! ```
+//!
+//! Unlike `hot_ice::macro_use`, which only serves the code the macros
+//! generate, the items of this module follow semver: they only change in a
+//! breaking way with a new major version. [`HotIceError`] is
+//! `#[non_exhaustive]`, new kinds of failures are added as new variants.
+
+pub use crate::error::{HotIceError, HotResult, NotTransferable};
+pub use crate::panic_hook::catch_panic;
+
+/// Runs `f`, catching a panic the way the wrappers of `#[hot_fn]` do: it is
+/// returned as [`HotIceError::FunctionPanicked`] with a copy of its message
+/// and location.
+pub fn guarded<T>(f: impl FnOnce() -> T) -> HotResult<T> {
+    HotResult(catch_panic(f).map_err(|err| HotIceError::FunctionPanicked(err.into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_errors() {
+        assert!(matches!(guarded(|| 3).into_inner(), Ok(3)));
+
+        let Err(HotIceError::FunctionPanicked(message)) =
+            guarded(|| panic!("chart has no axes")).into_inner()
+        else {
+            panic!("the panic was not caught");
+        };
+        assert!(message.ends_with("chart has no axes"), "got: {message}");
+    }
+}
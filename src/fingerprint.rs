@@ -0,0 +1,275 @@
+//! Build fingerprint compared between the host and the cdylib.
+//!
+//! Everything else crossing the hot boundary uses the Rust ABI, which is only
+//! stable for a single compiler and a single set of dependency versions. Both
+//! sides compile this module into their copy of `hot_ice`, and the cdylib
+//! exports its fingerprint through a C ABI function generated by
+//! [`export_fingerprint!`]. The host refuses to call into a library whose
+//! fingerprint differs from its own, or lays out the iced types passed between
+//! them differently. The panic strategy is part of the fingerprint too, a
+//! library built with `panic = "abort"` is loaded with a warning.
+
+use std::ffi::{CStr, CString, c_char};
+use std::sync::LazyLock;
+
+use hot_ice_common::FINGERPRINT_FUNCTION_NAME;
+
+use crate::lib_reloader::LibReloader;
+use crate::log_target::RELOAD;
+use crate::panic_hook::{self, PANIC_STRATEGY};
+
+/// Version of tokio whose runtime the libraries share with the host, see
+/// `src/host_runtime.rs`.
+#[cfg(feature = "tokio")]
+macro_rules! tokio_version {
+    () => {
+        env!("HOT_ICE_TOKIO_VERSION")
+    };
+}
+
+#[cfg(not(feature = "tokio"))]
+macro_rules! tokio_version {
+    () => {
+        "none"
+    };
+}
+
+#[cfg(panic = "unwind")]
+macro_rules! panic_strategy {
+    () => {
+        "unwind"
+    };
+}
+
+#[cfg(not(panic = "unwind"))]
+macro_rules! panic_strategy {
+    () => {
+        "abort"
+    };
+}
+
+/// `rustc`, `hot_ice`, iced and tokio versions, the panic strategy and the
+/// [`layout`] of this build, separated by newlines.
+#[doc(hidden)]
+pub static BUILD_FINGERPRINT: LazyLock<CString> = LazyLock::new(|| {
+    let versions = concat!(
+        env!("HOT_ICE_RUSTC_VERSION"),
+        "\n",
+        env!("CARGO_PKG_VERSION"),
+        "\n",
+        env!("HOT_ICE_ICED_VERSION"),
+        "\n",
+        tokio_version!(),
+        "\n",
+        panic_strategy!(),
+    );
+    CString::new(format!("{versions}\n{}", layout()))
+        .expect("build fingerprint contains a NUL byte")
+});
+
+/// Size and alignment of the iced types passed between the host and the
+/// library, as `Name size/align` separated by `, `. Catches a library built
+/// against another iced than the host even if the versions above match, e.g.
+/// after a `cargo update` in one of the crates.
+fn layout() -> String {
+    fn entry<T>(name: &str) -> String {
+        format!("{name} {}/{}", size_of::<T>(), align_of::<T>())
+    }
+
+    [
+        entry::<iced::Element<'static, ()>>("Element"),
+        entry::<iced::Task<()>>("Task"),
+        entry::<iced::Subscription<()>>("Subscription"),
+        entry::<iced::Theme>("Theme"),
+        entry::<iced::Event>("Event"),
+        entry::<iced::Size>("Size"),
+        entry::<iced::Color>("Color"),
+        entry::<iced::Font>("Font"),
+        entry::<iced::window::Id>("window::Id"),
+    ]
+    .join(", ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fingerprint {
+    rustc: String,
+    hot_ice: String,
+    iced: String,
+    tokio: String,
+    /// Empty for libraries built before it was part of the fingerprint.
+    panic: String,
+    /// Empty for libraries built before it was part of the fingerprint.
+    layout: String,
+}
+
+impl Fingerprint {
+    fn parse(fingerprint: &CStr) -> Self {
+        let fingerprint = fingerprint.to_string_lossy();
+        let mut parts = fingerprint.split('\n').map(str::to_string);
+
+        Self {
+            rustc: parts.next().unwrap_or_default(),
+            hot_ice: parts.next().unwrap_or_default(),
+            iced: parts.next().unwrap_or_default(),
+            tokio: parts.next().unwrap_or_default(),
+            panic: parts.next().unwrap_or_default(),
+            layout: parts.next().unwrap_or_default(),
+        }
+    }
+
+    /// Describes every component of `library` that differs from `self`.
+    fn mismatch(&self, library: &Fingerprint) -> Option<String> {
+        let mismatches: Vec<String> = [
+            ("rustc", &self.rustc, &library.rustc),
+            ("hot_ice", &self.hot_ice, &library.hot_ice),
+            ("iced", &self.iced, &library.iced),
+            ("tokio", &self.tokio, &library.tokio),
+        ]
+        .into_iter()
+        .filter(|(_, host, library)| host != library)
+        .map(|(name, host, library)| format!("{name}: host {host}, library {library}"))
+        .chain(self.layout_mismatch(library))
+        .collect();
+
+        (!mismatches.is_empty()).then(|| mismatches.join("; "))
+    }
+
+    /// Describes every type whose layout differs between `self` and
+    /// `library`.
+    fn layout_mismatch(&self, library: &Fingerprint) -> Vec<String> {
+        if library.layout.is_empty() {
+            return Vec::new();
+        }
+        let entries = |layout: &str| -> Vec<(String, String)> {
+            layout
+                .split(", ")
+                .filter_map(|entry| entry.rsplit_once(' '))
+                .map(|(name, layout)| (name.to_string(), layout.to_string()))
+                .collect()
+        };
+        let library = entries(&library.layout);
+
+        entries(&self.layout)
+            .into_iter()
+            .filter_map(|(name, host)| {
+                let library = library
+                    .iter()
+                    .find(|(other, _)| *other == name)
+                    .map_or("missing", |(_, layout)| layout.as_str());
+                (host != library)
+                    .then(|| format!("layout of {name}: host {host}, library {library}"))
+            })
+            .collect()
+    }
+}
+
+/// Checks that the loaded library was built with the same rustc, `hot_ice`,
+/// iced and tokio versions as the host, and agrees on the layout of the iced
+/// types passed between them.
+///
+/// Libraries that do not export a fingerprint are accepted with a warning.
+pub(crate) fn check_library(lib: &LibReloader) -> Result<(), String> {
+    let Ok(fingerprint_fn) = (unsafe {
+        lib.get_symbol::<unsafe extern "C" fn() -> *const c_char>(FINGERPRINT_FUNCTION_NAME)
+    }) else {
+        log::warn!(
+            target: RELOAD,
+            "Library does not export a build fingerprint, skipping version check"
+        );
+        return Ok(());
+    };
+
+    let library = unsafe {
+        let ptr = fingerprint_fn();
+        if ptr.is_null() {
+            return Err("library returned no build fingerprint".to_string());
+        }
+        Fingerprint::parse(CStr::from_ptr(ptr))
+    };
+
+    match Fingerprint::parse(&BUILD_FINGERPRINT).mismatch(&library) {
+        Some(mismatch) => Err(mismatch),
+        None => Ok(()),
+    }
+}
+
+/// Warns if the loaded library was built with `panic = "abort"`, its panics
+/// can't be caught by the host.
+pub(crate) fn check_panic_strategy(lib: &LibReloader) {
+    let Ok(fingerprint_fn) = (unsafe {
+        lib.get_symbol::<unsafe extern "C" fn() -> *const c_char>(FINGERPRINT_FUNCTION_NAME)
+    }) else {
+        return;
+    };
+    let ptr = unsafe { fingerprint_fn() };
+    if ptr.is_null() {
+        return;
+    }
+    let library = Fingerprint::parse(unsafe { CStr::from_ptr(ptr) });
+    if let Some(diagnostic) = panic_hook::abort_diagnostic("library", &library.panic) {
+        log::warn!(target: RELOAD, "{diagnostic}");
+    }
+}
+
+/// Warns if the host was built with `panic = "abort"`, called once at
+/// startup.
+pub(crate) fn check_host_panic_strategy() {
+    if let Some(diagnostic) = panic_hook::abort_diagnostic("host", PANIC_STRATEGY) {
+        log::warn!(target: RELOAD, "{diagnostic}");
+    }
+}
+
+/// Exports the build fingerprint of the cdylib. `#[hot_fn]` emits this next
+/// to the `update` function.
+#[macro_export]
+macro_rules! export_fingerprint {
+    () => {
+        /// Returns the versions, panic strategy and iced type layout this library was built with.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn hot_ice_fingerprint_lskdjfa3lkfjasdf() -> *const ::std::ffi::c_char {
+            $crate::macro_use::BUILD_FINGERPRINT.as_ptr()
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_only_differing_components() {
+        let host = Fingerprint::parse(&BUILD_FINGERPRINT);
+        assert_eq!(host.hot_ice, env!("CARGO_PKG_VERSION"));
+        // Resolved from the lockfile by the build script.
+        assert_ne!(host.iced, "unknown");
+        #[cfg(feature = "tokio")]
+        assert!(host.tokio.starts_with("1."), "got: {}", host.tokio);
+        assert_eq!(host.mismatch(&host), None);
+
+        let library = Fingerprint {
+            rustc: "rustc 0.0.1".to_string(),
+            ..host.clone()
+        };
+        let mismatch = host.mismatch(&library).unwrap();
+        assert!(mismatch.starts_with("rustc: host "));
+        assert!(mismatch.ends_with("library rustc 0.0.1"));
+        assert!(!mismatch.contains("iced"));
+        assert_eq!(host.panic, PANIC_STRATEGY);
+
+        let library = Fingerprint {
+            layout: host.layout.replacen("Task ", "Task 1", 1),
+            ..host.clone()
+        };
+        let mismatch = host.mismatch(&library).unwrap();
+        assert!(mismatch.starts_with("layout of Task: host "));
+        assert!(!mismatch.contains("Element"));
+
+        let library = Fingerprint {
+            layout: String::new(),
+            ..host.clone()
+        };
+        assert_eq!(host.mismatch(&library), None);
+    }
+}
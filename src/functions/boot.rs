@@ -1,16 +1,19 @@
 use iced_winit::runtime::Task;
 
+use crate::context::Context;
+use crate::functions::hot_update::WithContext;
 use crate::message::DynMessage;
 
 /// The logic to initialize the `State` of some [`Application`].
 ///
 /// This trait is implemented for both `Fn() -> State` and
-/// `Fn() -> (State, Task<Message>)`.
+/// `Fn() -> (State, Task<Message>)`, with or without a [`Context`] as
+/// argument.
 ///
 /// In practice, this means that [`application`] can both take
 /// simple functions like `State::default` and more advanced ones
 /// that return a [`Task`].
-pub trait Boot<State, Message> {
+pub trait Boot<State, Message, Marker = ()> {
     /// Initializes the [`Application`] state.
     fn boot(&self) -> (State, Task<Message>);
 }
@@ -25,6 +28,16 @@ where
     }
 }
 
+impl<T, C, State, Message> Boot<State, Message, WithContext> for T
+where
+    T: Fn(&Context) -> C,
+    C: IntoBoot<State, Message>,
+{
+    fn boot(&self) -> (State, Task<Message>) {
+        self(Context::host()).into_boot()
+    }
+}
+
 /// The initial state of some [`Application`].
 pub trait IntoBoot<State, Message> {
     /// Turns some type into the initial state of some [`Application`].
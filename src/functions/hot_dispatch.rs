@@ -0,0 +1,345 @@
+//! Hot functions of kinds the application defines itself.
+//!
+//! The functions the builder takes are all dispatched the same way: the one
+//! the loaded library exports is called if it has it, the one linked into
+//! the binary otherwise, and the [`FunctionState`] tells which one ran.
+//! [`HotDispatch`] is that pattern for functions of any signature, a
+//! [`HotExtension`] calls one with the state of the running application. Its
+//! state is listed in the status panel and its failures are shown like the
+//! ones of `view` or `update`:
+//!
+//! ```rust,ignore
+//! // In the ui crate
+//! #[hot_fn]
+//! pub fn export_report(&self) -> Vec<u8> {
+//!     self.rows.iter().flat_map(Row::to_csv).collect()
+//! }
+//!
+//! // In the binary
+//! let export_report = HotExtension::new(State::export_report);
+//! let app = hot_ice::application(State::boot, State::update, State::view)
+//!     .extension(&export_report);
+//!
+//! std::thread::spawn(move || {
+//!     let report = futures::executor::block_on(export_report.call(()));
+//! });
+//! app.run()
+//! ```
+
+use std::any::{Any, TypeId, type_name};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use futures::channel::oneshot;
+use iced_runtime::Action;
+
+use crate::{
+    error::{HotIceError, HotResult},
+    executor::get_action_sender,
+    hot_program::HotProgram,
+    library_handle::LibraryHandle,
+    metrics, registry,
+    reloader::{FunctionState, HotFunction, Message},
+};
+
+/// Calls a hot function of the signature `fn(&State, Args...) -> Output`,
+/// either the one linked into the binary or the one of the library.
+///
+/// `Args` is the tuple of the arguments after the state. It is implemented
+/// for the wrappers `#[hot_fn]` generates, which return a [`HotResult`], with
+/// up to three arguments after the state.
+pub trait HotDispatch<State, Args, Output> {
+    fn call_static(&self, state: &State, args: Args) -> Result<Output, HotIceError>;
+
+    /// Calls the function of the library at `symbol`.
+    ///
+    /// # Safety
+    ///
+    /// `symbol` has to be a function of the signature
+    /// [`signature_hash`](Self::signature_hash) describes, in a library that
+    /// stays loaded during the call.
+    unsafe fn call_hot(
+        &self,
+        symbol: *const (),
+        state: &State,
+        args: Args,
+    ) -> Result<Output, HotIceError>;
+
+    /// The hash the companion symbol of the exported function reports, see
+    /// [`registry::type_hash`].
+    fn signature_hash(&self) -> u64;
+}
+
+macro_rules! impl_hot_dispatch {
+    ($($arg:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<F, State, $($arg,)* Output> HotDispatch<State, ($($arg,)*), Output> for F
+        where
+            F: Fn(&State, $($arg),*) -> HotResult<Output>,
+        {
+            fn call_static(
+                &self,
+                state: &State,
+                ($($arg,)*): ($($arg,)*),
+            ) -> Result<Output, HotIceError> {
+                (self)(state, $($arg),*).into_inner()
+            }
+
+            unsafe fn call_hot(
+                &self,
+                symbol: *const (),
+                state: &State,
+                ($($arg,)*): ($($arg,)*),
+            ) -> Result<Output, HotIceError> {
+                let function = unsafe {
+                    std::mem::transmute_copy::<*const (), fn(&State, $($arg),*) -> HotResult<Output>>(
+                        &symbol,
+                    )
+                };
+                function(state, $($arg),*).into_inner()
+            }
+
+            fn signature_hash(&self) -> u64 {
+                registry::type_hash::<(&State, $($arg,)* HotResult<Output>)>()
+            }
+        }
+    };
+}
+
+impl_hot_dispatch!();
+impl_hot_dispatch!(A);
+impl_hot_dispatch!(A, B);
+impl_hot_dispatch!(A, B, C);
+
+/// A hot function the application calls itself, e.g. from a menu of the
+/// binary or from another thread. Passed to the builder with
+/// [`HotIce::extension`](crate::hot_application::HotIce::extension).
+///
+/// Clones call the same function.
+pub struct HotExtension<State, Args, Output> {
+    function_name: &'static str,
+    function: Arc<dyn HotDispatch<State, Args, Output> + Send + Sync>,
+}
+
+impl<State, Args, Output> HotExtension<State, Args, Output>
+where
+    State: 'static,
+    Args: Send + 'static,
+    Output: Send + 'static,
+{
+    pub fn new<F>(function: F) -> Self
+    where
+        F: HotDispatch<State, Args, Output> + Send + Sync + 'static,
+    {
+        let type_name = type_name::<F>();
+        let iterator = type_name.split("::");
+        let function_name = iterator.last().unwrap();
+
+        Self {
+            function_name,
+            function: Arc::new(function),
+        }
+    }
+
+    /// The function as listed in the status panel.
+    pub fn kind(&self) -> HotFunction {
+        HotFunction::Extension(self.function_name)
+    }
+
+    /// Calls the function with the state of the running application, in
+    /// between two messages.
+    ///
+    /// Fails with [`HotIceError::EventLoopNotRunning`] if the application
+    /// has not been started yet or has exited, and with
+    /// [`HotIceError::LibraryNotFound`] if the call arrives while a library
+    /// is swapped. A function that fails or panics is reported in the
+    /// overlay as well.
+    pub fn call(
+        &self,
+        args: Args,
+    ) -> impl Future<Output = Result<Output, HotIceError>> + use<State, Args, Output> {
+        let (sender, receiver) = oneshot::channel();
+        let this = self.clone();
+        let call = ExtensionCall::new(move |state, library| {
+            let started = Instant::now();
+            let result = this.dispatch(state, args, library);
+            metrics::record(this.kind(), started, &result);
+
+            let fn_state = match &result {
+                Ok((_, fn_state)) => fn_state.clone(),
+                Err(err) => FunctionState::Error(err.to_string()),
+            };
+            let _ = sender.send(result.map(|(output, _)| output));
+            (this.kind(), fn_state)
+        });
+        let sent = route(call);
+
+        async move {
+            sent?;
+            receiver.await.unwrap_or(Err(HotIceError::LibraryNotFound))
+        }
+    }
+
+    pub(crate) fn register(&self) {
+        registry::register(
+            self.function_name,
+            self.kind(),
+            self.function.signature_hash(),
+        );
+    }
+
+    fn dispatch(
+        &self,
+        state: &State,
+        args: Args,
+        library: Option<&LibraryHandle>,
+    ) -> Result<(Output, FunctionState), HotIceError> {
+        let Some(library) = library.filter(|library| library.is_hot(self.function_name)) else {
+            let output = self.function.call_static(state, args)?;
+            return Ok((output, FunctionState::Static));
+        };
+
+        let lib = library.load();
+        match unsafe { lib.get_symbol::<*const ()>(self.function_name) } {
+            Ok(symbol) => {
+                let output = unsafe { self.function.call_hot(symbol, state, args)? };
+                Ok((output, FunctionState::Hot))
+            }
+            Err(_) => {
+                let err = HotIceError::FunctionNotFound(self.function_name);
+                let output = self.function.call_static(state, args)?;
+                Ok((output, FunctionState::FallBackStatic(err.to_string())))
+            }
+        }
+    }
+}
+
+impl<State, Args, Output> Clone for HotExtension<State, Args, Output> {
+    fn clone(&self) -> Self {
+        Self {
+            function_name: self.function_name,
+            function: Arc::clone(&self.function),
+        }
+    }
+}
+
+impl<State, Args, Output> fmt::Debug for HotExtension<State, Args, Output> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HotExtension")
+            .field("function_name", &self.function_name)
+            .finish_non_exhaustive()
+    }
+}
+
+type Call<State> =
+    Box<dyn FnOnce(&State, Option<&LibraryHandle>) -> (HotFunction, FunctionState) + Send>;
+
+/// A call of a [`HotExtension`] on its way to the reloader, which runs it
+/// with the state and reports the state the function ended up in.
+pub(crate) struct ExtensionCall<State>(Arc<Mutex<Option<Call<State>>>>);
+
+impl<State> ExtensionCall<State> {
+    fn new(
+        call: impl FnOnce(&State, Option<&LibraryHandle>) -> (HotFunction, FunctionState)
+        + Send
+        + 'static,
+    ) -> Self {
+        Self(Arc::new(Mutex::new(Some(Box::new(call)))))
+    }
+
+    /// Runs the call, `None` if a clone of it ran already.
+    pub(crate) fn run(
+        &self,
+        state: &State,
+        library: Option<&LibraryHandle>,
+    ) -> Option<(HotFunction, FunctionState)> {
+        let call = self.0.lock().ok()?.take()?;
+        Some(call(state, library))
+    }
+}
+
+impl<State> Clone for ExtensionCall<State> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Routing calls to the reloader of the program with their state type
+// ---------------------------------------------------------------------------
+
+type SendCall<State> = fn(ExtensionCall<State>) -> Result<(), HotIceError>;
+
+static ROUTES: Mutex<Option<HashMap<TypeId, Box<dyn Any + Send>>>> = Mutex::new(None);
+
+/// Routes the calls of extensions on `P::State` to the reloader of `P`.
+pub(crate) fn connect<P>()
+where
+    P: HotProgram + 'static,
+{
+    let Ok(mut routes) = ROUTES.lock() else {
+        return;
+    };
+    routes.get_or_insert_with(HashMap::new).insert(
+        TypeId::of::<P::State>(),
+        Box::new(send_call::<P> as SendCall<P::State>),
+    );
+}
+
+fn route<State: 'static>(call: ExtensionCall<State>) -> Result<(), HotIceError> {
+    let send = ROUTES
+        .lock()
+        .ok()
+        .and_then(|routes| {
+            let route = routes.as_ref()?.get(&TypeId::of::<State>())?;
+            route.downcast_ref::<SendCall<State>>().copied()
+        })
+        .ok_or(HotIceError::EventLoopNotRunning)?;
+    send(call)
+}
+
+fn send_call<P>(call: ExtensionCall<P::State>) -> Result<(), HotIceError>
+where
+    P: HotProgram + 'static,
+{
+    let sender = get_action_sender::<Message<P>>().ok_or(HotIceError::EventLoopNotRunning)?;
+    sender.send_action(Action::Output(Message::Extension(call)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct State(Vec<u8>);
+
+    fn export_report(state: &State, separator: u8) -> HotResult<Vec<u8>> {
+        crate::ffi::guarded(|| {
+            assert!(!state.0.is_empty(), "nothing to export");
+            state.0.iter().flat_map(|row| [*row, separator]).collect()
+        })
+    }
+
+    #[test]
+    fn extensions_fall_back_while_the_library_lacks_them() {
+        let handle = LibraryHandle::new();
+        let extension = HotExtension::new(export_report);
+        assert_eq!(extension.kind(), HotFunction::Extension("export_report"));
+
+        let (report, fn_state) = extension
+            .dispatch(&State(vec![1, 2]), (b',',), Some(&handle))
+            .unwrap();
+        assert_eq!(report, [1, b',', 2, b',']);
+        assert!(matches!(fn_state, FunctionState::FallBackStatic(_)));
+
+        let (_, fn_state) = extension.dispatch(&State(vec![3]), (b',',), None).unwrap();
+        assert_eq!(fn_state, FunctionState::Static);
+
+        assert!(matches!(
+            extension.dispatch(&State(Vec::new()), (b',',), None),
+            Err(HotIceError::FunctionPanicked(_))
+        ));
+    }
+}
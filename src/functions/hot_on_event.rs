@@ -0,0 +1,157 @@
+use std::{any::type_name, marker::PhantomData};
+
+use iced_core::{Event, window};
+
+use crate::{
+    error::HotIceError,
+    into_result::IntoResult,
+    library_handle::LibraryHandle,
+    message::MessageSource,
+    registry,
+    reloader::{FunctionState, HotFunction},
+};
+
+pub trait IntoHotOnEvent<State, Message> {
+    fn static_on_event(
+        &self,
+        state: &State,
+        event: Event,
+        window: window::Id,
+    ) -> Result<Option<Message>, HotIceError>;
+
+    fn hot_on_event(
+        &self,
+        state: &State,
+        event: Event,
+        window: window::Id,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Option<Message>, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
+}
+
+impl<T, C, State, Message> IntoHotOnEvent<State, Message> for T
+where
+    T: Fn(&State, Event, window::Id) -> C,
+    C: IntoResult<Option<Message>>,
+{
+    fn static_on_event(
+        &self,
+        state: &State,
+        event: Event,
+        window: window::Id,
+    ) -> Result<Option<Message>, HotIceError> {
+        (self)(state, event, window).into_result()
+    }
+
+    fn hot_on_event(
+        &self,
+        state: &State,
+        event: Event,
+        window: window::Id,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Option<Message>, HotIceError> {
+        let lib = reloader.load();
+
+        let function = unsafe {
+            lib.get_symbol::<fn(&State, Event, window::Id) -> C>(function_name)
+                .map_err(|_| HotIceError::FunctionNotFound(function_name))?
+        };
+
+        function(state, event, window).into_result()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&State, Event, window::Id, C)>()
+    }
+}
+
+pub struct HotOnEvent<F, State, Message> {
+    function_name: &'static str,
+    function: F,
+    _state: PhantomData<State>,
+    _message: PhantomData<Message>,
+}
+
+impl<F, State, Message> HotOnEvent<F, State, Message>
+where
+    F: IntoHotOnEvent<State, Message>,
+{
+    pub fn new(function: F) -> Self {
+        let type_name = type_name::<F>();
+        let iterator = type_name.split("::");
+        let function_name = iterator.last().unwrap();
+
+        registry::register(
+            function_name,
+            HotFunction::OnEvent,
+            function.signature_hash(),
+        );
+
+        Self {
+            function,
+            function_name,
+            _state: PhantomData,
+            _message: PhantomData,
+        }
+    }
+
+    pub fn on_event(
+        &self,
+        state: &State,
+        event: Event,
+        window: window::Id,
+        reloader: Option<&LibraryHandle>,
+    ) -> Result<(Option<MessageSource<Message>>, FunctionState), HotIceError> {
+        let Some(reloader) = reloader.filter(|reloader| reloader.is_hot(self.function_name)) else {
+            let message = self.function.static_on_event(state, event, window)?;
+            return Ok((message.map(MessageSource::Static), FunctionState::Static));
+        };
+
+        match self
+            .function
+            .hot_on_event(state, event.clone(), window, reloader, self.function_name)
+        {
+            Ok(message) => Ok((message.map(MessageSource::Dynamic), FunctionState::Hot)),
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
+                let message = self.function.static_on_event(state, event, window)?;
+                Ok((
+                    message.map(MessageSource::Static),
+                    FunctionState::FallBackStatic(err.to_string()),
+                ))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use iced_core::keyboard;
+
+    #[test]
+    fn maps_events_to_static_messages() {
+        let on_event = HotOnEvent::new(|_: &(), event: Event, _: window::Id| match event {
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                Some(modifiers.shift())
+            }
+            _ => None,
+        });
+        let window = window::Id::unique();
+        let shift = Event::Keyboard(keyboard::Event::ModifiersChanged(
+            keyboard::Modifiers::SHIFT,
+        ));
+
+        let (message, state) = on_event.on_event(&(), shift, window, None).unwrap();
+        assert!(matches!(message, Some(MessageSource::Static(true))));
+        assert_eq!(state, FunctionState::Static);
+
+        let closed = Event::Window(window::Event::Closed);
+        let (message, _) = on_event.on_event(&(), closed, window, None).unwrap();
+        assert!(message.is_none());
+    }
+}
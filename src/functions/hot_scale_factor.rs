@@ -1,13 +1,13 @@
-use std::{
-    any::type_name,
-    marker::PhantomData,
-    sync::{Arc, Mutex},
-};
+use std::{any::type_name, marker::PhantomData};
 
 use iced_core::window;
 
 use crate::{
-    error::HotIceError, into_result::IntoResult, lib_reloader::LibReloader, reloader::FunctionState,
+    error::HotIceError,
+    into_result::IntoScaleFactor,
+    library_handle::LibraryHandle,
+    registry,
+    reloader::{FunctionState, HotFunction},
 };
 
 pub trait IntoHotScaleFactor<State> {
@@ -17,37 +17,41 @@ pub trait IntoHotScaleFactor<State> {
         &self,
         state: &State,
         window: window::Id,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<f32, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
 }
 
 impl<C, T, State> IntoHotScaleFactor<State> for T
 where
     T: Fn(&State) -> C,
-    C: IntoResult<f32>,
+    C: IntoScaleFactor,
 {
     fn static_scale_factor(&self, state: &State, _window: window::Id) -> Result<f32, HotIceError> {
-        (self)(state).into_result()
+        (self)(state).into_scale_factor()
     }
 
     fn hot_scale_factor(
         &self,
         state: &State,
         _window: window::Id,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<f32, HotIceError> {
-        let lib = reloader
-            .try_lock()
-            .map_err(|_| HotIceError::LockAcquisitionError)?;
+        let lib = reloader.load();
 
         let function = unsafe {
-            lib.get_symbol::<fn(&State) -> C>(function_name.as_bytes())
+            lib.get_symbol::<fn(&State) -> C>(function_name)
                 .map_err(|_| HotIceError::FunctionNotFound(function_name))?
         };
 
-        function(state).into_result()
+        function(state).into_scale_factor()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&State, C)>()
     }
 }
 
@@ -66,6 +70,12 @@ where
         let iterator = type_name.split("::");
         let function_name = iterator.last().unwrap();
 
+        registry::register(
+            function_name,
+            HotFunction::ScaleFactor,
+            function.signature_hash(),
+        );
+
         Self {
             function,
             function_name,
@@ -77,9 +87,9 @@ where
         &self,
         state: &State,
         window: window::Id,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<(f32, FunctionState), HotIceError> {
-        let Some(reloader) = reloader else {
+        let Some(reloader) = reloader.filter(|reloader| reloader.is_hot(self.function_name)) else {
             let scale_factor = self.function.static_scale_factor(state, window)?;
             return Ok((scale_factor, FunctionState::Static));
         };
@@ -89,11 +99,26 @@ where
             .hot_scale_factor(state, window, reloader, self.function_name)
         {
             Ok(scale_factor) => Ok((scale_factor, FunctionState::Hot)),
-            Err(HotIceError::FunctionNotFound(_)) => {
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
                 let scale_factor = self.function.static_scale_factor(state, window)?;
-                Ok((scale_factor, FunctionState::Static))
+                Ok((scale_factor, FunctionState::FallBackStatic(err.to_string())))
             }
             Err(err) => Err(err),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_f32_and_f64() {
+        let window = window::Id::unique();
+        let single = HotScaleFactor::new(|_: &()| 1.5_f32);
+        let double = HotScaleFactor::new(|_: &()| 2.0_f64);
+
+        assert_eq!(single.scale_factor(&(), window, None).unwrap().0, 1.5);
+        assert_eq!(double.scale_factor(&(), window, None).unwrap().0, 2.0);
+    }
+}
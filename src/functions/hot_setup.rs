@@ -0,0 +1,109 @@
+use std::{any::type_name, marker::PhantomData};
+
+use iced_winit::runtime::Task;
+
+use crate::{
+    error::HotIceError,
+    into_result::IntoResult,
+    library_handle::LibraryHandle,
+    message::MessageSource,
+    registry,
+    reloader::{FunctionState, HotFunction},
+};
+
+pub trait IntoHotSetup<State, Message> {
+    fn static_setup(&self, state: &mut State) -> Result<Task<Message>, HotIceError>;
+
+    fn hot_setup(
+        &self,
+        state: &mut State,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Task<Message>, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
+}
+
+impl<T, C, State, Message> IntoHotSetup<State, Message> for T
+where
+    T: Fn(&mut State) -> C,
+    C: IntoResult<Task<Message>>,
+    Message: Send + 'static,
+{
+    fn static_setup(&self, state: &mut State) -> Result<Task<Message>, HotIceError> {
+        (self)(state).into_result()
+    }
+
+    fn hot_setup(
+        &self,
+        state: &mut State,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Task<Message>, HotIceError> {
+        let lib = reloader.load();
+
+        let function = unsafe {
+            lib.get_symbol::<fn(&mut State) -> C>(function_name)
+                .map_err(|_| HotIceError::FunctionNotFound(function_name))?
+        };
+
+        function(state).into_result()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&mut State, C)>()
+    }
+}
+
+/// A function rerun on the state after every reload, see
+/// [`HotIce::setup`](crate::hot_application::HotIce::setup).
+pub struct HotSetup<F, State, Message> {
+    function_name: &'static str,
+    function: F,
+    _state: PhantomData<State>,
+    _message: PhantomData<Message>,
+}
+
+impl<F, State, Message> HotSetup<F, State, Message>
+where
+    F: IntoHotSetup<State, Message>,
+    Message: Send + 'static,
+{
+    pub fn new(function: F) -> Self {
+        let type_name = type_name::<F>();
+        let iterator = type_name.split("::");
+        let function_name = iterator.last().unwrap();
+
+        registry::register(function_name, HotFunction::Setup, function.signature_hash());
+
+        Self {
+            function,
+            function_name,
+            _state: PhantomData,
+            _message: PhantomData,
+        }
+    }
+
+    pub fn setup(
+        &self,
+        state: &mut State,
+        reloader: Option<&LibraryHandle>,
+    ) -> Result<(Task<MessageSource<Message>>, FunctionState), HotIceError> {
+        let Some(reloader) = reloader.filter(|reloader| reloader.is_hot(self.function_name)) else {
+            let task = self.function.static_setup(state)?;
+            return Ok((task.map(MessageSource::Static), FunctionState::Static));
+        };
+
+        match self.function.hot_setup(state, reloader, self.function_name) {
+            Ok(task) => Ok((task.map(MessageSource::Dynamic), FunctionState::Hot)),
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
+                let task = self.function.static_setup(state)?;
+                Ok((
+                    task.map(MessageSource::Static),
+                    FunctionState::FallBackStatic(err.to_string()),
+                ))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
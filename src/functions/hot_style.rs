@@ -1,13 +1,13 @@
-use std::{
-    any::type_name,
-    marker::PhantomData,
-    sync::{Arc, Mutex},
-};
+use std::{any::type_name, marker::PhantomData};
 
 use iced_core::theme;
 
 use crate::{
-    error::HotIceError, into_result::IntoResult, lib_reloader::LibReloader, reloader::FunctionState,
+    error::HotIceError,
+    into_result::IntoResult,
+    library_handle::LibraryHandle,
+    registry,
+    reloader::{FunctionState, HotFunction},
 };
 
 pub trait IntoHotStyle<State, Theme> {
@@ -17,9 +17,11 @@ pub trait IntoHotStyle<State, Theme> {
         &self,
         state: &State,
         theme: &Theme,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<theme::Style, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
 }
 
 impl<T, C, State, Theme> IntoHotStyle<State, Theme> for T
@@ -35,20 +37,22 @@ where
         &self,
         state: &State,
         theme: &Theme,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<theme::Style, HotIceError> {
-        let lib = reloader
-            .try_lock()
-            .map_err(|_| HotIceError::LockAcquisitionError)?;
+        let lib = reloader.load();
 
         let function = unsafe {
-            lib.get_symbol::<fn(&State, &Theme) -> C>(function_name.as_bytes())
+            lib.get_symbol::<fn(&State, &Theme) -> C>(function_name)
                 .map_err(|_| HotIceError::FunctionNotFound(function_name))?
         };
 
         function(state, theme).into_result()
     }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&State, &Theme, C)>()
+    }
 }
 
 pub struct HotStyle<F, State, Theme> {
@@ -67,6 +71,8 @@ where
         let iterator = type_name.split("::");
         let function_name = iterator.last().unwrap();
 
+        registry::register(function_name, HotFunction::Style, function.signature_hash());
+
         Self {
             function,
             function_name,
@@ -79,9 +85,9 @@ where
         &self,
         state: &State,
         theme: &Theme,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<(theme::Style, FunctionState), HotIceError> {
-        let Some(reloader) = reloader else {
+        let Some(reloader) = reloader.filter(|reloader| reloader.is_hot(self.function_name)) else {
             let style = self.function.static_style(state, theme)?;
             return Ok((style, FunctionState::Static));
         };
@@ -91,9 +97,9 @@ where
             .hot_style(state, theme, reloader, self.function_name)
         {
             Ok(style) => Ok((style, FunctionState::Hot)),
-            Err(HotIceError::FunctionNotFound(_)) => {
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
                 let style = self.function.static_style(state, theme)?;
-                Ok((style, FunctionState::Static))
+                Ok((style, FunctionState::FallBackStatic(err.to_string())))
             }
             Err(err) => Err(err),
         }
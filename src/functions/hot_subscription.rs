@@ -1,14 +1,14 @@
-use std::{
-    any::type_name,
-    marker::PhantomData,
-    sync::{Arc, Mutex},
-};
+use std::{any::type_name, marker::PhantomData};
 
 use iced_futures::Subscription;
 
 use crate::{
-    error::HotIceError, into_result::IntoResult, lib_reloader::LibReloader, message::MessageSource,
-    reloader::FunctionState,
+    error::HotIceError,
+    into_result::IntoResult,
+    library_handle::LibraryHandle,
+    message::MessageSource,
+    registry,
+    reloader::{FunctionState, HotFunction},
 };
 
 pub trait IntoHotSubscription<State, Message> {
@@ -17,9 +17,11 @@ pub trait IntoHotSubscription<State, Message> {
     fn hot_subscription(
         &self,
         state: &State,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Subscription<Message>, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
 }
 
 impl<T, C, State, Message> IntoHotSubscription<State, Message> for T
@@ -35,20 +37,22 @@ where
     fn hot_subscription(
         &self,
         state: &State,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Subscription<Message>, HotIceError> {
-        let lib = reloader
-            .try_lock()
-            .map_err(|_| HotIceError::LockAcquisitionError)?;
+        let lib = reloader.load();
 
         let function = unsafe {
-            lib.get_symbol::<fn(&State) -> C>(function_name.as_bytes())
+            lib.get_symbol::<fn(&State) -> C>(function_name)
                 .map_err(|_| HotIceError::FunctionNotFound(function_name))?
         };
 
         function(state).into_result()
     }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&State, C)>()
+    }
 }
 
 pub struct HotSubscription<F, State, Message> {
@@ -68,6 +72,12 @@ where
         let iterator = type_name.split("::");
         let function_name = iterator.last().unwrap();
 
+        registry::register(
+            function_name,
+            HotFunction::Subscription,
+            function.signature_hash(),
+        );
+
         Self {
             function,
             function_name,
@@ -79,9 +89,9 @@ where
     pub fn subscription(
         &self,
         state: &State,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<(Subscription<MessageSource<Message>>, FunctionState), HotIceError> {
-        let Some(reloader) = reloader else {
+        let Some(reloader) = reloader.filter(|reloader| reloader.is_hot(self.function_name)) else {
             let sub = self.function.static_subscription(state)?;
             return Ok((sub.map(MessageSource::Static), FunctionState::Static));
         };
@@ -91,9 +101,12 @@ where
             .hot_subscription(state, reloader, self.function_name)
         {
             Ok(sub) => Ok((sub.map(MessageSource::Dynamic), FunctionState::Hot)),
-            Err(HotIceError::FunctionNotFound(_)) => {
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
                 let sub = self.function.static_subscription(state)?;
-                Ok((sub.map(MessageSource::Static), FunctionState::Static))
+                Ok((
+                    sub.map(MessageSource::Static),
+                    FunctionState::FallBackStatic(err.to_string()),
+                ))
             }
             Err(err) => Err(err),
         }
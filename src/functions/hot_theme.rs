@@ -1,11 +1,11 @@
-use std::{
-    any::type_name,
-    marker::PhantomData,
-    sync::{Arc, Mutex},
-};
+use std::{any::type_name, marker::PhantomData};
 
 use crate::{
-    error::HotIceError, into_result::IntoResult, lib_reloader::LibReloader, reloader::FunctionState,
+    error::HotIceError,
+    into_result::IntoResult,
+    library_handle::LibraryHandle,
+    registry,
+    reloader::{FunctionState, HotFunction},
 };
 
 pub trait IntoHotTheme<State, Theme> {
@@ -14,9 +14,11 @@ pub trait IntoHotTheme<State, Theme> {
     fn hot_theme(
         &self,
         state: &State,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Option<Theme>, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
 }
 
 impl<T, C, State, Theme> IntoHotTheme<State, Theme> for T
@@ -31,20 +33,22 @@ where
     fn hot_theme(
         &self,
         state: &State,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Option<Theme>, HotIceError> {
-        let lib = reloader
-            .try_lock()
-            .map_err(|_| HotIceError::LockAcquisitionError)?;
+        let lib = reloader.load();
 
         let function = unsafe {
-            lib.get_symbol::<fn(&State) -> C>(function_name.as_bytes())
+            lib.get_symbol::<fn(&State) -> C>(function_name)
                 .map_err(|_| HotIceError::FunctionNotFound(function_name))?
         };
 
         function(state).into_result()
     }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&State, C)>()
+    }
 }
 
 pub struct HotTheme<F, State, Theme> {
@@ -63,6 +67,8 @@ where
         let iterator = type_name.split("::");
         let function_name = iterator.last().unwrap();
 
+        registry::register(function_name, HotFunction::Theme, function.signature_hash());
+
         Self {
             function,
             function_name,
@@ -74,18 +80,18 @@ where
     pub fn theme(
         &self,
         state: &State,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<(Option<Theme>, FunctionState), HotIceError> {
-        let Some(reloader) = reloader else {
+        let Some(reloader) = reloader.filter(|reloader| reloader.is_hot(self.function_name)) else {
             let theme = self.function.static_theme(state)?;
             return Ok((theme, FunctionState::Static));
         };
 
         match self.function.hot_theme(state, reloader, self.function_name) {
             Ok(theme) => Ok((theme, FunctionState::Hot)),
-            Err(HotIceError::FunctionNotFound(_)) => {
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
                 let theme = self.function.static_theme(state)?;
-                Ok((theme, FunctionState::Static))
+                Ok((theme, FunctionState::FallBackStatic(err.to_string())))
             }
             Err(err) => Err(err),
         }
@@ -1,44 +1,65 @@
-use std::{
-    any::type_name,
-    marker::PhantomData,
-    sync::{Arc, Mutex},
-};
+use std::{any::type_name, marker::PhantomData};
 
 use iced_core::window;
 
 use crate::{
-    error::HotIceError, into_result::IntoResult, lib_reloader::LibReloader, reloader::FunctionState,
+    error::HotIceError,
+    into_result::IntoResult,
+    library_handle::LibraryHandle,
+    registry,
+    reloader::{FunctionState, HotFunction},
 };
 
-pub trait IntoHotTitle<State> {
+/// Marks title functions that take the [`window::Id`] of the window as their
+/// last argument.
+pub struct WithWindow;
+
+/// `Marker` tells the implementations for functions with and without a
+/// [`window::Id`] apart.
+pub trait IntoHotTitle<State, Marker> {
     fn static_title(&self, state: &State, window: window::Id) -> Result<String, HotIceError>;
 
     fn hot_title(
         &self,
         state: &State,
         window: window::Id,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<String, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
+
+    /// Whether the title is a constant instead of a function of the library.
+    fn is_constant(&self) -> bool {
+        false
+    }
 }
 
-impl IntoHotTitle<()> for &'static str {
-    fn static_title(&self, _state: &(), _window: window::Id) -> Result<String, HotIceError> {
+impl<State> IntoHotTitle<State, ()> for &'static str {
+    fn static_title(&self, _state: &State, _window: window::Id) -> Result<String, HotIceError> {
         Ok(self.to_string())
     }
 
     fn hot_title(
         &self,
-        _state: &(),
+        _state: &State,
         _window: window::Id,
-        _reloader: &Arc<Mutex<LibReloader>>,
+        _reloader: &LibraryHandle,
         _function_name: &'static str,
     ) -> Result<String, HotIceError> {
         Ok(self.to_string())
     }
+
+    fn signature_hash(&self) -> u64 {
+        0
+    }
+
+    fn is_constant(&self) -> bool {
+        true
+    }
 }
 
-impl<T, C, State> IntoHotTitle<State> for T
+impl<T, C, State> IntoHotTitle<State, ()> for T
 where
     T: Fn(&State) -> C,
     C: IntoResult<String>,
@@ -51,41 +72,78 @@ where
         &self,
         state: &State,
         _window: window::Id,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<String, HotIceError> {
-        let lib = reloader
-            .try_lock()
-            .map_err(|_| HotIceError::LockAcquisitionError)?;
+        let lib = reloader.load();
 
         let function = unsafe {
-            lib.get_symbol::<fn(&State) -> C>(function_name.as_bytes())
+            lib.get_symbol::<fn(&State) -> C>(function_name)
                 .map_err(|_| HotIceError::FunctionNotFound(function_name))?
         };
 
         function(state).into_result()
     }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&State, C)>()
+    }
+}
+
+impl<T, C, State> IntoHotTitle<State, WithWindow> for T
+where
+    T: Fn(&State, window::Id) -> C,
+    C: IntoResult<String>,
+{
+    fn static_title(&self, state: &State, window: window::Id) -> Result<String, HotIceError> {
+        (self)(state, window).into_result()
+    }
+
+    fn hot_title(
+        &self,
+        state: &State,
+        window: window::Id,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<String, HotIceError> {
+        let lib = reloader.load();
+
+        let function = unsafe {
+            lib.get_symbol::<fn(&State, window::Id) -> C>(function_name)
+                .map_err(|_| HotIceError::FunctionNotFound(function_name))?
+        };
+
+        function(state, window).into_result()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&State, window::Id, C)>()
+    }
 }
 
-pub struct HotTitle<F, State> {
+pub struct HotTitle<F, State, Marker> {
     function_name: &'static str,
     function: F,
     _state: PhantomData<State>,
+    _marker: PhantomData<Marker>,
 }
 
-impl<F, State> HotTitle<F, State>
+impl<F, State, Marker> HotTitle<F, State, Marker>
 where
-    F: IntoHotTitle<State>,
+    F: IntoHotTitle<State, Marker>,
 {
     pub fn new(function: F) -> Self {
         let type_name = type_name::<F>();
         let iterator = type_name.split("::");
         let function_name = iterator.last().unwrap();
 
+        registry::register(function_name, HotFunction::Title, function.signature_hash());
+
         Self {
             function,
             function_name,
             _state: PhantomData,
+            _marker: PhantomData,
         }
     }
 
@@ -93,9 +151,11 @@ where
         &self,
         state: &State,
         window: window::Id,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<(String, FunctionState), HotIceError> {
-        let Some(reloader) = reloader else {
+        let Some(reloader) = reloader
+            .filter(|reloader| !self.function.is_constant() && reloader.is_hot(self.function_name))
+        else {
             let title = self.function.static_title(state, window)?;
             return Ok((title, FunctionState::Static));
         };
@@ -105,11 +165,41 @@ where
             .hot_title(state, window, reloader, self.function_name)
         {
             Ok(title) => Ok((title, FunctionState::Hot)),
-            Err(HotIceError::FunctionNotFound(_)) => {
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
                 let title = self.function.static_title(state, window)?;
-                Ok((title, FunctionState::Static))
+                Ok((title, FunctionState::FallBackStatic(err.to_string())))
             }
             Err(err) => Err(err),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct State(u32);
+
+    fn window_title(state: &State, window: window::Id) -> String {
+        format!("{} {window:?}", state.0)
+    }
+
+    #[test]
+    fn constant_and_window_aware_titles() {
+        let handle = LibraryHandle::new();
+        let window = window::Id::unique();
+
+        let constant = HotTitle::new("My App");
+        assert_eq!(
+            constant.title(&State(0), window, Some(&handle)).unwrap(),
+            ("My App".to_string(), FunctionState::Static)
+        );
+
+        // Nothing is loaded, the window-aware title falls back.
+        let (title, state) = HotTitle::new(window_title)
+            .title(&State(1), window, Some(&handle))
+            .unwrap();
+        assert_eq!(title, format!("1 {window:?}"));
+        assert!(matches!(state, FunctionState::FallBackStatic(_)));
+    }
+}
@@ -1,14 +1,15 @@
-use std::{
-    any::type_name,
-    marker::PhantomData,
-    sync::{Arc, Mutex},
-};
+use std::{any::type_name, marker::PhantomData};
 
 use iced_winit::runtime::Task;
 
 use crate::{
-    error::HotIceError, lib_reloader::LibReloader, message::DynMessage, message::MessageSource,
-    reloader::FunctionState,
+    context::Context,
+    error::HotIceError,
+    library_handle::LibraryHandle,
+    message::DynMessage,
+    message::MessageSource,
+    registry,
+    reloader::{FunctionState, HotFunction},
 };
 
 trait IntoResult<Message> {
@@ -27,7 +28,12 @@ impl<Message> IntoResult<Message> for Result<Task<Message>, HotIceError> {
     }
 }
 
-pub trait IntoHotUpdate<State, Message> {
+/// Marks update functions that take a [`Context`] as their last argument.
+pub struct WithContext;
+
+/// `Marker` tells the implementations for functions with and without a
+/// [`Context`] apart.
+pub trait IntoHotUpdate<State, Message, Marker> {
     fn static_update(
         &self,
         state: &mut State,
@@ -38,12 +44,14 @@ pub trait IntoHotUpdate<State, Message> {
         &self,
         state: &mut State,
         message: Message,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Task<Message>, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
 }
 
-impl<T, C, State, Message> IntoHotUpdate<State, Message> for T
+impl<T, C, State, Message> IntoHotUpdate<State, Message, ()> for T
 where
     T: Fn(&mut State, Message) -> C,
     C: IntoResult<Message>,
@@ -62,34 +70,74 @@ where
         &self,
         state: &mut State,
         message: Message,
-        reloader: &Arc<Mutex<LibReloader>>,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Task<Message>, HotIceError> {
-        let lib = reloader
-            .try_lock()
-            .map_err(|_| HotIceError::LockAcquisitionError)?;
+        let lib = reloader.load();
 
         let function = unsafe {
-            lib.get_symbol::<fn(&mut State, Message) -> C>(function_name.as_bytes())
+            lib.get_symbol::<fn(&mut State, Message) -> C>(function_name)
                 .map_err(|_| HotIceError::FunctionNotFound(function_name))?
         };
 
         function(state, message).into_result()
     }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&mut State, Message, C)>()
+    }
 }
 
-pub struct HotUpdate<F, State, Message> {
+impl<T, C, State, Message> IntoHotUpdate<State, Message, WithContext> for T
+where
+    T: Fn(&mut State, Message, &Context) -> C,
+    C: IntoResult<Message>,
+    Message: Send + 'static,
+    State: Send + 'static,
+{
+    fn static_update(
+        &self,
+        state: &mut State,
+        message: Message,
+    ) -> Result<Task<Message>, HotIceError> {
+        (self)(state, message, Context::host()).into_result()
+    }
+
+    fn hot_update(
+        &self,
+        state: &mut State,
+        message: Message,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Task<Message>, HotIceError> {
+        let lib = reloader.load();
+
+        let function = unsafe {
+            lib.get_symbol::<fn(&mut State, Message, &Context) -> C>(function_name)
+                .map_err(|_| HotIceError::FunctionNotFound(function_name))?
+        };
+
+        function(state, message, Context::host()).into_result()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&mut State, Message, &Context, C)>()
+    }
+}
+
+pub struct HotUpdate<F, State, Message, Marker> {
     pub lib_name: &'static str,
     function_name: &'static str,
     function: F,
     _state: PhantomData<State>,
     _message: PhantomData<Message>,
+    _marker: PhantomData<Marker>,
 }
 
-impl<F, State, Message> HotUpdate<F, State, Message>
+impl<F, State, Message, Marker> HotUpdate<F, State, Message, Marker>
 where
     Message: DynMessage + Clone,
-    F: IntoHotUpdate<State, Message>,
+    F: IntoHotUpdate<State, Message, Marker>,
 {
     pub fn new(function: F) -> Self {
         let type_name = type_name::<F>();
@@ -97,12 +145,19 @@ where
         let lib_name = iterator.next().unwrap();
         let function_name = iterator.last().unwrap();
 
+        registry::register(
+            function_name,
+            HotFunction::Update,
+            function.signature_hash(),
+        );
+
         Self {
             function,
             function_name,
             lib_name,
             _state: PhantomData,
             _message: PhantomData,
+            _marker: PhantomData,
         }
     }
 
@@ -110,7 +165,7 @@ where
         &self,
         state: &mut State,
         message: MessageSource<Message>,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<(Task<MessageSource<Message>>, FunctionState), HotIceError> {
         match message {
             MessageSource::Static(message) => {
@@ -118,7 +173,9 @@ where
                 Ok((task.map(MessageSource::Static), FunctionState::Static))
             }
             MessageSource::Dynamic(message) => {
-                let Some(reloader) = reloader else {
+                let Some(reloader) =
+                    reloader.filter(|reloader| reloader.is_hot(self.function_name))
+                else {
                     let task = self.function.static_update(state, message)?;
                     return Ok((task.map(MessageSource::Static), FunctionState::Static));
                 };
@@ -128,9 +185,12 @@ where
                     .hot_update(state, message.clone(), reloader, self.function_name)
                 {
                     Ok(task) => Ok((task.map(MessageSource::Dynamic), FunctionState::Hot)),
-                    Err(HotIceError::FunctionNotFound(_)) => {
+                    Err(err @ HotIceError::FunctionNotFound(_)) => {
                         let task = self.function.static_update(state, message)?;
-                        Ok((task.map(MessageSource::Static), FunctionState::Static))
+                        Ok((
+                            task.map(MessageSource::Static),
+                            FunctionState::FallBackStatic(err.to_string()),
+                        ))
                     }
                     Err(err) => Err(err),
                 }
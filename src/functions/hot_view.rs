@@ -1,31 +1,60 @@
-use std::{
-    any::type_name,
-    marker::PhantomData,
-    sync::{Arc, Mutex},
-};
+use std::{any::type_name, marker::PhantomData};
 
-use iced_core::Element;
+use iced_core::{Element, window};
 
 use crate::{
-    error::HotIceError, into_result::IntoResult, lib_reloader::LibReloader, message::MessageSource,
-    reloader::FunctionState,
+    context::Context,
+    error::HotIceError,
+    into_result::IntoResult,
+    library_handle::LibraryHandle,
+    message::{HotMessage, MessageSource},
+    registry,
+    reloader::{FunctionState, HotFunction},
 };
 
-pub trait IntoHotView<'a, State, Message, Theme, Renderer> {
+/// Names the `Element` a hot `view` returns to the host, for views whose
+/// `Element` is behind a type alias the macro cannot see through.
+#[doc(hidden)]
+pub trait HotElement {
+    type Hot;
+}
+
+impl<'a, Message, Theme, Renderer> HotElement for Element<'a, Message, Theme, Renderer> {
+    type Hot = Element<'a, HotMessage, Theme, Renderer>;
+}
+
+/// Marks view functions that take a [`Context`] as their last argument.
+pub struct WithContext;
+
+/// Marks view functions that take the [`window::Id`] of the window they
+/// draw, for programs with several windows.
+pub struct WithWindow;
+
+/// Marks view functions that take the [`window::Id`] followed by a
+/// [`Context`].
+pub struct WithWindowAndContext;
+
+/// `Marker` tells the implementations for functions with and without a
+/// [`window::Id`] and a [`Context`] apart.
+pub trait IntoHotView<'a, State, Message, Theme, Renderer, Marker> {
     fn static_view(
         &self,
         state: &'a State,
+        window: window::Id,
     ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError>;
 
     fn hot_view(
         &self,
         state: &'a State,
-        reloader: &Arc<Mutex<LibReloader>>,
+        window: window::Id,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError>;
+
+    fn signature_hash(&self) -> u64;
 }
 
-impl<'a, T, C, State, Message, Theme, Renderer> IntoHotView<'a, State, Message, Theme, Renderer>
+impl<'a, T, C, State, Message, Theme, Renderer> IntoHotView<'a, State, Message, Theme, Renderer, ()>
     for T
 where
     State: 'static,
@@ -35,6 +64,7 @@ where
     fn static_view(
         &self,
         state: &'a State,
+        _window: window::Id,
     ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
         (self)(state).into_result()
     }
@@ -42,22 +72,133 @@ where
     fn hot_view(
         &self,
         state: &'a State,
-        reloader: &Arc<Mutex<LibReloader>>,
+        _window: window::Id,
+        reloader: &LibraryHandle,
         function_name: &'static str,
     ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
-        let lib = reloader
-            .try_lock()
-            .map_err(|_| HotIceError::LockAcquisitionError)?;
+        let lib = reloader.load();
 
         let function = unsafe {
-            lib.get_symbol::<fn(&'a State) -> C>(function_name.as_bytes())
+            lib.get_symbol::<fn(&'a State) -> C>(function_name)
                 .map_err(|_| HotIceError::FunctionNotFound(function_name))?
         };
         function(state).into_result()
     }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&'a State, C)>()
+    }
+}
+
+impl<'a, T, C, State, Message, Theme, Renderer>
+    IntoHotView<'a, State, Message, Theme, Renderer, WithContext> for T
+where
+    State: 'static,
+    T: Fn(&'a State, &Context) -> C,
+    C: IntoResult<Element<'a, Message, Theme, Renderer>>,
+{
+    fn static_view(
+        &self,
+        state: &'a State,
+        _window: window::Id,
+    ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
+        (self)(state, Context::host()).into_result()
+    }
+
+    fn hot_view(
+        &self,
+        state: &'a State,
+        _window: window::Id,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
+        let lib = reloader.load();
+
+        let function = unsafe {
+            lib.get_symbol::<fn(&'a State, &Context) -> C>(function_name)
+                .map_err(|_| HotIceError::FunctionNotFound(function_name))?
+        };
+        function(state, Context::host()).into_result()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&'a State, &Context, C)>()
+    }
+}
+
+impl<'a, T, C, State, Message, Theme, Renderer>
+    IntoHotView<'a, State, Message, Theme, Renderer, WithWindow> for T
+where
+    State: 'static,
+    T: Fn(&'a State, window::Id) -> C,
+    C: IntoResult<Element<'a, Message, Theme, Renderer>>,
+{
+    fn static_view(
+        &self,
+        state: &'a State,
+        window: window::Id,
+    ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
+        (self)(state, window).into_result()
+    }
+
+    fn hot_view(
+        &self,
+        state: &'a State,
+        window: window::Id,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
+        let lib = reloader.load();
+
+        let function = unsafe {
+            lib.get_symbol::<fn(&'a State, window::Id) -> C>(function_name)
+                .map_err(|_| HotIceError::FunctionNotFound(function_name))?
+        };
+        function(state, window).into_result()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&'a State, window::Id, C)>()
+    }
 }
 
-pub struct HotView<F, State, Message, Theme, Renderer> {
+impl<'a, T, C, State, Message, Theme, Renderer>
+    IntoHotView<'a, State, Message, Theme, Renderer, WithWindowAndContext> for T
+where
+    State: 'static,
+    T: Fn(&'a State, window::Id, &Context) -> C,
+    C: IntoResult<Element<'a, Message, Theme, Renderer>>,
+{
+    fn static_view(
+        &self,
+        state: &'a State,
+        window: window::Id,
+    ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
+        (self)(state, window, Context::host()).into_result()
+    }
+
+    fn hot_view(
+        &self,
+        state: &'a State,
+        window: window::Id,
+        reloader: &LibraryHandle,
+        function_name: &'static str,
+    ) -> Result<Element<'a, Message, Theme, Renderer>, HotIceError> {
+        let lib = reloader.load();
+
+        let function = unsafe {
+            lib.get_symbol::<fn(&'a State, window::Id, &Context) -> C>(function_name)
+                .map_err(|_| HotIceError::FunctionNotFound(function_name))?
+        };
+        function(state, window, Context::host()).into_result()
+    }
+
+    fn signature_hash(&self) -> u64 {
+        registry::type_hash::<(&'a State, window::Id, &Context, C)>()
+    }
+}
+
+pub struct HotView<F, State, Message, Theme, Renderer, Marker> {
     pub lib_name: &'static str,
     function_name: &'static str,
     function: F,
@@ -65,11 +206,13 @@ pub struct HotView<F, State, Message, Theme, Renderer> {
     _message: PhantomData<Message>,
     _theme: PhantomData<Theme>,
     _renderer: PhantomData<Renderer>,
+    _marker: PhantomData<Marker>,
 }
 
-impl<'a, F, State, Message, Theme, Renderer> HotView<F, State, Message, Theme, Renderer>
+impl<'a, F, State, Message, Theme, Renderer, Marker>
+    HotView<F, State, Message, Theme, Renderer, Marker>
 where
-    F: IntoHotView<'a, State, Message, Theme, Renderer>,
+    F: IntoHotView<'a, State, Message, Theme, Renderer, Marker>,
     Renderer: iced_core::Renderer + iced_core::text::Renderer + 'a,
     Theme: 'a,
     Message: 'a,
@@ -80,6 +223,8 @@ where
         let lib_name = iterator.next().unwrap();
         let function_name = iterator.last().unwrap();
 
+        registry::register(function_name, HotFunction::View, function.signature_hash());
+
         Self {
             function,
             function_name,
@@ -88,13 +233,15 @@ where
             _state: PhantomData,
             _theme: PhantomData,
             _renderer: PhantomData,
+            _marker: PhantomData,
         }
     }
 
     pub fn view(
         &self,
         state: &'a State,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        window: window::Id,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<
         (
             Element<'a, MessageSource<Message>, Theme, Renderer>,
@@ -102,18 +249,54 @@ where
         ),
         HotIceError,
     > {
-        let Some(reloader) = reloader else {
-            let element = self.function.static_view(state)?;
+        let Some(reloader) = reloader.filter(|reloader| reloader.is_hot(self.function_name)) else {
+            let element = self.function.static_view(state, window)?;
             return Ok((element.map(MessageSource::Static), FunctionState::Static));
         };
 
-        match self.function.hot_view(state, reloader, self.function_name) {
+        match self
+            .function
+            .hot_view(state, window, reloader, self.function_name)
+        {
             Ok(element) => Ok((element.map(MessageSource::Dynamic), FunctionState::Hot)),
-            Err(HotIceError::FunctionNotFound(_)) => {
-                let element = self.function.static_view(state)?;
-                Ok((element.map(MessageSource::Static), FunctionState::Static))
+            Err(err @ HotIceError::FunctionNotFound(_)) => {
+                let element = self.function.static_view(state, window)?;
+                Ok((
+                    element.map(MessageSource::Static),
+                    FunctionState::FallBackStatic(err.to_string()),
+                ))
             }
             Err(err) => Err(err),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    use iced_core::Theme;
+
+    struct State(Cell<Option<window::Id>>);
+
+    fn window_view(state: &State, window: window::Id) -> Element<'_, (), Theme, ()> {
+        state.0.set(Some(window));
+        iced_widget::space().into()
+    }
+
+    #[test]
+    fn window_aware_views_draw_their_window() {
+        let handle = LibraryHandle::new();
+        let window = window::Id::unique();
+        let state = State(Cell::new(None));
+
+        // Nothing is loaded, the view falls back with the window it draws.
+        let (_, fn_state) = HotView::new(window_view)
+            .view(&state, window, Some(&handle))
+            .unwrap();
+        assert!(matches!(fn_state, FunctionState::FallBackStatic(_)));
+        assert_eq!(state.0.get(), Some(window));
+    }
+}
@@ -1,5 +1,8 @@
 pub(crate) mod boot;
+pub(crate) mod hot_dispatch;
+pub(crate) mod hot_on_event;
 pub(crate) mod hot_scale_factor;
+pub(crate) mod hot_setup;
 pub(crate) mod hot_style;
 pub(crate) mod hot_subscription;
 pub(crate) mod hot_theme;
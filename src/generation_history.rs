@@ -0,0 +1,137 @@
+//! The generations of the library loaded so far.
+//!
+//! Every library the reloader loads is recorded with the time it was loaded,
+//! the sources it was built from and the copy it was loaded from, so the
+//! overlay can list them and activate an older one again. Activating a
+//! generation copies its file once more and reloads it like a new build,
+//! with the state handed over as usual; the next build replaces it again.
+//! Copies of replaced generations are only kept on disk with
+//! [`ArtifactCleanup::keep_retired`](crate::ArtifactCleanup::keep_retired),
+//! a record whose copy is gone stays listed but can't be activated.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::source_revision::SourceRevision;
+
+/// A library the reloader loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GenerationRecord {
+    /// Counts the libraries loaded, 0 for the one loaded on startup.
+    pub(crate) generation: usize,
+    /// Time since the reloader started when the library was loaded.
+    pub(crate) loaded_at: Duration,
+    /// The copy the library was loaded from.
+    pub(crate) file: PathBuf,
+    pub(crate) hash: u32,
+    pub(crate) source: Option<SourceRevision>,
+    /// The older generation this one activated again.
+    pub(crate) rollback_of: Option<usize>,
+}
+
+impl GenerationRecord {
+    /// Whether the copy of the library is still there to be loaded again.
+    pub(crate) fn is_available(&self) -> bool {
+        self.file.exists()
+    }
+}
+
+/// The last generations loaded, oldest first.
+#[derive(Debug)]
+pub(crate) struct GenerationHistory {
+    records: VecDeque<GenerationRecord>,
+    capacity: usize,
+    started: Instant,
+}
+
+impl GenerationHistory {
+    /// A history of the last `capacity` generations.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            started: Instant::now(),
+        }
+    }
+
+    /// Records the library loaded from `file`. A rollback takes over the
+    /// sources of the generation it activated again.
+    pub(crate) fn push(
+        &mut self,
+        generation: usize,
+        file: PathBuf,
+        hash: u32,
+        rollback_of: Option<usize>,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        let source = rollback_of
+            .and_then(|older| self.get(older))
+            .and_then(|record| record.source.clone());
+        if self.records.len() == self.capacity {
+            let _ = self.records.pop_front();
+        }
+        self.records.push_back(GenerationRecord {
+            generation,
+            loaded_at: self.started.elapsed(),
+            file,
+            hash,
+            source,
+            rollback_of,
+        });
+    }
+
+    /// Sets the sources of the latest generation, unless it was a rollback,
+    /// and returns the sources it was built from.
+    pub(crate) fn built_from(&mut self, source: Option<SourceRevision>) -> Option<SourceRevision> {
+        match self.records.back_mut() {
+            Some(record) if record.rollback_of.is_some() => record.source.clone(),
+            Some(record) => {
+                record.source = source.clone();
+                source
+            }
+            None => source,
+        }
+    }
+
+    pub(crate) fn get(&self, generation: usize) -> Option<&GenerationRecord> {
+        self.records
+            .iter()
+            .find(|record| record.generation == generation)
+    }
+
+    /// Oldest first.
+    pub(crate) fn records(&self) -> impl DoubleEndedIterator<Item = &GenerationRecord> {
+        self.records.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollbacks_keep_the_sources_of_their_generation() {
+        let revision = |commit: &str| SourceRevision {
+            commit: commit.to_string(),
+            dirty: Vec::new(),
+        };
+        let mut history = GenerationHistory::new(3);
+
+        history.push(0, PathBuf::from("lib-hot-1-0.so"), 1, None);
+        assert_eq!(history.built_from(Some(revision("a"))), Some(revision("a")));
+        history.push(1, PathBuf::from("lib-hot-1-1.so"), 2, None);
+        history.built_from(Some(revision("b")));
+
+        history.push(2, PathBuf::from("lib-hot-1-2.so"), 1, Some(0));
+        assert_eq!(history.built_from(Some(revision("c"))), Some(revision("a")));
+        assert_eq!(history.records().next_back().unwrap().rollback_of, Some(0));
+
+        history.push(3, PathBuf::from("lib-hot-1-3.so"), 3, None);
+        let generations: Vec<_> = history.records().map(|record| record.generation).collect();
+        assert_eq!(generations, [1, 2, 3]);
+        assert!(history.get(0).is_none());
+    }
+}
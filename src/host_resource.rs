@@ -0,0 +1,191 @@
+//! Resources owned by the host and lent to the hot library.
+//!
+//! Some values (database pools, device handles, caches) must never be
+//! serialized or rebuilt on reload. Registered with `HotIce::host_resource`,
+//! they live in the host binary for the rest of the program and every library
+//! generation borrows the same instance through [`HostOwned`], handed out by
+//! the [`Context`](crate::Context) that `boot` and `update` can take.
+//!
+//! The library's copy of a resource type has another `TypeId` than the
+//! host's, so resources are keyed by the [`TypeHash`] of their type, like
+//! the state handed between libraries. An edit of the type changes the hash
+//! and the library finds no resource instead of reading the host's with the
+//! wrong layout.
+//!
+//! The cdylib links its own copy of this crate, so the host hands it a
+//! lookup function through the symbol exported by [`export_host_resources!`]
+//! right after every library load, the same way as the interner.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::log_target::RELOAD;
+use crate::type_hash::TypeHash;
+
+type Resources = HashMap<u64, Arc<dyn Any + Send + Sync>>;
+
+static RESOURCES: Mutex<Option<Resources>> = Mutex::new(None);
+
+static HOST_LOOKUP: OnceLock<fn(u64) -> Option<*const ()>> = OnceLock::new();
+
+/// A resource registered with `HotIce::host_resource`.
+///
+/// Dereferences to the resource. Clones share the instance owned by the host,
+/// which is never dropped, so it stays valid across reloads.
+pub struct HostOwned<T> {
+    resource: Arc<T>,
+}
+
+impl<T: TypeHash + Send + Sync> HostOwned<T> {
+    /// The resource of type `T` registered by the host, `None` if there is
+    /// none. Prefer [`Context::resource`](crate::Context::resource) where a
+    /// `&Context` is at hand.
+    pub fn get() -> Option<Self> {
+        match HOST_LOOKUP.get() {
+            Some(host_lookup) => Self::lookup_in(*host_lookup),
+            None => Self::lookup_in(lookup),
+        }
+    }
+
+    /// Looks the resource up with `lookup`, [`lookup`] of the host.
+    pub(crate) fn lookup_in(lookup: fn(u64) -> Option<*const ()>) -> Option<Self> {
+        let ptr = lookup(T::TYPE_HASH)? as *const T;
+
+        // The pointer comes from an `Arc<T>` the host keeps in `RESOURCES`
+        // forever, the key only matches for a `T` of the same definition.
+        let resource = unsafe {
+            Arc::increment_strong_count(ptr);
+            Arc::from_raw(ptr)
+        };
+        Some(Self { resource })
+    }
+}
+
+impl<T> Clone for HostOwned<T> {
+    fn clone(&self) -> Self {
+        Self {
+            resource: Arc::clone(&self.resource),
+        }
+    }
+}
+
+impl<T> Deref for HostOwned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T: Debug> Debug for HostOwned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HostOwned").field(&*self.resource).finish()
+    }
+}
+
+/// Registers `resource` in the host, replacing an earlier one of the same
+/// type. The replaced one is never dropped either.
+pub(crate) fn register<T: TypeHash + Send + Sync>(resource: T) {
+    let Ok(mut resources) = RESOURCES.lock() else {
+        log::error!(
+            target: RELOAD,
+            "Failed to register host resource {}",
+            std::any::type_name::<T>()
+        );
+        return;
+    };
+
+    let resource: Arc<dyn Any + Send + Sync> = Arc::new(resource);
+    if let Some(replaced) = resources
+        .get_or_insert_with(HashMap::new)
+        .insert(T::TYPE_HASH, resource)
+    {
+        // A library may have looked the old one up and not yet taken its
+        // reference, it stays valid for the rest of the program.
+        std::mem::forget(replaced);
+        log::warn!(
+            target: RELOAD,
+            "Replaced host resource {}, code holding the old one keeps it",
+            std::any::type_name::<T>()
+        );
+    }
+}
+
+/// Looks a resource up in the registry of this binary, handed to the
+/// library as the host's lookup.
+pub(crate) fn lookup(key: u64) -> Option<*const ()> {
+    let resources = RESOURCES.lock().ok()?;
+    resources
+        .as_ref()?
+        .get(&key)
+        .map(|resource| Arc::as_ptr(resource) as *const ())
+}
+
+/// Called by the symbol generated with [`export_host_resources!`]. Only the
+/// first call has an effect, each library load starts with fresh statics.
+#[doc(hidden)]
+pub fn set_host_resources(host_lookup: fn(u64) -> Option<*const ()>) {
+    let _ = HOST_LOOKUP.set(host_lookup);
+}
+
+/// Exports the FFI function the host uses to lend its resources to the
+/// cdylib, one of the [exports of `update`](crate::hot_fn#exports-of-update).
+#[macro_export]
+macro_rules! export_host_resources {
+    () => {
+        /// Receives the host's resource lookup, see `hot_ice::HostOwned`.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn set_host_resources_lskdjfa3lkfjasdf(
+            host_lookup: fn(u64) -> ::core::option::Option<*const ()>,
+        ) {
+            $crate::macro_use::set_host_resources(host_lookup);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Pool {
+        connections: usize,
+    }
+
+    unsafe impl TypeHash for Pool {
+        const TYPE_HASH: u64 = 1;
+    }
+
+    /// `Pool` after an edit, as seen by the next library.
+    struct EditedPool {
+        _connections: u16,
+    }
+
+    unsafe impl TypeHash for EditedPool {
+        const TYPE_HASH: u64 = 2;
+    }
+
+    #[test]
+    fn resources_are_shared() {
+        assert!(HostOwned::<Pool>::get().is_none());
+
+        register(Pool { connections: 4 });
+        let a = HostOwned::<Pool>::get().unwrap();
+        let b = HostOwned::<Pool>::get().unwrap();
+        assert_eq!(a.connections, 4);
+        assert!(std::ptr::eq(&*a, &*b));
+        drop(a);
+        assert_eq!(*b.clone(), Pool { connections: 4 });
+
+        register(Pool { connections: 8 });
+        assert_eq!(b.connections, 4);
+        assert_eq!(HostOwned::<Pool>::get().unwrap().connections, 8);
+
+        assert!(HostOwned::<EditedPool>::get().is_none());
+    }
+}
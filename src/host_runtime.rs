@@ -0,0 +1,154 @@
+//! The tokio runtime of the host, shared by every library generation.
+//!
+//! With the `tokio` feature, the worker threads of a library run on a tokio
+//! runtime the host creates once, instead of one the library builds and tears
+//! down on every reload. The host hands a [`RuntimeHandleFfi`] to the library
+//! with the worker pool. The library checks that it was built against the
+//! same tokio before it enters the handle on its worker threads, so
+//! `tokio::spawn`, timers and sockets of hot code use the host's runtime.
+
+use crate::executor::ErasedWorkerPool;
+
+/// Major version of tokio this release of `hot_ice` is built against. Keep in
+/// sync with the `tokio` dependency in `Cargo.toml`.
+#[cfg(feature = "tokio")]
+pub(crate) const TOKIO_MAJOR: u32 = 1;
+
+/// The host's tokio runtime handle as passed to a library, null without the
+/// `tokio` feature.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+pub struct RuntimeHandleFfi {
+    handle: *const (),
+    tokio_major: u32,
+    size: usize,
+    align: usize,
+}
+
+// Safety: the handle points to the host's runtime handle, which lives until
+// the process exits and is itself Send and Sync.
+unsafe impl Send for RuntimeHandleFfi {}
+
+impl RuntimeHandleFfi {
+    #[cfg(not(feature = "tokio"))]
+    const NONE: Self = Self {
+        handle: std::ptr::null(),
+        tokio_major: 0,
+        size: 0,
+        align: 0,
+    };
+
+    /// The handle of the host's runtime, which is started on first use.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn host() -> Self {
+        let handle: &'static tokio::runtime::Handle = runtime::host().handle();
+        Self {
+            handle: std::ptr::from_ref(handle).cast(),
+            tokio_major: TOKIO_MAJOR,
+            size: size_of::<tokio::runtime::Handle>(),
+            align: align_of::<tokio::runtime::Handle>(),
+        }
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    pub(crate) fn host() -> Self {
+        Self::NONE
+    }
+}
+
+/// Takes the host's runtime handle out of `pool` for the library's worker
+/// threads, called by `export_executor!` before the executor is created.
+///
+/// Fails if the library uses the host's runtime but the host passed none or
+/// one of another tokio version.
+#[doc(hidden)]
+pub fn install(pool: &ErasedWorkerPool) -> Result<(), String> {
+    #[cfg(feature = "tokio")]
+    runtime::install(pool.runtime)?;
+
+    #[cfg(not(feature = "tokio"))]
+    let _ = pool;
+
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+pub use runtime::HostRuntime;
+
+#[cfg(feature = "tokio")]
+mod runtime {
+    use std::sync::OnceLock;
+
+    use iced_futures::{Executor, MaybeSend};
+    use tokio::runtime::{Builder, Handle, Runtime};
+
+    use super::{RuntimeHandleFfi, TOKIO_MAJOR};
+
+    /// The runtime of the host's copy of `hot_ice`.
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    /// The host's handle, in a library's copy of `hot_ice`.
+    static INSTALLED: OnceLock<Handle> = OnceLock::new();
+
+    pub(super) fn host() -> &'static Runtime {
+        RUNTIME.get_or_init(|| {
+            Builder::new_multi_thread()
+                .enable_all()
+                .thread_name("hot-ice-tokio")
+                .build()
+                .expect("hot_ice: failed to start the tokio runtime")
+        })
+    }
+
+    pub(super) fn install(ffi: RuntimeHandleFfi) -> Result<(), String> {
+        if ffi.handle.is_null() {
+            return Err(
+                "the host runs no tokio runtime, enable hot_ice's `tokio` feature in the host"
+                    .to_string(),
+            );
+        }
+        if ffi.tokio_major != TOKIO_MAJOR {
+            return Err(format!(
+                "the host runs tokio {}, the library was built with tokio {TOKIO_MAJOR}",
+                ffi.tokio_major
+            ));
+        }
+        if (ffi.size, ffi.align) != (size_of::<Handle>(), align_of::<Handle>()) {
+            return Err("the host's tokio runtime handle has another layout".to_string());
+        }
+
+        // SAFETY: the pointer was checked above and refers to the handle of
+        // the host's runtime, which is never dropped.
+        let handle = unsafe { &*ffi.handle.cast::<Handle>() };
+        let _ = INSTALLED.set(handle.clone());
+        Ok(())
+    }
+
+    /// The executor of a library's worker threads with the `tokio` feature:
+    /// the host's runtime, or a runtime of its own outside a library.
+    pub struct HostRuntime(Handle);
+
+    impl Executor for HostRuntime {
+        fn new() -> Result<Self, futures::io::Error> {
+            let handle = INSTALLED
+                .get()
+                .cloned()
+                .unwrap_or_else(|| host().handle().clone());
+            Ok(Self(handle))
+        }
+
+        fn spawn(&self, future: impl Future<Output = ()> + MaybeSend + 'static) {
+            let _ = self.0.spawn(future);
+        }
+
+        fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+            self.0.block_on(future)
+        }
+
+        fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+            let _guard = self.0.enter();
+            f()
+        }
+    }
+}
@@ -3,20 +3,21 @@
 //! This module provides the [`application`] function and [`HotIce`] builder
 //! for creating Iced applications with hot reloading support.
 
-use std::{
-    borrow::Cow,
-    sync::{Arc, Mutex},
-};
+use std::{borrow::Cow, convert::Infallible, marker::PhantomData, sync::Arc};
 
-use iced_core::{Element, Font, Settings, Size, theme, window};
+use iced_core::{Element, Font, Settings, Size, Theme, theme, window};
 use iced_futures::Executor;
 use iced_winit::{Error, runtime::Task};
 
 use crate::{
     error::HotIceError,
+    external::ExternalHandle,
     functions::{
         boot,
+        hot_dispatch::HotExtension,
+        hot_on_event::IntoHotOnEvent,
         hot_scale_factor::IntoHotScaleFactor,
+        hot_setup::IntoHotSetup,
         hot_style::IntoHotStyle,
         hot_subscription::IntoHotSubscription,
         hot_theme::IntoHotTheme,
@@ -24,13 +25,25 @@ use crate::{
         hot_update::{self, HotUpdate},
         hot_view::{self, HotView},
     },
-    hot_program::{self, HotProgram},
-    lib_reloader::LibReloader,
+    host_resource,
+    hot_program::{self, HotProgram, WithSettings, WithWindow},
+    library_handle::LibraryHandle,
     message::{DynMessage, MessageSource},
-    reloader::{FunctionState, Reload, ReloaderSettings},
+    reloader::{
+        FunctionState, FunctionStateCallback, HotFunction, Reload, ReloadPlaceholder,
+        ReloaderSettings,
+    },
+    type_hash::TypeHash,
     winit,
 };
 
+/// Called once the application has exited, see [`HotIce::on_exit`].
+pub(crate) type OnExit = Box<dyn FnOnce()>;
+
+/// The program of a [`HotIce`] with the settings and window settings of its
+/// builder, the program the reloader runs.
+pub(crate) type Configured<P> = WithWindow<WithSettings<P>>;
+
 /// Creates a new hot-reloadable Iced application.
 ///
 /// This is the main entry point for creating a hot-reloadable application.
@@ -199,13 +212,16 @@ use crate::{
 /// - **Hot** (green): Successfully loaded from dynamic library
 /// - **Fallback** (orange): Failed to load, using static version
 /// - **Error** (red): Function returned an error
-pub fn application<State, Message, Theme, Renderer>(
-    boot: impl boot::Boot<State, Message>,
-    update: impl hot_update::IntoHotUpdate<State, Message>,
-    view: impl for<'a> hot_view::IntoHotView<'a, State, Message, Theme, Renderer>,
+pub fn application<State, Message, Theme, Renderer, BootMarker, UpdateMarker, ViewMarker>(
+    boot: impl boot::Boot<State, Message, BootMarker>,
+    update: impl hot_update::IntoHotUpdate<State, Message, UpdateMarker>,
+    view: impl for<'a> hot_view::IntoHotView<'a, State, Message, Theme, Renderer, ViewMarker>,
 ) -> HotIce<impl HotProgram<State = State, Message = Message, Theme = Theme, Renderer = Renderer>>
 where
     State: 'static,
+    BootMarker: 'static,
+    UpdateMarker: 'static,
+    ViewMarker: 'static,
     Message: DynMessage + Clone,
     Theme: theme::Base + iced_widget::container::Catalog + iced_widget::text::Catalog,
     Renderer: hot_program::Renderer,
@@ -222,22 +238,46 @@ where
 
     // initiate_lib_reloaders(&hot_view, &hot_update, dylib_path);
 
-    struct Instance<State, Message, Theme, Renderer, Boot, Update, View> {
+    struct Instance<
+        State,
+        Message,
+        Theme,
+        Renderer,
+        Boot,
+        Update,
+        View,
+        BootMarker,
+        UpdateMarker,
+        ViewMarker,
+    > {
         boot: Boot,
-        update: HotUpdate<Update, State, Message>,
-        view: HotView<View, State, Message, Theme, Renderer>,
+        boot_marker: PhantomData<fn() -> BootMarker>,
+        update: HotUpdate<Update, State, Message, UpdateMarker>,
+        view: HotView<View, State, Message, Theme, Renderer, ViewMarker>,
     }
 
-    impl<State, Message, Theme, Renderer, Boot, Update, View> HotProgram
-        for Instance<State, Message, Theme, Renderer, Boot, Update, View>
+    impl<State, Message, Theme, Renderer, Boot, Update, View, BootMarker, UpdateMarker, ViewMarker>
+        HotProgram
+        for Instance<
+            State,
+            Message,
+            Theme,
+            Renderer,
+            Boot,
+            Update,
+            View,
+            BootMarker,
+            UpdateMarker,
+            ViewMarker,
+        >
     where
         State: 'static,
         Message: DynMessage + Clone,
         Theme: theme::Base + iced_widget::container::Catalog + iced_widget::text::Catalog,
         Renderer: hot_program::Renderer,
-        Boot: boot::Boot<State, Message>,
-        Update: hot_update::IntoHotUpdate<State, Message>,
-        View: for<'a> hot_view::IntoHotView<'a, State, Message, Theme, Renderer>,
+        Boot: boot::Boot<State, Message, BootMarker>,
+        Update: hot_update::IntoHotUpdate<State, Message, UpdateMarker>,
+        View: for<'a> hot_view::IntoHotView<'a, State, Message, Theme, Renderer, ViewMarker>,
     {
         type State = State;
         type Message = Message;
@@ -260,7 +300,7 @@ where
             &self,
             state: &mut Self::State,
             message: MessageSource<Self::Message>,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(Task<MessageSource<Self::Message>>, FunctionState), HotIceError> {
             self.update.update(state, message, reloader)
         }
@@ -268,8 +308,8 @@ where
         fn view<'a>(
             &self,
             state: &'a Self::State,
-            _window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            window: window::Id,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<
             (
                 Element<'a, MessageSource<Self::Message>, Self::Theme, Self::Renderer>,
@@ -281,7 +321,7 @@ where
             Theme: 'a,
             Renderer: 'a,
         {
-            self.view.view(state, reloader)
+            self.view.view(state, window, reloader)
         }
 
         fn settings(&self) -> Settings {
@@ -296,6 +336,7 @@ where
     HotIce {
         program: Instance {
             boot,
+            boot_marker: PhantomData,
             update: hot_update,
             view: hot_view,
         },
@@ -303,6 +344,9 @@ where
         window: window::Settings::default(),
         reloader_settings: ReloaderSettings::default(),
         lib_name,
+        on_exit: None,
+        on_function_state_change: None,
+        reload_placeholder: None,
     }
 }
 
@@ -349,6 +393,9 @@ where
     window: window::Settings,
     reloader_settings: ReloaderSettings,
     lib_name: &'static str,
+    on_exit: Option<OnExit>,
+    on_function_state_change: Option<FunctionStateCallback>,
+    reload_placeholder: Option<ReloadPlaceholder<P::Renderer>>,
 }
 
 impl<P> HotIce<P>
@@ -375,16 +422,7 @@ where
     /// }
     /// ```
     pub fn run(self) -> Result<(), Error> {
-        let fonts = self.settings.fonts.clone();
-
-        let program = Reload::new(
-            self.program,
-            self.reloader_settings,
-            self.settings,
-            self.window,
-            self.lib_name,
-            fonts,
-        );
+        let (program, on_exit) = self.into_reload();
 
         #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
         let program = {
@@ -397,7 +435,157 @@ where
             iced_devtools::attach(program)
         };
 
-        winit::run(program)
+        let result = winit::run(program);
+
+        if let Some(on_exit) = on_exit {
+            on_exit();
+        }
+        result
+    }
+
+    /// The program with the configured settings wrapped in its reloader, and
+    /// the exit callback.
+    pub(crate) fn into_reload(self) -> (Reload<Configured<P>>, Option<OnExit>) {
+        let program = hot_program::with_window(
+            hot_program::with_settings(self.program, self.settings),
+            self.window,
+        );
+        let mut program = Reload::new(
+            program,
+            self.reloader_settings,
+            self.lib_name,
+            self.on_function_state_change,
+        );
+        program.set_reload_placeholder(self.reload_placeholder);
+        (program, self.on_exit)
+    }
+
+    /// The program, its reloader settings and the name of its library.
+    pub(crate) fn into_parts(self) -> (P, ReloaderSettings, &'static str) {
+        (self.program, self.reloader_settings, self.lib_name)
+    }
+
+    /// Sets a function that is called once the application has exited.
+    ///
+    /// It runs after the reloader has shut down: the worker has been
+    /// drained, the program state has been dropped and every library has
+    /// been unloaded.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// hot_ice::application(State::boot, State::update, State::view)
+    ///     .on_exit(|| println!("bye"))
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn on_exit(self, f: impl FnOnce() + 'static) -> Self {
+        Self {
+            on_exit: Some(Box::new(f)),
+            ..self
+        }
+    }
+
+    /// Sets a function that is called whenever a hot function changes its
+    /// [`FunctionState`], with the function, its old and its new state.
+    ///
+    /// It is called on the event loop right after the call that changed the
+    /// state, e.g. when `update` falls back to the static function or starts
+    /// returning errors. Every change is also emitted as a `tracing` event
+    /// with the target `hot_ice::function_state`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let app = hot_ice::application(State::boot, State::update, State::view);
+    /// let handle = app.external_handle();
+    ///
+    /// app.on_function_state_change(move |function, _old, new| {
+    ///     if matches!(new, FunctionState::Error(_)) {
+    ///         let _ = handle.send(Message::PauseSimulation);
+    ///     }
+    /// })
+    /// .run()
+    /// .unwrap();
+    /// ```
+    pub fn on_function_state_change(
+        self,
+        f: impl Fn(HotFunction, &FunctionState, &FunctionState) + 'static,
+    ) -> Self {
+        Self {
+            on_function_state_change: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
+    /// Returns a handle for sending messages to the application from
+    /// outside of iced, e.g. from another async runtime.
+    ///
+    /// The handle can be obtained before the application runs, sending
+    /// fails until the event loop has started.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let app = hot_ice::application(State::boot, State::update, State::view);
+    /// let handle = app.external_handle();
+    ///
+    /// std::thread::spawn(move || {
+    ///     let _ = handle.send(Message::Tick);
+    /// });
+    ///
+    /// app.run().unwrap();
+    /// ```
+    pub fn external_handle(&self) -> ExternalHandle<P::Message> {
+        ExternalHandle::new::<Configured<P>>()
+    }
+
+    /// Registers a [`HotExtension`], a hot function the application calls
+    /// itself. Its export is checked after every load like the ones of the
+    /// builder's functions, calling it before registering it works as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let export_report = HotExtension::new(State::export_report);
+    ///
+    /// hot_ice::application(State::boot, State::update, State::view)
+    ///     .extension(&export_report)
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn extension<Args, Output>(self, extension: &HotExtension<P::State, Args, Output>) -> Self
+    where
+        Args: Send + 'static,
+        Output: Send + 'static,
+    {
+        extension.register();
+        self
+    }
+
+    /// Registers a resource that is owned by the host and lent to the hot
+    /// library, e.g. a database pool. It is never serialized or dropped, hot
+    /// code gets it from the [`Context`](crate::Context) its `boot` or
+    /// `update` takes. Its type derives [`TypeHash`], the
+    /// resource is looked up by that hash.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// hot_ice::application(State::boot, State::update, State::view)
+    ///     .host_resource(Pool::connect("postgres://localhost"))
+    ///     .run()
+    ///     .unwrap();
+    ///
+    /// // In the ui crate
+    /// fn boot(context: &hot_ice::Context) -> Self {
+    ///     let pool = context.resource::<Pool>().expect("registered by the host");
+    ///     Self { pool }
+    /// }
+    /// ```
+    pub fn host_resource<T: TypeHash + Send + Sync>(self, resource: T) -> Self {
+        host_resource::register(resource);
+        self
     }
 
     /// Sets the hot reloader configuration.
@@ -433,6 +621,61 @@ where
         }
     }
 
+    /// Enables or disables the reloader's UI in the window: the status and
+    /// error overlay, the banners, the metrics table and the screens shown
+    /// while compiling or reloading. The application still reloads, the
+    /// reloader's status is logged and shown in the window title. Sets
+    /// [`ReloaderSettings::overlay`], so call it after
+    /// [`reloader_settings`](Self::reloader_settings).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// hot_ice::application(State::boot, State::update, State::view)
+    ///     .with_overlay(false)
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn with_overlay(self, overlay: bool) -> Self {
+        Self {
+            reloader_settings: ReloaderSettings {
+                overlay,
+                ..self.reloader_settings
+            },
+            ..self
+        }
+    }
+
+    /// Sets the view shown while a new library is swapped in, instead of the
+    /// centered "Reloading..." text. It is called with the theme of the
+    /// reloader's UI, see [`ReloaderSettings::overlay_palette`], fills the
+    /// window and can't produce messages. It is also shown without the
+    /// overlay, see [`with_overlay`](Self::with_overlay).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// hot_ice::application(State::boot, State::update, State::view)
+    ///     .reload_placeholder(|theme| {
+    ///         let background = theme.palette().primary;
+    ///         container(text("Applying your changes").size(32))
+    ///             .center(Fill)
+    ///             .style(move |_| container::background(background))
+    ///             .into()
+    ///     })
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn reload_placeholder(
+        self,
+        placeholder: impl Fn(&Theme) -> Element<'static, Infallible, Theme, P::Renderer> + 'static,
+    ) -> Self {
+        Self {
+            reload_placeholder: Some(Arc::new(placeholder)),
+            ..self
+        }
+    }
+
     /// Sets the [`Settings`] that will be used to run the application.
     ///
     /// This overwrites all previous settings. For individual settings,
@@ -688,7 +931,8 @@ where
     /// Sets the window title function.
     ///
     /// The title function is called to get the window title, allowing
-    /// dynamic titles based on application state.
+    /// dynamic titles based on application state. It may take the
+    /// [`window::Id`] of the window as well, or be a constant `&'static str`.
     ///
     /// # Example
     ///
@@ -704,16 +948,26 @@ where
     ///     .run()
     ///     .unwrap();
     /// ```
-    pub fn title(
+    pub fn title<Marker>(
         self,
-        f: impl IntoHotTitle<P::State>,
-    ) -> HotIce<impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme>> {
+        f: impl IntoHotTitle<P::State, Marker>,
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    > {
         HotIce {
             program: hot_program::with_title(self.program, f),
             settings: self.settings,
             window: self.window,
             reloader_settings: self.reloader_settings,
             lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
         }
     }
 
@@ -744,13 +998,23 @@ where
     pub fn subscription(
         self,
         f: impl IntoHotSubscription<P::State, P::Message>,
-    ) -> HotIce<impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme>> {
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    > {
         HotIce {
             program: hot_program::with_subscription(self.program, f),
             settings: self.settings,
             window: self.window,
             reloader_settings: self.reloader_settings,
             lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
         }
     }
 
@@ -782,13 +1046,23 @@ where
     pub fn theme(
         self,
         f: impl IntoHotTheme<P::State, P::Theme>,
-    ) -> HotIce<impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme>> {
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    > {
         HotIce {
             program: hot_program::with_theme(self.program, f),
             settings: self.settings,
             window: self.window,
             reloader_settings: self.reloader_settings,
             lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
         }
     }
 
@@ -815,13 +1089,23 @@ where
     pub fn style(
         self,
         f: impl IntoHotStyle<P::State, P::Theme>,
-    ) -> HotIce<impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme>> {
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    > {
         HotIce {
             program: hot_program::with_style(self.program, f),
             settings: self.settings,
             window: self.window,
             reloader_settings: self.reloader_settings,
             lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
         }
     }
 
@@ -829,6 +1113,7 @@ where
     ///
     /// The scale factor controls the size of UI elements. A value of `2.0`
     /// makes everything twice as large (useful for high-DPI displays).
+    /// The function may return an `f32` or an `f64`.
     ///
     /// # Example
     ///
@@ -847,13 +1132,123 @@ where
     pub fn scale_factor(
         self,
         f: impl IntoHotScaleFactor<P::State>,
-    ) -> HotIce<impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme>> {
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    > {
         HotIce {
             program: hot_program::with_scale_factor(self.program, f),
             settings: self.settings,
             window: self.window,
             reloader_settings: self.reloader_settings,
             lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
+        }
+    }
+
+    /// Sets a function that runs on the state after every reload.
+    ///
+    /// `boot` only runs once, when the application starts. State derived
+    /// from code, like caches computed from the state or fonts registered by
+    /// the library, is redone here after each reload instead of resetting
+    /// the whole state. The function should be idempotent, it does not run
+    /// on startup.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// impl State {
+    ///     #[hot_ice::hot_fn]
+    ///     fn setup(&mut self) -> Task<Message> {
+    ///         self.layout_cache = Layout::compute(&self.items);
+    ///         Task::none()
+    ///     }
+    /// }
+    ///
+    /// hot_ice::application(State::boot, State::update, State::view)
+    ///     .setup(State::setup)
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn setup(
+        self,
+        f: impl IntoHotSetup<P::State, P::Message>,
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    > {
+        HotIce {
+            program: hot_program::with_setup(self.program, f),
+            settings: self.settings,
+            window: self.window,
+            reloader_settings: self.reloader_settings,
+            lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
+        }
+    }
+
+    /// Sets a function mapping runtime events to messages.
+    ///
+    /// The function gets every keyboard, mouse, touch and window event that
+    /// no widget captured, like a subscription of `iced::event::listen_with`.
+    /// It is called by the host as the events arrive rather than from a
+    /// subscription of the library, so editing it reloads like `view` and
+    /// does not restart anything.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use hot_ice::iced::{Event, keyboard, window};
+    ///
+    /// impl State {
+    ///     #[hot_ice::hot_fn]
+    ///     fn on_event(&self, event: Event, _window: window::Id) -> Option<Message> {
+    ///         match event {
+    ///             Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) => {
+    ///                 Some(Message::KeyPressed(key))
+    ///             }
+    ///             _ => None,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// hot_ice::application(State::boot, State::update, State::view)
+    ///     .on_event(State::on_event)
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn on_event(
+        self,
+        f: impl IntoHotOnEvent<P::State, P::Message>,
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    > {
+        HotIce {
+            program: hot_program::with_on_event(self.program, f),
+            settings: self.settings,
+            window: self.window,
+            reloader_settings: self.reloader_settings,
+            lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
         }
     }
 
@@ -874,7 +1269,14 @@ where
     /// ```
     pub fn executor<E>(
         self,
-    ) -> HotIce<impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme>>
+    ) -> HotIce<
+        impl HotProgram<
+            State = P::State,
+            Message = P::Message,
+            Theme = P::Theme,
+            Renderer = P::Renderer,
+        >,
+    >
     where
         E: Executor,
     {
@@ -884,6 +1286,9 @@ where
             window: self.window,
             reloader_settings: self.reloader_settings,
             lib_name: self.lib_name,
+            on_exit: self.on_exit,
+            on_function_state_change: self.on_function_state_change,
+            reload_placeholder: self.reload_placeholder,
         }
     }
 }
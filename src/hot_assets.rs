@@ -0,0 +1,407 @@
+//! Assets that are reloaded without rebuilding the library.
+//!
+//! Editing a translation should not cost a rebuild of the ui crate.
+//! [`text_bundle`] parses a file of messages once, watches it and replaces
+//! the bundle whenever the file changes, after which the reloader calls
+//! `view` again so the new text shows up.
+//!
+//! Assets live in the host. Hot code reaches them through its
+//! [`Context`](crate::Context), [`text_bundle`] looks them up in
+//! [`Context::current`](crate::Context::current).
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crossfire::{AsyncRx, MTx, mpsc};
+use notify::RecursiveMode;
+use notify_debouncer_full::new_debouncer;
+
+use crate::log_target::RELOAD;
+
+/// Time to wait for an editor to finish writing a file.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Loaded assets by path. Replaced assets are leaked, they are referenced
+/// as `&'static` from code that may run in any library generation.
+static ASSETS: Mutex<Option<HashMap<PathBuf, &'static (dyn Any + Send + Sync)>>> = Mutex::new(None);
+
+static SUBSCRIBERS: Mutex<Vec<MTx<()>>> = Mutex::new(Vec::new());
+
+/// A file parsed into a value that is replaced whenever the file changes.
+pub(crate) trait Asset: Default + PartialEq + Send + Sync + 'static {
+    /// Names the asset in log messages.
+    const NAME: &'static str;
+
+    fn parse(path: &Path, source: &str) -> Result<Self, String>;
+}
+
+/// Messages by key, parsed from a Fluent (`.ftl`) or JSON file.
+///
+/// Only simple Fluent messages are supported: `key = value` lines, indented
+/// continuation lines and `#` comments. JSON files hold an object of
+/// strings, nested objects are flattened to dotted keys.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TextBundle {
+    messages: HashMap<String, String>,
+}
+
+impl Asset for TextBundle {
+    const NAME: &'static str = "text bundle";
+
+    fn parse(path: &Path, source: &str) -> Result<Self, String> {
+        Self::parse(path, source)
+    }
+}
+
+impl TextBundle {
+    /// Parses a bundle, JSON if `path` ends in `.json` and Fluent otherwise.
+    pub fn parse(path: &Path, source: &str) -> Result<Self, String> {
+        let messages = if path.extension().is_some_and(|ext| ext == "json") {
+            let value: serde_json::Value =
+                serde_json::from_str(source).map_err(|e| e.to_string())?;
+            let mut messages = HashMap::new();
+            flatten_json(String::new(), &value, &mut messages);
+            messages
+        } else {
+            parse_ftl(source)
+        };
+
+        Ok(Self { messages })
+    }
+
+    /// The message for `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    /// The message for `key`, or `key` itself if the bundle has none, so
+    /// missing translations stand out in the UI.
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.get(key).unwrap_or(key)
+    }
+
+    /// Number of messages in the bundle.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Whether the bundle has no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// The bundle parsed from `path`, reloaded whenever the file changes.
+///
+/// Returns the bundle current at the time of the call, call it from `view`
+/// instead of storing it to pick up changes. The first call for a path reads
+/// the file and starts watching it. A file that cannot be read or parsed
+/// gives an empty bundle and the error is logged, the previous bundle is kept
+/// if a later edit breaks it.
+pub fn text_bundle(path: impl AsRef<Path>) -> &'static TextBundle {
+    crate::Context::current().text_bundle(path)
+}
+
+/// The source of a shader, e.g. WGSL for a custom `iced_wgpu` primitive,
+/// reloaded whenever the file changes. See [`hot_shader`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Shader {
+    source: String,
+    version: u32,
+}
+
+impl Asset for Shader {
+    const NAME: &'static str = "shader";
+
+    fn parse(_path: &Path, source: &str) -> Result<Self, String> {
+        Ok(Self {
+            source: source.to_string(),
+            version: crc32fast::hash(source.as_bytes()),
+        })
+    }
+}
+
+impl Shader {
+    /// The source of the shader.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Changes whenever the source does, a hash of it.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+/// The shader read from `path`, reloaded whenever the file changes.
+///
+/// Shader tweaks show up without rebuilding the ui crate: the view is
+/// rebuilt, the primitive prepared again, and a [`ShaderCache`] kept in its
+/// pipeline creates the render pipeline again from the new source. As with
+/// [`text_bundle`], call it each time instead of storing the shader.
+///
+/// ```rust,ignore
+/// fn prepare(&self, pipeline: &mut Pipeline, device: &wgpu::Device, ...) {
+///     let shader = hot_ice::hot_shader("ui/shaders/blur.wgsl");
+///     let render = pipeline.render.get(shader, |source| {
+///         create_render_pipeline(device, pipeline.format, source)
+///     });
+///     ...
+/// }
+/// ```
+pub fn hot_shader(path: impl AsRef<Path>) -> &'static Shader {
+    crate::Context::current().shader(path)
+}
+
+/// A value created from a [`Shader`], e.g. a render pipeline, created again
+/// whenever the shader changes.
+///
+/// It lives in the host, in the pipeline storage of the renderer, so it
+/// outlives reloads of the library.
+#[derive(Debug)]
+pub struct ShaderCache<T> {
+    cached: Option<(u32, T)>,
+}
+
+impl<T> Default for ShaderCache<T> {
+    fn default() -> Self {
+        Self { cached: None }
+    }
+}
+
+impl<T> ShaderCache<T> {
+    /// An empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value created from `shader`, `create` is called with its source
+    /// the first time and whenever its version changed.
+    pub fn get(&mut self, shader: &Shader, create: impl FnOnce(&str) -> T) -> &T {
+        if self
+            .cached
+            .as_ref()
+            .is_none_or(|(version, _)| *version != shader.version)
+        {
+            log::debug!(target: RELOAD, "Creating from shader version {:#010x}", shader.version);
+            self.cached = Some((shader.version, create(&shader.source)));
+        }
+        let (_, value) = self.cached.as_ref().expect("created above");
+        value
+    }
+}
+
+/// The asset parsed from `path`, loading and watching the file on the first
+/// call. Only called in the host, libraries go through its context.
+pub(crate) fn load<T: Asset>(path: &Path) -> &'static T {
+    let Ok(mut assets) = ASSETS.lock() else {
+        return Box::leak(Box::default());
+    };
+    let assets = assets.get_or_insert_with(HashMap::new);
+
+    // Called from `view`, canonical paths skip the file system.
+    let key = if assets.contains_key(path) {
+        path.to_path_buf()
+    } else {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    };
+    if let Some(asset) = assets.get(&key) {
+        // A file loaded as another kind of asset gives the default.
+        return asset
+            .downcast_ref()
+            .unwrap_or_else(|| Box::leak(Box::default()));
+    }
+
+    let asset: &'static T = Box::leak(Box::new(read(&key).unwrap_or_else(|err| {
+        log::error!(target: RELOAD, "Failed to load {} {}: {}", T::NAME, key.display(), err);
+        T::default()
+    })));
+    assets.insert(key.clone(), asset);
+    watch::<T>(key);
+    asset
+}
+
+/// Receives a message after every asset change.
+pub(crate) fn subscribe_to_changes() -> AsyncRx<()> {
+    let (tx, rx) = mpsc::unbounded_async();
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.push(tx);
+    }
+    rx
+}
+
+fn read<T: Asset>(path: &Path) -> Result<T, String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    T::parse(path, &source)
+}
+
+/// Reloads the asset of `path` whenever the file changes. The directory is
+/// watched instead of the file, editors often replace files on save.
+fn watch<T: Asset>(path: PathBuf) {
+    let Some(dir) = path.parent().map(Path::to_path_buf) else {
+        return;
+    };
+
+    let spawned = thread::Builder::new()
+        .name("hot-ice-assets".into())
+        .spawn(move || {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let mut debouncer = match new_debouncer(DEBOUNCE, None, tx) {
+                Ok(debouncer) => debouncer,
+                Err(err) => {
+                    log::error!(target: RELOAD, "Failed to watch {}: {}", path.display(), err);
+                    return;
+                }
+            };
+            if let Err(err) = debouncer.watch(&dir, RecursiveMode::NonRecursive) {
+                log::error!(target: RELOAD, "Failed to watch {}: {}", dir.display(), err);
+                return;
+            }
+
+            for events in rx {
+                let Ok(events) = events else {
+                    continue;
+                };
+                let touched = events.iter().any(|event| {
+                    !matches!(event.kind, notify::EventKind::Access(_))
+                        && event.paths.iter().any(|p| p == &path)
+                });
+                if touched {
+                    reload::<T>(&path);
+                }
+            }
+        });
+
+    if let Err(err) = spawned {
+        log::error!(target: RELOAD, "Failed to spawn the asset watcher: {}", err);
+    }
+}
+
+fn reload<T: Asset>(path: &Path) {
+    let asset = match read::<T>(path) {
+        Ok(asset) => asset,
+        Err(err) => {
+            log::error!(target: RELOAD, "Failed to reload {} {}: {}", T::NAME, path.display(), err);
+            return;
+        }
+    };
+
+    if let Ok(mut assets) = ASSETS.lock() {
+        let assets = assets.get_or_insert_with(HashMap::new);
+        let old = assets.get(path).and_then(|old| old.downcast_ref::<T>());
+        if old.is_some_and(|old| *old == asset) {
+            return;
+        }
+        assets.insert(path.to_path_buf(), Box::leak(Box::new(asset)));
+    }
+
+    log::info!(target: RELOAD, "Reloaded {} {}", T::NAME, path.display());
+    if let Ok(subscribers) = SUBSCRIBERS.lock() {
+        for tx in &*subscribers {
+            let _ = tx.send(());
+        }
+    }
+}
+
+fn parse_ftl(source: &str) -> HashMap<String, String> {
+    let mut messages = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in source.lines() {
+        let continues = line.starts_with([' ', '\t']) && !line.trim().is_empty();
+        if continues && let Some((_, value)) = &mut current {
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            value.push_str(line.trim());
+            continue;
+        }
+
+        messages.extend(current.take());
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            current = Some((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    messages.extend(current);
+    messages
+}
+
+fn flatten_json(prefix: String, value: &serde_json::Value, out: &mut HashMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_json(key, value, out);
+            }
+        }
+        serde_json::Value::String(text) => {
+            out.insert(prefix, text.clone());
+        }
+        other => {
+            out.insert(prefix, other.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fluent_messages() {
+        let source =
+            "# Greetings\nhello = Hello\nwelcome =\n    Welcome back,\n    friend\n\nbye=Bye\n";
+        let bundle = TextBundle::parse(Path::new("en.ftl"), source).unwrap();
+
+        assert_eq!(bundle.get("hello"), Some("Hello"));
+        assert_eq!(bundle.get("welcome"), Some("Welcome back,\nfriend"));
+        assert_eq!(bundle.get("bye"), Some("Bye"));
+        assert_eq!(bundle.text("missing"), "missing");
+        assert_eq!(bundle.len(), 3);
+    }
+
+    #[test]
+    fn shader_caches_are_recreated_on_change() {
+        let path = Path::new("blur.wgsl");
+        let first = Shader::parse(path, "@fragment fn main() {}").unwrap();
+        let mut cache = ShaderCache::new();
+        let mut created = 0;
+
+        let mut create = |source: &str| {
+            created += 1;
+            source.len()
+        };
+        assert_eq!(*cache.get(&first, &mut create), 22);
+        assert_eq!(*cache.get(&first, &mut create), 22);
+
+        let edited = Shader::parse(path, "@fragment fn main() { }").unwrap();
+        assert_ne!(first.version(), edited.version());
+        assert_eq!(*cache.get(&edited, &mut create), 23);
+        assert_eq!(created, 2);
+    }
+
+    #[test]
+    fn flattens_json_objects() {
+        let source = r#"{"menu": {"open": "Open", "count": 3}, "title": "Editor"}"#;
+        let bundle = TextBundle::parse(Path::new("en.json"), source).unwrap();
+
+        assert_eq!(bundle.get("menu.open"), Some("Open"));
+        assert_eq!(bundle.get("menu.count"), Some("3"));
+        assert_eq!(bundle.get("title"), Some("Editor"));
+        assert!(TextBundle::parse(Path::new("en.json"), "{").is_err());
+    }
+}
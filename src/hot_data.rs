@@ -0,0 +1,182 @@
+//! Data tables of the library, read from the binary.
+//!
+//! `#[hot_data]` exports a getter for a `static` or `const` item of the ui
+//! crate. [`hot_data`] reads the item of the loaded library, so code of the
+//! binary sees an edited table after the next reload without a hot function
+//! around it:
+//!
+//! ```rust,ignore
+//! // In the ui crate
+//! #[derive(hot_ice::TypeHash)]
+//! pub struct LevelDef { /* ... */ }
+//!
+//! #[hot_ice::hot_data]
+//! pub static LEVELS: &[LevelDef] = &[LevelDef::new("meadow", 3), LevelDef::new("cave", 5)];
+//!
+//! // In the binary
+//! let hot = hot_ice::hot_data::<&[LevelDef]>("LEVELS");
+//! let levels: &[LevelDef] = match &hot {
+//!     Some(levels) => levels,
+//!     None => ui::LEVELS,
+//! };
+//! ```
+//!
+//! The item's type implements [`TypeHash`], the getter reports the hash of
+//! its full definition and the item is only read if it matches the `T` of
+//! the binary, so a field added to `LevelDef` is not read with the old
+//! layout. The getter is looked up once per library and type, later reads of
+//! the same generation only take the library's reference count.
+//!
+//! Every reloader of the process registers the library of its program, an
+//! item is read from the first one exporting it, or with [`hot_data_in`]
+//! from the library of the given ui crate.
+
+use std::any::type_name;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+use hot_ice_common::HOT_DATA_SYMBOL_PREFIX;
+
+use crate::library_handle::{LibraryHandle, LoadedLibrary};
+use crate::log_target::RELOAD;
+use crate::type_hash::TypeHash;
+
+/// What the getter `#[hot_data]` exports reports about its item.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct HotDataExport {
+    pub data: *const (),
+    pub type_hash: u64,
+}
+
+/// The item of the loaded library, which stays loaded while this is held.
+///
+/// References inside the item point into the library as well: a
+/// `&'static [LevelDef]` copied out of it is only valid as long as the
+/// `HotData` it came from.
+pub struct HotData<T: 'static> {
+    value: &'static T,
+    _library: LoadedLibrary,
+}
+
+impl<T: 'static> Deref for HotData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T: fmt::Debug + 'static> fmt::Debug for HotData<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// The libraries of the reloaders of the process, by the name of their ui
+/// crate, `hot_data` reads from them.
+static LIBRARIES: Mutex<Vec<(String, LibraryHandle)>> = Mutex::new(Vec::new());
+
+/// Makes `hot_data` read from the libraries of the ui crate `lib_name`
+/// published to `handle`, replacing an earlier handle of the same crate.
+pub(crate) fn follow(lib_name: &str, handle: LibraryHandle) {
+    if let Ok(mut libraries) = LIBRARIES.lock() {
+        libraries.retain(|(name, _)| name != lib_name);
+        libraries.push((lib_name.to_string(), handle));
+    }
+}
+
+/// Stops reading from the libraries of `lib_name`, when its reloader shuts
+/// down.
+pub(crate) fn unfollow(lib_name: &str) {
+    if let Ok(mut libraries) = LIBRARIES.lock() {
+        libraries.retain(|(name, _)| name != lib_name);
+    }
+}
+
+/// The item `#[hot_data]` exports as `name` from the loaded library.
+///
+/// Read from the first library exporting `name`, if several reloaders run.
+/// `None` while no library is loaded, if no library exports `name` or
+/// exports it with another definition of `T`. Fall back to the item linked
+/// into the binary then.
+pub fn hot_data<T: TypeHash>(name: &'static str) -> Option<HotData<T>> {
+    let handles: Vec<_> = LIBRARIES
+        .lock()
+        .ok()?
+        .iter()
+        .map(|(_, handle)| handle.clone())
+        .collect();
+    handles.iter().find_map(|handle| read(handle, name))
+}
+
+/// The item `#[hot_data]` exports as `name` from the loaded library of the
+/// ui crate `lib_name`, like [`hot_data`].
+pub fn hot_data_in<T: TypeHash>(lib_name: &str, name: &'static str) -> Option<HotData<T>> {
+    let handle = LIBRARIES
+        .lock()
+        .ok()?
+        .iter()
+        .find(|(library, _)| library == lib_name)
+        .map(|(_, handle)| handle.clone())?;
+    read(&handle, name)
+}
+
+/// The item `name` of the library loaded in `handle`.
+fn read<T: TypeHash>(handle: &LibraryHandle, name: &'static str) -> Option<HotData<T>> {
+    let library = handle.load();
+    let generation = library.resolved()?;
+
+    let type_hash = T::TYPE_HASH;
+    let address = generation.data(name, type_hash, || {
+        let getter = format!("{HOT_DATA_SYMBOL_PREFIX}{name}");
+        let getter = unsafe { generation.get_symbol::<fn() -> HotDataExport>(&getter) }.ok()?;
+        let export = getter();
+        if export.type_hash != type_hash {
+            log::warn!(
+                target: RELOAD,
+                "The library exports {name} with another definition of {}",
+                type_name::<T>()
+            );
+            return None;
+        }
+        Some(export.data as usize)
+    })?;
+
+    Some(HotData {
+        value: unsafe { &*(address as *const T) },
+        _library: library,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_read_without_a_library() {
+        follow("ui", LibraryHandle::new());
+        assert!(hot_data::<&[u32]>("LEVELS").is_none());
+        assert!(hot_data_in::<&[u32]>("ui", "LEVELS").is_none());
+        assert!(hot_data_in::<&[u32]>("other_ui", "LEVELS").is_none());
+        unfollow("ui");
+    }
+
+    #[test]
+    fn each_reloader_keeps_its_library() {
+        follow("first_ui", LibraryHandle::new());
+        follow("second_ui", LibraryHandle::new());
+        follow("first_ui", LibraryHandle::new());
+        let names: Vec<_> = LIBRARIES
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .filter(|name| name.ends_with("_ui"))
+            .collect();
+        assert_eq!(names, ["second_ui", "first_ui"]);
+        unfollow("first_ui");
+        unfollow("second_ui");
+    }
+}
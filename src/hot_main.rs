@@ -0,0 +1,342 @@
+//! Support for [`hot_main!`](crate::hot_main).
+//!
+//! The macro passes every optional method of the state to the builder, e.g.
+//! `State::theme`. [`DefaultMethods`] is implemented for every type, so a
+//! path names the trait's [`Missing`] constant when the state does not define
+//! the method itself, inherent items take precedence over trait items. The
+//! function is wrapped in an [`Optional`], whose inherent methods skip the
+//! builder call for [`Missing`] before the traits of [`hot`] and [`plain`]
+//! are considered.
+
+/// Stands in for a method the state does not define.
+pub struct Missing;
+
+/// Names each optional method of the builder, shadowed by the methods the
+/// state defines.
+#[allow(non_upper_case_globals)]
+pub trait DefaultMethods {
+    const subscription: Missing = Missing;
+    const theme: Missing = Missing;
+    const style: Missing = Missing;
+    const scale_factor: Missing = Missing;
+    const title: Missing = Missing;
+    const setup: Missing = Missing;
+}
+
+impl<T: ?Sized> DefaultMethods for T {}
+
+/// A method of the state, or [`Missing`], passed to a builder method of the
+/// same name.
+pub struct Optional<F>(pub F);
+
+impl Optional<Missing> {
+    pub fn subscription<B>(self, builder: B) -> B {
+        builder
+    }
+
+    pub fn theme<B>(self, builder: B) -> B {
+        builder
+    }
+
+    pub fn style<B>(self, builder: B) -> B {
+        builder
+    }
+
+    pub fn scale_factor<B>(self, builder: B) -> B {
+        builder
+    }
+
+    pub fn title<B>(self, builder: B) -> B {
+        builder
+    }
+
+    pub fn setup<B>(self, builder: B) -> B {
+        builder
+    }
+}
+
+/// Generates a trait for each optional builder method, passing the function
+/// of an [`Optional`] to it. A method whose functions come in several forms
+/// names the marker telling them apart, e.g. `Title<Marker>`.
+macro_rules! optional_methods {
+    (
+        builder = $builder:ident, program = $program:ident;
+        $($trait:ident $(<$marker:ident>)? :: $method:ident($($bound:tt)+);)+
+    ) => {$(
+        pub trait $trait<P: $program $(, $marker)?> {
+            fn $method(
+                self,
+                builder: $builder<P>,
+            ) -> $builder<
+                impl $program<State = P::State, Message = P::Message, Theme = P::Theme>,
+            >;
+        }
+
+        impl<P, F $(, $marker)?> $trait<P $(, $marker)?> for super::Optional<F>
+        where
+            P: $program + 'static,
+            F: $($bound)+,
+        {
+            fn $method(
+                self,
+                builder: $builder<P>,
+            ) -> $builder<
+                impl $program<State = P::State, Message = P::Message, Theme = P::Theme>,
+            > {
+                builder.$method(self.0)
+            }
+        }
+    )+};
+}
+
+/// The optional methods of [`HotIce`](crate::hot_application::HotIce).
+pub mod hot {
+    use crate::functions::{
+        hot_scale_factor::IntoHotScaleFactor, hot_setup::IntoHotSetup, hot_style::IntoHotStyle,
+        hot_subscription::IntoHotSubscription, hot_theme::IntoHotTheme, hot_title::IntoHotTitle,
+    };
+    use crate::hot_application::HotIce;
+    use crate::hot_program::HotProgram;
+
+    optional_methods! {
+        builder = HotIce, program = HotProgram;
+        Subscription::subscription(IntoHotSubscription<P::State, P::Message>);
+        Theme::theme(IntoHotTheme<P::State, P::Theme>);
+        Style::style(IntoHotStyle<P::State, P::Theme>);
+        ScaleFactor::scale_factor(IntoHotScaleFactor<P::State>);
+        Title<Marker>::title(IntoHotTitle<P::State, Marker>);
+        Setup::setup(IntoHotSetup<P::State, P::Message>);
+    }
+}
+
+/// The optional methods of [`iced::Application`], used without the reload
+/// feature.
+pub mod plain {
+    use iced::application::{Application, ThemeFn, TitleFn};
+    use iced::{Program, Subscription as IcedSubscription, theme};
+
+    use crate::into_result::IntoScaleFactor;
+
+    optional_methods! {
+        builder = Application, program = Program;
+        Subscription::subscription(Fn(&P::State) -> IcedSubscription<P::Message>);
+        Theme::theme(ThemeFn<P::State, P::Theme>);
+        Style::style(Fn(&P::State, &P::Theme) -> theme::Style);
+        Title::title(TitleFn<P::State>);
+    }
+
+    /// Scale factors returned as `f64` are converted, like with reloads.
+    pub trait ScaleFactor<P: Program> {
+        fn scale_factor(
+            self,
+            builder: Application<P>,
+        ) -> Application<impl Program<State = P::State, Message = P::Message, Theme = P::Theme>>;
+    }
+
+    impl<P, F, S> ScaleFactor<P> for super::Optional<F>
+    where
+        P: Program + 'static,
+        F: Fn(&P::State) -> S + 'static,
+        S: IntoScaleFactor,
+    {
+        fn scale_factor(
+            self,
+            builder: Application<P>,
+        ) -> Application<impl Program<State = P::State, Message = P::Message, Theme = P::Theme>>
+        {
+            let function = self.0;
+            builder.scale_factor(move |state| function(state).into_scale_factor().unwrap_or(1.0))
+        }
+    }
+
+    /// Nothing is reloaded, the setup function is not needed.
+    pub trait Setup<B> {
+        fn setup(self, builder: B) -> B;
+    }
+
+    impl<B, F> Setup<B> for super::Optional<F> {
+        fn setup(self, builder: B) -> B {
+            builder
+        }
+    }
+}
+
+/// Expands to the `main` function of an application's binary crate.
+///
+/// With the reload feature of the binary enabled, the application runs with
+/// [`application`](crate::application) and reloads the library crate the
+/// state is defined in. Without it, the application is built with plain
+/// [`iced::application`] and nothing is reloaded.
+///
+/// The state's `update` and `view` methods are always used, as is `new` to
+/// boot the application unless another method is named with `boot`. Of
+/// `subscription`, `theme`, `style`, `scale_factor`, `title` and `setup`, the
+/// methods the state defines are passed to the builder. `setup` is only
+/// used with the reload feature.
+///
+/// Options, all of them optional, follow the state:
+/// - `boot = boot`: the method booting the application, `new` by default.
+/// - `feature = "hot"`: the feature of the binary crate enabling reloads,
+///   `"reload"` by default. It is also the feature the library crate is
+///   compiled with, see [`ReloaderSettings::feature`](crate::ReloaderSettings).
+/// - `init = init_tracing`: a function called before the application is
+///   built, e.g. to set up tracing.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use ui::State;
+///
+/// hot_ice::hot_main!(State, init = init_tracing);
+///
+/// fn init_tracing() {
+///     tracing_subscriber::fmt::init();
+/// }
+/// ```
+///
+/// The binary crate depends on `hot_ice` without the reload feature too:
+///
+/// ```toml
+/// [features]
+/// reload = ["ui/reload"]
+///
+/// [dependencies]
+/// hot_ice = "0.3"
+/// ui = { path = "../ui" }
+/// ```
+#[macro_export]
+macro_rules! hot_main {
+    ($state:ident $(, $option:ident = $value:tt)* $(,)?) => {
+        $crate::hot_main!(@options $state, [new], ["reload"], []; $($option = $value,)*);
+    };
+    (@options $state:ident, [$boot:ident], [$feature:literal], [$($init:ident)?];) => {
+        fn main() {
+            use $crate::macro_use::DefaultMethods as _;
+
+            $($init();)?
+
+            #[cfg(feature = $feature)]
+            {
+                use $crate::macro_use::hot::{Subscription as _, Theme as _, Style as _, ScaleFactor as _, Title as _, Setup as _};
+
+                let app = $crate::application($state::$boot, $state::update, $state::view);
+                let app = $crate::macro_use::Optional($state::subscription).subscription(app);
+                let app = $crate::macro_use::Optional($state::theme).theme(app);
+                let app = $crate::macro_use::Optional($state::style).style(app);
+                let app = $crate::macro_use::Optional($state::scale_factor).scale_factor(app);
+                let app = $crate::macro_use::Optional($state::title).title(app);
+                let app = $crate::macro_use::Optional($state::setup).setup(app);
+
+                app.reloader_settings($crate::ReloaderSettings {
+                    feature: ::core::option::Option::Some(::std::string::String::from($feature)),
+                    ..::core::default::Default::default()
+                })
+                .run()
+                .unwrap();
+            }
+
+            #[cfg(not(feature = $feature))]
+            {
+                use $crate::macro_use::plain::{Subscription as _, Theme as _, Style as _, ScaleFactor as _, Title as _, Setup as _};
+
+                let app = $crate::iced::application($state::$boot, $state::update, $state::view);
+                let app = $crate::macro_use::Optional($state::subscription).subscription(app);
+                let app = $crate::macro_use::Optional($state::theme).theme(app);
+                let app = $crate::macro_use::Optional($state::style).style(app);
+                let app = $crate::macro_use::Optional($state::scale_factor).scale_factor(app);
+                let app = $crate::macro_use::Optional($state::title).title(app);
+                let app = $crate::macro_use::Optional($state::setup).setup(app);
+
+                app.run().unwrap();
+            }
+        }
+    };
+    (@options $state:ident, [$_boot:ident], $feature:tt, $init:tt; boot = $boot:ident, $($rest:tt)*) => {
+        $crate::hot_main!(@options $state, [$boot], $feature, $init; $($rest)*);
+    };
+    (@options $state:ident, $boot:tt, [$_feature:literal], $init:tt; feature = $feature:literal, $($rest:tt)*) => {
+        $crate::hot_main!(@options $state, $boot, [$feature], $init; $($rest)*);
+    };
+    (@options $state:ident, $boot:tt, $feature:tt, [$($_init:ident)?]; init = $init:ident, $($rest:tt)*) => {
+        $crate::hot_main!(@options $state, $boot, $feature, [$init]; $($rest)*);
+    };
+}
+
+/// Expands to the application builder of [`application`](crate::application)
+/// with the reload feature of the binary enabled, and to the one of plain
+/// [`iced::application`] without it, for a `main` that configures the builder
+/// itself in a single code path:
+///
+/// ```rust,ignore
+/// fn main() {
+///     hot_ice::maybe_hot_application!(State::new, State::update, State::view)
+///         .subscription(State::subscription)
+///         .title(State::title)
+///         .run()
+///         .unwrap();
+/// }
+/// ```
+///
+/// Only the builder methods both have can follow, settings of the reloader
+/// are passed to the macro instead. Options, all of them optional, follow the
+/// functions:
+/// - `feature = "hot"`: the feature of the binary crate enabling reloads,
+///   `"reload"` by default.
+/// - `reloader_settings = settings`: the [`ReloaderSettings`](crate::ReloaderSettings)
+///   used with the reload feature. By default the library crate is compiled
+///   with the same feature as the binary.
+#[macro_export]
+macro_rules! maybe_hot_application {
+    (@options [$($function:expr),*], [$feature:literal], [$($settings:expr)?];) => {{
+        #[cfg(feature = $feature)]
+        let app = $crate::application($($function),*)
+            .reloader_settings($crate::maybe_hot_application!(@settings $feature $(, $settings)?));
+        #[cfg(not(feature = $feature))]
+        let app = $crate::iced::application($($function),*);
+        app
+    }};
+    (@options $functions:tt, [$_feature:literal], $settings:tt; feature = $feature:literal $(, $($rest:tt)*)?) => {
+        $crate::maybe_hot_application!(@options $functions, [$feature], $settings; $($($rest)*)?)
+    };
+    (@options $functions:tt, $feature:tt, [$($_settings:expr)?]; reloader_settings = $settings:expr $(, $($rest:tt)*)?) => {
+        $crate::maybe_hot_application!(@options $functions, $feature, [$settings]; $($($rest)*)?)
+    };
+    (@settings $feature:literal) => {
+        $crate::ReloaderSettings {
+            feature: ::core::option::Option::Some(::std::string::String::from($feature)),
+            ..::core::default::Default::default()
+        }
+    };
+    (@settings $feature:literal, $settings:expr) => {
+        $settings
+    };
+    ($boot:expr, $update:expr, $view:expr $(, $($option:tt)*)?) => {
+        $crate::maybe_hot_application!(@options [$boot, $update, $view], ["reload"], []; $($($option)*)?)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct State;
+
+    impl State {
+        fn theme(&self) -> u8 {
+            1
+        }
+    }
+
+    #[test]
+    fn state_methods_shadow_the_defaults() {
+        let theme: fn(&State) -> u8 = State::theme;
+        assert_eq!(theme(&State), 1);
+
+        let _: Missing = State::style;
+        let _: Missing = State::title;
+        let _: Missing = State::setup;
+
+        let builder = Optional(State::scale_factor).scale_factor("builder");
+        assert_eq!(builder, "builder");
+    }
+}
@@ -1,7 +1,5 @@
-use std::sync::Arc;
-use std::sync::Mutex;
-
 use iced_core::Element;
+use iced_core::Event;
 use iced_core::Font;
 use iced_core::Settings;
 use iced_core::renderer;
@@ -13,8 +11,12 @@ use iced_winit::graphics::compositor;
 use iced_winit::runtime::Task;
 
 use crate::error::HotIceError;
+use crate::functions::hot_on_event::HotOnEvent;
+use crate::functions::hot_on_event::IntoHotOnEvent;
 use crate::functions::hot_scale_factor::HotScaleFactor;
 use crate::functions::hot_scale_factor::IntoHotScaleFactor;
+use crate::functions::hot_setup::HotSetup;
+use crate::functions::hot_setup::IntoHotSetup;
 use crate::functions::hot_style::HotStyle;
 use crate::functions::hot_style::IntoHotStyle;
 use crate::functions::hot_subscription::HotSubscription;
@@ -23,11 +25,19 @@ use crate::functions::hot_theme::HotTheme;
 use crate::functions::hot_theme::IntoHotTheme;
 use crate::functions::hot_title::HotTitle;
 use crate::functions::hot_title::IntoHotTitle;
-use crate::lib_reloader::LibReloader;
+use crate::library_handle::LibraryHandle;
 use crate::message::DynMessage;
 use crate::message::MessageSource;
 use crate::reloader::FunctionState;
 
+/// What [`HotProgram::setup`] returns.
+pub(crate) type SetupResult<Message> =
+    Result<(Task<MessageSource<Message>>, FunctionState), HotIceError>;
+
+/// What [`HotProgram::on_event`] returns.
+pub(crate) type OnEventResult<Message> =
+    Result<(Option<MessageSource<Message>>, FunctionState), HotIceError>;
+
 /// An interactive, native, cross-platform, multi-windowed application.
 ///
 /// A [`Program`] can execute asynchronous actions by returning a
@@ -58,14 +68,14 @@ pub trait HotProgram {
         &self,
         state: &mut Self::State,
         message: MessageSource<Self::Message>,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<(Task<MessageSource<Self::Message>>, FunctionState), HotIceError>;
 
     fn view<'a>(
         &self,
         state: &'a Self::State,
         window: window::Id,
-        reloader: Option<&Arc<Mutex<LibReloader>>>,
+        reloader: Option<&LibraryHandle>,
     ) -> Result<
         (
             Element<'a, MessageSource<Self::Message>, Self::Theme, Self::Renderer>,
@@ -81,7 +91,7 @@ pub trait HotProgram {
         &self,
         _state: &Self::State,
         _window: window::Id,
-        _reloader: Option<&Arc<Mutex<LibReloader>>>,
+        _reloader: Option<&LibraryHandle>,
     ) -> Result<(String, FunctionState), HotIceError> {
         let mut title = String::new();
 
@@ -114,7 +124,7 @@ pub trait HotProgram {
     fn subscription(
         &self,
         _state: &Self::State,
-        _reloader: Option<&Arc<Mutex<LibReloader>>>,
+        _reloader: Option<&LibraryHandle>,
     ) -> Result<(Subscription<MessageSource<Self::Message>>, FunctionState), HotIceError> {
         Ok((Subscription::none(), FunctionState::Static))
     }
@@ -123,20 +133,23 @@ pub trait HotProgram {
         &self,
         _state: &Self::State,
         _window: window::Id,
-        _reloader: Option<&Arc<Mutex<LibReloader>>>,
+        _reloader: Option<&LibraryHandle>,
     ) -> Result<(Option<Self::Theme>, FunctionState), HotIceError> {
         Ok((None, FunctionState::Static))
     }
 
+    /// The settings of the application, set with [`with_settings`].
     fn settings(&self) -> Settings;
 
+    /// The settings of the first window, set with [`with_window`]. `None`
+    /// opens no window.
     fn window(&self) -> Option<window::Settings>;
 
     fn style(
         &self,
         _state: &Self::State,
         theme: &Self::Theme,
-        _reloader: Option<&Arc<Mutex<LibReloader>>>,
+        _reloader: Option<&LibraryHandle>,
     ) -> Result<(theme::Style, FunctionState), HotIceError> {
         Ok((theme::Base::base(theme), FunctionState::Static))
     }
@@ -145,10 +158,36 @@ pub trait HotProgram {
         &self,
         _state: &Self::State,
         _window: window::Id,
-        _reloader: Option<&Arc<Mutex<LibReloader>>>,
+        _reloader: Option<&LibraryHandle>,
     ) -> Result<(f32, FunctionState), HotIceError> {
         Ok((1.0, FunctionState::Static))
     }
+
+    /// Runs on the state after every reload.
+    fn setup(
+        &self,
+        _state: &mut Self::State,
+        _reloader: Option<&LibraryHandle>,
+    ) -> SetupResult<Self::Message> {
+        Ok((Task::none(), FunctionState::Static))
+    }
+
+    /// Whether [`on_event`](Self::on_event) is set, the reloader only listens
+    /// to events if it is.
+    fn listens_to_events(&self) -> bool {
+        false
+    }
+
+    /// Maps a runtime event no widget captured to a message.
+    fn on_event(
+        &self,
+        _state: &Self::State,
+        _event: Event,
+        _window: window::Id,
+        _reloader: Option<&LibraryHandle>,
+    ) -> OnEventResult<Self::Message> {
+        Ok((None, FunctionState::None))
+    }
 }
 
 /// Generates the common boilerplate for a `HotProgram` decorator.
@@ -175,7 +214,7 @@ macro_rules! delegate_hot_program_common {
             &self,
             state: &mut Self::State,
             message: MessageSource<Self::Message>,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(Task<MessageSource<Self::Message>>, FunctionState), HotIceError> {
             self.$field.update(state, message, reloader)
         }
@@ -184,7 +223,7 @@ macro_rules! delegate_hot_program_common {
             &self,
             state: &'a Self::State,
             window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<
             (
                 Element<'a, MessageSource<Self::Message>, Self::Theme, Self::Renderer>,
@@ -198,14 +237,6 @@ macro_rules! delegate_hot_program_common {
         {
             self.$field.view(state, window, reloader)
         }
-
-        fn settings(&self) -> Settings {
-            self.$field.settings()
-        }
-
-        fn window(&self) -> Option<window::Settings> {
-            self.$field.window()
-        }
     };
 }
 
@@ -215,12 +246,22 @@ macro_rules! delegate_methods {
     ($p:ident, $field:ident, [$($method:ident),*]) => {
         $(delegate_methods!(@single $p, $field, $method);)*
     };
+    (@single $p:ident, $field:ident, settings) => {
+        fn settings(&self) -> Settings {
+            self.$field.settings()
+        }
+    };
+    (@single $p:ident, $field:ident, window) => {
+        fn window(&self) -> Option<window::Settings> {
+            self.$field.window()
+        }
+    };
     (@single $p:ident, $field:ident, title) => {
         fn title(
             &self,
             state: &Self::State,
             window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(String, FunctionState), HotIceError> {
             self.$field.title(state, window, reloader)
         }
@@ -229,7 +270,7 @@ macro_rules! delegate_methods {
         fn subscription(
             &self,
             state: &Self::State,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(Subscription<MessageSource<Self::Message>>, FunctionState), HotIceError>
         {
             self.$field.subscription(state, reloader)
@@ -240,7 +281,7 @@ macro_rules! delegate_methods {
             &self,
             state: &Self::State,
             window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(Option<Self::Theme>, FunctionState), HotIceError> {
             self.$field.theme(state, window, reloader)
         }
@@ -250,7 +291,7 @@ macro_rules! delegate_methods {
             &self,
             state: &Self::State,
             theme: &Self::Theme,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(theme::Style, FunctionState), HotIceError> {
             self.$field.style(state, theme, reloader)
         }
@@ -260,17 +301,41 @@ macro_rules! delegate_methods {
             &self,
             state: &Self::State,
             window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(f32, FunctionState), HotIceError> {
             self.$field.scale_factor(state, window, reloader)
         }
     };
+    (@single $p:ident, $field:ident, on_event) => {
+        fn listens_to_events(&self) -> bool {
+            self.$field.listens_to_events()
+        }
+
+        fn on_event(
+            &self,
+            state: &Self::State,
+            event: Event,
+            window: window::Id,
+            reloader: Option<&LibraryHandle>,
+        ) -> OnEventResult<Self::Message> {
+            self.$field.on_event(state, event, window, reloader)
+        }
+    };
+    (@single $p:ident, $field:ident, setup) => {
+        fn setup(
+            &self,
+            state: &mut Self::State,
+            reloader: Option<&LibraryHandle>,
+        ) -> SetupResult<Self::Message> {
+            self.$field.setup(state, reloader)
+        }
+    };
 }
 
 /// Decorates a [`Program`] with the given title function.
-pub fn with_title<P: HotProgram>(
+pub fn with_title<P: HotProgram, Marker>(
     program: P,
-    f: impl IntoHotTitle<P::State>,
+    f: impl IntoHotTitle<P::State, Marker>,
 ) -> impl HotProgram<
     State = P::State,
     Message = P::Message,
@@ -280,20 +345,35 @@ pub fn with_title<P: HotProgram>(
 > {
     let hot_title = HotTitle::new(f);
 
-    struct WithTitle<P: HotProgram, F> {
+    struct WithTitle<P: HotProgram, F, Marker> {
         program: P,
-        title: HotTitle<F, P::State>,
+        title: HotTitle<F, P::State, Marker>,
     }
 
-    impl<P: HotProgram, F: IntoHotTitle<P::State>> HotProgram for WithTitle<P, F> {
+    impl<P: HotProgram, F: IntoHotTitle<P::State, Marker>, Marker> HotProgram
+        for WithTitle<P, F, Marker>
+    {
         delegate_hot_program_common!(P, program);
-        delegate_methods!(P, program, [subscription, theme, style, scale_factor]);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                subscription,
+                theme,
+                style,
+                scale_factor,
+                setup,
+                on_event
+            ]
+        );
 
         fn title(
             &self,
             state: &Self::State,
             window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(String, FunctionState), HotIceError> {
             self.title.title(state, window, reloader)
         }
@@ -309,7 +389,8 @@ pub fn with_title<P: HotProgram>(
 pub fn with_subscription<P: HotProgram>(
     program: P,
     f: impl IntoHotSubscription<P::State, P::Message>,
-) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme> {
+) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme, Renderer = P::Renderer>
+{
     let hot_sub = HotSubscription::new(f);
 
     struct WithSubscription<P: HotProgram, F> {
@@ -321,12 +402,25 @@ pub fn with_subscription<P: HotProgram>(
         for WithSubscription<P, F>
     {
         delegate_hot_program_common!(P, program);
-        delegate_methods!(P, program, [title, theme, style, scale_factor]);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                title,
+                theme,
+                style,
+                scale_factor,
+                setup,
+                on_event
+            ]
+        );
 
         fn subscription(
             &self,
             state: &Self::State,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(Subscription<MessageSource<Self::Message>>, FunctionState), HotIceError>
         {
             self.subscription.subscription(state, reloader)
@@ -343,7 +437,8 @@ pub fn with_subscription<P: HotProgram>(
 pub fn with_theme<P: HotProgram>(
     program: P,
     f: impl IntoHotTheme<P::State, P::Theme>,
-) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme> {
+) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme, Renderer = P::Renderer>
+{
     let hot_theme = HotTheme::new(f);
 
     struct WithTheme<P: HotProgram, F> {
@@ -353,13 +448,26 @@ pub fn with_theme<P: HotProgram>(
 
     impl<P: HotProgram, F: IntoHotTheme<P::State, P::Theme>> HotProgram for WithTheme<P, F> {
         delegate_hot_program_common!(P, program);
-        delegate_methods!(P, program, [title, subscription, style, scale_factor]);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                title,
+                subscription,
+                style,
+                scale_factor,
+                setup,
+                on_event
+            ]
+        );
 
         fn theme(
             &self,
             state: &Self::State,
             _window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(Option<Self::Theme>, FunctionState), HotIceError> {
             self.theme.theme(state, reloader)
         }
@@ -375,7 +483,8 @@ pub fn with_theme<P: HotProgram>(
 pub fn with_style<P: HotProgram>(
     program: P,
     f: impl IntoHotStyle<P::State, P::Theme>,
-) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme> {
+) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme, Renderer = P::Renderer>
+{
     let hot_style = HotStyle::new(f);
 
     struct WithStyle<P: HotProgram, F> {
@@ -385,13 +494,26 @@ pub fn with_style<P: HotProgram>(
 
     impl<P: HotProgram, F: IntoHotStyle<P::State, P::Theme>> HotProgram for WithStyle<P, F> {
         delegate_hot_program_common!(P, program);
-        delegate_methods!(P, program, [title, subscription, theme, scale_factor]);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                title,
+                subscription,
+                theme,
+                scale_factor,
+                setup,
+                on_event
+            ]
+        );
 
         fn style(
             &self,
             state: &Self::State,
             theme: &Self::Theme,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(theme::Style, FunctionState), HotIceError> {
             self.style.style(state, theme, reloader)
         }
@@ -407,7 +529,8 @@ pub fn with_style<P: HotProgram>(
 pub fn with_scale_factor<P: HotProgram>(
     program: P,
     f: impl IntoHotScaleFactor<P::State>,
-) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme> {
+) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme, Renderer = P::Renderer>
+{
     let hot_scale_factor = HotScaleFactor::new(f);
 
     struct WithScaleFactor<P: HotProgram, F> {
@@ -417,13 +540,26 @@ pub fn with_scale_factor<P: HotProgram>(
 
     impl<P: HotProgram, F: IntoHotScaleFactor<P::State>> HotProgram for WithScaleFactor<P, F> {
         delegate_hot_program_common!(P, program);
-        delegate_methods!(P, program, [title, subscription, theme, style]);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                title,
+                subscription,
+                theme,
+                style,
+                setup,
+                on_event
+            ]
+        );
 
         fn scale_factor(
             &self,
             state: &Self::State,
             window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(f32, FunctionState), HotIceError> {
             self.scale_factor.scale_factor(state, window, reloader)
         }
@@ -435,13 +571,180 @@ pub fn with_scale_factor<P: HotProgram>(
     }
 }
 
+/// Decorates a [`Program`] with the given setup function.
+pub fn with_setup<P: HotProgram>(
+    program: P,
+    f: impl IntoHotSetup<P::State, P::Message>,
+) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme, Renderer = P::Renderer>
+{
+    let hot_setup = HotSetup::new(f);
+
+    struct WithSetup<P: HotProgram, F> {
+        program: P,
+        setup: HotSetup<F, P::State, P::Message>,
+    }
+
+    impl<P: HotProgram, F: IntoHotSetup<P::State, P::Message>> HotProgram for WithSetup<P, F> {
+        delegate_hot_program_common!(P, program);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                title,
+                subscription,
+                theme,
+                style,
+                scale_factor,
+                on_event
+            ]
+        );
+
+        fn setup(
+            &self,
+            state: &mut Self::State,
+            reloader: Option<&LibraryHandle>,
+        ) -> SetupResult<Self::Message> {
+            self.setup.setup(state, reloader)
+        }
+    }
+
+    WithSetup {
+        program,
+        setup: hot_setup,
+    }
+}
+
+/// Decorates a [`Program`] with the given event function.
+pub fn with_on_event<P: HotProgram>(
+    program: P,
+    f: impl IntoHotOnEvent<P::State, P::Message>,
+) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme, Renderer = P::Renderer>
+{
+    let hot_on_event = HotOnEvent::new(f);
+
+    struct WithOnEvent<P: HotProgram, F> {
+        program: P,
+        on_event: HotOnEvent<F, P::State, P::Message>,
+    }
+
+    impl<P: HotProgram, F: IntoHotOnEvent<P::State, P::Message>> HotProgram for WithOnEvent<P, F> {
+        delegate_hot_program_common!(P, program);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                title,
+                subscription,
+                theme,
+                style,
+                scale_factor,
+                setup
+            ]
+        );
+
+        fn listens_to_events(&self) -> bool {
+            true
+        }
+
+        fn on_event(
+            &self,
+            state: &Self::State,
+            event: Event,
+            window: window::Id,
+            reloader: Option<&LibraryHandle>,
+        ) -> OnEventResult<Self::Message> {
+            self.on_event.on_event(state, event, window, reloader)
+        }
+    }
+
+    WithOnEvent {
+        program,
+        on_event: hot_on_event,
+    }
+}
+
+/// Decorates a [`Program`] with the given settings.
+///
+/// Unlike the function decorators it returns a named type, the reloader's
+/// message is named after the program and routes its actions.
+pub fn with_settings<P: HotProgram>(program: P, settings: Settings) -> WithSettings<P> {
+    WithSettings { program, settings }
+}
+
+/// A [`Program`] with the settings of [`with_settings`].
+pub struct WithSettings<P> {
+    program: P,
+    settings: Settings,
+}
+
+impl<P: HotProgram> HotProgram for WithSettings<P> {
+    delegate_hot_program_common!(P, program);
+    delegate_methods!(
+        P,
+        program,
+        [
+            window,
+            title,
+            subscription,
+            theme,
+            style,
+            scale_factor,
+            setup,
+            on_event
+        ]
+    );
+
+    fn settings(&self) -> Settings {
+        self.settings.clone()
+    }
+}
+
+/// Decorates a [`Program`] with the settings of its window, see
+/// [`with_settings`].
+pub fn with_window<P: HotProgram>(program: P, window: window::Settings) -> WithWindow<P> {
+    WithWindow { program, window }
+}
+
+/// A [`Program`] with the window settings of [`with_window`].
+pub struct WithWindow<P> {
+    program: P,
+    window: window::Settings,
+}
+
+impl<P: HotProgram> HotProgram for WithWindow<P> {
+    delegate_hot_program_common!(P, program);
+    delegate_methods!(
+        P,
+        program,
+        [
+            settings,
+            title,
+            subscription,
+            theme,
+            style,
+            scale_factor,
+            setup,
+            on_event
+        ]
+    );
+
+    fn window(&self) -> Option<window::Settings> {
+        Some(self.window.clone())
+    }
+}
+
 /// Decorates a [`Program`] with the given executor function.
 ///
 /// Note: This decorator cannot use the delegation macros because it
 /// changes the `Executor` associated type.
 pub fn with_executor<P: HotProgram, E: Executor>(
     program: P,
-) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme> {
+) -> impl HotProgram<State = P::State, Message = P::Message, Theme = P::Theme, Renderer = P::Renderer>
+{
     use std::marker::PhantomData;
 
     struct WithExecutor<P, E> {
@@ -468,7 +771,7 @@ pub fn with_executor<P: HotProgram, E: Executor>(
             &self,
             state: &mut Self::State,
             message: MessageSource<Self::Message>,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<(Task<MessageSource<Self::Message>>, FunctionState), HotIceError> {
             self.program.update(state, message, reloader)
         }
@@ -477,7 +780,7 @@ pub fn with_executor<P: HotProgram, E: Executor>(
             &self,
             state: &'a Self::State,
             window: window::Id,
-            reloader: Option<&Arc<Mutex<LibReloader>>>,
+            reloader: Option<&LibraryHandle>,
         ) -> Result<
             (
                 Element<'a, MessageSource<Self::Message>, Self::Theme, Self::Renderer>,
@@ -492,15 +795,21 @@ pub fn with_executor<P: HotProgram, E: Executor>(
             self.program.view(state, window, reloader)
         }
 
-        fn settings(&self) -> Settings {
-            self.program.settings()
-        }
-
-        fn window(&self) -> Option<window::Settings> {
-            self.program.window()
-        }
-
-        delegate_methods!(P, program, [title, subscription, theme, style, scale_factor]);
+        delegate_methods!(
+            P,
+            program,
+            [
+                settings,
+                window,
+                title,
+                subscription,
+                theme,
+                style,
+                scale_factor,
+                setup,
+                on_event
+            ]
+        );
     }
 
     WithExecutor {
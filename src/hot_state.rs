@@ -1,8 +1,11 @@
 use serde::{Serialize, de::DeserializeOwned};
 use std::any::Any;
 use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::context::Context;
 use crate::error::HotIceError;
+use crate::registry::type_hash;
 
 /// Casts `&dyn Any` to `&T` without `TypeId` verification.
 ///
@@ -61,10 +64,165 @@ where
     }
 
     fn serialize_state(&self) -> Result<Vec<u8>, String> {
-        serde_json::to_vec(self).map_err(|e| e.to_string())
+        encode_state(self).map_err(|e| e.to_string())
     }
 }
 
+/// A named field of a `#[hot_state]` struct, listed by the library so the
+/// reloader can tell which fields changed between two builds.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateField {
+    /// Hash of the field's name.
+    pub name_hash: u64,
+    /// [`handoff_hash`](crate::handoff_hash) of the field's type, `0` if it
+    /// does not derive [`TypeHash`](crate::TypeHash). Only fields with the
+    /// same non-zero hash in both libraries are moved.
+    pub type_hash: u64,
+    /// Offset of the field in the struct.
+    pub offset: usize,
+}
+
+/// The field named `name_hash` of `fields`.
+pub fn find_field(fields: &[StateField], name_hash: u64) -> Option<&StateField> {
+    fields.iter().find(|field| field.name_hash == name_hash)
+}
+
+/// The fields of a state whose type changed, serialized one by one and keyed
+/// by their name.
+#[derive(Default)]
+pub struct FieldMap(serde_json::Map<String, serde_json::Value>);
+
+impl FieldMap {
+    pub fn insert<T: Serialize>(&mut self, name: &str, value: &T) -> Result<(), HotIceError> {
+        let value = serde_json::to_value(value)
+            .map_err(|e| HotIceError::FailedToSerializeState(format!("{name}: {e}")))?;
+        self.0.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Takes the field `name` out, `None` if it was not serialized or does
+    /// not fit the edited type.
+    pub fn take<T: DeserializeOwned>(&mut self, name: &str) -> Option<T> {
+        let value = self.0.remove(name)?;
+        serde_json::from_value(value)
+            .inspect_err(|e| {
+                Context::current().log(
+                    log::Level::Warn,
+                    &format!("State field `{name}` was reset to its default: {e}"),
+                );
+            })
+            .ok()
+    }
+
+    /// Encodes the fields like a whole state, see [`encode_state`].
+    pub fn encode(&self) -> Result<Vec<u8>, HotIceError> {
+        encode_state(&self.0)
+    }
+}
+
+/// Marks the start of a state buffer.
+const STATE_MAGIC: [u8; 4] = *b"HICE";
+
+/// Version of the header layout, bumped when it changes.
+const STATE_FORMAT_VERSION: u16 = 1;
+
+/// Magic, format version, reserved flags, type hash, payload length and
+/// crc32 of the payload, all little endian.
+const STATE_HEADER_LEN: usize = 4 + 2 + 2 + 8 + 8 + 4;
+
+/// Whether state buffers are framed, set from
+/// [`ReloaderSettings::checked_state`](crate::ReloaderSettings::checked_state)
+/// in the host and read by libraries through their [`Context`].
+static CHECKED_STATE: AtomicBool = AtomicBool::new(true);
+
+pub(crate) fn set_checked_state(checked: bool) {
+    CHECKED_STATE.store(checked, Ordering::Relaxed);
+}
+
+pub(crate) fn checked_state() -> bool {
+    CHECKED_STATE.load(Ordering::Relaxed)
+}
+
+/// Encodes `state` into the buffer `serialize_state` hands to the next
+/// library: a [`STATE_HEADER_LEN`] byte header followed by the state as
+/// JSON, or only the JSON if the host turned off `checked_state`.
+pub(crate) fn encode_state<T: Serialize>(state: &T) -> Result<Vec<u8>, HotIceError> {
+    encode(state, Context::current().checked_state())
+}
+
+/// Decodes a buffer written by [`encode_state`]. Never panics, whatever
+/// `data` contains.
+pub(crate) fn decode_state<T: DeserializeOwned>(data: &[u8]) -> Result<T, HotIceError> {
+    decode(data, Context::current().checked_state())
+}
+
+fn encode<T: Serialize>(state: &T, checked: bool) -> Result<Vec<u8>, HotIceError> {
+    let payload = serde_json::to_vec(state)
+        .map_err(|e| HotIceError::FailedToSerializeState(e.to_string()))?;
+    if !checked {
+        return Ok(payload);
+    }
+
+    let mut buffer = Vec::with_capacity(STATE_HEADER_LEN + payload.len());
+    buffer.extend_from_slice(&STATE_MAGIC);
+    buffer.extend_from_slice(&STATE_FORMAT_VERSION.to_le_bytes());
+    buffer.extend_from_slice(&0u16.to_le_bytes());
+    buffer.extend_from_slice(&type_hash::<T>().to_le_bytes());
+    buffer.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    buffer.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    buffer.extend_from_slice(&payload);
+    Ok(buffer)
+}
+
+/// Decodes framed buffers, and unframed ones unless `checked`, so a buffer
+/// written before the setting changed is still read.
+fn decode<T: DeserializeOwned>(data: &[u8], checked: bool) -> Result<T, HotIceError> {
+    if data.is_empty() {
+        return Err(HotIceError::FailedToDeserializeState(
+            "Empty data".to_string(),
+        ));
+    }
+
+    if !checked && !data.starts_with(&STATE_MAGIC) {
+        return serde_json::from_slice(data)
+            .map_err(|e| HotIceError::FailedToDeserializeState(e.to_string()));
+    }
+
+    let corrupt = |reason: &str| HotIceError::CorruptStateBuffer(reason.to_string());
+    let Some((header, payload)) = data.split_at_checked(STATE_HEADER_LEN) else {
+        return Err(corrupt("shorter than its header"));
+    };
+    let (magic, header) = header.split_at(4);
+    let (version, header) = header.split_at(2);
+    let (flags, header) = header.split_at(2);
+    let (hash, header) = header.split_at(8);
+    let (len, crc) = header.split_at(8);
+    // The slices have the lengths of the integers they are parsed into.
+    let u16_at = |bytes: &[u8]| u16::from_le_bytes(bytes.try_into().unwrap_or_default());
+    let u32_at = |bytes: &[u8]| u32::from_le_bytes(bytes.try_into().unwrap_or_default());
+    let u64_at = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+
+    if magic != STATE_MAGIC {
+        return Err(corrupt("bad magic"));
+    }
+    if u16_at(version) != STATE_FORMAT_VERSION || u16_at(flags) != 0 {
+        return Err(corrupt("unknown format version"));
+    }
+    if u64_at(len) != payload.len() as u64 {
+        return Err(corrupt("length does not match the header"));
+    }
+    if u32_at(crc) != crc32fast::hash(payload) {
+        return Err(corrupt("checksum mismatch"));
+    }
+    if u64_at(hash) != type_hash::<T>() {
+        return Err(HotIceError::StateTypeMismatch);
+    }
+
+    serde_json::from_slice(payload)
+        .map_err(|e| HotIceError::FailedToDeserializeState(e.to_string()))
+}
+
 pub struct HotState {
     state: Box<dyn DynState>,
 }
@@ -96,6 +254,48 @@ impl HotState {
         unsafe { unchecked_downcast_mut::<T>(self.state.as_any_mut()) }
     }
 
+    /// Takes over the state built by the previous library without
+    /// serializing it, so its methods are looked up in this library.
+    ///
+    /// Only called by the reloader when both libraries report the same
+    /// non-zero [`handoff_hash`](crate::handoff_hash) of `T`, were built by
+    /// the same compiler and allocate through the host allocator, so the
+    /// previous `T` has the layout of this one down to its nested types,
+    /// holds no reference into the previous library and can be freed here.
+    pub fn adopt_state<T: DynState>(&mut self) {
+        let state = mem::replace(&mut self.state, Box::new(()));
+        let state = Box::into_raw(state) as *mut T;
+        self.state = unsafe { Box::from_raw(state) };
+    }
+
+    /// Builds the state of this library from the one of the previous
+    /// library field by field. `restore` is called with the `Default` state,
+    /// the address of the previous state and the fields serialized because
+    /// their type changed; it moves the other fields out of the previous
+    /// state.
+    ///
+    /// Only called by the reloader under the conditions of
+    /// [`HotState::adopt_state`] for the moved fields. The previous state is
+    /// returned to be dropped by the previous library, which knows the types
+    /// of the fields that were not moved.
+    pub fn restore_fields<T: DynState + Default>(
+        &mut self,
+        data: &[u8],
+        restore: impl FnOnce(&mut T, *const u8, &mut FieldMap),
+    ) -> (*mut u8, Result<(), HotIceError>) {
+        let (mut fields, result) = match decode_state(data) {
+            Ok(fields) => (FieldMap(fields), Ok(())),
+            Err(err) => (FieldMap::default(), Err(err)),
+        };
+
+        let previous = &*self.state as *const dyn DynState as *const u8;
+        let mut state = T::default();
+        restore(&mut state, previous, &mut fields);
+
+        let old_state = mem::replace(&mut self.state, Box::new(state));
+        (Box::into_raw(old_state) as *mut u8, result)
+    }
+
     pub fn ref_state<T: 'static>(&self) -> &T {
         unsafe { unchecked_downcast_ref::<T>(self.state.as_any()) }
     }
@@ -116,20 +316,9 @@ impl HotState {
     where
         T: DynState + DeserializeOwned + Default,
     {
-        let mut result = Ok(());
-        let new_state: T = if data.is_empty() {
-            result = Err(HotIceError::FailedToDeserializeState(
-                "Empty data".to_string(),
-            ));
-            T::default()
-        } else {
-            match serde_json::from_slice(data) {
-                Ok(state) => state,
-                Err(e) => {
-                    result = Err(HotIceError::FailedToDeserializeState(e.to_string()));
-                    T::default()
-                }
-            }
+        let (new_state, result) = match decode_state::<T>(data) {
+            Ok(state) => (state, Ok(())),
+            Err(err) => (T::default(), Err(err)),
         };
 
         let old_state = std::mem::replace(&mut self.state, Box::new(new_state));
@@ -143,3 +332,117 @@ impl HotState {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::testing::{corrupted_buffers, roundtrip_check};
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Document {
+        title: String,
+        lines: Vec<String>,
+        cursor: Option<(u32, u32)>,
+    }
+
+    fn document() -> Document {
+        Document {
+            title: "notes".to_string(),
+            lines: vec!["first".to_string(), "ünïcødé".to_string()],
+            cursor: Some((1, 3)),
+        }
+    }
+
+    #[test]
+    fn state_survives_round_trip() {
+        roundtrip_check(&document());
+        roundtrip_check(&Document::default());
+    }
+
+    #[test]
+    fn corrupted_buffers_never_panic() {
+        let valid = encode_state(&document()).unwrap();
+
+        for buffer in corrupted_buffers(&valid) {
+            let mut state = HotState::new(document());
+            assert!(state.deserialize_state::<Document>(&buffer).is_err());
+            assert_eq!(state.ref_state::<Document>(), &Document::default());
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn generated_corrupted_buffers_reset_the_state(
+            buffer in crate::testing::corrupted_buffer(&encode_state(&document()).unwrap())
+        ) {
+            let mut state = HotState::new(document());
+            proptest::prop_assert!(state.deserialize_state::<Document>(&buffer).is_err());
+            proptest::prop_assert_eq!(state.ref_state::<Document>(), &Document::default());
+        }
+    }
+
+    #[test]
+    fn unchecked_state_is_plain_json() {
+        let buffer = encode(&document(), false).unwrap();
+        assert_eq!(buffer, serde_json::to_vec(&document()).unwrap());
+        assert_eq!(decode::<Document>(&buffer, false).unwrap(), document());
+        assert!(matches!(
+            decode::<Document>(&buffer, true),
+            Err(HotIceError::CorruptStateBuffer(_))
+        ));
+
+        let framed = encode(&document(), true).unwrap();
+        assert_eq!(decode::<Document>(&framed, false).unwrap(), document());
+    }
+
+    #[test]
+    fn header_is_validated() {
+        let mut buffer = encode_state(&document()).unwrap();
+        assert!(decode_state::<Document>(&buffer).is_ok());
+        assert!(matches!(
+            decode_state::<Vec<u8>>(&buffer),
+            Err(HotIceError::StateTypeMismatch)
+        ));
+
+        let last = buffer.len() - 1;
+        buffer[last] ^= 1;
+        assert!(matches!(
+            decode_state::<Document>(&buffer),
+            Err(HotIceError::CorruptStateBuffer(reason)) if reason == "checksum mismatch"
+        ));
+    }
+
+    #[test]
+    fn adopted_state_keeps_its_value() {
+        let mut state = HotState::new(document());
+        state.adopt_state::<Document>();
+        assert_eq!(state.ref_state::<Document>(), &document());
+    }
+
+    #[test]
+    fn restores_fields_by_moving_or_deserializing_them() {
+        let mut changed = FieldMap::default();
+        changed.insert("title", &"renamed").unwrap();
+        let data = changed.encode().unwrap();
+
+        let mut state = HotState::new(document());
+        let (previous, result) =
+            state.restore_fields::<Document>(&data, |new, previous, fields| {
+                let previous = unsafe { &*previous.cast::<Document>() };
+                new.lines = previous.lines.clone();
+                new.title = fields.take("title").unwrap();
+                assert_eq!(fields.take::<Option<(u32, u32)>>("cursor"), None);
+            });
+        result.unwrap();
+        // Nothing was moved out, the previous library drops all of it.
+        drop(unsafe { Box::from_raw(previous.cast::<Document>()) });
+
+        let restored = state.ref_state::<Document>();
+        assert_eq!(restored.title, "renamed");
+        assert_eq!(restored.lines, document().lines);
+        assert_eq!(restored.cursor, None);
+    }
+}
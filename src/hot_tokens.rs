@@ -0,0 +1,282 @@
+//! Design tokens read from a file at view time.
+//!
+//! [`hot_tokens!`](crate::hot_tokens!) declares a struct of named tokens
+//! (spacings, radii, colors) with defaults, backed by a TOML file that the
+//! host watches like a [text bundle](crate::hot_assets). Saving the file
+//! calls `view` again with the new values, no compile needed.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use iced::Color;
+
+use crate::hot_assets::Asset;
+
+/// A value in a token file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenValue {
+    Number(f64),
+    Bool(bool),
+    Text(String),
+}
+
+/// Token values by key, parsed from a TOML file.
+///
+/// Only a flat subset of TOML is supported: `key = value` lines with numbers,
+/// booleans or quoted strings, `#` comments and `[table]` headers, whose
+/// keys are prefixed with the table name and a dot.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TokenFile {
+    values: HashMap<String, TokenValue>,
+}
+
+impl Asset for TokenFile {
+    const NAME: &'static str = "token file";
+
+    fn parse(_path: &Path, source: &str) -> Result<Self, String> {
+        Self::parse(source)
+    }
+}
+
+impl TokenFile {
+    /// Parses a token file, the error names the first invalid line.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        let mut table = String::new();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = format!("{}.", name.trim());
+                continue;
+            }
+
+            let invalid = || format!("line {}: expected `key = value`", number + 1);
+            let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+            let key = key.trim().trim_matches('"');
+            if key.is_empty() {
+                return Err(invalid());
+            }
+            let value = parse_value(value.trim())
+                .ok_or_else(|| format!("line {}: invalid value `{}`", number + 1, value.trim()))?;
+            values.insert(format!("{table}{key}"), value);
+        }
+
+        Ok(Self { values })
+    }
+
+    /// The value of `key`.
+    pub fn value(&self, key: &str) -> Option<&TokenValue> {
+        self.values.get(key)
+    }
+
+    /// The value of `key` converted to `T`, `None` if it is missing or of
+    /// another type.
+    pub fn get<T: Token>(&self, key: &str) -> Option<T> {
+        self.value(key).and_then(T::from_token)
+    }
+}
+
+/// A type a token can be read as.
+pub trait Token: Sized {
+    fn from_token(value: &TokenValue) -> Option<Self>;
+}
+
+macro_rules! number_token {
+    ($($ty:ty),*) => {
+        $(
+            impl Token for $ty {
+                fn from_token(value: &TokenValue) -> Option<Self> {
+                    match value {
+                        TokenValue::Number(number) => Some(*number as $ty),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+number_token!(f32, f64);
+
+macro_rules! integer_token {
+    ($($ty:ty),*) => {
+        $(
+            impl Token for $ty {
+                fn from_token(value: &TokenValue) -> Option<Self> {
+                    match value {
+                        TokenValue::Number(number) if number.fract() == 0.0 => {
+                            <$ty>::try_from(*number as i64).ok()
+                        }
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_token!(u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+impl Token for bool {
+    fn from_token(value: &TokenValue) -> Option<Self> {
+        match value {
+            TokenValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl Token for String {
+    fn from_token(value: &TokenValue) -> Option<Self> {
+        match value {
+            TokenValue::Text(text) => Some(text.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Colors are written as hex strings, `"#4c6ef5"` or `"#4c6ef580"`.
+impl Token for Color {
+    fn from_token(value: &TokenValue) -> Option<Self> {
+        match value {
+            TokenValue::Text(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(value: &str) -> Option<TokenValue> {
+    if let Some(text) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        return Some(TokenValue::Text(text.replace("\\\"", "\"")));
+    }
+    match value {
+        "true" => Some(TokenValue::Bool(true)),
+        "false" => Some(TokenValue::Bool(false)),
+        _ => value.replace('_', "").parse().ok().map(TokenValue::Number),
+    }
+}
+
+/// Declares design tokens that are read from a TOML file at view time.
+///
+/// The macro generates a struct with a public field per token, a `Default`
+/// impl with the declared values and a function returning the tokens
+/// currently in the file. Tokens missing from the file, or of the wrong
+/// type, keep their default. The path is relative to the crate's manifest
+/// directory.
+///
+/// ```rust,ignore
+/// hot_ice::hot_tokens! {
+///     pub fn tokens() -> Tokens from "tokens.toml" {
+///         spacing_md: f32 = 12.0,
+///         radius: f32 = 4.0,
+///         accent: Color = Color::from_rgb8(0x4c, 0x6e, 0xf5),
+///     }
+/// }
+///
+/// fn view(&self) -> Element<'_, Message> {
+///     let tokens = tokens();
+///     column![...].spacing(tokens.spacing_md).into()
+/// }
+/// ```
+///
+/// with a `tokens.toml` next to `Cargo.toml`:
+///
+/// ```toml
+/// spacing_md = 16
+/// accent = "#e8590c"
+/// ```
+#[macro_export]
+macro_rules! hot_tokens {
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $fn_name:ident() -> $name:ident from $file:literal {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $ty:ty = $default:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl ::core::default::Default for $name {
+            fn default() -> Self {
+                Self {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        /// The tokens currently in the token file.
+        $vis fn $fn_name() -> $name {
+            let file = $crate::Context::current()
+                .token_file(concat!(env!("CARGO_MANIFEST_DIR"), "/", $file));
+            $name {
+                $(
+                    $field: file
+                        .get::<$ty>(stringify!($field))
+                        .unwrap_or_else(|| $default),
+                )*
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_flat_toml() {
+        let source = "# Spacing\nspacing_md = 12\nscale = 1.5 # comment\n\n[colors]\naccent = \"#4c6ef5\"\nhash = \"a # b\"\ndark = false\n";
+        let file = TokenFile::parse(source).unwrap();
+
+        assert_eq!(file.get::<f32>("spacing_md"), Some(12.0));
+        assert_eq!(file.get::<u16>("spacing_md"), Some(12));
+        assert_eq!(file.get::<u16>("scale"), None);
+        assert_eq!(file.get::<f64>("scale"), Some(1.5));
+        assert_eq!(
+            file.get::<Color>("colors.accent"),
+            Some(Color::from_rgb8(0x4c, 0x6e, 0xf5))
+        );
+        assert_eq!(file.get::<String>("colors.hash").as_deref(), Some("a # b"));
+        assert_eq!(file.get::<bool>("colors.dark"), Some(false));
+        assert!(TokenFile::parse("spacing").is_err());
+        assert!(TokenFile::parse("spacing = twelve").is_err());
+    }
+
+    crate::hot_tokens! {
+        fn tokens() -> Tokens from "tests/missing_tokens.toml" {
+            spacing_md: f32 = 12.0,
+            accent: Color = Color::BLACK,
+        }
+    }
+
+    #[test]
+    fn missing_tokens_keep_their_defaults() {
+        assert_eq!(tokens(), Tokens::default());
+        assert_eq!(tokens().spacing_md, 12.0);
+    }
+}
@@ -0,0 +1,138 @@
+//! Host-owned string interner.
+//!
+//! A `&'static str` created inside the cdylib points into memory owned by that
+//! library and dangles once the library is unloaded. [`intern`] copies the
+//! string into an interner that lives in the host binary instead, so the
+//! returned reference stays valid across reloads.
+//!
+//! The cdylib links its own copy of this crate, so the host hands it a pointer
+//! to its interner through the symbol exported by [`export_interner!`] right
+//! after every library load. Until that happens (and in the host itself) the
+//! local interner is used.
+//!
+//! Interned strings are leaked: they are never freed, not even when the
+//! library that interned them is unloaded. Only intern strings from a bounded
+//! set, like widget ids and subscription names, never ones built from user
+//! input or a counter. [`intern`] panics once the interned strings exceed
+//! [`MAX_INTERNED_BYTES`], so such a mistake fails the call instead of growing
+//! the host for the rest of the session. Messages of caught panics are copied
+//! into an `Arc<str>` instead.
+
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// Total length of the strings the host interns before [`intern`] panics.
+pub const MAX_INTERNED_BYTES: usize = 1 << 20;
+
+static INTERNED: Mutex<Interner> = Mutex::new(Interner::new());
+
+static HOST_INTERNER: OnceLock<fn(&str) -> &'static str> = OnceLock::new();
+
+/// Returns a `&'static str` equal to `value` that is owned by the host binary
+/// and outlives every loaded library. The string is never freed.
+///
+/// # Panics
+///
+/// If `value` is not interned yet and interning it would exceed
+/// [`MAX_INTERNED_BYTES`].
+pub fn intern(value: &str) -> &'static str {
+    match HOST_INTERNER.get() {
+        Some(host_intern) => host_intern(value),
+        None => intern_local(value),
+    }
+}
+
+fn intern_local(value: &str) -> &'static str {
+    // The lock is released before panicking, it is never poisoned by the cap.
+    let interned = INTERNED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_leak(value, MAX_INTERNED_BYTES);
+
+    interned.unwrap_or_else(|| {
+        panic!(
+            "Interning {value:?} exceeds {MAX_INTERNED_BYTES} bytes of interned strings, \
+             only intern strings from a bounded set"
+        )
+    })
+}
+
+struct Interner {
+    strings: Option<HashSet<&'static str>>,
+    bytes: usize,
+}
+
+impl Interner {
+    const fn new() -> Self {
+        Self {
+            strings: None,
+            bytes: 0,
+        }
+    }
+
+    /// The interned copy of `value`, leaked now unless it already was. `None`
+    /// if leaking it would grow the interned strings beyond `limit` bytes.
+    fn get_or_leak(&mut self, value: &str, limit: usize) -> Option<&'static str> {
+        let strings = self.strings.get_or_insert_with(HashSet::new);
+        if let Some(existing) = strings.get(value) {
+            return Some(existing);
+        }
+
+        let bytes = self.bytes + value.len();
+        if bytes > limit {
+            return None;
+        }
+
+        let leaked: &'static str = Box::leak(value.into());
+        strings.insert(leaked);
+        self.bytes = bytes;
+        Some(leaked)
+    }
+}
+
+/// Called by the symbol generated with [`export_interner!`]. Only the first
+/// call has an effect, each library load starts with fresh statics.
+#[doc(hidden)]
+pub fn set_host_interner(host_intern: fn(&str) -> &'static str) {
+    let _ = HOST_INTERNER.set(host_intern);
+}
+
+/// Exports the FFI function the host uses to hand its interner to the
+/// cdylib, one of the [exports of `update`](crate::hot_fn#exports-of-update).
+#[macro_export]
+macro_rules! export_interner {
+    () => {
+        /// Receives the host's interner, see `hot_ice::intern`.
+        #[doc(hidden)]
+        #[allow(non_snake_case, clippy::all)]
+        #[unsafe(no_mangle)]
+        pub fn set_interner_lskdjfa3lkfjasdf(host_intern: fn(&str) -> &'static str) {
+            $crate::macro_use::set_host_interner(host_intern);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern_deduplicates() {
+        let a = intern(&String::from("widget-id"));
+        let b = intern("widget-id");
+        assert_eq!(a, "widget-id");
+        assert!(std::ptr::eq(a, b));
+    }
+
+    #[test]
+    fn interner_stops_at_the_limit() {
+        let mut interner = Interner::new();
+        let id = interner.get_or_leak("row-1", 10).unwrap();
+        assert!(interner.get_or_leak("row-2", 10).is_some());
+        assert_eq!(interner.get_or_leak("row-3", 10), None);
+
+        // Strings interned before the limit was hit are still handed out.
+        assert!(std::ptr::eq(interner.get_or_leak("row-1", 10).unwrap(), id));
+        assert_eq!(interner.bytes, 10);
+    }
+}
@@ -48,15 +48,26 @@ impl<Message> IntoResult<Task<Message>> for HotResult<Task<Message>> {
 // ============================================================================
 // f32 (scale factor)
 // ============================================================================
-impl<T: Into<f32>> IntoResult<f32> for T {
-    fn into_result(self) -> Result<f32, HotIceError> {
-        Ok(self.into())
+/// A scale factor returned as `f32` or `f64`, iced uses `f32`.
+pub trait IntoScaleFactor {
+    fn into_scale_factor(self) -> Result<f32, HotIceError>;
+}
+
+impl IntoScaleFactor for f32 {
+    fn into_scale_factor(self) -> Result<f32, HotIceError> {
+        Ok(self)
     }
 }
 
-impl IntoResult<f32> for HotResult<f32> {
-    fn into_result(self) -> Result<f32, HotIceError> {
-        self.0
+impl IntoScaleFactor for f64 {
+    fn into_scale_factor(self) -> Result<f32, HotIceError> {
+        Ok(self as f32)
+    }
+}
+
+impl<T: IntoScaleFactor> IntoScaleFactor for HotResult<T> {
+    fn into_scale_factor(self) -> Result<f32, HotIceError> {
+        self.0?.into_scale_factor()
     }
 }
 
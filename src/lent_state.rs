@@ -0,0 +1,86 @@
+//! The state while an `async fn update` holds it.
+//!
+//! An async update that does not finish on its first poll keeps borrowing
+//! the state mutably while it runs on the cdylib worker, see
+//! [`spawn_update`](crate::executor::spawn_update). The reloader calls none
+//! of the program's functions on the state until the update returns it:
+//! the program's messages are buffered and replayed afterwards, its events
+//! are dropped, the window shows nothing of the program and its
+//! subscriptions pause. A new library waits for the state without a
+//! timeout, `hot_ice::task::reset_state` is ignored.
+
+use std::sync::Mutex;
+use std::task::{Poll, Waker};
+
+use crate::context::Context;
+
+/// Held by the future of an async update while it borrows the state.
+#[derive(Debug)]
+pub(crate) struct LentState(());
+
+impl LentState {
+    pub(crate) fn new() -> Self {
+        Context::current().lend_state(true);
+        Self(())
+    }
+}
+
+impl Drop for LentState {
+    fn drop(&mut self) {
+        Context::current().lend_state(false);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Host side, called through the context's table
+// ---------------------------------------------------------------------------
+
+struct Lent {
+    updates: usize,
+    waiting: Vec<Waker>,
+}
+
+static LENT: Mutex<Lent> = Mutex::new(Lent {
+    updates: 0,
+    waiting: Vec::new(),
+});
+
+pub(crate) fn lend(lent: bool) {
+    let waiting = {
+        let Ok(mut state) = LENT.lock() else {
+            return;
+        };
+        if lent {
+            state.updates += 1;
+            return;
+        }
+        state.updates = state.updates.saturating_sub(1);
+        if state.updates > 0 {
+            return;
+        }
+        std::mem::take(&mut state.waiting)
+    };
+    waiting.into_iter().for_each(Waker::wake);
+}
+
+/// Whether an async update still borrows the state.
+pub(crate) fn is_lent() -> bool {
+    LENT.lock().is_ok_and(|state| state.updates > 0)
+}
+
+/// Completes once no async update borrows the state any more.
+pub(crate) async fn returned() {
+    std::future::poll_fn(|cx| {
+        let Ok(mut state) = LENT.lock() else {
+            return Poll::Ready(());
+        };
+        if state.updates == 0 {
+            return Poll::Ready(());
+        }
+        if !state.waiting.iter().any(|w| w.will_wake(cx.waker())) {
+            state.waiting.push(cx.waker().clone());
+        }
+        Poll::Pending
+    })
+    .await
+}
@@ -1,29 +1,123 @@
+mod allocator;
+#[cfg(feature = "signed-artifacts")]
+pub mod artifact_signature;
+pub mod bench;
+mod blocking;
+mod build_inputs;
+mod build_progress;
 #[cfg(target_os = "macos")]
 mod codesign;
+mod compiler;
+mod component;
+mod context;
+mod daemon;
+mod during_reload;
 mod error;
+mod error_toasts;
 pub mod executor;
+mod external;
+pub mod ffi;
+mod fingerprint;
 mod functions;
+mod generation_history;
+mod host_resource;
+mod host_runtime;
 mod hot_application;
+pub mod hot_assets;
+mod hot_data;
+mod hot_main;
 mod hot_program;
 mod hot_state;
+pub mod hot_tokens;
+mod intern;
 mod into_result;
+mod lent_state;
 mod lib_reloader;
+mod library_handle;
+mod log_target;
 mod message;
+mod metrics;
+mod overlay_cache;
+mod palette_editor;
 mod panic_hook;
+mod prelink;
+mod recording;
+pub mod registry;
+mod rekeyed;
+mod reload_deferral;
 mod reloader;
+mod restart;
+mod source_revision;
+mod stale_artifact;
+mod stream_handle;
+mod stuck_worker;
+mod subscriptions;
+pub mod task;
+mod taskbar;
+pub mod testing;
+mod transfer;
+mod transport;
+mod type_hash;
+mod ui_crate;
+mod widget_state;
+mod window_geometry;
 mod winit;
+mod worker_queue;
 
+pub use blocking::spawn_blocking;
+pub use compiler::{Cargo, CompileRequest, CompileResult, Compiler, Cranelift, External};
+pub use component::{ComponentMessage, HotComponent, component};
+pub use context::{Context, generation};
+pub use daemon::{HotDaemon, daemon};
+pub use during_reload::{DuringReload, during_reload};
+pub use error::NotTransferable;
+pub use external::ExternalHandle;
+pub use functions::hot_dispatch::{HotDispatch, HotExtension};
+pub use host_resource::HostOwned;
 pub use hot_application::application;
-pub use hot_ice_macros::{hot_fn, hot_state};
-pub use reloader::ReloaderSettings;
+pub use hot_assets::hot_shader;
+pub use hot_data::{HotData, hot_data, hot_data_in};
+pub use hot_ice_macros::{HotMessage, hot_data, hot_fn, hot_state};
+pub use intern::{MAX_INTERNED_BYTES, intern};
+pub use lib_reloader::ArtifactCleanup;
+pub use metrics::{
+    FunctionMetrics, MessageLatency, MessageOrigin, Metrics, QueueMetrics, StateTransfer, metrics,
+};
+pub use reload_deferral::{DeferReloadGuard, defer_reload_guard};
+pub use reloader::{Corner, FunctionState, HotFunction, OverlayPlacement, ReloaderSettings};
+pub use restart::{OnPanic, run_with_restart};
+pub use source_revision::SourceRevision;
+pub use stream_handle::{StreamHandle, run_stream_handle};
+pub use transfer::TransferMessage;
+pub use transport::{FileWatch, ManualTrigger, ReloadSignal, ReloadTransport};
+pub use type_hash::{TypeHash, handoff_hash};
+pub use worker_queue::{Backpressure, WorkerQueue};
 
 pub mod macro_use {
+    pub use super::allocator::{AllocatorVTable, HostAllocator};
+    pub use super::context::set_host_context;
     pub use super::error::{HotIceError, HotResult};
-    pub use super::hot_state::{DynState, HotState};
+    pub use super::executor::{block_on_update, spawn_update};
+    pub use super::fingerprint::BUILD_FINGERPRINT;
+    pub use super::functions::boot::IntoBoot;
+    pub use super::functions::hot_view::HotElement;
+    pub use super::host_resource::set_host_resources;
+    pub use super::hot_data::HotDataExport;
+    pub use super::hot_main::{DefaultMethods, Missing, Optional, hot, plain};
+    pub use super::hot_state::{DynState, FieldMap, HotState, StateField, find_field};
+    pub use super::intern::{intern, set_host_interner};
     pub use super::message::{DynMessage, HotMessage};
+    pub use super::metrics::time_update;
     pub use super::panic_hook::catch_panic;
+    pub use super::registry::{HotExport, type_hash};
+    pub use super::reloader::HotFunction;
+    pub use super::transfer::{Bridge, ViaDowncast, ViaTransfer};
+    pub use super::type_hash::{Unverified, Verified, ViaTypeHash, combine_hashes};
     pub use iced_futures;
     pub use iced_graphics::text::font_system;
+    pub use log;
+    pub use serde;
+    pub use serde_derive;
 }
 
 /// Re-export iced so downstream cdylib crates can use `hot_ice::iced` to
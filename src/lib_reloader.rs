@@ -1,36 +1,86 @@
 use crossfire::{AsyncRx, MTx, mpsc};
 ///This file is copied from the hot-lib-reloader crate
-use libloading::{Library, Symbol};
-use notify::RecursiveMode;
-use notify_debouncer_full::new_debouncer;
+use libloading::Library;
+#[cfg(feature = "signed-artifacts")]
+use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::{
     Arc, Mutex,
     atomic::{AtomicBool, AtomicU32, Ordering},
 };
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use crate::allocator::{self, AllocatorVTable};
+#[cfg(feature = "signed-artifacts")]
+use crate::artifact_signature::{ArtifactVerifier, SignatureError, signature_path};
 use crate::error::HotReloaderError;
+use crate::generation_history::GenerationHistory;
+use crate::library_handle::{LibraryHandle, ResolvedGeneration};
+use crate::log_target::RELOAD;
+use crate::source_revision::SourceRevision;
+use crate::transport::{ReloadEvent, ReloadSignal, ReloadTransport};
 
 // #[cfg(feature = "verbose")]
 // use log;
 
+/// Which copies of the library [`LibReloader`] deletes from the library
+/// directory. Copies are named `<lib>-hot-<pid>-<generation>`, they are
+/// cleaned up when a library is loaded and when the reloader is dropped.
+///
+/// Retired libraries that are still loaded delete their own copy once they
+/// are unloaded, unless [`keep_retired`](Self::keep_retired) is set.
+///
+/// Several instances of the application can watch the same directory: every
+/// instance loads copies of its own, and copies of other running instances
+/// are never deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactCleanup {
+    /// Number of copies to keep, including the loaded one. Default: 3
+    pub keep_last_n: usize,
+    /// Copies older than this are deleted even if they are among the last
+    /// `keep_last_n`. Default: `None`
+    pub max_age: Option<Duration>,
+    /// Delete every copy made by this process when the reloader is dropped.
+    /// Default: true
+    pub on_exit: bool,
+    /// Keep the copies of replaced libraries once they are unloaded, within
+    /// `keep_last_n` and `max_age`, so the overlay's library history can
+    /// activate them again. Default: false
+    pub keep_retired: bool,
+}
+
+impl Default for ArtifactCleanup {
+    fn default() -> Self {
+        Self {
+            keep_last_n: 3,
+            max_age: None,
+            on_exit: true,
+            keep_retired: false,
+        }
+    }
+}
+
 /// A previously loaded library that has been replaced by a newer version.
 ///
 /// The caller is responsible for keeping this alive until any in-flight work
 /// (e.g. spawned async futures) that references code in this library completes.
 /// Dropping this will close the library and clean up its file on disk.
 pub struct RetiredLibrary {
-    pub library: Library,
+    pub library: Arc<Library>,
     pub file_path: PathBuf,
+    /// Size of the library file in bytes, used as an estimate of the memory
+    /// kept mapped while the library stays loaded.
+    pub size: u64,
+    /// Leave the file for the cleanup, see [`ArtifactCleanup::keep_retired`].
+    pub keep_file: bool,
 }
 
 impl Drop for RetiredLibrary {
     fn drop(&mut self) {
-        log::info!("Closing retired library {:?}", self.file_path);
-        if self.file_path.exists() {
+        log::info!(target: RELOAD, "Closing retired library {:?}", self.file_path);
+        if !self.keep_file && self.file_path.exists() {
             let _ = fs::remove_file(&self.file_path);
         }
     }
@@ -69,14 +119,24 @@ pub struct LibReloader {
     lib_dir: PathBuf,
     lib_name: String,
     changed: Arc<AtomicBool>,
-    lib: Option<Library>,
+    lib: Option<Arc<ResolvedGeneration>>,
+    handle: LibraryHandle,
     watched_lib_file: PathBuf,
     loaded_lib_file: PathBuf,
     lib_file_hash: Arc<AtomicU32>,
-    file_change_subscribers: Arc<Mutex<Vec<MTx<()>>>>,
+    file_change_subscribers: Arc<Mutex<Vec<MTx<ReloadEvent>>>>,
     #[cfg(target_os = "macos")]
     codesigner: super::codesign::CodeSigner,
     loaded_lib_name_template: Option<String>,
+    cleanup: ArtifactCleanup,
+    history: GenerationHistory,
+    /// The older generation the next reload activates again instead of the
+    /// watched library.
+    rollback: Option<usize>,
+    checks: LoadChecks,
+    /// The signatures the generations in the history were loaded with.
+    #[cfg(feature = "signed-artifacts")]
+    signatures: HashMap<usize, Vec<u8>>,
 }
 
 impl LibReloader {
@@ -87,16 +147,21 @@ impl LibReloader {
     /// normally be just the crate name of the cargo project you want to hot-reload.
     /// LibReloader will take care to figure out the actual file name with
     /// platform-specific prefix and extension.
+    /// `transport` is started on the library file and signals its changes.
+    /// The last `history_len` libraries loaded are recorded, see
+    /// [`LibReloader::activate`].
     pub fn new(
         lib_dir: impl AsRef<Path>,
         lib_name: impl AsRef<str>,
-        file_watch_debounce: Option<Duration>,
+        transport: &dyn ReloadTransport,
         loaded_lib_name_template: Option<String>,
+        cleanup: ArtifactCleanup,
+        history_len: usize,
     ) -> Result<Self, HotReloaderError> {
         // find the target dir in which the build is happening and where we should find
         // the library
         let lib_dir = find_file_or_dir_in_parent_directories(lib_dir.as_ref())?;
-        log::debug!("found lib dir at {lib_dir:?}");
+        log::debug!(target: RELOAD, "found lib dir at {lib_dir:?}");
 
         let load_counter = 0;
 
@@ -110,32 +175,69 @@ impl LibReloader {
             &loaded_lib_name_template,
         );
 
-        let (lib_file_hash, lib) = if watched_lib_file.exists() {
+        let checks = LoadChecks::new(transport);
+        let mut loaded = None;
+        let complete = watched_lib_file.exists()
+            && wait_for_artifact(&watched_lib_file, ARTIFACT_TIMEOUT)
+                .inspect_err(|reason| {
+                    log::error!(
+                        target: RELOAD,
+                        "Not loading {watched_lib_file:?}, it is incomplete: {reason}"
+                    )
+                })
+                .is_ok();
+        if complete {
             // We don't load the actual lib because this can get problems e.g. on Windows
             // where a file lock would be held, preventing the lib from changing later.
-            log::debug!("copying {watched_lib_file:?} -> {loaded_lib_file:?}");
-            fs::copy(&watched_lib_file, &loaded_lib_file)?;
-            let hash = hash_file(&loaded_lib_file);
-            #[cfg(target_os = "macos")]
-            codesigner.codesign(&loaded_lib_file);
-            let library = load_library(&loaded_lib_file)?;
-            log::debug!("Loaded {loaded_lib_file:?}");
-            (hash, Some(library))
-        } else {
-            log::debug!("library {watched_lib_file:?} does not yet exist");
-            (0, None)
-        };
+            log::debug!(target: RELOAD, "copying {watched_lib_file:?} -> {loaded_lib_file:?}");
+            copy_artifact(&lib_dir, &watched_lib_file, &loaded_lib_file)?;
+            // A library that fails the checks is not loaded, the next build is.
+            match checks.check(&loaded_lib_file, Expected::Build(&watched_lib_file)) {
+                Ok(checked) => {
+                    #[cfg(target_os = "macos")]
+                    codesigner.codesign(&loaded_lib_file);
+                    let library = load_library(&loaded_lib_file)?;
+                    log::debug!(target: RELOAD, "Loaded {loaded_lib_file:?}");
+                    loaded = Some((checked, library));
+                }
+                Err(err) => {
+                    log::error!(target: RELOAD, "{err}");
+                    let _ = fs::remove_file(&loaded_lib_file);
+                }
+            }
+        } else if !watched_lib_file.exists() {
+            log::debug!(target: RELOAD, "library {watched_lib_file:?} does not yet exist");
+        }
+
+        let lib_file_hash = loaded.as_ref().map_or(0, |(checked, _)| checked.hash);
+        #[cfg(feature = "signed-artifacts")]
+        let signatures = loaded
+            .as_mut()
+            .and_then(|(checked, _)| checked.signature.take())
+            .map(|signature| HashMap::from([(load_counter, signature)]))
+            .unwrap_or_default();
+        let lib =
+            loaded.map(|(_, library)| Arc::new(ResolvedGeneration::resolve(Arc::new(library))));
+
+        let mut history = GenerationHistory::new(history_len);
+        if lib.is_some() {
+            history.push(load_counter, loaded_lib_file.clone(), lib_file_hash, None);
+        }
 
         let lib_file_hash = Arc::new(AtomicU32::new(lib_file_hash));
         let changed = Arc::new(AtomicBool::new(false));
         let file_change_subscribers = Arc::new(Mutex::new(Vec::new()));
-        Self::watch(
-            watched_lib_file.clone(),
-            lib_file_hash.clone(),
-            changed.clone(),
-            file_change_subscribers.clone(),
-            file_watch_debounce.unwrap_or_else(|| Duration::from_millis(500)),
-        )?;
+        transport.start(
+            &watched_lib_file,
+            ReloadSignal::new(
+                changed.clone(),
+                lib_file_hash.clone(),
+                file_change_subscribers.clone(),
+            ),
+        );
+
+        let handle = LibraryHandle::new();
+        handle.publish(lib.clone());
 
         let lib_loader = Self {
             load_counter,
@@ -144,26 +246,92 @@ impl LibReloader {
             watched_lib_file,
             loaded_lib_file,
             lib,
+            handle,
             lib_file_hash,
             changed,
             file_change_subscribers,
             #[cfg(target_os = "macos")]
             codesigner,
             loaded_lib_name_template,
+            cleanup,
+            history,
+            rollback: None,
+            checks,
+            #[cfg(feature = "signed-artifacts")]
+            signatures,
         };
+        lib_loader.cleanup_artifacts();
 
         Ok(lib_loader)
     }
 
-    // needs to be public as it is used inside the hot_module macro.
-    pub fn subscribe_to_file_changes(&mut self) -> AsyncRx<()> {
-        log::debug!("subscribe to file change");
+    pub(crate) fn subscribe_to_file_changes(&mut self) -> AsyncRx<ReloadEvent> {
+        log::debug!(target: RELOAD, "subscribe to file change");
         let (tx, rx) = mpsc::unbounded_async();
         let mut subscribers = self.file_change_subscribers.lock().unwrap();
         subscribers.push(tx);
         rx
     }
 
+    /// Loads the library at the watched path again even if it did not
+    /// change, unless a reload is pending already. Returns whether it starts
+    /// a reload.
+    pub(crate) fn request_reload(&self) -> bool {
+        if !self.watched_lib_file.exists() || self.changed.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        self.notify_subscribers();
+        true
+    }
+
+    /// Loads the older `generation` of the history again, unless a reload is
+    /// pending already. Returns whether it starts a reload.
+    pub(crate) fn activate(&mut self, generation: usize) -> Result<bool, String> {
+        let record = self
+            .history
+            .get(generation)
+            .ok_or_else(|| format!("Library {generation} is no longer in the history"))?;
+        if record.file == self.loaded_lib_file {
+            return Ok(false);
+        }
+        if !record.is_available() {
+            return Err(format!(
+                "The copy of library {generation} was deleted, keep it with \
+                 `ArtifactCleanup::keep_retired`"
+            ));
+        }
+        if self.changed.swap(true, Ordering::AcqRel) {
+            return Ok(false);
+        }
+        log::info!(target: RELOAD, "Activating library {generation} again");
+        self.rollback = Some(generation);
+        self.notify_subscribers();
+        Ok(true)
+    }
+
+    /// Tells the subscribers that a reload is pending.
+    fn notify_subscribers(&self) {
+        if let Ok(subscribers) = self.file_change_subscribers.lock() {
+            for tx in &*subscribers {
+                let _ = tx.send(ReloadEvent::Changed);
+            }
+        }
+    }
+
+    /// Starts `transport` on the library file again, after it told the
+    /// subscribers that it died.
+    pub(crate) fn restart_transport(&self, transport: &dyn ReloadTransport) {
+        log::info!(target: RELOAD, "Restarting the transport");
+        transport.start(
+            &self.watched_lib_file,
+            ReloadSignal::new(
+                self.changed.clone(),
+                self.lib_file_hash.clone(),
+                self.file_change_subscribers.clone(),
+            ),
+        );
+    }
+
     /// Checks if the watched library has changed. If it has, reload it and return
     /// true. Otherwise return false.
     ///
@@ -186,189 +354,114 @@ impl LibReloader {
     /// Returns the old library (if any) instead of closing it, so the caller
     /// can retain it while futures spawned on it are still running.
     fn reload(&mut self) -> Result<Option<RetiredLibrary>, HotReloaderError> {
-        let Self {
-            load_counter,
-            lib_dir,
-            lib_name,
-            watched_lib_file,
-            loaded_lib_file,
-            lib,
-            loaded_lib_name_template,
-            ..
-        } = self;
+        // An older generation activated again is loaded from its copy.
+        let rollback = self.rollback.take().and_then(|generation| {
+            let record = self.history.get(generation)?;
+            Some((generation, record.file.clone(), record.hash))
+        });
+        let source_file = match &rollback {
+            Some((_, file, _)) => file.clone(),
+            None => self.watched_lib_file.clone(),
+        };
 
-        log::info!("reloading lib {watched_lib_file:?}");
+        log::info!(target: RELOAD, "reloading lib {source_file:?}");
 
-        // Take the old library out instead of closing it
-        let retired = if let Some(old_lib) = lib.take() {
-            let old_path = loaded_lib_file.clone();
-            Some(RetiredLibrary {
-                library: old_lib,
-                file_path: old_path,
-            })
-        } else {
-            None
+        if !source_file.exists() {
+            log::warn!(target: RELOAD, "trying to reload library but it does not exist");
+            return Ok(self.retire());
+        }
+
+        let generation = self.load_counter + 1;
+        let (_, loaded_lib_file) = watched_and_loaded_library_paths(
+            &self.lib_dir,
+            &self.lib_name,
+            generation,
+            &self.loaded_lib_name_template,
+        );
+        log::info!(target: RELOAD, "copy {source_file:?} -> {loaded_lib_file:?}");
+        copy_artifact(&self.lib_dir, &source_file, &loaded_lib_file)?;
+
+        // The copy is checked right before it is loaded, one that fails, e.g.
+        // because the linker is still writing the new library, leaves the
+        // loaded library in place. The next change starts another reload.
+        #[cfg(feature = "signed-artifacts")]
+        let signature = rollback
+            .as_ref()
+            .and_then(|(generation, _, _)| self.signatures.get(generation))
+            .map(Vec::as_slice);
+        let expected = match &rollback {
+            Some((_, _, hash)) => Expected::Generation {
+                hash: *hash,
+                #[cfg(feature = "signed-artifacts")]
+                signature,
+            },
+            None => Expected::Build(&self.watched_lib_file),
         };
+        let checked = self
+            .checks
+            .check(&loaded_lib_file, expected)
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&loaded_lib_file);
+            })?;
+        log::info!(
+            target: RELOAD,
+            "loaded {loaded_lib_file:?} (hash={:#010x}, size={})",
+            checked.hash,
+            checked.size
+        );
 
-        if watched_lib_file.exists() {
-            *load_counter += 1;
-            let (_, loaded_lib_file) = watched_and_loaded_library_paths(
-                lib_dir,
-                lib_name,
-                *load_counter,
-                loaded_lib_name_template,
-            );
-            let watched_hash = hash_file(watched_lib_file.as_path());
-            let watched_size = fs::metadata(watched_lib_file.as_path())
-                .map(|m| m.len())
-                .unwrap_or(0);
-            log::info!(
-                "copy {watched_lib_file:?} (hash={:#010x}, size={}) -> {loaded_lib_file:?}",
-                watched_hash,
-                watched_size
-            );
-            fs::copy(watched_lib_file, &loaded_lib_file)?;
-            let copied_hash = hash_file(&loaded_lib_file);
-            log::info!("loaded {loaded_lib_file:?} (hash={:#010x})", copied_hash);
-            self.lib_file_hash.store(copied_hash, Ordering::Release);
-            #[cfg(target_os = "macos")]
-            self.codesigner.codesign(&loaded_lib_file);
-            self.lib = Some(load_library(&loaded_lib_file)?);
-            self.loaded_lib_file = loaded_lib_file;
-        } else {
-            log::warn!("trying to reload library but it does not exist");
+        self.load_counter = generation;
+        let retired = self.retire();
+        // After a rollback the watched library stays the latest build,
+        // only its next change is loaded.
+        let watched_hash = match rollback {
+            Some(_) => hash_file(&self.watched_lib_file),
+            None => checked.hash,
+        };
+        self.lib_file_hash.store(watched_hash, Ordering::Release);
+        #[cfg(target_os = "macos")]
+        self.codesigner.codesign(&loaded_lib_file);
+        let library = Arc::new(load_library(&loaded_lib_file)?);
+        self.lib = Some(Arc::new(ResolvedGeneration::resolve(library)));
+        self.handle.publish(self.lib.clone());
+        self.history.push(
+            self.load_counter,
+            loaded_lib_file.clone(),
+            checked.hash,
+            rollback.map(|(generation, _, _)| generation),
+        );
+        #[cfg(feature = "signed-artifacts")]
+        {
+            if let Some(signature) = checked.signature {
+                self.signatures.insert(self.load_counter, signature);
+            }
+            let history = &self.history;
+            self.signatures
+                .retain(|generation, _| history.get(*generation).is_some());
         }
+        self.loaded_lib_file = loaded_lib_file;
+        self.cleanup_artifacts();
 
         Ok(retired)
     }
 
-    /// Watch for changes of `lib_file`.
-    fn watch(
-        lib_file: impl AsRef<Path>,
-        lib_file_hash: Arc<AtomicU32>,
-        changed: Arc<AtomicBool>,
-        file_change_subscribers: Arc<Mutex<Vec<MTx<()>>>>,
-        debounce: Duration,
-    ) -> Result<(), HotReloaderError> {
-        let lib_file = lib_file.as_ref().to_path_buf();
-        log::info!("start watching changes of file {}", lib_file.display());
-
-        // File watcher thread. We watch `self.lib_file`, when it changes and we haven't
-        // a pending change still waiting to be loaded, set `self.changed` to true. This
-        // then gets picked up by `self.update`.
-        thread::spawn(move || {
-            let (tx, rx) = std::sync::mpsc::channel();
-
-            let mut debouncer =
-                new_debouncer(debounce, None, tx).expect("creating notify debouncer");
-
-            debouncer
-                .watch(&lib_file, RecursiveMode::NonRecursive)
-                .expect("watch lib file");
-
-            let signal_change = || {
-                let current_hash = hash_file(&lib_file);
-                let stored_hash = lib_file_hash.load(Ordering::Acquire);
-                let already_changed = changed.load(Ordering::Acquire);
-                if current_hash == stored_hash || already_changed {
-                    log::debug!(
-                        "signal_change: skip (current={:#010x}, stored={:#010x}, pending={})",
-                        current_hash,
-                        stored_hash,
-                        already_changed
-                    );
-                    return false;
-                }
-
-                log::info!(
-                    "{lib_file:?} changed (hash {:#010x} -> {:#010x})",
-                    stored_hash,
-                    current_hash
-                );
-
-                changed.store(true, Ordering::Release);
-
-                // inform subscribers
-                let subscribers = file_change_subscribers.lock().unwrap();
-                log::debug!(
-                    "sending ChangedEvent::LibFileChanged to {} subscribers",
-                    subscribers.len()
-                );
-                for tx in &*subscribers {
-                    let _ = tx.send(());
-                }
-
-                true
-            };
-
-            loop {
-                match rx.recv() {
-                    Err(_) => {
-                        log::info!("file watcher channel closed");
-                        break;
-                    }
-                    Ok(events) => {
-                        let events = match events {
-                            Err(errors) => {
-                                log::error!("{} file watcher error!", errors.len());
-                                for err in errors {
-                                    log::error!("  {err}");
-                                }
-                                continue;
-                            }
-                            Ok(events) => events,
-                        };
-
-                        // Ignore Access events (Open/Read/Close) — they don't
-                        // indicate file content changed and fire continuously
-                        // after reload when the library is memory-mapped.
-                        let dominated_by_access = events
-                            .iter()
-                            .all(|e| matches!(e.kind, notify::EventKind::Access(_)));
-                        if dominated_by_access {
-                            continue;
-                        }
-
-                        log::debug!("file change events: {events:?}");
-                        let was_removed =
-                            events
-                                .iter()
-                                .fold(false, |was_removed, event| match event.kind {
-                                    notify::EventKind::Create(_) | notify::EventKind::Modify(_) => {
-                                        false
-                                    }
-                                    notify::EventKind::Remove(_) => true,
-                                    _ => was_removed,
-                                });
-                        // just one hard link removed?
-                        if was_removed || !lib_file.exists() {
-                            log::debug!(
-                                "{} was removed, trying to watch it again...",
-                                lib_file.display()
-                            );
-                            loop {
-                                if debouncer
-                                    .watch(&lib_file, RecursiveMode::NonRecursive)
-                                    .is_ok()
-                                {
-                                    log::info!("watching {lib_file:?} again after removal");
-                                    break;
-                                }
-                                thread::sleep(Duration::from_millis(500));
-                            }
-                        }
-
-                        signal_change();
-                    }
-                }
-            }
+    /// Takes the loaded library out instead of closing it.
+    fn retire(&mut self) -> Option<RetiredLibrary> {
+        let retired = self.lib.take().map(|old_lib| RetiredLibrary {
+            library: old_lib.library().clone(),
+            file_path: self.loaded_lib_file.clone(),
+            size: fs::metadata(&self.loaded_lib_file)
+                .map(|m| m.len())
+                .unwrap_or(0),
+            keep_file: self.cleanup.keep_retired,
         });
-
-        Ok(())
+        self.handle.publish(None);
+        retired
     }
 
-    /// Get a pointer to a function or static variable by symbol name. Just a
-    /// wrapper around [libloading::Library::get].
+    /// Get a pointer to a function or static variable by symbol name. Known
+    /// symbols were resolved when the library was loaded, others are looked
+    /// up with [libloading::Library::get].
     ///
     /// The `symbol` may not contain any null bytes, with the exception of the
     /// last byte. Providing a null-terminated `symbol` may help to avoid an
@@ -376,35 +469,162 @@ impl LibReloader {
     ///
     /// # Safety
     ///
-    /// Users of this API must specify the correct type of the function or variable loaded.
-    pub unsafe fn get_symbol<'lib, T>(
-        &'lib self,
-        name: &[u8],
-    ) -> Result<Symbol<'lib, T>, HotReloaderError> {
+    /// Users of this API must specify the correct type of the function or variable loaded,
+    /// and must not use it after the library is unloaded.
+    pub unsafe fn get_symbol<T: Copy>(&self, name: &str) -> Result<T, HotReloaderError> {
         match &self.lib {
             None => Err(HotReloaderError::LibraryNotLoaded),
-            Some(lib) => Ok(unsafe { lib.get(name)? }),
+            Some(lib) => unsafe { lib.get_symbol(name) },
+        }
+    }
+
+    /// The loaded library, if any.
+    pub(crate) fn library(&self) -> Option<&Library> {
+        self.lib.as_deref().map(|lib| &**lib.library())
+    }
+
+    /// The loaded library, for threads that keep it loaded while they use it.
+    pub(crate) fn shared_library(&self) -> Option<Arc<Library>> {
+        self.lib.as_ref().map(|lib| Arc::clone(lib.library()))
+    }
+
+    /// The handle hot functions load the library from, it follows every
+    /// reload of this reloader.
+    pub(crate) fn handle(&self) -> LibraryHandle {
+        self.handle.clone()
+    }
+
+    /// Copies of the library in the library directory, except the loaded
+    /// one, newest first. With `own_only`, only copies made by this process,
+    /// otherwise also the ones of processes that are no longer running.
+    /// Copies of other running instances are never listed, they may be
+    /// loaded.
+    fn artifacts(&self, own_only: bool) -> Vec<(PathBuf, SystemTime)> {
+        let (prefix, ext) = lib_prefix_and_ext();
+        let artifact_prefix = match own_only {
+            true => format!("{prefix}{}-hot-{}-", self.lib_name, std::process::id()),
+            false => format!("{prefix}{}-hot-", self.lib_name),
+        };
+
+        let Ok(entries) = fs::read_dir(&self.lib_dir) else {
+            return Vec::new();
+        };
+        let mut artifacts: Vec<_> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().is_some_and(|e| e == ext)
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(&artifact_prefix))
+                    && *path != self.loaded_lib_file
+                    && artifact_pid(path)
+                        .is_none_or(|pid| pid == std::process::id() || !process_is_running(pid))
+            })
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, modified))
+            })
+            .collect();
+        artifacts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        artifacts
+    }
+
+    /// Deletes the copies of the library the [`ArtifactCleanup`] policy does
+    /// not keep. Copies still mapped by a retained library are deleted too,
+    /// which only fails on platforms that lock loaded libraries.
+    fn cleanup_artifacts(&self) {
+        if self.loaded_lib_name_template.is_some() {
+            return;
+        }
+
+        // Other instances list and copy the artifacts under the same lock.
+        let _lock = lock_artifacts(&self.lib_dir);
+        let keep = self.cleanup.keep_last_n.saturating_sub(1);
+        let now = SystemTime::now();
+        for (index, (path, modified)) in self.artifacts(false).into_iter().enumerate() {
+            let expired = self
+                .cleanup
+                .max_age
+                .is_some_and(|max_age| now.duration_since(modified).is_ok_and(|age| age > max_age));
+            if index < keep && !expired {
+                continue;
+            }
+
+            match fs::remove_file(&path) {
+                Ok(()) => log::debug!(target: RELOAD, "removed old library copy {path:?}"),
+                Err(err) => log::debug!(
+                    target: RELOAD,
+                    "failed to remove old library copy {path:?}: {err}"
+                ),
+            }
         }
     }
 
+    /// The libraries loaded so far.
+    pub(crate) fn history(&self) -> &GenerationHistory {
+        &self.history
+    }
+
+    /// Records the sources the library just loaded was built from, and
+    /// returns them. A rollback keeps the sources of its generation.
+    pub(crate) fn built_from(&mut self, source: Option<SourceRevision>) -> Option<SourceRevision> {
+        self.history.built_from(source)
+    }
+
+    /// Whether the watched library changed since it was last loaded.
+    pub fn has_changed(&self) -> bool {
+        self.changed.load(Ordering::Acquire)
+    }
+
+    /// Path of the library the build writes, which is copied before loading.
+    pub fn watched_lib_file(&self) -> &Path {
+        &self.watched_lib_file
+    }
+
+    /// Path of the library copy that is currently loaded, if any.
+    pub fn loaded_lib_file(&self) -> Option<&Path> {
+        self.lib.as_ref().map(|_| self.loaded_lib_file.as_path())
+    }
+
     // Helper to log from the macro without requiring the user to have the log
     // crate around
     // #[doc(hidden)]
     // pub fn log_info(what: impl std::fmt::Display) {
-    //     log::info!("{}", what);
+    //     log::info!(target: RELOAD, "{}", what);
     // }
 }
 
-/// Deletes the currently loaded lib file if it exists
+/// Deletes the currently loaded lib file if it exists, and with
+/// [`ArtifactCleanup::on_exit`] every other copy made by this process.
 impl Drop for LibReloader {
     fn drop(&mut self) {
         if self.loaded_lib_file.exists() {
-            log::debug!("removing {:?}", self.loaded_lib_file);
+            log::debug!(target: RELOAD, "removing {:?}", self.loaded_lib_file);
             let _ = fs::remove_file(&self.loaded_lib_file);
         }
+
+        if self.cleanup.on_exit && self.loaded_lib_name_template.is_none() {
+            for (path, _) in self.artifacts(true) {
+                log::debug!(target: RELOAD, "removing {path:?}");
+                let _ = fs::remove_file(&path);
+            }
+        }
     }
 }
 
+/// OS dependent file name prefix and extension of a dynamic library.
+pub(crate) fn lib_prefix_and_ext() -> (&'static str, &'static str) {
+    #[cfg(target_os = "macos")]
+    let (prefix, ext) = ("lib", "dylib");
+    #[cfg(target_os = "linux")]
+    let (prefix, ext) = ("lib", "so");
+    #[cfg(target_os = "windows")]
+    let (prefix, ext) = ("", "dll");
+    (prefix, ext)
+}
+
 fn watched_and_loaded_library_paths(
     lib_dir: impl AsRef<Path>,
     lib_name: impl AsRef<str>,
@@ -413,13 +633,7 @@ fn watched_and_loaded_library_paths(
 ) -> (PathBuf, PathBuf) {
     let lib_dir = &lib_dir.as_ref();
 
-    // sort out os dependent file name
-    #[cfg(target_os = "macos")]
-    let (prefix, ext) = ("lib", "dylib");
-    #[cfg(target_os = "linux")]
-    let (prefix, ext) = ("lib", "so");
-    #[cfg(target_os = "windows")]
-    let (prefix, ext) = ("", "dll");
+    let (prefix, ext) = lib_prefix_and_ext();
     let lib_name = format!("{prefix}{}", lib_name.as_ref());
 
     let watched_lib_file = lib_dir.join(&lib_name).with_extension(ext);
@@ -438,12 +652,231 @@ fn watched_and_loaded_library_paths(
             // #[cfg(not(feature = "uuid"))]
             result
         }
-        None => format!("{lib_name}-hot-{load_counter}"),
+        None => format!("{lib_name}-hot-{}-{load_counter}", std::process::id()),
     };
     let loaded_lib_file = lib_dir.join(loaded_lib_filename).with_extension(ext);
     (watched_lib_file, loaded_lib_file)
 }
 
+/// Name of the lock file in the library directory, held while an instance
+/// copies the library or deletes old copies.
+const ARTIFACT_LOCK_FILE: &str = ".hot_ice.lock";
+
+/// Takes the advisory lock of the library directory, so instances watching
+/// the same directory do not delete the copies others are making. Blocks
+/// until other instances released it, `None` if locking is not supported.
+fn lock_artifacts(lib_dir: &Path) -> Option<fs::File> {
+    let lock = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lib_dir.join(ARTIFACT_LOCK_FILE))
+        .inspect_err(|err| log::debug!(target: RELOAD, "failed to open the artifact lock: {err}"))
+        .ok()?;
+    lock.lock()
+        .inspect_err(|err| log::debug!(target: RELOAD, "failed to lock the artifacts: {err}"))
+        .ok()?;
+    Some(lock)
+}
+
+/// Copies the library the build wrote to this instance's copy. The copy is
+/// written under a temporary name and renamed once complete, another instance
+/// never sees a truncated copy.
+fn copy_artifact(lib_dir: &Path, watched: &Path, loaded: &Path) -> std::io::Result<()> {
+    let _lock = lock_artifacts(lib_dir);
+    let (_, ext) = lib_prefix_and_ext();
+    let partial = loaded.with_extension(format!("{ext}.partial"));
+    fs::copy(watched, &partial)?;
+    fs::rename(&partial, loaded).inspect_err(|_| {
+        let _ = fs::remove_file(&partial);
+    })
+}
+
+/// The id of the process that made the copy of the library at `path`, named
+/// `<lib>-hot-<pid>-<generation>`.
+fn artifact_pid(path: &Path) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    let (_, suffix) = stem.rsplit_once("-hot-")?;
+    let (pid, _generation) = suffix.split_once('-')?;
+    pid.parse().ok()
+}
+
+/// Whether the process `pid` is still running. Assumed to be on platforms
+/// where this cannot be checked, so its copies are left alone.
+fn process_is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // Signal 0 only checks that the process exists, `EPERM` means it
+        // belongs to another user.
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        let exists = unsafe { libc::kill(pid, 0) } == 0;
+        exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        true
+    }
+}
+
+/// How long the size of a new library has to stay the same before it is
+/// considered completely written.
+const ARTIFACT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a changed library may take to be completely written before it is
+/// skipped until it changes again.
+pub(crate) const ARTIFACT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The sidecar next to the library holding the hash of the last complete
+/// build, `<lib>.<ext>.hash`. The compile watcher removes it when a build
+/// starts and writes it once cargo finished.
+pub(crate) fn artifact_hash_path(library: &Path) -> PathBuf {
+    let (_, ext) = lib_prefix_and_ext();
+    library.with_extension(format!("{ext}.hash"))
+}
+
+/// Writes the sidecar of `library` after a build finished.
+pub(crate) fn write_artifact_hash(library: &Path) {
+    let hash = format!("{:08x}\n", hash_file(library));
+    if let Err(err) = fs::write(artifact_hash_path(library), hash) {
+        log::warn!(target: RELOAD, "Failed to write the hash of {library:?}: {err}");
+    }
+}
+
+/// Removes the sidecar of `library` when a build starts.
+pub(crate) fn remove_artifact_hash(library: &Path) {
+    let _ = fs::remove_file(artifact_hash_path(library));
+}
+
+/// Checks that the library at `path` is completely written: it passes
+/// [`check_artifact`] and matches the hash of its sidecar if there is one.
+/// Returns its hash.
+fn verify_artifact(path: &Path) -> Result<u32, String> {
+    let Digest {
+        hash,
+        size,
+        content,
+    } = digest_file(path, false).map_err(|err| err.to_string())?;
+    check_header(&content, size)?;
+    check_sidecar(path, hash)?;
+    Ok(hash)
+}
+
+/// Checks that the library at `path` looks completely written from its
+/// header and size alone, without reading the rest. Returns its size.
+fn check_artifact(path: &Path) -> Result<u64, String> {
+    let mut file = fs::File::open(path).map_err(|err| err.to_string())?;
+    let size = file.metadata().map_err(|err| err.to_string())?.len();
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    (&mut file)
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)
+        .map_err(|err| err.to_string())?;
+    check_header(&header, size)?;
+    Ok(size)
+}
+
+/// Waits until the library at `path` is completely written: it passes
+/// [`check_artifact`] with the same size over two polls, and then
+/// [`verify_artifact`]. Only the last poll reads the whole file. Gives up
+/// after `timeout`, returns the hash of the library.
+pub(crate) fn wait_for_artifact(path: &Path, timeout: Duration) -> Result<u32, String> {
+    let started = std::time::Instant::now();
+    let mut previous = None;
+    loop {
+        let result = check_artifact(path);
+        if matches!((&result, &previous), (Ok(size), Some(Ok(previous))) if size == previous) {
+            match verify_artifact(path) {
+                Ok(hash) => return Ok(hash),
+                Err(reason) if started.elapsed() >= timeout => return Err(reason),
+                Err(_) => {}
+            }
+        } else if started.elapsed() >= timeout {
+            result?;
+            return Err("the size is still changing".to_string());
+        }
+        previous = Some(result);
+        std::thread::sleep(ARTIFACT_POLL_INTERVAL);
+    }
+}
+
+/// Checks that `hash` matches the sidecar of `library`, if it has one.
+fn check_sidecar(library: &Path, hash: u32) -> Result<(), String> {
+    let Ok(sidecar) = fs::read_to_string(artifact_hash_path(library)) else {
+        return Ok(());
+    };
+    let expected = u32::from_str_radix(sidecar.trim(), 16)
+        .map_err(|_| format!("invalid hash sidecar {sidecar:?}"))?;
+    if hash != expected {
+        return Err(format!(
+            "hash {hash:#010x} does not match the build's {expected:#010x}"
+        ));
+    }
+    Ok(())
+}
+
+/// How many bytes of a library [`check_header`] reads.
+const HEADER_LEN: usize = 64;
+
+/// Checks the magic number of a dynamic library of this platform, in the
+/// first bytes of a file of `size` bytes. Sections headers are written last
+/// by the linkers, an ELF file too short for its table is truncated.
+fn check_header(content: &[u8], size: u64) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let valid = content.starts_with(b"MZ");
+    #[cfg(target_os = "macos")]
+    let valid = content.len() >= 4
+        && matches!(
+            u32::from_be_bytes([content[0], content[1], content[2], content[3]]),
+            0xfeed_face | 0xfeed_facf | 0xcefa_edfe | 0xcffa_edfe | 0xcafe_babe
+        );
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let valid = content.starts_with(b"\x7fELF");
+
+    if !valid {
+        return Err("not a dynamic library, the header is missing".to_string());
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let end = elf_section_headers_end(content)
+            .ok_or_else(|| "the ELF header is truncated".to_string())?;
+        if end > size {
+            return Err(format!(
+                "truncated, the section headers end at {end} but the file has {size} bytes"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Where the section header table of an ELF file ends, `None` if the header
+/// itself is incomplete.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn elf_section_headers_end(content: &[u8]) -> Option<u64> {
+    let little_endian = *content.get(5)? == 1;
+    let field = |offset: usize, len: usize| -> Option<u64> {
+        let mut bytes = content.get(offset..offset + len)?.to_vec();
+        if little_endian {
+            bytes.reverse();
+        }
+        let value = bytes
+            .iter()
+            .fold(0, |value, byte| (value << 8) | u64::from(*byte));
+        Some(value)
+    };
+    // 32 and 64 bit files differ in the width of the offsets.
+    let (shoff, shentsize, shnum) = match content.get(4)? {
+        1 => (field(0x20, 4)?, field(0x2e, 2)?, field(0x30, 2)?),
+        2 => (field(0x28, 8)?, field(0x3a, 2)?, field(0x3c, 2)?),
+        _ => return None,
+    };
+    Some(shoff + shentsize * shnum)
+}
+
 /// Try to find that might be a relative path such as `target/debug/` by walking
 /// up the directories, starting from cwd. This helps finding the lib when the
 /// app was started from a directory that is not the project/workspace root.
@@ -475,12 +908,312 @@ fn find_file_or_dir_in_parent_directories(
     }
 }
 
+/// What the copy of a library is checked against before it is loaded.
+enum Expected<'a> {
+    /// The library the build wrote, with its sidecar and signature.
+    Build(&'a Path),
+    /// An older generation activated again, with the hash and signature it
+    /// was loaded with.
+    Generation {
+        hash: u32,
+        #[cfg(feature = "signed-artifacts")]
+        signature: Option<&'a [u8]>,
+    },
+}
+
+/// A copy that passed the [`LoadChecks`].
+struct CheckedCopy {
+    hash: u32,
+    size: u64,
+    /// The signature the copy was verified with.
+    #[cfg(feature = "signed-artifacts")]
+    signature: Option<Vec<u8>>,
+}
+
+/// The checks every copy of the library passes right before it is loaded,
+/// on startup, on a reload and on a rollback. They run on the bytes of the
+/// copy itself, the file that is loaded, not the one the transport wrote.
+#[derive(Debug, Default)]
+struct LoadChecks {
+    /// Checks the signature, set by a [`Signed`](crate::artifact_signature::Signed)
+    /// transport.
+    #[cfg(feature = "signed-artifacts")]
+    verifier: Option<Arc<ArtifactVerifier>>,
+}
+
+impl LoadChecks {
+    #[cfg_attr(not(feature = "signed-artifacts"), allow(unused_variables))]
+    fn new(transport: &dyn ReloadTransport) -> Self {
+        Self {
+            #[cfg(feature = "signed-artifacts")]
+            verifier: transport.verifier(),
+        }
+    }
+
+    /// Reads the copy at `copy` once and checks that it is a complete
+    /// library, the one `expected` describes, and signed by a trusted key if
+    /// signatures are checked. Only a signature needs the whole content in
+    /// memory, otherwise the copy is hashed in chunks.
+    fn check(&self, copy: &Path, expected: Expected<'_>) -> Result<CheckedCopy, HotReloaderError> {
+        let incomplete =
+            |reason: String| HotReloaderError::IncompleteArtifact(copy.to_path_buf(), reason);
+        #[cfg(feature = "signed-artifacts")]
+        let keep_content = self.verifier.is_some();
+        #[cfg(not(feature = "signed-artifacts"))]
+        let keep_content = false;
+        let Digest {
+            hash,
+            size,
+            content,
+        } = digest_file(copy, keep_content)?;
+        check_header(&content, size).map_err(incomplete)?;
+        match &expected {
+            Expected::Build(watched) => check_sidecar(watched, hash).map_err(incomplete)?,
+            Expected::Generation { hash: loaded, .. } if *loaded != hash => {
+                return Err(incomplete(format!(
+                    "hash {hash:#010x} differs from the {loaded:#010x} it was loaded with"
+                )));
+            }
+            Expected::Generation { .. } => {}
+        }
+
+        #[cfg(feature = "signed-artifacts")]
+        if let Some(verifier) = &self.verifier {
+            let untrusted =
+                |err: SignatureError| HotReloaderError::UntrustedArtifact(copy.to_path_buf(), err);
+            let signature = match expected {
+                Expected::Build(watched) => {
+                    fs::read(signature_path(watched)).map_err(|err| untrusted(err.into()))?
+                }
+                Expected::Generation { signature, .. } => signature
+                    .ok_or_else(|| untrusted(SignatureError::Untrusted))?
+                    .to_vec(),
+            };
+            verifier
+                .verify_bytes(&content, &signature)
+                .map_err(untrusted)?;
+            return Ok(CheckedCopy {
+                hash,
+                size,
+                signature: Some(signature),
+            });
+        }
+
+        Ok(CheckedCopy {
+            hash,
+            size,
+            #[cfg(feature = "signed-artifacts")]
+            signature: None,
+        })
+    }
+}
+
+/// Loads the library and, if it was built with
+/// `hot_ice::use_host_allocator!()`, hands it the host's allocator before
+/// anything else calls into it.
 fn load_library(lib_file: impl AsRef<Path>) -> Result<Library, HotReloaderError> {
-    Ok(unsafe { Library::new(lib_file.as_ref())? })
+    let library = unsafe { Library::new(lib_file.as_ref())? };
+    if let Ok(set_host_allocator) = unsafe {
+        library.get::<fn(&'static AllocatorVTable)>(
+            hot_ice_common::SET_HOST_ALLOCATOR_FUNCTION_NAME.as_bytes(),
+        )
+    } {
+        set_host_allocator(allocator::host_vtable());
+        log::debug!(target: RELOAD, "Routed library allocations to the host allocator");
+    }
+    Ok(library)
 }
 
-fn hash_file(f: impl AsRef<Path>) -> u32 {
-    fs::read(f.as_ref())
-        .map(|content| crc32fast::hash(&content))
+pub(crate) fn hash_file(f: impl AsRef<Path>) -> u32 {
+    digest_file(f.as_ref(), false)
+        .map(|digest| digest.hash)
         .unwrap_or_default()
 }
+
+/// A file read once by [`digest_file`].
+struct Digest {
+    hash: u32,
+    size: u64,
+    /// The first [`HEADER_LEN`] bytes, or the whole content if kept.
+    content: Vec<u8>,
+}
+
+/// Reads the file at `path` once, in chunks, hashing it on the way. Keeps
+/// the whole content with `keep_content`, only its header otherwise.
+fn digest_file(path: &Path, keep_content: bool) -> std::io::Result<Digest> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut content = Vec::new();
+    let mut size = 0;
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        let read = match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let chunk = &chunk[..read];
+        hasher.update(chunk);
+        let kept = match keep_content {
+            true => read,
+            false => HEADER_LEN.saturating_sub(content.len()).min(read),
+        };
+        content.extend_from_slice(&chunk[..kept]);
+        size += read as u64;
+    }
+    Ok(Digest {
+        hash: hasher.finalize(),
+        size,
+        content,
+    })
+}
+
+/// The header of a dynamic library of this platform followed by `content`,
+/// which [`verify_artifact`] accepts.
+#[cfg(test)]
+pub(crate) fn fake_artifact(content: &[u8]) -> Vec<u8> {
+    #[cfg(target_os = "windows")]
+    let mut artifact = b"MZ".to_vec();
+    #[cfg(target_os = "macos")]
+    let mut artifact = 0xfeed_facf_u32.to_be_bytes().to_vec();
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut artifact = {
+        // A 64 bit little endian ELF header without section headers.
+        let mut header = vec![0; 64];
+        header[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        header
+    };
+    artifact.extend_from_slice(content);
+    artifact
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artifacts_of_other_running_instances_are_recognized() {
+        let (prefix, ext) = lib_prefix_and_ext();
+        let own = PathBuf::from(format!("{prefix}ui-hot-{}-3.{ext}", std::process::id()));
+        assert_eq!(artifact_pid(&own), Some(std::process::id()));
+        assert!(process_is_running(std::process::id()));
+
+        let watched = PathBuf::from(format!("{prefix}ui.{ext}"));
+        assert_eq!(artifact_pid(&watched), None);
+        let templated = PathBuf::from(format!("{prefix}my-hot-lib-hot-12-x.{ext}"));
+        assert_eq!(artifact_pid(&templated), Some(12));
+    }
+
+    #[test]
+    fn copies_are_never_seen_truncated() {
+        let dir = std::env::temp_dir().join(format!("hot_ice_copy_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (prefix, ext) = lib_prefix_and_ext();
+        let watched = dir.join(format!("{prefix}ui.{ext}"));
+        let loaded = dir.join(format!("{prefix}ui-hot-{}-1.{ext}", std::process::id()));
+        fs::write(&watched, vec![7; 4096]).unwrap();
+
+        copy_artifact(&dir, &watched, &loaded).unwrap();
+        assert_eq!(fs::read(&loaded).unwrap(), vec![7; 4096]);
+        assert!(!loaded.with_extension(format!("{ext}.partial")).exists());
+        assert!(dir.join(ARTIFACT_LOCK_FILE).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn incomplete_artifacts_are_not_loaded() {
+        let dir = std::env::temp_dir().join(format!("hot_ice_verify_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (prefix, ext) = lib_prefix_and_ext();
+        let library = dir.join(format!("{prefix}ui.{ext}"));
+
+        fs::write(&library, b"").unwrap();
+        assert!(verify_artifact(&library).is_err());
+        fs::write(&library, b"garbage").unwrap();
+        assert!(verify_artifact(&library).is_err());
+
+        fs::write(&library, fake_artifact(b"build 1")).unwrap();
+        assert!(check_artifact(&library).is_ok());
+        assert_eq!(verify_artifact(&library), Ok(hash_file(&library)));
+        assert_eq!(
+            wait_for_artifact(&library, Duration::from_secs(1)),
+            Ok(hash_file(&library))
+        );
+
+        // The sidecar describes another build.
+        write_artifact_hash(&library);
+        fs::write(&library, fake_artifact(b"build 2")).unwrap();
+        assert!(verify_artifact(&library).is_err());
+        write_artifact_hash(&library);
+        assert!(verify_artifact(&library).is_ok());
+        remove_artifact_hash(&library);
+        assert!(!artifact_hash_path(&library).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn copies_are_checked_before_loading() {
+        let dir = std::env::temp_dir().join(format!("hot_ice_checks_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (prefix, ext) = lib_prefix_and_ext();
+        let watched = dir.join(format!("{prefix}ui.{ext}"));
+        let copy = dir.join(format!("{prefix}ui-hot-{}-1.{ext}", std::process::id()));
+        fs::write(&watched, fake_artifact(b"build 1")).unwrap();
+        copy_artifact(&dir, &watched, &copy).unwrap();
+
+        let checks = LoadChecks::default();
+        let checked = checks.check(&copy, Expected::Build(&watched)).unwrap();
+        assert_eq!(checked.hash, hash_file(&copy));
+        let other_generation = Expected::Generation {
+            hash: checked.hash ^ 1,
+            #[cfg(feature = "signed-artifacts")]
+            signature: None,
+        };
+        assert!(checks.check(&copy, other_generation).is_err());
+
+        #[cfg(feature = "signed-artifacts")]
+        {
+            use crate::artifact_signature::ArtifactSigner;
+
+            let signer = ArtifactSigner::from_bytes(&[7; 32]);
+            let checks = LoadChecks {
+                verifier: Some(Arc::new(
+                    ArtifactVerifier::new([signer.public_key()]).unwrap(),
+                )),
+            };
+            assert!(matches!(
+                checks.check(&copy, Expected::Build(&watched)),
+                Err(HotReloaderError::UntrustedArtifact(..))
+            ));
+
+            signer.sign(&watched).unwrap();
+            let checked = checks.check(&copy, Expected::Build(&watched)).unwrap();
+            assert!(checked.signature.is_some());
+
+            // The copy is replaced after the transport wrote the library.
+            fs::write(&copy, fake_artifact(b"build 2")).unwrap();
+            assert!(matches!(
+                checks.check(&copy, Expected::Build(&watched)),
+                Err(HotReloaderError::UntrustedArtifact(..))
+            ));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    #[test]
+    fn truncated_elf_files_are_recognized() {
+        let mut elf = fake_artifact(&[0; 64]);
+        // Two section headers of 64 bytes at offset 64.
+        elf[0x28] = 64;
+        elf[0x3a] = 64;
+        elf[0x3c] = 2;
+        assert!(check_header(&elf, elf.len() as u64).is_err());
+        elf.extend_from_slice(&[0; 64]);
+        assert!(check_header(&elf, elf.len() as u64).is_ok());
+    }
+}
@@ -0,0 +1,270 @@
+//! Lock-free access to the loaded library from hot functions.
+//!
+//! Every call of a hot function looks up its symbol in the current library.
+//! Taking the [`LibReloader`](crate::lib_reloader::LibReloader)'s mutex for
+//! that made `view` fail with a lock error whenever the reload thread held
+//! it. The reloader publishes each library it loads to a [`LibraryHandle`]
+//! instead, which readers load from without taking a lock.
+//!
+//! The symbols of a library are resolved once when it is loaded, see
+//! [`ResolvedGeneration`], so a call of a hot function is a table lookup
+//! instead of a `dlsym` of its name.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwapOption;
+
+use hot_ice_common::{
+    ADOPT_STATE_FUNCTION_NAME, DESERIALIZE_STATE_FUNCTION_NAME, FINGERPRINT_FUNCTION_NAME,
+    FREE_SERIALIZED_DATA_FUNCTION_NAME, LOAD_FONT_FUNCTION_NAME, NOT_HOT_SYMBOL_PREFIX,
+    SERIALIZE_STATE_FUNCTION_NAME, SET_HOST_ALLOCATOR_FUNCTION_NAME,
+    SET_HOST_CONTEXT_FUNCTION_NAME, SET_HOST_RESOURCES_FUNCTION_NAME, SET_INTERNER_FUNCTION_NAME,
+    START_WORKER_FUNCTION_NAME, STATE_TYPE_HASH_FUNCTION_NAME, STOP_WORKER_FUNCTION_NAME,
+};
+use libloading::Library;
+use rustc_hash::FxHashMap;
+
+use crate::error::HotReloaderError;
+use crate::log_target::RELOAD;
+use crate::registry;
+
+/// Symbols of the FFI contract between host and library, resolved with the
+/// registered hot functions whenever a library is loaded.
+const FRAMEWORK_SYMBOLS: &[&str] = &[
+    SERIALIZE_STATE_FUNCTION_NAME,
+    DESERIALIZE_STATE_FUNCTION_NAME,
+    FREE_SERIALIZED_DATA_FUNCTION_NAME,
+    STATE_TYPE_HASH_FUNCTION_NAME,
+    ADOPT_STATE_FUNCTION_NAME,
+    LOAD_FONT_FUNCTION_NAME,
+    START_WORKER_FUNCTION_NAME,
+    STOP_WORKER_FUNCTION_NAME,
+    SET_INTERNER_FUNCTION_NAME,
+    SET_HOST_RESOURCES_FUNCTION_NAME,
+    SET_HOST_CONTEXT_FUNCTION_NAME,
+    SET_HOST_ALLOCATOR_FUNCTION_NAME,
+    FINGERPRINT_FUNCTION_NAME,
+];
+
+/// A loaded library with the addresses of every symbol the host knows of.
+///
+/// Names outside the table, like the companion symbols of
+/// [`registry`], are looked up in the library on each call.
+pub(crate) struct ResolvedGeneration {
+    library: Arc<Library>,
+    /// Address of each known symbol, `None` if the library does not export it.
+    symbols: FxHashMap<&'static str, Option<usize>>,
+    /// Registered hot functions marked `#[hot_fn(not_hot)]` in this library.
+    not_hot: HashSet<&'static str>,
+    /// Addresses of the `#[hot_data]` items read so far, by name and type
+    /// hash, see [`hot_data`](crate::hot_data).
+    data: Mutex<HashMap<(&'static str, u64), Option<usize>>>,
+}
+
+impl ResolvedGeneration {
+    /// Resolves the framework symbols and the hot functions registered so
+    /// far. Missing ones are logged here instead of on every call.
+    pub(crate) fn resolve(library: Arc<Library>) -> Self {
+        let hot_functions: Vec<_> = registry::expected_exports()
+            .into_iter()
+            .map(|export| export.name)
+            .collect();
+        let not_hot = hot_functions
+            .iter()
+            .copied()
+            .filter(|name| {
+                let marker = format!("{NOT_HOT_SYMBOL_PREFIX}{name}");
+                unsafe { library.get::<*const ()>(marker.as_bytes()) }.is_ok()
+            })
+            .collect();
+        let names = FRAMEWORK_SYMBOLS.iter().copied().chain(hot_functions);
+
+        let symbols: FxHashMap<_, _> = names
+            .map(|name| {
+                let address = unsafe { library.get::<*const ()>(name.as_bytes()) }
+                    .ok()
+                    .map(|symbol| *symbol as usize);
+                (name, address)
+            })
+            .collect();
+
+        let mut missing: Vec<_> = symbols
+            .iter()
+            .filter(|(_, address)| address.is_none())
+            .map(|(name, _)| *name)
+            .collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            log::debug!(target: RELOAD, "Library does not export: {}", missing.join(", "));
+        }
+
+        Self {
+            library,
+            symbols,
+            not_hot,
+            data: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `name` was marked `#[hot_fn(not_hot)]` in this library.
+    pub(crate) fn is_not_hot(&self, name: &str) -> bool {
+        self.not_hot.contains(name)
+    }
+
+    pub(crate) fn library(&self) -> &Arc<Library> {
+        &self.library
+    }
+
+    /// The address of the item `name` of the type with `type_hash`, found
+    /// with `lookup` the first time it is asked for.
+    pub(crate) fn data(
+        &self,
+        name: &'static str,
+        type_hash: u64,
+        lookup: impl FnOnce() -> Option<usize>,
+    ) -> Option<usize> {
+        let mut data = self
+            .data
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *data.entry((name, type_hash)).or_insert_with(lookup)
+    }
+
+    /// The function or static variable `name` refers to.
+    ///
+    /// # Safety
+    ///
+    /// `T` must be the type of the function pointer, or a pointer to the
+    /// variable, the library exports as `name`. The value is only valid
+    /// while the library stays loaded.
+    pub(crate) unsafe fn get_symbol<T: Copy>(&self, name: &str) -> Result<T, HotReloaderError> {
+        const {
+            assert!(
+                mem::size_of::<T>() == mem::size_of::<usize>(),
+                "symbols are read as pointers"
+            )
+        };
+
+        match self.symbols.get(name) {
+            Some(Some(address)) => Ok(unsafe { mem::transmute_copy::<usize, T>(address) }),
+            Some(None) => Err(HotReloaderError::SymbolNotFound(name.to_string())),
+            None => Ok(unsafe { *self.library.get::<T>(name.as_bytes())? }),
+        }
+    }
+}
+
+/// The library last published by a [`LibReloader`](crate::lib_reloader::LibReloader).
+///
+/// Clones share the published library.
+#[derive(Clone)]
+pub struct LibraryHandle {
+    current: Arc<ArcSwapOption<ResolvedGeneration>>,
+}
+
+impl LibraryHandle {
+    pub(crate) fn new() -> Self {
+        Self {
+            current: Arc::new(ArcSwapOption::empty()),
+        }
+    }
+
+    /// Replaces the library, `None` while no library is loaded.
+    pub(crate) fn publish(&self, library: Option<Arc<ResolvedGeneration>>) {
+        self.current.store(library);
+    }
+
+    /// The current library, kept loaded while the returned guard lives.
+    /// Never blocks, a reload swaps the library in place.
+    pub fn load(&self) -> LoadedLibrary {
+        LoadedLibrary {
+            library: self.current.load_full(),
+        }
+    }
+
+    /// Whether the hot function `name` is called from the library. Functions
+    /// marked `#[hot_fn(not_hot)]` in the loaded library are called
+    /// statically.
+    pub fn is_hot(&self, name: &str) -> bool {
+        self.load()
+            .library
+            .is_none_or(|library| !library.is_not_hot(name))
+    }
+}
+
+/// A library loaded from a [`LibraryHandle`], see [`LibraryHandle::load`].
+pub struct LoadedLibrary {
+    library: Option<Arc<ResolvedGeneration>>,
+}
+
+impl LoadedLibrary {
+    pub(crate) fn resolved(&self) -> Option<&ResolvedGeneration> {
+        self.library.as_deref()
+    }
+
+    /// The function or static variable `name` refers to, resolved when the
+    /// library was loaded.
+    ///
+    /// # Safety
+    ///
+    /// Users of this API must specify the correct type of the function or
+    /// variable loaded, and must not use it after dropping `self`.
+    pub unsafe fn get_symbol<T: Copy>(&self, name: &str) -> Result<T, HotReloaderError> {
+        match &self.library {
+            None => Err(HotReloaderError::LibraryNotLoaded),
+            Some(library) => unsafe { library.get_symbol(name) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn this() -> Arc<ResolvedGeneration> {
+        let library = libloading::os::unix::Library::this().into();
+        Arc::new(ResolvedGeneration::resolve(Arc::new(library)))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn loads_the_published_library() {
+        let handle = LibraryHandle::new();
+        assert!(handle.load().library.is_none());
+
+        let first = this();
+        handle.publish(Some(first.clone()));
+        let loaded = handle.load().library.unwrap();
+        assert!(Arc::ptr_eq(&loaded, &first));
+        assert!(Arc::ptr_eq(&handle.load().library.unwrap(), &first));
+
+        let second = this();
+        handle.clone().publish(Some(second.clone()));
+        assert!(Arc::ptr_eq(&handle.load().library.unwrap(), &second));
+
+        // The handle does not keep a replaced library alive.
+        drop((loaded, first));
+        handle.publish(None);
+        assert!(handle.load().library.is_none());
+        assert_eq!(Arc::strong_count(&second), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolves_known_symbols_once() {
+        let generation = this();
+        let missing = unsafe { generation.get_symbol::<fn()>(SET_HOST_CONTEXT_FUNCTION_NAME) };
+        assert!(matches!(missing, Err(HotReloaderError::SymbolNotFound(_))));
+
+        // Unknown names are looked up in the library.
+        let malloc = unsafe { generation.get_symbol::<*const ()>("malloc") };
+        assert!(malloc.is_ok_and(|address| !address.is_null()));
+
+        // Data is looked up once per name and type.
+        assert_eq!(generation.data("LEVELS", 1, || Some(7)), Some(7));
+        assert_eq!(generation.data("LEVELS", 1, || unreachable!()), Some(7));
+        assert_eq!(generation.data("LEVELS", 2, || None), None);
+    }
+}
@@ -0,0 +1,11 @@
+//! Targets of the log records of `hot_ice`.
+//!
+//! Each target is below `hot_ice`, so `RUST_LOG=hot_ice=warn` quiets all of
+//! them and e.g. `RUST_LOG=hot_ice::reload=debug` surfaces one part.
+
+/// Building, loading and swapping libraries.
+pub(crate) const RELOAD: &str = "hot_ice::reload";
+/// The worker thread running the library's tasks and subscriptions.
+pub(crate) const WORKER: &str = "hot_ice::worker";
+/// Calls of the hot functions and their state changes.
+pub(crate) const DISPATCH: &str = "hot_ice::dispatch";
@@ -1,4 +1,9 @@
 use std::any::{Any, TypeId};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+
+use crate::transfer::{Transfer, TransferMessage};
 
 #[derive(Debug, Clone)]
 pub enum MessageSource<M> {
@@ -39,11 +44,119 @@ where
     }
 }
 
-#[derive(Debug)]
-pub struct HotMessage(pub Box<dyn DynMessage>);
+/// A message of the program, type-erased so it can cross the hot boundary.
+///
+/// Messages of at most [`INLINE_WORDS`] words are moved by value into the
+/// message itself, next to a table of functions monomorphized for their type,
+/// so the common small message costs no allocation. Larger ones are boxed.
+/// Either way the message is taken out by comparing its `TypeId`, which is
+/// only sound because the host refuses libraries built by another compiler,
+/// see `src/fingerprint.rs`, and for messages whose type may have been edited
+/// since because of the table of `src/transfer.rs` they carry.
+///
+/// The inline storage is the only fast path. There is no FFI shim generated
+/// per message type that would pass every message by value: the host only
+/// handles `HotMessage`s, it has no call site typed with the program's message
+/// to call such a shim from. Messages over [`INLINE_WORDS`] words are boxed
+/// whether their type changed or not.
+pub struct HotMessage(Repr, Option<&'static Transfer>);
+
+/// Messages up to this many words are stored without a box.
+const INLINE_WORDS: usize = 4;
+
+enum Repr {
+    Inline(Inline),
+    Boxed(Box<dyn DynMessage>),
+}
+
+struct Inline {
+    data: MaybeUninit<[usize; INLINE_WORDS]>,
+    vtable: &'static InlineVTable,
+    /// Keeps the message `!Sync` like a boxed one, the stored type may be.
+    _not_sync: PhantomData<Box<dyn DynMessage>>,
+}
+
+struct InlineVTable {
+    type_id: fn() -> TypeId,
+    clone: unsafe fn(*const (), *mut ()),
+    drop: unsafe fn(*mut ()),
+    debug: unsafe fn(*const (), &mut fmt::Formatter<'_>) -> fmt::Result,
+}
+
+struct Shims<M>(PhantomData<M>);
+
+impl<M: DynMessage + Clone> Shims<M> {
+    const VTABLE: InlineVTable = InlineVTable {
+        type_id: TypeId::of::<M>,
+        clone: Self::clone,
+        drop: Self::drop,
+        debug: Self::debug,
+    };
+
+    const FITS: bool = size_of::<M>() <= size_of::<[usize; INLINE_WORDS]>()
+        && align_of::<M>() <= align_of::<usize>();
+
+    unsafe fn clone(src: *const (), dst: *mut ()) {
+        unsafe { dst.cast::<M>().write((*src.cast::<M>()).clone()) }
+    }
+
+    unsafe fn drop(data: *mut ()) {
+        unsafe { std::ptr::drop_in_place(data.cast::<M>()) }
+    }
+
+    unsafe fn debug(data: *const (), f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        unsafe { (*data.cast::<M>()).fmt(f) }
+    }
+}
+
+impl Inline {
+    fn new<M: DynMessage + Clone>(message: M) -> Self {
+        let mut data = MaybeUninit::<[usize; INLINE_WORDS]>::uninit();
+        // SAFETY: `Shims::<M>::FITS` was checked by the caller.
+        unsafe { data.as_mut_ptr().cast::<M>().write(message) };
+        Self {
+            data,
+            vtable: &Shims::<M>::VTABLE,
+            _not_sync: PhantomData,
+        }
+    }
+
+    fn take<M: 'static>(self) -> Result<M, Self> {
+        if (self.vtable.type_id)() != TypeId::of::<M>() {
+            return Err(self);
+        }
+        let this = ManuallyDrop::new(self);
+        // SAFETY: the data holds an `M`, it is not dropped again.
+        Ok(unsafe { this.data.as_ptr().cast::<M>().read() })
+    }
+}
+
+impl Clone for Inline {
+    fn clone(&self) -> Self {
+        let mut data = MaybeUninit::<[usize; INLINE_WORDS]>::uninit();
+        unsafe { (self.vtable.clone)(self.data.as_ptr().cast(), data.as_mut_ptr().cast()) };
+        Self {
+            data,
+            vtable: self.vtable,
+            _not_sync: PhantomData,
+        }
+    }
+}
+
+impl Drop for Inline {
+    fn drop(&mut self) {
+        unsafe { (self.vtable.drop)(self.data.as_mut_ptr().cast()) }
+    }
+}
+
+impl fmt::Debug for Inline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        unsafe { (self.vtable.debug)(self.data.as_ptr().cast(), f) }
+    }
+}
 
 impl HotMessage {
-    pub fn from_message<M: DynMessage>(message: M) -> Self {
+    pub fn from_message<M: DynMessage + Clone>(message: M) -> Self {
         if TypeId::of::<M>() == TypeId::of::<Self>() {
             unsafe {
                 let result = std::ptr::read(&message as *const M as *const Self);
@@ -51,24 +164,116 @@ impl HotMessage {
                 return result;
             }
         }
-        Self(Box::new(message) as Box<dyn DynMessage>)
+        if Shims::<M>::FITS {
+            Self(Repr::Inline(Inline::new(message)), None)
+        } else {
+            Self(Repr::Boxed(Box::new(message)), None)
+        }
+    }
+
+    /// Like [`from_message`](Self::from_message), the message can also be
+    /// taken out as an edited version of its type.
+    pub fn from_transferable<M: TransferMessage>(message: M) -> Self {
+        let mut hot = Self::from_message(message);
+        hot.1 = Some(Transfer::of::<M>());
+        hot
+    }
+
+    pub(crate) fn transfer(&self) -> Option<&'static Transfer> {
+        self.1
+    }
+
+    /// The message, of the type its `type_id` names.
+    pub(crate) fn data(&self) -> *const () {
+        match &self.0 {
+            Repr::Inline(inline) => inline.data.as_ptr().cast(),
+            Repr::Boxed(message) => (&**message as *const dyn DynMessage).cast(),
+        }
     }
 
     pub fn into_message<M: DynMessage>(self) -> Result<M, Self> {
-        if let Some(_) = self.0.as_any().downcast_ref::<M>() {
-            Ok(*self.0.into_any().downcast::<M>().unwrap())
-        } else {
-            Err(self)
+        let transfer = self.1;
+        match self.0 {
+            Repr::Inline(inline) => inline
+                .take()
+                .map_err(|inline| Self(Repr::Inline(inline), transfer)),
+            Repr::Boxed(message) => {
+                if message.as_any().is::<M>() {
+                    Ok(*message.into_any().downcast::<M>().unwrap())
+                } else {
+                    Err(Self(Repr::Boxed(message), transfer))
+                }
+            }
         }
     }
 
     pub fn type_id(&self) -> TypeId {
-        self.0.type_id()
+        match &self.0 {
+            Repr::Inline(inline) => (inline.vtable.type_id)(),
+            Repr::Boxed(message) => DynMessage::type_id(&**message),
+        }
     }
 }
 
 impl Clone for HotMessage {
     fn clone(&self) -> Self {
-        Self(self.0.clone_boxed())
+        let repr = match &self.0 {
+            Repr::Inline(inline) => Repr::Inline(inline.clone()),
+            Repr::Boxed(message) => Repr::Boxed(message.clone_boxed()),
+        };
+        Self(repr, self.1)
+    }
+}
+
+impl fmt::Debug for HotMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple("HotMessage");
+        match &self.0 {
+            Repr::Inline(inline) => tuple.field(inline),
+            Repr::Boxed(message) => tuple.field(message),
+        };
+        tuple.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Small {
+        Pressed(u32),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Large([u64; 8]);
+
+    #[test]
+    fn small_messages_are_stored_inline() {
+        let message = Small::Pressed(7).into_hot_message();
+        assert!(matches!(message.0, Repr::Inline(_)));
+        assert_eq!(format!("{message:?}"), "HotMessage(Pressed(7))");
+
+        let message = message.into_message::<Large>().unwrap_err();
+        assert_eq!(message.type_id(), TypeId::of::<Small>());
+        assert_eq!(
+            message.clone().into_message::<Small>().unwrap(),
+            Small::Pressed(7)
+        );
+
+        let large = Large([1; 8]).into_hot_message();
+        assert!(matches!(large.0, Repr::Boxed(_)));
+        assert_eq!(large.type_id(), TypeId::of::<Large>());
+        assert_eq!(large.into_message::<Large>().unwrap(), Large([1; 8]));
+
+        // Clones and drops run the message's own code.
+        let shared = Arc::new(());
+        let message = Arc::clone(&shared).into_hot_message();
+        let copy = message.clone();
+        assert_eq!(Arc::strong_count(&shared), 3);
+        drop((message, copy));
+        assert_eq!(Arc::strong_count(&shared), 1);
     }
 }
@@ -0,0 +1,371 @@
+//! Per hot function call profiling.
+//!
+//! The reloader times every call into the program's hot functions and records
+//! it here. Counters are reset every time a new library is loaded, so the
+//! numbers always describe the code that is currently running.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use iced_core::time::Instant;
+use serde_derive::Serialize;
+
+use crate::context::Context;
+use crate::error::HotIceError;
+use crate::reloader::{FunctionState, HotFunction};
+use crate::source_revision::SourceRevision;
+
+/// Call statistics of a single hot function since the last reload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FunctionMetrics {
+    /// Number of calls, including the ones that panicked or fell back.
+    pub calls: u64,
+    /// Mean duration of a call.
+    pub mean: Duration,
+    /// Longest duration of a single call.
+    pub max: Duration,
+    /// Calls that panicked inside the library.
+    pub panics: u64,
+    /// Calls that ran the static function because the library did not
+    /// export the hot one.
+    pub fallbacks: u64,
+}
+
+/// Snapshot of the collected metrics, returned by [`metrics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Metrics {
+    /// Number of libraries loaded since the application started.
+    pub reloads: u64,
+    /// Number of retired library generations that are still loaded.
+    pub retained_generations: usize,
+    /// Combined file size of the retained library generations in bytes.
+    pub retained_bytes: u64,
+    /// How the state reached the loaded library, `None` until the first
+    /// `#[hot_state]` reload.
+    pub state_transfer: Option<StateTransfer>,
+    /// Time it took to transfer the state.
+    pub state_transfer_time: Duration,
+    /// Part of `state_transfer_time` the previous library spent serializing
+    /// the state, on a background thread while the reloading screen was
+    /// drawn.
+    pub state_serialize_time: Duration,
+    /// Git revision the loaded library was built from, `None` outside a git
+    /// repository and for a library that was not built while the
+    /// application ran.
+    pub source: Option<SourceRevision>,
+    /// Backpressure of the worker's actions since the last reload.
+    pub worker_queue: QueueMetrics,
+    /// Statistics of every hot function called since the last reload.
+    pub functions: BTreeMap<HotFunction, FunctionMetrics>,
+    /// Latency of the program's messages by where they came from, recorded
+    /// while [`ReloaderSettings::trace_message_latency`](crate::ReloaderSettings)
+    /// is set.
+    pub message_latency: BTreeMap<MessageOrigin, MessageLatency>,
+}
+
+/// How far the event loop fell behind the actions of the worker's streams,
+/// see [`WorkerQueue`](crate::WorkerQueue).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueueMetrics {
+    /// Actions the worker holds back right now.
+    pub backlog: usize,
+    /// Most actions held back at once.
+    pub peak_backlog: usize,
+    /// Actions dropped by [`Backpressure::DropOldest`](crate::Backpressure).
+    pub dropped: u64,
+    /// Time the worker was blocked by [`Backpressure::Block`](crate::Backpressure).
+    pub blocked: Duration,
+}
+
+/// Where a message of the program came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum MessageOrigin {
+    /// Published by a widget of the view.
+    View,
+    /// Produced by a subscription.
+    Subscription,
+    /// Produced by a task returned from `update`.
+    Task,
+}
+
+/// Where the time between producing a message and `update` returning it
+/// went, for the messages of one [`MessageOrigin`] since the last reload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MessageLatency {
+    /// Number of messages handled.
+    pub messages: u64,
+    /// Mean time from producing a message to calling `update` with it.
+    pub mean_queued: Duration,
+    pub max_queued: Duration,
+    /// Mean time spent in the body of `update`, or in the whole call of a
+    /// static `update`.
+    pub mean_update: Duration,
+    pub max_update: Duration,
+    /// Mean time of the rest of the call: boxing and downcasting the
+    /// message, looking up the symbol, calling through it and mapping the
+    /// returned task. Zero for a static `update`.
+    pub mean_boundary: Duration,
+    pub max_boundary: Duration,
+}
+
+#[derive(Default)]
+struct LatencyTotals {
+    queued: Duration,
+    update: Duration,
+    boundary: Duration,
+}
+
+/// How the `#[hot_state]` state was handed to a new library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StateTransfer {
+    /// Moved without serializing it, the state type did not change.
+    Handoff,
+    /// Serialized by the old library and deserialized by the new one.
+    Serialized {
+        /// Size of the serialized state.
+        bytes: usize,
+    },
+    /// Built field by field: the fields whose type did not change were
+    /// moved, the others serialized by the old library and deserialized by
+    /// the new one.
+    Fields {
+        moved: usize,
+        serialized: usize,
+        /// Size of the serialized fields.
+        bytes: usize,
+    },
+}
+
+#[derive(Default)]
+struct Collector {
+    reloads: u64,
+    retained_generations: usize,
+    retained_bytes: u64,
+    state_transfer: Option<StateTransfer>,
+    state_transfer_time: Duration,
+    state_serialize_time: Duration,
+    source: Option<SourceRevision>,
+    worker_queue: QueueMetrics,
+    functions: BTreeMap<HotFunction, (FunctionMetrics, Duration)>,
+    message_latency: BTreeMap<MessageOrigin, (MessageLatency, LatencyTotals)>,
+}
+
+static METRICS: Mutex<Collector> = Mutex::new(Collector {
+    reloads: 0,
+    retained_generations: 0,
+    retained_bytes: 0,
+    state_transfer: None,
+    state_transfer_time: Duration::ZERO,
+    state_serialize_time: Duration::ZERO,
+    source: None,
+    worker_queue: QueueMetrics {
+        backlog: 0,
+        peak_backlog: 0,
+        dropped: 0,
+        blocked: Duration::ZERO,
+    },
+    functions: BTreeMap::new(),
+    message_latency: BTreeMap::new(),
+});
+
+/// Time the body of the last hot `update` took, reported by the library.
+static UPDATE_BODY: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// Returns the call statistics of every hot function since the last reload.
+///
+/// ```rust,ignore
+/// let json = serde_json::to_string_pretty(&hot_ice::metrics())?;
+/// ```
+pub fn metrics() -> Metrics {
+    let Ok(collector) = METRICS.lock() else {
+        return Metrics::default();
+    };
+
+    Metrics {
+        reloads: collector.reloads,
+        retained_generations: collector.retained_generations,
+        retained_bytes: collector.retained_bytes,
+        state_transfer: collector.state_transfer,
+        state_transfer_time: collector.state_transfer_time,
+        state_serialize_time: collector.state_serialize_time,
+        source: collector.source.clone(),
+        worker_queue: collector.worker_queue,
+        functions: collector
+            .functions
+            .iter()
+            .map(|(&func, (metrics, _))| (func, *metrics))
+            .collect(),
+        message_latency: collector
+            .message_latency
+            .iter()
+            .map(|(&origin, (latency, _))| (origin, *latency))
+            .collect(),
+    }
+}
+
+/// Records one call of `func` that started at `started`.
+pub(crate) fn record<T>(
+    func: HotFunction,
+    started: Instant,
+    result: &Result<(T, FunctionState), HotIceError>,
+) {
+    let elapsed = started.elapsed();
+    let Ok(mut collector) = METRICS.lock() else {
+        return;
+    };
+
+    let (metrics, total) = collector.functions.entry(func).or_default();
+    metrics.calls += 1;
+    *total += elapsed;
+    metrics.mean = Duration::from_nanos((total.as_nanos() / u128::from(metrics.calls)) as u64);
+    metrics.max = metrics.max.max(elapsed);
+
+    match result {
+        Err(HotIceError::FunctionPanicked(_)) => metrics.panics += 1,
+        Ok((_, FunctionState::FallBackStatic(_))) => metrics.fallbacks += 1,
+        _ => {}
+    }
+}
+
+/// Clears the per-function statistics after a new library was loaded.
+pub(crate) fn reset() {
+    if let Ok(mut collector) = METRICS.lock() {
+        collector.reloads += 1;
+        collector.functions.clear();
+        collector.message_latency.clear();
+        collector.worker_queue = QueueMetrics {
+            backlog: collector.worker_queue.backlog,
+            ..QueueMetrics::default()
+        };
+    }
+}
+
+/// Number of libraries loaded so far.
+pub(crate) fn reloads() -> u64 {
+    METRICS.lock().map_or(0, |collector| collector.reloads)
+}
+
+/// Records how the state reached the library that was just loaded and how
+/// long the previous library serialized it and the new one restored it.
+pub(crate) fn set_state_transfer(transfer: StateTransfer, serialize: Duration, restore: Duration) {
+    if let Ok(mut collector) = METRICS.lock() {
+        collector.state_transfer = Some(transfer);
+        collector.state_transfer_time = serialize + restore;
+        collector.state_serialize_time = serialize;
+    }
+}
+
+/// Updates the number and size of retired library generations still loaded.
+pub(crate) fn set_retained(generations: usize, bytes: u64) {
+    if let Ok(mut collector) = METRICS.lock() {
+        collector.retained_generations = generations;
+        collector.retained_bytes = bytes;
+    }
+}
+
+/// Updates the number of actions the worker holds back.
+pub(crate) fn record_backlog(backlog: usize) {
+    if let Ok(mut collector) = METRICS.lock() {
+        let queue = &mut collector.worker_queue;
+        queue.backlog = backlog;
+        queue.peak_backlog = queue.peak_backlog.max(backlog);
+    }
+}
+
+/// Counts an action dropped to make room in the worker's queue.
+pub(crate) fn record_dropped_action() {
+    if let Ok(mut collector) = METRICS.lock() {
+        collector.worker_queue.dropped += 1;
+    }
+}
+
+/// Adds time the worker waited for room in its queue.
+pub(crate) fn record_blocked(elapsed: Duration) {
+    if let Ok(mut collector) = METRICS.lock() {
+        collector.worker_queue.blocked += elapsed;
+    }
+}
+
+/// Records the git revision the loaded library was built from.
+pub(crate) fn set_source(source: Option<SourceRevision>) {
+    if let Ok(mut collector) = METRICS.lock() {
+        collector.source = source;
+    }
+}
+
+/// Records a message from `origin` that waited `queued` for `update`, which
+/// spent `update` in its body and `boundary` around it.
+pub(crate) fn record_latency(
+    origin: MessageOrigin,
+    queued: Duration,
+    update: Duration,
+    boundary: Duration,
+) {
+    let Ok(mut collector) = METRICS.lock() else {
+        return;
+    };
+
+    let (latency, totals) = collector.message_latency.entry(origin).or_default();
+    latency.messages += 1;
+    let mean = |total: Duration| {
+        Duration::from_nanos((total.as_nanos() / u128::from(latency.messages)) as u64)
+    };
+
+    totals.queued += queued;
+    totals.update += update;
+    totals.boundary += boundary;
+    latency.mean_queued = mean(totals.queued);
+    latency.mean_update = mean(totals.update);
+    latency.mean_boundary = mean(totals.boundary);
+    latency.max_queued = latency.max_queued.max(queued);
+    latency.max_update = latency.max_update.max(update);
+    latency.max_boundary = latency.max_boundary.max(boundary);
+}
+
+/// Called by the library through its context with the time the body of
+/// `update` took.
+pub(crate) fn record_update_body(elapsed: Duration) {
+    if let Ok(mut body) = UPDATE_BODY.lock() {
+        *body = Some(elapsed);
+    }
+}
+
+/// The time reported by the last hot `update` since this was last called.
+pub(crate) fn take_update_body() -> Option<Duration> {
+    UPDATE_BODY.lock().ok().and_then(|mut body| body.take())
+}
+
+/// Runs the body of a hot `update` and reports its duration to the host,
+/// called by the code `#[hot_fn]` generates.
+#[doc(hidden)]
+pub fn time_update<R>(body: impl FnOnce() -> R) -> R {
+    let started = Instant::now();
+    let result = body();
+    Context::current().record_update_body(started.elapsed());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_the_latency_of_messages() {
+        let millis = Duration::from_millis;
+        record_latency(MessageOrigin::Subscription, millis(4), millis(2), millis(1));
+        record_latency(MessageOrigin::Subscription, millis(2), millis(2), millis(3));
+
+        let latency = metrics().message_latency[&MessageOrigin::Subscription];
+        assert_eq!(latency.messages, 2);
+        assert_eq!(latency.mean_queued, millis(3));
+        assert_eq!(latency.max_queued, millis(4));
+        assert_eq!(latency.mean_update, millis(2));
+        assert_eq!(latency.mean_boundary, millis(2));
+        assert_eq!(latency.max_boundary, millis(3));
+
+        time_update(|| ());
+        assert!(take_update_body().is_some());
+        assert_eq!(take_update_body(), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Reuses the reloader's overlay between frames.
+//!
+//! The status bar and the status dot only depend on the function states,
+//! the active errors and a few flags of the reloader. [`OverlayCache`] keeps
+//! the element built for a revision of those inputs and hands out
+//! [`Shared`] widgets that draw it, so frames without changes build no
+//! widgets or strings for the overlay.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use iced_core::{
+    Clipboard, Element, Event, Layout, Length, Rectangle, Shell, Size, Vector, layout, mouse,
+    overlay, renderer,
+    widget::{Operation, Tree, Widget, tree},
+};
+
+type SharedElement<Message, Theme, Renderer> =
+    Rc<RefCell<Element<'static, Message, Theme, Renderer>>>;
+
+/// An element and the revision it was built for.
+type Built<Message, Theme, Renderer> = (u64, SharedElement<Message, Theme, Renderer>);
+
+/// The overlay element of the last revision it was built for.
+pub(crate) struct OverlayCache<Message, Theme, Renderer> {
+    cached: RefCell<Option<Built<Message, Theme, Renderer>>>,
+}
+
+impl<Message, Theme, Renderer> OverlayCache<Message, Theme, Renderer>
+where
+    Message: 'static,
+    Theme: 'static,
+    Renderer: renderer::Renderer + 'static,
+{
+    pub(crate) fn new() -> Self {
+        Self {
+            cached: RefCell::new(None),
+        }
+    }
+
+    /// The element of `revision`, built with `build` if the cached one is
+    /// of another revision.
+    pub(crate) fn get<'a>(
+        &self,
+        revision: u64,
+        build: impl FnOnce() -> Element<'static, Message, Theme, Renderer>,
+    ) -> Element<'a, Message, Theme, Renderer> {
+        let mut cached = self.cached.borrow_mut();
+        let element = match &*cached {
+            Some((built, element)) if *built == revision => Rc::clone(element),
+            _ => {
+                let element = Rc::new(RefCell::new(build()));
+                *cached = Some((revision, Rc::clone(&element)));
+                element
+            }
+        };
+
+        Element::new(Shared { element })
+    }
+
+    /// Drops the cached element, e.g. while it is animated.
+    pub(crate) fn clear(&self) {
+        self.cached.borrow_mut().take();
+    }
+}
+
+/// Draws an element owned by an [`OverlayCache`]. Overlays of the element
+/// are not shown, the reloader's overlay has none.
+struct Shared<Message, Theme, Renderer> {
+    element: SharedElement<Message, Theme, Renderer>,
+}
+
+impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer> for Shared<Message, Theme, Renderer>
+where
+    Renderer: renderer::Renderer,
+{
+    fn size(&self) -> Size<Length> {
+        self.element.borrow().as_widget().size()
+    }
+
+    fn size_hint(&self) -> Size<Length> {
+        self.element.borrow().as_widget().size_hint()
+    }
+
+    fn layout(
+        &mut self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.element
+            .borrow_mut()
+            .as_widget_mut()
+            .layout(tree, renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.element
+            .borrow()
+            .as_widget()
+            .draw(tree, renderer, theme, style, layout, cursor, viewport)
+    }
+
+    fn tag(&self) -> tree::Tag {
+        self.element.borrow().as_widget().tag()
+    }
+
+    fn state(&self) -> tree::State {
+        self.element.borrow().as_widget().state()
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        self.element.borrow().as_widget().children()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        self.element.borrow().as_widget().diff(tree)
+    }
+
+    fn operate(
+        &mut self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        self.element
+            .borrow_mut()
+            .as_widget_mut()
+            .operate(tree, layout, renderer, operation)
+    }
+
+    fn update(
+        &mut self,
+        tree: &mut Tree,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) {
+        self.element.borrow_mut().as_widget_mut().update(
+            tree, event, layout, cursor, renderer, clipboard, shell, viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.element
+            .borrow()
+            .as_widget()
+            .mouse_interaction(tree, layout, cursor, viewport, renderer)
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        _tree: &'b mut Tree,
+        _layout: Layout<'b>,
+        _renderer: &Renderer,
+        _viewport: &Rectangle,
+        _translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use iced_widget::{Renderer, Text};
+
+    #[test]
+    fn rebuilds_only_for_a_new_revision() {
+        let cache = OverlayCache::<(), iced_core::Theme, Renderer>::new();
+        let mut builds = 0;
+        let mut build = || {
+            builds += 1;
+            Text::new("bar").into()
+        };
+
+        let _ = cache.get(1, &mut build);
+        let _ = cache.get(1, &mut build);
+        let _ = cache.get(2, &mut build);
+        cache.clear();
+        let _ = cache.get(2, &mut build);
+        assert_eq!(builds, 3);
+    }
+}
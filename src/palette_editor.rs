@@ -0,0 +1,403 @@
+//! Live editing of the program's palette from the overlay.
+//!
+//! The editor starts from the palette of the theme the program returns.
+//! Every edit replaces the program's theme with a custom one of the edited
+//! palette until it is reset, which only works for programs using
+//! [`iced::Theme`](Theme). The result is copied as Rust code for
+//! `Theme::custom` or as a `[palette]` table with the hex colors of a
+//! [token file](crate::hot_tokens).
+
+use std::any::{Any, TypeId};
+use std::sync::Mutex;
+
+use iced_core::{
+    Alignment, Background, Border, Color, Element, Font, Length, Theme, text, theme::Palette,
+};
+use iced_widget::{
+    Text, button, column, container, container::Style as ContainerStyle, row, slider, space,
+    text::Style as TextStyle, text_input,
+};
+
+/// The colors of a [`Palette`] the editor changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PaletteColor {
+    Background,
+    Text,
+    Primary,
+    Success,
+    Danger,
+}
+
+impl PaletteColor {
+    const ALL: [PaletteColor; 5] = [
+        PaletteColor::Background,
+        PaletteColor::Text,
+        PaletteColor::Primary,
+        PaletteColor::Success,
+        PaletteColor::Danger,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PaletteColor::Background => "background",
+            PaletteColor::Text => "text",
+            PaletteColor::Primary => "primary",
+            PaletteColor::Success => "success",
+            PaletteColor::Danger => "danger",
+        }
+    }
+
+    fn get(self, palette: &Palette) -> Color {
+        match self {
+            PaletteColor::Background => palette.background,
+            PaletteColor::Text => palette.text,
+            PaletteColor::Primary => palette.primary,
+            PaletteColor::Success => palette.success,
+            PaletteColor::Danger => palette.danger,
+        }
+    }
+
+    fn set(self, palette: &mut Palette, color: Color) {
+        let slot = match self {
+            PaletteColor::Background => &mut palette.background,
+            PaletteColor::Text => &mut palette.text,
+            PaletteColor::Primary => &mut palette.primary,
+            PaletteColor::Success => &mut palette.success,
+            PaletteColor::Danger => &mut palette.danger,
+        };
+        *slot = color;
+    }
+}
+
+/// A channel of a color, edited with a slider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Channel {
+    Red,
+    Green,
+    Blue,
+}
+
+/// An input of the editor.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PaletteEdit {
+    Select(PaletteColor),
+    Hex(PaletteColor, String),
+    Channel(PaletteColor, Channel, u8),
+    Reset,
+    CopyRust,
+    CopyToml,
+}
+
+#[derive(Debug)]
+pub(crate) struct PaletteEditor {
+    pub(crate) open: bool,
+    /// The palette of the program's theme, as of its last `theme` call.
+    program: Mutex<Option<Palette>>,
+    /// Overrides the program's palette from the first edit on.
+    edited: Option<Palette>,
+    selected: PaletteColor,
+    /// A hex color being typed, until it parses.
+    typed: Option<(PaletteColor, String)>,
+}
+
+impl Default for PaletteEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            program: Mutex::new(None),
+            edited: None,
+            selected: PaletteColor::Primary,
+            typed: None,
+        }
+    }
+}
+
+impl PaletteEditor {
+    /// Records the palette of the theme the program returned.
+    pub(crate) fn observe(&self, palette: Option<Palette>) {
+        if let Ok(mut program) = self.program.lock() {
+            *program = palette;
+        }
+    }
+
+    /// The palette shown, the edited one or the program's.
+    fn palette(&self) -> Palette {
+        self.edited.unwrap_or_else(|| {
+            self.program
+                .lock()
+                .ok()
+                .and_then(|program| *program)
+                .unwrap_or(Palette::DARK)
+        })
+    }
+
+    /// Whether edits can replace a theme of type `T`.
+    pub(crate) fn applies_to<T: 'static>() -> bool {
+        TypeId::of::<T>() == TypeId::of::<Theme>()
+    }
+
+    /// `theme`, or a theme of the edited palette once there is one.
+    pub(crate) fn apply<T: 'static>(&self, theme: Option<T>) -> Option<T> {
+        let Some(palette) = self.edited else {
+            return theme;
+        };
+        let custom: Box<dyn Any> = Box::new(Theme::custom("Palette editor".to_string(), palette));
+        match custom.downcast::<T>() {
+            Ok(custom) => Some(*custom),
+            Err(_) => theme,
+        }
+    }
+
+    /// Applies `edit`, returns the text to copy for the copy buttons.
+    pub(crate) fn edit(&mut self, edit: PaletteEdit) -> Option<String> {
+        match edit {
+            PaletteEdit::Select(color) => self.selected = color,
+            PaletteEdit::Hex(color, hex) => match parse_hex(&hex) {
+                Some(parsed) => {
+                    self.set(color, parsed);
+                    self.typed = None;
+                }
+                None => self.typed = Some((color, hex)),
+            },
+            PaletteEdit::Channel(color, channel, value) => {
+                let [r, g, b, a] = color.get(&self.palette()).into_rgba8();
+                let [r, g, b] = match channel {
+                    Channel::Red => [value, g, b],
+                    Channel::Green => [r, value, b],
+                    Channel::Blue => [r, g, value],
+                };
+                self.set(color, Color::from_rgba8(r, g, b, f32::from(a) / 255.0));
+            }
+            PaletteEdit::Reset => {
+                self.edited = None;
+                self.typed = None;
+            }
+            PaletteEdit::CopyRust => return Some(to_rust(&self.palette())),
+            PaletteEdit::CopyToml => return Some(to_toml(&self.palette())),
+        }
+        None
+    }
+
+    fn set(&mut self, color: PaletteColor, value: Color) {
+        let mut palette = self.palette();
+        color.set(&mut palette, value);
+        self.edited = Some(palette);
+        self.selected = color;
+    }
+
+    /// The panel of the overlay. `applies` tells whether edits show up in
+    /// the program.
+    pub(crate) fn view<'a, Message, Renderer>(
+        &self,
+        applies: bool,
+        on_edit: fn(PaletteEdit) -> Message,
+    ) -> Element<'a, Message, Theme, Renderer>
+    where
+        Message: Clone + 'a,
+        Renderer: text::Renderer<Font = Font> + 'a,
+    {
+        let dim = |_: &Theme| TextStyle {
+            color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.7)),
+        };
+        let palette = self.palette();
+
+        let colors = column(PaletteColor::ALL.into_iter().map(|color| {
+            let value = color.get(&palette);
+            let hex = match &self.typed {
+                Some((typed, hex)) if *typed == color => hex.clone(),
+                _ => to_hex(value),
+            };
+            let selected = color == self.selected;
+            let swatch = container(space().width(14).height(14)).style(move |_| ContainerStyle {
+                background: Some(Background::Color(value)),
+                border: Border {
+                    color: if selected {
+                        Color::WHITE
+                    } else {
+                        Color::from_rgba(1.0, 1.0, 1.0, 0.3)
+                    },
+                    width: 1.0,
+                    radius: 3.0.into(),
+                },
+                ..Default::default()
+            });
+
+            row![
+                button(swatch)
+                    .on_press(on_edit(PaletteEdit::Select(color)))
+                    .padding(0)
+                    .style(button::text),
+                Text::new(color.name()).size(11).width(80).style(dim),
+                text_input("#rrggbb", &hex)
+                    .on_input(move |hex| on_edit(PaletteEdit::Hex(color, hex)))
+                    .font(Font::MONOSPACE)
+                    .size(11)
+                    .width(90),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .into()
+        }))
+        .spacing(4);
+
+        let selected = self.selected;
+        let [r, g, b, _] = selected.get(&palette).into_rgba8();
+        let channels = column(
+            [
+                (Channel::Red, "R", r),
+                (Channel::Green, "G", g),
+                (Channel::Blue, "B", b),
+            ]
+            .into_iter()
+            .map(|(channel, label, value)| {
+                row![
+                    Text::new(label).size(11).width(14).style(dim),
+                    slider(0..=u8::MAX, value, move |value| {
+                        on_edit(PaletteEdit::Channel(selected, channel, value))
+                    })
+                    .width(200),
+                    Text::new(value.to_string()).size(11).style(dim),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center)
+                .into()
+            }),
+        )
+        .spacing(4);
+
+        let action = |label: &'static str, edit: Option<PaletteEdit>| {
+            button(Text::new(label).size(12).style(dim))
+                .on_press_maybe(edit.map(on_edit))
+                .style(button::text)
+        };
+        let mut panel = column![
+            colors,
+            Text::new(format!("Editing {}", selected.name()))
+                .size(11)
+                .style(dim),
+            channels,
+            row![
+                space().width(Length::Fill),
+                action("Copy as Rust", Some(PaletteEdit::CopyRust)),
+                action("Copy as TOML", Some(PaletteEdit::CopyToml)),
+                action("Reset", self.edited.is_some().then_some(PaletteEdit::Reset)),
+            ]
+            .align_y(Alignment::Center),
+        ]
+        .spacing(6);
+
+        if !applies {
+            panel = panel.push(
+                Text::new("The program's theme is not an `iced::Theme`, edits are not applied")
+                    .size(11)
+                    .style(|_| TextStyle {
+                        color: Some(Color::from_rgb8(245, 158, 11)),
+                    }),
+            );
+        }
+        panel.into()
+    }
+}
+
+/// `#rrggbb`, with the alpha as `#rrggbbaa` if the color is translucent.
+fn to_hex(color: Color) -> String {
+    let [r, g, b, a] = color.into_rgba8();
+    if a == u8::MAX {
+        format!("#{r:02x}{g:02x}{b:02x}")
+    } else {
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.trim().strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = match hex.len() {
+        8 => channel(6)?,
+        _ => u8::MAX,
+    };
+    Some(Color::from_rgba8(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        f32::from(alpha) / 255.0,
+    ))
+}
+
+/// A `Palette` expression for `Theme::custom`, the colors not edited are
+/// taken from `Palette::DARK`.
+fn to_rust(palette: &Palette) -> String {
+    let mut code = String::from("Palette {\n");
+    for color in PaletteColor::ALL {
+        let [r, g, b, a] = color.get(palette).into_rgba8();
+        let alpha = match a {
+            u8::MAX => String::new(),
+            a => format!(", {:.2}", f32::from(a) / 255.0),
+        };
+        code.push_str(&format!(
+            "    {}: color!(0x{r:02x}{g:02x}{b:02x}{alpha}),\n",
+            color.name()
+        ));
+    }
+    code.push_str("    ..Palette::DARK\n}");
+    code
+}
+
+/// A `[palette]` table of a token file.
+fn to_toml(palette: &Palette) -> String {
+    let mut toml = String::from("[palette]\n");
+    for color in PaletteColor::ALL {
+        toml.push_str(&format!(
+            "{} = \"{}\"\n",
+            color.name(),
+            to_hex(color.get(palette))
+        ));
+    }
+    toml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_override_the_program_palette() {
+        let mut editor = PaletteEditor::default();
+        editor.observe(Some(Palette::LIGHT));
+        assert_eq!(editor.apply(Some(7_u32)), Some(7));
+        assert_eq!(editor.palette(), Palette::LIGHT);
+
+        // A hex color applies once it parses.
+        editor.edit(PaletteEdit::Hex(PaletteColor::Primary, "#12".to_string()));
+        assert!(editor.edited.is_none());
+        editor.edit(PaletteEdit::Hex(
+            PaletteColor::Primary,
+            "#123456".to_string(),
+        ));
+        editor.edit(PaletteEdit::Channel(
+            PaletteColor::Primary,
+            Channel::Blue,
+            0xff,
+        ));
+        assert_eq!(to_hex(editor.palette().primary), "#1234ff");
+        assert_eq!(editor.palette().text, Palette::LIGHT.text);
+
+        let theme = editor.apply(Some(Theme::Dark)).unwrap();
+        assert_eq!(theme.palette().primary, editor.palette().primary);
+
+        let toml = editor.edit(PaletteEdit::CopyToml).unwrap();
+        assert!(toml.contains("primary = \"#1234ff\""), "{toml}");
+        let rust = editor.edit(PaletteEdit::CopyRust).unwrap();
+        assert!(rust.contains("    primary: color!(0x1234ff),"), "{rust}");
+
+        editor.edit(PaletteEdit::Reset);
+        assert_eq!(editor.palette(), Palette::LIGHT);
+        assert_eq!(
+            parse_hex("#00000080").map(to_hex).as_deref(),
+            Some("#00000080")
+        );
+    }
+}
@@ -21,11 +21,7 @@ pub(crate) fn ensure_panic_hook_installed() {
             if let Some(loc) = info.location() {
                 // Store the file as an owned String instead of Box::leak to avoid
                 // memory leaks on every panic.
-                PANIC_LOCATION.set(Some((
-                    loc.file().to_string(),
-                    loc.line(),
-                    loc.column(),
-                )));
+                PANIC_LOCATION.set(Some((loc.file().to_string(), loc.line(), loc.column())));
             }
         }));
     });
@@ -36,7 +32,11 @@ pub(crate) fn ensure_panic_hook_installed() {
 /// and `&str` payloads.
 ///
 /// A panic hook is auto-installed on first call to capture location info
-/// (file:line:col) which is prepended to the message.
+/// (file:line:col) which is prepended to the message. It replaces the hook
+/// set before.
+///
+/// The message points into a buffer the next caught panic overwrites, copy
+/// it first, as [`ffi::guarded`](crate::ffi::guarded) does.
 pub fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, &'static str> {
     ensure_panic_hook_installed();
     PANIC_LOCATION.set(None);
@@ -87,6 +87,25 @@ fn extract_and_format_message(err: Box<dyn std::any::Any + Send>) -> &'static st
     }
 }
 
+/// Panic strategy of this build, `"unwind"` or `"abort"`.
+pub(crate) const PANIC_STRATEGY: &str = if cfg!(panic = "unwind") {
+    "unwind"
+} else {
+    "abort"
+};
+
+/// Explains that [`catch_panic`] can't protect the app if `side` (the host or
+/// the library) was built with `panic = "abort"`. `None` for `"unwind"`.
+pub(crate) fn abort_diagnostic(side: &str, strategy: &str) -> Option<String> {
+    (strategy == "abort").then(|| {
+        format!(
+            "The {side} is built with panic = \"abort\", a panic in a hot function \
+             ends the process instead of showing an error. Set panic = \"unwind\" \
+             in the profile the {side} is built with, e.g. [profile.dev] or [profile.hot]"
+        )
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,4 +149,12 @@ mod tests {
         assert!(err.contains("panicked at"), "got: {err}");
         assert!(err.contains("located panic"), "got: {err}");
     }
+
+    #[test]
+    fn only_abort_needs_a_diagnostic() {
+        assert_eq!(PANIC_STRATEGY, "unwind");
+        assert_eq!(abort_diagnostic("host", PANIC_STRATEGY), None);
+        let diagnostic = abort_diagnostic("library", "abort").unwrap();
+        assert!(diagnostic.contains("library is built with panic = \"abort\""));
+    }
 }
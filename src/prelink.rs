@@ -0,0 +1,237 @@
+//! Dynamic linking of iced and `hot_ice` into the hot library.
+//!
+//! Most of the time spent rebuilding the cdylib goes into linking iced, wgpu
+//! and the rest of the dependency tree into it, again on every edit. With
+//! `ReloaderSettings::prelink` the dependencies are compiled once into the
+//! Rust `dylib` of a generated `hot_ice_prelink` crate, and the ui crate is
+//! linked against it with `-C prefer-dynamic`, so a rebuild only links the
+//! ui crate's own code.
+//!
+//! The generated crate has to be part of the workspace and a dependency of
+//! the ui crate, so cargo resolves one shared copy of iced for both:
+//!
+//! ```toml
+//! # Cargo.toml
+//! [workspace]
+//! members = ["bin", "ui", "hot_ice_prelink"]
+//!
+//! # ui/Cargo.toml
+//! [dependencies]
+//! hot_ice_prelink = { path = "../hot_ice_prelink", optional = true }
+//!
+//! [features]
+//! reload = ["dep:hot_ice_prelink"]
+//! ```
+//!
+//! ```rust,ignore
+//! // ui/src/lib.rs
+//! #[cfg(feature = "reload")]
+//! use hot_ice_prelink as _;
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Once;
+
+use cargo_metadata::{Dependency, Metadata, Package};
+
+use crate::log_target::RELOAD;
+
+/// Name and directory (relative to the workspace root) of the generated crate.
+const CRATE_NAME: &str = "hot_ice_prelink";
+
+/// Dependencies of the ui crate that are moved into the prelinked dylib.
+const PRELINKED: &[&str] = &["iced", "hot_ice"];
+
+/// Extra `rustc` arguments for building the ui crate against the prelinked
+/// dylib. Empty if the ui crate does not depend on it yet, in which case the
+/// crate is generated and the remaining setup is logged.
+///
+/// `profile_dir` is the directory cargo places the build artifacts in.
+pub(crate) fn rustc_args(metadata: &Metadata, lib_name: &str, profile_dir: &Path) -> Vec<String> {
+    let Some(package) = metadata.packages.iter().find(|p| p.name == lib_name) else {
+        log::error!(target: RELOAD, "Found no crate named {lib_name}, not prelinking dependencies");
+        return Vec::new();
+    };
+
+    if !package.dependencies.iter().any(|d| d.name == CRATE_NAME) {
+        static SETUP: Once = Once::new();
+        SETUP.call_once(|| setup(metadata, package));
+        return Vec::new();
+    }
+
+    if cfg!(windows) {
+        log::warn!(target: RELOAD, "Prelinking dependencies is not supported on Windows");
+        return Vec::new();
+    }
+
+    let mut args = vec!["-C".to_string(), "prefer-dynamic".to_string()];
+    // The library is loaded with `dlopen`, which only finds the prelinked
+    // dylib and the Rust standard library through the rpath.
+    let search_dirs = [Some(profile_dir.join("deps")), target_libdir()];
+    for dir in search_dirs.into_iter().flatten() {
+        args.push("-C".to_string());
+        args.push(format!("link-arg=-Wl,-rpath,{}", dir.display()));
+    }
+    args
+}
+
+/// Generates the prelink crate if it does not exist yet and logs how to
+/// link it into the ui crate.
+fn setup(metadata: &Metadata, package: &Package) {
+    let workspace_root = metadata.workspace_root.as_std_path();
+    let crate_dir = workspace_root.join(CRATE_NAME);
+
+    if !crate_dir.exists() {
+        match generate(&crate_dir, workspace_root, package) {
+            Ok(()) => log::info!(target: RELOAD, "Generated {}", crate_dir.display()),
+            Err(err) => {
+                log::error!(target: RELOAD, "Failed to generate {}: {}", crate_dir.display(), err);
+                return;
+            }
+        }
+    }
+
+    log::warn!(
+        target: RELOAD,
+        "{name} does not depend on {CRATE_NAME} yet, dependencies are linked statically. \
+         Add {CRATE_NAME} to the workspace members, add \
+         `{CRATE_NAME} = {{ path = \"{path}\", optional = true }}` to the dependencies of \
+         {name}, enable it with the reload feature and add \
+         `#[cfg(feature = \"reload\")] use {CRATE_NAME} as _;` to its lib.rs",
+        name = package.name,
+        path = relative_to(
+            &crate_dir,
+            package.manifest_path.parent().map(|p| p.as_std_path())
+        )
+        .display(),
+    );
+}
+
+fn generate(crate_dir: &Path, workspace_root: &Path, package: &Package) -> io::Result<()> {
+    fs::create_dir_all(crate_dir.join("src"))?;
+    fs::write(
+        crate_dir.join("Cargo.toml"),
+        manifest(package, crate_dir, workspace_root),
+    )?;
+    fs::write(
+        crate_dir.join("src").join("lib.rs"),
+        format!(
+            "//! Generated by hot_ice. Compiles the dependencies of `{}` into a dylib\n\
+             //! that is linked dynamically into the hot library.\n\n{}",
+            package.name,
+            prelinked(package)
+                .map(|dep| format!("pub use {};\n", dep.name))
+                .collect::<String>()
+        ),
+    )
+}
+
+fn manifest(package: &Package, crate_dir: &Path, workspace_root: &Path) -> String {
+    let dependencies: String = prelinked(package)
+        .map(|dep| dependency(dep, crate_dir, workspace_root))
+        .collect();
+
+    format!(
+        "# Generated by hot_ice, see `ReloaderSettings::prelink`.\n\
+         [package]\n\
+         name = \"{CRATE_NAME}\"\n\
+         version = \"0.1.0\"\n\
+         edition = \"{edition}\"\n\
+         publish = false\n\
+         \n\
+         [lib]\n\
+         crate-type = [\"dylib\"]\n\
+         \n\
+         [dependencies]\n\
+         {dependencies}",
+        edition = package.edition,
+    )
+}
+
+fn prelinked(package: &Package) -> impl Iterator<Item = &Dependency> {
+    package
+        .dependencies
+        .iter()
+        .filter(|dep| PRELINKED.contains(&dep.name.as_str()) && dep.rename.is_none())
+}
+
+/// Dependency line equivalent to `dep` for a crate in `crate_dir`.
+fn dependency(dep: &Dependency, crate_dir: &Path, workspace_root: &Path) -> String {
+    let mut fields = vec![format!("version = \"{}\"", dep.req)];
+    if let Some(path) = &dep.path {
+        let path = match path.as_std_path().strip_prefix(workspace_root) {
+            Ok(_) => relative_to(path.as_std_path(), Some(crate_dir)),
+            Err(_) => path.as_std_path().to_path_buf(),
+        };
+        fields.push(format!("path = {:?}", path.display().to_string()));
+    }
+    if !dep.uses_default_features {
+        fields.push("default-features = false".to_string());
+    }
+    if !dep.features.is_empty() {
+        fields.push(format!("features = {:?}", dep.features));
+    }
+
+    format!("{} = {{ {} }}\n", dep.name, fields.join(", "))
+}
+
+/// `path` relative to `base`, assuming both are absolute and `base` is a
+/// directory.
+fn relative_to(path: &Path, base: Option<&Path>) -> PathBuf {
+    let Some(base) = base else {
+        return path.to_path_buf();
+    };
+
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(path.components().skip(common));
+    relative
+}
+
+/// Directory of the Rust standard library dylib of the current toolchain.
+fn target_libdir() -> Option<PathBuf> {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--print", "target-libdir"])
+        .output()
+        .inspect_err(|err| log::error!(target: RELOAD, "Failed to run rustc: {}", err))
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths_between_workspace_crates() {
+        assert_eq!(
+            relative_to(
+                Path::new("/ws/hot_ice_prelink"),
+                Some(Path::new("/ws/crates/ui"))
+            ),
+            Path::new("../../hot_ice_prelink")
+        );
+        assert_eq!(
+            relative_to(
+                Path::new("/ws/vendor/iced"),
+                Some(Path::new("/ws/hot_ice_prelink"))
+            ),
+            Path::new("../vendor/iced")
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Recording the program's messages into a fixture for tests.
+//!
+//! While the overlay's "Record messages" toggle is on, every message that
+//! reaches `update` is written to `ReloaderSettings::message_fixture`, one
+//! RON value per line, so a session clicked through by hand can be replayed
+//! with [`replay_fixture`](crate::testing::replay_fixture). Messages are
+//! serialized with the serde code of the library that produced them, which
+//! only types deriving [`HotMessage`](crate::HotMessage) carry. Variants
+//! marked `#[hot_message(skip)]` are left out.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::log_target::RELOAD;
+use crate::message::{DynMessage, HotMessage};
+
+/// First line of every fixture.
+const HEADER: &str = "// Messages recorded by hot_ice, replay them with \
+                      `hot_ice::testing::replay_fixture`.";
+
+/// Writes the messages of the program to a fixture while recording.
+pub(crate) struct MessageRecorder {
+    path: PathBuf,
+    file: BufWriter<File>,
+    recorded: usize,
+    skipped: usize,
+    /// Whether the missing `#[derive(HotMessage)]` was logged already.
+    warned: bool,
+}
+
+impl MessageRecorder {
+    /// Starts a new fixture at `path`, replacing the one there.
+    pub(crate) fn start(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "{HEADER}")?;
+        log::info!(target: RELOAD, "Recording messages to {}", path.display());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            recorded: 0,
+            skipped: 0,
+            warned: false,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `message` to the fixture, if it can be serialized.
+    pub(crate) fn record(&mut self, message: &dyn DynMessage) -> io::Result<()> {
+        let transfer = message
+            .as_any()
+            .downcast_ref::<HotMessage>()
+            .and_then(|hot| Some((hot, hot.transfer()?)));
+        let Some((message, transfer)) = transfer else {
+            self.skipped += 1;
+            if !std::mem::replace(&mut self.warned, true) {
+                log::warn!(
+                    target: RELOAD,
+                    "{message:?} can't be recorded, derive `hot_ice::HotMessage` for the \
+                     message type"
+                );
+            }
+            return Ok(());
+        };
+
+        match transfer.to_ron(message) {
+            Ok(Some(line)) => {
+                writeln!(self.file, "{line}")?;
+                self.recorded += 1;
+            }
+            Ok(None) => self.skipped += 1,
+            Err(err) => {
+                self.skipped += 1;
+                log::warn!(target: RELOAD, "Failed to record {message:?}: {err}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the fixture and returns the number of recorded messages.
+    pub(crate) fn finish(mut self) -> io::Result<usize> {
+        self.file.flush()?;
+        log::info!(
+            target: RELOAD,
+            "Recorded {} messages to {}, {} could not be serialized",
+            self.recorded,
+            self.path.display(),
+            self.skipped
+        );
+        Ok(self.recorded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::NotTransferable;
+    use crate::testing::replay_fixture;
+    use crate::transfer::TransferMessage;
+
+    #[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+    enum Message {
+        Rename(String),
+        Resized { width: u32, height: u32 },
+        Opened(#[serde(skip)] ()),
+    }
+
+    impl TransferMessage for Message {
+        const TYPE_HASH: u64 = 1;
+
+        fn transferable(&self) -> Result<(), NotTransferable> {
+            match self {
+                Message::Opened(()) => Err(NotTransferable { variant: "Opened" }),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn recorded_messages_are_replayed_in_order() {
+        let path = std::env::temp_dir()
+            .join(format!("hot_ice_fixture_{}", std::process::id()))
+            .join("messages.ron");
+        let sent = [
+            Message::Rename("a\nb".to_string()),
+            Message::Opened(()),
+            Message::Resized {
+                width: 640,
+                height: 480,
+            },
+        ];
+
+        let mut recorder = MessageRecorder::start(&path).unwrap();
+        for message in &sent {
+            recorder
+                .record(&HotMessage::from_transferable(message.clone()))
+                .unwrap();
+        }
+        recorder.record(&HotMessage::from_message(7_u8)).unwrap();
+        assert_eq!(recorder.finish().unwrap(), 2);
+
+        let mut replayed = Vec::new();
+        replay_fixture(&path, |message: Message| replayed.push(message));
+        assert_eq!(replayed, [sent[0].clone(), sent[2].clone()]);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,415 @@
+//! Host-side registry of the hot functions the binary expects to find in the
+//! cdylib.
+//!
+//! Every hot function handed to the application builder is recorded here with
+//! its symbol name, [`HotFunction`] kind and a hash of its signature. The
+//! `#[hot_fn]` macro emits a companion symbol next to each exported function
+//! (prefixed with [`HOT_EXPORT_SYMBOL_PREFIX`]) that reports the same data from
+//! inside the cdylib. After every library load the reloader diffs the two and
+//! logs a table of missing and changed functions, and with debug logging the
+//! extra ones. Functions marked
+//! `#[hot_fn(not_hot)]` export a marker symbol instead (prefixed with
+//! [`NOT_HOT_SYMBOL_PREFIX`]) and are listed as static.
+//!
+//! Functions that are not passed to the builder can be registered manually
+//! with [`register_hot_fn!`](crate::register_hot_fn).
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Mutex;
+
+use hot_ice_common::{HOT_EXPORT_SYMBOL_PREFIX, NOT_HOT_SYMBOL_PREFIX};
+use libloading::Library;
+
+use crate::lib_reloader::LibReloader;
+use crate::log_target::RELOAD;
+use crate::reloader::HotFunction;
+
+/// A hot function the host binary expects the cdylib to export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpectedExport {
+    pub name: &'static str,
+    pub kind: HotFunction,
+    pub type_hash: u64,
+}
+
+/// Description of an exported hot function, returned by the companion symbol
+/// the `#[hot_fn]` macro generates inside the cdylib.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotExport {
+    pub kind: HotFunction,
+    pub type_hash: u64,
+}
+
+static EXPECTED_EXPORTS: Mutex<Vec<ExpectedExport>> = Mutex::new(Vec::new());
+
+/// Records a hot function the host expects to find in the cdylib.
+///
+/// Names that are not valid symbol names (closures, `&'static str` titles)
+/// are ignored, as are duplicate registrations of the same name.
+pub fn register(name: &'static str, kind: HotFunction, type_hash: u64) {
+    if !is_symbol_name(name) {
+        return;
+    }
+
+    let Ok(mut exports) = EXPECTED_EXPORTS.lock() else {
+        return;
+    };
+
+    if exports.iter().any(|export| export.name == name) {
+        return;
+    }
+
+    exports.push(ExpectedExport {
+        name,
+        kind,
+        type_hash,
+    });
+}
+
+/// Returns a snapshot of every registered export.
+pub fn expected_exports() -> Vec<ExpectedExport> {
+    EXPECTED_EXPORTS
+        .lock()
+        .map(|exports| exports.clone())
+        .unwrap_or_default()
+}
+
+/// Hashes the name of `T` with 64-bit FNV-1a.
+///
+/// The host and the cdylib both compute this for the tuple of argument and
+/// return types of a hot function, so a mismatch means the two sides disagree
+/// on the function's signature.
+pub fn type_hash<T: ?Sized>() -> u64 {
+    fnv1a(std::any::type_name::<T>().as_bytes())
+}
+
+/// 64-bit FNV-1a hash of `bytes`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Registers a hot function that is not passed to the application builder.
+///
+/// The signature is given as a tuple of the argument types followed by the
+/// return type of the exported wrapper. Without a signature the function is
+/// only checked for presence.
+///
+/// ```rust,ignore
+/// hot_ice::register_hot_fn!(Update, "update", (&mut State, HotMessage, Task<HotMessage>));
+/// hot_ice::register_hot_fn!(View, "sidebar");
+/// ```
+#[macro_export]
+macro_rules! register_hot_fn {
+    ($kind:ident, $name:expr) => {
+        $crate::registry::register($name, $crate::macro_use::HotFunction::$kind, 0)
+    };
+    ($kind:ident, $name:expr, $signature:ty) => {
+        $crate::registry::register(
+            $name,
+            $crate::macro_use::HotFunction::$kind,
+            $crate::registry::type_hash::<$signature>(),
+        )
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportStatus {
+    Ok,
+    Missing,
+    NotHot,
+    Changed,
+    Extra,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExportDiff {
+    name: String,
+    kind: Option<HotFunction>,
+    status: ExportStatus,
+}
+
+/// What the companion symbol of the hot function `name` in the loaded
+/// library reports, `None` if the library exports no such hot function.
+pub(crate) fn exported(lib: &LibReloader, name: &str) -> Option<HotExport> {
+    companion(lib.library()?, name)
+}
+
+fn companion(library: &Library, name: &str) -> Option<HotExport> {
+    let symbol = format!("{HOT_EXPORT_SYMBOL_PREFIX}{name}");
+    let function = unsafe { library.get::<fn() -> HotExport>(symbol.as_bytes()) }.ok()?;
+    Some(function())
+}
+
+/// Compares the registered exports against the companion symbols found in
+/// the currently loaded library and logs the result as a table.
+///
+/// Only the registered names are looked up in the library. Finding the
+/// companion symbols the host does not use means reading the library file,
+/// that is done on a thread of its own and only while debug logging is on.
+pub(crate) fn log_export_diff(lib: &LibReloader) {
+    let Some(library) = lib.shared_library() else {
+        return;
+    };
+    let expected = expected_exports();
+
+    let not_hot = |name: &str| {
+        let marker = format!("{NOT_HOT_SYMBOL_PREFIX}{name}");
+        unsafe { library.get::<*const ()>(marker.as_bytes()) }.is_ok()
+    };
+    let diff = diff_exports(
+        &expected,
+        &BTreeSet::new(),
+        |name| companion(&library, name),
+        not_hot,
+    );
+    if !diff.is_empty() {
+        let table = export_table(&diff);
+        if diff
+            .iter()
+            .any(|entry| entry.status == ExportStatus::Changed)
+        {
+            log::warn!(target: RELOAD, "Hot function exports differ from the host:{}", table);
+        } else {
+            log::info!(target: RELOAD, "Hot function exports:{}", table);
+        }
+    }
+
+    if !log::log_enabled!(target: RELOAD, log::Level::Debug) {
+        return;
+    }
+    let Some(file) = lib.loaded_lib_file().map(Path::to_path_buf) else {
+        return;
+    };
+    let spawned = std::thread::Builder::new()
+        .name("hot-ice-exports".to_string())
+        .spawn(move || {
+            let exported_names = match std::fs::read(&file) {
+                Ok(bytes) => scan_export_names(&bytes),
+                Err(err) => {
+                    log::debug!(target: RELOAD, "export diff: failed to read {:?}: {}", file, err);
+                    return;
+                }
+            };
+            let extra: Vec<_> = diff_exports(
+                &expected,
+                &exported_names,
+                |name| companion(&library, name),
+                |_| false,
+            )
+            .into_iter()
+            .filter(|entry| entry.status == ExportStatus::Extra)
+            .collect();
+            if !extra.is_empty() {
+                log::debug!(target: RELOAD, "Hot functions the host does not use:{}", export_table(&extra));
+            }
+        });
+    if let Err(err) = spawned {
+        log::debug!(target: RELOAD, "export diff: failed to spawn thread: {}", err);
+    }
+}
+
+fn export_table(diff: &[ExportDiff]) -> String {
+    let name_width = diff
+        .iter()
+        .map(|entry| entry.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("function".len());
+
+    let mut table = format!("\n  {:<name_width$}  {:<12}  status", "function", "kind");
+    for entry in diff {
+        let kind = match entry.kind {
+            // Named in the first column already.
+            Some(HotFunction::Extension(_)) => "Extension".to_string(),
+            Some(kind) => kind.to_string(),
+            None => "?".to_string(),
+        };
+        let status = match entry.status {
+            ExportStatus::Ok => "ok",
+            ExportStatus::Missing => "missing (static fallback)",
+            ExportStatus::NotHot => "static (not_hot)",
+            ExportStatus::Changed => "changed signature",
+            ExportStatus::Extra => "extra (not used by host)",
+        };
+        table.push_str(&format!(
+            "\n  {:<name_width$}  {:<12}  {}",
+            entry.name, kind, status
+        ));
+    }
+    table
+}
+
+fn diff_exports(
+    expected: &[ExpectedExport],
+    exported_names: &BTreeSet<String>,
+    lookup: impl Fn(&str) -> Option<HotExport>,
+    not_hot: impl Fn(&str) -> bool,
+) -> Vec<ExportDiff> {
+    let mut diff = Vec::new();
+
+    for export in expected {
+        let status = match lookup(export.name) {
+            None if not_hot(export.name) => ExportStatus::NotHot,
+            None => ExportStatus::Missing,
+            Some(actual) if export.type_hash != 0 && actual.type_hash != export.type_hash => {
+                ExportStatus::Changed
+            }
+            Some(_) => ExportStatus::Ok,
+        };
+        diff.push(ExportDiff {
+            name: export.name.to_string(),
+            kind: Some(export.kind),
+            status,
+        });
+    }
+
+    for name in exported_names {
+        if expected.iter().any(|export| export.name == name) {
+            continue;
+        }
+        diff.push(ExportDiff {
+            name: name.clone(),
+            kind: lookup(name).map(|actual| actual.kind),
+            status: ExportStatus::Extra,
+        });
+    }
+
+    diff
+}
+
+/// Collects the names of all companion symbols in a library file.
+///
+/// Exported symbol names are stored as plain strings in the library's
+/// dynamic symbol (ELF), export (PE) or string (Mach-O) table, so scanning the
+/// raw bytes for the companion prefix is enough to enumerate them without a
+/// format-specific parser.
+fn scan_export_names(bytes: &[u8]) -> BTreeSet<String> {
+    let prefix = HOT_EXPORT_SYMBOL_PREFIX.as_bytes();
+    let mut names = BTreeSet::new();
+    let mut offset = 0;
+
+    while let Some(position) = bytes[offset..]
+        .windows(prefix.len())
+        .position(|window| window == prefix)
+    {
+        let start = offset + position + prefix.len();
+        let end = bytes[start..]
+            .iter()
+            .position(|byte| !(byte.is_ascii_alphanumeric() || *byte == b'_'))
+            .map_or(bytes.len(), |len| start + len);
+
+        if end > start {
+            names.insert(String::from_utf8_lossy(&bytes[start..end]).into_owned());
+        }
+        offset = end;
+    }
+
+    names
+}
+
+fn is_symbol_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected(name: &'static str, type_hash: u64) -> ExpectedExport {
+        ExpectedExport {
+            name,
+            kind: HotFunction::Update,
+            type_hash,
+        }
+    }
+
+    #[test]
+    fn type_hash_differs_between_types() {
+        assert_eq!(type_hash::<(&u8, String)>(), type_hash::<(&u8, String)>());
+        assert_ne!(
+            type_hash::<(&u8, String)>(),
+            type_hash::<(&mut u8, String)>()
+        );
+    }
+
+    #[test]
+    fn scan_finds_companion_symbols() {
+        let mut bytes = b"\0junk\0".to_vec();
+        bytes.extend_from_slice(HOT_EXPORT_SYMBOL_PREFIX.as_bytes());
+        bytes.extend_from_slice(b"update\0_");
+        bytes.extend_from_slice(HOT_EXPORT_SYMBOL_PREFIX.as_bytes());
+        bytes.extend_from_slice(b"view\0");
+        bytes.extend_from_slice(HOT_EXPORT_SYMBOL_PREFIX.as_bytes());
+        bytes.extend_from_slice(b"update\0");
+
+        let names: Vec<_> = scan_export_names(&bytes).into_iter().collect();
+        assert_eq!(names, ["update", "view"]);
+    }
+
+    #[test]
+    fn diff_reports_missing_not_hot_extra_and_changed() {
+        let expected = [
+            expected("update", 1),
+            expected("view", 2),
+            expected("title", 3),
+            expected("theme", 6),
+        ];
+        let exported: BTreeSet<String> = ["update", "view", "sidebar"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let not_hot = |name: &str| name == "theme";
+        let diff = diff_exports(
+            &expected,
+            &exported,
+            |name| match name {
+                "update" => Some(HotExport {
+                    kind: HotFunction::Update,
+                    type_hash: 1,
+                }),
+                "view" => Some(HotExport {
+                    kind: HotFunction::View,
+                    type_hash: 5,
+                }),
+                "sidebar" => Some(HotExport {
+                    kind: HotFunction::View,
+                    type_hash: 4,
+                }),
+                _ => None,
+            },
+            not_hot,
+        );
+
+        let statuses: Vec<_> = diff
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("update", ExportStatus::Ok),
+                ("view", ExportStatus::Changed),
+                ("title", ExportStatus::Missing),
+                ("theme", ExportStatus::NotHot),
+                ("sidebar", ExportStatus::Extra),
+            ]
+        );
+    }
+
+    #[test]
+    fn register_ignores_closures() {
+        assert!(!is_symbol_name("{{closure}}"));
+        assert!(!is_symbol_name("&str"));
+        assert!(is_symbol_name("update"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Resets the widget state of the program's view after a reload, see
+//! [`ReloaderSettings::rekey_widgets_on_reload`](crate::ReloaderSettings).
+//!
+//! iced keeps the state of a widget, like a scroll offset or a text cursor,
+//! as long as a widget of the same type sits at the same place in the tree.
+//! [`Rekeyed`] drops the state of everything below it once its key changes.
+
+use iced_core::{
+    Clipboard, Element, Event, Layout, Length, Rectangle, Shell, Size, Vector, layout, mouse,
+    overlay, renderer,
+    widget::{Operation, Tree, Widget, tree},
+};
+
+/// The key the state below a [`Rekeyed`] was built for.
+struct Key(u64);
+
+/// Draws `content`, with fresh widget state whenever `key` changes.
+pub(crate) struct Rekeyed<'a, Message, Theme, Renderer> {
+    key: u64,
+    content: Element<'a, Message, Theme, Renderer>,
+}
+
+impl<'a, Message, Theme, Renderer> Rekeyed<'a, Message, Theme, Renderer> {
+    pub(crate) fn new(key: u64, content: impl Into<Element<'a, Message, Theme, Renderer>>) -> Self {
+        Self {
+            key,
+            content: content.into(),
+        }
+    }
+}
+
+impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer>
+    for Rekeyed<'_, Message, Theme, Renderer>
+where
+    Renderer: renderer::Renderer,
+{
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn size_hint(&self) -> Size<Length> {
+        self.content.as_widget().size_hint()
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<Key>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(Key(self.key))
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        let key = tree.state.downcast_mut::<Key>();
+        if key.0 == self.key {
+            tree.diff_children(std::slice::from_ref(&self.content));
+        } else {
+            key.0 = self.key;
+            tree.children = self.children();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget_mut()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        )
+    }
+
+    fn operate(
+        &mut self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        self.content
+            .as_widget_mut()
+            .operate(&mut tree.children[0], layout, renderer, operation)
+    }
+
+    fn update(
+        &mut self,
+        tree: &mut Tree,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget_mut().update(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'b>,
+        renderer: &Renderer,
+        viewport: &Rectangle,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.content.as_widget_mut().overlay(
+            &mut tree.children[0],
+            layout,
+            renderer,
+            viewport,
+            translation,
+        )
+    }
+}
+
+impl<'a, Message, Theme, Renderer> From<Rekeyed<'a, Message, Theme, Renderer>>
+    for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Theme: 'a,
+    Renderer: renderer::Renderer + 'a,
+{
+    fn from(rekeyed: Rekeyed<'a, Message, Theme, Renderer>) -> Self {
+        Element::new(rekeyed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use iced_widget::{Renderer, space};
+
+    type Element<'a> = iced_core::Element<'a, (), iced_core::Theme, Renderer>;
+
+    #[test]
+    fn a_new_key_drops_the_state_below() {
+        let element = |key| Element::from(Rekeyed::new(key, space()));
+        let mark = |tree: &mut Tree| tree.children[0].state = tree::State::new(7_u8);
+        let is_marked = |tree: &Tree| matches!(tree.children[0].state, tree::State::Some(_));
+
+        let mut tree = Tree::new(&element(1));
+        mark(&mut tree);
+
+        tree.diff(&element(1));
+        assert!(is_marked(&tree));
+
+        tree.diff(&element(2));
+        assert!(!is_marked(&tree));
+    }
+}
@@ -0,0 +1,171 @@
+//! Reloads waiting for the user to finish an interaction.
+//!
+//! A library swap replaces the program's widgets with the reloading screen,
+//! so a reload in the middle of a drag drops the drag and one during an IME
+//! composition drops the text being composed. The reloader holds a new
+//! library back while a mouse button or a finger is down, while the input
+//! method has text pending and while the program holds a
+//! [`DeferReloadGuard`], and swaps it in once they are done, at the latest
+//! after `ReloaderSettings::max_reload_deferral`.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use iced_core::{Event, input_method, mouse, touch, window};
+
+use crate::context::Context;
+
+/// Holds back reloads until it is dropped, see [`defer_reload_guard`].
+#[must_use = "the reload is only deferred while the guard is held"]
+#[derive(Debug)]
+pub struct DeferReloadGuard(());
+
+/// Defers reloads until the returned guard is dropped, for critical
+/// sections the reloader can't see, e.g. an animation that must not be
+/// cut off:
+///
+/// ```rust,ignore
+/// Message::TransitionStarted => {
+///     self.transition = Some(hot_ice::defer_reload_guard());
+/// }
+/// Message::TransitionEnded => self.transition = None,
+/// ```
+///
+/// The library is swapped anyway once `ReloaderSettings::max_reload_deferral`
+/// passed. Guards kept in a `#[hot_state]` state have to be `#[serde(skip)]`.
+pub fn defer_reload_guard() -> DeferReloadGuard {
+    Context::current().defer_reload(true);
+    DeferReloadGuard(())
+}
+
+impl Drop for DeferReloadGuard {
+    fn drop(&mut self) {
+        Context::current().defer_reload(false);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Host side, called through the context's table
+// ---------------------------------------------------------------------------
+
+static GUARDS: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn hold(held: bool) {
+    if held {
+        GUARDS.fetch_add(1, Ordering::Relaxed);
+    } else {
+        let _ = GUARDS.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |guards| {
+            guards.checked_sub(1)
+        });
+    }
+}
+
+/// The input the reloader waits for before it swaps a library.
+#[derive(Debug, Default)]
+pub(crate) struct Interaction {
+    buttons: HashSet<mouse::Button>,
+    fingers: HashSet<touch::Finger>,
+    composing: bool,
+}
+
+impl Interaction {
+    /// Whether `event` starts or ends an interaction, the ones
+    /// [`track`](Self::track) needs to see.
+    pub(crate) fn is_relevant(event: &Event) -> bool {
+        matches!(
+            event,
+            Event::Mouse(mouse::Event::ButtonPressed(_) | mouse::Event::ButtonReleased(_))
+                | Event::Touch(
+                    touch::Event::FingerPressed { .. }
+                        | touch::Event::FingerLifted { .. }
+                        | touch::Event::FingerLost { .. }
+                )
+                | Event::InputMethod(_)
+                | Event::Window(window::Event::Unfocused)
+        )
+    }
+
+    pub(crate) fn track(&mut self, event: &Event) {
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(button)) => {
+                self.buttons.insert(*button);
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(button)) => {
+                self.buttons.remove(button);
+            }
+            Event::Touch(touch::Event::FingerPressed { id, .. }) => {
+                self.fingers.insert(*id);
+            }
+            Event::Touch(
+                touch::Event::FingerLifted { id, .. } | touch::Event::FingerLost { id, .. },
+            ) => {
+                self.fingers.remove(id);
+            }
+            Event::InputMethod(input_method::Event::Preedit(text, _)) => {
+                self.composing = !text.is_empty();
+            }
+            Event::InputMethod(
+                input_method::Event::Commit(_)
+                | input_method::Event::Closed
+                | input_method::Event::Opened,
+            ) => self.composing = false,
+            // Releases are not delivered to a window without focus.
+            Event::Window(window::Event::Unfocused) => *self = Self::default(),
+            _ => {}
+        }
+    }
+
+    /// Why a reload has to wait now, `None` if it may start.
+    pub(crate) fn blocker(&self) -> Option<&'static str> {
+        if !self.buttons.is_empty() {
+            Some("a mouse button is pressed")
+        } else if !self.fingers.is_empty() {
+            Some("the screen is touched")
+        } else if self.composing {
+            Some("the input method is composing text")
+        } else if GUARDS.load(Ordering::Relaxed) > 0 {
+            Some("the program holds a `defer_reload_guard`")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use iced_core::Point;
+
+    #[test]
+    fn interactions_defer_until_they_end() {
+        let mut interaction = Interaction::default();
+        assert_eq!(interaction.blocker(), None);
+
+        let pressed = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        assert!(Interaction::is_relevant(&pressed));
+        interaction.track(&pressed);
+        interaction.track(&Event::Touch(touch::Event::FingerPressed {
+            id: touch::Finger(1),
+            position: Point::ORIGIN,
+        }));
+        assert_eq!(interaction.blocker(), Some("a mouse button is pressed"));
+
+        interaction.track(&Event::Mouse(mouse::Event::ButtonReleased(
+            mouse::Button::Left,
+        )));
+        assert_eq!(interaction.blocker(), Some("the screen is touched"));
+
+        interaction.track(&Event::InputMethod(input_method::Event::Preedit(
+            "か".to_string(),
+            None,
+        )));
+        interaction.track(&Event::Window(window::Event::Unfocused));
+        assert_eq!(interaction.blocker(), None);
+
+        let guard = defer_reload_guard();
+        assert!(interaction.blocker().is_some());
+        drop(guard);
+        assert_eq!(interaction.blocker(), None);
+    }
+}
@@ -1,27 +1,36 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::VecDeque,
+    convert::Infallible,
     fmt::Debug,
     io::{BufRead, BufReader},
+    mem::ManuallyDrop,
     path::PathBuf,
     process::{Child, Command, Stdio},
-    sync::{Arc, Mutex, OnceLock},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread::JoinHandle,
     time::Duration,
 };
 
+use arc_swap::ArcSwap;
 use cargo_metadata::{MetadataCommand, camino::Utf8PathBuf};
-use crossfire::{AsyncRx, MAsyncRx, MTx, mpmc};
+use crossfire::{AsyncRx, MAsyncRx, MTx, RecvTimeoutError, mpmc};
 use hot_ice_common::{
-    DESERIALIZE_STATE_FUNCTION_NAME, FREE_SERIALIZED_DATA_FUNCTION_NAME,
-    SERIALIZE_STATE_FUNCTION_NAME,
+    ADOPT_STATE_FUNCTION_NAME, DESERIALIZE_STATE_FUNCTION_NAME, DROP_STATE_FIELDS_FUNCTION_NAME,
+    FINGERPRINT_FUNCTION_NAME, FREE_SERIALIZED_DATA_FUNCTION_NAME,
+    RESTORE_STATE_FIELDS_FUNCTION_NAME, SERIALIZE_STATE_FIELDS_FUNCTION_NAME,
+    SERIALIZE_STATE_FUNCTION_NAME, SET_HOST_ALLOCATOR_FUNCTION_NAME, STATE_FIELDS_FUNCTION_NAME,
+    STATE_TYPE_HASH_FUNCTION_NAME,
 };
 use iced_core::{
-    Alignment, Animation, Background, Border, Color, Element, Font, Length, Padding, Settings,
-    Theme,
-    animation::Easing,
-    theme::{self, Base, Mode},
+    Alignment, Background, Border, Color, Element, Event, Font, Length, Padding, Settings, Theme,
+    event,
+    theme::{self, Base, Palette},
     time::Instant,
-    window,
+    window::{self, UserAttention},
 };
 use iced_futures::{
     BoxStream, Subscription,
@@ -30,26 +39,93 @@ use iced_futures::{
     subscription::{self as iced_subscription, EventStream, Hasher, Recipe},
 };
 use iced_widget::{
-    Stack, Text, button, column, container, container::Style as ContainerStyle, row, scrollable,
-    sensor, space, text::Style as TextStyle, themer,
+    Column, Stack, Text, button, column, container, container::Style as ContainerStyle, mouse_area,
+    row, scrollable, sensor, space, text::Style as TextStyle, themer,
 };
 use iced_winit::{
     program::Program,
-    runtime::{Action, Task, task, window as runtime_window},
+    runtime::{Action, Task, clipboard, task, window as runtime_window},
 };
-use log::info;
+use libloading::Library;
+use log::{LevelFilter, info};
+use serde::{Serialize, Serializer};
 use thiserror::Error;
 
+use crate::log_target::{DISPATCH, RELOAD, WORKER};
 use crate::{
+    blocking, build_inputs,
+    build_progress::{BuildEvent, BuildProgress},
+    compiler::{Cargo, CompileRequest, CompileResult, Compiler, HOT_PROFILE, shell_line},
+    during_reload,
     error::HotIceError,
-    executor::{CdylibWorker, DrainHandle},
+    error_toasts::{ErrorRecord, ErrorToasts, format_time},
+    executor::{self, CdylibWorker, DrainHandle, StuckWorker},
+    fingerprint,
+    functions::hot_dispatch::{self, ExtensionCall},
+    generation_history::GenerationRecord,
+    hot_assets, hot_data,
     hot_program::HotProgram,
-    lib_reloader::{LibReloader, RetiredLibrary},
-    message::MessageSource,
+    hot_state::{self, StateField},
+    lent_state,
+    lib_reloader::{
+        ArtifactCleanup, LibReloader, RetiredLibrary, UpdateResult, remove_artifact_hash,
+        write_artifact_hash,
+    },
+    library_handle::{LibraryHandle, ResolvedGeneration},
+    message::{DynMessage, HotMessage, MessageSource},
+    metrics::{self, MessageOrigin, StateTransfer},
+    overlay_cache::OverlayCache,
+    palette_editor::{PaletteEdit, PaletteEditor},
+    prelink,
+    recording::MessageRecorder,
+    registry,
+    rekeyed::Rekeyed,
+    reload_deferral::Interaction,
+    source_revision::SourceRevision,
+    stale_artifact,
+    stuck_worker::STOP_GRACE,
+    subscriptions::{self, Change},
+    task::{self as task_requests, Request},
+    taskbar,
+    transport::{FileWatch, ReloadEvent, ReloadTransport},
+    ui_crate::UiCrate,
+    widget_state::{self, WidgetSnapshot},
+    window_geometry::{WindowChange, WindowGeometry},
+    worker_queue::WorkerQueue,
 };
 
 const DEFAULT_TARGET_DIR: &str = "target/reload";
-const DEFAULT_LIB_DIR: &str = "target/reload/debug";
+const DEFAULT_LIB_DIR: &str = "target/reload/hot";
+
+/// How long either side of the reload handshake waits for the other before
+/// retrying, see [`ReadyToReload`].
+const READY_SIGNAL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Messages kept during a reload, see
+/// [`ReloaderSettings::buffer_messages_during_reload`].
+const MAX_BUFFERED_MESSAGES: usize = 1024;
+
+/// Whether a message received during a reload can be handed to the new
+/// library: one of the binary's own code, or one carrying the serde table of
+/// `#[derive(HotMessage)]`, see `src/transfer.rs`. Any other message made by
+/// the replaced library may have the layout of an edited type.
+fn survives_reload<M: DynMessage>(message: &MessageSource<M>) -> bool {
+    match message {
+        MessageSource::Static(_) => true,
+        MessageSource::Dynamic(message) => message
+            .as_any()
+            .downcast_ref::<HotMessage>()
+            .is_some_and(|message| message.transfer().is_some()),
+    }
+}
+
+/// How often a deferred reload checks whether it may start, see
+/// [`ReloaderSettings::max_reload_deferral`].
+const DEFERRED_RELOAD_POLL: Duration = Duration::from_millis(100);
+
+/// How long the window has to stay unchanged before its geometry is saved,
+/// see [`ReloaderSettings::persist_window`].
+const WINDOW_GEOMETRY_SAVE_DELAY: Duration = Duration::from_millis(500);
 
 /// Global handle to the cargo watch child process for cleanup on exit
 static CARGO_WATCH_CHILD: OnceLock<Mutex<Option<Child>>> = OnceLock::new();
@@ -60,7 +136,7 @@ pub fn kill_cargo_watch() {
     if let Some(mutex) = CARGO_WATCH_CHILD.get() {
         if let Ok(mut guard) = mutex.lock() {
             if let Some(ref mut child) = *guard {
-                log::info!("Killing cargo watch process (pid: {:?})", child.id());
+                log::info!(target: RELOAD, "Killing cargo watch process (pid: {:?})", child.id());
                 #[cfg(unix)]
                 {
                     // Kill the entire process group
@@ -104,26 +180,232 @@ fn register_cleanup_handler() {
     });
 }
 
+/// Where the reloader draws its status overlay (function errors) on top of
+/// the program's view. The overlay is always stacked over the view, so the
+/// program's layout is never affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlayPlacement {
+    /// Full width bar along the top edge of the window.
+    #[default]
+    Top,
+    /// Full width bar along the bottom edge of the window.
+    Bottom,
+    /// Compact panel floating in a corner of the window.
+    FloatingCorner(Corner),
+    /// Only a colored status dot in a corner of the window, which expands
+    /// into the per-function status panel on hover or click. Useful for
+    /// small windows.
+    StatusDot(Corner),
+    /// No overlay, errors are only logged.
+    Hidden,
+}
+
+/// Corner of the window, see [`OverlayPlacement::FloatingCorner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl OverlayPlacement {
+    /// Corner of the metrics table, away from the error overlay.
+    fn metrics_corner(self) -> Corner {
+        match self {
+            OverlayPlacement::Bottom
+            | OverlayPlacement::FloatingCorner(Corner::BottomRight)
+            | OverlayPlacement::StatusDot(Corner::BottomRight) => Corner::TopRight,
+            _ => Corner::BottomRight,
+        }
+    }
+}
+
+/// Wraps `content` in a full size container aligned to `corner`.
+fn place_in_corner<'a, Message, Renderer>(
+    content: impl Into<Element<'a, Message, Theme, Renderer>>,
+    corner: Corner,
+) -> iced_widget::Container<'a, Message, Theme, Renderer>
+where
+    Renderer: iced_core::Renderer,
+{
+    let container = container(content);
+    let container = match corner {
+        Corner::TopLeft | Corner::BottomLeft => container.align_left(Length::Fill),
+        Corner::TopRight | Corner::BottomRight => container.align_right(Length::Fill),
+    };
+    match corner {
+        Corner::TopLeft | Corner::TopRight => container.align_top(Length::Fill),
+        Corner::BottomLeft | Corner::BottomRight => container.align_bottom(Length::Fill),
+    }
+}
+
 #[derive(Clone)]
 pub struct ReloaderSettings {
     /// The target directory for the build command, default: target/reload
     pub target_dir: String,
-    /// The directory where the compiled dynamic library is located, default: target/reload/debug
+    /// The directory where the compiled dynamic library is located, default: target/reload/hot
     pub lib_dir: String,
     /// Default is true, if this is set to false, you need to initiate the cargo watch command youself
     /// and make the lib accessible in the supplied `lib_dir`
     pub compile_in_reloader: bool,
     /// The time between each check for a new dynamic library file, default is 25ms
     pub file_watch_debounce: Duration,
+    /// Signals that a new build of the library is ready, see
+    /// [`ReloadTransport`]. `None` watches the library file with
+    /// `file_watch_debounce`. Default: `None`
+    pub transport: Option<Arc<dyn ReloadTransport>>,
+    /// Builds the library when `compile_in_reloader` is set, see [`Compiler`].
+    /// Default: [`Cargo`]
+    pub compiler: Arc<dyn Compiler>,
     /// The directory to watch for changes before recompiling, None means it will watch
     /// the UI crate root, default: None
     pub watch_dir: Option<PathBuf>,
+    /// Files and directories outside of the watch directory that rebuild the
+    /// library when they change, relative to the UI crate's workspace root.
+    /// Inputs of the last build inside the workspace, e.g. files read with
+    /// `include_str!` or listed by a build script's `rerun-if-changed`, are
+    /// watched without being listed. Default: empty
+    pub extra_watch_paths: Vec<PathBuf>,
+    /// Manifest of the UI crate when it is not a member of the host's
+    /// workspace, e.g. a path dependency checked out from another repository
+    /// with its own workspace and target directory. The library is built in
+    /// that workspace, a relative `target_dir` still resolves against the
+    /// host's. `None` finds the UI crate among the host's workspace members
+    /// and path dependencies. Default: `None`
+    pub ui_manifest_path: Option<PathBuf>,
     /// Maximum time to wait for in-flight async streams to complete during a
     /// hot reload before dropping them. Default: 5 seconds.
     pub drain_timeout: Duration,
     /// Optional cargo feature to enable when compiling the cdylib.
     /// When set, `--features <feature>` is appended to the build command.
     pub feature: Option<String>,
+    /// Show a table of per-function call counts and timings and of the
+    /// running subscriptions in the corner of the window, see
+    /// [`metrics`](crate::metrics). Default: false
+    pub show_metrics: bool,
+    /// Time every message of the program from when it is produced until
+    /// `update` returned, split into the wait for `update`, the body of
+    /// `update` and the hot boundary around it, see
+    /// [`Metrics::message_latency`](crate::Metrics). Default: false
+    pub trace_message_latency: bool,
+    /// Retired libraries are kept loaded after their worker has drained, so
+    /// code still referenced from them (e.g. leaked closures) stays valid.
+    /// Once their combined file size exceeds this limit, the oldest ones are
+    /// unloaded. `None` keeps every generation loaded. Default: `Some(0)`,
+    /// unload as soon as the drain has finished
+    pub max_retained_bytes: Option<u64>,
+    /// Palette of the reloader's own UI (compile output, errors, metrics),
+    /// independent of the program's theme. Default: `None`, the dark palette
+    pub overlay_palette: Option<Palette>,
+    /// Where the error overlay is drawn. Default: [`OverlayPlacement::Top`]
+    pub overlay_placement: OverlayPlacement,
+    /// Draw the reloader's UI into the window. Without it nothing but the
+    /// program's view is drawn: the window stays empty while a library is
+    /// swapped or can't be called, and the reloader's status is only logged
+    /// and shown in the window title, e.g. `[Reloading]`. Default: true
+    pub overlay: bool,
+    /// Link the library with `mold` or `lld` if one of them is installed.
+    /// Only has an effect on Linux, and replaces `rustflags` from the cargo
+    /// config for the reload build. Default: false
+    pub fast_linker: bool,
+    /// Link iced and `hot_ice` into the library dynamically from a generated
+    /// `hot_ice_prelink` dylib, so rebuilds only link the ui crate. The
+    /// crate is generated in the workspace root on the first build, see the
+    /// log for how to add it to the ui crate. Not supported on Windows.
+    /// Default: false
+    pub prelink: bool,
+    /// Which copies of the library are deleted from `lib_dir`, see
+    /// [`ArtifactCleanup`].
+    pub artifact_cleanup: ArtifactCleanup,
+    /// Hand the `#[hot_state]` state to the new library without serializing
+    /// it when both libraries report the same [`TypeHash`](crate::TypeHash)
+    /// of the state, were built by the same compiler and use
+    /// `hot_ice::use_host_allocator!()`. States that do not derive
+    /// `TypeHash`, or hold references into the library, are always
+    /// serialized. Default: false
+    pub state_handoff: bool,
+    /// Frame the serialized `#[hot_state]` state with a header holding its
+    /// length, a crc32 and the hash of its type, so a damaged or stale buffer
+    /// resets the state instead of being deserialized. Without it the state
+    /// is passed as plain JSON. Default: true
+    pub checked_state: bool,
+    /// Keep the program's messages that arrive while a library is being
+    /// swapped, e.g. typed characters, and hand them to the new library's
+    /// `update` in order. Without it they are dropped. Only messages of the
+    /// binary and of types deriving [`HotMessage`](crate::HotMessage) are
+    /// kept, those are serialized if the new library edited their type;
+    /// others made by the replaced library are dropped. At most 1024
+    /// messages are kept. Default: true
+    pub buffer_messages_during_reload: bool,
+    /// What the overlay shows, on the scale of `RUST_LOG`: `Off` hides it,
+    /// `Error` shows functions that failed, `Warn` and above also functions
+    /// that fell back to their static version. The reloader's logs are
+    /// filtered by the `hot_ice::reload`, `hot_ice::worker` and
+    /// `hot_ice::dispatch` targets instead. Default: `LevelFilter::Error`
+    pub overlay_verbosity: LevelFilter,
+    /// Keep the window's size, position and maximized state across restarts,
+    /// saved to `{target_dir}/window-{lib_name}.json` whenever it changes.
+    /// The state of the program is not affected. Default: false
+    pub persist_window: bool,
+    /// Show the progress of a build in the window title, as the progress bar
+    /// of the taskbar button on Windows and as the dock badge on macOS, and
+    /// request attention when the build finished: a dock bounce on macOS, a
+    /// flashing taskbar button on Windows. A failed build turns the progress
+    /// bar red or the badge into `!` until the next one starts.
+    /// Default: true
+    pub taskbar_progress: bool,
+    /// Drop the state of the program's widgets, like scroll offsets and text
+    /// cursors, whenever a new library is loaded, for edits that change the
+    /// layout so much the old state no longer fits. Default: false
+    pub rekey_widgets_on_reload: bool,
+    /// Scroll the program's scrollables back to where they were and focus
+    /// the widget that had focus once a reload completed, the reloading
+    /// screen drops the state of the program's widgets. Widgets are matched
+    /// by their `Id`, or by their position if they have none. Ignored with
+    /// `rekey_widgets_on_reload`. Default: true
+    pub restore_widget_state: bool,
+    /// Limits the actions of the worker's tasks and subscriptions waiting for
+    /// the event loop, so a runaway stream cannot exhaust memory, see
+    /// [`WorkerQueue`].
+    pub worker_queue: WorkerQueue,
+    /// Threads polling the library's tasks and subscriptions, all in the
+    /// library's executor context. Streams are spread over them round-robin,
+    /// so a CPU-heavy stream only holds up the streams on its own thread.
+    /// Default: 1
+    pub worker_threads: usize,
+    /// Where the status panel's "Record messages" toggle writes the
+    /// program's messages, relative to the working directory. Replay them
+    /// in tests with [`replay_fixture`](crate::testing::replay_fixture).
+    /// Default: `tests/fixtures/messages.ron`
+    pub message_fixture: PathBuf,
+    /// How long a new library waits to be swapped in while the user
+    /// interacts with the window: a mouse button or finger is down, the
+    /// input method is composing text or the program holds a
+    /// [`defer_reload_guard`](crate::defer_reload_guard). `Duration::ZERO`
+    /// reloads right away. Default: 10 seconds
+    pub max_reload_deferral: Duration,
+    /// Libraries listed in the overlay's library history, where an older
+    /// one can be activated again. Their copies are only kept with
+    /// [`ArtifactCleanup::keep_retired`]. Default: 10
+    pub generation_history: usize,
+}
+
+impl ReloaderSettings {
+    /// The UI crate named `lib_name` and the workspace it is built in.
+    pub(crate) fn ui_crate(&self, lib_name: &str) -> UiCrate {
+        UiCrate::locate(self.ui_manifest_path.as_deref(), lib_name)
+    }
+
+    /// The transport set, or a watch of the library file.
+    pub(crate) fn transport(&self) -> Arc<dyn ReloadTransport> {
+        self.transport.clone().unwrap_or_else(|| {
+            Arc::new(FileWatch {
+                debounce: self.file_watch_debounce,
+            })
+        })
+    }
 }
 
 impl Default for ReloaderSettings {
@@ -133,9 +415,35 @@ impl Default for ReloaderSettings {
             lib_dir: DEFAULT_LIB_DIR.to_string(),
             compile_in_reloader: true,
             file_watch_debounce: Duration::from_millis(25),
+            transport: None,
+            compiler: Arc::new(Cargo),
             watch_dir: None,
+            extra_watch_paths: Vec::new(),
+            ui_manifest_path: None,
             drain_timeout: Duration::from_secs(5),
             feature: None,
+            show_metrics: false,
+            trace_message_latency: false,
+            max_retained_bytes: Some(0),
+            overlay_palette: None,
+            overlay_placement: OverlayPlacement::Top,
+            overlay: true,
+            fast_linker: false,
+            prelink: false,
+            artifact_cleanup: ArtifactCleanup::default(),
+            state_handoff: false,
+            checked_state: true,
+            buffer_messages_during_reload: true,
+            overlay_verbosity: LevelFilter::Error,
+            persist_window: false,
+            taskbar_progress: true,
+            rekey_widgets_on_reload: false,
+            restore_widget_state: true,
+            worker_queue: WorkerQueue::default(),
+            worker_threads: 1,
+            message_fixture: PathBuf::from("tests/fixtures/messages.ron"),
+            max_reload_deferral: Duration::from_secs(10),
+            generation_history: 10,
         }
     }
 }
@@ -147,10 +455,10 @@ where
 {
     program: P,
     reloader_settings: ReloaderSettings,
-    settings: Settings,
-    window_settings: window::Settings,
     lib_name: &'static str,
     fonts: Vec<Cow<'static, [u8]>>,
+    on_function_state_change: Option<FunctionStateCallback>,
+    reload_placeholder: Option<ReloadPlaceholder<P::Renderer>>,
 }
 
 impl<P> Reload<P>
@@ -161,18 +469,39 @@ where
     pub fn new(
         program: P,
         reloader_settings: ReloaderSettings,
-        settings: Settings,
-        window_settings: window::Settings,
         lib_name: &'static str,
-        fonts: Vec<Cow<'static, [u8]>>,
+        on_function_state_change: Option<FunctionStateCallback>,
     ) -> Self {
+        let fonts = program.settings().fonts;
         Self {
             program,
             reloader_settings,
-            settings,
-            window_settings,
             lib_name,
             fonts,
+            on_function_state_change,
+            reload_placeholder: None,
+        }
+    }
+
+    /// Sets the view shown while a new library is swapped in.
+    pub(crate) fn set_reload_placeholder(
+        &mut self,
+        reload_placeholder: Option<ReloadPlaceholder<P::Renderer>>,
+    ) {
+        self.reload_placeholder = reload_placeholder;
+    }
+
+    /// Turns [`ReloaderSettings::persist_window`] off. Every program of a
+    /// daemon sees the events of all windows, so none can tell its own
+    /// window's geometry apart.
+    pub(crate) fn disable_window_persistence(&mut self) {
+        if self.reloader_settings.persist_window {
+            log::warn!(
+                target: RELOAD,
+                "persist_window is not supported in a daemon, ignoring it for {}",
+                self.lib_name
+            );
+            self.reloader_settings.persist_window = false;
         }
     }
 }
@@ -193,12 +522,15 @@ where
     }
 
     fn boot(&self) -> (Self::State, Task<Self::Message>) {
-        Reloader::new(
+        let (mut reloader, task) = Reloader::new(
             &self.program,
             &self.reloader_settings,
             &self.lib_name,
             self.fonts.clone(),
-        )
+        );
+        reloader.on_function_state_change = self.on_function_state_change.clone();
+        reloader.reload_placeholder = self.reload_placeholder.clone();
+        (reloader, task)
     }
 
     fn update(&self, state: &mut Self::State, message: Self::Message) -> Task<Self::Message> {
@@ -214,11 +546,18 @@ where
     }
 
     fn settings(&self) -> Settings {
-        self.settings.clone()
+        self.program.settings()
     }
 
     fn window(&self) -> Option<window::Settings> {
-        Some(self.window_settings.clone())
+        let mut settings = self.program.window()?;
+        if self.reloader_settings.persist_window {
+            let path = WindowGeometry::path(&self.reloader_settings.target_dir, self.lib_name);
+            if let Some(geometry) = WindowGeometry::load(&path) {
+                geometry.restore(&mut settings);
+            }
+        }
+        Some(settings)
     }
 
     fn title(&self, state: &Self::State, window: window::Id) -> String {
@@ -252,17 +591,69 @@ where
 {
     CompilationComplete,
     AboutToReload,
+    /// Checks again whether a deferred reload may start.
+    DeferredReload,
+    /// An event that starts or ends an interaction a reload waits for, see
+    /// [`reload_deferral`].
+    Interaction(Event),
+    /// The program's widgets were captured, the reloading screen can be
+    /// shown.
+    WidgetsCaptured(WidgetSnapshot),
     ReloadComplete(Option<SharedRetired>),
     SendReadySignal,
+    /// The state was serialized on a background thread, the library can be
+    /// swapped.
+    StateSerialized,
+    ReadySignalTimeout,
     Error(ReloaderError),
-    ErrorShown(HotFunction),
-    AutoDismissError(HotFunction),
-    DismissError(HotFunction),
-    ToggleErrorExpand(HotFunction),
+    ErrorShown(u64),
+    AutoDismissError(u64),
+    DismissError(u64),
+    ToggleErrorExpand(u64),
+    ToggleErrorHistory,
+    ClearErrorHistory,
+    StatusPanelHovered(bool),
+    ToggleStatusPanel,
+    /// Start or stop writing the program's messages to `message_fixture`.
+    ToggleRecording,
+    ToggleGenerationHistory,
+    TogglePaletteEditor,
+    /// An edit of the palette that overrides the program's.
+    PaletteEdited(PaletteEdit),
+    /// Load an older library of the history again.
+    ActivateGeneration(usize),
+    /// Show the view of the previous library next to the current one.
+    ToggleCompare,
+    /// A message of the previous library's view while comparing, dropped:
+    /// the state is only shown to it.
+    ComparedMessage,
+    /// A watcher stopped, no new builds are loaded until it is restarted.
+    WatcherDied(Watcher, String),
+    RestartWatchers,
+    BuildMissingArtifact,
+    MissingArtifactBuilt,
     AnimationTick(Instant),
     CompilationOutput(String),
     ClearCompilationOutput,
+    BuildEvent(BuildEvent),
+    AssetsChanged,
+    /// A task of [`hot_ice::task`](crate::task) returned by the program.
+    Requested(Request),
+    /// The worker of a retired library did not stop, see [`stuck_worker`].
+    WorkerStuck(StuckWorker),
+    /// No async `update` holds the state any more, see [`lent_state`].
+    StateReturned,
+    WindowChanged(window::Id, WindowChange),
+    SaveWindowGeometry(window::Id),
+    WindowGeometryChecked(bool),
+    /// An event no widget captured, for the program's `on_event`.
+    Event(Event, window::Id),
+    /// A call of one of the program's [`HotExtension`](crate::HotExtension)s.
+    Extension(ExtensionCall<P::State>),
     AppMessage(MessageSource<P::Message>),
+    /// A message of the program and when it was produced, while
+    /// `trace_message_latency` is set.
+    TracedApp(MessageSource<P::Message>, MessageOrigin, Instant),
 }
 
 impl<P> Clone for Message<P>
@@ -273,18 +664,51 @@ where
     fn clone(&self) -> Self {
         match &self {
             Self::AppMessage(message) => Self::AppMessage(message.clone()),
+            Self::TracedApp(message, origin, produced) => {
+                Self::TracedApp(message.clone(), *origin, *produced)
+            }
             Self::SendReadySignal => Self::SendReadySignal,
+            Self::StateSerialized => Self::StateSerialized,
+            Self::ReadySignalTimeout => Self::ReadySignalTimeout,
             Self::AboutToReload => Self::AboutToReload,
+            Self::DeferredReload => Self::DeferredReload,
+            Self::Interaction(event) => Self::Interaction(event.clone()),
+            Self::WidgetsCaptured(snapshot) => Self::WidgetsCaptured(snapshot.clone()),
             Self::ReloadComplete(r) => Self::ReloadComplete(r.clone()),
             Self::CompilationComplete => Self::CompilationComplete,
             Self::Error(error) => Self::Error(error.clone()),
-            Self::ErrorShown(func) => Self::ErrorShown(*func),
-            Self::AutoDismissError(func) => Self::AutoDismissError(*func),
-            Self::DismissError(func) => Self::DismissError(*func),
-            Self::ToggleErrorExpand(func) => Self::ToggleErrorExpand(*func),
+            Self::ErrorShown(id) => Self::ErrorShown(*id),
+            Self::AutoDismissError(id) => Self::AutoDismissError(*id),
+            Self::DismissError(id) => Self::DismissError(*id),
+            Self::ToggleErrorExpand(id) => Self::ToggleErrorExpand(*id),
+            Self::ToggleErrorHistory => Self::ToggleErrorHistory,
+            Self::ClearErrorHistory => Self::ClearErrorHistory,
+            Self::StatusPanelHovered(hovered) => Self::StatusPanelHovered(*hovered),
+            Self::ToggleStatusPanel => Self::ToggleStatusPanel,
+            Self::ToggleRecording => Self::ToggleRecording,
+            Self::ToggleGenerationHistory => Self::ToggleGenerationHistory,
+            Self::TogglePaletteEditor => Self::TogglePaletteEditor,
+            Self::PaletteEdited(edit) => Self::PaletteEdited(edit.clone()),
+            Self::ActivateGeneration(generation) => Self::ActivateGeneration(*generation),
+            Self::ToggleCompare => Self::ToggleCompare,
+            Self::ComparedMessage => Self::ComparedMessage,
+            Self::WatcherDied(watcher, reason) => Self::WatcherDied(*watcher, reason.clone()),
+            Self::RestartWatchers => Self::RestartWatchers,
+            Self::BuildMissingArtifact => Self::BuildMissingArtifact,
+            Self::MissingArtifactBuilt => Self::MissingArtifactBuilt,
             Self::AnimationTick(t) => Self::AnimationTick(*t),
             Self::CompilationOutput(s) => Self::CompilationOutput(s.clone()),
             Self::ClearCompilationOutput => Self::ClearCompilationOutput,
+            Self::BuildEvent(event) => Self::BuildEvent(*event),
+            Self::AssetsChanged => Self::AssetsChanged,
+            Self::Requested(request) => Self::Requested(*request),
+            Self::WorkerStuck(stuck) => Self::WorkerStuck(stuck.clone()),
+            Self::StateReturned => Self::StateReturned,
+            Self::WindowChanged(id, change) => Self::WindowChanged(*id, *change),
+            Self::SaveWindowGeometry(id) => Self::SaveWindowGeometry(*id),
+            Self::WindowGeometryChecked(maximized) => Self::WindowGeometryChecked(*maximized),
+            Self::Event(event, id) => Self::Event(event.clone(), *id),
+            Self::Extension(call) => Self::Extension(call.clone()),
         }
     }
 }
@@ -292,26 +716,62 @@ where
 impl<P: HotProgram> Debug for Message<P> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::AppMessage(message) => message.fmt(f),
+            Self::AppMessage(message) | Self::TracedApp(message, ..) => message.fmt(f),
             Self::SendReadySignal => write!(f, "SendReadySignal"),
+            Self::StateSerialized => write!(f, "StateSerialized"),
+            Self::ReadySignalTimeout => write!(f, "ReadySignalTimeout"),
             Self::AboutToReload => write!(f, "AboutToReload"),
+            Self::DeferredReload => write!(f, "DeferredReload"),
+            Self::Interaction(event) => write!(f, "Interaction({:?})", event),
+            Self::WidgetsCaptured(_) => write!(f, "WidgetsCaptured"),
             Self::ReloadComplete(_) => write!(f, "ReloadComplete"),
             Self::CompilationComplete => write!(f, "CompilationComplete"),
             Self::Error(error) => write!(f, "{}", error),
-            Self::ErrorShown(func) => write!(f, "ErrorShown({})", func),
-            Self::AutoDismissError(func) => write!(f, "AutoDismissError({})", func),
-            Self::DismissError(func) => write!(f, "DismissError({})", func),
-            Self::ToggleErrorExpand(func) => write!(f, "ToggleErrorExpand({})", func),
+            Self::ErrorShown(id) => write!(f, "ErrorShown({})", id),
+            Self::AutoDismissError(id) => write!(f, "AutoDismissError({})", id),
+            Self::DismissError(id) => write!(f, "DismissError({})", id),
+            Self::ToggleErrorExpand(id) => write!(f, "ToggleErrorExpand({})", id),
+            Self::ToggleErrorHistory => write!(f, "ToggleErrorHistory"),
+            Self::ClearErrorHistory => write!(f, "ClearErrorHistory"),
+            Self::StatusPanelHovered(hovered) => write!(f, "StatusPanelHovered({})", hovered),
+            Self::ToggleStatusPanel => write!(f, "ToggleStatusPanel"),
+            Self::ToggleRecording => write!(f, "ToggleRecording"),
+            Self::ToggleGenerationHistory => write!(f, "ToggleGenerationHistory"),
+            Self::TogglePaletteEditor => write!(f, "TogglePaletteEditor"),
+            Self::PaletteEdited(edit) => write!(f, "PaletteEdited({:?})", edit),
+            Self::ActivateGeneration(generation) => {
+                write!(f, "ActivateGeneration({})", generation)
+            }
+            Self::ToggleCompare => write!(f, "ToggleCompare"),
+            Self::ComparedMessage => write!(f, "ComparedMessage"),
+            Self::WatcherDied(watcher, reason) => {
+                write!(f, "WatcherDied({:?}, {})", watcher, reason)
+            }
+            Self::RestartWatchers => write!(f, "RestartWatchers"),
+            Self::BuildMissingArtifact => write!(f, "BuildMissingArtifact"),
+            Self::MissingArtifactBuilt => write!(f, "MissingArtifactBuilt"),
             Self::AnimationTick(_) => write!(f, "AnimationTick"),
             Self::CompilationOutput(line) => write!(f, "CompilationOutput({})", line),
             Self::ClearCompilationOutput => write!(f, "ClearCompilationOutput"),
+            Self::BuildEvent(event) => write!(f, "BuildEvent({:?})", event),
+            Self::AssetsChanged => write!(f, "AssetsChanged"),
+            Self::Requested(request) => write!(f, "Requested({:?})", request),
+            Self::WorkerStuck(stuck) => write!(f, "WorkerStuck({:?})", stuck),
+            Self::StateReturned => write!(f, "StateReturned"),
+            Self::WindowChanged(id, change) => write!(f, "WindowChanged({:?}, {:?})", id, change),
+            Self::SaveWindowGeometry(id) => write!(f, "SaveWindowGeometry({:?})", id),
+            Self::WindowGeometryChecked(maximized) => {
+                write!(f, "WindowGeometryChecked({})", maximized)
+            }
+            Self::Event(event, id) => write!(f, "Event({:?}, {:?})", event, id),
+            Self::Extension(_) => write!(f, "Extension"),
         }
     }
 }
 
 pub struct ReadyToReload;
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum FunctionState {
     None,
     Static,
@@ -320,29 +780,54 @@ pub enum FunctionState {
     Error(String),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+impl FunctionState {
+    /// Label and color of the state in the status panel.
+    fn describe(&self) -> (&'static str, Color) {
+        match self {
+            FunctionState::None => ("none", Color::from_rgba(1.0, 1.0, 1.0, 0.5)),
+            FunctionState::Static => ("static", Color::WHITE),
+            FunctionState::Hot => ("hot", STATUS_GREEN),
+            FunctionState::FallBackStatic(_) => ("fallback", STATUS_ORANGE),
+            FunctionState::Error(_) => ("error", STATUS_RED),
+        }
+    }
+}
+
+const STATUS_GREEN: Color = Color::from_rgb8(34, 197, 94);
+const STATUS_ORANGE: Color = Color::from_rgb8(249, 115, 22);
+const STATUS_RED: Color = Color::from_rgb8(225, 29, 72);
+const STATUS_BLUE: Color = Color::from_rgb8(59, 130, 246);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum HotFunction {
     Update,
+    View,
     Subscription,
     Theme,
     Style,
     Title,
     ScaleFactor,
+    Setup,
+    OnEvent,
+    /// A [`HotExtension`](crate::HotExtension) of the application, by the
+    /// name of its function.
+    Extension(&'static str),
 }
 
 impl std::fmt::Display for HotFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            HotFunction::Extension(name) => write!(f, "{}", name),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
-struct ErrorEntry {
-    message: String,
-    handle: Option<task::Handle>,
-    sensor_key: u16,
-    expanded: bool,
-    animation: Animation<bool>,
-    dismissing: bool,
+// By name, the functions are keys of the metrics snapshot.
+impl Serialize for HotFunction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
 
 /// Bridge channel capacity from the main-thread event broadcaster to the
@@ -361,6 +846,8 @@ const EVENT_BRIDGE_CAPACITY: usize = 100;
 ///    recipe running on the worker thread.
 struct SubscriptionGuardStream<T> {
     _cancel_tx: futures::channel::oneshot::Sender<()>,
+    /// Lists the subscription in the metrics table while it runs.
+    _tracked: subscriptions::Tracked,
     event_input: iced_subscription::EventStream,
     event_bridge_tx: futures::channel::mpsc::Sender<iced_subscription::Event>,
     _phantom: std::marker::PhantomData<T>,
@@ -435,6 +922,11 @@ impl<M: Send + 'static> Recipe for WorkerRecipe<M> {
         let inner = self.inner;
         let worker = self.worker;
 
+        let mut hasher = Hasher::default();
+        inner.hash(&mut hasher);
+        let tracked = subscriptions::track(std::hash::Hasher::finish(&hasher));
+        let messages = tracked.counter();
+
         // Create a bridge channel to forward events from the main thread
         // (where the tracker broadcasts) to the worker thread (where the
         // inner recipe consumes them). The SubscriptionGuardStream polls the
@@ -481,10 +973,16 @@ impl<M: Send + 'static> Recipe for WorkerRecipe<M> {
         let (cancel_tx, cancel_rx) = futures::channel::oneshot::channel::<()>();
 
         let factory: Box<dyn FnOnce() -> ErasedStream + Send + 'static> = Box::new(move || {
-            log::debug!("[sub] RunRecipeFactory: calling inner.stream() in worker context");
+            log::debug!(
+                target: WORKER,
+                "RunRecipeFactory: calling inner.stream() in worker context"
+            );
             let app_stream = inner.into_stream(worker_input);
-            log::debug!("[sub] RunRecipeFactory: inner.stream() returned");
-            Box::pin(app_stream.map(|msg| Box::into_raw(Box::new(Action::Output(msg))) as *mut ()))
+            log::debug!(target: WORKER, "RunRecipeFactory: inner.stream() returned");
+            Box::pin(app_stream.map(move |msg| {
+                messages.fetch_add(1, Ordering::Relaxed);
+                Box::into_raw(Box::new(Action::Output(msg))) as *mut ()
+            }))
         });
 
         worker.run_recipe_factory(factory, cancel_rx);
@@ -495,6 +993,7 @@ impl<M: Send + 'static> Recipe for WorkerRecipe<M> {
         // 3. Never yields items itself
         Box::pin(SubscriptionGuardStream {
             _cancel_tx: cancel_tx,
+            _tracked: tracked,
             event_input: input,
             event_bridge_tx: bridge_tx,
             _phantom: std::marker::PhantomData::<M>,
@@ -514,31 +1013,257 @@ enum ReloaderState {
 pub enum ReloaderError {
     #[error("Failed to build command {0}")]
     FailedToBuildCommand(String),
+    #[error("The library was built with incompatible versions and was not loaded: {0}")]
+    IncompatibleLibrary(String),
 }
 
 type UpdateChannel = (MTx<ReadyToReload>, MAsyncRx<ReadyToReload>);
 
+/// Called with a function and its old and new state, see
+/// `HotIce::on_function_state_change`.
+pub(crate) type FunctionStateCallback = Arc<dyn Fn(HotFunction, &FunctionState, &FunctionState)>;
+
+/// Draws the window while a library is swapped, see
+/// `HotIce::reload_placeholder`.
+pub(crate) type ReloadPlaceholder<Renderer> =
+    Arc<dyn Fn(&Theme) -> Element<'static, Infallible, Theme, Renderer>>;
+
+/// The fields of the previous library's state, while the loaded library
+/// moves the unchanged ones out of it.
+struct FieldTransfer {
+    previous: Vec<StateField>,
+    /// Names of the fields moved, the previous library drops the others.
+    moved: Vec<u64>,
+    serialized: usize,
+    /// The previous library, which drops what is left of its state.
+    library: Arc<Library>,
+}
+
+/// Serializes the state with the functions of a library, run on the thread
+/// started by [`Reloader::spawn_serializer`].
+type SerializeJob<S> =
+    Box<dyn FnOnce(&S, *mut *mut u8, *mut usize) -> Result<(), HotIceError> + Send>;
+
+/// The state of the program, sent to the thread serializing it.
+struct StatePtr<S>(*const S);
+
+// SAFETY: see `Reloader::spawn_serializer`.
+unsafe impl<S> Send for StatePtr<S> {}
+
+/// The buffer the previous library serialized the state into on a
+/// background thread.
+struct SerializedState {
+    ptr: *mut u8,
+    len: usize,
+    result: Result<(), HotIceError>,
+    elapsed: Duration,
+}
+
+// SAFETY: the buffer is owned by the reloader once the thread finished.
+unsafe impl Send for SerializedState {}
+
+/// The previous library, kept loaded while its view is compared with the
+/// one of the current library.
+struct ComparedLibrary {
+    handle: LibraryHandle,
+    generation: Option<usize>,
+}
+
+/// A thread the reloader relies on for new builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watcher {
+    /// The transport announcing new builds of the library.
+    Transport,
+    /// `cargo watch`, building the library when its sources change.
+    Compiler,
+}
+
 pub struct Reloader<P: HotProgram + 'static> {
-    state: P::State,
+    /// Boxed so the serializer thread's reference survives moves of the
+    /// reloader, see [`Reloader::spawn_serializer`]. Leaked instead of
+    /// dropped if an async `update` on a stuck worker still borrows it.
+    state: ManuallyDrop<Box<P::State>>,
     serialized_state_ptr: *mut u8,
     serialized_state_len: usize,
+    /// Time the previous library took to serialize the state.
+    serialize_time: Duration,
+    /// The previous library serializing the state on a background thread,
+    /// see [`Reloader::begin_serialize_state`].
+    serializing: Option<JoinHandle<SerializedState>>,
+    /// State type hash of the previous library while its state waits to be
+    /// handed to the next one without serializing it.
+    pending_handoff: Option<u64>,
+    /// The loaded library takes the state over without deserializing it.
+    adopt_state: bool,
+    /// The loaded library builds the state from the fields of the previous
+    /// one, see [`Reloader::serialize_changed_fields`].
+    field_transfer: Option<FieldTransfer>,
+    /// Messages of the program received during a reload, replayed once the
+    /// new library is ready.
+    buffered_messages: VecDeque<MessageSource<P::Message>>,
     reloader_state: ReloaderState,
+    /// Set while an async `update` runs on the worker with the state, see
+    /// [`lent_state`].
+    state_lent: bool,
+    /// A new library waits for the state to be returned.
+    reload_after_update: bool,
     lib_reloader: Option<Arc<Mutex<LibReloader>>>,
+    /// The library of `lib_reloader`, read by hot functions without locking.
+    library: Option<LibraryHandle>,
     worker: Option<Arc<CdylibWorker<Message<P>>>>,
     pending_drain: Option<DrainHandle<Message<P>>>,
     reloader_settings: ReloaderSettings,
     lib_name: &'static str,
     reloading_sensor_key: u16,
+    /// Whether the library change currently being handled was answered with
+    /// [`ReadyToReload`] already.
+    ready_signal_sent: bool,
+    /// The number of library changes waiting for the program's widgets to
+    /// be captured before the reloading screen replaces them.
+    capturing_widgets: Option<u16>,
+    /// The program's widgets before the reloading screen replaced them.
+    widget_snapshot: Option<WidgetSnapshot>,
     update_fn_state: FunctionState,
-    subscription_fn_state: Mutex<FunctionState>,
-    theme_fn_state: Mutex<FunctionState>,
-    style_fn_state: Mutex<FunctionState>,
-    scale_factor_fn_state: Mutex<FunctionState>,
-    title_fn_state: Mutex<FunctionState>,
+    setup_fn_state: FunctionState,
+    on_event_fn_state: FunctionState,
+    /// The extensions called so far, in the order of their first call.
+    extension_fn_states: Vec<(HotFunction, FunctionState)>,
+    /// Set from `&self` while drawing, so they swap without locking.
+    view_fn_state: ArcSwap<FunctionState>,
+    subscription_fn_state: ArcSwap<FunctionState>,
+    theme_fn_state: ArcSwap<FunctionState>,
+    style_fn_state: ArcSwap<FunctionState>,
+    scale_factor_fn_state: ArcSwap<FunctionState>,
+    title_fn_state: ArcSwap<FunctionState>,
+    on_function_state_change: Option<FunctionStateCallback>,
+    reload_placeholder: Option<ReloadPlaceholder<P::Renderer>>,
     update_channel: UpdateChannel,
     loaded_fonts: Vec<Cow<'static, [u8]>>,
-    active_errors: Mutex<HashMap<HotFunction, ErrorEntry>>,
+    active_errors: Mutex<ErrorToasts>,
+    show_error_history: bool,
     compilation_output: Vec<String>,
+    retained_libraries: Arc<Mutex<VecDeque<RetiredLibrary>>>,
+    incompatible_library: bool,
+    last_compatible_library: Option<RetiredLibrary>,
+    overlay_theme: Theme,
+    /// Changes with every input of the overlay, see [`OverlayCache`].
+    overlay_revision: AtomicU64,
+    overlay_cache: OverlayCache<Message<P>, Theme, P::Renderer>,
+    status_panel_hovered: bool,
+    status_panel_pinned: bool,
+    /// Set while the program's messages are recorded to a fixture.
+    recorder: Option<MessageRecorder>,
+    /// The libraries loaded so far, as of the last reload.
+    generations: Vec<GenerationRecord>,
+    show_generation_history: bool,
+    palette_editor: PaletteEditor,
+    /// The previous library while its view is compared with the current
+    /// one, or why it can't be.
+    compare: Option<Result<ComparedLibrary, String>>,
+    /// A library no longer compared, kept loaded until the widgets of its
+    /// view were dropped by the next view.
+    compare_released: Option<ComparedLibrary>,
+    interaction: Interaction,
+    /// Since when a new library waits for an interaction to end.
+    deferred_reload: Option<Instant>,
+    /// Command that builds the library, set while no library is loaded.
+    missing_artifact: Option<String>,
+    building_missing_artifact: bool,
+    /// Command that rebuilds the library, set while the library loaded on
+    /// startup is older than its sources.
+    stale_artifact: Option<String>,
+    /// The watchers that stopped, with the reason.
+    dead_watchers: Vec<(Watcher, String)>,
+    /// The window's geometry, tracked while `persist_window` is set.
+    window_geometry: Option<WindowGeometry>,
+    /// Delays saving the geometry until the window stopped changing.
+    window_geometry_save: Option<task::Handle>,
+    build_progress: BuildProgress,
+    cleanup_threads: Vec<JoinHandle<()>>,
+    /// Held by every task of the current library generation that runs on
+    /// the host executor because the library starts no worker. The
+    /// generation is only unloaded once all of them have finished.
+    host_task_pin: Arc<()>,
+}
+
+impl<P: HotProgram + 'static> Reloader<P> {
+    /// Tears the reloader down in dependency order when the application
+    /// exits: the worker is drained, pending drains and cleanup threads are
+    /// joined within the drain timeout and `cargo watch` is stopped. The
+    /// fields are dropped afterwards in declaration order: the program state
+    /// first, while every library it may reference is still loaded, then the
+    /// current and the retired libraries.
+    fn shutdown(&mut self) {
+        log::info!(target: RELOAD, "Shutting down the reloader");
+        hot_data::unfollow(self.lib_name);
+        let timeout = self.reloader_settings.drain_timeout;
+
+        if let Some(Err(err)) = self.recorder.take().map(MessageRecorder::finish) {
+            log::error!(target: RELOAD, "Failed to write the fixture: {err}");
+        }
+
+        let mut drains = Vec::new();
+        if let Some(worker) = self.worker.take() {
+            match Arc::try_unwrap(worker) {
+                Ok(worker) => drains.push(worker.begin_drain(timeout)),
+                Err(_) => log::warn!(target: WORKER, "Worker is still referenced, not draining it"),
+            }
+        }
+        drains.extend(self.pending_drain.take());
+        let mut stuck = false;
+        for drain in drains {
+            if let Err(worker) = drain.join_within(timeout + STOP_GRACE) {
+                log::error!(target: WORKER, "{worker}");
+                stuck = true;
+            }
+        }
+        if stuck {
+            // The worker still runs code of the libraries, they are leaked
+            // instead of being unloaded under it.
+            std::mem::forget(self.lib_reloader.take());
+            std::mem::forget(self.library.take());
+            std::mem::forget(self.last_compatible_library.take());
+            std::mem::forget(Arc::clone(&self.retained_libraries));
+        }
+        // The serializer reads the state, which is dropped after this.
+        if let Some(serializing) = self.serializing.take() {
+            let _ = serializing.join();
+        }
+
+        let deadline = Instant::now() + timeout;
+        while self.cleanup_threads.iter().any(|h| !h.is_finished()) {
+            if Instant::now() >= deadline {
+                log::warn!(
+                    target: RELOAD,
+                    "Cleanup threads did not finish within {timeout:?}, detaching them"
+                );
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        for handle in self.cleanup_threads.drain(..) {
+            if handle.is_finished() {
+                let _ = handle.join();
+            }
+        }
+
+        kill_cargo_watch();
+        log::logger().flush();
+    }
+}
+
+impl<P: HotProgram + 'static> Drop for Reloader<P> {
+    fn drop(&mut self) {
+        self.shutdown();
+        if self.state_lent && lent_state::is_lent() {
+            log::error!(
+                target: WORKER,
+                "An async update still holds the state, leaking it"
+            );
+            return;
+        }
+        unsafe { ManuallyDrop::drop(&mut self.state) }
+    }
 }
 
 impl<'a, P> Reloader<P>
@@ -553,86 +1278,314 @@ where
         fonts: Vec<Cow<'static, [u8]>>,
     ) -> (Self, Task<Message<P>>) {
         let (state, program_task) = program.boot();
+        fingerprint::check_host_panic_strategy();
+        hot_dispatch::connect::<P>();
+        hot_state::set_checked_state(reloader_settings.checked_state);
 
         let mut reloader = Self {
-            state,
+            state: ManuallyDrop::new(Box::new(state)),
             serialized_state_ptr: std::ptr::null_mut(),
             serialized_state_len: 0,
+            serialize_time: Duration::ZERO,
+            serializing: None,
+            pending_handoff: None,
+            adopt_state: false,
+            field_transfer: None,
+            buffered_messages: VecDeque::new(),
             reloader_state: ReloaderState::Compiling,
+            state_lent: false,
+            reload_after_update: false,
             lib_reloader: None,
+            library: None,
             worker: None,
             pending_drain: None,
             reloader_settings: reloader_settings.clone(),
             lib_name,
             reloading_sensor_key: 0,
+            ready_signal_sent: false,
+            capturing_widgets: None,
+            widget_snapshot: None,
             update_fn_state: FunctionState::Static,
-            subscription_fn_state: Mutex::new(FunctionState::Static),
-            theme_fn_state: Mutex::new(FunctionState::Static),
-            style_fn_state: Mutex::new(FunctionState::Static),
-            scale_factor_fn_state: Mutex::new(FunctionState::Static),
-            title_fn_state: Mutex::new(FunctionState::Static),
+            setup_fn_state: FunctionState::Static,
+            on_event_fn_state: FunctionState::None,
+            extension_fn_states: Vec::new(),
+            view_fn_state: ArcSwap::from_pointee(FunctionState::Static),
+            subscription_fn_state: ArcSwap::from_pointee(FunctionState::Static),
+            theme_fn_state: ArcSwap::from_pointee(FunctionState::Static),
+            style_fn_state: ArcSwap::from_pointee(FunctionState::Static),
+            scale_factor_fn_state: ArcSwap::from_pointee(FunctionState::Static),
+            title_fn_state: ArcSwap::from_pointee(FunctionState::Static),
+            on_function_state_change: None,
+            reload_placeholder: None,
             update_channel: mpmc::bounded_tx_blocking_rx_async(1),
             loaded_fonts: fonts,
-            active_errors: Mutex::new(HashMap::new()),
+            active_errors: Mutex::new(ErrorToasts::new()),
+            show_error_history: false,
             compilation_output: Vec::new(),
+            retained_libraries: Arc::new(Mutex::new(VecDeque::new())),
+            incompatible_library: false,
+            last_compatible_library: None,
+            overlay_theme: Theme::custom(
+                "reloader".to_string(),
+                reloader_settings.overlay_palette.unwrap_or(Palette::DARK),
+            ),
+            overlay_revision: AtomicU64::new(0),
+            overlay_cache: OverlayCache::new(),
+            status_panel_hovered: false,
+            status_panel_pinned: false,
+            recorder: None,
+            generations: Vec::new(),
+            show_generation_history: false,
+            palette_editor: PaletteEditor::default(),
+            compare: None,
+            compare_released: None,
+            interaction: Interaction::default(),
+            deferred_reload: None,
+            missing_artifact: None,
+            building_missing_artifact: false,
+            stale_artifact: None,
+            dead_watchers: Vec::new(),
+            window_geometry: reloader_settings.persist_window.then(|| {
+                WindowGeometry::load(&WindowGeometry::path(
+                    &reloader_settings.target_dir,
+                    lib_name,
+                ))
+                .unwrap_or_default()
+            }),
+            window_geometry_save: None,
+            build_progress: BuildProgress::default(),
+            cleanup_threads: Vec::new(),
+            host_task_pin: Arc::new(()),
         };
 
         let task = if reloader_settings.compile_in_reloader {
-            Task::stream(Self::build_library(
-                reloader.lib_name,
-                reloader_settings.target_dir.clone(),
-                reloader_settings.feature.clone(),
-            ))
+            Task::stream(Self::build_library(reloader.lib_name, reloader_settings))
         } else {
+            create_missing_lib_dir(&reloader.reloader_settings.lib_dir);
             let mut lib_reloader = LibReloader::new(
                 &reloader.reloader_settings.lib_dir,
                 reloader.lib_name,
-                Some(reloader.reloader_settings.file_watch_debounce),
+                &*reloader.reloader_settings.transport(),
                 None,
+                reloader.reloader_settings.artifact_cleanup.clone(),
+                reloader.reloader_settings.generation_history,
             )
             .expect("Unable to create LibReloader");
 
             let change_subscriber = lib_reloader.subscribe_to_file_changes();
+            reloader.library = Some(lib_reloader.handle());
+            hot_data::follow(reloader.lib_name, lib_reloader.handle());
             let lib_reloader = Arc::new(Mutex::new(lib_reloader));
             reloader.lib_reloader = Some(lib_reloader.clone());
 
-            reloader.sync_fonts_to_library();
-            reloader.start_worker_from_library();
-
-            reloader.reloader_state = ReloaderState::Ready;
+            let ui_crate = reloader.reloader_settings.ui_crate(reloader.lib_name);
+            reloader.prepare_loaded_library(false);
+            reloader.check_stale_artifact(&ui_crate);
             Task::stream(Self::listen_for_lib_changes(
                 lib_reloader,
                 reloader.update_channel.1.clone(),
                 change_subscriber,
+                ui_crate.workspace_root,
             ))
         };
 
-        (reloader, task.chain(program_task.map(Message::AppMessage)))
+        (
+            reloader,
+            Task::batch([
+                task.chain(program_task.map(Message::AppMessage)),
+                Task::stream(Self::listen_for_asset_changes()),
+                Task::stream(Self::listen_for_requests()),
+            ]),
+        )
     }
 
     pub fn update(&mut self, program: &P, message: Message<P>) -> Task<Message<P>> {
+        // Messages of the reloader itself may change anything the overlay
+        // shows, the program's own ones only through function states.
+        if !matches!(
+            message,
+            Message::AppMessage(_)
+                | Message::TracedApp(..)
+                | Message::WindowChanged(..)
+                | Message::SaveWindowGeometry(_)
+                | Message::Event(..)
+                | Message::Extension(_)
+                | Message::Interaction(_)
+                | Message::DeferredReload
+                | Message::ComparedMessage
+        ) {
+            self.invalidate_overlay();
+        }
+
         match message {
+            Message::TracedApp(message, origin, produced) => {
+                // Buffered messages and the ones dropped while no library is
+                // loaded are not traced.
+                let handled = self.program_ready();
+                let dispatched = Instant::now();
+                metrics::take_update_body();
+
+                let task = self.update(program, Message::AppMessage(message));
+
+                if handled {
+                    let total = dispatched.elapsed();
+                    let update = metrics::take_update_body().unwrap_or(total).min(total);
+                    metrics::record_latency(
+                        origin,
+                        dispatched.saturating_duration_since(produced),
+                        update,
+                        total - update,
+                    );
+                }
+                task
+            }
             Message::AppMessage(message) => {
+                // The library stays, the message is handled once the state
+                // is returned.
+                if self.state_lent {
+                    self.buffer_message(message);
+                    return Task::none();
+                }
+                if matches!(self.reloader_state, ReloaderState::Reloading(_))
+                    && self.reloader_settings.buffer_messages_during_reload
+                {
+                    if !survives_reload(&message) {
+                        log::warn!(
+                            target: RELOAD,
+                            "Dropping a message of the replaced library, its type does not \
+                             derive HotMessage: {message:?}"
+                        );
+                        return Task::none();
+                    }
+                    self.buffer_message(message);
+                    return Task::none();
+                }
                 if self.reloader_state != ReloaderState::Ready {
                     return Task::none();
                 }
 
-                match program.update(&mut self.state, message, self.lib_reloader.as_ref()) {
+                if let Some(recorder) = &mut self.recorder {
+                    let (MessageSource::Static(recorded) | MessageSource::Dynamic(recorded)) =
+                        &message;
+                    if let Err(err) = recorder.record(recorded) {
+                        log::error!(target: RELOAD, "Failed to record a message: {err}");
+                        self.recorder = None;
+                        self.invalidate_overlay();
+                    }
+                }
+
+                self.join_serializer();
+                let started = Instant::now();
+                let result = program.update(&mut self.state, message, self.library.as_ref());
+                metrics::record(HotFunction::Update, started, &result);
+
+                match result {
                     Ok((task, fn_state)) => {
-                        self.update_fn_state = fn_state;
+                        let old = std::mem::replace(&mut self.update_fn_state, fn_state);
+                        self.report_transition(HotFunction::Update, &old, &self.update_fn_state);
                         self.sync_error_state(HotFunction::Update, &self.update_fn_state);
-                        self.intercept_app_task(task.map(Message::AppMessage))
+                        let task = if self.reloader_settings.trace_message_latency {
+                            task.map(|message| {
+                                Message::TracedApp(message, MessageOrigin::Task, Instant::now())
+                            })
+                        } else {
+                            task.map(Message::AppMessage)
+                        };
+                        let returned = self.lend_state();
+                        Task::batch([self.intercept_app_task(task), returned])
                     }
                     Err(err) => {
-                        log::error!("update(): {}", err);
-                        self.update_fn_state = FunctionState::Error(err.to_string());
+                        log::error!(target: DISPATCH, "update(): {}", err);
+                        let old = std::mem::replace(
+                            &mut self.update_fn_state,
+                            FunctionState::Error(err.to_string()),
+                        );
+                        self.report_transition(HotFunction::Update, &old, &self.update_fn_state);
                         self.sync_error_state(HotFunction::Update, &self.update_fn_state);
                         Task::none()
                     }
                 }
             }
+            Message::Event(event, window) => {
+                // Dropped during a reload, the library that maps them is
+                // being replaced, and while an async update holds the state.
+                if !self.program_ready() {
+                    return Task::none();
+                }
+
+                let started = Instant::now();
+                let result = program.on_event(&self.state, event, window, self.library.as_ref());
+                metrics::record(HotFunction::OnEvent, started, &result);
+
+                match result {
+                    Ok((message, fn_state)) => {
+                        let old = std::mem::replace(&mut self.on_event_fn_state, fn_state);
+                        self.report_transition(HotFunction::OnEvent, &old, &self.on_event_fn_state);
+                        self.sync_error_state(HotFunction::OnEvent, &self.on_event_fn_state);
+                        match message {
+                            Some(message) => self.update(program, Message::AppMessage(message)),
+                            None => Task::none(),
+                        }
+                    }
+                    Err(err) => {
+                        log::error!(target: DISPATCH, "on_event(): {}", err);
+                        let old = std::mem::replace(
+                            &mut self.on_event_fn_state,
+                            FunctionState::Error(err.to_string()),
+                        );
+                        self.report_transition(HotFunction::OnEvent, &old, &self.on_event_fn_state);
+                        self.sync_error_state(HotFunction::OnEvent, &self.on_event_fn_state);
+                        Task::none()
+                    }
+                }
+            }
+            Message::Extension(call) => {
+                // Dropped during a reload, the caller is told the library is
+                // not loaded.
+                if !self.program_ready() {
+                    return Task::none();
+                }
+                let Some((func, fn_state)) = call.run(&self.state, self.library.as_ref()) else {
+                    return Task::none();
+                };
+
+                let states = &mut self.extension_fn_states;
+                let index = match states.iter().position(|(f, _)| *f == func) {
+                    Some(index) => index,
+                    None => {
+                        states.push((func, FunctionState::None));
+                        states.len() - 1
+                    }
+                };
+                let old = std::mem::replace(&mut states[index].1, fn_state);
+                let new = &self.extension_fn_states[index].1;
+                self.report_transition(func, &old, new);
+                self.sync_error_state(func, new);
+                Task::none()
+            }
             Message::CompilationOutput(line) => {
+                let task = match BuildEvent::parse(&line) {
+                    Some(event) => self.record_build_event(event),
+                    None => Task::none(),
+                };
                 self.compilation_output.push(line);
+                task
+            }
+            Message::BuildEvent(event) => self.record_build_event(event),
+            // Nothing to do, the view is rebuilt after every message.
+            Message::AssetsChanged => Task::none(),
+            Message::Requested(request) => self.handle_request(program, request),
+            Message::StateReturned => self.state_returned(program),
+            Message::WorkerStuck(stuck) => {
+                if let Ok(mut errors) = self.active_errors.lock() {
+                    errors.report(
+                        HotFunction::Subscription,
+                        &stuck.to_string(),
+                        metrics::reloads(),
+                        Instant::now(),
+                    );
+                }
+                self.invalidate_overlay();
                 Task::none()
             }
             Message::ClearCompilationOutput => {
@@ -641,261 +1594,514 @@ where
             }
             Message::CompilationComplete => {
                 self.compilation_output.clear();
+                let ui_crate = self.reloader_settings.ui_crate(self.lib_name);
+                metrics::set_source(SourceRevision::capture(&ui_crate.workspace_root));
+                create_missing_lib_dir(&self.reloader_settings.lib_dir);
                 let mut lib_reloader = LibReloader::new(
                     &self.reloader_settings.lib_dir,
                     self.lib_name,
-                    Some(self.reloader_settings.file_watch_debounce),
+                    &*self.reloader_settings.transport(),
                     None,
+                    self.reloader_settings.artifact_cleanup.clone(),
+                    self.reloader_settings.generation_history,
                 )
                 .expect("Unable to create LibReloader");
 
                 let change_subscriber = lib_reloader.subscribe_to_file_changes();
+                let library = lib_reloader.watched_lib_file().to_path_buf();
+                self.library = Some(lib_reloader.handle());
+                hot_data::follow(self.lib_name, lib_reloader.handle());
                 let lib_reloader = Arc::new(Mutex::new(lib_reloader));
                 self.lib_reloader = Some(lib_reloader.clone());
 
-                self.sync_fonts_to_library();
-                self.start_worker_from_library();
-
-                self.reloader_state = ReloaderState::Ready;
+                self.prepare_loaded_library(false);
                 let listen_for_lib_changes = Task::stream(Self::listen_for_lib_changes(
                     lib_reloader,
                     self.update_channel.1.clone(),
                     change_subscriber,
+                    ui_crate.workspace_root.clone(),
                 ));
 
-                let watch_dir = self
-                    .reloader_settings
-                    .watch_dir
-                    .clone()
-                    .and_then(|p| Utf8PathBuf::from_path_buf(p).ok());
-
-                let watch_dir = match watch_dir {
-                    Some(dir) => dir,
-                    None => {
-                        let metadata = MetadataCommand::new()
-                            .exec()
-                            .expect("Failed to get cargo metadata");
-
-                        let package = metadata
-                            .packages
-                            .iter()
-                            .find(|p| p.name == self.lib_name)
-                            .expect("Found no crate matching the lib name");
-
-                        let mut manifest_path = package.manifest_path.clone();
-                        manifest_path.pop();
-                        manifest_path
-                    }
-                };
-
-                log::info!("Directory to watch: {:?}", watch_dir);
-
-                let watch = Task::stream(Self::watch_library(
-                    watch_dir,
-                    self.lib_name,
-                    self.reloader_settings.target_dir.clone(),
-                    self.reloader_settings.feature.clone(),
-                ));
+                let watch = self.watch_sources(library, ui_crate);
                 Task::batch([listen_for_lib_changes, watch])
             }
             Message::Error(error) => {
+                during_reload::set_reloading(false);
+                self.capturing_widgets = None;
+                self.widget_snapshot = None;
                 self.reloader_state = ReloaderState::Error(error);
                 Task::none()
             }
             Message::AboutToReload => {
-                log::debug!("[reload] AboutToReload received");
-                match self.reloader_state {
-                    ReloaderState::Reloading(num) => {
-                        self.reloader_state = ReloaderState::Reloading(num + 1);
-                        log::info!("[reload] Already reloading, count now: {}", num + 1);
+                log::debug!(target: RELOAD, "AboutToReload received");
+                // The library runs the update, it is swapped once the state
+                // was returned.
+                if self.state_lent {
+                    if !std::mem::replace(&mut self.reload_after_update, true) {
+                        log::info!(target: RELOAD, "Reload deferred, an async update holds the state");
                     }
-                    _ => self.reloader_state = ReloaderState::Reloading(1),
+                    return Task::none();
+                }
+                if let Some(deferred) = self.defer_reload() {
+                    return deferred;
                 }
+                self.begin_reload()
+            }
+            // Already started once the interaction ended.
+            Message::DeferredReload if self.deferred_reload.is_none() => Task::none(),
+            Message::DeferredReload => self.update(program, Message::AboutToReload),
+            Message::Interaction(event) => {
+                self.interaction.track(&event);
+                if self.deferred_reload.is_some() && self.interaction.blocker().is_none() {
+                    return self.update(program, Message::AboutToReload);
+                }
+                Task::none()
+            }
+            Message::WidgetsCaptured(snapshot) => {
+                // Late, the reloading screen is shown already.
+                let Some(num) = self.capturing_widgets.take() else {
+                    return Task::none();
+                };
+                self.widget_snapshot = (!snapshot.is_empty()).then_some(snapshot);
+                self.reloader_state = ReloaderState::Reloading(num);
+                self.ready_signal_sent = false;
                 self.reloading_sensor_key += 1;
                 Task::none()
             }
-            Message::SendReadySignal => {
-                log::debug!("[reload] SendReadySignal received");
-                self.serialize_state()
-                    .inspect_err(|e| log::error!("{}", e))
-                    .ok();
-                log::debug!("[reload] State serialized");
-
-                // Begin draining the old worker instead of hard shutdown.
-                // The worker stops accepting new streams and polls active ones
-                // to completion (or until timeout). The actual thread join
-                // happens later in a background cleanup thread spawned from
-                // ReloadComplete.
-                if let Some(worker_arc) = self.worker.take() {
+            Message::ReadySignalTimeout => {
+                // The widgets were not captured in time, reload without them.
+                if let Some(num) = self.capturing_widgets.take() {
+                    log::debug!(target: RELOAD, "Widgets not captured, reloading without them");
+                    self.reloader_state = ReloaderState::Reloading(num);
+                    self.ready_signal_sent = false;
+                    self.reloading_sensor_key += 1;
+                    return Task::none();
+                }
+                // The reloading screen was not shown yet, e.g. because a
+                // modal dialog blocked the event loop. Re-arm its sensor so
+                // the signal is sent on the next frame that is drawn.
+                if matches!(self.reloader_state, ReloaderState::Reloading(_))
+                    && !self.ready_signal_sent
+                {
                     log::debug!(
-                        "[reload] Beginning drain of cdylib worker (arc strong count: {})",
-                        Arc::strong_count(&worker_arc)
+                        target: RELOAD,
+                        "Still waiting for the reloading screen to be shown"
                     );
-                    match Arc::try_unwrap(worker_arc) {
-                        Ok(worker) => {
-                            log::debug!("[reload] Arc::try_unwrap succeeded, calling begin_drain");
-                            let drain_handle =
-                                worker.begin_drain(self.reloader_settings.drain_timeout);
-                            self.pending_drain = Some(drain_handle);
-                            log::debug!("[reload] begin_drain returned");
-                        }
-                        Err(arc) => {
-                            log::warn!(
-                                "[reload] Worker had {} outstanding references during shutdown, \
-                                 dropping without drain (potential resource leak)",
-                                Arc::strong_count(&arc),
-                            );
+                    self.reloading_sensor_key += 1;
+                }
+                Task::none()
+            }
+            Message::SendReadySignal => {
+                log::debug!(target: RELOAD, "SendReadySignal received");
+                if self.ready_signal_sent || self.serializing.is_some() {
+                    log::debug!(target: RELOAD, "Ready signal already sent for this reload");
+                    return Task::none();
+                }
+                // Never call into a library that failed the fingerprint check,
+                // the state serialized from the last compatible one is kept.
+                // A pending handoff is serialized once the next library is
+                // known to be incompatible with it.
+                if !self.incompatible_library && self.pending_handoff.is_none() {
+                    self.pending_handoff = self
+                        .reloader_settings
+                        .state_handoff
+                        .then(|| {
+                            self.lib_reloader
+                                .as_ref()?
+                                .lock()
+                                .ok()
+                                .and_then(|lib| state_handoff_hash(&lib))
+                        })
+                        .flatten();
+                    if self.pending_handoff.is_some() {
+                        log::debug!(target: RELOAD, "State kept for handoff");
+                    } else {
+                        // The reloading screen keeps being drawn while the
+                        // state is serialized, the library is swapped once
+                        // it finished.
+                        match self.begin_serialize_state() {
+                            Ok(serialized) => return serialized,
+                            Err(e) => log::error!(target: RELOAD, "{}", e),
                         }
                     }
                 }
 
-                log::debug!("[reload] About to send ReadyToReload on update_channel");
-                self.update_channel
-                    .0
-                    .send(ReadyToReload)
-                    .expect("Update Channel closed");
-                log::debug!("[reload] ReadyToReload sent");
+                self.signal_ready();
+                Task::none()
+            }
+            Message::StateSerialized => {
+                self.finish_serialize_state()
+                    .inspect_err(|e| log::error!(target: RELOAD, "{}", e))
+                    .ok();
+                log::debug!(target: RELOAD, "State serialized");
+                self.signal_ready();
                 Task::none()
             }
             Message::ReloadComplete(retired_wrapper) => {
-                log::debug!("[reload] ReloadComplete received");
+                log::debug!(target: RELOAD, "ReloadComplete received");
+                let mut replayed = Task::none();
                 match &self.reloader_state {
                     ReloaderState::Reloading(num) => {
                         if *num == 1 {
-                            self.deserialize_state()
-                                .inspect_err(|e| log::error!("{}", e))
-                                .ok();
-
-                            self.sync_fonts_to_library();
-                            self.start_worker_from_library();
-
-                            self.reloader_state = ReloaderState::Ready;
+                            // A state the loaded library cannot adopt is
+                            // serialized on a background thread first, this
+                            // message arrives again once it finished.
+                            if let Some(serializing) =
+                                self.begin_state_handoff(retired_wrapper.as_ref())
+                            {
+                                return serializing;
+                            }
+                            if self.serializing.is_some() {
+                                self.finish_serialize_state()
+                                    .inspect_err(|e| log::error!(target: RELOAD, "{}", e))
+                                    .ok();
+                            }
+                            let was_incompatible = self.incompatible_library;
+                            let host_task_pin =
+                                std::mem::replace(&mut self.host_task_pin, Arc::new(()));
+                            let mut retired =
+                                retired_wrapper.as_ref().and_then(|w| w.lock().ok()?.take());
+                            self.prepare_loaded_library(true);
+                            if self.compare.is_some() {
+                                let previous = retired.as_ref().map(|retired| &retired.library);
+                                self.compare = Some(self.compared_library(previous));
+                            }
+                            during_reload::set_reloading(false);
                             if let Ok(mut errors) = self.active_errors.lock() {
-                                for entry in errors.values() {
-                                    if let Some(h) = &entry.handle {
-                                        h.abort();
-                                    }
-                                }
-                                errors.clear();
+                                errors.clear_toasts();
                             }
+                            let setup = self.run_setup(program);
+                            let restore = match self.widget_snapshot.take() {
+                                Some(snapshot) => task::widget(widget_state::restore(snapshot)),
+                                None => Task::none(),
+                            };
+                            // Buffered messages may point into the retired
+                            // library, replay them before it can be unloaded.
+                            replayed = Task::batch([
+                                setup,
+                                restore,
+                                self.replay_buffered_messages(program),
+                            ]);
 
                             // Spawn background cleanup thread: join the old
                             // (draining) worker, then drop the retired library.
                             let drain_handle = self.pending_drain.take();
-                            let retired =
-                                retired_wrapper.as_ref().and_then(|w| w.lock().ok()?.take());
 
-                            if drain_handle.is_some() || retired.is_some() {
-                                std::thread::Builder::new()
+                            // The state still references code in the last
+                            // compatible library, keep it loaded until a
+                            // compatible replacement has taken the state over.
+                            if self.incompatible_library && !was_incompatible {
+                                self.last_compatible_library = retired.take();
+                            }
+                            let stale_compatible = if self.incompatible_library {
+                                None
+                            } else {
+                                self.last_compatible_library.take()
+                            };
+
+                            if drain_handle.is_some()
+                                || retired.is_some()
+                                || stale_compatible.is_some()
+                            {
+                                let retained = Arc::clone(&self.retained_libraries);
+                                let max_retained_bytes = self.reloader_settings.max_retained_bytes;
+                                let drain_timeout = self.reloader_settings.drain_timeout;
+                                let (stuck_tx, stuck_rx) = futures::channel::oneshot::channel();
+                                let cleanup = std::thread::Builder::new()
                                     .name("hot-ice-drain-cleanup".into())
                                     .spawn(move || {
-                                        if let Some(h) = drain_handle {
-                                            h.join();
+                                        if let Some(h) = drain_handle
+                                            && let Err(stuck) =
+                                                h.join_within(drain_timeout + STOP_GRACE)
+                                        {
+                                            // The retired generation still
+                                            // runs, it is never unloaded.
+                                            log::error!(target: WORKER, "{stuck}");
+                                            let _ = stuck_tx.send(stuck);
+                                            std::mem::forget(retired);
+                                            std::mem::forget(stale_compatible);
+                                            return;
                                         }
-                                        if let Some(retired) = retired {
-                                            log::info!(
-                                                "Dropping retired library: {:?}",
+                                        // Jobs spawned by now may belong to
+                                        // the retired library.
+                                        let jobs = blocking::next_sequence();
+                                        if !(wait_for_host_tasks(&host_task_pin, drain_timeout)
+                                            && blocking::wait_for_jobs(jobs, drain_timeout))
+                                            && let Some(retired) = retired.take()
+                                        {
+                                            log::warn!(
+                                                target: RELOAD,
+                                                "Tasks on the host still run code \
+                                                 of {:?}, keeping it loaded",
                                                 retired.file_path
                                             );
-                                            drop(retired);
+                                            std::mem::forget(retired);
                                         }
-                                        log::info!("hot-ice drain: cleanup thread finished");
+                                        for retired in
+                                            [stale_compatible, retired].into_iter().flatten()
+                                        {
+                                            retain_library(&retained, retired, max_retained_bytes);
+                                        }
+                                        log::info!(target: RELOAD, "cleanup thread finished");
                                     })
                                     .expect("spawn drain cleanup thread");
+                                self.cleanup_threads.retain(|handle| !handle.is_finished());
+                                self.cleanup_threads.push(cleanup);
+                                replayed = Task::batch([
+                                    replayed,
+                                    Task::future(stuck_rx).then(|stuck| match stuck {
+                                        Ok(stuck) => Task::done(Message::WorkerStuck(stuck)),
+                                        Err(_) => Task::none(),
+                                    }),
+                                ]);
                             }
                         } else {
                             self.reloader_state = ReloaderState::Reloading(num - 1);
                             // Drop the retired library from a skipped reload
-                            // immediately — no worker was using it.
-                            if let Some(wrapper) = &retired_wrapper {
-                                if let Ok(mut guard) = wrapper.lock() {
-                                    guard.take();
-                                }
+                            // immediately — no worker was using it. A state
+                            // kept for handoff is serialized before its code
+                            // is unloaded.
+                            if let Some(wrapper) = &retired_wrapper
+                                && let Ok(mut guard) = wrapper.lock()
+                                && let Some(retired) = guard.take()
+                                && self.pending_handoff.take().is_some()
+                            {
+                                self.serialize_state_from(&retired.library)
+                                    .inspect_err(|e| log::error!(target: RELOAD, "{}", e))
+                                    .ok();
                             }
                         }
                     }
                     s => {
                         log::error!(
+                            target: RELOAD,
                             "Invalid state, Should have ReloaderState::Reloading, found {:?}",
                             s
                         )
                     }
                 }
-                Task::none()
+                replayed
             }
-            Message::ErrorShown(func) => {
+            Message::ErrorShown(id) => {
                 let mut errors = self.active_errors.lock().unwrap();
-                if let Some(entry) = errors.get_mut(&func) {
-                    if entry.dismissing {
+                if let Some(toast) = errors.get_mut(id) {
+                    if toast.dismissing {
                         return Task::none();
                     }
-                    if let Some(h) = entry.handle.take() {
+                    if let Some(h) = toast.handle.take() {
                         h.abort();
                     }
                     let (task, handle) = Task::future(async move {
                         futures_timer::Delay::new(Duration::from_secs(10)).await;
-                        Message::AutoDismissError(func)
+                        Message::AutoDismissError(id)
                     })
                     .abortable();
-                    entry.handle = Some(handle);
+                    toast.handle = Some(handle);
                     drop(errors);
                     task
                 } else {
                     Task::none()
                 }
             }
-            Message::AutoDismissError(func) | Message::DismissError(func) => {
+            Message::AutoDismissError(id) | Message::DismissError(id) => {
                 let mut errors = self.active_errors.lock().unwrap();
-                if let Some(entry) = errors.get_mut(&func) {
-                    if let Some(h) = entry.handle.take() {
-                        h.abort();
-                    }
-                    if !entry.dismissing {
-                        entry.dismissing = true;
-                        entry.animation.go_mut(false, Instant::now());
-                    }
+                if let Some(toast) = errors.get_mut(id) {
+                    toast.dismiss(Instant::now());
                 }
                 drop(errors);
                 Task::none()
             }
-            Message::ToggleErrorExpand(func) => {
+            Message::ToggleErrorExpand(id) => {
                 let mut errors = self.active_errors.lock().unwrap();
-                if let Some(entry) = errors.get_mut(&func) {
-                    entry.expanded = !entry.expanded;
-                    if entry.expanded {
+                if let Some(toast) = errors.get_mut(id) {
+                    toast.expanded = !toast.expanded;
+                    if toast.expanded {
                         // Expanding — abort countdown so it stays visible
-                        if let Some(h) = entry.handle.take() {
+                        if let Some(h) = toast.handle.take() {
                             h.abort();
                         }
                     } else {
                         // Collapsing — restart countdown
-                        if let Some(h) = entry.handle.take() {
+                        if let Some(h) = toast.handle.take() {
                             h.abort();
                         }
                         let (task, handle) = Task::future(async move {
                             futures_timer::Delay::new(Duration::from_secs(10)).await;
-                            Message::AutoDismissError(func)
+                            Message::AutoDismissError(id)
                         })
                         .abortable();
-                        entry.handle = Some(handle);
+                        toast.handle = Some(handle);
                         drop(errors);
                         return task;
                     }
                 }
                 Task::none()
             }
-            Message::AnimationTick(now) => {
-                let mut errors = self.active_errors.lock().unwrap();
-                errors.retain(|_, entry| {
-                    if entry.dismissing && !entry.animation.is_animating(now) {
-                        if let Some(h) = entry.handle.take() {
-                            h.abort();
+            Message::ToggleErrorHistory => {
+                self.show_error_history = !self.show_error_history;
+                self.invalidate_overlay();
+                Task::none()
+            }
+            Message::ClearErrorHistory => {
+                if let Ok(mut errors) = self.active_errors.lock() {
+                    errors.clear_history();
+                }
+                self.show_error_history = false;
+                self.invalidate_overlay();
+                Task::none()
+            }
+            Message::StatusPanelHovered(hovered) => {
+                self.status_panel_hovered = hovered;
+                Task::none()
+            }
+            Message::ToggleRecording => {
+                match self.recorder.take() {
+                    Some(recorder) => {
+                        if let Err(err) = recorder.finish() {
+                            log::error!(target: RELOAD, "Failed to write the fixture: {err}");
                         }
-                        false
-                    } else {
-                        true
                     }
-                });
+                    None => match MessageRecorder::start(&self.reloader_settings.message_fixture) {
+                        Ok(recorder) => self.recorder = Some(recorder),
+                        Err(err) => {
+                            let err = format!(
+                                "Failed to record to {}: {err}",
+                                self.reloader_settings.message_fixture.display()
+                            );
+                            if let Ok(mut errors) = self.active_errors.lock() {
+                                errors.report(
+                                    HotFunction::Update,
+                                    &err,
+                                    metrics::reloads(),
+                                    Instant::now(),
+                                );
+                            }
+                        }
+                    },
+                }
+                Task::none()
+            }
+            Message::ToggleStatusPanel => {
+                self.status_panel_pinned = !self.status_panel_pinned;
+                Task::none()
+            }
+            Message::ToggleGenerationHistory => {
+                self.show_generation_history = !self.show_generation_history;
+                Task::none()
+            }
+            Message::TogglePaletteEditor => {
+                self.palette_editor.open = !self.palette_editor.open;
+                Task::none()
+            }
+            Message::PaletteEdited(edit) => match self.palette_editor.edit(edit) {
+                Some(copied) => clipboard::write(copied),
+                None => Task::none(),
+            },
+            Message::ToggleCompare => {
+                self.compare = match self.compare.take() {
+                    Some(compared) => {
+                        self.compare_released = compared.ok();
+                        None
+                    }
+                    None => {
+                        self.compare_released = None;
+                        Some(self.compared_library(None))
+                    }
+                };
+                Task::none()
+            }
+            Message::ComparedMessage => Task::none(),
+            Message::WatcherDied(watcher, reason) => {
+                log::error!(target: RELOAD, "{watcher:?} stopped, no more reloads: {reason}");
+                self.dead_watchers.retain(|(dead, _)| *dead != watcher);
+                self.dead_watchers.push((watcher, reason));
+                Task::none()
+            }
+            Message::RestartWatchers => self.restart_watchers(),
+            Message::ActivateGeneration(generation) => {
+                if !self.is_ready() {
+                    log::info!(target: RELOAD, "Not activating library {generation}, not ready");
+                    return Task::none();
+                }
+                let activated = self
+                    .lib_reloader
+                    .as_ref()
+                    .and_then(|lib_reloader| lib_reloader.lock().ok())
+                    .map(|mut lib_reloader| lib_reloader.activate(generation));
+                match activated {
+                    Some(Ok(true)) => {}
+                    Some(Ok(false)) | None => {
+                        log::info!(target: RELOAD, "Activating library {generation} not started");
+                    }
+                    Some(Err(err)) => {
+                        if let Ok(mut errors) = self.active_errors.lock() {
+                            errors.report(
+                                HotFunction::Update,
+                                &err,
+                                metrics::reloads(),
+                                Instant::now(),
+                            );
+                        }
+                    }
+                }
+                Task::none()
+            }
+            Message::BuildMissingArtifact => {
+                if self.building_missing_artifact {
+                    return Task::none();
+                }
+                self.building_missing_artifact = true;
+
+                // The lib file watcher picks up the new library, so the build
+                // must not go through `CompilationComplete` again.
+                Task::stream(Self::build_library(self.lib_name, &self.reloader_settings)).map(
+                    |message| match message {
+                        Message::CompilationComplete => Message::MissingArtifactBuilt,
+                        message => message,
+                    },
+                )
+            }
+            Message::MissingArtifactBuilt => {
+                self.building_missing_artifact = false;
+                self.compilation_output.clear();
+                Task::none()
+            }
+            Message::AnimationTick(now) => {
+                let mut errors = self.active_errors.lock().unwrap();
+                errors.remove_dismissed(now);
+                Task::none()
+            }
+            Message::WindowChanged(id, change) => {
+                let Some(geometry) = &mut self.window_geometry else {
+                    return Task::none();
+                };
+                geometry.apply_change(change);
+
+                if let Some(handle) = self.window_geometry_save.take() {
+                    handle.abort();
+                }
+                let (task, handle) = Task::future(async move {
+                    futures_timer::Delay::new(WINDOW_GEOMETRY_SAVE_DELAY).await;
+                    Message::SaveWindowGeometry(id)
+                })
+                .abortable();
+                self.window_geometry_save = Some(handle);
+                task
+            }
+            Message::SaveWindowGeometry(id) => {
+                self.window_geometry_save = None;
+                // Maximizing emits no event of its own, so it is queried
+                // once the window has settled.
+                runtime_window::is_maximized(id).map(Message::WindowGeometryChecked)
+            }
+            Message::WindowGeometryChecked(maximized) => {
+                if let Some(geometry) = &mut self.window_geometry {
+                    geometry.apply_change(WindowChange::Maximized(maximized));
+                    geometry.save(&WindowGeometry::path(
+                        &self.reloader_settings.target_dir,
+                        self.lib_name,
+                    ));
+                }
                 Task::none()
             }
         }
@@ -906,26 +2112,45 @@ where
         program: &P,
         window: window::Id,
     ) -> Element<'a, Message<P>, P::Theme, P::Renderer> {
-        let with_default_theme = |content| {
-            let theme = P::Theme::default(Mode::Dark);
-
-            let derive_theme = move || {
-                theme
-                    .palette()
-                    .map(|palette| Theme::custom("reloader".to_string(), palette))
-            };
-
-            themer(derive_theme(), content).into()
-        };
+        // The overlay brings its own theme so it renders the same no matter
+        // which theme type the program uses.
+        let with_default_theme = |content| themer(Some(self.overlay_theme.clone()), content).into();
+        let overlay = self.reloader_settings.overlay;
 
         let program_view = match &self.reloader_state {
-            ReloaderState::Ready => {
-                match program.view(&self.state, window, self.lib_reloader.as_ref()) {
-                    Ok((element, _fn_state)) => element.map(Message::AppMessage),
-                    Err(err) => {
-                        log::error!("view(): {}", err);
-                        with_default_theme(
-                            container(
+            ReloaderState::Ready if !self.state_lent => {
+                let started = Instant::now();
+                let result = program.view(&self.state, window, self.library.as_ref());
+                metrics::record(HotFunction::View, started, &result);
+
+                match result {
+                    Ok((element, fn_state)) => {
+                        self.store_function_state(HotFunction::View, &self.view_fn_state, fn_state);
+                        let element = if self.reloader_settings.trace_message_latency {
+                            element.map(|message| {
+                                Message::TracedApp(message, MessageOrigin::View, Instant::now())
+                            })
+                        } else {
+                            element.map(Message::AppMessage)
+                        };
+                        if self.reloader_settings.rekey_widgets_on_reload {
+                            Rekeyed::new(metrics::reloads(), element).into()
+                        } else {
+                            element
+                        }
+                    }
+                    Err(err) => {
+                        log::error!(target: DISPATCH, "view(): {}", err);
+                        self.store_function_state(
+                            HotFunction::View,
+                            &self.view_fn_state,
+                            FunctionState::Error(err.to_string()),
+                        );
+                        if !overlay {
+                            return space().width(Length::Fill).height(Length::Fill).into();
+                        }
+                        with_default_theme(
+                            container(
                                 container(
                                     column![
                                         iced_widget::Text::new("View Error")
@@ -957,6 +2182,28 @@ where
                     }
                 }
             }
+            // An async update holds the state, the view can't draw it.
+            ReloaderState::Ready => space().width(Length::Fill).height(Length::Fill).into(),
+            // The program's widgets must be gone before its library is
+            // swapped, an empty window is all that is left without overlay.
+            ReloaderState::Reloading(_) if self.reload_placeholder.is_some() || !overlay => {
+                let placeholder = match &self.reload_placeholder {
+                    Some(placeholder) => with_default_theme(
+                        container(placeholder(&self.overlay_theme).map(|never| match never {}))
+                            .width(Length::Fill)
+                            .height(Length::Fill)
+                            .into(),
+                    ),
+                    None => space().width(Length::Fill).height(Length::Fill).into(),
+                };
+                sensor(placeholder)
+                    .key(self.reloading_sensor_key)
+                    .on_show(|_| Message::SendReadySignal)
+                    .into()
+            }
+            ReloaderState::Error(_) | ReloaderState::Compiling if !overlay => {
+                space().width(Length::Fill).height(Length::Fill).into()
+            }
             ReloaderState::Reloading(_) => {
                 let reloading_message = container(
                     sensor(Text::new("Reloading...").size(20))
@@ -1005,133 +2252,972 @@ where
             }
         };
 
-        // Build error bar from active_errors HashMap.
+        let program_view = match &self.compare {
+            Some(compared) if self.program_ready() => {
+                self.compare_view(program, window, compared, program_view)
+            }
+            _ => program_view,
+        };
+
+        if !overlay {
+            return program_view;
+        }
+
+        let program_view =
+            if self.reloader_settings.show_metrics && self.reloader_state == ReloaderState::Ready {
+                Stack::new()
+                    .push(program_view)
+                    .push(with_default_theme(Self::metrics_table(
+                        self.reloader_settings.overlay_placement.metrics_corner(),
+                    )))
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .into()
+            } else {
+                program_view
+            };
+
+        if self.reloader_settings.overlay_verbosity == LevelFilter::Off {
+            return program_view;
+        }
+
+        let program_view = match (&self.missing_artifact, &self.stale_artifact) {
+            _ if !self.dead_watchers.is_empty() => Stack::new()
+                .push(program_view)
+                .push(with_default_theme(self.dead_watchers_banner()))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+            (Some(command), _) if self.reloader_state == ReloaderState::Ready => Stack::new()
+                .push(program_view)
+                .push(with_default_theme(self.missing_artifact_banner(command)))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+            (None, Some(command)) if self.reloader_state == ReloaderState::Ready => Stack::new()
+                .push(program_view)
+                .push(with_default_theme(self.stale_artifact_banner(command)))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+            _ => program_view,
+        };
+
+        let placement = self.reloader_settings.overlay_placement;
+        let revision = self.overlay_revision.load(Ordering::Relaxed);
+        if let OverlayPlacement::StatusDot(corner) = placement {
+            let status_dot = self.overlay_cache.get(revision, || self.status_dot(corner));
+            return Stack::new()
+                .push(program_view)
+                .push(with_default_theme(status_dot))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into();
+        }
+
         let now = Instant::now();
-        let errors = self.active_errors.lock().unwrap();
-        if errors.is_empty() {
-            drop(errors);
-            program_view
+        let animating = {
+            let errors = self.active_errors.lock().unwrap();
+            // The bar stays to stop a recording, to roll back and to compare
+            // without errors.
+            if (errors.history().is_empty()
+                && self.recorder.is_none()
+                && self.generations.len() < 2
+                && self.compare.is_none())
+                || placement == OverlayPlacement::Hidden
+            {
+                return program_view;
+            }
+            errors.is_animating(now)
+        };
+
+        // Animated frames differ from each other, only a resting bar is
+        // reused.
+        let error_bar = if animating {
+            self.overlay_cache.clear();
+            self.error_bar(placement, now)
         } else {
-            let mut error_col = column![].spacing(2);
-            let mut max_t: f32 = 0.0;
+            self.overlay_cache
+                .get(revision, || self.error_bar(placement, now))
+        };
 
-            for (&func, entry) in errors.iter() {
-                let t: f32 = entry.animation.interpolate(0.0f32, 1.0f32, now);
-                if t < 0.001 && entry.dismissing {
-                    continue;
-                }
-                if t > max_t {
-                    max_t = t;
-                }
+        Stack::new()
+            .push(program_view)
+            .push(with_default_theme(error_bar))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
 
-                let text_alpha = t;
-                let detail_alpha = 0.7 * t;
+    /// The bar listing the active errors, faded in and out by their
+    /// animations at `now`, with the error history below it. Without
+    /// active errors only the button opening the history is shown.
+    fn error_bar(
+        &self,
+        placement: OverlayPlacement,
+        now: Instant,
+    ) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let errors = self.active_errors.lock().unwrap();
+        let mut error_col = column![].spacing(2);
+        let mut max_t: f32 = 0.0;
 
-                let toggle_label = if entry.expanded {
-                    "Show less"
-                } else {
-                    "Read more"
-                };
+        for toast in errors.toasts() {
+            let t: f32 = toast.animation.interpolate(0.0f32, 1.0f32, now);
+            if t < 0.001 && toast.dismissing {
+                continue;
+            }
+            if t > max_t {
+                max_t = t;
+            }
 
-                let summary = row![
-                    Text::new(format!("Error: {}", func))
+            let text_alpha = t;
+            let detail_alpha = 0.7 * t;
+            let id = toast.id;
+
+            let toggle_label = if toast.expanded {
+                "Show less"
+            } else {
+                "Read more"
+            };
+
+            let summary = row![
+                Text::new(format_time(toast.at))
+                    .font(Font::MONOSPACE)
+                    .style(move |_| TextStyle {
+                        color: Some(Color::from_rgba(1.0, 1.0, 1.0, detail_alpha)),
+                    })
+                    .size(12),
+                Text::new(format!("Error: {}", toast.function))
+                    .style(move |_| TextStyle {
+                        color: Some(Color::from_rgba8(225, 29, 72, text_alpha)),
+                    })
+                    .size(13),
+                space().width(Length::Fill),
+                button(Text::new(toggle_label).size(12).style(move |_| TextStyle {
+                    color: Some(Color::from_rgba(1.0, 1.0, 1.0, text_alpha)),
+                }),)
+                .on_press(Message::ToggleErrorExpand(id))
+                .style(button::text),
+                button(Text::new("X").size(12).style(move |_| TextStyle {
+                    color: Some(Color::from_rgba(1.0, 1.0, 1.0, text_alpha)),
+                }),)
+                .on_press(Message::DismissError(id))
+                .style(button::text),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center);
+
+            let error_row = if toast.expanded {
+                column![
+                    summary,
+                    Text::new(toast.message.clone())
                         .style(move |_| TextStyle {
-                            color: Some(Color::from_rgba8(225, 29, 72, text_alpha)),
+                            color: Some(Color::from_rgba8(225, 29, 72, detail_alpha)),
                         })
-                        .size(13),
-                    space().width(Length::Fill),
-                    button(Text::new(toggle_label).size(12).style(move |_| TextStyle {
-                        color: Some(Color::from_rgba(1.0, 1.0, 1.0, text_alpha)),
-                    }),)
-                    .on_press(Message::ToggleErrorExpand(func))
-                    .style(button::text),
-                    button(Text::new("X").size(12).style(move |_| TextStyle {
-                        color: Some(Color::from_rgba(1.0, 1.0, 1.0, text_alpha)),
-                    }),)
-                    .on_press(Message::DismissError(func))
-                    .style(button::text),
+                        .size(11),
                 ]
-                .spacing(8)
-                .align_y(Alignment::Center);
+                .spacing(4)
+            } else {
+                column![summary]
+            };
 
-                let error_row = if entry.expanded {
-                    column![
-                        summary,
-                        Text::new(entry.message.clone())
-                            .style(move |_| TextStyle {
-                                color: Some(Color::from_rgba8(225, 29, 72, detail_alpha)),
-                            })
-                            .size(11),
-                    ]
-                    .spacing(4)
-                } else {
-                    column![summary]
-                };
+            let sensor_key = toast.sensor_key;
+            error_col = error_col.push(
+                sensor(error_row)
+                    .key(sensor_key)
+                    .on_show(move |_| Message::ErrorShown(id)),
+            );
+        }
 
-                let sensor_key = entry.sensor_key;
-                error_col = error_col.push(
-                    sensor(error_row)
-                        .key(sensor_key)
-                        .on_show(move |_| Message::ErrorShown(func)),
-                );
+        // The history stays reachable once every toast has faded out.
+        let compact = max_t == 0.0
+            && !self.show_error_history
+            && !self.show_generation_history
+            && !self.palette_editor.open;
+        if !compact {
+            max_t = 1.0_f32.max(max_t);
+        }
+        let history_label = if self.show_error_history {
+            "Hide history".to_string()
+        } else {
+            format!("Error history ({})", errors.history().len())
+        };
+        error_col = error_col.push(
+            row![
+                space().width(Length::Fill),
+                self.record_button(),
+                self.compare_button(),
+                self.generation_history_button(),
+                self.palette_editor_button(),
+                button(Text::new(history_label).size(12).style(|_| TextStyle {
+                    color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.7)),
+                }))
+                .on_press(Message::ToggleErrorHistory)
+                .style(button::text),
+            ]
+            .align_y(Alignment::Center),
+        );
+        if self.show_generation_history {
+            error_col = error_col.push(self.generation_history());
+        }
+        if self.palette_editor.open {
+            error_col = error_col.push(self.palette_editor_panel());
+        }
+        if self.show_error_history {
+            error_col = error_col.push(Self::error_history(errors.history()));
+        }
+        drop(errors);
+
+        let floating = matches!(placement, OverlayPlacement::FloatingCorner(_));
+        let error_bar = container(error_col)
+            .style(move |_| ContainerStyle {
+                background: Some(Background::Color(Color::from_rgba(
+                    0.0,
+                    0.0,
+                    0.0,
+                    if compact { 0.6 } else { 0.85 * max_t },
+                ))),
+                border: Border {
+                    radius: if floating || compact { 6.0 } else { 0.0 }.into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .padding(Padding {
+                top: 6.,
+                bottom: 6.,
+                left: 16.,
+                right: 16.,
+            });
+
+        let error_bar = match placement {
+            OverlayPlacement::FloatingCorner(corner) => {
+                place_in_corner(error_bar.max_width(420), corner).padding(12)
             }
-            drop(errors);
-
-            let error_bar = with_default_theme(Element::from(
-                container(error_col)
-                    .style(move |_| ContainerStyle {
-                        background: Some(Background::Color(Color::from_rgba(
-                            0.0,
-                            0.0,
-                            0.0,
-                            0.85 * max_t,
-                        ))),
-                        ..Default::default()
-                    })
-                    .width(Length::Fill)
-                    .padding(Padding {
-                        top: 6.,
-                        bottom: 6.,
-                        left: 16.,
-                        right: 16.,
-                    }),
-            ));
+            OverlayPlacement::Bottom if compact => {
+                place_in_corner(error_bar, Corner::BottomRight).padding(12)
+            }
+            _ if compact => place_in_corner(error_bar, Corner::TopRight).padding(12),
+            OverlayPlacement::Bottom => container(error_bar.width(Length::Fill))
+                .align_bottom(Length::Fill)
+                .width(Length::Fill),
+            _ => container(error_bar.width(Length::Fill)).width(Length::Fill),
+        };
+        error_bar.into()
+    }
+
+    /// Starts or stops recording the program's messages, see
+    /// `ReloaderSettings::message_fixture`.
+    fn record_button(&self) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let (label, color) = match &self.recorder {
+            Some(recorder) => (
+                format!("Stop recording to {}", recorder.path().display()),
+                STATUS_RED,
+            ),
+            None => (
+                "Record messages".to_string(),
+                Color::from_rgba(1.0, 1.0, 1.0, 0.7),
+            ),
+        };
+        button(
+            Text::new(label)
+                .size(12)
+                .style(move |_| TextStyle { color: Some(color) }),
+        )
+        .on_press(Message::ToggleRecording)
+        .style(button::text)
+        .into()
+    }
+
+    /// `current` on the right, and on the left the view the previous
+    /// library draws of the same state. Its messages are dropped.
+    fn compare_view(
+        &'a self,
+        program: &P,
+        window: window::Id,
+        compared: &Result<ComparedLibrary, String>,
+        current: Element<'a, Message<P>, P::Theme, P::Renderer>,
+    ) -> Element<'a, Message<P>, P::Theme, P::Renderer> {
+        let with_default_theme = |content| themer(Some(self.overlay_theme.clone()), content).into();
+        let notice = |notice: String| {
+            with_default_theme(Element::from(
+                container(Text::new(notice).size(14)).center(Length::Fill),
+            ))
+        };
 
+        let previous = match compared {
+            Ok(compared) => match program.view(&self.state, window, Some(&compared.handle)) {
+                Ok((element, _)) => element.map(|_| Message::ComparedMessage),
+                Err(err) => notice(format!("view() of the previous library failed: {err}")),
+            },
+            Err(reason) => notice(reason.clone()),
+        };
+
+        let library = |generation: Option<usize>, fallback: &str| match generation {
+            Some(generation) => format!("library {generation}"),
+            None => fallback.to_string(),
+        };
+        let previous_label = library(
+            compared
+                .as_ref()
+                .ok()
+                .and_then(|compared| compared.generation),
+            "previous library",
+        );
+        let current_label = library(
+            self.generations.last().map(|record| record.generation),
+            "current library",
+        );
+        let pane = |content: Element<'a, Message<P>, P::Theme, P::Renderer>, label: String| {
+            let label = container(Text::new(label).size(11).style(|_| TextStyle {
+                color: Some(Color::WHITE),
+            }))
+            .style(|_| ContainerStyle {
+                background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.6))),
+                border: Border {
+                    radius: 4.0.into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .padding([2, 6]);
             Stack::new()
-                .push(program_view)
-                .push(error_bar)
+                .push(content)
+                .push(with_default_theme(Element::from(
+                    container(label).padding(6),
+                )))
                 .width(Length::Fill)
                 .height(Length::Fill)
+        };
+        let divider = with_default_theme(Element::from(
+            container(space().width(2).height(Length::Fill)).style(|_| ContainerStyle {
+                background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.6))),
+                ..Default::default()
+            }),
+        ));
+
+        row![
+            pane(previous, previous_label),
+            divider,
+            pane(current, current_label)
+        ]
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    /// Starts or stops comparing the view of the previous library with the
+    /// current one.
+    fn compare_button(&self) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let label = match self.compare {
+            Some(_) => "Stop comparing",
+            None => "Compare with previous",
+        };
+        button(Text::new(label).size(12).style(|_| TextStyle {
+            color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.7)),
+        }))
+        .on_press(Message::ToggleCompare)
+        .style(button::text)
+        .into()
+    }
+
+    /// Opens the list of the libraries loaded so far.
+    fn generation_history_button(&self) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let label = if self.show_generation_history {
+            "Hide libraries".to_string()
+        } else {
+            format!("Library history ({})", self.generations.len())
+        };
+        button(Text::new(label).size(12).style(|_| TextStyle {
+            color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.7)),
+        }))
+        .on_press(Message::ToggleGenerationHistory)
+        .style(button::text)
+        .into()
+    }
+
+    /// Opens the editor of the program's palette.
+    fn palette_editor_button(&self) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let label = if self.palette_editor.open {
+            "Hide palette"
+        } else {
+            "Edit palette"
+        };
+        button(Text::new(label).size(12).style(|_| TextStyle {
+            color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.7)),
+        }))
+        .on_press(Message::TogglePaletteEditor)
+        .style(button::text)
+        .into()
+    }
+
+    fn palette_editor_panel(&self) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        self.palette_editor.view(
+            PaletteEditor::applies_to::<P::Theme>(),
+            Message::PaletteEdited,
+        )
+    }
+
+    /// The libraries loaded so far, newest first, each older one with a
+    /// button loading it again.
+    fn generation_history(&self) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let active = self.generations.last().map(|record| record.generation);
+        let ready = self.is_ready();
+        let records = column(self.generations.iter().rev().map(|record| {
+            let built_from = match (&record.source, record.rollback_of) {
+                (_, Some(older)) => format!("library {older} again"),
+                (Some(source), None) => source.summary(),
+                (None, None) => format!("{:#010x}", record.hash),
+            };
+            let action: Element<'static, Message<P>, Theme, P::Renderer> =
+                if Some(record.generation) == active {
+                    Text::new("Active")
+                        .size(11)
+                        .style(|_| TextStyle {
+                            color: Some(STATUS_GREEN),
+                        })
+                        .into()
+                } else if record.is_available() {
+                    button(Text::new("Activate").size(11))
+                        .on_press_maybe(
+                            ready.then_some(Message::ActivateGeneration(record.generation)),
+                        )
+                        .style(button::text)
+                        .into()
+                } else {
+                    Text::new("Deleted")
+                        .size(11)
+                        .style(|_| TextStyle {
+                            color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.5)),
+                        })
+                        .into()
+                };
+
+            row![
+                Text::new(format!(
+                    "{}  library {}  {}",
+                    format_time(record.loaded_at),
+                    record.generation,
+                    built_from
+                ))
+                .font(Font::MONOSPACE)
+                .style(|_| TextStyle {
+                    color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.7)),
+                })
+                .size(11),
+                space().width(Length::Fill),
+                action,
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center)
+            .into()
+        }))
+        .spacing(4);
+
+        scrollable(records)
+            .height(Length::Shrink)
+            .width(Length::Fill)
+            .into()
+    }
+
+    /// The errors of the history, newest first.
+    fn error_history(
+        history: &VecDeque<ErrorRecord>,
+    ) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let records = column(history.iter().rev().map(|record| {
+            column![
+                Text::new(format!(
+                    "{}  library {}  {}",
+                    format_time(record.at),
+                    record.generation,
+                    record.function
+                ))
+                .font(Font::MONOSPACE)
+                .style(|_| TextStyle {
+                    color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.7)),
+                })
+                .size(11),
+                Text::new(record.message.clone())
+                    .style(|_| TextStyle {
+                        color: Some(Color::from_rgba8(225, 29, 72, 0.8)),
+                    })
+                    .size(11),
+            ]
+            .spacing(2)
+            .into()
+        }))
+        .spacing(8);
+
+        column![
+            scrollable(records)
+                .height(Length::Shrink)
+                .width(Length::Fill),
+            row![
+                space().width(Length::Fill),
+                button(Text::new("Clear").size(12))
+                    .on_press(Message::ClearErrorHistory)
+                    .style(button::text),
+            ],
+        ]
+        .spacing(4)
+        .into()
+    }
+
+    /// Banner shown while no library was ever built, with the command that
+    /// builds it.
+    fn missing_artifact_banner(
+        &self,
+        command: &str,
+    ) -> Element<'a, Message<P>, Theme, P::Renderer> {
+        let mut content = column![
+            Text::new(format!(
+                "No hot library found in {}, the application runs its static code.",
+                self.reloader_settings.lib_dir
+            ))
+            .size(13),
+            Text::new("Build it with:").size(12),
+            Text::new(command.to_string())
+                .font(Font::MONOSPACE)
+                .size(12),
+        ]
+        .spacing(6);
+
+        if self.reloader_settings.compile_in_reloader {
+            let label = if self.building_missing_artifact {
+                "Building..."
+            } else {
+                "Build now"
+            };
+            content = content.push(
+                button(Text::new(label).size(12))
+                    .on_press_maybe(
+                        (!self.building_missing_artifact).then_some(Message::BuildMissingArtifact),
+                    )
+                    .style(button::primary),
+            );
+        }
+
+        artifact_banner(content)
+    }
+
+    /// Banner shown while the library loaded on startup is older than its
+    /// sources, with the command that rebuilds it.
+    fn stale_artifact_banner(&self, command: &str) -> Element<'a, Message<P>, Theme, P::Renderer> {
+        artifact_banner(
+            column![
+                Text::new("The hot library is stale, rebuild it to see your changes.").size(13),
+                Text::new(format!(
+                    "It was built before the latest change to the sources of {}.",
+                    self.lib_name
+                ))
+                .size(12),
+                Text::new("Rebuild it with:").size(12),
+                Text::new(command.to_string())
+                    .font(Font::MONOSPACE)
+                    .size(12),
+            ]
+            .spacing(6),
+        )
+    }
+
+    /// Banner shown while a watcher is stopped and new builds are not
+    /// loaded, with the button starting it again.
+    fn dead_watchers_banner(&self) -> Element<'a, Message<P>, Theme, P::Renderer> {
+        let reasons = self.dead_watchers.iter().map(|(watcher, reason)| {
+            let watcher = match watcher {
+                Watcher::Transport => "The library watcher",
+                Watcher::Compiler => "The compiler",
+            };
+            Text::new(format!("{watcher} stopped: {reason}"))
+                .font(Font::MONOSPACE)
+                .size(12)
                 .into()
+        });
+
+        artifact_banner(
+            column![
+                Text::new("Hot reloading stopped, changes are not loaded anymore.").size(13),
+                Column::with_children(reasons).spacing(4),
+                button(Text::new("Restart watcher").size(12))
+                    .on_press(Message::RestartWatchers)
+                    .style(button::primary),
+            ]
+            .spacing(6),
+        )
+    }
+
+    /// Warns if the library loaded on startup was built before the latest
+    /// change to the library crate, see [`stale_artifact`].
+    fn check_stale_artifact(&mut self, ui_crate: &UiCrate) {
+        let Some(artifact) = self
+            .lib_reloader
+            .as_ref()
+            .and_then(|lib_reloader| lib_reloader.lock().ok())
+            .filter(|lib| lib.loaded_lib_file().is_some())
+            .map(|lib| lib.watched_lib_file().to_path_buf())
+        else {
+            return;
+        };
+        let Some(sources) = stale_artifact::library_crate_dir(
+            self.reloader_settings.watch_dir.as_deref(),
+            ui_crate,
+        ) else {
+            return;
+        };
+        if !stale_artifact::is_stale(&artifact, &sources) {
+            return;
         }
+
+        // Another tool builds the library, there is no command to show.
+        let Some(command) = build_command(&self.reloader_settings, self.lib_name) else {
+            return;
+        };
+        log::warn!(
+            target: RELOAD,
+            "{} is older than the sources in {}, rebuild it with: {}",
+            artifact.display(),
+            sources.display(),
+            command
+        );
+        self.stale_artifact = Some(command);
+    }
+
+    /// Current state of every hot function, in [`HotFunction`] order.
+    fn function_states(&self) -> Vec<(HotFunction, FunctionState)> {
+        let get = |state: &ArcSwap<FunctionState>| FunctionState::clone(&state.load());
+
+        let mut states = vec![
+            (HotFunction::Update, self.update_fn_state.clone()),
+            (HotFunction::View, get(&self.view_fn_state)),
+            (HotFunction::Subscription, get(&self.subscription_fn_state)),
+            (HotFunction::Theme, get(&self.theme_fn_state)),
+            (HotFunction::Style, get(&self.style_fn_state)),
+            (HotFunction::Title, get(&self.title_fn_state)),
+            (HotFunction::ScaleFactor, get(&self.scale_factor_fn_state)),
+            (HotFunction::Setup, self.setup_fn_state.clone()),
+            (HotFunction::OnEvent, self.on_event_fn_state.clone()),
+        ];
+        states.extend(self.extension_fn_states.iter().cloned());
+        states
+    }
+
+    /// Colored dot summarizing the reloader and function states, expanded
+    /// into a per-function status panel while hovered or after a click.
+    fn status_dot(&self, corner: Corner) -> Element<'static, Message<P>, Theme, P::Renderer> {
+        let states = self.function_states();
+        let has = |check: fn(&FunctionState) -> bool| states.iter().any(|(_, state)| check(state));
+
+        let color = match self.reloader_state {
+            ReloaderState::Compiling | ReloaderState::Reloading(_) => STATUS_BLUE,
+            ReloaderState::Error(_) => STATUS_RED,
+            ReloaderState::Ready if has(|s| matches!(s, FunctionState::Error(_))) => STATUS_RED,
+            ReloaderState::Ready if has(|s| matches!(s, FunctionState::FallBackStatic(_))) => {
+                STATUS_ORANGE
+            }
+            ReloaderState::Ready if has(|s| matches!(s, FunctionState::Hot)) => STATUS_GREEN,
+            ReloaderState::Ready => Color::WHITE,
+        };
+
+        // A red ring while messages are recorded.
+        let recording = self.recorder.is_some();
+        let dot = container(space().width(12).height(12)).style(move |_| ContainerStyle {
+            background: Some(Background::Color(color)),
+            border: Border {
+                radius: 6.0.into(),
+                color: if recording {
+                    STATUS_RED
+                } else {
+                    Color::from_rgba(0.0, 0.0, 0.0, 0.5)
+                },
+                width: if recording { 2.0 } else { 1.0 },
+            },
+            ..Default::default()
+        });
+
+        let content: Element<'static, Message<P>, Theme, P::Renderer> =
+            if self.status_panel_hovered || self.status_panel_pinned {
+                let text = |content: String, color: Color| {
+                    Text::new(content)
+                        .font(Font::MONOSPACE)
+                        .size(11)
+                        .style(move |_| TextStyle { color: Some(color) })
+                };
+
+                let rows = states.into_iter().map(|(func, state)| {
+                    let (label, color) = state.describe();
+                    let detail = match &state {
+                        FunctionState::FallBackStatic(msg) | FunctionState::Error(msg) => {
+                            Some(text(msg.clone(), Color::from_rgba(1.0, 1.0, 1.0, 0.7)))
+                        }
+                        _ => None,
+                    };
+
+                    column![
+                        row![
+                            text(func.to_string(), Color::WHITE),
+                            space().width(Length::Fill),
+                            text(label.to_string(), color),
+                        ]
+                        .spacing(12),
+                    ]
+                    .push(detail)
+                    .spacing(2)
+                    .into()
+                });
+
+                let rows = column(rows)
+                    .push(self.record_button())
+                    .push(self.compare_button())
+                    .push(self.generation_history_button())
+                    .push(
+                        self.show_generation_history
+                            .then(|| self.generation_history()),
+                    )
+                    .push(self.palette_editor_button())
+                    .push(
+                        self.palette_editor
+                            .open
+                            .then(|| self.palette_editor_panel()),
+                    );
+                let panel = container(rows.spacing(4).width(220))
+                    .style(|_| ContainerStyle {
+                        background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.85))),
+                        border: Border {
+                            radius: 6.0.into(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .padding(8);
+
+                let align = match corner {
+                    Corner::TopLeft | Corner::BottomLeft => Alignment::Start,
+                    Corner::TopRight | Corner::BottomRight => Alignment::End,
+                };
+                match corner {
+                    Corner::TopLeft | Corner::TopRight => column![dot, panel],
+                    Corner::BottomLeft | Corner::BottomRight => column![panel, dot],
+                }
+                .spacing(6)
+                .align_x(align)
+                .into()
+            } else {
+                dot.into()
+            };
+
+        place_in_corner(
+            mouse_area(content)
+                .on_press(Message::ToggleStatusPanel)
+                .on_enter(Message::StatusPanelHovered(true))
+                .on_exit(Message::StatusPanelHovered(false)),
+            corner,
+        )
+        .padding(12)
+        .into()
+    }
+
+    /// Compact table of the collected [`metrics`], aligned to `corner` of the
+    /// window.
+    fn metrics_table(corner: Corner) -> Element<'a, Message<P>, Theme, P::Renderer> {
+        let metrics = metrics::metrics();
+
+        let cell = |content: String| {
+            Text::new(content)
+                .font(Font::MONOSPACE)
+                .size(11)
+                .style(|_| TextStyle {
+                    color: Some(Color::from_rgba(1.0, 1.0, 1.0, 0.85)),
+                })
+        };
+        let column_of = |header: &str, values: Vec<String>| {
+            column(
+                std::iter::once(cell(header.to_string()).into())
+                    .chain(values.into_iter().map(|value| cell(value).into())),
+            )
+            .spacing(2)
+            .align_x(Alignment::End)
+        };
+        let millis = |duration: Duration| format!("{:.2}ms", duration.as_secs_f64() * 1000.0);
+
+        let retained = cell(format!(
+            "retained: {} ({:.1} MB)",
+            metrics.retained_generations,
+            metrics.retained_bytes as f64 / (1024.0 * 1024.0)
+        ));
+        let source = metrics
+            .source
+            .as_ref()
+            .map(|source| cell(format!("built from: {}", source.summary())));
+        let state = metrics.state_transfer.map(|transfer| {
+            cell(match transfer {
+                StateTransfer::Handoff => format!(
+                    "state: handed off in {}",
+                    millis(metrics.state_transfer_time)
+                ),
+                StateTransfer::Fields {
+                    moved,
+                    serialized,
+                    bytes,
+                } => format!(
+                    "state: {moved} fields moved, {serialized} serialized ({:.1} KB) in {}",
+                    bytes as f64 / 1024.0,
+                    millis(metrics.state_transfer_time)
+                ),
+                StateTransfer::Serialized { bytes } => format!(
+                    "state: {:.1} KB in {} ({} serializing)",
+                    bytes as f64 / 1024.0,
+                    millis(metrics.state_transfer_time),
+                    millis(metrics.state_serialize_time)
+                ),
+            })
+        });
+        let queue = metrics.worker_queue;
+        let backpressure = (queue.peak_backlog > 0).then(|| {
+            cell(format!(
+                "worker queue: {} held (peak {}), {} dropped, blocked {}",
+                queue.backlog,
+                queue.peak_backlog,
+                queue.dropped,
+                millis(queue.blocked)
+            ))
+        });
+
+        let latency = metrics.message_latency.iter().map(|(origin, latency)| {
+            cell(format!(
+                "{origin:?} messages: {}, queued {}, update {}, boundary {} (max {})",
+                latency.messages,
+                millis(latency.mean_queued),
+                millis(latency.mean_update),
+                millis(latency.mean_boundary),
+                millis(latency.max_boundary)
+            ))
+            .into()
+        });
+        let latency = (!metrics.message_latency.is_empty()).then(|| column(latency).spacing(2));
+
+        let listing = subscriptions::listing();
+        let subscriptions =
+            (!listing.active.is_empty() || !listing.dropped.is_empty()).then(|| {
+                let active = listing.active.iter().map(|row| {
+                    let change = match row.change {
+                        Change::Kept => "",
+                        Change::Added => "  new",
+                        Change::Duplicated => "  duplicate",
+                    };
+                    cell(format!(
+                        "{:08x}  gen {}  {} msgs{change}",
+                        row.key as u32, row.generation, row.messages
+                    ))
+                    .into()
+                });
+                let dropped = listing
+                    .dropped
+                    .iter()
+                    .map(|key| cell(format!("{:08x}  dropped", *key as u32)).into());
+                column(
+                    std::iter::once(
+                        cell(format!("subscriptions: {}", listing.active.len())).into(),
+                    )
+                    .chain(active)
+                    .chain(dropped),
+                )
+                .spacing(2)
+            });
+
+        let functions = metrics.functions.values();
+        let table = row![
+            column(
+                std::iter::once(cell(format!("reload #{}", metrics.reloads)).into()).chain(
+                    metrics
+                        .functions
+                        .keys()
+                        .map(|func| cell(func.to_string()).into())
+                ),
+            )
+            .spacing(2),
+            column_of(
+                "calls",
+                functions.clone().map(|m| m.calls.to_string()).collect()
+            ),
+            column_of("mean", functions.clone().map(|m| millis(m.mean)).collect()),
+            column_of("max", functions.clone().map(|m| millis(m.max)).collect()),
+            column_of(
+                "panics",
+                functions.clone().map(|m| m.panics.to_string()).collect()
+            ),
+            column_of(
+                "fallbacks",
+                functions.map(|m| m.fallbacks.to_string()).collect()
+            ),
+        ]
+        .spacing(12);
+
+        place_in_corner(
+            container(
+                column![table, retained]
+                    .push(source)
+                    .push(state)
+                    .push(backpressure)
+                    .push(latency)
+                    .push(subscriptions)
+                    .spacing(6),
+            )
+            .style(|_| ContainerStyle {
+                background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.75))),
+                border: Border {
+                    radius: 6.0.into(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .padding(8),
+            corner,
+        )
+        .padding(12)
+        .into()
     }
 
     pub fn subscription(&self, program: &P) -> Subscription<Message<P>> {
         log::trace!(
-            "[sub] subscription() called, state={:?}",
+            target: DISPATCH,
+            "subscription() called, state={:?}",
             self.reloader_state
         );
-        let app_sub = if self.reloader_state == ReloaderState::Ready {
-            match program.subscription(&self.state, self.lib_reloader.as_ref()) {
+        let app_sub = if self.program_ready() {
+            let started = Instant::now();
+            let result = program.subscription(&self.state, self.library.as_ref());
+            metrics::record(HotFunction::Subscription, started, &result);
+
+            match result {
                 Ok((sub, fn_state)) => {
-                    if let Ok(mut state) = self.subscription_fn_state.try_lock() {
-                        *state = fn_state.clone();
-                    }
+                    self.store_function_state(
+                        HotFunction::Subscription,
+                        &self.subscription_fn_state,
+                        fn_state.clone(),
+                    );
                     self.sync_error_state(HotFunction::Subscription, &fn_state);
-                    sub.map(Message::AppMessage)
+                    if self.reloader_settings.trace_message_latency {
+                        sub.map(|message| {
+                            Message::TracedApp(message, MessageOrigin::Subscription, Instant::now())
+                        })
+                    } else {
+                        sub.map(Message::AppMessage)
+                    }
                 }
                 Err(err) => {
-                    log::error!("subscription(): {}", err);
+                    log::error!(target: DISPATCH, "subscription(): {}", err);
                     let fn_state = FunctionState::Error(err.to_string());
-                    if let Ok(mut state) = self.subscription_fn_state.try_lock() {
-                        *state = fn_state.clone();
-                    }
+                    self.store_function_state(
+                        HotFunction::Subscription,
+                        &self.subscription_fn_state,
+                        fn_state.clone(),
+                    );
                     self.sync_error_state(HotFunction::Subscription, &fn_state);
                     Subscription::none()
                 }
             }
         } else {
-            log::debug!("Called subscription when Reloader was not ready");
+            log::debug!(target: DISPATCH, "Called subscription when Reloader was not ready");
             Subscription::none()
         };
 
@@ -1159,10 +3245,29 @@ where
             let now = Instant::now();
             self.active_errors
                 .lock()
-                .map(|errors| errors.values().any(|e| e.animation.is_animating(now)))
+                .map(|errors| errors.is_animating(now))
                 .unwrap_or(false)
         };
 
+        let app_sub = if self.window_geometry.is_some() {
+            Subscription::batch([app_sub, window_changes()])
+        } else {
+            app_sub
+        };
+
+        let app_sub = if self.reloader_settings.max_reload_deferral.is_zero() {
+            app_sub
+        } else {
+            Subscription::batch([app_sub, interaction_events()])
+        };
+
+        // Listened to by the host, so editing `on_event` restarts nothing.
+        let app_sub = if program.listens_to_events() {
+            Subscription::batch([app_sub, uncaptured_events()])
+        } else {
+            app_sub
+        };
+
         if needs_frames {
             Subscription::batch([
                 app_sub,
@@ -1174,240 +3279,326 @@ where
     }
 
     pub fn title(&self, program: &P, window: window::Id) -> String {
-        if self.reloader_state == ReloaderState::Ready {
-            match program.title(&self.state, window, self.lib_reloader.as_ref()) {
+        let title = self.program_title(program, window);
+        match self
+            .build_progress
+            .title_prefix()
+            .or_else(|| self.status_badge())
+        {
+            Some(prefix) => format!("{prefix} {title}"),
+            None => title,
+        }
+    }
+
+    /// The status the overlay would show, as a prefix of the window title
+    /// when it is disabled.
+    fn status_badge(&self) -> Option<String> {
+        if self.reloader_settings.overlay {
+            return None;
+        }
+        let badge = match self.reloader_state {
+            ReloaderState::Compiling => "[Compiling]",
+            ReloaderState::Reloading(_) => "[Reloading]",
+            ReloaderState::Error(_) => "[Error]",
+            ReloaderState::Ready if !self.dead_watchers.is_empty() => "[Watcher stopped]",
+            ReloaderState::Ready if self.missing_artifact.is_some() => "[Not built]",
+            ReloaderState::Ready if self.stale_artifact.is_some() => "[Stale]",
+            ReloaderState::Ready
+                if self
+                    .function_states()
+                    .iter()
+                    .any(|(_, state)| matches!(state, FunctionState::Error(_))) =>
+            {
+                "[Error]"
+            }
+            ReloaderState::Ready => return None,
+        };
+        Some(badge.to_string())
+    }
+
+    fn program_title(&self, program: &P, window: window::Id) -> String {
+        if self.program_ready() {
+            let started = Instant::now();
+            let result = program.title(&self.state, window, self.library.as_ref());
+            metrics::record(HotFunction::Title, started, &result);
+
+            match result {
                 Ok((title, fn_state)) => {
-                    if let Ok(mut state) = self.title_fn_state.lock() {
-                        *state = fn_state;
-                    }
+                    self.store_function_state(HotFunction::Title, &self.title_fn_state, fn_state);
                     format!("Hot-Reloading: {}", title)
                 }
                 Err(err) => {
-                    log::error!("title(): {}", err);
+                    log::error!(target: DISPATCH, "title(): {}", err);
                     let fn_state = FunctionState::Error(err.to_string());
-                    if let Ok(mut state) = self.title_fn_state.lock() {
-                        *state = fn_state.clone();
-                    }
+                    self.store_function_state(
+                        HotFunction::Title,
+                        &self.title_fn_state,
+                        fn_state.clone(),
+                    );
                     self.sync_error_state(HotFunction::Title, &fn_state);
                     "An ice-hot application".to_string()
                 }
             }
         } else {
-            log::debug!("Called title when Reloader was not ready");
+            log::debug!(target: DISPATCH, "Called title when Reloader was not ready");
             String::from("Reloader")
         }
     }
 
     pub fn theme(&self, program: &P, window: window::Id) -> Option<P::Theme> {
-        if self.reloader_state == ReloaderState::Ready {
-            match program.theme(&self.state, window, self.lib_reloader.as_ref()) {
+        if self.program_ready() {
+            let started = Instant::now();
+            let result = program.theme(&self.state, window, self.library.as_ref());
+            metrics::record(HotFunction::Theme, started, &result);
+
+            match result {
                 Ok((theme, fn_state)) => {
-                    if let Ok(mut state) = self.theme_fn_state.lock() {
-                        *state = fn_state;
-                    }
-                    theme
+                    self.store_function_state(HotFunction::Theme, &self.theme_fn_state, fn_state);
+                    self.palette_editor
+                        .observe(theme.as_ref().and_then(Base::palette));
+                    self.palette_editor.apply(theme)
                 }
                 Err(err) => {
-                    log::error!("theme(): {}", err);
+                    log::error!(target: DISPATCH, "theme(): {}", err);
                     let fn_state = FunctionState::Error(err.to_string());
-                    if let Ok(mut state) = self.theme_fn_state.lock() {
-                        *state = fn_state.clone();
-                    }
+                    self.store_function_state(
+                        HotFunction::Theme,
+                        &self.theme_fn_state,
+                        fn_state.clone(),
+                    );
                     self.sync_error_state(HotFunction::Theme, &fn_state);
                     None
                 }
             }
         } else {
-            log::debug!("Called theme when Reloader was not ready");
+            log::debug!(target: DISPATCH, "Called theme when Reloader was not ready");
             None
         }
     }
 
     pub fn style(&self, program: &P, theme: &P::Theme) -> theme::Style {
-        if self.reloader_state == ReloaderState::Ready {
-            match program.style(&self.state, theme, self.lib_reloader.as_ref()) {
+        if self.program_ready() {
+            let started = Instant::now();
+            let result = program.style(&self.state, theme, self.library.as_ref());
+            metrics::record(HotFunction::Style, started, &result);
+
+            match result {
                 Ok((style, fn_state)) => {
-                    if let Ok(mut state) = self.style_fn_state.lock() {
-                        *state = fn_state;
-                    }
+                    self.store_function_state(HotFunction::Style, &self.style_fn_state, fn_state);
                     style
                 }
                 Err(err) => {
-                    log::error!("style(): {}", err);
+                    log::error!(target: DISPATCH, "style(): {}", err);
                     let fn_state = FunctionState::Error(err.to_string());
-                    if let Ok(mut state) = self.style_fn_state.lock() {
-                        *state = fn_state.clone();
-                    }
+                    self.store_function_state(
+                        HotFunction::Style,
+                        &self.style_fn_state,
+                        fn_state.clone(),
+                    );
                     self.sync_error_state(HotFunction::Style, &fn_state);
                     theme.base()
                 }
             }
         } else {
-            log::debug!("Called style when Reloader was not ready");
+            log::debug!(target: DISPATCH, "Called style when Reloader was not ready");
             theme.base()
         }
     }
 
     pub fn scale_factor(&self, program: &P, window: window::Id) -> f32 {
-        if self.reloader_state == ReloaderState::Ready {
-            match program.scale_factor(&self.state, window, self.lib_reloader.as_ref()) {
+        if self.program_ready() {
+            let started = Instant::now();
+            let result = program.scale_factor(&self.state, window, self.library.as_ref());
+            metrics::record(HotFunction::ScaleFactor, started, &result);
+
+            match result {
                 Ok((factor, fn_state)) => {
-                    if let Ok(mut state) = self.scale_factor_fn_state.lock() {
-                        *state = fn_state;
-                    }
+                    self.store_function_state(
+                        HotFunction::ScaleFactor,
+                        &self.scale_factor_fn_state,
+                        fn_state,
+                    );
                     factor
                 }
                 Err(err) => {
-                    log::error!("scale_factor(): {}", err);
+                    log::error!(target: DISPATCH, "scale_factor(): {}", err);
                     let fn_state = FunctionState::Error(err.to_string());
-                    if let Ok(mut state) = self.scale_factor_fn_state.lock() {
-                        *state = fn_state.clone();
-                    }
+                    self.store_function_state(
+                        HotFunction::ScaleFactor,
+                        &self.scale_factor_fn_state,
+                        fn_state.clone(),
+                    );
                     self.sync_error_state(HotFunction::ScaleFactor, &fn_state);
                     1.0
                 }
             }
         } else {
-            log::debug!("Called scale_factor when Reloader was not ready");
+            log::debug!(target: DISPATCH, "Called scale_factor when Reloader was not ready");
             1.0
         }
     }
 
+    /// Stores the state `func` ended up in.
+    fn store_function_state(
+        &self,
+        func: HotFunction,
+        slot: &ArcSwap<FunctionState>,
+        fn_state: FunctionState,
+    ) {
+        if **slot.load() == fn_state {
+            return;
+        }
+        let new = Arc::new(fn_state);
+        let old = slot.swap(Arc::clone(&new));
+        self.report_transition(func, &old, &new);
+    }
+
+    /// Reports `func` changing its state as a `tracing` event and to the
+    /// callback set with `HotIce::on_function_state_change`.
+    fn report_transition(&self, func: HotFunction, old: &FunctionState, new: &FunctionState) {
+        if old == new {
+            return;
+        }
+        self.invalidate_overlay();
+
+        match new {
+            FunctionState::Error(error) => tracing::error!(
+                target: DISPATCH,
+                function = %func,
+                from = ?old,
+                error = %error,
+                "{func} failed",
+            ),
+            FunctionState::FallBackStatic(reason) => tracing::warn!(
+                target: DISPATCH,
+                function = %func,
+                from = ?old,
+                reason = %reason,
+                "{func} fell back to the static function",
+            ),
+            _ => tracing::info!(
+                target: DISPATCH,
+                function = %func,
+                from = ?old,
+                to = ?new,
+                "{func} is {}",
+                new.describe().0,
+            ),
+        }
+
+        if let Some(callback) = &self.on_function_state_change {
+            callback(func, old, new);
+        }
+    }
+
+    /// Tracks the progress shown in the window title and on the taskbar
+    /// button or dock tile, see [`ReloaderSettings::taskbar_progress`].
+    fn record_build_event(&mut self, event: BuildEvent) -> Task<Message<P>> {
+        if !self.reloader_settings.taskbar_progress {
+            return Task::none();
+        }
+        let shown = self.build_progress.indicator();
+        let finished = self.build_progress.record(event);
+        let indicator = self.build_progress.indicator();
+        if indicator == shown && !finished {
+            return Task::none();
+        }
+
+        runtime_window::latest().then(move |id| {
+            let Some(id) = id else {
+                return Task::none();
+            };
+            let progress =
+                runtime_window::run(id, move |window| taskbar::show(window, indicator)).discard();
+            if !finished {
+                return progress;
+            }
+
+            // Has no effect while the window is focused.
+            let attention = match event {
+                BuildEvent::Finished { success: false } => UserAttention::Critical,
+                _ => UserAttention::Informational,
+            };
+            Task::batch([
+                progress,
+                runtime_window::request_user_attention(id, Some(attention)),
+            ])
+        })
+    }
+
+    /// Makes the next `view` rebuild the overlay.
+    fn invalidate_overlay(&self) {
+        self.overlay_revision.fetch_add(1, Ordering::Relaxed);
+    }
+
     fn sync_error_state(&self, func: HotFunction, fn_state: &FunctionState) {
         let Ok(mut errors) = self.active_errors.lock() else {
             return;
         };
-        let now = Instant::now();
-        match fn_state {
-            FunctionState::Error(msg) => {
-                use std::collections::hash_map::Entry;
-                match errors.entry(func) {
-                    Entry::Occupied(mut e) => {
-                        if e.get().dismissing {
-                            // Was being dismissed but error came back — re-animate in
-                            if let Some(h) = &e.get().handle {
-                                h.abort();
-                            }
-                            let old_key = e.get().sensor_key;
-                            let mut anim = Animation::new(false).quick().easing(Easing::EaseOut);
-                            anim.go_mut(true, now);
-                            e.insert(ErrorEntry {
-                                message: msg.clone(),
-                                handle: None,
-                                sensor_key: old_key.wrapping_add(1),
-                                expanded: false,
-                                animation: anim,
-                                dismissing: false,
-                            });
-                        }
-                        if e.get().message != *msg {
-                            // Error message changed — new error
-                            if let Some(h) = &e.get().handle {
-                                h.abort();
-                            }
-                            let old_key = e.get().sensor_key;
-                            let mut anim = Animation::new(false).quick().easing(Easing::EaseOut);
-                            anim.go_mut(true, now);
-                            e.insert(ErrorEntry {
-                                message: msg.clone(),
-                                handle: None,
-                                sensor_key: old_key.wrapping_add(1),
-                                expanded: false,
-                                animation: anim,
-                                dismissing: false,
-                            });
-                        }
-                    }
-                    Entry::Vacant(e) => {
-                        let mut anim = Animation::new(false).quick().easing(Easing::EaseOut);
-                        anim.go_mut(true, now);
-                        e.insert(ErrorEntry {
-                            message: msg.clone(),
-                            handle: None,
-                            sensor_key: 0,
-                            expanded: false,
-                            animation: anim,
-                            dismissing: false,
-                        });
-                    }
-                }
+        let verbosity = self.reloader_settings.overlay_verbosity;
+        let fallback;
+        let msg = match fn_state {
+            FunctionState::Error(msg) if verbosity >= LevelFilter::Error => msg,
+            FunctionState::FallBackStatic(reason) if verbosity >= LevelFilter::Warn => {
+                fallback = format!("Fell back to the static function: {reason}");
+                &fallback
             }
-            _ => {}
-        }
+            _ => return,
+        };
+        errors.report(func, msg, metrics::reloads(), Instant::now());
     }
 
     fn build_library(
         lib_crate_name: &'static str,
-        target_dir: String,
-        feature: Option<String>,
-    ) -> impl Stream<Item = Message<P>> {
-        stream::channel(200, async move |mut output| {
-            let metadata = MetadataCommand::new()
-                .exec()
-                .expect("Failed to get cargo metadata");
+        settings: &ReloaderSettings,
+    ) -> impl Stream<Item = Message<P>> + use<P> {
+        let compiler = Arc::clone(&settings.compiler);
+        let (target_dir, feature) = (settings.target_dir.clone(), settings.feature.clone());
+        let (fast_linker, prelink) = (settings.fast_linker, settings.prelink);
+        let ui_crate_path = settings.ui_manifest_path.clone();
 
-            let workspace_root = metadata.workspace_root.into_std_path_buf();
+        stream::channel(200, async move |mut output| {
+            let ui_crate = UiCrate::locate(ui_crate_path.as_deref(), lib_crate_name);
+            let request = compile_request(
+                lib_crate_name,
+                &ui_crate,
+                &target_dir,
+                feature,
+                fast_linker,
+                prelink,
+            );
 
             loop {
-                info!(
-                    "Working directory for build command: {}",
-                    workspace_root.display()
-                );
-
-                let result = Command::new("cargo")
-                    .current_dir(&workspace_root)
-                    .args(build_args(lib_crate_name, feature.as_deref()))
-                    .environment_variables(&target_dir)
-                    .stderr(Stdio::piped())
-                    .spawn();
-
-                let mut child = match result {
-                    Ok(child) => child,
-                    Err(err) => {
-                        if let Err(err) = output.try_send(Message::Error(
-                            ReloaderError::FailedToBuildCommand(err.to_string()),
-                        )) {
-                            log::error!("Failed to send Message: {}", err);
-                        }
-                        return;
-                    }
-                };
-
-                let stderr = child.stderr.take().unwrap();
-
-                // Read stderr on a background thread so we can await each send,
-                // giving the runtime a chance to drain the channel and render.
+                // The compiler blocks, run it on a thread and await each line
+                // so the runtime gets a chance to drain the channel and render.
                 let (line_tx, mut line_rx) =
                     iced_futures::futures::channel::mpsc::channel::<String>(16);
+                let (result_tx, result_rx) = futures::channel::oneshot::channel();
+                let (compiler, request) = (Arc::clone(&compiler), request.clone());
                 std::thread::spawn(move || {
-                    let stderr_reader = BufReader::new(stderr);
-                    for line in stderr_reader.lines() {
-                        match line {
-                            Ok(line) => {
-                                if line_tx.clone().try_send(line).is_err() {
-                                    break;
-                                }
-                            }
-                            Err(err) => {
-                                log::error!("Failed to read line from stderr: {}", err);
-                            }
-                        }
-                    }
+                    let result = compiler.compile(&request, &mut |line| {
+                        let _ = line_tx.clone().try_send(line);
+                    });
+                    let _ = result_tx.send(result);
                 });
 
                 while let Some(line) = line_rx.next().await {
                     let _ = output.send(Message::CompilationOutput(line)).await;
                 }
 
-                match child.wait() {
-                    Ok(status) => {
-                        if status.success() {
-                            let _ = output.try_send(Message::CompilationComplete);
-                            return;
-                        }
+                match result_rx.await {
+                    Ok(CompileResult::Built) => {
+                        let _ = output.try_send(Message::CompilationComplete);
+                        return;
+                    }
+                    Ok(CompileResult::Failed(status)) => {
                         // Compilation failed — keep the error output visible,
                         // then clear and retry.
-                        log::warn!("Compilation failed ({}), retrying in 3s...", status);
+                        log::warn!(
+                            target: RELOAD,
+                            "Compilation failed ({}), retrying in 3s...",
+                            status
+                        );
                         let _ = output
                             .send(Message::CompilationOutput(
                                 "\n--- Compilation failed, retrying in 3 seconds... ---"
@@ -1417,8 +3608,16 @@ where
                         futures_timer::Delay::new(Duration::from_secs(3)).await;
                         let _ = output.send(Message::ClearCompilationOutput).await;
                     }
-                    Err(err) => {
-                        log::error!("Failed to wait for child process: {}", err);
+                    Ok(CompileResult::NotRun(err)) => {
+                        if let Err(err) = output
+                            .try_send(Message::Error(ReloaderError::FailedToBuildCommand(err)))
+                        {
+                            log::error!(target: RELOAD, "Failed to send Message: {}", err);
+                        }
+                        return;
+                    }
+                    Err(_) => {
+                        log::error!(target: RELOAD, "The compiler thread panicked");
                         return;
                     }
                 }
@@ -1426,38 +3625,136 @@ where
         })
     }
 
+    /// Runs `cargo watch` on the sources of the library crate, building
+    /// `library` whenever they change.
+    fn watch_sources(&self, library: PathBuf, ui_crate: UiCrate) -> Task<Message<P>> {
+        let watch_dir = self
+            .reloader_settings
+            .watch_dir
+            .clone()
+            .and_then(|p| Utf8PathBuf::from_path_buf(p).ok());
+
+        let watch_dir = match watch_dir {
+            Some(dir) => dir,
+            None => ui_crate
+                .dir
+                .and_then(|dir| Utf8PathBuf::from_path_buf(dir).ok())
+                .expect("Found no crate matching the lib name"),
+        };
+
+        log::info!(target: RELOAD, "Directory to watch: {:?}", watch_dir);
+
+        Task::stream(Self::watch_library(
+            watch_dir,
+            library,
+            self.lib_name,
+            &self.reloader_settings,
+        ))
+    }
+
+    /// Starts the watchers that stopped again, see [`Message::WatcherDied`].
+    fn restart_watchers(&mut self) -> Task<Message<P>> {
+        let Some(lib_reloader) = self.lib_reloader.clone() else {
+            return Task::none();
+        };
+        let ui_crate = self.reloader_settings.ui_crate(self.lib_name);
+        let mut tasks = Vec::new();
+        for (watcher, _) in std::mem::take(&mut self.dead_watchers) {
+            let Ok(mut lib) = lib_reloader.lock() else {
+                continue;
+            };
+            match watcher {
+                Watcher::Transport => {
+                    let change_subscriber = lib.subscribe_to_file_changes();
+                    lib.restart_transport(&*self.reloader_settings.transport());
+                    tasks.push(Task::stream(Self::listen_for_lib_changes(
+                        Arc::clone(&lib_reloader),
+                        self.update_channel.1.clone(),
+                        change_subscriber,
+                        ui_crate.workspace_root.clone(),
+                    )));
+                }
+                Watcher::Compiler => {
+                    let library = lib.watched_lib_file().to_path_buf();
+                    drop(lib);
+                    log::info!(target: RELOAD, "Restarting cargo watch");
+                    tasks.push(self.watch_sources(library, ui_crate.clone()));
+                }
+            }
+        }
+        Task::batch(tasks)
+    }
+
     fn watch_library(
         watch_dir: Utf8PathBuf,
+        library: PathBuf,
         lib_crate_name: &'static str,
-        target_dir: String,
-        feature: Option<String>,
-    ) -> impl Stream<Item = Message<P>> {
-        stream::channel(200, async move |mut output| {
-            let metadata = MetadataCommand::new()
-                .exec()
-                .expect("Failed to get cargo metadata");
+        settings: &ReloaderSettings,
+    ) -> impl Stream<Item = Message<P>> + use<P> {
+        let compiler = Arc::clone(&settings.compiler);
+        let (target_dir, feature) = (settings.target_dir.clone(), settings.feature.clone());
+        let (fast_linker, prelink) = (settings.fast_linker, settings.prelink);
+        let ui_crate_path = settings.ui_manifest_path.clone();
+        let extra_watch_paths = settings.extra_watch_paths.clone();
 
-            let workspace_root = metadata.workspace_root;
-
-            let Ok(watch_dir) = watch_dir.strip_prefix(&workspace_root) else {
-                log::error!("Failed to strip prefix");
+        stream::channel(200, async move |mut output| {
+            let ui_crate = UiCrate::locate(ui_crate_path.as_deref(), lib_crate_name);
+            let request = compile_request(
+                lib_crate_name,
+                &ui_crate,
+                &target_dir,
+                feature,
+                fast_linker,
+                prelink,
+            );
+            let Some(build) = compiler.command(&request) else {
+                log::info!(target: RELOAD, "{compiler:?} builds nothing, not watching the sources");
+                return;
+            };
+            let workspace_root = &request.workspace_root;
+
+            let target_dir = ui_crate.host_root.join(ui_crate.target_dir(&target_dir));
+            let roots = [&*ui_crate.workspace_root, &*ui_crate.host_root];
+            let build_inputs = extra_watch_paths
+                .iter()
+                .map(|path| workspace_root.join(path))
+                .chain(build_inputs::outside_watch_dir(
+                    &library,
+                    watch_dir.as_std_path(),
+                    &roots,
+                    &target_dir,
+                ))
+                .filter(|path| path.exists())
+                .collect::<Vec<_>>();
+
+            let Ok(watch_dir) = watch_dir.as_std_path().strip_prefix(workspace_root) else {
+                log::error!(target: RELOAD, "Failed to strip prefix");
                 return;
             };
 
-            log::info!("workspace_root: {}", workspace_root);
-            log::info!("watch dir relative path: {}", watch_dir);
+            log::info!(target: RELOAD, "workspace_root: {}", workspace_root.display());
+            log::info!(target: RELOAD, "watch dir relative path: {}", watch_dir.display());
 
             let mut command = Command::new("cargo");
             command
-                .current_dir(&workspace_root)
+                .current_dir(workspace_root)
                 .arg("watch")
                 .arg("-w")
-                .arg(watch_dir)
+                .arg(watch_dir);
+            for input in &build_inputs {
+                log::info!(target: RELOAD, "Also watching build input {}", input.display());
+                command.arg("-w").arg(input);
+            }
+            command
                 .arg("-d")
                 .arg("0.01")
-                .arg("-x")
-                .arg(build_args(lib_crate_name, feature.as_deref()).join(" "))
-                .environment_variables(&target_dir)
+                .arg("-s")
+                .arg(shell_line(&build, false))
+                .envs(
+                    build
+                        .get_envs()
+                        .filter_map(|(key, value)| Some((key, value?))),
+                )
                 .stderr(Stdio::piped());
 
             // On Unix, set up process group and death signal so child dies when parent dies
@@ -1484,13 +3781,13 @@ where
                     if let Err(err) = output.try_send(Message::Error(
                         ReloaderError::FailedToBuildCommand(err.to_string()),
                     )) {
-                        log::error!("Failed to send Message: {}", err);
+                        log::error!(target: RELOAD, "Failed to send Message: {}", err);
                     }
                     return;
                 }
             };
 
-            log::info!("cargo watch started successfully (pid: {})", child.id());
+            log::info!(target: RELOAD, "cargo watch started successfully (pid: {})", child.id());
 
             // Register the cleanup handler and store the child handle
             register_cleanup_handler();
@@ -1510,106 +3807,464 @@ where
                     for line in stderr_reader.lines() {
                         match line {
                             Ok(line) => {
-                                log::info!("[cargo watch] {}", line);
+                                log::info!(target: RELOAD, "[cargo watch] {}", line);
+                                if let Some(event) = BuildEvent::parse(&line) {
+                                    // The hash sidecar tells the file watcher
+                                    // when the library is completely written.
+                                    match event {
+                                        BuildEvent::Started => remove_artifact_hash(&library),
+                                        BuildEvent::Finished { success: true } => {
+                                            write_artifact_hash(&library)
+                                        }
+                                        _ => {}
+                                    }
+                                    let _ = output.try_send(Message::BuildEvent(event));
+                                }
                             }
                             Err(err) => {
-                                log::error!("Failed to read line from stderr: {}", err);
+                                log::error!(
+                                    target: RELOAD,
+                                    "Failed to read line from stderr: {}",
+                                    err
+                                );
                                 break;
                             }
                         };
                     }
-                    log::info!("cargo watch stderr reader stopped");
+                    log::info!(target: RELOAD, "cargo watch stderr reader stopped");
+                    let _ = output.try_send(Message::WatcherDied(
+                        Watcher::Compiler,
+                        "cargo watch exited".to_string(),
+                    ));
                 });
             }
         })
     }
 
+    /// Emits [`Message::AssetsChanged`] whenever a `hot_assets` bundle was
+    /// reloaded, so the view picks up the new text.
+    fn listen_for_asset_changes() -> impl Stream<Item = Message<P>> {
+        let changes = hot_assets::subscribe_to_changes();
+        stream::channel(10, async move |mut output| {
+            while changes.recv().await.is_ok() {
+                if output.send(Message::AssetsChanged).await.is_err() {
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Emits [`Message::Requested`] for every task of
+    /// [`hot_ice::task`](crate::task) the program ran.
+    fn listen_for_requests() -> impl Stream<Item = Message<P>> {
+        let requests = task_requests::subscribe();
+        stream::channel(10, async move |mut output| {
+            while let Ok(request) = requests.recv().await {
+                if output.send(Message::Requested(request)).await.is_err() {
+                    return;
+                }
+            }
+        })
+    }
+
     fn listen_for_lib_changes(
         lib_reloader: Arc<Mutex<LibReloader>>,
         update_ch_rx: MAsyncRx<ReadyToReload>,
-        change_subscriber: AsyncRx<()>,
+        change_subscriber: AsyncRx<ReloadEvent>,
+        workspace_root: PathBuf,
     ) -> impl Stream<Item = Message<P>> {
         stream::channel(10, async move |mut output| {
             loop {
-                log::info!("Waiting for lib changes");
-                change_subscriber.recv().await.expect("Sub channel closed");
-
-                if let Err(err) = output.try_send(Message::AboutToReload) {
-                    log::error!("Failed to send reloading message: {err}")
+                log::info!(target: RELOAD, "Waiting for lib changes");
+                // A new listener is started with the transport, this one
+                // stops with it.
+                let reason = match change_subscriber.recv().await {
+                    Ok(ReloadEvent::Changed) => None,
+                    Ok(ReloadEvent::WatcherDied(reason)) => Some(reason),
+                    Err(_) => Some("the transport's channel closed".to_string()),
+                };
+                if let Some(reason) = reason {
+                    let _ = output
+                        .send(Message::WatcherDied(Watcher::Transport, reason))
+                        .await;
+                    return;
                 }
 
-                update_ch_rx.recv().await.expect("Update Channel closed");
+                // The library was just written, the sources are the ones it
+                // was built from.
+                let source = SourceRevision::capture(&workspace_root);
 
-                log::info!("Reloading library");
+                // Wait for capacity instead of dropping the message while the
+                // event loop is blocked, e.g. by a modal dialog.
+                if output.send(Message::AboutToReload).await.is_err() {
+                    log::error!(
+                        target: RELOAD,
+                        "Failed to send reloading message, event loop is gone"
+                    );
+                    return;
+                }
 
+                // The state is only safe to hand over once the UI has
+                // serialized it, keep asking until it did.
                 loop {
-                    if let Ok(mut reloader) = lib_reloader.lock() {
-                        match reloader.update() {
-                            Ok(result) => {
-                                let retired_wrapper =
-                                    if let crate::lib_reloader::UpdateResult::Reloaded { retired } =
-                                        result
-                                    {
-                                        retired.map(|r| {
-                                            log::info!(
-                                                "Library reloaded, retired old: {:?}",
-                                                r.file_path
-                                            );
-                                            Arc::new(Mutex::new(Some(r)))
-                                        })
-                                    } else {
-                                        None
-                                    };
+                    let timeout = futures_timer::Delay::new(READY_SIGNAL_TIMEOUT);
+                    match update_ch_rx.recv_with_timer(timeout).await {
+                        Ok(ReadyToReload) => break,
+                        Err(RecvTimeoutError::Timeout) => {
+                            let _ = output.send(Message::ReadySignalTimeout).await;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => {
+                            log::error!(target: RELOAD, "Update channel closed");
+                            return;
+                        }
+                    }
+                }
 
-                                if let Err(err) =
-                                    output.try_send(Message::ReloadComplete(retired_wrapper))
-                                {
-                                    log::error!("Failed to send reload complete message: {err}");
-                                }
-                                break;
+                log::info!(target: RELOAD, "Reloading library");
+
+                let retired_wrapper = loop {
+                    // Never block the executor on the lock, a hot function
+                    // running a nested event loop may hold it for a while.
+                    let result = match lib_reloader.try_lock() {
+                        Ok(mut reloader) => Some(reloader.update().map(|result| match result {
+                            UpdateResult::Reloaded { retired } => {
+                                Some((retired, reloader.built_from(source.clone())))
                             }
-                            Err(err) => log::error!("{err}"),
+                            UpdateResult::Unchanged => None,
+                        })),
+                        Err(_) => None,
+                    };
+                    match result {
+                        Some(Ok(Some((retired, source)))) => {
+                            metrics::set_source(source);
+                            break retired.map(|r| {
+                                log::info!(
+                                    target: RELOAD,
+                                    "Library reloaded, retired old: {:?}",
+                                    r.file_path
+                                );
+                                Arc::new(Mutex::new(Some(r)))
+                            });
                         }
+                        Some(Ok(None)) => break None,
+                        Some(Err(err)) => log::error!(target: RELOAD, "{err}"),
+                        None => {}
                     }
-                    std::thread::sleep(Duration::from_millis(1));
+                    futures_timer::Delay::new(Duration::from_millis(1)).await;
+                };
+
+                if let Err(err) = output.send(Message::ReloadComplete(retired_wrapper)).await {
+                    log::error!(target: RELOAD, "Failed to send reload complete message: {err}");
                 }
 
-                log::info!("Reload complete");
+                log::info!(target: RELOAD, "Reload complete");
             }
         })
     }
 
-    fn serialize_state(&mut self) -> Result<(), HotIceError> {
-        let reloader = self
-            .lib_reloader
-            .as_ref()
-            .expect("reloader not initialized");
+    /// Holds a new library back while the user interacts with the window,
+    /// see [`reload_deferral`]. Returns the task checking again, `None` if
+    /// the reload may start.
+    fn defer_reload(&mut self) -> Option<Task<Message<P>>> {
+        let max = self.reloader_settings.max_reload_deferral;
+        if self.reloader_state != ReloaderState::Ready
+            || self.capturing_widgets.is_some()
+            || max.is_zero()
+        {
+            return None;
+        }
+        let Some(blocker) = self.interaction.blocker() else {
+            self.deferred_reload = None;
+            return None;
+        };
+
+        let now = Instant::now();
+        let since = *self.deferred_reload.get_or_insert_with(|| {
+            log::info!(target: RELOAD, "Reload deferred, {blocker}");
+            now
+        });
+        if now.saturating_duration_since(since) >= max {
+            log::info!(target: RELOAD, "Reloading after {max:?}, {blocker}");
+            self.deferred_reload = None;
+            return None;
+        }
+        Some(Task::future(async {
+            futures_timer::Delay::new(DEFERRED_RELOAD_POLL).await;
+            Message::DeferredReload
+        }))
+    }
+
+    /// Starts swapping the library once it was built: the program's
+    /// widgets are captured, then the reloading screen is shown.
+    fn begin_reload(&mut self) -> Task<Message<P>> {
+        during_reload::set_reloading(true);
+        match (&self.reloader_state, self.capturing_widgets) {
+            (&ReloaderState::Reloading(num), _) => {
+                self.reloader_state = ReloaderState::Reloading(num + 1);
+                log::info!(target: RELOAD, "Already reloading, count now: {}", num + 1);
+            }
+            (_, Some(num)) => self.capturing_widgets = Some(num + 1),
+            // The program's widgets are captured while they are still
+            // shown, the reloading screen follows.
+            (ReloaderState::Ready, None)
+                if self.reloader_settings.restore_widget_state
+                    && !self.reloader_settings.rekey_widgets_on_reload =>
+            {
+                self.capturing_widgets = Some(1);
+                return task::widget(widget_state::capture()).map(Message::WidgetsCaptured);
+            }
+            _ => self.reloader_state = ReloaderState::Reloading(1),
+        }
+        self.ready_signal_sent = false;
+        self.reloading_sensor_key += 1;
+        Task::none()
+    }
+
+    /// Handles a task of [`hot_ice::task`](crate::task). Reloads and state
+    /// resets wait for the library to be ready, a reload in progress must
+    /// not see the state change.
+    fn handle_request(&mut self, program: &P, request: Request) -> Task<Message<P>> {
+        match request {
+            Request::ToggleOverlay => {
+                self.reloader_settings.overlay = !self.reloader_settings.overlay;
+                Task::none()
+            }
+            Request::ToggleRecording => self.update(program, Message::ToggleRecording),
+            _ if !self.is_ready() => {
+                log::info!(target: RELOAD, "Ignoring {request:?}, the library is not ready");
+                Task::none()
+            }
+            Request::Reload => {
+                let started = self
+                    .lib_reloader
+                    .as_ref()
+                    .and_then(|lib_reloader| lib_reloader.lock().ok())
+                    .is_some_and(|lib_reloader| lib_reloader.request_reload());
+                if !started {
+                    log::info!(target: RELOAD, "Requested reload not started");
+                }
+                Task::none()
+            }
+            Request::ResetState if self.state_lent => {
+                log::info!(target: RELOAD, "Ignoring {request:?}, an async update holds the state");
+                Task::none()
+            }
+            Request::ResetState => {
+                log::info!(target: RELOAD, "Resetting the state");
+                let (state, boot_task) = program.boot();
+                self.join_serializer();
+                **self.state = state;
+                let boot_task = self.intercept_app_task(boot_task.map(Message::AppMessage));
+                Task::batch([boot_task, self.run_setup(program)])
+            }
+        }
+    }
+
+    /// Reruns the program's setup function on the state once a new library
+    /// is ready, see [`HotIce::setup`](crate::hot_application::HotIce::setup).
+    fn run_setup(&mut self, program: &P) -> Task<Message<P>> {
+        if !self.program_ready() {
+            return Task::none();
+        }
+
+        self.join_serializer();
+        let started = Instant::now();
+        let result = program.setup(&mut self.state, self.library.as_ref());
+        metrics::record(HotFunction::Setup, started, &result);
+
+        let (task, fn_state) = match result {
+            Ok((task, fn_state)) => (
+                self.intercept_app_task(task.map(Message::AppMessage)),
+                fn_state,
+            ),
+            Err(err) => {
+                log::error!(target: DISPATCH, "setup(): {}", err);
+                (Task::none(), FunctionState::Error(err.to_string()))
+            }
+        };
+        let old = std::mem::replace(&mut self.setup_fn_state, fn_state);
+        self.report_transition(HotFunction::Setup, &old, &self.setup_fn_state);
+        self.sync_error_state(HotFunction::Setup, &self.setup_fn_state);
+        task
+    }
+
+    /// Lets the reload proceed: the worker of the current library starts
+    /// draining and [`ReadyToReload`] is sent to the library watcher.
+    fn signal_ready(&mut self) {
+        // Begin draining the old worker instead of hard shutdown.
+        // The worker stops accepting new streams and polls active ones
+        // to completion (or until timeout). The actual thread join
+        // happens later in a background cleanup thread spawned from
+        // ReloadComplete.
+        if let Some(worker_arc) = self.worker.take() {
+            log::debug!(
+                target: WORKER,
+                "Beginning drain of cdylib worker (arc strong count: {})",
+                Arc::strong_count(&worker_arc)
+            );
+            match Arc::try_unwrap(worker_arc) {
+                Ok(worker) => {
+                    log::debug!(target: RELOAD, "Arc::try_unwrap succeeded, calling begin_drain");
+                    let drain_handle = worker.begin_drain(self.reloader_settings.drain_timeout);
+                    self.pending_drain = Some(drain_handle);
+                    log::debug!(target: RELOAD, "begin_drain returned");
+                }
+                Err(arc) => {
+                    log::warn!(
+                        target: WORKER,
+                        "Worker had {} outstanding references during shutdown, \
+                         dropping without drain (potential resource leak)",
+                        Arc::strong_count(&arc),
+                    );
+                }
+            }
+        }
+
+        log::debug!(target: RELOAD, "About to send ReadyToReload on update_channel");
+        match self
+            .update_channel
+            .0
+            .send_timeout(ReadyToReload, READY_SIGNAL_TIMEOUT)
+        {
+            Ok(()) => {
+                self.ready_signal_sent = true;
+                log::debug!(target: RELOAD, "ReadyToReload sent");
+            }
+            Err(err) => log::error!(target: RELOAD, "Failed to send ReadyToReload: {err}"),
+        }
+    }
+
+    /// Starts serializing the state with the loaded library on a background
+    /// thread, so a large state does not freeze the reloading screen. The
+    /// returned task reports [`Message::StateSerialized`] once it finished,
+    /// the library is not swapped before.
+    fn begin_serialize_state(&mut self) -> Result<Task<Message<P>>, HotIceError> {
+        let reloader = Arc::clone(
+            self.lib_reloader
+                .as_ref()
+                .expect("reloader not initialized"),
+        );
 
         let Ok(reloader) = reloader.lock() else {
             return Err(HotIceError::LockAcquisitionError);
         };
+        let Some(library) = reloader.library() else {
+            return Err(HotIceError::FunctionNotFound(SERIALIZE_STATE_FUNCTION_NAME));
+        };
 
-        if !self.serialized_state_ptr.is_null() && self.serialized_state_len > 0 {
-            let Ok(free_fn) = (unsafe {
-                reloader
-                    .get_symbol::<fn(*mut u8, usize)>(FREE_SERIALIZED_DATA_FUNCTION_NAME.as_bytes())
-            }) else {
-                log::warn!("Failed to get free_serialized_data function");
-                self.serialized_state_ptr = std::ptr::null_mut();
-                self.serialized_state_len = 0;
-                return Ok(());
-            };
+        self.free_serialized_state(library);
+        let Ok(serialize_fn) = (unsafe {
+            library.get::<fn(&P::State, *mut *mut u8, *mut usize) -> Result<(), HotIceError>>(
+                SERIALIZE_STATE_FUNCTION_NAME.as_bytes(),
+            )
+        }) else {
+            return Err(HotIceError::FunctionNotFound(SERIALIZE_STATE_FUNCTION_NAME));
+        };
 
-            free_fn(self.serialized_state_ptr, self.serialized_state_len);
-            self.serialized_state_ptr = std::ptr::null_mut();
-            self.serialized_state_len = 0;
+        // The library is only swapped after the thread finished.
+        self.spawn_serializer(Box::new(*serialize_fn), Message::StateSerialized)
+    }
+
+    /// Runs `serialize` on a background thread and keeps its handle, the
+    /// returned task reports `done` once it finished.
+    fn spawn_serializer(
+        &mut self,
+        serialize: SerializeJob<P::State>,
+        done: Message<P>,
+    ) -> Result<Task<Message<P>>, HotIceError> {
+        // SAFETY: only `#[hot_state]` libraries export the serializer, their
+        // state is a `HotState`, which is `Send + Sync`. The state is boxed,
+        // so it stays in place when the reloader moves, and the thread is
+        // joined by `join_serializer` before the state is borrowed mutably,
+        // replaced or dropped, and before the library is swapped.
+        let state = StatePtr(&**self.state as *const P::State);
+        let (done_tx, done_rx) = futures::channel::oneshot::channel();
+        let serializing = std::thread::Builder::new()
+            .name("hot_ice state serializer".to_string())
+            .spawn(move || {
+                let state = state;
+                let start = Instant::now();
+                let mut serialized = SerializedState {
+                    ptr: std::ptr::null_mut(),
+                    len: 0,
+                    result: Ok(()),
+                    elapsed: Duration::ZERO,
+                };
+                serialized.result = serialize(
+                    unsafe { &*state.0 },
+                    &mut serialized.ptr,
+                    &mut serialized.len,
+                );
+                serialized.elapsed = start.elapsed();
+                let _ = done_tx.send(());
+                serialized
+            })
+            .map_err(|e| HotIceError::FailedToSerializeState(e.to_string()))?;
+
+        self.serializing = Some(serializing);
+        Ok(Task::future(async move {
+            let _ = done_rx.await;
+            done
+        }))
+    }
+
+    /// Waits for a serializer still reading the state, called before every
+    /// mutable borrow of the state.
+    fn join_serializer(&mut self) {
+        if self.serializing.is_some()
+            && let Err(err) = self.finish_serialize_state()
+        {
+            log::error!(target: RELOAD, "Failed to serialize state: {}", err);
+        }
+    }
+
+    /// Waits for the serialization started by
+    /// [`Reloader::begin_serialize_state`] and keeps its buffer for the next
+    /// library.
+    fn finish_serialize_state(&mut self) -> Result<(), HotIceError> {
+        let Some(serializing) = self.serializing.take() else {
+            return Ok(());
+        };
+
+        let serialized = serializing
+            .join()
+            .map_err(|_| HotIceError::FailedToSerializeState("serializer panicked".to_string()))?;
+        self.serialized_state_ptr = serialized.ptr;
+        self.serialized_state_len = serialized.len;
+        self.serialize_time = serialized.elapsed;
+        serialized.result?;
+
+        info!(target: RELOAD, "Size of serialized state: {}", self.serialized_state_len);
+        Ok(())
+    }
+
+    /// Frees the buffer of the last serialization with the functions of
+    /// `library`, which allocated it.
+    fn free_serialized_state(&mut self, library: &Library) {
+        if self.serialized_state_ptr.is_null() || self.serialized_state_len == 0 {
+            return;
         }
 
+        match unsafe {
+            library.get::<fn(*mut u8, usize)>(FREE_SERIALIZED_DATA_FUNCTION_NAME.as_bytes())
+        } {
+            Ok(free_fn) => free_fn(self.serialized_state_ptr, self.serialized_state_len),
+            Err(_) => log::warn!(target: RELOAD, "Failed to get free_serialized_data function"),
+        }
+        self.serialized_state_ptr = std::ptr::null_mut();
+        self.serialized_state_len = 0;
+    }
+
+    /// Serializes the state with the functions of `library`, which has to be
+    /// the library that built or took over the state.
+    fn serialize_state_from(&mut self, library: &Library) -> Result<(), HotIceError> {
+        let start = Instant::now();
+        self.free_serialized_state(library);
+
         let Ok(serialize_fn) = (unsafe {
-            reloader
-                .get_symbol::<fn(&P::State, *mut *mut u8, *mut usize) -> Result<(), HotIceError>>(
-                    SERIALIZE_STATE_FUNCTION_NAME.as_bytes(),
-                )
+            library.get::<fn(&P::State, *mut *mut u8, *mut usize) -> Result<(), HotIceError>>(
+                SERIALIZE_STATE_FUNCTION_NAME.as_bytes(),
+            )
         }) else {
             return Err(HotIceError::FunctionNotFound(SERIALIZE_STATE_FUNCTION_NAME));
         };
@@ -1620,11 +4275,172 @@ where
             &mut self.serialized_state_len,
         )?;
 
-        info!("Size of serialized state: {}", self.serialized_state_len);
+        self.serialize_time = start.elapsed();
+        info!(target: RELOAD, "Size of serialized state: {}", self.serialized_state_len);
         Ok(())
     }
 
+    /// Decides how the state kept for handoff reaches the loaded library:
+    /// it is adopted as is if the library reports the same state type hash,
+    /// otherwise the `retired` library serializes it, or only the fields
+    /// whose type changed, on a background thread. The returned task
+    /// delivers [`Message::ReloadComplete`] with `retired` again once it
+    /// finished.
+    fn begin_state_handoff(&mut self, retired: Option<&SharedRetired>) -> Option<Task<Message<P>>> {
+        let previous_hash = self.pending_handoff.take()?;
+
+        let hash = self
+            .lib_reloader
+            .as_ref()
+            .and_then(|lib_reloader| lib_reloader.lock().ok())
+            .filter(|lib| fingerprint::check_library(lib).is_ok())
+            .and_then(|lib| state_handoff_hash(&lib));
+        // `0` is reported for states without a verified `TypeHash`.
+        if previous_hash != 0 && hash == Some(previous_hash) {
+            self.adopt_state = true;
+            return None;
+        }
+
+        let library =
+            retired.and_then(|retired| Some(Arc::clone(&retired.lock().ok()?.as_ref()?.library)));
+        let Some(library) = library else {
+            log::error!(target: RELOAD, "The library holding the state was unloaded");
+            return None;
+        };
+
+        let job = match hash {
+            Some(_) => self.serialize_changed_fields(&library),
+            None => Ok(None),
+        };
+        let job = match job {
+            Ok(Some(job)) => Ok(job),
+            Ok(None) => {
+                log::info!(target: RELOAD, "State type changed, serializing the state");
+                self.serialize_state_job(&library)
+            }
+            Err(e) => Err(e),
+        };
+        let done = Message::ReloadComplete(retired.cloned());
+        match job.and_then(|job| self.spawn_serializer(job, done)) {
+            Ok(serializing) => Some(serializing),
+            Err(e) => {
+                log::error!(target: RELOAD, "{}", e);
+                self.field_transfer = None;
+                None
+            }
+        }
+    }
+
+    /// Serializes the whole state with the functions of `library`, which
+    /// built or took over the state, on the serializer thread.
+    fn serialize_state_job(
+        &mut self,
+        library: &Arc<Library>,
+    ) -> Result<SerializeJob<P::State>, HotIceError> {
+        self.free_serialized_state(library);
+
+        let Ok(serialize_fn) = (unsafe {
+            library.get::<fn(&P::State, *mut *mut u8, *mut usize) -> Result<(), HotIceError>>(
+                SERIALIZE_STATE_FUNCTION_NAME.as_bytes(),
+            )
+        }) else {
+            return Err(HotIceError::FunctionNotFound(SERIALIZE_STATE_FUNCTION_NAME));
+        };
+        let serialize_fn = *serialize_fn;
+
+        // The library stays loaded until the thread finished.
+        let library = Arc::clone(library);
+        Ok(Box::new(
+            move |state: &P::State, out_ptr: *mut *mut u8, out_len: *mut usize| {
+                let _library = library;
+                serialize_fn(state, out_ptr, out_len)
+            },
+        ))
+    }
+
+    /// Serializes the fields of the state whose type changed with the
+    /// functions of the `retired` library, the loaded one moves the others.
+    /// Only fields both libraries report the same non-zero
+    /// [`TypeHash`](crate::TypeHash) for are moved. `None` if either library
+    /// does not list the fields of its state.
+    fn serialize_changed_fields(
+        &mut self,
+        retired: &Arc<Library>,
+    ) -> Result<Option<SerializeJob<P::State>>, HotIceError> {
+        let Ok(previous_fn) = (unsafe {
+            retired
+                .get::<fn(*mut usize) -> *const StateField>(STATE_FIELDS_FUNCTION_NAME.as_bytes())
+        }) else {
+            return Ok(None);
+        };
+        let current = self
+            .lib_reloader
+            .as_ref()
+            .and_then(|lib_reloader| lib_reloader.lock().ok())
+            .and_then(|lib| {
+                let current_fn = unsafe {
+                    lib.get_symbol::<fn(*mut usize) -> *const StateField>(
+                        STATE_FIELDS_FUNCTION_NAME,
+                    )
+                }
+                .ok()?;
+                Some(state_fields(current_fn))
+            });
+        let Some(current) = current else {
+            return Ok(None);
+        };
+        let Ok(serialize_fn) = (unsafe {
+            retired.get::<fn(
+                &P::State,
+                *const u64,
+                usize,
+                *mut *mut u8,
+                *mut usize,
+            ) -> Result<(), HotIceError>>(
+                SERIALIZE_STATE_FIELDS_FUNCTION_NAME.as_bytes()
+            )
+        }) else {
+            return Ok(None);
+        };
+        let serialize_fn = *serialize_fn;
+
+        let previous = state_fields(*previous_fn);
+        let (mut moved, mut changed) = (Vec::new(), Vec::new());
+        for field in &previous {
+            match current.iter().find(|c| c.name_hash == field.name_hash) {
+                Some(c) if c.type_hash != 0 && c.type_hash == field.type_hash => {
+                    moved.push(field.name_hash)
+                }
+                Some(_) => changed.push(field.name_hash),
+                None => {}
+            }
+        }
+
+        self.free_serialized_state(retired);
+        info!(
+            target: RELOAD,
+            "Moving {} state fields, serializing {}",
+            moved.len(),
+            changed.len()
+        );
+        self.field_transfer = Some(FieldTransfer {
+            previous,
+            moved,
+            serialized: changed.len(),
+            library: Arc::clone(retired),
+        });
+
+        let library = Arc::clone(retired);
+        Ok(Some(Box::new(
+            move |state: &P::State, out_ptr: *mut *mut u8, out_len: *mut usize| {
+                let _library = library;
+                serialize_fn(state, changed.as_ptr(), changed.len(), out_ptr, out_len)
+            },
+        )))
+    }
+
     fn deserialize_state(&mut self) -> Result<(), HotIceError> {
+        self.join_serializer();
         let reloader = self
             .lib_reloader
             .as_ref()
@@ -1634,29 +4450,98 @@ where
             return Err(HotIceError::LockAcquisitionError);
         };
 
-        let Ok(deserialize_fn) = (unsafe {
-            reloader.get_symbol::<fn(&mut P::State, *const u8, usize) -> Result<(), HotIceError>>(
-                DESERIALIZE_STATE_FUNCTION_NAME.as_bytes(),
-            )
-        }) else {
-            return Err(HotIceError::FunctionNotFound(
-                DESERIALIZE_STATE_FUNCTION_NAME,
-            ));
-        };
+        let start = Instant::now();
+        if std::mem::take(&mut self.adopt_state) {
+            let Ok(adopt_fn) = (unsafe {
+                reloader.get_symbol::<fn(&mut P::State) -> Result<(), HotIceError>>(
+                    ADOPT_STATE_FUNCTION_NAME,
+                )
+            }) else {
+                return Err(HotIceError::FunctionNotFound(ADOPT_STATE_FUNCTION_NAME));
+            };
 
-        deserialize_fn(
-            &mut self.state,
-            self.serialized_state_ptr,
-            self.serialized_state_len,
-        )?;
+            // The state is handed over even if its rebuild panicked.
+            let result = adopt_fn(&mut self.state);
+            metrics::set_state_transfer(StateTransfer::Handoff, Duration::ZERO, start.elapsed());
+            info!(target: RELOAD, "Handed the state over without serializing it");
+            return result;
+        }
+
+        let transfer = if let Some(fields) = self.field_transfer.take() {
+            let Ok(restore_fn) = (unsafe {
+                reloader.get_symbol::<fn(
+                    &mut P::State,
+                    *const StateField,
+                    usize,
+                    *const u8,
+                    usize,
+                    *mut *mut u8,
+                ) -> Result<(), HotIceError>>(
+                    RESTORE_STATE_FIELDS_FUNCTION_NAME
+                )
+            }) else {
+                return Err(HotIceError::FunctionNotFound(
+                    RESTORE_STATE_FIELDS_FUNCTION_NAME,
+                ));
+            };
+
+            let mut previous_state = std::ptr::null_mut();
+            let result = restore_fn(
+                &mut self.state,
+                fields.previous.as_ptr(),
+                fields.previous.len(),
+                self.serialized_state_ptr,
+                self.serialized_state_len,
+                &mut previous_state,
+            );
+            // The fields not moved are dropped with the types of the previous
+            // library, the state is leaked if it cannot.
+            match unsafe {
+                fields.library.get::<fn(*mut u8, *const u64, usize)>(
+                    DROP_STATE_FIELDS_FUNCTION_NAME.as_bytes(),
+                )
+            } {
+                Ok(drop_fn) if !previous_state.is_null() => {
+                    drop_fn(previous_state, fields.moved.as_ptr(), fields.moved.len())
+                }
+                Ok(_) => {}
+                Err(_) => log::warn!(target: RELOAD, "Failed to get drop_state_fields function"),
+            }
+            result?;
+            StateTransfer::Fields {
+                moved: fields.moved.len(),
+                serialized: fields.serialized,
+                bytes: self.serialized_state_len,
+            }
+        } else {
+            let Ok(deserialize_fn) = (unsafe {
+                reloader
+                    .get_symbol::<fn(&mut P::State, *const u8, usize) -> Result<(), HotIceError>>(
+                        DESERIALIZE_STATE_FUNCTION_NAME,
+                    )
+            }) else {
+                return Err(HotIceError::FunctionNotFound(
+                    DESERIALIZE_STATE_FUNCTION_NAME,
+                ));
+            };
+
+            deserialize_fn(
+                &mut self.state,
+                self.serialized_state_ptr,
+                self.serialized_state_len,
+            )?;
+            StateTransfer::Serialized {
+                bytes: self.serialized_state_len,
+            }
+        };
+        metrics::set_state_transfer(transfer, self.serialize_time, start.elapsed());
 
         // Free the memory after successful deserialization
         if !self.serialized_state_ptr.is_null() && self.serialized_state_len > 0 {
             let Ok(free_fn) = (unsafe {
-                reloader
-                    .get_symbol::<fn(*mut u8, usize)>(FREE_SERIALIZED_DATA_FUNCTION_NAME.as_bytes())
+                reloader.get_symbol::<fn(*mut u8, usize)>(FREE_SERIALIZED_DATA_FUNCTION_NAME)
             }) else {
-                log::warn!("Failed to get free_serialized_data function");
+                log::warn!(target: RELOAD, "Failed to get free_serialized_data function");
                 // Continue anyway
                 self.serialized_state_ptr = std::ptr::null_mut();
                 self.serialized_state_len = 0;
@@ -1674,29 +4559,29 @@ where
     /// Sync all tracked fonts to the loaded library's font system
     fn sync_fonts_to_library(&self) {
         log::debug!(
+            target: RELOAD,
             "sync_fonts_to_library called with {} fonts",
             self.loaded_fonts.len()
         );
 
         let Some(lib_reloader) = &self.lib_reloader else {
-            log::debug!("lib_reloader is None");
+            log::debug!(target: RELOAD, "lib_reloader is None");
             return;
         };
 
         let Ok(reloader) = lib_reloader.lock() else {
-            log::debug!("Failed to acquire lock on lib_reloader");
+            log::debug!(target: RELOAD, "Failed to acquire lock on lib_reloader");
             return;
         };
 
-        log::debug!("Attempting to get font loading function symbol");
+        log::debug!(target: RELOAD, "Attempting to get font loading function symbol");
 
         // Get the font loading function from the library
         let Ok(load_font_fn) = (unsafe {
-            reloader.get_symbol::<fn(*const u8, usize)>(
-                hot_ice_common::LOAD_FONT_FUNCTION_NAME.as_bytes(),
-            )
+            reloader.get_symbol::<fn(*const u8, usize)>(hot_ice_common::LOAD_FONT_FUNCTION_NAME)
         }) else {
             log::debug!(
+                target: RELOAD,
                 "Font loading function not found in library. Function name: {}",
                 hot_ice_common::LOAD_FONT_FUNCTION_NAME
             );
@@ -1704,6 +4589,7 @@ where
         };
 
         log::debug!(
+            target: RELOAD,
             "Font loading function found, loading {} fonts",
             self.loaded_fonts.len()
         );
@@ -1711,11 +4597,273 @@ where
         // Load each tracked font into the library
         for (i, font_cow) in self.loaded_fonts.iter().enumerate() {
             let font_bytes: &[u8] = font_cow.as_ref();
-            log::debug!("Loading font {} with {} bytes", i, font_bytes.len());
+            log::debug!(target: RELOAD, "Loading font {} with {} bytes", i, font_bytes.len());
             load_font_fn(font_bytes.as_ptr(), font_bytes.len());
         }
 
-        log::info!("Synced {} fonts to loaded library", self.loaded_fonts.len());
+        log::info!(target: RELOAD, "Synced {} fonts to loaded library", self.loaded_fonts.len());
+    }
+
+    /// Hands the messages received during the reload to the new library,
+    /// in the order they arrived. They are dropped if it failed to load.
+    fn replay_buffered_messages(&mut self, program: &P) -> Task<Message<P>> {
+        let messages = std::mem::take(&mut self.buffered_messages);
+        if messages.is_empty() {
+            return Task::none();
+        }
+        if self.reloader_state != ReloaderState::Ready {
+            log::warn!(
+                target: RELOAD,
+                "Dropping {} messages received during a failed reload",
+                messages.len()
+            );
+            return Task::none();
+        }
+
+        log::debug!(target: RELOAD, "Replaying {} messages", messages.len());
+        Task::batch(
+            messages
+                .into_iter()
+                .map(|message| self.update(program, Message::AppMessage(message)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// The previous library to compare the current one with: `retired` after
+    /// a reload, otherwise the newest library still retained. Its view may
+    /// only draw the state if both libraries report the same
+    /// [`TypeHash`](crate::TypeHash) of the state.
+    fn compared_library(&self, retired: Option<&Arc<Library>>) -> Result<ComparedLibrary, String> {
+        let library = match retired {
+            Some(library) => Arc::clone(library),
+            None => self
+                .retained_libraries
+                .lock()
+                .ok()
+                .and_then(|retained| retained.back().map(|retired| Arc::clone(&retired.library)))
+                .ok_or_else(|| {
+                    "The previous library is no longer loaded, it is compared from the next \
+                     reload on"
+                        .to_string()
+                })?,
+        };
+
+        let state_type_hash = |library: &Library| {
+            unsafe { library.get::<fn() -> u64>(STATE_TYPE_HASH_FUNCTION_NAME.as_bytes()) }
+                .ok()
+                .map(|hash_fn| hash_fn())
+        };
+        let current = self
+            .lib_reloader
+            .as_ref()
+            .and_then(|lib_reloader| lib_reloader.lock().ok())
+            .and_then(|lib| lib.library().and_then(state_type_hash));
+        // Only a state whose full definition is known to be the same in both
+        // libraries can be drawn by the previous one, `0` is a state that
+        // does not derive `TypeHash`.
+        match current {
+            None | Some(0) => {
+                return Err(
+                    "Comparing needs a #[hot_state] state deriving TypeHash, the previous \
+                     library can't be checked to draw this state"
+                        .to_string(),
+                );
+            }
+            Some(_) if state_type_hash(&library) != current => {
+                return Err(
+                    "The state type changed since the previous library, it can't draw this \
+                     state"
+                        .to_string(),
+                );
+            }
+            Some(_) => {}
+        }
+
+        let handle = LibraryHandle::new();
+        handle.publish(Some(Arc::new(ResolvedGeneration::resolve(library))));
+        Ok(ComparedLibrary {
+            handle,
+            generation: self
+                .generations
+                .iter()
+                .rev()
+                .nth(1)
+                .map(|record| record.generation),
+        })
+    }
+
+    /// Prepares a freshly loaded library and marks the reloader ready.
+    ///
+    /// If the library was built with a different rustc, `hot_ice` or iced
+    /// version nothing is called into it and the reloader shows an error
+    /// until a compatible library is loaded. `restore_state` deserializes the
+    /// state saved from the previous library.
+    fn prepare_loaded_library(&mut self, restore_state: bool) {
+        let (loaded, generations) = self
+            .lib_reloader
+            .as_ref()
+            .and_then(|lib_reloader| lib_reloader.lock().ok())
+            .map(|lib| {
+                let generations = lib.history().records().cloned().collect();
+                (lib.loaded_lib_file().is_some(), generations)
+            })
+            .unwrap_or_default();
+        self.generations = generations;
+        self.stale_artifact = None;
+        self.missing_artifact = if loaded {
+            None
+        } else {
+            build_command(&self.reloader_settings, self.lib_name).inspect(|command| {
+                log::warn!(
+                    target: RELOAD,
+                    "No library found in {}, build it with: {}",
+                    self.reloader_settings.lib_dir,
+                    command
+                );
+            })
+        };
+
+        let fingerprint = match &self.lib_reloader {
+            Some(lib_reloader) => match lib_reloader.lock() {
+                Ok(lib) => fingerprint::check_library(&lib)
+                    .and_then(|()| executor::check_executor::<P::Executor>(&lib)),
+                Err(_) => Ok(()),
+            },
+            None => Ok(()),
+        };
+
+        if let Err(mismatch) = fingerprint {
+            log::error!(
+                target: DISPATCH,
+                "Refusing to call into incompatible library: {}",
+                mismatch
+            );
+            self.incompatible_library = true;
+            self.reloader_state =
+                ReloaderState::Error(ReloaderError::IncompatibleLibrary(mismatch));
+            return;
+        }
+        self.incompatible_library = false;
+        if let Some(lib) = self.lib_reloader.as_ref().and_then(|lib| lib.lock().ok()) {
+            fingerprint::check_panic_strategy(&lib);
+        }
+
+        if restore_state {
+            self.deserialize_state()
+                .inspect_err(|e| log::error!(target: RELOAD, "{}", e))
+                .ok();
+        }
+
+        self.sync_fonts_to_library();
+        self.sync_interner_to_library();
+        self.sync_host_resources_to_library();
+        self.sync_context_to_library();
+        self.start_worker_from_library();
+        self.log_export_diff();
+        subscriptions::mark_reload();
+        metrics::reset();
+        blocking::cancel_older(metrics::reloads());
+
+        self.reloader_state = ReloaderState::Ready;
+    }
+
+    /// Hands the host's interner to the loaded library so strings interned
+    /// there outlive the library.
+    fn sync_interner_to_library(&self) {
+        let Some(lib_reloader) = &self.lib_reloader else {
+            return;
+        };
+
+        let Ok(reloader) = lib_reloader.lock() else {
+            log::debug!(target: RELOAD, "Failed to acquire lock on lib_reloader");
+            return;
+        };
+
+        let Ok(set_interner) = (unsafe {
+            reloader.get_symbol::<fn(fn(&str) -> &'static str)>(
+                hot_ice_common::SET_INTERNER_FUNCTION_NAME,
+            )
+        }) else {
+            log::debug!(
+                target: RELOAD,
+                "Interner function not found in library. Function name: {}",
+                hot_ice_common::SET_INTERNER_FUNCTION_NAME
+            );
+            return;
+        };
+
+        set_interner(crate::intern::intern);
+    }
+
+    /// Lends the resources registered with `HotIce::host_resource` to the
+    /// loaded library.
+    fn sync_host_resources_to_library(&self) {
+        let Some(lib_reloader) = &self.lib_reloader else {
+            return;
+        };
+
+        let Ok(reloader) = lib_reloader.lock() else {
+            log::debug!(target: RELOAD, "Failed to acquire lock on lib_reloader");
+            return;
+        };
+
+        let Ok(set_host_resources) = (unsafe {
+            reloader.get_symbol::<fn(fn(u64) -> Option<*const ()>)>(
+                hot_ice_common::SET_HOST_RESOURCES_FUNCTION_NAME,
+            )
+        }) else {
+            log::debug!(
+                target: RELOAD,
+                "Host resource function not found in library. Function name: {}",
+                hot_ice_common::SET_HOST_RESOURCES_FUNCTION_NAME
+            );
+            return;
+        };
+
+        set_host_resources(crate::host_resource::lookup);
+    }
+
+    /// Hands the host's context to the loaded library for
+    /// `Context::current`.
+    fn sync_context_to_library(&self) {
+        let Some(lib_reloader) = &self.lib_reloader else {
+            return;
+        };
+
+        let Ok(reloader) = lib_reloader.lock() else {
+            log::debug!(target: RELOAD, "Failed to acquire lock on lib_reloader");
+            return;
+        };
+
+        let Ok(set_host_context) = (unsafe {
+            reloader.get_symbol::<fn(&'static crate::Context)>(
+                hot_ice_common::SET_HOST_CONTEXT_FUNCTION_NAME,
+            )
+        }) else {
+            log::debug!(
+                target: RELOAD,
+                "Host context function not found in library. Function name: {}",
+                hot_ice_common::SET_HOST_CONTEXT_FUNCTION_NAME
+            );
+            return;
+        };
+
+        set_host_context(crate::Context::host());
+    }
+
+    /// Logs how the hot functions exported by the loaded library compare to
+    /// the ones the host registered.
+    fn log_export_diff(&self) {
+        let Some(lib_reloader) = &self.lib_reloader else {
+            return;
+        };
+
+        let Ok(lib) = lib_reloader.lock() else {
+            log::debug!(target: RELOAD, "Failed to acquire lock on lib_reloader");
+            return;
+        };
+
+        registry::log_export_diff(&lib);
     }
 
     /// Starts a cdylib worker thread from the currently loaded library.
@@ -1725,27 +4873,39 @@ where
     /// Streams from app tasks are sent to the worker for async polling.
     fn start_worker_from_library(&mut self) {
         let Some(lib_reloader) = &self.lib_reloader else {
-            log::warn!("Cannot start worker: lib_reloader is None");
+            log::warn!(target: WORKER, "Cannot start worker: lib_reloader is None");
             return;
         };
 
         let Ok(lib) = lib_reloader.lock() else {
-            log::error!("Cannot start worker: failed to lock lib_reloader");
+            log::error!(target: WORKER, "Cannot start worker: failed to lock lib_reloader");
             return;
         };
 
-        let Some(proxy) = crate::executor::get_global_proxy::<Message<P>>() else {
-            log::error!("Cannot start worker: global proxy not set");
+        let Some(sender) = crate::executor::get_action_sender::<Message<P>>() else {
+            log::error!(target: WORKER, "Cannot start worker: global proxy not set");
             return;
         };
 
-        match unsafe { CdylibWorker::start(&lib, proxy) } {
+        let settings = &self.reloader_settings;
+        match unsafe {
+            CdylibWorker::start(
+                &lib,
+                sender,
+                &settings.worker_queue,
+                settings.worker_threads,
+            )
+        } {
             Ok(worker) => {
-                log::info!("Started cdylib worker thread");
+                log::info!(
+                    target: WORKER,
+                    "Started {} cdylib worker thread(s)",
+                    settings.worker_threads.max(1)
+                );
                 self.worker = Some(Arc::new(worker));
             }
             Err(err) => {
-                log::warn!("Failed to start worker from library: {}", err);
+                log::warn!(target: WORKER, "Failed to start worker from library: {}", err);
                 // Not fatal — the library may not export worker functions
                 // (e.g. if export_executor! was not used)
             }
@@ -1760,11 +4920,21 @@ where
     /// by the stream are sent back to the event loop via `Proxy::send_action()`.
     ///
     /// If no worker is running (e.g. the library doesn't export executor
-    /// functions), the task is returned as-is and runs on the binary's
-    /// executor — which works for non-tokio-dependent tasks.
+    /// functions), the task runs on the binary's executor — which works for
+    /// non-tokio-dependent tasks — and keeps the library generation that
+    /// produced it loaded until it has finished.
     fn intercept_app_task(&self, task: Task<Message<P>>) -> Task<Message<P>> {
         let Some(worker) = self.worker.as_ref() else {
-            return task;
+            if self.lib_reloader.is_none() {
+                return task;
+            }
+            // The stream may run code of the current library, pin the
+            // generation until it has finished.
+            let pin = Arc::clone(&self.host_task_pin);
+            return task.map(move |message| {
+                let _pin = &pin;
+                message
+            });
         };
 
         let Some(stream) = iced_winit::runtime::task::into_stream(task) else {
@@ -1774,36 +4944,394 @@ where
         worker.run_stream(stream);
         Task::none()
     }
+
+    /// Whether a library is loaded and the program can be updated.
+    pub(crate) fn is_ready(&self) -> bool {
+        self.reloader_state == ReloaderState::Ready
+    }
+
+    /// Whether the program's functions may be called with the state: a
+    /// library is loaded and no async `update` holds the state.
+    fn program_ready(&self) -> bool {
+        self.is_ready() && !self.state_lent
+    }
+
+    /// Marks the state as lent if the update left an async update running
+    /// with it, see [`lent_state`]. Returns the task announcing that it was
+    /// returned.
+    fn lend_state(&mut self) -> Task<Message<P>> {
+        if self.state_lent || !lent_state::is_lent() {
+            return Task::none();
+        }
+        log::debug!(target: DISPATCH, "An async update holds the state");
+        self.state_lent = true;
+        Task::future(lent_state::returned()).map(|()| Message::StateReturned)
+    }
+
+    /// Hands the state back to the program once the async update holding
+    /// it finished: the messages received in the meantime are replayed and
+    /// a reload waiting for it starts.
+    fn state_returned(&mut self, program: &P) -> Task<Message<P>> {
+        if !self.state_lent {
+            return Task::none();
+        }
+        self.state_lent = false;
+        let replayed = self.replay_buffered_messages(program);
+        if !std::mem::take(&mut self.reload_after_update) {
+            return replayed;
+        }
+        // A replayed message may have lent the state again.
+        let reload = self.update(program, Message::AboutToReload);
+        Task::batch([replayed, reload])
+    }
+
+    /// Keeps `message` until the program can handle it, dropping the
+    /// oldest one once the buffer is full.
+    fn buffer_message(&mut self, message: MessageSource<P::Message>) {
+        if self.buffered_messages.len() == MAX_BUFFERED_MESSAGES {
+            log::warn!(
+                target: RELOAD,
+                "Message buffer is full, dropping the oldest message"
+            );
+            self.buffered_messages.pop_front();
+        }
+        self.buffered_messages.push_back(message);
+    }
+
+    pub(crate) fn state(&self) -> &P::State {
+        &self.state
+    }
+
+    /// The loaded library, `None` while no library is loaded.
+    pub(crate) fn library(&self) -> Option<&LibraryHandle> {
+        self.library.as_ref()
+    }
+
+    /// The state and the loaded library, for calling the program's
+    /// functions without the reloader, see [`bench`](crate::bench).
+    pub(crate) fn state_and_library(&mut self) -> (&mut P::State, Option<&LibraryHandle>) {
+        self.join_serializer();
+        (&mut *self.state, self.library.as_ref())
+    }
+
+    pub(crate) fn function_state(&self, function: HotFunction) -> FunctionState {
+        self.function_states()
+            .into_iter()
+            .find_map(|(f, state)| (f == function).then_some(state))
+            .unwrap_or(FunctionState::None)
+    }
+
+    /// Waits for the library to change and runs the reload handshake the
+    /// event loop would run, without showing the reloading screen. Returns
+    /// `false` if the library did not change within `timeout`.
+    pub(crate) fn reload_blocking(&mut self, program: &P, timeout: Duration) -> bool {
+        let Some(lib_reloader) = self.lib_reloader.clone() else {
+            return false;
+        };
+
+        let deadline = Instant::now() + timeout;
+        while !lib_reloader.lock().is_ok_and(|lib| lib.has_changed()) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // There is no user to wait for without an event loop.
+        self.deferred_reload = None;
+        let _ = self.begin_reload();
+        // There are no widgets to capture without an event loop.
+        if self.capturing_widgets.is_some() {
+            let _ = self.update(program, Message::WidgetsCaptured(WidgetSnapshot::default()));
+        }
+        let _ = self.update(program, Message::SendReadySignal);
+        if self.serializing.is_some() {
+            let _ = self.update(program, Message::StateSerialized);
+        }
+        let _ = self.update_channel.1.try_recv();
+
+        let result = match lib_reloader.lock() {
+            Ok(mut lib) => lib.update(),
+            Err(_) => return false,
+        };
+        let retired = match result {
+            Ok(crate::lib_reloader::UpdateResult::Reloaded { retired }) => retired,
+            Ok(_) => None,
+            Err(err) => {
+                log::error!(target: RELOAD, "{err}");
+                None
+            }
+        };
+
+        let retired = retired.map(|r| Arc::new(Mutex::new(Some(r))));
+        let _ = self.update(program, Message::ReloadComplete(retired.clone()));
+        // The state was handed to a background thread to be serialized.
+        if self.serializing.is_some() {
+            let _ = self.update(program, Message::ReloadComplete(retired));
+        }
+        true
+    }
+}
+
+/// Copies the fields a library lists with `fields_fn`.
+fn state_fields(fields_fn: fn(*mut usize) -> *const StateField) -> Vec<StateField> {
+    let mut len = 0;
+    let fields = fields_fn(&mut len);
+    if fields.is_null() {
+        return Vec::new();
+    }
+    unsafe { std::slice::from_raw_parts(fields, len) }.to_vec()
+}
+
+/// State type hash of a library that can hand its state to the next one
+/// without serializing it, see [`ReloaderSettings::state_handoff`]. `0` if
+/// the state does not derive [`TypeHash`](crate::TypeHash).
+fn state_handoff_hash(lib: &LibReloader) -> Option<u64> {
+    let exports = |name: &str| unsafe { lib.get_symbol::<*const ()>(name) }.is_ok();
+    if !exports(SET_HOST_ALLOCATOR_FUNCTION_NAME) || !exports(FINGERPRINT_FUNCTION_NAME) {
+        return None;
+    }
+
+    let hash_fn = unsafe { lib.get_symbol::<fn() -> u64>(STATE_TYPE_HASH_FUNCTION_NAME) }.ok()?;
+    Some(hash_fn())
 }
 
-fn build_args<'a>(library_name: &'a str, feature: Option<&'a str>) -> Vec<&'a str> {
-    let mut args = vec![
-        "rustc",
-        "--package",
-        library_name,
-        "--lib",
-        "--crate-type",
-        "cdylib",
-        "--profile",
-        "dev",
-    ];
-    if let Some(feature) = feature {
-        args.push("--features");
-        args.push(feature);
-    }
-    args
+/// Waits until every task pinning a retired generation has finished,
+/// returns `false` if some are still running after `timeout`.
+fn wait_for_host_tasks(pin: &Arc<()>, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while Arc::strong_count(pin) > 1 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    true
 }
 
-trait EnvVariables {
-    fn environment_variables(&mut self, target_dir: &str) -> &mut Self;
+/// Keeps a drained library loaded, then unloads the oldest retained
+/// generations until their combined size fits within `max_retained_bytes`.
+fn retain_library(
+    retained: &Mutex<VecDeque<RetiredLibrary>>,
+    retired: RetiredLibrary,
+    max_retained_bytes: Option<u64>,
+) {
+    let Ok(mut retained) = retained.lock() else {
+        return;
+    };
+
+    retained.push_back(retired);
+    let mut retained_bytes: u64 = retained.iter().map(|lib| lib.size).sum();
+
+    while max_retained_bytes.is_some_and(|max| retained_bytes > max) {
+        let Some(oldest) = retained.pop_front() else {
+            break;
+        };
+        log::info!(
+            target: RELOAD,
+            "Dropping retired library: {:?} ({} bytes)",
+            oldest.file_path,
+            oldest.size
+        );
+        retained_bytes -= oldest.size;
+        drop(oldest);
+    }
+
+    metrics::set_retained(retained.len(), retained_bytes);
 }
 
-impl EnvVariables for Command {
-    fn environment_variables(&mut self, target_dir: &str) -> &mut Self {
-        self.env("CARGO_PROFILE_DEV_OPT_LEVEL", "0")
-            .env("CARGO_PROFILE_DEV_CODEGEN_UNITS", "1")
-            .env("CARGO_PROFILE_DEV_DEBUG", "false")
-            .env("CARGO_PROFILE_DEV_LTO", "false")
-            .env("CARGO_TARGET_DIR", target_dir)
+/// Shell command equivalent to the build the reloader runs, shown when the
+/// library has never been built. `None` if the compiler builds nothing.
+pub(crate) fn build_command(settings: &ReloaderSettings, library_name: &str) -> Option<String> {
+    let ui_crate = settings.ui_crate(library_name);
+    let root = ui_crate.workspace_root.clone();
+    let request = CompileRequest {
+        library: library_name.to_string(),
+        workspace_root: root.clone(),
+        target_dir: ui_crate.target_dir(&settings.target_dir),
+        feature: settings.feature.clone(),
+        fast_linker: settings.fast_linker,
+        rustc_args: Vec::new(),
+    };
+    let command = settings.compiler.command(&request)?;
+    Some(format!(
+        "cd {} && {}",
+        root.display(),
+        shell_line(&command, true)
+    ))
+}
+
+/// Resizes and moves of the windows, tracked for
+/// [`ReloaderSettings::persist_window`].
+fn window_changes<P: HotProgram + 'static>() -> Subscription<Message<P>> {
+    iced_futures::event::listen_with(|event, _, id| match event {
+        iced_core::Event::Window(window::Event::Resized(size)) => {
+            Some(Message::WindowChanged(id, WindowChange::Resized(size)))
+        }
+        iced_core::Event::Window(window::Event::Moved(position)) => {
+            Some(Message::WindowChanged(id, WindowChange::Moved(position)))
+        }
+        _ => None,
+    })
+}
+
+/// Events starting or ending an interaction a reload waits for, captured
+/// or not.
+fn interaction_events<P: HotProgram + 'static>() -> Subscription<Message<P>> {
+    iced_futures::event::listen_with(|event, _, _| {
+        Interaction::is_relevant(&event).then_some(Message::Interaction(event))
+    })
+}
+
+/// Events no widget captured, passed to the program's `on_event`.
+fn uncaptured_events<P: HotProgram + 'static>() -> Subscription<Message<P>> {
+    iced_futures::event::listen_with(|event, status, id| match status {
+        event::Status::Ignored => Some(Message::Event(event, id)),
+        event::Status::Captured => None,
+    })
+}
+
+/// Frame of the banners about the library artifact.
+fn artifact_banner<'a, Message: 'a, Renderer>(
+    content: impl Into<Element<'a, Message, Theme, Renderer>>,
+) -> Element<'a, Message, Theme, Renderer>
+where
+    Renderer: iced_core::text::Renderer + 'a,
+{
+    container(
+        container(content)
+            .style(|_| ContainerStyle {
+                background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.85))),
+                border: Border {
+                    radius: 6.0.into(),
+                    color: STATUS_ORANGE,
+                    width: 1.0,
+                },
+                ..Default::default()
+            })
+            .max_width(640)
+            .padding(12),
+    )
+    .center_x(Length::Fill)
+    .align_top(Length::Fill)
+    .padding(12)
+    .into()
+}
+
+/// Creates `lib_dir` in the workspace root if it does not exist anywhere
+/// above the working directory yet, so it can be watched before the first
+/// build.
+pub(crate) fn create_missing_lib_dir(lib_dir: &str) {
+    let lib_dir = PathBuf::from(lib_dir);
+    let exists = std::env::current_dir()
+        .map(|cwd| cwd.ancestors().any(|dir| dir.join(&lib_dir).exists()))
+        .unwrap_or(false);
+    if lib_dir.exists() || exists {
+        return;
+    }
+
+    let lib_dir = match MetadataCommand::new().no_deps().exec() {
+        Ok(metadata) if lib_dir.is_relative() => {
+            metadata.workspace_root.into_std_path_buf().join(lib_dir)
+        }
+        _ => lib_dir,
+    };
+    log::warn!(target: RELOAD, "{} does not exist, creating it", lib_dir.display());
+    if let Err(err) = std::fs::create_dir_all(&lib_dir) {
+        log::error!(target: RELOAD, "Failed to create {}: {}", lib_dir.display(), err);
+    }
+}
+
+/// What the compiler builds for the settings, asks cargo for the workspace
+/// of the UI crate.
+fn compile_request(
+    library: &str,
+    ui_crate: &UiCrate,
+    target_dir: &str,
+    feature: Option<String>,
+    fast_linker: bool,
+    prelink: bool,
+) -> CompileRequest {
+    let metadata = ui_crate
+        .metadata()
+        .exec()
+        .expect("Failed to get cargo metadata");
+    let target_dir = ui_crate.target_dir(target_dir);
+
+    CompileRequest {
+        library: library.to_string(),
+        rustc_args: prelink_args(prelink, &metadata, library, &target_dir),
+        workspace_root: metadata.workspace_root.into_std_path_buf(),
+        target_dir,
+        feature,
+        fast_linker,
+    }
+}
+
+/// Extra `rustc` arguments linking the library against the prelinked
+/// dependencies, see [`prelink`].
+fn prelink_args(
+    prelink: bool,
+    metadata: &cargo_metadata::Metadata,
+    library_name: &str,
+    target_dir: &str,
+) -> Vec<String> {
+    if !prelink {
+        return Vec::new();
+    }
+
+    let profile_dir = metadata
+        .workspace_root
+        .as_std_path()
+        .join(target_dir)
+        .join(HOT_PROFILE);
+    prelink::rustc_args(metadata, library_name, &profile_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn retained_libraries_are_evicted_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("hot_ice_retain_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let library: Arc<libloading::Library> =
+            Arc::new(libloading::os::unix::Library::this().into());
+        let retire = |generation: u32| {
+            let file_path = dir.join(format!("ui-hot-{generation}"));
+            std::fs::write(&file_path, b"").unwrap();
+            RetiredLibrary {
+                library: Arc::clone(&library),
+                file_path,
+                size: 100,
+                keep_file: false,
+            }
+        };
+        let current = retire(5);
+        let retained = Mutex::new(VecDeque::new());
+
+        for generation in 1..=3 {
+            retain_library(&retained, retire(generation), Some(250));
+        }
+        assert_eq!(metrics::metrics().retained_bytes, 200);
+        let kept: Vec<_> = retained
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|lib| lib.file_path.clone())
+            .collect();
+        assert_eq!(kept, [dir.join("ui-hot-2"), dir.join("ui-hot-3")]);
+        assert!(!dir.join("ui-hot-1").exists());
+
+        // Only retired generations are handed over, the current one stays
+        // loaded even when nothing may be retained.
+        retain_library(&retained, retire(4), Some(0));
+        assert!(retained.lock().unwrap().is_empty());
+        assert!(current.file_path.exists());
+
+        drop(current);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -0,0 +1,148 @@
+//! Restarting subscriptions whose stream panicked.
+//!
+//! The worker catches a panic of a stream and reports it, the stream is gone
+//! until the next reload rebuilds the subscriptions. A subscription made with
+//! [`run_with_restart`] builds its stream again instead, as [`OnPanic`] says.
+//! Like [`Subscription::run_with`], the subscription is identified by its key
+//! and builder, and by its policy.
+
+use std::hash::Hash;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use futures::stream::{self, Stream, StreamExt};
+use futures::{FutureExt, ready};
+use iced_futures::{MaybeSend, Subscription};
+
+use crate::context::Context;
+
+/// What happens to a stream of [`run_with_restart`] once it panicked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OnPanic {
+    /// The stream ends, the worker reports the panic.
+    #[default]
+    Drop,
+    /// The stream is built again after `backoff`. Once it panicked more than
+    /// `max_retries` times in a row it is dropped. A stream that produced an
+    /// item since its restart has its count of retries reset.
+    Restart { max_retries: u32, backoff: Duration },
+}
+
+/// Like [`Subscription::run_with`], with the stream built again from `key` by
+/// `builder` after a panic, as `on_panic` says.
+///
+/// ```rust,ignore
+/// fn subscription(&self) -> Subscription<Message> {
+///     let on_panic = OnPanic::Restart { max_retries: 5, backoff: Duration::from_secs(1) };
+///     hot_ice::run_with_restart(self.server.clone(), on_panic, connect)
+/// }
+/// ```
+pub fn run_with_restart<D, S>(
+    key: D,
+    on_panic: OnPanic,
+    builder: fn(&D) -> S,
+) -> Subscription<S::Item>
+where
+    D: Hash + Clone + MaybeSend + 'static,
+    S: Stream + MaybeSend + 'static,
+    S::Item: 'static,
+{
+    Subscription::run_with((key, on_panic, builder), |(key, on_panic, builder)| {
+        restarting(key.clone(), *on_panic, *builder)
+    })
+}
+
+fn restarting<D, S>(key: D, on_panic: OnPanic, builder: fn(&D) -> S) -> impl Stream<Item = S::Item>
+where
+    D: MaybeSend + 'static,
+    S: Stream + MaybeSend + 'static,
+{
+    let mut stream: Option<Pin<Box<S>>> = None;
+    let mut backoff: Option<futures_timer::Delay> = None;
+    let mut retries = 0;
+
+    stream::poll_fn(move |cx| {
+        loop {
+            if let Some(delay) = &mut backoff {
+                ready!(delay.poll_unpin(cx));
+                backoff = None;
+            }
+
+            let polled = panic::catch_unwind(AssertUnwindSafe(|| {
+                stream
+                    .get_or_insert_with(|| Box::pin(builder(&key)))
+                    .poll_next_unpin(cx)
+            }));
+
+            let panic = match polled {
+                Ok(Poll::Ready(Some(item))) => {
+                    retries = 0;
+                    return Poll::Ready(Some(item));
+                }
+                Ok(poll) => return poll,
+                Err(panic) => panic,
+            };
+
+            stream = None;
+            match on_panic {
+                OnPanic::Restart {
+                    max_retries,
+                    backoff: delay,
+                } if retries < max_retries => {
+                    retries += 1;
+                    let message = format!(
+                        "Restarting panicked subscription in {delay:?} ({retries}/{max_retries}): {}",
+                        panic_message(&*panic)
+                    );
+                    Context::current().log(log::Level::Warn, &message);
+                    backoff = Some(futures_timer::Delay::new(delay));
+                }
+                _ => panic::resume_unwind(panic),
+            }
+        }
+    })
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| panic.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use futures::executor::block_on;
+
+    static BUILT: AtomicU32 = AtomicU32::new(0);
+
+    /// Panics on its first item until it was built `healthy_after` times.
+    fn flaky(healthy_after: &u32) -> impl Stream<Item = u32> + use<> {
+        let built = BUILT.fetch_add(1, Ordering::SeqCst) + 1;
+        let healthy = built >= *healthy_after;
+        stream::iter([built]).inspect(move |_| assert!(healthy, "not yet"))
+    }
+
+    #[test]
+    fn a_panicked_stream_is_built_again() {
+        let on_panic = OnPanic::Restart {
+            max_retries: 2,
+            backoff: Duration::from_millis(1),
+        };
+
+        let items = block_on(restarting(3, on_panic, flaky).collect::<Vec<_>>());
+        assert_eq!(items, [3]);
+
+        let dropped =
+            panic::catch_unwind(|| block_on(restarting(10, on_panic, flaky).collect::<Vec<_>>()));
+        assert!(dropped.is_err());
+        assert_eq!(BUILT.load(Ordering::SeqCst), 6);
+    }
+}
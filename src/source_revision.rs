@@ -0,0 +1,120 @@
+//! The git revision each library generation is built from.
+//!
+//! When the compile watcher produces a new library, the commit checked out
+//! in the workspace and its uncommitted files are recorded, so the overlay
+//! and [`metrics`](crate::metrics()) can tell exactly which code is running.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde_derive::Serialize;
+
+use crate::log_target::RELOAD;
+
+/// Uncommitted files listed in the overlay, the rest is counted.
+const SHOWN_DIRTY_FILES: usize = 3;
+
+/// The state of the workspace's git repository when a library was built.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SourceRevision {
+    /// Hash of the commit checked out.
+    pub commit: String,
+    /// Files with uncommitted changes, relative to the repository root.
+    pub dirty: Vec<String>,
+}
+
+impl SourceRevision {
+    /// Reads the revision of the git repository containing `workspace`,
+    /// `None` if it is not in one or git is not installed.
+    pub(crate) fn capture(workspace: &Path) -> Option<Self> {
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .current_dir(workspace)
+                .args(args)
+                .output()
+                .ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            String::from_utf8(output.stdout).ok()
+        };
+
+        let commit = git(&["rev-parse", "HEAD"])?.trim().to_string();
+        let status = git(&["status", "--porcelain"]).unwrap_or_default();
+
+        let revision = Self {
+            commit,
+            dirty: parse_status(&status),
+        };
+        log::debug!(target: RELOAD, "Library built from {}", revision.summary());
+        Some(revision)
+    }
+
+    /// The abbreviated commit hash, e.g. `1a2b3c4`.
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+
+    /// One line describing the revision, e.g.
+    /// `1a2b3c4 + 2 uncommitted: src/lib.rs, src/view.rs`.
+    pub fn summary(&self) -> String {
+        if self.dirty.is_empty() {
+            return self.short_commit().to_string();
+        }
+
+        let mut files = self
+            .dirty
+            .iter()
+            .take(SHOWN_DIRTY_FILES)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.dirty.len() > SHOWN_DIRTY_FILES {
+            files.push_str(&format!(", {} more", self.dirty.len() - SHOWN_DIRTY_FILES));
+        }
+        format!(
+            "{} + {} uncommitted: {}",
+            self.short_commit(),
+            self.dirty.len(),
+            files
+        )
+    }
+}
+
+/// The paths of `git status --porcelain` output. A rename is listed under
+/// its new path.
+fn parse_status(status: &str) -> Vec<String> {
+    status
+        .lines()
+        .filter_map(|line| line.get(3..))
+        .map(|path| match path.split_once(" -> ") {
+            Some((_, to)) => to,
+            None => path,
+        })
+        .map(|path| path.trim_matches('"').to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_summarizes_the_status() {
+        let dirty = parse_status(" M src/lib.rs\nR  old.rs -> src/new.rs\n?? \"a b.rs\"\n");
+        assert_eq!(dirty, ["src/lib.rs", "src/new.rs", "a b.rs"]);
+
+        let mut revision = SourceRevision {
+            commit: "1a2b3c4d5e6f".to_string(),
+            dirty: Vec::new(),
+        };
+        assert_eq!(revision.summary(), "1a2b3c4");
+
+        revision.dirty = dirty;
+        revision.dirty.push("README.md".to_string());
+        assert_eq!(
+            revision.summary(),
+            "1a2b3c4 + 4 uncommitted: src/lib.rs, src/new.rs, a b.rs, 1 more"
+        );
+    }
+}
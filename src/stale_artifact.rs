@@ -0,0 +1,106 @@
+//! Detection of a library built before the latest change to its sources.
+//!
+//! Without compiling in the reloader, the host loads whatever library the
+//! last build left in the library directory, which may be days old. Its
+//! modification time is compared to the newest file of the library crate,
+//! so the overlay can tell the library is stale instead of silently showing
+//! old code.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::ui_crate::UiCrate;
+
+/// Directories of a crate that hold no sources.
+const SKIPPED_DIRS: &[&str] = &["target"];
+
+/// The directory of the library crate: `watch_dir` if set, otherwise the
+/// directory of the `ui_crate`.
+pub(crate) fn library_crate_dir(watch_dir: Option<&Path>, ui_crate: &UiCrate) -> Option<PathBuf> {
+    watch_dir
+        .map(Path::to_path_buf)
+        .or_else(|| ui_crate.dir.clone())
+}
+
+/// The latest modification time of the files in `dir`, build output and
+/// hidden entries excluded.
+pub(crate) fn newest_source_mtime(dir: &Path) -> Option<SystemTime> {
+    let mut newest = None;
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !SKIPPED_DIRS.contains(&name.as_ref()) {
+                    pending.push(entry.path());
+                }
+            } else if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                newest = newest.max(Some(modified));
+            }
+        }
+    }
+
+    newest
+}
+
+/// Whether `artifact` was built before the newest file in `sources` changed.
+pub(crate) fn is_stale(artifact: &Path, sources: &Path) -> bool {
+    let Ok(built) = fs::metadata(artifact).and_then(|m| m.modified()) else {
+        return false;
+    };
+    newest_source_mtime(sources).is_some_and(|newest| newest > built)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn artifacts_older_than_their_sources_are_stale() {
+        let root = std::env::temp_dir().join(format!("hot_ice_stale_{}", std::process::id()));
+        let src = root.join("src");
+        let target = root.join("target");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&target).unwrap();
+
+        let artifact = target.join("libui.so");
+        let source = src.join("lib.rs");
+        fs::write(&artifact, b"").unwrap();
+        fs::write(&source, b"").unwrap();
+
+        let now = SystemTime::now();
+        let set_mtime = |path: &Path, time: SystemTime| {
+            fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+
+        set_mtime(&artifact, now);
+        set_mtime(&source, now - Duration::from_secs(60));
+        assert!(!is_stale(&artifact, &root));
+
+        // The artifact itself lives in the skipped target directory.
+        set_mtime(&source, now + Duration::from_secs(60));
+        assert!(is_stale(&artifact, &root));
+        assert!(!is_stale(&root.join("missing.so"), &root));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+//! Cancellation of streams across library generations.
+//!
+//! A stream started by `update` keeps running on the worker after a reload
+//! until it ends or the drain timeout passes. [`Context::run_stream`] starts
+//! it with a [`StreamHandle`], a plain id that survives the state transfer,
+//! so the code of the next generation can abort it. The abort flags live in
+//! the host, the library reaches them through its [`Context`].
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Poll, Waker};
+
+use futures::stream::{self, Stream, StreamExt};
+use iced_futures::MaybeSend;
+use iced_runtime::Task;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::context::Context;
+
+/// Identifies a stream started with [`Context::run_stream`] or
+/// [`run_stream_handle`], in any library generation.
+///
+/// It is `Copy` and serializable, so it can be kept in a `#[hot_state]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct StreamHandle(u64);
+
+impl StreamHandle {
+    pub(crate) fn from_id(id: u64) -> Self {
+        Self(id)
+    }
+
+    pub(crate) fn id(self) -> u64 {
+        self.0
+    }
+
+    /// Ends the stream before its next item. Aborting a stream that already
+    /// ended does nothing.
+    pub fn abort(&self) {
+        Context::current().abort_stream(*self);
+    }
+}
+
+impl Context {
+    /// Runs `stream` as a task that can be aborted through the returned
+    /// handle, also by the code of a later library generation.
+    ///
+    /// ```rust,ignore
+    /// let (task, handle) = context.run_stream(import(path));
+    /// self.import = Some(handle);
+    /// ```
+    pub fn run_stream<T>(
+        &'static self,
+        stream: impl Stream<Item = T> + MaybeSend + 'static,
+    ) -> (Task<T>, StreamHandle)
+    where
+        T: MaybeSend + 'static,
+    {
+        let handle = self.register_stream();
+        let release = Release {
+            context: self,
+            handle,
+        };
+
+        let mut stream = Box::pin(stream);
+        let abortable = stream::poll_fn(move |cx| {
+            if release.is_aborted(cx.waker()) {
+                return Poll::Ready(None);
+            }
+            stream.poll_next_unpin(cx)
+        });
+
+        (Task::stream(abortable), handle)
+    }
+}
+
+/// Runs `stream` as a task that can be aborted through the returned handle,
+/// see [`Context::run_stream`].
+pub fn run_stream_handle<T>(
+    stream: impl Stream<Item = T> + MaybeSend + 'static,
+) -> (Task<T>, StreamHandle)
+where
+    T: MaybeSend + 'static,
+{
+    Context::current().run_stream(stream)
+}
+
+/// Forgets the stream's flag in the host once the stream is dropped.
+struct Release {
+    context: &'static Context,
+    handle: StreamHandle,
+}
+
+impl Release {
+    fn is_aborted(&self, waker: &Waker) -> bool {
+        self.context.poll_stream_aborted(self.handle, waker)
+    }
+}
+
+impl Drop for Release {
+    fn drop(&mut self) {
+        self.context.release_stream(self.handle);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Host side, called through the context's table
+// ---------------------------------------------------------------------------
+
+#[derive(Default)]
+struct Entry {
+    aborted: bool,
+    waker: Option<Waker>,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+static STREAMS: Mutex<Option<HashMap<u64, Entry>>> = Mutex::new(None);
+
+pub(crate) fn register() -> u64 {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut streams) = STREAMS.lock() {
+        streams
+            .get_or_insert_with(HashMap::new)
+            .insert(id, Entry::default());
+    }
+    id
+}
+
+/// Whether the stream was aborted, otherwise `waker` is woken once it is.
+pub(crate) fn poll_aborted(id: u64, waker: &Waker) -> bool {
+    let Ok(mut streams) = STREAMS.lock() else {
+        return false;
+    };
+    match streams.as_mut().and_then(|streams| streams.get_mut(&id)) {
+        Some(entry) if !entry.aborted => {
+            entry.waker = Some(waker.clone());
+            false
+        }
+        _ => true,
+    }
+}
+
+pub(crate) fn abort(id: u64) {
+    let waker = STREAMS.lock().ok().and_then(|mut streams| {
+        let entry = streams.as_mut()?.get_mut(&id)?;
+        entry.aborted = true;
+        entry.waker.take()
+    });
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+pub(crate) fn release(id: u64) {
+    if let Ok(mut streams) = STREAMS.lock()
+        && let Some(streams) = streams.as_mut()
+    {
+        streams.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on_stream;
+    use iced_runtime::{Action, task};
+
+    #[test]
+    fn an_aborted_stream_ends_before_its_next_item() {
+        let (task, handle) = Context::host().run_stream(stream::iter([1, 2, 3]));
+        let mut actions = block_on_stream(task::into_stream(task).unwrap());
+
+        assert!(matches!(actions.next(), Some(Action::Output(1))));
+        handle.abort();
+        assert!(actions.next().is_none());
+
+        // The flag is released with the stream.
+        drop(actions);
+        assert!(STREAMS.lock().unwrap().as_ref().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+//! Workers that did not stop when their library was retired.
+//!
+//! A stream that blocks without reaching an await point keeps its worker
+//! thread from ever seeing the drain, and `stop_worker` waits for the thread
+//! forever. The join is left to a supervisor thread instead, see
+//! [`DrainHandle::join_within`](crate::executor::DrainHandle::join_within),
+//! and the worker is reported with where its threads are blocked, as far as
+//! the platform tells: on Linux the kernel's stack of each thread if it may
+//! be read, otherwise the function it waits in. The library of a stuck
+//! worker is never unloaded, its code is still running.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Extra time given to a draining worker after the drain timeout before it
+/// is reported as stuck.
+pub(crate) const STOP_GRACE: Duration = Duration::from_secs(1);
+
+/// A worker whose threads did not exit within the time given to them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StuckWorker {
+    timeout: Duration,
+    threads: Vec<StuckThread>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StuckThread {
+    id: u64,
+    /// Where the thread is blocked, `None` where the platform does not tell.
+    blocked_in: Option<String>,
+}
+
+impl StuckWorker {
+    /// Looks up where the threads `thread_ids` are blocked.
+    pub(crate) fn inspect(thread_ids: &[u64], timeout: Duration) -> Self {
+        Self {
+            timeout,
+            threads: thread_ids
+                .iter()
+                .map(|&id| StuckThread {
+                    id,
+                    blocked_in: blocked_in(id),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for StuckWorker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The worker did not stop within {:?}, a stream blocks without \
+             awaiting. Its library stays loaded.",
+            self.timeout
+        )?;
+        for thread in &self.threads {
+            match &thread.blocked_in {
+                Some(blocked_in) => write!(f, "\nThread {}:\n{blocked_in}", thread.id)?,
+                None if thread.id != 0 => write!(f, "\nThread {}", thread.id)?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The id the operating system knows the calling thread by, 0 where it is
+/// not known.
+pub(crate) fn current_thread_id() -> u64 {
+    #[cfg(target_os = "linux")]
+    {
+        (unsafe { libc::gettid() }) as u64
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        0
+    }
+}
+
+/// The kernel's stack of the thread `id` of this process, or the function it
+/// waits in if the stack may not be read.
+#[cfg(target_os = "linux")]
+fn blocked_in(id: u64) -> Option<String> {
+    if id == 0 {
+        return None;
+    }
+    let task = std::path::PathBuf::from(format!("/proc/self/task/{id}"));
+    std::fs::read_to_string(task.join("stack"))
+        .ok()
+        .map(|stack| stack.trim_end().to_string())
+        .filter(|stack| !stack.is_empty())
+        .or_else(|| {
+            let wchan = std::fs::read_to_string(task.join("wchan")).ok()?;
+            (!wchan.is_empty() && wchan != "0").then(|| format!("waiting in {wchan}"))
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn blocked_in(_id: u64) -> Option<String> {
+    None
+}
@@ -0,0 +1,182 @@
+//! The subscriptions running on the worker, listed in the metrics table.
+//!
+//! iced keeps a subscription running across a reload as long as the new
+//! library returns one with the same recipe hash, so a reload that changes
+//! how a subscription is built can start a second copy or drop it without a
+//! trace. Every subscription of the worker is registered here with its hash,
+//! the generation that started it and the number of messages it produced.
+//! The hashes running when a library is loaded are kept to tell which
+//! subscriptions the reload added and which it dropped.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::metrics;
+
+/// How a subscription relates to the ones running before the last reload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Change {
+    Kept,
+    Added,
+    /// Another subscription with the same hash is running.
+    Duplicated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Row {
+    pub(crate) key: u64,
+    pub(crate) generation: u64,
+    pub(crate) messages: u64,
+    pub(crate) change: Change,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Listing {
+    pub(crate) active: Vec<Row>,
+    /// Hashes running before the last reload that are gone.
+    pub(crate) dropped: Vec<u64>,
+}
+
+struct Active {
+    id: u64,
+    key: u64,
+    generation: u64,
+    messages: Arc<AtomicU64>,
+}
+
+struct Registry {
+    active: Vec<Active>,
+    before_reload: Vec<u64>,
+    next_id: u64,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    active: Vec::new(),
+    before_reload: Vec::new(),
+    next_id: 0,
+});
+
+/// Registration of a running subscription, removed when dropped.
+pub(crate) struct Tracked {
+    id: u64,
+    messages: Arc<AtomicU64>,
+}
+
+impl Tracked {
+    /// Counts the messages the subscription produced.
+    pub(crate) fn counter(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.messages)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        if let Ok(mut registry) = REGISTRY.lock() {
+            registry.active.retain(|active| active.id != self.id);
+        }
+    }
+}
+
+/// Registers a subscription with recipe hash `key` started by the current
+/// generation.
+pub(crate) fn track(key: u64) -> Tracked {
+    let messages = Arc::new(AtomicU64::new(0));
+    let Ok(mut registry) = REGISTRY.lock() else {
+        return Tracked {
+            id: u64::MAX,
+            messages,
+        };
+    };
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.active.push(Active {
+        id,
+        key,
+        generation: metrics::reloads(),
+        messages: Arc::clone(&messages),
+    });
+    Tracked { id, messages }
+}
+
+/// Remembers the subscriptions running now, called when a library is loaded.
+pub(crate) fn mark_reload() {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.before_reload = registry.active.iter().map(|active| active.key).collect();
+    }
+}
+
+/// The running subscriptions, compared with the ones before the last reload.
+pub(crate) fn listing() -> Listing {
+    let Ok(registry) = REGISTRY.lock() else {
+        return Listing::default();
+    };
+
+    let active = registry
+        .active
+        .iter()
+        .map(|active| {
+            let copies = registry
+                .active
+                .iter()
+                .filter(|other| other.key == active.key)
+                .count();
+            let change = if copies > 1 {
+                Change::Duplicated
+            } else if registry.before_reload.contains(&active.key) {
+                Change::Kept
+            } else {
+                Change::Added
+            };
+            Row {
+                key: active.key,
+                generation: active.generation,
+                messages: active.messages.load(Ordering::Relaxed),
+                change,
+            }
+        })
+        .collect();
+
+    let mut dropped: Vec<u64> = registry
+        .before_reload
+        .iter()
+        .copied()
+        .filter(|key| !registry.active.iter().any(|active| active.key == *key))
+        .collect();
+    dropped.dedup();
+
+    Listing { active, dropped }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_with_the_subscriptions_before_the_reload() {
+        let kept = track(1);
+        let replaced = track(2);
+        mark_reload();
+
+        drop(replaced);
+        let added = track(3);
+        let duplicate = [track(4), track(4)];
+        kept.counter().fetch_add(2, Ordering::Relaxed);
+
+        let listing = listing();
+        let change_of = |key| {
+            listing
+                .active
+                .iter()
+                .find(|row| row.key == key)
+                .map(|row| row.change)
+        };
+        assert_eq!(change_of(1), Some(Change::Kept));
+        assert_eq!(change_of(3), Some(Change::Added));
+        assert_eq!(change_of(4), Some(Change::Duplicated));
+        assert_eq!(listing.active[0].messages, 2);
+        assert_eq!(listing.dropped, [2]);
+
+        drop((kept, added, duplicate));
+        assert!(super::listing().active.is_empty());
+    }
+}
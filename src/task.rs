@@ -0,0 +1,119 @@
+//! Tasks controlling the reloader from the program's own UI.
+//!
+//! `update` returns them like any other task, e.g. for a "Reload" button of
+//! a demo:
+//!
+//! ```rust,ignore
+//! Message::ReloadPressed => hot_ice::task::reload(),
+//! Message::ResetPressed => hot_ice::task::reset_state(),
+//! ```
+//!
+//! When the task runs it hands its request to the host through the
+//! [`Context`], and the reloader handles it before the program sees any
+//! message. The tasks produce no output. Every reloader of a
+//! [`daemon`](crate::daemon) handles the request.
+
+use std::sync::Mutex;
+
+use crossfire::{AsyncRx, MTx, mpsc};
+use iced_futures::MaybeSend;
+use iced_runtime::Task;
+
+use crate::context::Context;
+use crate::log_target::RELOAD;
+
+/// What a task of this module asks the reloader to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum Request {
+    /// Load the library again, even if the build did not change it.
+    Reload,
+    /// Replace the state with the one `boot` returns.
+    ResetState,
+    /// Show or hide the reloader's overlay.
+    ToggleOverlay,
+    /// Start or stop recording the program's messages.
+    ToggleRecording,
+}
+
+/// Reloads the library, the same as a new build would. The current library
+/// is loaded again if it did not change.
+pub fn reload<T>() -> Task<T>
+where
+    T: MaybeSend + 'static,
+{
+    request(Request::Reload)
+}
+
+/// Throws the state away and boots the program again, then runs its setup.
+pub fn reset_state<T>() -> Task<T>
+where
+    T: MaybeSend + 'static,
+{
+    request(Request::ResetState)
+}
+
+/// Shows or hides the reloader's overlay, see `ReloaderSettings::overlay`.
+pub fn toggle_overlay<T>() -> Task<T>
+where
+    T: MaybeSend + 'static,
+{
+    request(Request::ToggleOverlay)
+}
+
+/// Starts or stops recording the program's messages to
+/// `ReloaderSettings::message_fixture`, like the overlay's "Record messages"
+/// button.
+pub fn toggle_recording<T>() -> Task<T>
+where
+    T: MaybeSend + 'static,
+{
+    request(Request::ToggleRecording)
+}
+
+fn request<T>(request: Request) -> Task<T>
+where
+    T: MaybeSend + 'static,
+{
+    Task::future(async move { Context::current().request(request) }).discard()
+}
+
+// ---------------------------------------------------------------------------
+// Host side, called through the context's table
+// ---------------------------------------------------------------------------
+
+static SUBSCRIBERS: Mutex<Vec<MTx<Request>>> = Mutex::new(Vec::new());
+
+pub(crate) fn send(request: Request) {
+    log::debug!(target: RELOAD, "{request:?} requested by the program");
+    if let Ok(subscribers) = SUBSCRIBERS.lock() {
+        for tx in &*subscribers {
+            let _ = tx.send(request);
+        }
+    }
+}
+
+/// Receives the requests of the program's tasks.
+pub(crate) fn subscribe() -> AsyncRx<Request> {
+    let (tx, rx) = mpsc::unbounded_async();
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.push(tx);
+    }
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::executor::block_on_stream;
+    use iced_runtime::task;
+
+    #[test]
+    fn requests_reach_the_reloader_without_output() {
+        let requests = subscribe();
+        let actions = block_on_stream(task::into_stream(reset_state::<()>()).unwrap());
+        assert_eq!(actions.count(), 0);
+        assert!(matches!(requests.try_recv(), Ok(Request::ResetState)));
+    }
+}
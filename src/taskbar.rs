@@ -0,0 +1,97 @@
+//! Build progress on the taskbar button on Windows, through `ITaskbarList3`,
+//! and on the dock tile on macOS, as its badge. See
+//! [`ReloaderSettings::taskbar_progress`](crate::ReloaderSettings).
+//!
+//! Other platforms have no such indicator, the window title carries the
+//! progress there.
+
+use iced_winit::runtime::window::Window;
+
+use crate::build_progress::Indicator;
+
+/// Shows `indicator` on the taskbar button of `window` or the dock tile.
+/// Called on the event loop's thread by `window::run`.
+pub(crate) fn show(window: &dyn Window, indicator: Indicator) {
+    platform::show(window, indicator);
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance};
+    use windows::Win32::UI::Shell::{
+        ITaskbarList3, TBPF_ERROR, TBPF_INDETERMINATE, TBPF_NOPROGRESS, TBPF_NORMAL, TaskbarList,
+    };
+    use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    use super::{Indicator, Window};
+    use crate::log_target::RELOAD;
+
+    pub(super) fn show(window: &dyn Window, indicator: Indicator) {
+        let Ok(handle) = window.window_handle() else {
+            return;
+        };
+        let RawWindowHandle::Win32(handle) = handle.as_raw() else {
+            return;
+        };
+        let hwnd = HWND(handle.hwnd.get() as *mut _);
+
+        // SAFETY: the handle is of a live window of this thread.
+        if let Err(err) = unsafe { set_progress(hwnd, indicator) } {
+            log::debug!(target: RELOAD, "Failed to set the taskbar progress: {err}");
+        }
+    }
+
+    unsafe fn set_progress(hwnd: HWND, indicator: Indicator) -> windows::core::Result<()> {
+        // winit initialized COM on the event loop's thread for drag and drop.
+        let taskbar: ITaskbarList3 =
+            unsafe { CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER)? };
+        unsafe {
+            taskbar.HrInit()?;
+            match indicator {
+                Indicator::Idle => taskbar.SetProgressState(hwnd, TBPF_NOPROGRESS),
+                Indicator::Building(None) => taskbar.SetProgressState(hwnd, TBPF_INDETERMINATE),
+                Indicator::Building(Some(percent)) => {
+                    taskbar.SetProgressState(hwnd, TBPF_NORMAL)?;
+                    taskbar.SetProgressValue(hwnd, u64::from(percent), 100)
+                }
+                Indicator::Failed => {
+                    taskbar.SetProgressState(hwnd, TBPF_ERROR)?;
+                    taskbar.SetProgressValue(hwnd, 100, 100)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::NSApplication;
+    use objc2_foundation::NSString;
+
+    use super::{Indicator, Window};
+
+    /// The dock tile belongs to the application, `window` is not needed.
+    pub(super) fn show(_window: &dyn Window, indicator: Indicator) {
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        let label = match indicator {
+            Indicator::Idle => None,
+            Indicator::Building(Some(percent)) => Some(NSString::from_str(&format!("{percent}%"))),
+            Indicator::Building(None) => Some(NSString::from_str("…")),
+            Indicator::Failed => Some(NSString::from_str("!")),
+        };
+        NSApplication::sharedApplication(mtm)
+            .dockTile()
+            .setBadgeLabel(label.as_deref());
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::{Indicator, Window};
+
+    pub(super) fn show(_window: &dyn Window, _indicator: Indicator) {}
+}
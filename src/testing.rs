@@ -0,0 +1,284 @@
+//! Headless reload driver for integration tests.
+//!
+//! [`Harness`] runs the reloader of an application without a window or an
+//! event loop: messages are passed to `update` directly and reloads run the
+//! same handshake the event loop would, so tests can swap libraries and
+//! assert on the state in between.
+//!
+//! ```rust,ignore
+//! let app = hot_ice::application(State::new, State::update, State::view);
+//! let mut harness = Harness::new(app, "target/debug");
+//!
+//! harness.send(Message::Increment.into_hot_message());
+//! // rebuild the library...
+//! assert!(harness.reload(Duration::from_secs(60)));
+//! assert_eq!(harness.function_state(HotFunction::Update), FunctionState::Hot);
+//! ```
+//!
+//! [`roundtrip_check`] and [`corrupted_buffers`] test the state transfer of
+//! `#[hot_state]` types without building a library. With the `proptest`
+//! feature, `corrupted_buffer` generates the damaged buffers as a strategy.
+//! [`replay_fixture`] runs the messages recorded from the overlay through
+//! `update`.
+
+use std::fmt::Debug;
+use std::path::Path;
+use std::time::Duration;
+
+#[cfg(feature = "proptest")]
+use std::sync::Arc;
+
+use iced_core::layout::{Layout, Limits};
+use iced_core::renderer::Headless;
+use iced_core::widget::{self, Operation, Tree};
+use iced_core::{Font, Pixels, Rectangle, Size, window};
+#[cfg(feature = "proptest")]
+use proptest::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::hot_application::HotIce;
+use crate::hot_program::HotProgram;
+use crate::hot_state::{decode_state, encode_state};
+use crate::message::MessageSource;
+use crate::reloader::{Message, Reloader};
+
+pub use crate::reloader::{FunctionState, HotFunction};
+
+/// Drives the reloader of an application without an event loop.
+///
+/// Tasks returned by `update` are dropped, tests have to send their
+/// messages themselves.
+pub struct Harness<P: HotProgram + 'static> {
+    reloader: Reloader<P>,
+    program: P,
+    window: window::Id,
+}
+
+impl<P> Harness<P>
+where
+    P: HotProgram + 'static,
+    P::Message: Clone,
+{
+    /// Boots `app` and loads its library from `lib_dir`, which has to be
+    /// built already.
+    pub fn new(app: HotIce<P>, lib_dir: impl Into<String>) -> Self {
+        let (program, mut settings, lib_name) = app.into_parts();
+        settings.lib_dir = lib_dir.into();
+        settings.compile_in_reloader = false;
+
+        let (reloader, _listen_for_lib_changes) =
+            Reloader::new(&program, &settings, lib_name, Vec::new());
+
+        Self {
+            reloader,
+            program,
+            window: window::Id::unique(),
+        }
+    }
+
+    /// Whether the library is loaded and messages reach `update`.
+    pub fn is_ready(&self) -> bool {
+        self.reloader.is_ready()
+    }
+
+    /// Passes `message` to `update` as if it was produced by the hot view,
+    /// so it is handled by the loaded library.
+    pub fn send(&mut self, message: P::Message) {
+        let _ = self.reloader.update(
+            &self.program,
+            Message::AppMessage(MessageSource::Dynamic(message)),
+        );
+    }
+
+    /// Builds the view of the main window as the event loop would, so its
+    /// function state is updated. The element is dropped.
+    pub fn view(&self) {
+        drop(self.reloader.view(&self.program, self.window));
+    }
+
+    /// Lays out the view of the main window in `size` with a headless
+    /// tiny-skia renderer and returns its texts with their bounds, in the
+    /// order they are drawn. `None` if the renderer has no tiny-skia backend.
+    pub fn view_texts(&self, size: Size) -> Option<Vec<(String, Rectangle)>>
+    where
+        P::Renderer: Headless,
+    {
+        let renderer = futures::executor::block_on(<P::Renderer as Headless>::new(
+            Font::default(),
+            Pixels(16.0),
+            Some("tiny-skia"),
+        ))?;
+        let mut element = self.reloader.view(&self.program, self.window);
+        let mut tree = Tree::new(element.as_widget());
+        let node =
+            element
+                .as_widget_mut()
+                .layout(&mut tree, &renderer, &Limits::new(Size::ZERO, size));
+
+        let mut texts = Texts(Vec::new());
+        element
+            .as_widget_mut()
+            .operate(&mut tree, Layout::new(&node), &renderer, &mut texts);
+        Some(texts.0)
+    }
+
+    /// Waits for the library to be rebuilt and reloads it. Returns `false`
+    /// if it did not change within `timeout`.
+    pub fn reload(&mut self, timeout: Duration) -> bool {
+        self.reloader.reload_blocking(&self.program, timeout)
+    }
+
+    pub fn state(&self) -> &P::State {
+        self.reloader.state()
+    }
+
+    /// State of `function` after its last call.
+    pub fn function_state(&self, function: HotFunction) -> FunctionState {
+        self.reloader.function_state(function)
+    }
+}
+
+/// Collects the texts of a view, see [`Harness::view_texts`].
+struct Texts(Vec<(String, Rectangle)>);
+
+impl Operation for Texts {
+    fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation)) {
+        operate(self);
+    }
+
+    fn text(&mut self, _id: Option<&widget::Id>, bounds: Rectangle, text: &str) {
+        self.0.push((text.to_string(), bounds));
+    }
+}
+
+/// Panics unless `value` comes out of the state transfer between two
+/// libraries unchanged.
+///
+/// Meant for property tests of `#[hot_state]` types, e.g. with `proptest`:
+///
+/// ```rust,ignore
+/// proptest! {
+///     #[test]
+///     fn state_survives_reload(state in any::<State>()) {
+///         hot_ice::testing::roundtrip_check(&state);
+///     }
+/// }
+/// ```
+pub fn roundtrip_check<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let buffer = encode_state(value).unwrap_or_else(|err| panic!("{err}"));
+    let decoded: T = decode_state(&buffer).unwrap_or_else(|err| panic!("{err}"));
+    assert_eq!(&decoded, value, "state changed in the round trip");
+}
+
+/// Passes the messages of a fixture recorded with the overlay's "Record
+/// messages" toggle to `update`, in the order they were recorded. Panics if
+/// the fixture can't be read or a message doesn't deserialize as an `M`,
+/// e.g. because the message type was edited since.
+///
+/// ```rust,ignore
+/// #[test]
+/// fn recorded_session() {
+///     let mut state = State::default();
+///     hot_ice::testing::replay_fixture("tests/fixtures/messages.ron", |message| {
+///         let _ = state.update(message);
+///     });
+///     assert_eq!(state.count, 3);
+/// }
+/// ```
+pub fn replay_fixture<M>(path: impl AsRef<Path>, mut update: impl FnMut(M))
+where
+    M: DeserializeOwned,
+{
+    let path = path.as_ref();
+    let fixture = std::fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("Failed to read {}: {err}", path.display()));
+
+    for (index, line) in fixture.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let message = ron::from_str(line)
+            .unwrap_or_else(|err| panic!("{}:{}: {err}", path.display(), index + 1));
+        update(message);
+    }
+}
+
+/// Damaged copies of `valid`, a buffer of the state transfer: truncated,
+/// with single bit flips, trailing and random bytes.
+///
+/// The state has to be reset to its default when one of them is handed to
+/// the next library, never to crash it. Their number grows with eight times
+/// the length of `valid`, keep it small or use `corrupted_buffer`.
+pub fn corrupted_buffers(valid: &[u8]) -> Vec<Vec<u8>> {
+    let mut buffers = vec![Vec::new()];
+
+    buffers.extend((1..valid.len()).map(|len| valid[..len].to_vec()));
+
+    for index in 0..valid.len() {
+        for bit in 0..8 {
+            let mut buffer = valid.to_vec();
+            buffer[index] ^= 1 << bit;
+            buffers.push(buffer);
+        }
+    }
+
+    buffers.push([valid, &[0xff; 8]].concat());
+    buffers.push([valid, valid].concat());
+
+    // xorshift, deterministic so failures can be reproduced
+    let mut seed: u32 = 0x9e37_79b9;
+    for len in [1, valid.len(), valid.len() * 2] {
+        let buffer = (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        buffers.push(buffer);
+    }
+
+    buffers
+}
+
+/// Strategy generating damaged copies of `valid`, a buffer of the state
+/// transfer, like [`corrupted_buffers`]: truncated, with a bit flipped, with
+/// bytes appended, or random bytes.
+///
+/// ```rust,ignore
+/// proptest! {
+///     #[test]
+///     fn corrupted_state_is_reset(buffer in corrupted_buffer(&valid())) {
+///         // hand `buffer` to `HotState::deserialize_state`
+///     }
+/// }
+/// ```
+#[cfg(feature = "proptest")]
+pub fn corrupted_buffer(valid: &[u8]) -> impl Strategy<Value = Vec<u8>> {
+    assert!(!valid.is_empty(), "`valid` has to be a serialized state");
+    let valid: Arc<[u8]> = valid.into();
+    let len = valid.len();
+
+    let truncated = {
+        let valid = valid.clone();
+        (0..len).prop_map(move |len| valid[..len].to_vec())
+    };
+    let flipped = {
+        let valid = valid.clone();
+        (0..len, 0..8u32).prop_map(move |(index, bit)| {
+            let mut buffer = valid.to_vec();
+            buffer[index] ^= 1 << bit;
+            buffer
+        })
+    };
+    let extended = proptest::collection::vec(any::<u8>(), 1..=len)
+        .prop_map(move |tail| [&valid[..], &tail].concat());
+    let random = proptest::collection::vec(any::<u8>(), 0..=len * 2);
+
+    prop_oneof![truncated, flipped, extended, random]
+}
@@ -0,0 +1,226 @@
+//! Messages crossing an edit of their type.
+//!
+//! A task or subscription started by one library generation can deliver its
+//! message to `update` of the next. The message type has the same `TypeId` in
+//! both libraries even if the edit changed the enum, so taking the message out
+//! as is would read it with the wrong layout. A type deriving
+//! [`HotMessage`](crate::HotMessage) implements [`TransferMessage`]: its
+//! messages carry the hash of the type's definition, and one of an earlier
+//! definition is serialized by the library that produced it and deserialized
+//! into the current type. Variants marked `#[hot_message(skip)]` cannot be
+//! serialized, their messages are dropped with a warning.
+//!
+//! A message whose type hash matches the current definition skips serde and
+//! is taken out of the [`HotMessage`] as is.
+//!
+//! The wrappers generated by `#[hot_fn]` pick the bridge of the message type
+//! with [`Bridge`]: the transfer for types implementing [`TransferMessage`],
+//! the plain downcast for every other.
+
+use std::marker::PhantomData;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::context::Context;
+use crate::error::{HotIceError, NotTransferable};
+use crate::message::{DynMessage, HotMessage};
+
+/// A message type whose messages survive an edit of the type, implemented by
+/// `#[derive(HotMessage)]`.
+pub trait TransferMessage: DynMessage + Clone + Serialize + DeserializeOwned {
+    /// Hash of the type's definition, changes with every edit of it.
+    const TYPE_HASH: u64;
+
+    /// Fails for the variants marked `#[hot_message(skip)]`.
+    fn transferable(&self) -> Result<(), NotTransferable>;
+}
+
+/// Serializes a message for the library that takes it out.
+pub(crate) struct Transfer {
+    type_hash: u64,
+    transferable: unsafe fn(*const ()) -> Result<(), NotTransferable>,
+    encode: unsafe fn(*const ()) -> Result<Vec<u8>, String>,
+    /// Readable form for fixtures, see `src/recording.rs`.
+    encode_ron: unsafe fn(*const ()) -> Result<String, String>,
+}
+
+struct Shims<M>(PhantomData<M>);
+
+impl<M: TransferMessage> Shims<M> {
+    const TRANSFER: Transfer = Transfer {
+        type_hash: M::TYPE_HASH,
+        transferable: Self::transferable,
+        encode: Self::encode,
+        encode_ron: Self::encode_ron,
+    };
+
+    unsafe fn transferable(data: *const ()) -> Result<(), NotTransferable> {
+        unsafe { (*data.cast::<M>()).transferable() }
+    }
+
+    unsafe fn encode(data: *const ()) -> Result<Vec<u8>, String> {
+        serde_json::to_vec(unsafe { &*data.cast::<M>() }).map_err(|err| err.to_string())
+    }
+
+    unsafe fn encode_ron(data: *const ()) -> Result<String, String> {
+        ron::to_string(unsafe { &*data.cast::<M>() }).map_err(|err| err.to_string())
+    }
+}
+
+impl Transfer {
+    pub(crate) fn of<M: TransferMessage>() -> &'static Self {
+        &Shims::<M>::TRANSFER
+    }
+
+    /// `message` as a line of RON, with the serde code of the library that
+    /// produced it. `Ok(None)` if its variant is not transferable.
+    pub(crate) fn to_ron(&self, message: &HotMessage) -> Result<Option<String>, String> {
+        // SAFETY: the table was made for the type of the message's data.
+        if unsafe { (self.transferable)(message.data()) }.is_err() {
+            return Ok(None);
+        }
+        unsafe { (self.encode_ron)(message.data()) }.map(Some)
+    }
+}
+
+/// Takes `message` out as an `M`, through serde if it was produced with
+/// another definition of `M`. `None` if its variant is not transferable.
+fn take_transferred<M: TransferMessage>(message: HotMessage) -> Result<Option<M>, HotIceError> {
+    let Some(transfer) = message.transfer().filter(|t| t.type_hash != M::TYPE_HASH) else {
+        return message
+            .into_message()
+            .map(Some)
+            .map_err(|message| HotIceError::MessageDowncastError(format!("{message:?}")));
+    };
+
+    // SAFETY: the table was made for the type of the message's data.
+    if let Err(err) = unsafe { (transfer.transferable)(message.data()) } {
+        Context::current().log(
+            log::Level::Warn,
+            &format!("Dropped a message of the edited message type: {err}"),
+        );
+        return Ok(None);
+    }
+    let encoded =
+        unsafe { (transfer.encode)(message.data()) }.map_err(HotIceError::MessageTransferError)?;
+    serde_json::from_slice(&encoded)
+        .map(Some)
+        .map_err(|err| HotIceError::MessageTransferError(format!("{message:?}: {err}")))
+}
+
+/// Picks the conversion of a message type to and from [`HotMessage`], see the
+/// module documentation.
+#[doc(hidden)]
+pub struct Bridge<M>(PhantomData<M>);
+
+impl<M> Bridge<M> {
+    pub fn of(_message: &M) -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M> Default for Bridge<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaTransfer<M> {
+    fn send(&self, message: M) -> HotMessage;
+    fn receive(&self, message: HotMessage) -> Result<Option<M>, HotIceError>;
+}
+
+impl<M: TransferMessage> ViaTransfer<M> for Bridge<M> {
+    fn send(&self, message: M) -> HotMessage {
+        HotMessage::from_transferable(message)
+    }
+
+    fn receive(&self, message: HotMessage) -> Result<Option<M>, HotIceError> {
+        take_transferred(message)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaDowncast<M> {
+    fn send(&self, message: M) -> HotMessage;
+    fn receive(&self, message: HotMessage) -> Result<Option<M>, HotIceError>;
+}
+
+impl<M: DynMessage + Clone> ViaDowncast<M> for &Bridge<M> {
+    fn send(&self, message: M) -> HotMessage {
+        HotMessage::from_message(message)
+    }
+
+    fn receive(&self, message: HotMessage) -> Result<Option<M>, HotIceError> {
+        message
+            .into_message()
+            .map(Some)
+            .map_err(|message| HotIceError::MessageDowncastError(format!("{message:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+
+    /// `Before` stands for the definition of `After` in the previous library.
+    #[derive(Debug, Clone, serde_derive::Serialize)]
+    #[serde(rename = "Message")]
+    enum Before {
+        Set(i64),
+        Opened(#[serde(skip)] ()),
+    }
+
+    #[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+    #[serde(rename = "Message")]
+    enum After {
+        Reset,
+        Set(i64),
+    }
+
+    impl<'de> Deserialize<'de> for Before {
+        fn deserialize<D: serde::Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+            unreachable!()
+        }
+    }
+
+    impl TransferMessage for Before {
+        const TYPE_HASH: u64 = 1;
+
+        fn transferable(&self) -> Result<(), NotTransferable> {
+            match self {
+                Before::Opened(()) => Err(NotTransferable { variant: "Opened" }),
+                Before::Set(_) => Ok(()),
+            }
+        }
+    }
+
+    impl TransferMessage for After {
+        const TYPE_HASH: u64 = 2;
+
+        fn transferable(&self) -> Result<(), NotTransferable> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transfers_messages_of_an_earlier_definition() {
+        let sent = Bridge::of(&Before::Set(3)).send(Before::Set(3));
+        let received = (&Bridge::<After>::default()).receive(sent);
+        assert_eq!(received.unwrap(), Some(After::Set(3)));
+
+        let skipped = Bridge::of(&Before::Opened(())).send(Before::Opened(()));
+        assert!(matches!(take_transferred::<After>(skipped), Ok(None)));
+
+        let same = Bridge::of(&After::Reset).send(After::Reset);
+        assert_eq!(take_transferred::<After>(same).unwrap(), Some(After::Reset));
+
+        let plain = (&Bridge::of(&7_u8)).send(7_u8);
+        assert!(plain.transfer().is_none());
+        assert_eq!((&Bridge::<u8>::default()).receive(plain).unwrap(), Some(7));
+    }
+}
@@ -0,0 +1,348 @@
+//! How the reloader learns that a new build of the library is ready.
+//!
+//! The reloader's state machine only waits for a signal, saves the state and
+//! loads whatever library is at the watched path. A [`ReloadTransport`] sends
+//! that signal: [`FileWatch`] watches the file the build writes, the default,
+//! and [`ManualTrigger`] reloads when the application asks for it. Other
+//! transports, e.g. one receiving libraries over the network, write the
+//! library to the watched path and signal it the same way.
+
+use std::fmt::{self, Debug};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use crossfire::MTx;
+use notify::RecursiveMode;
+use notify_debouncer_full::new_debouncer;
+
+#[cfg(feature = "signed-artifacts")]
+use crate::artifact_signature::ArtifactVerifier;
+use crate::lib_reloader::{ARTIFACT_TIMEOUT, wait_for_artifact};
+use crate::log_target::RELOAD;
+use crate::panic_hook::catch_panic;
+
+/// Sends the reloader the signal to load a new build of the library, set with
+/// [`ReloaderSettings::transport`](crate::ReloaderSettings::transport).
+pub trait ReloadTransport: Send + Sync + 'static {
+    /// Starts announcing new builds of `library`, the file the reloader loads
+    /// from, through `signal`. Called once the reloader knows the path. A
+    /// transport runs on its own thread or task, and logs its failures. A
+    /// transport that stops for good calls [`ReloadSignal::watcher_died`].
+    fn start(&self, library: &Path, signal: ReloadSignal);
+
+    /// Checks the signature of every library before it is loaded, see
+    /// [`Signed`](crate::artifact_signature::Signed).
+    #[cfg(feature = "signed-artifacts")]
+    fn verifier(&self) -> Option<Arc<ArtifactVerifier>> {
+        None
+    }
+}
+
+/// What a transport tells the reloader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ReloadEvent {
+    /// The library file was replaced.
+    Changed,
+    /// The transport stopped, no more builds are loaded until it is started
+    /// again.
+    WatcherDied(String),
+}
+
+/// Tells the reloader that the library file was replaced, handed to
+/// [`ReloadTransport::start`].
+#[derive(Clone)]
+pub struct ReloadSignal {
+    changed: Arc<AtomicBool>,
+    lib_file_hash: Arc<AtomicU32>,
+    subscribers: Arc<Mutex<Vec<MTx<ReloadEvent>>>>,
+}
+
+impl ReloadSignal {
+    pub(crate) fn new(
+        changed: Arc<AtomicBool>,
+        lib_file_hash: Arc<AtomicU32>,
+        subscribers: Arc<Mutex<Vec<MTx<ReloadEvent>>>>,
+    ) -> Self {
+        Self {
+            changed,
+            lib_file_hash,
+            subscribers,
+        }
+    }
+
+    /// Starts a reload if `library` differs from the library loaded now and
+    /// no reload is pending. Returns whether it did.
+    pub fn library_changed(&self, library: &Path) -> bool {
+        if self.changed.load(Ordering::Acquire) {
+            log::debug!(target: RELOAD, "signal_change: skip, a reload is pending");
+            return false;
+        }
+
+        // The watcher may fire while the linker is still writing the file,
+        // it is hashed once it is complete.
+        let current_hash = match wait_for_artifact(library, ARTIFACT_TIMEOUT) {
+            Ok(hash) => hash,
+            Err(reason) => {
+                log::warn!(target: RELOAD, "Not loading {library:?}, it is incomplete: {reason}");
+                return false;
+            }
+        };
+        let stored_hash = self.lib_file_hash.load(Ordering::Acquire);
+        if current_hash == stored_hash {
+            log::debug!(
+                target: RELOAD,
+                "signal_change: skip, unchanged (hash={:#010x})",
+                current_hash
+            );
+            return false;
+        }
+
+        log::info!(
+            target: RELOAD,
+            "{library:?} changed (hash {:#010x} -> {:#010x})",
+            stored_hash,
+            current_hash
+        );
+
+        self.changed.store(true, Ordering::Release);
+
+        // inform subscribers
+        let subscribers = self.subscribers.lock().unwrap();
+        log::debug!(
+            target: RELOAD,
+            "sending ChangedEvent::LibFileChanged to {} subscribers",
+            subscribers.len()
+        );
+        for tx in &*subscribers {
+            let _ = tx.send(ReloadEvent::Changed);
+        }
+
+        true
+    }
+
+    /// Tells the reloader that the transport stopped and announces no more
+    /// builds. The overlay shows `reason` and offers to start it again.
+    pub fn watcher_died(&self, reason: impl Into<String>) {
+        let reason = reason.into();
+        log::error!(target: RELOAD, "The transport stopped: {reason}");
+        if let Ok(subscribers) = self.subscribers.lock() {
+            for tx in &*subscribers {
+                let _ = tx.send(ReloadEvent::WatcherDied(reason.clone()));
+            }
+        }
+    }
+}
+
+impl Debug for ReloadSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReloadSignal")
+            .field("pending", &self.changed.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reloads whenever the build writes the library file, the transport used
+/// unless another is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileWatch {
+    /// How long the file has to stay unchanged before it is loaded.
+    pub debounce: Duration,
+}
+
+impl ReloadTransport for FileWatch {
+    fn start(&self, library: &Path, signal: ReloadSignal) {
+        let lib_file = library.to_path_buf();
+        let debounce = self.debounce;
+        log::info!(target: RELOAD, "start watching changes of file {}", lib_file.display());
+
+        // File watcher thread. We watch `lib_file`, when it changes and we
+        // haven't a pending change still waiting to be loaded, the signal
+        // starts a reload.
+        thread::spawn(move || {
+            let reason = match catch_panic(|| watch_file(&lib_file, debounce, &signal)) {
+                Ok(reason) => reason,
+                Err(panic) => format!("the file watcher panicked: {panic}"),
+            };
+            signal.watcher_died(reason);
+        });
+    }
+}
+
+/// Watches `lib_file` until the watcher fails. Returns why it stopped.
+fn watch_file(lib_file: &Path, debounce: Duration, signal: &ReloadSignal) -> String {
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    let mut debouncer = match new_debouncer(debounce, None, tx) {
+        Ok(debouncer) => debouncer,
+        Err(err) => return format!("the file watcher could not be created: {err}"),
+    };
+
+    // The library may not have been built yet, the file can only be
+    // watched once it exists.
+    let mut watching = debouncer
+        .watch(lib_file, RecursiveMode::NonRecursive)
+        .is_ok();
+
+    if !watching {
+        log::warn!(
+            target: RELOAD,
+            "{} does not exist yet, waiting for it...",
+            lib_file.display()
+        );
+        while !watching {
+            thread::sleep(Duration::from_millis(500));
+            watching = debouncer
+                .watch(lib_file, RecursiveMode::NonRecursive)
+                .is_ok();
+        }
+        log::info!(target: RELOAD, "watching {lib_file:?} after it was created");
+        signal.library_changed(lib_file);
+    }
+
+    loop {
+        match rx.recv() {
+            Err(_) => return "the file watcher channel closed".to_string(),
+            Ok(events) => {
+                let events = match events {
+                    Err(errors) => {
+                        log::error!(target: RELOAD, "{} file watcher error!", errors.len());
+                        for err in errors {
+                            log::error!(target: RELOAD, "  {err}");
+                        }
+                        continue;
+                    }
+                    Ok(events) => events,
+                };
+
+                // Ignore Access events (Open/Read/Close) — they don't
+                // indicate file content changed and fire continuously
+                // after reload when the library is memory-mapped.
+                let dominated_by_access = events
+                    .iter()
+                    .all(|e| matches!(e.kind, notify::EventKind::Access(_)));
+                if dominated_by_access {
+                    continue;
+                }
+
+                log::debug!(target: RELOAD, "file change events: {events:?}");
+                let was_removed =
+                    events
+                        .iter()
+                        .fold(false, |was_removed, event| match event.kind {
+                            notify::EventKind::Create(_) | notify::EventKind::Modify(_) => false,
+                            notify::EventKind::Remove(_) => true,
+                            _ => was_removed,
+                        });
+                // just one hard link removed?
+                if was_removed || !lib_file.exists() {
+                    log::debug!(
+                        target: RELOAD,
+                        "{} was removed, trying to watch it again...",
+                        lib_file.display()
+                    );
+                    loop {
+                        if debouncer
+                            .watch(lib_file, RecursiveMode::NonRecursive)
+                            .is_ok()
+                        {
+                            log::info!(
+                                target: RELOAD,
+                                "watching {lib_file:?} again after removal"
+                            );
+                            break;
+                        }
+                        thread::sleep(Duration::from_millis(500));
+                    }
+                }
+
+                signal.library_changed(lib_file);
+            }
+        }
+    }
+}
+
+/// Reloads only when [`ManualTrigger::reload`] is called, e.g. from a
+/// keyboard shortcut of the host. Clones trigger the same reloader.
+///
+/// ```rust,ignore
+/// let trigger = hot_ice::ManualTrigger::default();
+/// hot_ice::application(State::boot, State::update, State::view)
+///     .reloader_settings(ReloaderSettings {
+///         transport: Some(Arc::new(trigger.clone())),
+///         ..Default::default()
+///     })
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManualTrigger {
+    started: Arc<OnceLock<(PathBuf, ReloadSignal)>>,
+}
+
+impl ManualTrigger {
+    /// Loads the library if the build changed it since it was last loaded.
+    /// Returns whether a reload was started.
+    pub fn reload(&self) -> bool {
+        match self.started.get() {
+            Some((library, signal)) => signal.library_changed(library),
+            None => {
+                log::warn!(target: RELOAD, "Reload triggered before the reloader started");
+                false
+            }
+        }
+    }
+}
+
+impl ReloadTransport for ManualTrigger {
+    fn start(&self, library: &Path, signal: ReloadSignal) {
+        if self.started.set((library.to_path_buf(), signal)).is_err() {
+            log::warn!(target: RELOAD, "Manual trigger started twice, keeping the first reloader");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib_reloader::{fake_artifact, hash_file};
+
+    #[test]
+    fn a_manual_trigger_reloads_changed_libraries_only() {
+        let library = std::env::temp_dir().join(format!("hot_ice_trigger_{}", std::process::id()));
+        std::fs::write(&library, fake_artifact(b"generation 1")).unwrap();
+
+        let (sender, receiver) = crossfire::mpsc::unbounded_async();
+        let changed = Arc::new(AtomicBool::new(false));
+        let signal = ReloadSignal::new(
+            Arc::clone(&changed),
+            Arc::new(AtomicU32::new(hash_file(&library))),
+            Arc::new(Mutex::new(vec![sender])),
+        );
+
+        let trigger = ManualTrigger::default();
+        assert!(!trigger.reload());
+        trigger.clone().start(&library, signal);
+        assert!(!trigger.reload());
+
+        std::fs::write(&library, fake_artifact(b"generation 2")).unwrap();
+        assert!(trigger.reload());
+        assert!(receiver.try_recv().is_ok());
+        // Pending until the reloader loaded it.
+        assert!(!trigger.reload());
+        changed.store(false, Ordering::Release);
+        assert!(trigger.reload());
+        assert_eq!(receiver.try_recv().ok(), Some(ReloadEvent::Changed));
+
+        let (_, signal) = trigger.started.get().unwrap();
+        signal.watcher_died("the connection was lost");
+        assert_eq!(
+            receiver.try_recv().ok(),
+            Some(ReloadEvent::WatcherDied(
+                "the connection was lost".to_string()
+            ))
+        );
+
+        let _ = std::fs::remove_file(&library);
+    }
+}
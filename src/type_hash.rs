@@ -0,0 +1,244 @@
+//! Hashes of the full definition of a type.
+//!
+//! A value built by one library can only be moved into the next without
+//! serializing it if both libraries agree on its layout, down to every type
+//! nested in it. `#[derive(TypeHash)]` combines the hash of a definition's
+//! tokens with the [`TypeHash`] of each of its fields, so editing any type
+//! the value contains changes the hash of the value.
+//!
+//! Function pointers and trait objects do not implement [`TypeHash`], they
+//! point into the code of the library that made them. References to statics,
+//! like `&'static str`, do but are marked
+//! [`BORROWS_LIBRARY`](TypeHash::BORROWS_LIBRARY): they can be read while
+//! their library is loaded, but not handed to the next one.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use hot_ice_macros::TypeHash;
+
+/// A hash of the full definition of a type, implemented with
+/// `#[derive(hot_ice::TypeHash)]`.
+///
+/// # Safety
+///
+/// `TYPE_HASH` has to change with every edit of the type that changes its
+/// layout or the meaning of its bits, including edits of the types nested
+/// in it, and `BORROWS_LIBRARY` has to be `true` if the type holds a
+/// reference into the library.
+pub unsafe trait TypeHash: 'static {
+    const TYPE_HASH: u64;
+
+    /// The type holds a reference to a static of the library that built it.
+    const BORROWS_LIBRARY: bool = false;
+}
+
+/// The [`TypeHash`] of `T` if a value of it can outlive its library, `0`
+/// otherwise.
+pub const fn handoff_hash<T: TypeHash>() -> u64 {
+    if T::BORROWS_LIBRARY { 0 } else { T::TYPE_HASH }
+}
+
+/// 64-bit FNV-1a hash of `bytes`, the same as `registry::fnv1a`.
+#[doc(hidden)]
+pub const fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Combines the hashes of a definition and its parts, order matters.
+#[doc(hidden)]
+pub const fn combine_hashes(hashes: &[u64]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut i = 0;
+    while i < hashes.len() {
+        let bytes = hashes[i].to_le_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            hash = (hash ^ bytes[j] as u64).wrapping_mul(0x0000_0100_0000_01b3);
+            j += 1;
+        }
+        i += 1;
+    }
+    hash
+}
+
+/// Hash of a type's name together with its layout.
+const fn leaf<T>(name: &str) -> u64 {
+    combine_hashes(&[
+        hash_bytes(name.as_bytes()),
+        size_of::<T>() as u64,
+        align_of::<T>() as u64,
+    ])
+}
+
+macro_rules! leaf_types {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            unsafe impl TypeHash for $ty {
+                const TYPE_HASH: u64 = leaf::<$ty>(stringify!($ty));
+            }
+        )*
+    };
+}
+
+leaf_types!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    String,
+    PathBuf,
+    Duration,
+);
+
+macro_rules! generic_types {
+    ($($name:ident < $($param:ident),+ >),* $(,)?) => {
+        $(
+            unsafe impl<$($param: TypeHash),+> TypeHash for $name<$($param),+> {
+                const TYPE_HASH: u64 = combine_hashes(&[
+                    leaf::<Self>(stringify!($name)),
+                    $($param::TYPE_HASH),+
+                ]);
+                const BORROWS_LIBRARY: bool = false $(|| $param::BORROWS_LIBRARY)+;
+            }
+        )*
+    };
+}
+
+generic_types!(
+    Vec<T>,
+    VecDeque<T>,
+    Option<T>,
+    Box<T>,
+    Arc<T>,
+    Result<T, E>,
+    BTreeMap<K, V>,
+    BTreeSet<T>,
+    HashMap<K, V>,
+    HashSet<T>,
+    PhantomData<T>,
+);
+
+macro_rules! tuples {
+    ($(($($param:ident),+)),* $(,)?) => {
+        $(
+            unsafe impl<$($param: TypeHash),+> TypeHash for ($($param,)+) {
+                const TYPE_HASH: u64 = combine_hashes(&[
+                    leaf::<Self>("tuple"),
+                    $($param::TYPE_HASH),+
+                ]);
+                const BORROWS_LIBRARY: bool = false $(|| $param::BORROWS_LIBRARY)+;
+            }
+        )*
+    };
+}
+
+tuples!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
+
+unsafe impl<T: TypeHash, const N: usize> TypeHash for [T; N] {
+    const TYPE_HASH: u64 = combine_hashes(&[leaf::<Self>("array"), N as u64, T::TYPE_HASH]);
+    const BORROWS_LIBRARY: bool = T::BORROWS_LIBRARY;
+}
+
+unsafe impl TypeHash for &'static str {
+    const TYPE_HASH: u64 = leaf::<Self>("&str");
+    const BORROWS_LIBRARY: bool = true;
+}
+
+unsafe impl<T: TypeHash> TypeHash for &'static [T] {
+    const TYPE_HASH: u64 = combine_hashes(&[leaf::<Self>("&[]"), T::TYPE_HASH]);
+    const BORROWS_LIBRARY: bool = true;
+}
+
+/// Reads the [`handoff_hash`] of `T` in the code `#[hot_state]` generates,
+/// `0` for types that do not implement [`TypeHash`].
+#[doc(hidden)]
+pub struct Verified<T>(PhantomData<T>);
+
+impl<T> Default for Verified<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaTypeHash {
+    fn handoff_hash(&self) -> u64;
+}
+
+impl<T: TypeHash> ViaTypeHash for Verified<T> {
+    fn handoff_hash(&self) -> u64 {
+        handoff_hash::<T>()
+    }
+}
+
+#[doc(hidden)]
+pub trait Unverified {
+    fn handoff_hash(&self) -> u64 {
+        0
+    }
+}
+
+impl<T> Unverified for &Verified<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_covers_nested_types() {
+        assert_ne!(
+            <Vec<i32> as TypeHash>::TYPE_HASH,
+            <Vec<f32> as TypeHash>::TYPE_HASH
+        );
+        assert_ne!(
+            <(u8, u16) as TypeHash>::TYPE_HASH,
+            <(u16, u8) as TypeHash>::TYPE_HASH
+        );
+        assert_ne!(
+            <[u8; 2] as TypeHash>::TYPE_HASH,
+            <[u8; 3] as TypeHash>::TYPE_HASH
+        );
+    }
+
+    #[test]
+    fn references_into_the_library_are_not_handed_off() {
+        assert_eq!(handoff_hash::<Option<&'static str>>(), 0);
+        assert_ne!(handoff_hash::<Option<String>>(), 0);
+
+        assert_eq!(
+            (&Verified::<Vec<u8>>::default()).handoff_hash(),
+            handoff_hash::<Vec<u8>>()
+        );
+        assert_eq!((&Verified::<fn()>::default()).handoff_hash(), 0);
+    }
+}
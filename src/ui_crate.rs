@@ -0,0 +1,141 @@
+//! Where the ui crate is built.
+//!
+//! The ui crate is usually a member of the host's workspace, but it may also
+//! be a path dependency with a workspace and target directory of its own,
+//! e.g. a checkout of another repository. Cargo then runs in the ui crate's
+//! workspace, while a relative `target_dir` keeps resolving against the
+//! host's workspace, so the library is still written to the `lib_dir` the
+//! host watches.
+
+use std::path::{Path, PathBuf};
+
+use cargo_metadata::{Metadata, MetadataCommand};
+
+use crate::log_target::RELOAD;
+
+/// The ui crate and the workspace it is built in, see
+/// `ReloaderSettings::ui_manifest_path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UiCrate {
+    /// Manifest of the ui crate, `None` if it is a member of the host's
+    /// workspace.
+    pub(crate) manifest_path: Option<PathBuf>,
+    /// Directory of the ui crate, `None` if cargo does not know it.
+    pub(crate) dir: Option<PathBuf>,
+    /// Root of the workspace the ui crate is built in.
+    pub(crate) workspace_root: PathBuf,
+    /// Root of the host's workspace.
+    pub(crate) host_root: PathBuf,
+}
+
+impl UiCrate {
+    /// Finds the ui crate named `lib_name`: at `manifest_path` if set,
+    /// otherwise among the host's workspace members and path dependencies.
+    pub(crate) fn locate(manifest_path: Option<&Path>, lib_name: &str) -> Self {
+        let host = MetadataCommand::new().exec().ok();
+        let host_root = host.as_ref().map_or_else(
+            || PathBuf::from("."),
+            |m| m.workspace_root.clone().into_std_path_buf(),
+        );
+
+        let manifest_path = manifest_path.map(Path::to_path_buf).or_else(|| {
+            let host = host.as_ref()?;
+            let package = host.packages.iter().find(|p| p.name == lib_name)?;
+            // Members are built in the host's workspace, a path dependency
+            // outside of it in its own.
+            (!host.workspace_members.contains(&package.id))
+                .then(|| package.manifest_path.clone().into_std_path_buf())
+        });
+
+        let Some(manifest_path) = manifest_path else {
+            return Self {
+                manifest_path: None,
+                dir: host.as_ref().and_then(|host| package_dir(host, lib_name)),
+                workspace_root: host_root.clone(),
+                host_root,
+            };
+        };
+
+        let manifest_dir = manifest_path.parent().map(Path::to_path_buf);
+        let metadata = MetadataCommand::new()
+            .manifest_path(&manifest_path)
+            .no_deps()
+            .exec()
+            .inspect_err(|err| {
+                log::warn!(
+                    target: RELOAD,
+                    "Failed to read the workspace of {}: {err}",
+                    manifest_path.display()
+                );
+            })
+            .ok();
+        let dir = metadata
+            .as_ref()
+            .and_then(|metadata| package_dir(metadata, lib_name))
+            .or(manifest_dir.clone());
+        let workspace_root = metadata
+            .map(|m| m.workspace_root.into_std_path_buf())
+            .or(manifest_dir)
+            .unwrap_or_else(|| host_root.clone());
+
+        Self {
+            manifest_path: Some(manifest_path),
+            dir,
+            workspace_root,
+            host_root,
+        }
+    }
+
+    /// `cargo metadata` of the workspace the ui crate is built in.
+    pub(crate) fn metadata(&self) -> MetadataCommand {
+        let mut command = MetadataCommand::new();
+        if let Some(manifest_path) = &self.manifest_path {
+            command.manifest_path(manifest_path);
+        }
+        command
+    }
+
+    /// The `CARGO_TARGET_DIR` for `target_dir`: relative to the host's
+    /// workspace, also when the ui crate is built in another one.
+    pub(crate) fn target_dir(&self, target_dir: &str) -> String {
+        if self.workspace_root == self.host_root || Path::new(target_dir).is_absolute() {
+            return target_dir.to_string();
+        }
+        self.host_root.join(target_dir).display().to_string()
+    }
+}
+
+/// The directory of the package named `lib_name` in `metadata`.
+fn package_dir(metadata: &Metadata, lib_name: &str) -> Option<PathBuf> {
+    let package = metadata.packages.iter().find(|p| p.name == lib_name)?;
+    let dir = package.manifest_path.parent()?;
+    Some(dir.as_std_path().to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_dir_resolves_against_the_host_workspace() {
+        let member = UiCrate {
+            manifest_path: None,
+            dir: Some(PathBuf::from("/host/ui")),
+            workspace_root: PathBuf::from("/host"),
+            host_root: PathBuf::from("/host"),
+        };
+        assert_eq!(member.target_dir("target/reload"), "target/reload");
+
+        let external = UiCrate {
+            manifest_path: Some(PathBuf::from("/ui/Cargo.toml")),
+            dir: Some(PathBuf::from("/ui")),
+            workspace_root: PathBuf::from("/ui"),
+            ..member
+        };
+        assert_eq!(
+            PathBuf::from(external.target_dir("target/reload")),
+            PathBuf::from("/host/target/reload")
+        );
+        assert_eq!(external.target_dir("/tmp/reload"), "/tmp/reload");
+    }
+}
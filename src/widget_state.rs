@@ -0,0 +1,227 @@
+//! Scroll offsets and focus of the program's widgets across a reload, see
+//! [`ReloaderSettings::restore_widget_state`](crate::ReloaderSettings).
+//!
+//! The reloading screen replaces the program's view while a library is
+//! swapped, so iced drops the state of every widget in it. Right before the
+//! screen is shown the reloader captures the offsets of the scrollables and
+//! which widget has focus with a widget operation, and applies them to the
+//! new view once the reload completed. Widgets are matched by their `Id`,
+//! widgets without one by their position among the scrollables or focusable
+//! widgets of the window.
+
+use iced_core::widget::operation::scrollable::AbsoluteOffset;
+use iced_core::widget::operation::{Focusable, Outcome, Scrollable};
+use iced_core::widget::{Id, Operation};
+use iced_core::{Rectangle, Vector};
+
+/// A widget of the program's view.
+#[derive(Debug, Clone, PartialEq)]
+enum WidgetKey {
+    Id(Id),
+    /// The n-th scrollable or focusable widget, without an `Id`.
+    Nth(usize),
+}
+
+/// What the program's widgets looked like before a reload.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct WidgetSnapshot {
+    /// Offsets of the scrollables that were scrolled.
+    scrolled: Vec<(WidgetKey, Vector)>,
+    focused: Option<WidgetKey>,
+}
+
+impl WidgetSnapshot {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.scrolled.is_empty() && self.focused.is_none()
+    }
+}
+
+/// Numbers the scrollables and focusable widgets in the order they are
+/// visited, the same for capturing and restoring.
+#[derive(Default)]
+struct Walk {
+    scrollables: usize,
+    focusables: usize,
+}
+
+impl Walk {
+    fn next_scrollable(&mut self, id: Option<&Id>) -> WidgetKey {
+        self.scrollables += 1;
+        key(id, self.scrollables - 1)
+    }
+
+    fn next_focusable(&mut self, id: Option<&Id>) -> WidgetKey {
+        self.focusables += 1;
+        key(id, self.focusables - 1)
+    }
+}
+
+fn key(id: Option<&Id>, nth: usize) -> WidgetKey {
+    match id {
+        Some(id) => WidgetKey::Id(id.clone()),
+        None => WidgetKey::Nth(nth),
+    }
+}
+
+/// Captures the scroll offsets and the focus of the widgets.
+pub(crate) fn capture() -> impl Operation<WidgetSnapshot> {
+    #[derive(Default)]
+    struct Capture {
+        walk: Walk,
+        snapshot: WidgetSnapshot,
+    }
+
+    impl Operation<WidgetSnapshot> for Capture {
+        fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation<WidgetSnapshot>)) {
+            operate(self);
+        }
+
+        fn scrollable(
+            &mut self,
+            id: Option<&Id>,
+            _bounds: Rectangle,
+            _content_bounds: Rectangle,
+            translation: Vector,
+            _state: &mut dyn Scrollable,
+        ) {
+            let key = self.walk.next_scrollable(id);
+            if translation != Vector::ZERO {
+                self.snapshot.scrolled.push((key, translation));
+            }
+        }
+
+        fn focusable(&mut self, id: Option<&Id>, _bounds: Rectangle, state: &mut dyn Focusable) {
+            let key = self.walk.next_focusable(id);
+            if state.is_focused() {
+                self.snapshot.focused = Some(key);
+            }
+        }
+
+        fn finish(&self) -> Outcome<WidgetSnapshot> {
+            Outcome::Some(self.snapshot.clone())
+        }
+    }
+
+    Capture::default()
+}
+
+/// Scrolls and focuses the widgets as they were in `snapshot`.
+pub(crate) fn restore<T>(snapshot: WidgetSnapshot) -> impl Operation<T> {
+    struct Restore {
+        walk: Walk,
+        snapshot: WidgetSnapshot,
+    }
+
+    impl<T> Operation<T> for Restore {
+        fn traverse(&mut self, operate: &mut dyn FnMut(&mut dyn Operation<T>)) {
+            operate(self);
+        }
+
+        fn scrollable(
+            &mut self,
+            id: Option<&Id>,
+            _bounds: Rectangle,
+            _content_bounds: Rectangle,
+            _translation: Vector,
+            state: &mut dyn Scrollable,
+        ) {
+            let key = self.walk.next_scrollable(id);
+            if let Some((_, offset)) = self.snapshot.scrolled.iter().find(|(k, _)| *k == key) {
+                state.scroll_to(AbsoluteOffset {
+                    x: Some(offset.x),
+                    y: Some(offset.y),
+                });
+            }
+        }
+
+        fn focusable(&mut self, id: Option<&Id>, _bounds: Rectangle, state: &mut dyn Focusable) {
+            let key = self.walk.next_focusable(id);
+            if self.snapshot.focused.as_ref() == Some(&key) {
+                state.focus();
+            }
+        }
+    }
+
+    Restore {
+        walk: Walk::default(),
+        snapshot,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use iced_core::widget::operation::scrollable::RelativeOffset;
+
+    #[derive(Default)]
+    struct Widget {
+        offset: Option<AbsoluteOffset<Option<f32>>>,
+        focused: bool,
+    }
+
+    impl Scrollable for Widget {
+        fn snap_to(&mut self, _offset: RelativeOffset<Option<f32>>) {}
+
+        fn scroll_to(&mut self, offset: AbsoluteOffset<Option<f32>>) {
+            self.offset = Some(offset);
+        }
+
+        fn scroll_by(&mut self, _offset: AbsoluteOffset, _bounds: Rectangle, _content: Rectangle) {}
+    }
+
+    impl Focusable for Widget {
+        fn is_focused(&self) -> bool {
+            self.focused
+        }
+
+        fn focus(&mut self) {
+            self.focused = true;
+        }
+
+        fn unfocus(&mut self) {
+            self.focused = false;
+        }
+    }
+
+    /// Visits a scrollable with an id, one without and a focusable widget.
+    fn visit<T>(
+        operation: &mut dyn Operation<T>,
+        widgets: &mut [Widget; 3],
+        scrolled: [Vector; 2],
+    ) {
+        let bounds = Rectangle::with_size(iced_core::Size::new(100.0, 100.0));
+        let [list, unnamed, input] = widgets;
+        operation.scrollable(Some(&Id::new("list")), bounds, bounds, scrolled[0], list);
+        operation.scrollable(None, bounds, bounds, scrolled[1], unnamed);
+        operation.focusable(None, bounds, input);
+    }
+
+    #[test]
+    fn restores_offsets_and_focus_by_id_and_position() {
+        let mut before: [Widget; 3] = Default::default();
+        before[2].focused = true;
+        let mut capture = capture();
+        visit(
+            &mut capture,
+            &mut before,
+            [Vector::new(0.0, 120.0), Vector::ZERO],
+        );
+        let Outcome::Some(snapshot) = capture.finish() else {
+            panic!("no snapshot captured");
+        };
+        assert!(!snapshot.is_empty());
+
+        let mut after: [Widget; 3] = Default::default();
+        visit(&mut restore::<()>(snapshot), &mut after, [Vector::ZERO; 2]);
+        assert_eq!(
+            after[0].offset,
+            Some(AbsoluteOffset {
+                x: Some(0.0),
+                y: Some(120.0)
+            })
+        );
+        assert_eq!(after[1].offset, None);
+        assert!(after[2].focused);
+    }
+}
@@ -0,0 +1,117 @@
+//! Persistence of the window's size, position and maximized state across
+//! runs, see [`ReloaderSettings::persist_window`](crate::ReloaderSettings).
+//!
+//! The window keeps its geometry across reloads anyway, only a restart
+//! opened it at its default size again. The geometry is tracked from the
+//! window's events, written to a file in the target directory a moment
+//! after it last changed and applied to the window settings on startup.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use iced_core::{Point, Size, window};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::log_target::RELOAD;
+
+/// A change of the window, sent by the reloader's subscription.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowChange {
+    Resized(Size),
+    Moved(Point),
+    Maximized(bool),
+}
+
+/// The geometry of the window, in logical pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WindowGeometry {
+    width: f32,
+    height: f32,
+    position: Option<(f32, f32)>,
+    maximized: bool,
+}
+
+impl WindowGeometry {
+    /// The file the geometry of the window of `lib_name` is kept in.
+    pub(crate) fn path(target_dir: &str, lib_name: &str) -> PathBuf {
+        Path::new(target_dir).join(format!("window-{lib_name}.json"))
+    }
+
+    /// Reads the geometry saved by an earlier run.
+    pub(crate) fn load(path: &Path) -> Option<Self> {
+        let json = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json)
+            .inspect_err(|err| log::warn!(target: RELOAD, "Ignoring {}: {}", path.display(), err))
+            .ok()
+    }
+
+    pub(crate) fn save(&self, path: &Path) {
+        let result = serde_json::to_string(self)
+            .map_err(std::io::Error::other)
+            .and_then(|json| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(path, json)
+            });
+        if let Err(err) = result {
+            log::warn!(target: RELOAD, "Failed to save {}: {}", path.display(), err);
+        }
+    }
+
+    /// Records `change`. The size of a maximized window is not kept, so it
+    /// is restored to its previous size when unmaximized.
+    pub(crate) fn apply_change(&mut self, change: WindowChange) {
+        match change {
+            WindowChange::Resized(size) if !self.maximized => {
+                self.width = size.width;
+                self.height = size.height;
+            }
+            WindowChange::Resized(_) => {}
+            WindowChange::Moved(position) => self.position = Some((position.x, position.y)),
+            WindowChange::Maximized(maximized) => self.maximized = maximized,
+        }
+    }
+
+    /// Opens the window with the saved geometry.
+    pub(crate) fn restore(&self, settings: &mut window::Settings) {
+        if self.width > 0.0 && self.height > 0.0 {
+            settings.size = Size::new(self.width, self.height);
+        }
+        if let Some((x, y)) = self.position {
+            settings.position = window::Position::Specific(Point::new(x, y));
+        }
+        settings.maximized = self.maximized;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restores_the_saved_geometry() {
+        let path = std::env::temp_dir().join(format!("hot_ice_window_{}.json", std::process::id()));
+
+        let mut geometry = WindowGeometry::default();
+        geometry.apply_change(WindowChange::Resized(Size::new(800.0, 600.0)));
+        geometry.apply_change(WindowChange::Moved(Point::new(10.0, 20.0)));
+        geometry.apply_change(WindowChange::Maximized(true));
+        geometry.apply_change(WindowChange::Resized(Size::new(1920.0, 1080.0)));
+        geometry.save(&path);
+
+        let loaded = WindowGeometry::load(&path).unwrap();
+        assert_eq!(loaded, geometry);
+
+        let mut settings = window::Settings::default();
+        loaded.restore(&mut settings);
+        assert_eq!(settings.size, Size::new(800.0, 600.0));
+        assert!(matches!(
+            settings.position,
+            window::Position::Specific(Point { x: 10.0, y: 20.0 })
+        ));
+        assert!(settings.maximized);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
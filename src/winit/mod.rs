@@ -70,6 +70,8 @@ use std::mem::ManuallyDrop;
 use std::slice;
 use std::sync::Arc;
 
+use crate::log_target::DISPATCH;
+
 /// Runs a [`Program`] with the provided settings.
 pub fn run<P>(program: P) -> Result<(), Error>
 where
@@ -1031,6 +1033,8 @@ async fn run_instance<P>(
                         }
                     }
                     event::Event::AboutToWait => {
+                        crate::worker_queue::event_loop_idle();
+
                         if actions > 0 {
                             proxy.free_slots(actions);
                             actions = 0;
@@ -1206,17 +1210,18 @@ where
         }
     }
 
-    log::trace!("[sub] about to call program.subscription()");
+    log::trace!(target: DISPATCH, "about to call program.subscription()");
     let subscription = program.subscription();
-    log::trace!("[sub] subscription() returned, converting to recipes");
+    log::trace!(target: DISPATCH, "subscription() returned, converting to recipes");
     let recipes = subscription::into_recipes(subscription.map(Action::Output));
     log::trace!(
-        "[sub] calling runtime.track() with {} recipes",
+        target: DISPATCH,
+        "calling runtime.track() with {} recipes",
         recipes.len()
     );
 
     runtime.track(recipes);
-    log::trace!("[sub] runtime.track() completed");
+    log::trace!(target: DISPATCH, "runtime.track() completed");
 
     actions
 }
@@ -0,0 +1,238 @@
+//! Bounded delivery of the actions produced on the cdylib worker.
+//!
+//! A stream on the worker can produce actions faster than the event loop
+//! handles them. Sent straight to the event loop they would pile up in its
+//! unbounded queue until the application runs out of memory. [`ActionQueue`]
+//! lets at most `capacity` actions wait in the event loop and holds up to
+//! `capacity` more itself; what happens beyond that is decided by
+//! [`Backpressure`]. The event loop reports that it handled everything sent
+//! so far through [`event_loop_idle`].
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+use iced_core::time::Instant;
+use iced_runtime::Action;
+
+use crate::executor::ActionSender;
+use crate::log_target::WORKER;
+use crate::metrics;
+
+/// How long a blocked worker sleeps before it checks whether it was closed.
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Limits the actions of the worker's streams waiting to be handled, see
+/// [`ReloaderSettings::worker_queue`](crate::ReloaderSettings).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerQueue {
+    /// Actions that wait in the event loop, the worker holds as many again.
+    /// Default: 1024
+    pub capacity: usize,
+    /// What happens once both are full. Default: [`Backpressure::Block`]
+    pub when_full: Backpressure,
+}
+
+impl Default for WorkerQueue {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            when_full: Backpressure::Block,
+        }
+    }
+}
+
+/// What the worker does with an action while the queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Wait until the event loop caught up. Every stream of the worker is
+    /// paused meanwhile, nothing is lost.
+    #[default]
+    Block,
+    /// Drop the oldest queued action to make room, for streams whose latest
+    /// value is all that matters.
+    DropOldest,
+}
+
+pub(crate) struct ActionQueue<M> {
+    sender: ActionSender<M>,
+    capacity: usize,
+    when_full: Backpressure,
+    state: Mutex<QueueState<M>>,
+    space: Condvar,
+}
+
+struct QueueState<M> {
+    /// Actions sent to the event loop since it was last idle.
+    in_flight: usize,
+    /// Actions held back until the event loop is idle.
+    pending: VecDeque<Action<M>>,
+    closed: bool,
+}
+
+/// An [`ActionQueue`] of any message type.
+trait Flush: Send + Sync {
+    fn flush(&self);
+}
+
+static QUEUES: Mutex<Vec<Weak<dyn Flush>>> = Mutex::new(Vec::new());
+
+impl<M: Send + 'static> ActionQueue<M> {
+    pub(crate) fn new(sender: ActionSender<M>, settings: &WorkerQueue) -> Arc<Self> {
+        let queue = Arc::new(Self {
+            sender,
+            capacity: settings.capacity.max(1),
+            when_full: settings.when_full,
+            state: Mutex::new(QueueState {
+                in_flight: 0,
+                pending: VecDeque::new(),
+                closed: false,
+            }),
+            space: Condvar::new(),
+        });
+
+        let flush: Arc<dyn Flush> = queue.clone();
+        if let Ok(mut queues) = QUEUES.lock() {
+            queues.push(Arc::downgrade(&flush));
+        }
+        queue
+    }
+
+    /// Delivers an action of the worker, called on the worker thread.
+    pub(crate) fn push(&self, action: Action<M>) {
+        let Ok(mut state) = self.state.lock() else {
+            return self.sender.send_action(action);
+        };
+
+        if state.pending.is_empty() && state.in_flight < self.capacity {
+            state.in_flight += 1;
+            return self.sender.send_action(action);
+        }
+
+        if state.pending.len() >= self.capacity {
+            match self.when_full {
+                Backpressure::DropOldest => {
+                    state.pending.pop_front();
+                    metrics::record_dropped_action();
+                }
+                Backpressure::Block => {
+                    let started = Instant::now();
+                    while state.pending.len() >= self.capacity && !state.closed {
+                        state = match self.space.wait_timeout(state, BLOCK_POLL_INTERVAL) {
+                            Ok((state, _)) => state,
+                            Err(_) => return,
+                        };
+                    }
+                    metrics::record_blocked(started.elapsed());
+                }
+            }
+        }
+
+        state.pending.push_back(action);
+        metrics::record_backlog(state.pending.len());
+    }
+
+    /// Stops blocking the worker, it is about to be joined. Actions still
+    /// held are delivered once the event loop is idle.
+    pub(crate) fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+        }
+        self.space.notify_all();
+    }
+}
+
+impl<M: Send + 'static> Flush for ActionQueue<M> {
+    fn flush(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        let held = state.pending.len();
+        let delivered = held.min(self.capacity);
+        state.in_flight = delivered;
+        for action in state.pending.drain(..delivered) {
+            self.sender.send_action(action);
+        }
+        if held > 0 {
+            log::trace!(target: WORKER, "Delivered {} held back action(s)", delivered);
+            metrics::record_backlog(state.pending.len());
+            self.space.notify_all();
+        }
+    }
+}
+
+/// Called by the event loop once it handled every action sent so far.
+pub(crate) fn event_loop_idle() {
+    let queues = {
+        let Ok(mut queues) = QUEUES.lock() else {
+            return;
+        };
+        queues.retain(|queue| queue.strong_count() > 0);
+        queues.iter().filter_map(Weak::upgrade).collect::<Vec<_>>()
+    };
+    for queue in queues {
+        queue.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn queue(when_full: Backpressure) -> (Arc<ActionQueue<u32>>, Arc<Mutex<Vec<u32>>>) {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&delivered);
+        let sender = ActionSender::new(move |action| {
+            if let Action::Output(value) = action {
+                sink.lock().unwrap().push(value);
+            }
+        });
+        let settings = WorkerQueue {
+            capacity: 2,
+            when_full,
+        };
+        (ActionQueue::new(sender, &settings), delivered)
+    }
+
+    #[test]
+    fn holds_actions_back_until_the_event_loop_is_idle() {
+        let (queue, delivered) = queue(Backpressure::DropOldest);
+        for value in 0..6 {
+            queue.push(Action::Output(value));
+        }
+        // Two in the event loop, 2 and 3 were dropped for 4 and 5.
+        assert_eq!(*delivered.lock().unwrap(), [0, 1]);
+
+        queue.flush();
+        assert_eq!(*delivered.lock().unwrap(), [0, 1, 4, 5]);
+    }
+
+    #[test]
+    fn blocks_the_worker_while_full() {
+        let (queue, delivered) = queue(Backpressure::Block);
+        for value in 0..4 {
+            queue.push(Action::Output(value));
+        }
+
+        let pushed = Arc::new(AtomicUsize::new(0));
+        let worker = std::thread::spawn({
+            let queue = Arc::clone(&queue);
+            let pushed = Arc::clone(&pushed);
+            move || {
+                queue.push(Action::Output(4));
+                pushed.store(1, Ordering::SeqCst);
+            }
+        });
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(pushed.load(Ordering::SeqCst), 0);
+
+        queue.flush();
+        worker.join().unwrap();
+        queue.flush();
+        assert_eq!(*delivered.lock().unwrap(), [0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Builds the fixture ui crate in `tests/fixture` as a hot library.
+//!
+//! The first build compiles iced for the fixture library and takes a few
+//! minutes, later builds only rebuild the fixture. A cargo that cannot run or
+//! a fixture that fails to build fails the tests.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// How long to wait for the file watcher to notice a rebuilt library.
+pub const RELOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The fixture crate of the `hot_ice` workspace, rebuilt with
+/// `HOT_ICE_FIXTURE_VERSION=N` to swap in other versions of its functions.
+pub struct Fixture {
+    cargo: OsString,
+}
+
+impl Fixture {
+    /// Panics if there is no cargo to build the fixture with.
+    pub fn new() -> Self {
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let output = Command::new(&cargo)
+            .arg("--version")
+            .output()
+            .unwrap_or_else(|error| panic!("cannot run {cargo:?} to build the fixture: {error}"));
+        assert!(output.status.success(), "{cargo:?} --version failed");
+        Self { cargo }
+    }
+
+    /// Directory the library is built into.
+    pub fn lib_dir(&self) -> String {
+        target_dir().join("debug").display().to_string()
+    }
+
+    /// Builds version `version` of the fixture library.
+    ///
+    /// The fixture is a member of the `hot_ice` workspace, so the library is
+    /// built with the same crate metadata as the copy linked into the tests:
+    /// their message types match and the library can downcast the messages
+    /// sent by the tests. That takes the features the dev-dependencies unify
+    /// into the build of the tests as well: criterion enables `alloc` of
+    /// serde.
+    pub fn build(&self, version: u32) {
+        let mut command = Command::new(&self.cargo);
+        command
+            .args(["build", "-p", "hot_ice_fixture", "-p", "hot_ice"])
+            .args(["--lib", "--features", "serde/alloc", "--manifest-path"])
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+            .env("CARGO_TARGET_DIR", target_dir());
+        if version > 1 {
+            command.env("HOT_ICE_FIXTURE_VERSION", version.to_string());
+        } else {
+            command.env_remove("HOT_ICE_FIXTURE_VERSION");
+        }
+
+        let status = command.status().expect("failed to run cargo");
+        assert!(
+            status.success(),
+            "failed to build version {version} of the fixture"
+        );
+    }
+}
+
+/// Target directory of the fixture libraries, separate from the one the
+/// tests are built in so the nested cargo never waits for its lock.
+fn target_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("reload-fixture")
+}
@@ -0,0 +1,51 @@
+//! Reloads the fixture ui crate in `tests/fixture`, whose `view` takes the
+//! host's `Context` and whose `update` reaches it through
+//! `hot_ice::generation`.
+
+mod common;
+
+use hot_ice::macro_use::DynMessage;
+use hot_ice::testing::{FunctionState, Harness, HotFunction};
+use hot_ice_fixture::{Message, State};
+
+use common::{Fixture, RELOAD_TIMEOUT};
+
+#[test]
+fn functions_reaching_the_context_stay_hot_across_reloads() {
+    let fixture = Fixture::new();
+    fixture.build(1);
+
+    let app = hot_ice::application(State::new, State::update, State::view);
+    let mut harness = Harness::new(app, fixture.lib_dir());
+    assert!(harness.is_ready());
+
+    harness.send(Message::Increment.into_hot_message());
+    harness.view();
+    assert_eq!(harness.state().value, 1);
+    assert_eq!(
+        harness.function_state(HotFunction::Update),
+        FunctionState::Hot
+    );
+    assert_eq!(
+        harness.function_state(HotFunction::View),
+        FunctionState::Hot
+    );
+    let generation = harness.state().generation;
+
+    // The context handed to the new library counts the reload.
+    fixture.build(2);
+    assert!(harness.reload(RELOAD_TIMEOUT), "version 2 was not reloaded");
+
+    harness.send(Message::Increment.into_hot_message());
+    harness.view();
+    assert_eq!(harness.state().value, 11);
+    assert_eq!(harness.state().generation, generation + 1);
+    assert_eq!(
+        harness.function_state(HotFunction::Update),
+        FunctionState::Hot
+    );
+    assert_eq!(
+        harness.function_state(HotFunction::View),
+        FunctionState::Hot
+    );
+}
@@ -0,0 +1,9 @@
+fn main() {
+    // The integration tests pick the version of the functions to build, see
+    // `tests/common/mod.rs`.
+    println!("cargo:rerun-if-env-changed=HOT_ICE_FIXTURE_VERSION");
+    println!("cargo:rustc-check-cfg=cfg(fixture_version, values(\"2\", \"3\", \"4\", \"5\"))");
+    if let Ok(version) = std::env::var("HOT_ICE_FIXTURE_VERSION") {
+        println!("cargo:rustc-cfg=fixture_version=\"{version}\"");
+    }
+}
@@ -0,0 +1,81 @@
+//! Version 1 is linked into the test binaries, the other versions are only
+//! built as hot libraries:
+//!
+//! 2. `update` increments by 10 instead of 1.
+//! 3. `update` is not exported, so the reloader falls back to version 1.
+//! 4. `view` returns a [`Row`](widget::Row) instead of a
+//!    [`Column`](widget::Column).
+//! 5. `update` increments by the host's [`Step`] resource instead of 1.
+//!
+//! `new` and `view` take the [`Context`](hot_ice::Context), `view` leaves
+//! the conversion of its widget into an `Element` to the library and
+//! `update` reaches the context through [`hot_ice::generation`].
+
+use iced::Task;
+use iced::widget::{self, button, text};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Increment,
+}
+
+/// Lent to `boot` and version 5 by the host.
+#[derive(Debug, hot_ice::TypeHash)]
+pub struct Step(pub i64);
+
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub value: i64,
+    /// Generation of the library that handled the last message.
+    pub generation: u64,
+    /// The step `boot` got from the [`Context`](hot_ice::Context).
+    pub boot_step: i64,
+}
+
+impl State {
+    #[hot_ice::hot_fn]
+    pub fn new(context: &hot_ice::Context) -> (State, Task<Message>) {
+        let boot_step = context.resource::<Step>().map_or(0, |step| step.0);
+        (
+            State {
+                boot_step,
+                ..State::default()
+            },
+            Task::none(),
+        )
+    }
+
+    #[cfg_attr(not(fixture_version = "3"), hot_ice::hot_fn)]
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        let step = if cfg!(fixture_version = "2") {
+            10
+        } else if cfg!(fixture_version = "5") {
+            hot_ice::HostOwned::<Step>::get().map_or(0, |step| step.0)
+        } else {
+            1
+        };
+        match message {
+            Message::Increment => self.value += step,
+        }
+        self.generation = hot_ice::generation();
+        Task::none()
+    }
+
+    #[cfg(not(fixture_version = "4"))]
+    #[hot_ice::hot_fn]
+    pub fn view(&self, context: &hot_ice::Context) -> widget::Column<'_, Message> {
+        widget::column![
+            text!("{} (generation {})", self.value, context.generation()),
+            button("Increment").on_press(Message::Increment)
+        ]
+    }
+
+    #[cfg(fixture_version = "4")]
+    #[hot_ice::hot_fn]
+    pub fn view(&self, context: &hot_ice::Context) -> widget::Row<'_, Message> {
+        widget::row![
+            text!("{} (generation {})", self.value, context.generation()),
+            button("Increment").on_press(Message::Increment)
+        ]
+    }
+}
@@ -0,0 +1,35 @@
+//! Lends a resource of the host to the fixture ui crate in
+//! `tests/fixture` and reloads a version borrowing it.
+
+mod common;
+
+use hot_ice::macro_use::DynMessage;
+use hot_ice::testing::{FunctionState, Harness, HotFunction};
+use hot_ice_fixture::{Message, State, Step};
+
+use common::{Fixture, RELOAD_TIMEOUT};
+
+#[test]
+fn host_resources_reach_boot_and_reloaded_libraries() {
+    let fixture = Fixture::new();
+    fixture.build(1);
+
+    let app = hot_ice::application(State::new, State::update, State::view).host_resource(Step(10));
+    let mut harness = Harness::new(app, fixture.lib_dir());
+    assert!(harness.is_ready());
+    assert_eq!(harness.state().boot_step, 10);
+
+    harness.send(Message::Increment.into_hot_message());
+    assert_eq!(harness.state().value, 1);
+
+    // The new update borrows the same step from the host.
+    fixture.build(5);
+    assert!(harness.reload(RELOAD_TIMEOUT), "version 5 was not reloaded");
+
+    harness.send(Message::Increment.into_hot_message());
+    assert_eq!(harness.state().value, 11);
+    assert_eq!(
+        harness.function_state(HotFunction::Update),
+        FunctionState::Hot
+    );
+}
@@ -0,0 +1,62 @@
+//! Reloads the fixture ui crate in `tests/fixture`, whose views return
+//! widgets converting into an `Element`.
+
+mod common;
+
+use iced::{Rectangle, Size};
+
+use hot_ice::testing::{FunctionState, Harness, HotFunction};
+use hot_ice_fixture::State;
+
+use common::{Fixture, RELOAD_TIMEOUT};
+
+const SIZE: Size = Size::new(800.0, 600.0);
+
+/// Bounds of the value and of the button's label among the `texts` of the
+/// view.
+fn value_and_button(texts: Option<Vec<(String, Rectangle)>>) -> (Rectangle, Rectangle) {
+    let texts = texts.expect("no headless renderer");
+    let find = |predicate: &dyn Fn(&str) -> bool| {
+        texts
+            .iter()
+            .find(|(text, _)| predicate(text))
+            .map(|(_, bounds)| *bounds)
+            .unwrap_or_else(|| panic!("text not rendered, got {texts:?}"))
+    };
+    (
+        find(&|text| text.starts_with("0 (generation")),
+        find(&|text| text == "Increment"),
+    )
+}
+
+#[test]
+fn views_returning_widgets_stay_hot_across_reloads() {
+    let fixture = Fixture::new();
+    fixture.build(1);
+
+    let app = hot_ice::application(State::new, State::update, State::view);
+    let mut harness = Harness::new(app, fixture.lib_dir());
+    assert!(harness.is_ready());
+
+    // The column puts the button below the value.
+    let (value, button) = value_and_button(harness.view_texts(SIZE));
+    assert!(button.y >= value.y + value.height, "{value:?} {button:?}");
+    assert_eq!(
+        harness.function_state(HotFunction::View),
+        FunctionState::Hot
+    );
+
+    // The new view returns another widget type, it is converted into the
+    // same `Element` by the library and puts the button next to the value, on the
+    // same line.
+    fixture.build(4);
+    assert!(harness.reload(RELOAD_TIMEOUT), "version 4 was not reloaded");
+
+    let (value, button) = value_and_button(harness.view_texts(SIZE));
+    assert!(button.x >= value.x + value.width, "{value:?} {button:?}");
+    assert!(button.y < value.y + value.height, "{value:?} {button:?}");
+    assert_eq!(
+        harness.function_state(HotFunction::View),
+        FunctionState::Hot
+    );
+}
@@ -0,0 +1,52 @@
+//! Rebuilds the fixture ui crate in `tests/fixture` in several versions and
+//! swaps them under a headless reloader.
+
+mod common;
+
+use hot_ice::macro_use::DynMessage;
+use hot_ice::testing::{FunctionState, Harness, HotFunction};
+use hot_ice_fixture::{Message, State};
+
+use common::{Fixture, RELOAD_TIMEOUT};
+
+#[test]
+fn reload_keeps_state_and_falls_back_to_static_functions() {
+    let fixture = Fixture::new();
+    fixture.build(1);
+
+    let app = hot_ice::application(State::new, State::update, State::view);
+    let mut harness = Harness::new(app, fixture.lib_dir());
+    assert!(harness.is_ready());
+
+    // Sent the way the hot view produces messages, so they reach the
+    // library instead of the statically linked update.
+    harness.send(Message::Increment.into_hot_message());
+    assert_eq!(harness.state().value, 1);
+    assert_eq!(
+        harness.function_state(HotFunction::Update),
+        FunctionState::Hot
+    );
+
+    // The new update is called on the state built by the old one.
+    fixture.build(2);
+    assert!(harness.reload(RELOAD_TIMEOUT), "version 2 was not reloaded");
+    assert!(harness.is_ready());
+    assert_eq!(harness.state().value, 1);
+
+    harness.send(Message::Increment.into_hot_message());
+    assert_eq!(harness.state().value, 11);
+    assert_eq!(
+        harness.function_state(HotFunction::Update),
+        FunctionState::Hot
+    );
+
+    fixture.build(3);
+    assert!(harness.reload(RELOAD_TIMEOUT), "version 3 was not reloaded");
+
+    harness.send(Message::Increment.into_hot_message());
+    assert_eq!(harness.state().value, 12);
+    assert!(matches!(
+        harness.function_state(HotFunction::Update),
+        FunctionState::FallBackStatic(_)
+    ));
+}